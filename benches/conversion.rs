@@ -0,0 +1,89 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use kamera::convert;
+use kamera::{ColorRange, ColorSpace, YuvMatrix};
+
+const SIZES: [(u32, u32); 2] = [(640, 480), (1920, 1080)];
+
+fn input(w: u32, h: u32, bytes_per_pixel: f32) -> Vec<u8> {
+    let len = (w * h) as f32 * bytes_per_pixel;
+    (0..len as usize).map(|i| (i * 31 % 251) as u8).collect()
+}
+
+fn yuv_to_bgra(c: &mut Criterion) {
+    let mut group = c.benchmark_group("to_bgra");
+    let color_space = ColorSpace::default();
+    for (w, h) in SIZES {
+        let id = format!("{w}x{h}");
+        group.throughput(Throughput::Elements((w * h) as u64));
+        // the output buffer is reused like the Linux backend does with a frame pool
+        let mut out = Vec::new();
+        let yuyv = input(w, h, 2.0);
+        group.bench_with_input(BenchmarkId::new("yuyv", &id), &yuyv, |b, buf| {
+            b.iter(|| {
+                convert::yuyv_to_bgra(black_box(buf), w, h, w as usize * 2, color_space, &mut out)
+            })
+        });
+        let nv12 = input(w, h, 1.5);
+        group.bench_with_input(BenchmarkId::new("nv12", &id), &nv12, |b, buf| {
+            b.iter(|| {
+                convert::nv12_to_bgra(black_box(buf), w, h, w as usize, color_space, &mut out)
+            })
+        });
+        let rgb = input(w, h, 3.0);
+        group.bench_with_input(BenchmarkId::new("rgb24", &id), &rgb, |b, buf| {
+            b.iter(|| convert::rgb24_to_bgra(black_box(buf), w, h, w as usize * 3, &mut out))
+        });
+        // with the decoders of the `turbojpeg` or `zune-jpeg` features when those are on
+        #[cfg(all(target_os = "linux", feature = "mjpeg"))]
+        group.bench_with_input(BenchmarkId::new("mjpeg", &id), &jpeg(w, h), |b, buf| {
+            b.iter(|| convert::mjpeg_to_bgra(black_box(buf), w, h, &mut out))
+        });
+    }
+    group.finish();
+}
+
+/// A JPEG of smooth gradients, which compresses about like a camera image.
+#[cfg(all(target_os = "linux", feature = "mjpeg"))]
+fn jpeg(w: u32, h: u32) -> Vec<u8> {
+    use image::codecs::jpeg::JpegEncoder;
+
+    let rgb: Vec<u8> = (0..h)
+        .flat_map(|y| (0..w).flat_map(move |x| [(x * 255 / w) as u8, (y * 255 / h) as u8, 128]))
+        .collect();
+    let mut jpeg = Vec::new();
+    JpegEncoder::new_with_quality(&mut jpeg, 90)
+        .encode(&rgb, w, h, image::ExtendedColorType::Rgb8)
+        .unwrap();
+    jpeg
+}
+
+fn bgra_to(c: &mut Criterion) {
+    let mut group = c.benchmark_group("from_bgra");
+    for (w, h) in SIZES {
+        let id = format!("{w}x{h}");
+        let stride = w as usize * 4;
+        group.throughput(Throughput::Elements((w * h) as u64));
+        let bgra = input(w, h, 4.0);
+        group.bench_with_input(BenchmarkId::new("rgb", &id), &bgra, |b, buf| {
+            b.iter(|| convert::bgra_to_rgb(black_box(buf), w, h, stride))
+        });
+        group.bench_with_input(BenchmarkId::new("gray", &id), &bgra, |b, buf| {
+            b.iter(|| convert::bgra_to_gray(black_box(buf), w, h, stride))
+        });
+        group.bench_with_input(BenchmarkId::new("luma_histogram", &id), &bgra, |b, buf| {
+            b.iter(|| convert::bgra_luma_histogram(black_box(buf), w, h, stride))
+        });
+    }
+    group.finish();
+}
+
+fn yuv_to_rgb_setup(c: &mut Criterion) {
+    let color_space =
+        ColorSpace { matrix: YuvMatrix::Bt709, range: ColorRange::Full, ..Default::default() };
+    c.bench_function("YuvToRgb::new", |b| {
+        b.iter(|| convert::YuvToRgb::new(black_box(color_space), 1080))
+    });
+}
+
+criterion_group!(benches, yuv_to_bgra, bgra_to, yuv_to_rgb_setup);
+criterion_main!(benches);
@@ -0,0 +1,39 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use kamera::Camera;
+
+// Needs a camera, like the tests. Accessing frame data must not copy the frame.
+fn frame_data(c: &mut Criterion) {
+    let camera = Camera::new_default_device();
+    camera.start();
+    let frame = camera.wait_for_frame().unwrap();
+
+    c.bench_function("frame.data().data_u8()", |b| {
+        b.iter(|| black_box(frame.data().data_u8().len()))
+    });
+    c.bench_function("frame.data().data_u32()", |b| {
+        b.iter(|| black_box(frame.data().data_u32().len()))
+    });
+
+    camera.stop();
+}
+
+// End to end, from the device to converted pixels. Bounded by the frame rate of the camera.
+fn frame_delivery(c: &mut Criterion) {
+    let camera = Camera::new_default_device();
+    camera.start();
+    camera.wait_for_frame().unwrap();
+
+    let mut group = c.benchmark_group("delivery");
+    group.sample_size(10);
+    group.bench_function("wait_for_frame", |b| b.iter(|| black_box(camera.wait_for_frame())));
+    group.bench_function("wait_for_frame + data_rgb", |b| {
+        b.iter(|| black_box(camera.wait_for_frame().unwrap().data().data_rgb().len()))
+    });
+    group.finish();
+
+    println!("{:?}", camera.perf_counters());
+    camera.stop();
+}
+
+criterion_group!(benches, frame_data, frame_delivery);
+criterion_main!(benches);
@@ -0,0 +1,44 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use kamera::{scale, Filter, OwnedFrame};
+
+const SIZES: [(u32, u32); 2] = [(640, 480), (1920, 1080)];
+
+fn input(w: u32, h: u32) -> OwnedFrame {
+    let bgra = (0..w as usize * h as usize * 4).map(|i| (i * 31 % 251) as u8).collect();
+    OwnedFrame::new(bgra, w, h)
+}
+
+// Thumbnails shrink with wide kernels, the rows pass with its many taps dominates.
+fn thumbnail(c: &mut Criterion) {
+    let mut group = c.benchmark_group("thumbnail");
+    for (w, h) in SIZES {
+        let frame = input(w, h);
+        group.throughput(Throughput::Elements((w * h) as u64));
+        for filter in [Filter::Nearest, Filter::Bilinear, Filter::Lanczos] {
+            let id = BenchmarkId::new(format!("{filter:?}"), format!("{w}x{h}"));
+            group.bench_with_input(id, &frame, |b, frame| {
+                b.iter(|| scale::thumbnail(black_box(frame), 160, 160, filter))
+            });
+        }
+    }
+    group.finish();
+}
+
+// Halving and doubling, a few taps per pixel in both passes.
+fn resize(c: &mut Criterion) {
+    let mut group = c.benchmark_group("resize");
+    for (w, h) in SIZES {
+        let frame = input(w, h);
+        group.throughput(Throughput::Elements((w * h) as u64));
+        for (name, (dst_w, dst_h)) in [("half", (w / 2, h / 2)), ("double", (w * 2, h * 2))] {
+            let id = BenchmarkId::new(name, format!("{w}x{h}"));
+            group.bench_with_input(id, &frame, |b, frame| {
+                b.iter(|| scale::resize(black_box(frame), dst_w, dst_h, Filter::Bilinear))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, thumbnail, resize);
+criterion_main!(benches);
@@ -0,0 +1,152 @@
+//! Small command line tool, run with `cargo run --example kamera-cli --features record -- list`.
+//!
+//! ```text
+//! kamera-cli list                        devices and their formats
+//! kamera-cli snap -o out.png             one frame as PNG
+//! kamera-cli preview                     ASCII preview in the terminal, Ctrl-C to quit
+//! kamera-cli record -o out.mp4 -n 90     frames as H.264, or YUV4MPEG2 for .y4m
+//! ```
+//!
+//! For a preview in a window see the `window-rgb` example.
+
+use std::io::{self, Write};
+use std::process::exit;
+
+use kamera::record::{RecordSettings, VideoRecorder};
+use kamera::{describe_device, Camera, Filter, Fit};
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let option = |name: &str| args.iter().position(|a| a == name).and_then(|i| args.get(i + 1));
+    let result = match args.first().map(String::as_str) {
+        Some("list") => list(),
+        Some("snap") => snap(option("-o").map_or("snap.png", |o| o.as_str())),
+        Some("preview") => preview(),
+        Some("record") => {
+            let frames = option("-n").and_then(|n| n.parse().ok()).unwrap_or(90);
+            record(option("-o").map_or("record.mp4", |o| o.as_str()), frames)
+        }
+        _ => {
+            eprintln!("usage: kamera-cli list | snap [-o out.png] | preview");
+            eprintln!("       kamera-cli record [-o out.mp4] [-n frames]");
+            exit(2);
+        }
+    };
+    if let Err(err) = result {
+        eprintln!("{err}");
+        exit(1);
+    }
+}
+
+fn list() -> io::Result<()> {
+    for device in Camera::device_list() {
+        println!("{} ({:?}, {})", device.name, device.kind(), device.id);
+        for format in describe_device(&device).formats {
+            let (w, h) = (format.width, format.height);
+            let fps = format.max_fps;
+            println!("    {} {w}x{h} {fps:.0} fps", format.pixel_format);
+        }
+    }
+    Ok(())
+}
+
+fn snap(path: &str) -> io::Result<()> {
+    let camera = Camera::new_default_device();
+    camera.start();
+    // give auto exposure a moment to settle
+    for _ in 0..10 {
+        camera.wait_for_frame();
+    }
+    let frame = camera.wait_for_frame().ok_or(io::Error::other("no frame"))?;
+    let (w, h) = frame.size_u32();
+    write_png(path, w, h, frame.data().data_rgb())?;
+    println!("{path}: {w}x{h}");
+    Ok(())
+}
+
+fn preview() -> io::Result<()> {
+    const RAMP: &[u8] = b" .:-=+*#%@";
+    let (cols, rows) = (80, 30);
+    let mut buffer = vec![0; cols * rows];
+    let camera = Camera::new_default_device();
+    camera.start();
+    print!("\x1b[2J");
+    while let Some(frame) = camera.latest_frame() {
+        // terminal cells are about twice as high as wide
+        frame.copy_to_buffer(&mut buffer, cols as u32, rows as u32, Fit::Stretch, Filter::Bilinear);
+        let mut out = String::from("\x1b[H");
+        for row in buffer.chunks_exact(cols) {
+            for [b, g, r, _] in row.iter().map(|px| px.to_le_bytes()) {
+                let luma = (29 * b as usize + 150 * g as usize + 77 * r as usize) >> 8;
+                out.push(RAMP[luma * (RAMP.len() - 1) / 255] as char);
+            }
+            out.push('\n');
+        }
+        io::stdout().write_all(out.as_bytes())?;
+    }
+    Ok(())
+}
+
+fn record(path: &str, frames: usize) -> io::Result<()> {
+    let camera = Camera::new_default_device();
+    camera.start();
+    let mut recorder = VideoRecorder::new(path, RecordSettings::default())?;
+    for _ in 0..frames {
+        let frame = camera.wait_for_frame().ok_or(io::Error::other("no frame"))?;
+        recorder.write_frame(&frame)?;
+    }
+    recorder.finish()?;
+    println!("{path}: {frames} frames");
+    Ok(())
+}
+
+/// RGB PNG with uncompressed deflate blocks, big but without an image crate.
+fn write_png(path: &str, w: u32, h: u32, rgb: &[u8]) -> io::Result<()> {
+    let mut raw = Vec::with_capacity((w as usize * 3 + 1) * h as usize);
+    for row in rgb.chunks_exact(w as usize * 3) {
+        raw.push(0); // no filter
+        raw.extend_from_slice(row);
+    }
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = raw.chunks(0xffff).peekable();
+    while let Some(block) = blocks.next() {
+        zlib.push(blocks.peek().is_none() as u8);
+        let len = block.len() as u16;
+        zlib.extend(len.to_le_bytes());
+        zlib.extend((!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend(adler32(&raw).to_be_bytes());
+
+    let mut header = [w.to_be_bytes(), h.to_be_bytes()].concat();
+    header.extend([8, 2, 0, 0, 0]); // 8 bit RGB
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    for (kind, data) in [(b"IHDR", &header[..]), (b"IDAT", &zlib), (b"IEND", &[])] {
+        png.extend((data.len() as u32).to_be_bytes());
+        let start = png.len();
+        png.extend(kind);
+        png.extend(data);
+        png.extend(crc32(&png[start..]).to_be_bytes());
+    }
+    std::fs::write(path, png)
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
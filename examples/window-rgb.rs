@@ -1,7 +1,6 @@
 use std::num::NonZeroU32;
 
 use winit::{
-    dpi::PhysicalSize,
     event::{Event, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
     window::WindowBuilder,
@@ -26,14 +25,16 @@ fn main() {
         match event {
             Event::RedrawRequested(window_id) if window_id == window.id() => {
                 let Some(frame) = camera.wait_for_frame() else { return };
-                let (w, h) = frame.size_u32();
-
-                surface.resize(NonZeroU32::new(w).unwrap(), NonZeroU32::new(h).unwrap()).unwrap();
-                window.set_inner_size(PhysicalSize::new(w, h));
-
+                let size = window.inner_size();
+                let (Some(w), Some(h)) =
+                    (NonZeroU32::new(size.width), NonZeroU32::new(size.height))
+                else {
+                    return;
+                };
+
+                surface.resize(w, h).unwrap();
                 let mut buffer = surface.buffer_mut().unwrap();
-                let len = buffer.len();
-                buffer.copy_from_slice(&frame.data().data_u32()[0..len]);
+                frame.copy_to_buffer(&mut buffer, w.get(), h.get(), Fit::Contain, Filter::Bilinear);
                 buffer.present().unwrap();
             }
             Event::WindowEvent { event: WindowEvent::CloseRequested, window_id }
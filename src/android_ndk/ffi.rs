@@ -0,0 +1,254 @@
+//! The parts of the NDK camera and media headers kamera uses, `camera/NdkCameraManager.h`,
+//! `media/NdkImageReader.h` and the headers they include. Available since API level 24.
+
+#![allow(non_camel_case_types, non_snake_case)]
+
+use std::ffi::{c_char, c_int, c_long, c_void};
+
+macro_rules! opaque {
+    ($($name:ident),*) => {
+        $(
+            #[repr(C)]
+            pub struct $name {
+                _private: [u8; 0],
+            }
+        )*
+    };
+}
+
+opaque!(
+    ACameraManager,
+    ACameraMetadata,
+    ACameraDevice,
+    ACaptureRequest,
+    ACaptureSessionOutputContainer,
+    ACaptureSessionOutput,
+    ACameraOutputTarget,
+    ACameraCaptureSession,
+    AImageReader,
+    AImage,
+    ANativeWindow
+);
+
+pub type camera_status_t = c_int;
+pub type media_status_t = c_int;
+
+pub const ACAMERA_OK: camera_status_t = 0;
+pub const ACAMERA_ERROR_CAMERA_DISCONNECTED: camera_status_t = -10002;
+pub const ACAMERA_ERROR_CAMERA_IN_USE: camera_status_t = -10010;
+pub const ACAMERA_ERROR_MAX_CAMERA_IN_USE: camera_status_t = -10011;
+pub const ACAMERA_ERROR_CAMERA_DISABLED: camera_status_t = -10012;
+pub const ACAMERA_ERROR_PERMISSION_DENIED: camera_status_t = -10013;
+pub const AMEDIA_OK: media_status_t = 0;
+
+/// The `errorCode` of [`ACameraDevice_StateCallbacks::onError`].
+pub const ERROR_CAMERA_IN_USE: c_int = 1;
+pub const ERROR_MAX_CAMERAS_IN_USE: c_int = 2;
+pub const ERROR_CAMERA_DISABLED: c_int = 3;
+
+pub const TEMPLATE_RECORD: c_int = 3;
+pub const AIMAGE_FORMAT_YUV_420_888: i32 = 0x23;
+
+// metadata tags, the section index shifted by 16 plus the index of the tag
+pub const ACAMERA_CONTROL_AE_AVAILABLE_TARGET_FPS_RANGES: u32 = 0x1_0014;
+pub const ACAMERA_FLASH_MODE: u32 = 0x4_0002;
+pub const ACAMERA_FLASH_INFO_AVAILABLE: u32 = 0x5_0000;
+pub const ACAMERA_LENS_FACING: u32 = 0x8_0005;
+pub const ACAMERA_SCALER_AVAILABLE_STREAM_CONFIGURATIONS: u32 = 0xd_000a;
+pub const ACAMERA_SENSOR_INFO_TIMESTAMP_SOURCE: u32 = 0xf_0008;
+
+/// The `type` of [`ACameraMetadata_const_entry`].
+pub const ACAMERA_TYPE_BYTE: u8 = 0;
+pub const ACAMERA_TYPE_INT32: u8 = 1;
+
+pub const ACAMERA_LENS_FACING_FRONT: u8 = 0;
+pub const ACAMERA_LENS_FACING_BACK: u8 = 1;
+pub const ACAMERA_FLASH_MODE_OFF: u8 = 0;
+pub const ACAMERA_FLASH_MODE_TORCH: u8 = 2;
+pub const ACAMERA_SENSOR_INFO_TIMESTAMP_SOURCE_REALTIME: u8 = 1;
+/// The last value of the stream configurations, which are `(format, width, height, input)`.
+pub const ACAMERA_SCALER_AVAILABLE_STREAM_CONFIGURATIONS_OUTPUT: i32 = 0;
+
+#[repr(C)]
+pub struct ACameraIdList {
+    pub numCameras: c_int,
+    pub cameraIds: *const *const c_char,
+}
+
+/// The `data` union of the header is a pointer to `count` values of `type`.
+#[repr(C)]
+pub struct ACameraMetadata_const_entry {
+    pub tag: u32,
+    pub type_: u8,
+    pub count: u32,
+    pub data: *const c_void,
+}
+
+#[repr(C)]
+pub struct ACameraDevice_StateCallbacks {
+    pub context: *mut c_void,
+    pub onDisconnected: unsafe extern "C" fn(context: *mut c_void, device: *mut ACameraDevice),
+    pub onError:
+        unsafe extern "C" fn(context: *mut c_void, device: *mut ACameraDevice, error: c_int),
+}
+
+pub type ACameraCaptureSession_stateCallback =
+    unsafe extern "C" fn(context: *mut c_void, session: *mut ACameraCaptureSession);
+
+#[repr(C)]
+pub struct ACameraCaptureSession_stateCallbacks {
+    pub context: *mut c_void,
+    pub onClosed: ACameraCaptureSession_stateCallback,
+    pub onReady: ACameraCaptureSession_stateCallback,
+    pub onActive: ACameraCaptureSession_stateCallback,
+}
+
+#[repr(C)]
+pub struct AImageReader_ImageListener {
+    pub context: *mut c_void,
+    pub onImageAvailable:
+        Option<unsafe extern "C" fn(context: *mut c_void, reader: *mut AImageReader)>,
+}
+
+#[repr(C)]
+pub struct timespec {
+    pub tv_sec: c_long,
+    pub tv_nsec: c_long,
+}
+
+pub const CLOCK_MONOTONIC: c_int = 1;
+pub const CLOCK_BOOTTIME: c_int = 7;
+
+#[link(name = "camera2ndk")]
+extern "C" {
+    pub fn ACameraManager_create() -> *mut ACameraManager;
+    pub fn ACameraManager_delete(manager: *mut ACameraManager);
+    pub fn ACameraManager_getCameraIdList(
+        manager: *mut ACameraManager,
+        list: *mut *mut ACameraIdList,
+    ) -> camera_status_t;
+    pub fn ACameraManager_deleteCameraIdList(list: *mut ACameraIdList);
+    pub fn ACameraManager_getCameraCharacteristics(
+        manager: *mut ACameraManager,
+        id: *const c_char,
+        characteristics: *mut *mut ACameraMetadata,
+    ) -> camera_status_t;
+    pub fn ACameraManager_openCamera(
+        manager: *mut ACameraManager,
+        id: *const c_char,
+        callbacks: *mut ACameraDevice_StateCallbacks,
+        device: *mut *mut ACameraDevice,
+    ) -> camera_status_t;
+
+    pub fn ACameraMetadata_getConstEntry(
+        metadata: *const ACameraMetadata,
+        tag: u32,
+        entry: *mut ACameraMetadata_const_entry,
+    ) -> camera_status_t;
+    pub fn ACameraMetadata_free(metadata: *mut ACameraMetadata);
+
+    pub fn ACameraDevice_close(device: *mut ACameraDevice) -> camera_status_t;
+    pub fn ACameraDevice_createCaptureRequest(
+        device: *const ACameraDevice,
+        template: c_int,
+        request: *mut *mut ACaptureRequest,
+    ) -> camera_status_t;
+    pub fn ACameraDevice_createCaptureSession(
+        device: *mut ACameraDevice,
+        outputs: *const ACaptureSessionOutputContainer,
+        callbacks: *const ACameraCaptureSession_stateCallbacks,
+        session: *mut *mut ACameraCaptureSession,
+    ) -> camera_status_t;
+
+    pub fn ACaptureSessionOutputContainer_create(
+        container: *mut *mut ACaptureSessionOutputContainer,
+    ) -> camera_status_t;
+    pub fn ACaptureSessionOutputContainer_free(container: *mut ACaptureSessionOutputContainer);
+    pub fn ACaptureSessionOutputContainer_add(
+        container: *mut ACaptureSessionOutputContainer,
+        output: *const ACaptureSessionOutput,
+    ) -> camera_status_t;
+    pub fn ACaptureSessionOutput_create(
+        window: *mut ANativeWindow,
+        output: *mut *mut ACaptureSessionOutput,
+    ) -> camera_status_t;
+    pub fn ACaptureSessionOutput_free(output: *mut ACaptureSessionOutput);
+    pub fn ACameraOutputTarget_create(
+        window: *mut ANativeWindow,
+        target: *mut *mut ACameraOutputTarget,
+    ) -> camera_status_t;
+    pub fn ACameraOutputTarget_free(target: *mut ACameraOutputTarget);
+
+    pub fn ACaptureRequest_addTarget(
+        request: *mut ACaptureRequest,
+        target: *const ACameraOutputTarget,
+    ) -> camera_status_t;
+    pub fn ACaptureRequest_setEntry_u8(
+        request: *mut ACaptureRequest,
+        tag: u32,
+        count: u32,
+        data: *const u8,
+    ) -> camera_status_t;
+    pub fn ACaptureRequest_free(request: *mut ACaptureRequest);
+
+    pub fn ACameraCaptureSession_setRepeatingRequest(
+        session: *mut ACameraCaptureSession,
+        callbacks: *mut c_void,
+        count: c_int,
+        requests: *mut *mut ACaptureRequest,
+        sequence_id: *mut c_int,
+    ) -> camera_status_t;
+    pub fn ACameraCaptureSession_stopRepeating(
+        session: *mut ACameraCaptureSession,
+    ) -> camera_status_t;
+    pub fn ACameraCaptureSession_close(session: *mut ACameraCaptureSession);
+}
+
+#[link(name = "mediandk")]
+extern "C" {
+    pub fn AImageReader_new(
+        width: i32,
+        height: i32,
+        format: i32,
+        max_images: i32,
+        reader: *mut *mut AImageReader,
+    ) -> media_status_t;
+    pub fn AImageReader_delete(reader: *mut AImageReader);
+    pub fn AImageReader_getWindow(
+        reader: *mut AImageReader,
+        window: *mut *mut ANativeWindow,
+    ) -> media_status_t;
+    pub fn AImageReader_setImageListener(
+        reader: *mut AImageReader,
+        listener: *mut AImageReader_ImageListener,
+    ) -> media_status_t;
+    pub fn AImageReader_acquireNextImage(
+        reader: *mut AImageReader,
+        image: *mut *mut AImage,
+    ) -> media_status_t;
+
+    pub fn AImage_delete(image: *mut AImage);
+    pub fn AImage_getWidth(image: *const AImage, width: *mut i32) -> media_status_t;
+    pub fn AImage_getHeight(image: *const AImage, height: *mut i32) -> media_status_t;
+    pub fn AImage_getTimestamp(image: *const AImage, timestamp_ns: *mut i64) -> media_status_t;
+    pub fn AImage_getPlaneRowStride(
+        image: *const AImage,
+        plane: c_int,
+        row_stride: *mut i32,
+    ) -> media_status_t;
+    pub fn AImage_getPlanePixelStride(
+        image: *const AImage,
+        plane: c_int,
+        pixel_stride: *mut i32,
+    ) -> media_status_t;
+    pub fn AImage_getPlaneData(
+        image: *const AImage,
+        plane: c_int,
+        data: *mut *mut u8,
+        len: *mut c_int,
+    ) -> media_status_t;
+}
+
+extern "C" {
+    pub fn clock_gettime(clock: c_int, time: *mut timespec) -> c_int;
+}
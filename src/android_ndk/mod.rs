@@ -0,0 +1,1015 @@
+//! Cameras on Android through the camera API of the NDK. `ACameraManager` lists and opens the
+//! cameras, a repeating capture request fills an `AImageReader` with `YUV_420_888` images,
+//! which are converted to BGRA on the thread of its listener.
+//!
+//! The app needs the `CAMERA` permission, which only the Java side can ask the user for.
+//! Without it opening fails like [`CameraEvent::AccessDenied`].
+
+mod ffi;
+
+use std::collections::VecDeque;
+use std::ffi::{c_int, c_void, CStr, CString};
+use std::io::{Read, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::net::UnixStream;
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use ffi::*;
+
+use crate::config::{RequestedConfig, SizeRequest};
+use crate::device_policy::{self, Placement};
+use crate::open_policy::OpenPolicy;
+use crate::rate_limit::FrameRateLimit;
+use crate::sync::{lock, wait};
+use crate::time::Instant;
+use crate::{
+    convert, BackendCapabilities, CameraBuilder, CameraDevice, CameraEvent, CaptureFormat,
+    CaptureMetadata, ColorRange, ColorSpace, ConfigMismatch, DepthFrame, DeviceCapabilities,
+    DeviceDetails, DeviceKind, DeviceType, Enhancement, EnumError, Error, FaceRect, FourCC,
+    FrameReadyFd, InnerCamera, Latency, MetadataKind, PlaneView, PtzAxis, PtzRange, ThreadPriority,
+    TransferFunction, VideoOrientation, YuvMatrix,
+};
+
+/// Images the reader holds at most, one being converted and the rest queued by the camera.
+const MAX_IMAGES: i32 = 4;
+/// The `pixel_format` of the capture formats, the name of the NDK.
+const PIXEL_FORMAT: &str = "YUV_420_888";
+
+fn status(status: camera_status_t) -> Result<(), Error> {
+    match status {
+        ACAMERA_OK => Ok(()),
+        ACAMERA_ERROR_CAMERA_IN_USE | ACAMERA_ERROR_MAX_CAMERA_IN_USE => {
+            Err(Error::InUseByOtherApp)
+        }
+        ACAMERA_ERROR_CAMERA_DISCONNECTED => Err(Error::NoDevice),
+        ACAMERA_ERROR_PERMISSION_DENIED | ACAMERA_ERROR_CAMERA_DISABLED => {
+            Err(Error::Other("access to the camera was denied".into()))
+        }
+        status => Err(Error::Other(format!("camera error {status}"))),
+    }
+}
+
+fn media_status(status: media_status_t) -> Result<(), Error> {
+    match status {
+        AMEDIA_OK => Ok(()),
+        status => Err(Error::Other(format!("media error {status}"))),
+    }
+}
+
+/// The camera service of the process.
+struct Manager(*mut ACameraManager);
+
+// the NDK camera objects lock internally and may be used from any thread
+unsafe impl Send for Manager {}
+unsafe impl Sync for Manager {}
+
+impl Manager {
+    fn new() -> Self {
+        Manager(unsafe { ACameraManager_create() })
+    }
+
+    fn camera_ids(&self) -> Vec<String> {
+        let mut list = null_mut();
+        if unsafe { ACameraManager_getCameraIdList(self.0, &mut list) } != ACAMERA_OK {
+            return Vec::new();
+        }
+        let list_ref = unsafe { &*list };
+        let ids = (0..list_ref.numCameras.max(0) as usize)
+            .map(|i| unsafe { CStr::from_ptr(*list_ref.cameraIds.add(i)) })
+            .map(|id| id.to_string_lossy().into_owned())
+            .collect();
+        unsafe { ACameraManager_deleteCameraIdList(list) };
+        ids
+    }
+
+    fn characteristics(&self, id: &str) -> Option<Characteristics> {
+        let id = CString::new(id).ok()?;
+        let mut metadata = null_mut();
+        let result =
+            unsafe { ACameraManager_getCameraCharacteristics(self.0, id.as_ptr(), &mut metadata) };
+        (result == ACAMERA_OK).then_some(Characteristics(metadata))
+    }
+
+    /// Each camera with the position it faces, external ones are plugged in over USB.
+    fn devices(&self) -> Vec<(CameraDevice, Placement)> {
+        let mut counts = [0; 3];
+        let ids = self.camera_ids().into_iter();
+        let devices = ids.filter_map(|id| {
+            let facing = self.characteristics(&id)?.u8s(ACAMERA_LENS_FACING).first().copied();
+            let (name, index, placement) = match facing {
+                Some(ACAMERA_LENS_FACING_FRONT) => ("Front Camera", 0, Placement::BuiltIn),
+                Some(ACAMERA_LENS_FACING_BACK) => ("Back Camera", 1, Placement::BuiltIn),
+                _ => ("External Camera", 2, Placement::External),
+            };
+            counts[index] += 1;
+            let name = match counts[index] {
+                1 => name.to_string(),
+                n => format!("{name} {n}"),
+            };
+            Some((CameraDevice::new(id, name, DeviceKind::Physical), placement))
+        });
+        devices.collect()
+    }
+}
+
+impl Drop for Manager {
+    fn drop(&mut self) {
+        unsafe { ACameraManager_delete(self.0) };
+    }
+}
+
+/// The static metadata of a camera.
+struct Characteristics(*mut ACameraMetadata);
+
+impl Characteristics {
+    /// The values of `tag`, empty if the camera doesn't have it or they aren't `T`.
+    fn values<T>(&self, tag: u32, kind: u8) -> &[T] {
+        let mut entry =
+            ACameraMetadata_const_entry { tag, type_: 0, count: 0, data: std::ptr::null() };
+        let result = unsafe { ACameraMetadata_getConstEntry(self.0, tag, &mut entry) };
+        if result != ACAMERA_OK || entry.type_ != kind || entry.data.is_null() {
+            return &[];
+        }
+        unsafe { std::slice::from_raw_parts(entry.data.cast(), entry.count as usize) }
+    }
+
+    fn u8s(&self, tag: u32) -> &[u8] {
+        self.values(tag, ACAMERA_TYPE_BYTE)
+    }
+
+    fn i32s(&self, tag: u32) -> &[i32] {
+        self.values(tag, ACAMERA_TYPE_INT32)
+    }
+
+    /// The `YUV_420_888` output sizes with the frame rate range of the auto exposure.
+    fn formats(&self) -> Vec<CaptureFormat> {
+        let ranges = self.i32s(ACAMERA_CONTROL_AE_AVAILABLE_TARGET_FPS_RANGES).chunks_exact(2);
+        let min_fps = ranges.clone().map(|range| range[0]).min().unwrap_or_default() as f64;
+        let max_fps = ranges.map(|range| range[1]).max().unwrap_or_default() as f64;
+        let configs = self.i32s(ACAMERA_SCALER_AVAILABLE_STREAM_CONFIGURATIONS).chunks_exact(4);
+        configs
+            .filter(|config| {
+                config[0] == AIMAGE_FORMAT_YUV_420_888
+                    && config[3] == ACAMERA_SCALER_AVAILABLE_STREAM_CONFIGURATIONS_OUTPUT
+            })
+            .map(|config| CaptureFormat {
+                pixel_format: PIXEL_FORMAT.into(),
+                width: config[1] as u32,
+                height: config[2] as u32,
+                min_fps,
+                max_fps,
+            })
+            .collect()
+    }
+
+    /// The clock of the image timestamps.
+    fn clock(&self) -> c_int {
+        match self.u8s(ACAMERA_SENSOR_INFO_TIMESTAMP_SOURCE).first() {
+            Some(&ACAMERA_SENSOR_INFO_TIMESTAMP_SOURCE_REALTIME) => CLOCK_BOOTTIME,
+            _ => CLOCK_MONOTONIC,
+        }
+    }
+}
+
+impl Drop for Characteristics {
+    fn drop(&mut self) {
+        unsafe { ACameraMetadata_free(self.0) };
+    }
+}
+
+/// The size kamera picks without a request, the largest up to 1080p. Larger ones are often
+/// sensor modes for stills at a low frame rate.
+fn default_size(formats: &[CaptureFormat]) -> Option<(u32, u32)> {
+    let pixels = |f: &&CaptureFormat| f.width as u64 * f.height as u64;
+    let fitting = formats.iter().filter(|f| pixels(f) <= 1920 * 1080).max_by_key(pixels);
+    fitting.or(formats.first()).map(|f| (f.width, f.height))
+}
+
+/// What the callbacks of the NDK hand over to the [`Camera`], their context pointer.
+#[derive(Debug)]
+struct Shared {
+    state: Mutex<State>,
+    condvar: Condvar,
+    /// Socket pair used as a pollable notification, readable as long as frames are queued.
+    ready_rx: UnixStream,
+    ready_tx: UnixStream,
+    queue_size: usize,
+    memory_limit: AtomicUsize,
+    rate_limit: FrameRateLimit,
+    events_tx: Sender<CameraEvent>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    frames: VecDeque<Frame>,
+    /// Cleared when the stream stops or the device is lost, which wakes up waiting readers.
+    running: bool,
+}
+
+impl Shared {
+    /// Drops the oldest frames beyond the queue size or the memory limit.
+    fn push(&self, frame: Frame) {
+        let mut state = lock(&self.state);
+        state.frames.push_back(frame);
+        let limit = self.memory_limit.load(Ordering::Relaxed);
+        while state.frames.len() > self.queue_size
+            || state.frames.len() > 1
+                && state.frames.iter().map(|f| f.data.len()).sum::<usize>() > limit
+        {
+            state.frames.pop_front();
+        }
+        // a full socket buffer still means readable, so a failed write can be ignored
+        let _ = (&self.ready_tx).write(&[1]);
+        self.condvar.notify_all();
+    }
+
+    fn set_running(&self, running: bool) {
+        let mut state = lock(&self.state);
+        state.running = running;
+        if !running {
+            state.frames.clear();
+            self.drain_ready();
+        }
+        self.condvar.notify_all();
+    }
+
+    /// The oldest frame or the newest one, which drops the older ones.
+    fn pop(&self, state: &mut State, latest: bool) -> Option<Frame> {
+        let frame = match latest {
+            true => state.frames.drain(..).next_back(),
+            false => state.frames.pop_front(),
+        };
+        if state.frames.is_empty() {
+            self.drain_ready();
+        }
+        frame
+    }
+
+    fn drain_ready(&self) {
+        let mut buf = [0u8; 64];
+        while let Ok(n) = (&self.ready_rx).read(&mut buf) {
+            if n == 0 {
+                break;
+            }
+        }
+    }
+}
+
+unsafe extern "C" fn on_image_available(context: *mut c_void, reader: *mut AImageReader) {
+    let shared = &*(context as *const Shared);
+    let mut image = null_mut();
+    // the image is acquired in any case, it blocks a buffer of the reader until deleted
+    if AImageReader_acquireNextImage(reader, &mut image) != AMEDIA_OK {
+        return;
+    }
+    let frame = match shared.rate_limit.accept(Instant::now()) {
+        true => to_frame(image),
+        false => None,
+    };
+    AImage_delete(image);
+    if let Some(frame) = frame {
+        shared.push(frame);
+    }
+}
+
+unsafe extern "C" fn on_disconnected(context: *mut c_void, _device: *mut ACameraDevice) {
+    let shared = &*(context as *const Shared);
+    shared.set_running(false);
+    let _ = shared.events_tx.send(CameraEvent::DeviceLost);
+}
+
+unsafe extern "C" fn on_error(context: *mut c_void, _device: *mut ACameraDevice, error: c_int) {
+    let shared = &*(context as *const Shared);
+    shared.set_running(false);
+    let event = match error {
+        ERROR_CAMERA_IN_USE | ERROR_MAX_CAMERAS_IN_USE => CameraEvent::InUseByOtherApp,
+        // by a device policy or the privacy toggle of the camera
+        ERROR_CAMERA_DISABLED => CameraEvent::AccessDenied,
+        _ => CameraEvent::DeviceLost,
+    };
+    let _ = shared.events_tx.send(event);
+}
+
+unsafe extern "C" fn on_session_state(_context: *mut c_void, _session: *mut ACameraCaptureSession) {
+}
+
+/// The NDK objects of a running stream, the null ones weren't created.
+struct Stream {
+    device: *mut ACameraDevice,
+    reader: *mut AImageReader,
+    output: *mut ACaptureSessionOutput,
+    container: *mut ACaptureSessionOutputContainer,
+    target: *mut ACameraOutputTarget,
+    request: *mut ACaptureRequest,
+    session: *mut ACameraCaptureSession,
+}
+
+unsafe impl Send for Stream {}
+
+impl Stream {
+    /// Opens the device and repeats a capture request of the video template into a reader of
+    /// `width` x `height`.
+    fn open(
+        manager: &Manager,
+        id: &str,
+        (width, height): (u32, u32),
+        shared: &Arc<Shared>,
+        torch: bool,
+    ) -> Result<Self, Error> {
+        let id = CString::new(id).map_err(|_| Error::NoDevice)?;
+        // the camera outlives the stream, it keeps `shared` alive until the device is closed
+        let context = Arc::as_ptr(shared) as *mut c_void;
+        let mut stream = Stream {
+            device: null_mut(),
+            reader: null_mut(),
+            output: null_mut(),
+            container: null_mut(),
+            target: null_mut(),
+            request: null_mut(),
+            session: null_mut(),
+        };
+        // the NDK copies the callback structs
+        let mut device_callbacks = ACameraDevice_StateCallbacks {
+            context,
+            onDisconnected: on_disconnected,
+            onError: on_error,
+        };
+        let mut listener =
+            AImageReader_ImageListener { context, onImageAvailable: Some(on_image_available) };
+        let session_callbacks = ACameraCaptureSession_stateCallbacks {
+            context,
+            onClosed: on_session_state,
+            onReady: on_session_state,
+            onActive: on_session_state,
+        };
+        let (width, height) = (width as i32, height as i32);
+        let format = AIMAGE_FORMAT_YUV_420_888;
+        let mut window = null_mut();
+        unsafe {
+            let s = &mut stream;
+            status(ACameraManager_openCamera(
+                manager.0,
+                id.as_ptr(),
+                &mut device_callbacks,
+                &mut s.device,
+            ))?;
+            media_status(AImageReader_new(width, height, format, MAX_IMAGES, &mut s.reader))?;
+            media_status(AImageReader_setImageListener(s.reader, &mut listener))?;
+            media_status(AImageReader_getWindow(s.reader, &mut window))?;
+            status(ACaptureSessionOutput_create(window, &mut s.output))?;
+            status(ACaptureSessionOutputContainer_create(&mut s.container))?;
+            status(ACaptureSessionOutputContainer_add(s.container, s.output))?;
+            status(ACameraOutputTarget_create(window, &mut s.target))?;
+            status(ACameraDevice_createCaptureRequest(s.device, TEMPLATE_RECORD, &mut s.request))?;
+            status(ACaptureRequest_addTarget(s.request, s.target))?;
+            status(ACameraDevice_createCaptureSession(
+                s.device,
+                s.container,
+                &session_callbacks,
+                &mut s.session,
+            ))?;
+        }
+        stream.repeat(torch)?;
+        Ok(stream)
+    }
+
+    /// Sets the repeating request again with the flash mode, which changes the torch without
+    /// a restart.
+    fn repeat(&self, torch: bool) -> Result<(), Error> {
+        let mode = match torch {
+            true => ACAMERA_FLASH_MODE_TORCH,
+            false => ACAMERA_FLASH_MODE_OFF,
+        };
+        let mut request = self.request;
+        unsafe {
+            status(ACaptureRequest_setEntry_u8(self.request, ACAMERA_FLASH_MODE, 1, &mode))?;
+            status(ACameraCaptureSession_setRepeatingRequest(
+                self.session,
+                null_mut(),
+                1,
+                &mut request,
+                null_mut(),
+            ))
+        }
+    }
+}
+
+/// Frees in reverse order of creation, closing the device waits for the capture to end.
+impl Drop for Stream {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.session.is_null() {
+                ACameraCaptureSession_stopRepeating(self.session);
+                ACameraCaptureSession_close(self.session);
+            }
+            if !self.request.is_null() {
+                ACaptureRequest_free(self.request);
+            }
+            if !self.target.is_null() {
+                ACameraOutputTarget_free(self.target);
+            }
+            if !self.container.is_null() {
+                ACaptureSessionOutputContainer_free(self.container);
+            }
+            if !self.output.is_null() {
+                ACaptureSessionOutput_free(self.output);
+            }
+            if !self.device.is_null() {
+                ACameraDevice_close(self.device);
+            }
+            if !self.reader.is_null() {
+                AImageReader_setImageListener(self.reader, null_mut());
+                AImageReader_delete(self.reader);
+            }
+        }
+    }
+}
+
+/// A plane of a `YUV_420_888` image.
+#[derive(Debug, Clone, Copy)]
+struct Plane<'a> {
+    data: &'a [u8],
+    row_stride: usize,
+    pixel_stride: usize,
+}
+
+impl Plane<'_> {
+    /// Whether `data` holds `width` x `height` samples.
+    fn holds(&self, width: usize, height: usize) -> bool {
+        width == 0
+            || height == 0
+            || (height - 1) * self.row_stride + (width - 1) * self.pixel_stride < self.data.len()
+    }
+}
+
+/// Cameras deliver the chroma planes either planar or as the interleaved halves of a single
+/// NV12 or NV21 plane, any strides are converted. `None` if the planes are too short.
+fn yuv_420_888_to_bgra(
+    [y, u, v]: [Plane; 3],
+    w: u32,
+    h: u32,
+    color_space: ColorSpace,
+    bgra: &mut Vec<u8>,
+) -> Option<()> {
+    let (w, h) = (w as usize, h as usize);
+    let (chroma_w, chroma_h) = (w.div_ceil(2), h.div_ceil(2));
+    if !y.holds(w, h) || !u.holds(chroma_w, chroma_h) || !v.holds(chroma_w, chroma_h) {
+        return None;
+    }
+    let yuv = convert::YuvToRgb::new(color_space, h as u32);
+    bgra.clear();
+    bgra.reserve(w * h * 4);
+    for row in 0..h {
+        let (y_row, chroma_row) = (row * y.row_stride, row / 2);
+        for col in 0..w {
+            let sample = |plane: Plane| {
+                plane.data[chroma_row * plane.row_stride + col / 2 * plane.pixel_stride]
+            };
+            let luma = y.data[y_row + col * y.pixel_stride];
+            bgra.extend_from_slice(&yuv.bgra(luma, sample(u), sample(v)));
+        }
+    }
+    Some(())
+}
+
+/// The camera fills `YUV_420_888` as JFIF, full range BT.601.
+const COLOR_SPACE: ColorSpace = ColorSpace {
+    matrix: YuvMatrix::Bt601,
+    range: ColorRange::Full,
+    transfer: TransferFunction::Srgb,
+};
+
+unsafe fn to_frame(image: *mut AImage) -> Option<Frame> {
+    let start = Instant::now();
+    let (mut width, mut height, mut timestamp) = (0, 0, 0);
+    media_status(AImage_getWidth(image, &mut width)).ok()?;
+    media_status(AImage_getHeight(image, &mut height)).ok()?;
+    media_status(AImage_getTimestamp(image, &mut timestamp)).ok()?;
+    let plane = |index: c_int| -> Option<Plane> {
+        let (mut data, mut len, mut row_stride, mut pixel_stride) = (null_mut(), 0, 0, 0);
+        media_status(AImage_getPlaneData(image, index, &mut data, &mut len)).ok()?;
+        media_status(AImage_getPlaneRowStride(image, index, &mut row_stride)).ok()?;
+        media_status(AImage_getPlanePixelStride(image, index, &mut pixel_stride)).ok()?;
+        Some(Plane {
+            data: std::slice::from_raw_parts(data, len.max(0) as usize),
+            row_stride: row_stride.max(0) as usize,
+            pixel_stride: pixel_stride.max(0) as usize,
+        })
+    };
+    let planes = [plane(0)?, plane(1)?, plane(2)?];
+    let (u, v) = (planes[1].data.as_ptr(), planes[2].data.as_ptr());
+    let fourcc = match planes[1].pixel_stride {
+        1 => FourCC::new(b"I420"),
+        _ if v == u.wrapping_add(1) => FourCC::new(b"NV12"),
+        _ => FourCC::new(b"NV21"),
+    };
+    let size = (width as u32, height as u32);
+    let mut data = Vec::new();
+    yuv_420_888_to_bgra(planes, size.0, size.1, COLOR_SPACE, &mut data)?;
+    Some(Frame {
+        data,
+        size,
+        fourcc,
+        conversion_time: start.elapsed(),
+        timestamp: Duration::from_nanos(timestamp as u64),
+    })
+}
+
+/// What the next stream is opened with.
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    size: (u32, u32),
+    torch: bool,
+    latency: Latency,
+}
+
+pub struct Camera {
+    manager: Manager,
+    device: CameraDevice,
+    /// The output sizes of the device.
+    formats: Vec<CaptureFormat>,
+    has_torch: bool,
+    /// `CLOCK_BOOTTIME` or `CLOCK_MONOTONIC`, whichever the image timestamps are on.
+    clock: c_int,
+    open_policy: OpenPolicy,
+    settings: Mutex<Settings>,
+    /// Boxed to keep the camera small, its NDK objects are only needed to free them.
+    stream: Mutex<Option<Box<Stream>>>,
+    shared: Arc<Shared>,
+    events: Receiver<CameraEvent>,
+}
+
+impl Camera {
+    fn open(
+        manager: Manager,
+        device: CameraDevice,
+        builder: &CameraBuilder,
+    ) -> Result<Self, Error> {
+        let characteristics = manager.characteristics(&device.id).ok_or(Error::NoDevice)?;
+        let formats = characteristics.formats();
+        let size = default_size(&formats).ok_or(Error::Unsupported)?;
+        let has_torch = characteristics.u8s(ACAMERA_FLASH_INFO_AVAILABLE).first() == Some(&1);
+        let clock = characteristics.clock();
+        drop(characteristics);
+        let (ready_rx, ready_tx) = UnixStream::pair().map_err(Error::from)?;
+        ready_rx.set_nonblocking(true)?;
+        ready_tx.set_nonblocking(true)?;
+        let (events_tx, events) = channel();
+        let shared = Arc::new(Shared {
+            state: Default::default(),
+            condvar: Condvar::new(),
+            ready_rx,
+            ready_tx,
+            queue_size: builder.frame_queue_size.max(1),
+            memory_limit: AtomicUsize::new(usize::MAX),
+            rate_limit: Default::default(),
+            events_tx,
+        });
+        Ok(Camera {
+            manager,
+            device,
+            formats,
+            has_torch,
+            clock,
+            open_policy: builder.open_policy,
+            settings: Mutex::new(Settings { size, torch: false, latency: Latency::default() }),
+            stream: Default::default(),
+            shared,
+            events,
+        })
+    }
+
+    fn is_running(&self) -> bool {
+        lock(&self.stream).is_some()
+    }
+
+    /// Opens the stream again with the current size, if it runs.
+    fn restart(&self) -> bool {
+        if !self.is_running() {
+            return true;
+        }
+        self.stop();
+        self.start().is_ok()
+    }
+
+    /// Takes the size if the device outputs it, the reader only accepts those.
+    fn set_size(&self, width: u32, height: u32) -> bool {
+        let supported = self.formats.iter().any(|f| (f.width, f.height) == (width, height));
+        if supported {
+            lock(&self.settings).size = (width, height);
+        }
+        supported
+    }
+}
+
+impl InnerCamera for Camera {
+    type Frame = Frame;
+
+    fn new_with(builder: &CameraBuilder) -> Result<Self, Error> {
+        let manager = Manager::new();
+        let device = device_policy::pick(manager.devices(), &builder.device_policy)
+            .ok_or(Error::NoDevice)?;
+        Self::open(manager, device, builder)
+    }
+
+    fn start(&self) -> Result<(), Error> {
+        self.open_policy.retry(|| self.try_exclusive())
+    }
+
+    /// Android gives a camera to one app at a time, opening it fails while another has it.
+    fn try_exclusive(&self) -> Result<(), Error> {
+        let mut stream = lock(&self.stream);
+        if stream.is_some() {
+            return Ok(());
+        }
+        self.shared.set_running(true);
+        let Settings { size, torch, .. } = *lock(&self.settings);
+        match Stream::open(&self.manager, &self.device.id, size, &self.shared, torch) {
+            Ok(opened) => {
+                *stream = Some(Box::new(opened));
+                Ok(())
+            }
+            Err(err) => {
+                self.shared.set_running(false);
+                Err(err)
+            }
+        }
+    }
+
+    fn stop(&self) {
+        // dropped before waking readers, no image arrives after that
+        drop(lock(&self.stream).take());
+        self.shared.set_running(false);
+    }
+
+    /// `None` once the stream stopped or the device is gone.
+    fn wait_for_frame(&self) -> Option<Frame> {
+        let latest = lock(&self.settings).latency == Latency::Lowest;
+        let mut state = lock(&self.shared.state);
+        while state.frames.is_empty() && state.running {
+            state = wait(&self.shared.condvar, state);
+        }
+        self.shared.pop(&mut state, latest)
+    }
+
+    fn try_next_frame(&self) -> Option<Frame> {
+        self.shared.pop(&mut lock(&self.shared.state), false)
+    }
+
+    fn latest_frame(&self) -> Option<Frame> {
+        self.shared.pop(&mut lock(&self.shared.state), true)
+    }
+
+    fn frame_ready_fd(&self) -> Option<FrameReadyFd> {
+        Some(self.shared.ready_rx.as_raw_fd())
+    }
+
+    fn events(&self) -> &Receiver<CameraEvent> {
+        &self.events
+    }
+
+    fn send_event(&self, event: CameraEvent) {
+        let _ = self.shared.events_tx.send(event);
+    }
+
+    fn current_format(&self) -> Option<CaptureFormat> {
+        let size = lock(&self.settings).size;
+        self.formats.iter().find(|f| (f.width, f.height) == size).cloned()
+    }
+
+    fn device_clock(&self) -> Option<Duration> {
+        let mut time = timespec { tv_sec: 0, tv_nsec: 0 };
+        if unsafe { clock_gettime(self.clock, &mut time) } != 0 {
+            return None;
+        }
+        Some(Duration::new(time.tv_sec as u64, time.tv_nsec as u32))
+    }
+
+    /// The NDK reports the exposure only in the results of each capture.
+    fn capture_metadata(&self) -> CaptureMetadata {
+        CaptureMetadata::new(self.device.name.clone())
+    }
+
+    /// Only sizes of [`Camera::describe_device`](crate::Camera::describe_device), a running
+    /// stream is opened again.
+    fn set_resolution(&self, width: u32, height: u32) -> bool {
+        self.set_size(width, height) && self.restart()
+    }
+
+    /// The pixel format is always `YUV_420_888`, only the size is taken.
+    fn set_format(&self, format: &CaptureFormat) -> bool {
+        self.set_resolution(format.width, format.height)
+    }
+
+    fn renegotiate(&self, invalid_frames: u32) {
+        self.restart();
+        let _ = self.shared.events_tx.send(CameraEvent::StreamRenegotiated { invalid_frames });
+    }
+
+    /// The image reader only hands out complete images.
+    fn short_frames(&self) -> u32 {
+        0
+    }
+
+    fn set_max_fps(&self, fps: f32) {
+        self.shared.rate_limit.set_max_fps(fps);
+    }
+
+    /// `Lowest` hands out the newest frame and drops the queued ones.
+    fn set_latency_mode(&self, latency: Latency) {
+        lock(&self.settings).latency = latency;
+    }
+
+    fn configure(&self, changes: &RequestedConfig) -> Vec<ConfigMismatch> {
+        if let Some(fps) = changes.max_fps {
+            self.set_max_fps(fps);
+        }
+        if let Some(latency) = changes.latency {
+            self.set_latency_mode(latency);
+        }
+        if changes.format.is_none() && changes.resolution.is_none() {
+            return Vec::new();
+        }
+        let mismatches = changes.set_size(|request| match request {
+            SizeRequest::Format(format) => self.set_size(format.width, format.height),
+            SizeRequest::Resolution(width, height) => self.set_size(width, height),
+        });
+        self.restart();
+        mismatches
+    }
+
+    fn enable_metadata(&self, _kind: MetadataKind) -> bool {
+        false
+    }
+
+    fn faces(&self) -> Vec<FaceRect> {
+        Vec::new()
+    }
+
+    fn enable_depth(&self) -> bool {
+        false
+    }
+
+    fn depth(&self, _frame: &Frame) -> Option<Arc<DepthFrame>> {
+        None
+    }
+
+    /// The NDK calls back on threads of its own.
+    fn set_thread_priority(&self, _priority: ThreadPriority) {}
+
+    /// Queued frames over the limit are dropped, the newest one is always kept.
+    fn set_memory_limit(&self, bytes: usize) {
+        self.shared.memory_limit.store(bytes, Ordering::Relaxed);
+    }
+
+    fn memory_usage(&self) -> usize {
+        lock(&self.shared.state).frames.iter().map(|frame| frame.data.len()).sum()
+    }
+
+    fn supports_enhancement(&self, _enhancement: Enhancement) -> bool {
+        false
+    }
+
+    fn set_enhancement(&self, _enhancement: Enhancement, _enabled: bool) -> bool {
+        false
+    }
+
+    fn set_video_orientation(&self, _orientation: VideoOrientation) -> bool {
+        false
+    }
+
+    fn ptz_range(&self, _axis: PtzAxis) -> Option<PtzRange> {
+        None
+    }
+
+    fn ptz(&self, _axis: PtzAxis) -> Option<f32> {
+        None
+    }
+
+    fn set_ptz(&self, _axis: PtzAxis, _value: f32) -> bool {
+        false
+    }
+
+    fn has_torch(&self) -> bool {
+        self.has_torch
+    }
+
+    /// The NDK has no torch strength, any level above 0 turns it on.
+    fn set_torch_level(&self, level: f32) -> Result<(), Error> {
+        if !self.has_torch {
+            return Err(Error::Unsupported);
+        }
+        let torch = level > 0.0;
+        lock(&self.settings).torch = torch;
+        match &*lock(&self.stream) {
+            Some(stream) => stream.repeat(torch),
+            None => Ok(()),
+        }
+    }
+
+    fn device(&self) -> CameraDevice {
+        self.device.clone()
+    }
+
+    fn set_device(&mut self, device: &CameraDevice) -> Result<(), Error> {
+        if device.id == self.device.id {
+            return Ok(());
+        }
+        self.stop();
+        let manager = Manager::new();
+        let Some((device, _)) = manager.devices().into_iter().find(|(d, _)| d.id == device.id)
+        else {
+            return Err(Error::NoDevice);
+        };
+        let builder = CameraBuilder {
+            frame_queue_size: self.shared.queue_size,
+            open_policy: self.open_policy,
+            ..Default::default()
+        };
+        *self = Self::open(manager, device, &builder)?;
+        self.start()
+    }
+
+    fn device_list() -> Vec<CameraDevice> {
+        Manager::new().devices().into_iter().map(|(device, _)| device).collect()
+    }
+
+    /// Device types only exist on macOS.
+    fn device_list_of_types(_types: &[DeviceType]) -> Vec<CameraDevice> {
+        Self::device_list()
+    }
+
+    fn enumerate_with_errors() -> Vec<Result<CameraDevice, EnumError>> {
+        Self::device_list().into_iter().map(Ok).collect()
+    }
+
+    /// The camera id of the NDK, which stays the same for the built-in cameras.
+    fn stable_id(device: &CameraDevice) -> String {
+        device.id.clone()
+    }
+
+    fn group_id(device: &CameraDevice) -> String {
+        device.id.clone()
+    }
+
+    fn describe_device(device: &CameraDevice) -> DeviceCapabilities {
+        let characteristics = Manager::new().characteristics(&device.id);
+        let formats = characteristics.map(|c| c.formats()).unwrap_or_default();
+        DeviceCapabilities { formats }
+    }
+
+    fn default_format(device: &CameraDevice) -> Option<CaptureFormat> {
+        let formats = Self::describe_device(device).formats;
+        let size = default_size(&formats)?;
+        formats.into_iter().find(|f| (f.width, f.height) == size)
+    }
+
+    fn backend_capabilities() -> BackendCapabilities {
+        BackendCapabilities {
+            capture: true,
+            device_types: false,
+            face_metadata: false,
+            depth: false,
+        }
+    }
+
+    fn device_details(_device: &CameraDevice) -> DeviceDetails {
+        DeviceDetails::default()
+    }
+}
+
+/// Closes the device before `shared`, the context of its callbacks, is freed.
+impl Drop for Camera {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+impl std::fmt::Debug for Camera {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Camera").field("device", &self.device.name).finish()
+    }
+}
+
+pub struct Frame {
+    data: Vec<u8>,
+    size: (u32, u32),
+    /// The layout of the `YUV_420_888` planes before conversion.
+    fourcc: FourCC,
+    conversion_time: Duration,
+    /// The sensor timestamp, see [`Camera::device_clock`](crate::Camera::device_clock).
+    timestamp: Duration,
+}
+
+impl Frame {
+    pub fn data(&self) -> FrameData {
+        FrameData { data: &self.data, stride: self.size.0 as usize * 4, size: self.size }
+    }
+
+    pub fn size_u32(&self) -> (u32, u32) {
+        self.size
+    }
+
+    pub fn conversion_time(&self) -> Duration {
+        self.conversion_time
+    }
+
+    pub fn timestamp(&self) -> Option<Duration> {
+        Some(self.timestamp)
+    }
+
+    pub fn color_space(&self) -> ColorSpace {
+        COLOR_SPACE
+    }
+
+    pub fn fourcc(&self) -> FourCC {
+        self.fourcc
+    }
+
+    /// The converted buffer has a single owner, a crop copies.
+    pub fn share(&self) -> Option<Frame> {
+        None
+    }
+}
+
+impl std::fmt::Debug for Frame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Frame")
+            .field("data", &self.data.len())
+            .field("fourcc", &self.fourcc)
+            .finish()
+    }
+}
+
+#[derive(Debug)]
+pub struct FrameData<'a> {
+    data: &'a [u8],
+    stride: usize,
+    size: (u32, u32),
+}
+
+impl<'a> FrameData<'a> {
+    pub fn data_u8(&self) -> &[u8] {
+        self.data
+    }
+
+    pub fn data_u32(&self) -> &[u32] {
+        unsafe { self.data.align_to().1 }
+    }
+
+    pub fn data_u16(&self) -> Option<&[u16]> {
+        None
+    }
+
+    pub fn luma(&self) -> Option<&[u8]> {
+        None
+    }
+
+    pub fn chroma(&self) -> Option<&[u8]> {
+        None
+    }
+
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    pub fn plane_count(&self) -> usize {
+        1
+    }
+
+    pub fn plane(&self, index: usize) -> Option<PlaneView> {
+        let (width, height) = self.size;
+        (index == 0).then_some(PlaneView { data: self.data, stride: self.stride, width, height })
+    }
+}
+
+#[test]
+fn interleaved_and_planar_chroma_agree() {
+    let (w, h) = (4, 2);
+    let luma: Vec<u8> = (0..8).map(|i| 16 + i * 20).collect();
+    let (u, v) = ([90, 160], [200, 60]);
+    let y = Plane { data: &luma, row_stride: 4, pixel_stride: 1 };
+    let planar = [
+        y,
+        Plane { data: &u, row_stride: 2, pixel_stride: 1 },
+        Plane { data: &v, row_stride: 2, pixel_stride: 1 },
+    ];
+    // NV12 with a padded row, V is the second byte of each pair
+    let uv = [90, 200, 160, 60, 0, 0];
+    let nv12 = [
+        y,
+        Plane { data: &uv[..3], row_stride: 6, pixel_stride: 2 },
+        Plane { data: &uv[1..4], row_stride: 6, pixel_stride: 2 },
+    ];
+    let (mut a, mut b) = (Vec::new(), Vec::new());
+    yuv_420_888_to_bgra(planar, w, h, COLOR_SPACE, &mut a).unwrap();
+    yuv_420_888_to_bgra(nv12, w, h, COLOR_SPACE, &mut b).unwrap();
+    assert_eq!(a.len(), 4 * 2 * 4);
+    assert_eq!(a, b);
+    let short = [y, Plane { data: &u[..1], row_stride: 2, pixel_stride: 1 }, planar[2]];
+    assert!(yuv_420_888_to_bgra(short, w, h, COLOR_SPACE, &mut a).is_none());
+}
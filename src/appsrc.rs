@@ -0,0 +1,113 @@
+//! Feed camera frames into a GStreamer pipeline, enabled with the `gstreamer` feature.
+//!
+//! [`CameraAppSrc`] wraps an `appsrc` element with BGRA caps of the format the camera
+//! negotiated. Add it to a pipeline, put `videoconvert` behind it and push frames, or let
+//! [`CameraAppSrc::stream`] do that. Frames are timestamped when they arrive, relative to the
+//! first one. A pipeline which falls behind misses frames instead of queueing them up.
+//!
+//! ```no_run
+//! use gstreamer::prelude::*;
+//!
+//! gstreamer::init().unwrap();
+//! let camera = kamera::Camera::new_default_device();
+//! let mut src = kamera::appsrc::CameraAppSrc::new(&camera).unwrap();
+//! let pipeline = gstreamer::Pipeline::new();
+//! let convert = gstreamer::ElementFactory::make("videoconvert").build().unwrap();
+//! let sink = gstreamer::ElementFactory::make("autovideosink").build().unwrap();
+//! let elements = [src.appsrc().upcast_ref(), &convert, &sink];
+//! pipeline.add_many(elements).unwrap();
+//! gstreamer::Element::link_many(elements).unwrap();
+//! pipeline.set_state(gstreamer::State::Playing).unwrap();
+//! src.stream(&camera, &kamera::CancelToken::new()).unwrap();
+//! ```
+
+use std::time::Instant;
+
+use gstreamer as gst;
+use gstreamer_app as gst_app;
+use gstreamer_video as gst_video;
+
+use crate::{Camera, CancelToken, Error, Frame};
+
+/// Frames queued in the element before newer ones are dropped.
+const MAX_QUEUED_FRAMES: u64 = 4;
+
+/// An `appsrc` element fed with the frames of a camera.
+#[derive(Debug)]
+pub struct CameraAppSrc {
+    appsrc: gst_app::AppSrc,
+    size: (u32, u32),
+    fps: f64,
+    start: Option<Instant>,
+}
+
+impl CameraAppSrc {
+    /// An element with caps for the current format of `camera`. Calls `gstreamer::init`.
+    pub fn new(camera: &Camera) -> Result<Self, Error> {
+        gst::init().map_err(Error::os)?;
+        let format = camera.current_format().ok_or(Error::Unsupported)?;
+        let (size, fps) = ((format.width, format.height), format.max_fps);
+        let appsrc = gst_app::AppSrc::builder()
+            .caps(&caps(size, fps)?)
+            .format(gst::Format::Time)
+            .is_live(true)
+            .build();
+        Ok(Self { appsrc, size, fps, start: None })
+    }
+
+    /// The element to add to a pipeline.
+    pub fn appsrc(&self) -> &gst_app::AppSrc {
+        &self.appsrc
+    }
+
+    /// Copies `frame` into a buffer and pushes it. A frame of another size changes the caps,
+    /// e.g. after [`Camera::set_resolution`]. `Err` once the pipeline stopped or failed.
+    pub fn push(&mut self, frame: &Frame) -> Result<(), Error> {
+        let size = frame.size_u32();
+        if size != self.size {
+            self.appsrc.set_caps(Some(&caps(size, self.fps)?));
+            self.size = size;
+        }
+        let data = frame.data();
+        let bgra = data.data_bgra();
+        if self.appsrc.current_level_bytes() >= MAX_QUEUED_FRAMES * bgra.len() as u64 {
+            return Ok(());
+        }
+        let start = *self.start.get_or_insert_with(Instant::now);
+        let pts = gst::ClockTime::from_nseconds(start.elapsed().as_nanos() as u64);
+        let mut buffer = gst::Buffer::from_slice(bgra.to_vec());
+        let buffer_ref = buffer.get_mut().expect("new buffer is writable");
+        buffer_ref.set_pts(pts);
+        if self.fps > 0.0 {
+            buffer_ref.set_duration(gst::ClockTime::from_nseconds((1e9 / self.fps) as u64));
+        }
+        self.appsrc.push_buffer(buffer).map_err(Error::os)?;
+        Ok(())
+    }
+
+    /// Runs `camera` with [`Camera::run`] and pushes its frames until `cancel` is cancelled or
+    /// the pipeline stops, then ends the stream.
+    pub fn stream(&mut self, camera: &Camera, cancel: &CancelToken) -> Result<(), Error> {
+        let mut pushed = Ok(());
+        let result = camera.run(cancel, |frame| {
+            if pushed.is_ok() {
+                pushed = self.push(&frame);
+            }
+            if pushed.is_err() {
+                cancel.cancel();
+            }
+        });
+        let _ = self.appsrc.end_of_stream();
+        result.and(pushed)
+    }
+}
+
+/// BGRA caps, the frame rate is rounded to a thousandth.
+fn caps((width, height): (u32, u32), fps: f64) -> Result<gst::Caps, Error> {
+    let mut info = gst_video::VideoInfo::builder(gst_video::VideoFormat::Bgra, width, height);
+    if fps > 0.0 {
+        info = info.fps(gst::Fraction::new((fps * 1000.0).round() as i32, 1000));
+    }
+    let info = info.build().map_err(Error::os)?;
+    info.to_caps().map_err(Error::os)
+}
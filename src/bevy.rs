@@ -0,0 +1,181 @@
+//! Camera frames as a Bevy texture, enabled with the `bevy` feature.
+//!
+//! [`KameraPlugin`] captures on its own thread and copies the latest frame into the image of
+//! [`CameraTexture`] before each update, ready for a sprite or a UI image. [`CameraDevices`]
+//! lists the cameras, a [`SelectCamera`] event switches to another one and a [`CaptureFailed`]
+//! event reports a camera which can't be opened or stopped delivering frames.
+//!
+//! ```no_run
+//! use bevy::prelude::*;
+//! use kamera::bevy::{CameraTexture, KameraPlugin};
+//!
+//! fn show(texture: Res<CameraTexture>, images: Res<Assets<Image>>) {
+//!     if let Some(image) = images.get(&texture.image) {
+//!         println!("{:?}", image.size());
+//!     }
+//! }
+//!
+//! App::new()
+//!     .add_plugins((DefaultPlugins, KameraPlugin::default()))
+//!     .add_systems(Update, show)
+//!     .run();
+//! ```
+
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+use ::bevy::prelude::*;
+use ::bevy::render::render_asset::RenderAssetUsages;
+use ::bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+
+use crate::sync::lock;
+use crate::{Camera, CameraDevice, CancelToken, Error, Frame};
+
+/// Add it after `DefaultPlugins`, it needs the image assets.
+#[derive(Debug, Clone, Default)]
+pub struct KameraPlugin {
+    /// The camera to start with, the default one if `None`.
+    pub device: Option<CameraDevice>,
+}
+
+/// The image with the latest frame, in BGRA. It is black until the first frame arrives and
+/// takes the size of the frames.
+#[derive(Resource, Debug, Clone)]
+pub struct CameraTexture {
+    pub image: Handle<Image>,
+}
+
+#[derive(Resource, Debug, Clone, Default)]
+pub struct CameraDevices {
+    /// The cameras when the plugin was added.
+    pub devices: Vec<CameraDevice>,
+    /// The last one selected, `None` for the default camera.
+    pub selected: Option<CameraDevice>,
+}
+
+/// Switches to another camera.
+#[derive(Event, Debug, Clone)]
+pub struct SelectCamera(pub CameraDevice);
+
+/// The camera couldn't be opened or stopped, capture resumes with the next [`SelectCamera`].
+#[derive(Event, Debug, Clone)]
+pub struct CaptureFailed(pub Error);
+
+impl Plugin for KameraPlugin {
+    fn build(&self, app: &mut App) {
+        let mut images = app.world_mut().resource_mut::<Assets<Image>>();
+        let image = images.add(Image::new_fill(
+            Extent3d { width: 1, height: 1, depth_or_array_layers: 1 },
+            TextureDimension::D2,
+            &[0, 0, 0, 255],
+            TextureFormat::Bgra8UnormSrgb,
+            RenderAssetUsages::default(),
+        ));
+        let capture = Capture::spawn();
+        capture.select(self.device.clone());
+        let devices =
+            CameraDevices { devices: Camera::device_list(), selected: self.device.clone() };
+        app.insert_resource(CameraTexture { image })
+            .insert_resource(devices)
+            .insert_resource(capture)
+            .add_event::<SelectCamera>()
+            .add_event::<CaptureFailed>()
+            .add_systems(PreUpdate, (select_camera, update_texture).chain());
+    }
+}
+
+#[derive(Default)]
+struct Shared {
+    latest: Mutex<Option<Frame>>,
+    failed: Mutex<Option<Error>>,
+    /// Ends the current run of the camera, replaced for each run.
+    cancel: Mutex<CancelToken>,
+}
+
+/// The capture thread, which ends when this is dropped with the app.
+#[derive(Resource)]
+struct Capture {
+    shared: Arc<Shared>,
+    select: Sender<Option<CameraDevice>>,
+}
+
+impl Capture {
+    fn spawn() -> Self {
+        let shared = Arc::new(Shared::default());
+        let (select, selected) = channel();
+        let thread_shared = shared.clone();
+        std::thread::spawn(move || capture(&thread_shared, selected));
+        Self { shared, select }
+    }
+
+    fn select(&self, device: Option<CameraDevice>) {
+        let _ = self.select.send(device);
+        lock(&self.shared.cancel).cancel();
+    }
+}
+
+impl Drop for Capture {
+    fn drop(&mut self) {
+        lock(&self.shared.cancel).cancel();
+    }
+}
+
+/// Runs the selected camera until another one is selected or the app ends. The token of a run
+/// is in place before the selection is taken, so a selection after that cancels the run.
+fn capture(shared: &Shared, selected: Receiver<Option<CameraDevice>>) {
+    let mut camera = None;
+    while let Ok(device) = selected.recv() {
+        let cancel = CancelToken::new();
+        *lock(&shared.cancel) = cancel.clone();
+        let device = selected.try_iter().last().unwrap_or(device);
+        let result = open(&mut camera, device.as_ref())
+            .and_then(|camera| camera.run(&cancel, |frame| *lock(&shared.latest) = Some(frame)));
+        if let Err(err) = result {
+            *lock(&shared.failed) = Some(err);
+        }
+    }
+}
+
+fn open<'a>(
+    camera: &'a mut Option<Camera>,
+    device: Option<&CameraDevice>,
+) -> Result<&'a Camera, Error> {
+    let camera = match camera {
+        Some(camera) => camera,
+        slot => slot.insert(Camera::try_new_default_device()?),
+    };
+    if let Some(device) = device.filter(|device| **device != camera.device()) {
+        camera.try_set_device(device)?;
+    }
+    Ok(camera)
+}
+
+fn select_camera(
+    mut events: EventReader<SelectCamera>,
+    capture: Res<Capture>,
+    mut devices: ResMut<CameraDevices>,
+) {
+    for SelectCamera(device) in events.read() {
+        devices.selected = Some(device.clone());
+        capture.select(Some(device.clone()));
+    }
+}
+
+fn update_texture(
+    capture: Res<Capture>,
+    texture: Res<CameraTexture>,
+    mut images: ResMut<Assets<Image>>,
+    mut failed: EventWriter<CaptureFailed>,
+) {
+    if let Some(err) = lock(&capture.shared.failed).take() {
+        failed.send(CaptureFailed(err));
+    }
+    let Some(frame) = lock(&capture.shared.latest).take() else { return };
+    let Some(image) = images.get_mut(&texture.image) else { return };
+    let (width, height) = frame.size_u32();
+    let size = Extent3d { width, height, depth_or_array_layers: 1 };
+    if image.texture_descriptor.size != size {
+        image.resize(size);
+    }
+    image.data.copy_from_slice(frame.data().data_bgra());
+}
@@ -0,0 +1,163 @@
+use crate::scale::resize_bgra;
+
+/// How [`Frame::copy_to_buffer`](crate::Frame::copy_to_buffer) maps the frame onto a buffer of
+/// another size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Fit {
+    /// Keep the aspect ratio and show the whole frame, the rest of the buffer turns black.
+    #[default]
+    Contain,
+    /// Keep the aspect ratio and fill the whole buffer, cropping the frame.
+    Cover,
+    /// Fill the whole buffer, distorting the frame.
+    Stretch,
+}
+
+/// Sampling used when the frame gets scaled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Filter {
+    Nearest,
+    #[default]
+    Bilinear,
+    /// Lanczos with three lobes, the sharpest and slowest, see [`scale`](crate::scale).
+    Lanczos,
+}
+
+/// Opaque black in the `0xAARRGGBB` layout of the destination.
+const BLACK: u32 = 0xff00_0000;
+
+/// Source position and weight of the next source pixel for one destination column or row.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Sample {
+    index: usize,
+    next: usize,
+    weight: f32,
+}
+
+/// Samples for `dst_len` destination pixels of which `scaled_len` show `src_len` source
+/// pixels, starting at `offset`, which is negative for cropping.
+fn samples(
+    src_len: u32,
+    scaled_len: u32,
+    offset: i64,
+    dst_len: u32,
+    filter: Filter,
+) -> Vec<Option<Sample>> {
+    let scale = src_len as f32 / scaled_len as f32;
+    let last = src_len as usize - 1;
+    (0..dst_len as i64)
+        .map(|dst| {
+            let pos = dst - offset;
+            if pos < 0 || pos >= scaled_len as i64 {
+                return None;
+            }
+            let center = (pos as f32 + 0.5) * scale - 0.5;
+            Some(match filter {
+                // Lanczos is scaled beforehand, only placed here
+                Filter::Nearest | Filter::Lanczos => {
+                    let index = (center.round().max(0.0) as usize).min(last);
+                    Sample { index, next: index, weight: 0.0 }
+                }
+                Filter::Bilinear => {
+                    let index = (center.floor().max(0.0) as usize).min(last);
+                    let weight = (center - index as f32).clamp(0.0, 1.0);
+                    Sample { index, next: (index + 1).min(last), weight }
+                }
+            })
+        })
+        .collect()
+}
+
+/// Scales packed BGRA rows of `stride` bytes into `dst`, one `0xAARRGGBB` value per pixel,
+/// which is what softbuffer and minifb expect.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn blit(
+    src: &[u8],
+    (w, h): (u32, u32),
+    stride: usize,
+    dst: &mut [u32],
+    (dst_w, dst_h): (u32, u32),
+    fit: Fit,
+    filter: Filter,
+) {
+    assert!(dst.len() >= dst_w as usize * dst_h as usize, "buffer smaller than its size");
+    let dst = &mut dst[..dst_w as usize * dst_h as usize];
+    if w == 0 || h == 0 || dst_w == 0 || dst_h == 0 {
+        dst.fill(BLACK);
+        return;
+    }
+    let (scale_x, scale_y) = (dst_w as f64 / w as f64, dst_h as f64 / h as f64);
+    let (scaled_w, scaled_h) = match fit {
+        Fit::Contain => {
+            let scale = scale_x.min(scale_y);
+            (w as f64 * scale, h as f64 * scale)
+        }
+        Fit::Cover => {
+            let scale = scale_x.max(scale_y);
+            (w as f64 * scale, h as f64 * scale)
+        }
+        Fit::Stretch => (dst_w as f64, dst_h as f64),
+    };
+    let scaled_w = scaled_w.round().max(1.0) as u32;
+    let scaled_h = scaled_h.round().max(1.0) as u32;
+    let offset_x = (dst_w as i64 - scaled_w as i64) / 2;
+    let offset_y = (dst_h as i64 - scaled_h as i64) / 2;
+    let resized;
+    let (src, (w, h), stride) = match filter {
+        Filter::Lanczos => {
+            resized = resize_bgra(src, (w, h), stride, (scaled_w, scaled_h), filter);
+            (&resized[..], (scaled_w, scaled_h), scaled_w as usize * 4)
+        }
+        _ => (src, (w, h), stride),
+    };
+    let columns = samples(w, scaled_w, offset_x, dst_w, filter);
+    let rows = samples(h, scaled_h, offset_y, dst_h, filter);
+
+    let lerp = |a: [f32; 3], b: [f32; 3], t: f32| [0, 1, 2].map(|i| a[i] + (b[i] - a[i]) * t);
+    let pixel = |row: usize, col: usize| {
+        let px = &src[row * stride + col * 4..][..4];
+        [px[0] as f32, px[1] as f32, px[2] as f32]
+    };
+    for (dst_row, row) in dst.chunks_exact_mut(dst_w as usize).zip(&rows) {
+        let Some(row) = row else {
+            dst_row.fill(BLACK);
+            continue;
+        };
+        for (dst_px, col) in dst_row.iter_mut().zip(&columns) {
+            let Some(col) = col else {
+                *dst_px = BLACK;
+                continue;
+            };
+            let top = lerp(pixel(row.index, col.index), pixel(row.index, col.next), col.weight);
+            let bottom = lerp(pixel(row.next, col.index), pixel(row.next, col.next), col.weight);
+            let [b, g, r] = lerp(top, bottom, row.weight).map(|c| (c + 0.5) as u8);
+            *dst_px = u32::from_le_bytes([b, g, r, 255]);
+        }
+    }
+}
+
+#[test]
+fn blit_same_size_copies() {
+    let src = [1, 2, 3, 255, 4, 5, 6, 255, 0, 0, 0, 0];
+    let mut dst = [0; 2];
+    blit(&src, (2, 1), 12, &mut dst, (2, 1), Fit::Contain, Filter::Bilinear);
+    assert_eq!(dst, [0xff030201, 0xff060504]);
+    blit(&src, (2, 1), 12, &mut dst, (2, 1), Fit::Contain, Filter::Lanczos);
+    assert_eq!(dst, [0xff030201, 0xff060504]);
+}
+
+#[test]
+fn blit_fits() {
+    // 2x1 white and black onto 2x2
+    let src = [255, 255, 255, 255, 0, 0, 0, 255];
+    let white = 0xffffffff;
+    let mut dst = [0; 4];
+    blit(&src, (2, 1), 8, &mut dst, (2, 2), Fit::Contain, Filter::Nearest);
+    assert_eq!(dst, [white, BLACK, BLACK, BLACK]);
+    blit(&src, (2, 1), 8, &mut dst, (2, 2), Fit::Stretch, Filter::Nearest);
+    assert_eq!(dst, [white, BLACK, white, BLACK]);
+    // black, white, white, black cropped to the middle
+    let src = [[0, 0, 0, 255], [255; 4], [255; 4], [0, 0, 0, 255]].concat();
+    blit(&src, (4, 1), 16, &mut dst, (2, 2), Fit::Cover, Filter::Bilinear);
+    assert_eq!(dst, [white; 4]);
+}
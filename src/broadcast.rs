@@ -0,0 +1,127 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError, Weak};
+
+use crate::sync::lock;
+use crate::Frame;
+
+/// Frames kept per receiver before the oldest one is dropped. On macOS and Windows every queued
+/// frame holds on to a capture buffer of the OS.
+const QUEUE_SIZE: usize = 4;
+
+/// The receivers of [`Camera::subscribe`](crate::Camera::subscribe).
+#[derive(Debug)]
+pub(crate) struct Broadcast {
+    receivers: Mutex<Vec<Weak<Queue>>>,
+    /// Bytes of the frames each receiver queues at most, see
+    /// [`Camera::set_memory_limit`](crate::Camera::set_memory_limit).
+    memory_limit: AtomicUsize,
+}
+
+impl Default for Broadcast {
+    fn default() -> Self {
+        Self { receivers: Default::default(), memory_limit: usize::MAX.into() }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Queue {
+    state: Mutex<QueueState>,
+    ready: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    frames: VecDeque<Arc<Frame>>,
+    closed: bool,
+}
+
+/// One of several consumers of the frames of a camera, see
+/// [`Camera::subscribe`](crate::Camera::subscribe).
+///
+/// Receivers are `Send` and can be moved to their own threads. Frames are shared between all
+/// receivers, one which falls behind by more than a few frames misses the oldest ones.
+#[derive(Debug)]
+pub struct FrameReceiver {
+    queue: Arc<Queue>,
+}
+
+impl Broadcast {
+    pub(crate) fn subscribe(&self) -> FrameReceiver {
+        let queue = Arc::new(Queue::default());
+        lock(&self.receivers).push(Arc::downgrade(&queue));
+        FrameReceiver { queue }
+    }
+
+    pub(crate) fn has_receivers(&self) -> bool {
+        let mut receivers = lock(&self.receivers);
+        receivers.retain(|queue| queue.strong_count() > 0);
+        !receivers.is_empty()
+    }
+
+    pub(crate) fn set_memory_limit(&self, bytes: usize) {
+        self.memory_limit.store(bytes, Ordering::Relaxed);
+    }
+
+    /// Queues `frame` for every receiver, dropping the oldest frames of a receiver above
+    /// [`QUEUE_SIZE`] or the memory limit. The queues hold the same frames, so together they
+    /// stay within the limit too, apart from the newest frame, which is always queued.
+    pub(crate) fn send(&self, frame: Frame) {
+        let frame = Arc::new(frame);
+        let limit = self.memory_limit.load(Ordering::Relaxed);
+        lock(&self.receivers).retain(|queue| {
+            let Some(queue) = queue.upgrade() else { return false };
+            let mut state = lock(&queue.state);
+            let mut bytes = frame_bytes(&frame);
+            let queued = state.frames.iter().rev().take(QUEUE_SIZE - 1).take_while(|queued| {
+                bytes += frame_bytes(queued);
+                bytes <= limit
+            });
+            let drop = state.frames.len() - queued.count();
+            state.frames.drain(..drop);
+            state.frames.push_back(frame.clone());
+            queue.ready.notify_all();
+            true
+        });
+    }
+
+    /// Ends all receivers once they received their queued frames.
+    pub(crate) fn close(&self) {
+        for queue in lock(&self.receivers).drain(..).filter_map(|q| q.upgrade()) {
+            lock(&queue.state).closed = true;
+            queue.ready.notify_all();
+        }
+    }
+}
+
+impl FrameReceiver {
+    /// Waits for the next frame, `None` once the broadcast ended and all frames were received.
+    pub fn recv(&self) -> Option<Arc<Frame>> {
+        self.wait().frames.pop_front()
+    }
+
+    /// Returns immediately, `None` if no new frame is ready.
+    pub fn try_recv(&self) -> Option<Arc<Frame>> {
+        lock(&self.queue.state).frames.pop_front()
+    }
+
+    /// Waits for a frame like [`FrameReceiver::recv`] but drops queued older frames.
+    pub fn latest(&self) -> Option<Arc<Frame>> {
+        let mut state = self.wait();
+        let frame = state.frames.pop_back();
+        state.frames.clear();
+        frame
+    }
+
+    fn wait(&self) -> MutexGuard<QueueState> {
+        let state = lock(&self.queue.state);
+        let ready = self.queue.ready.wait_while(state, |s| s.frames.is_empty() && !s.closed);
+        ready.unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Bytes of `frame` as BGRA, about what it holds on to in any format.
+fn frame_bytes(frame: &Frame) -> usize {
+    let (width, height) = frame.size_u32();
+    width as usize * height as usize * 4
+}
@@ -0,0 +1,198 @@
+use std::sync::Arc;
+
+use crate::decoder::SharedProvider;
+use crate::{
+    Camera, DecoderProvider, DefaultDevicePolicy, Error, LinuxBackend, OpenPolicy, WinBackend,
+};
+
+/// Configures a [`Camera`] before it opens the default device.
+///
+/// ```no_run
+/// let camera = kamera::Camera::builder().discard_late_frames(false).frame_queue_size(8).build();
+/// ```
+#[derive(Debug, Clone)]
+pub struct CameraBuilder {
+    pub(crate) discard_late_frames: bool,
+    pub(crate) frame_queue_size: usize,
+    pub(crate) pixel_formats: Vec<String>,
+    pub(crate) frame_pool_size: usize,
+    pub(crate) pipeline_workers: usize,
+    pub(crate) buffer_count: u32,
+    pub(crate) restore_format: bool,
+    pub(crate) device_policy: DefaultDevicePolicy,
+    pub(crate) decoder_provider: Option<SharedProvider>,
+    pub(crate) open_policy: OpenPolicy,
+    pub(crate) win_backend: WinBackend,
+    pub(crate) linux_backend: LinuxBackend,
+}
+
+impl Default for CameraBuilder {
+    fn default() -> Self {
+        Self {
+            discard_late_frames: true,
+            frame_queue_size: 1,
+            pixel_formats: [
+                "RGB3",
+                "YUYV",
+                "UYVY",
+                "NV12",
+                "GREY",
+                #[cfg(feature = "mjpeg")]
+                "MJPG",
+                "Y10 ",
+                "Y12 ",
+                "Y16 ",
+                "RGGB",
+                "BA81",
+                "GRBG",
+                "GBRG",
+                "RG10",
+                "BG10",
+                "BA10",
+                "GB10",
+                "RG12",
+                "BG12",
+                "BA12",
+                "GB12",
+                "RG16",
+                "BYR2",
+                "GR16",
+                "GB16",
+            ]
+            .map(String::from)
+            .to_vec(),
+            frame_pool_size: 2,
+            pipeline_workers: 0,
+            buffer_count: 4,
+            restore_format: true,
+            device_policy: DefaultDevicePolicy::default(),
+            decoder_provider: None,
+            open_policy: OpenPolicy::default(),
+            win_backend: WinBackend::default(),
+            linux_backend: LinuxBackend::default(),
+        }
+    }
+}
+
+impl CameraBuilder {
+    /// Drop frames which arrive while the previous one is still being delivered (the default),
+    /// or queue them up, which favors completeness over latency. Only macOS.
+    pub fn discard_late_frames(mut self, discard: bool) -> Self {
+        self.discard_late_frames = discard;
+        self
+    }
+
+    /// Number of unread frames kept before the oldest one is dropped, default is 1.
+    ///
+    /// This is a queue in kamera between the sample buffer delegate and
+    /// [`Camera::wait_for_frame`], not the buffer pool of the `AVCaptureVideoDataOutput`, whose
+    /// size AVFoundation doesn't let applications set. Every queued frame holds on to a buffer
+    /// of that pool, when it runs dry new frames get dropped by AVFoundation, so a large queue
+    /// can drop frames of the camera instead of old ones. Only macOS, Windows queues all frames.
+    pub fn frame_queue_size(mut self, size: usize) -> Self {
+        self.frame_queue_size = size.max(1);
+        self
+    }
+
+    /// Pixel formats to negotiate with the device as four character codes, most preferred first.
+    ///
+    /// The first one the device offers is used at its largest size, if none is offered the
+    /// current format of the device stays. Only Linux, default is `RGB3`, `YUYV`, `UYVY`, `NV12`
+    /// and `GREY`, `MJPG` with the `mjpeg` feature, then the 8 to 16-bit grayscale and Bayer
+    /// formats like `Y10 ` and `RG10`, which are all formats it can convert.
+    pub fn pixel_formats(mut self, fourccs: &[&str]) -> Self {
+        self.pixel_formats = fourccs.iter().map(|f| f.to_string()).collect();
+        self
+    }
+
+    /// Number of frame buffers kept for reuse after their frames are dropped, which saves an
+    /// allocation per frame. The default of 2 covers a frame the application holds on to while
+    /// the next one is converted, more help with [`CameraBuilder::pipeline_workers`] or frames
+    /// kept around longer. 0 allocates every frame. Only Linux, where frames are converted.
+    pub fn frame_pool_size(mut self, size: usize) -> Self {
+        self.frame_pool_size = size;
+        self
+    }
+
+    /// Convert frames on this many worker threads while a capture thread dequeues the next
+    /// buffers, instead of converting in [`Camera::wait_for_frame`]. This takes the conversion
+    /// time off the latency of each call and sustains a higher frame rate, at the cost of
+    /// copying each buffer once. Frames keep their order, while the application is behind new
+    /// ones are dropped. [`Camera::frame_ready_fd`] doesn't work with a pipeline.
+    ///
+    /// MJPG at 1080p and 60 fps needs about four workers to decode in time, see the `mjpeg`
+    /// feature.
+    ///
+    /// Only Linux, default is 0, which converts on the calling thread.
+    pub fn pipeline_workers(mut self, workers: usize) -> Self {
+        self.pipeline_workers = workers;
+        self
+    }
+
+    /// Number of buffers the driver captures into. Fewer buffers lower the latency, more of them
+    /// tolerate longer pauses of the application before frames get dropped. Only Linux, where
+    /// the driver may adjust the number, default is 4. libcamera may allocate fewer.
+    pub fn buffer_count(mut self, count: u32) -> Self {
+        self.buffer_count = count.max(1);
+        self
+    }
+
+    /// Give the device back the format it had before it was opened when the camera is stopped
+    /// or dropped (the default), so other applications find it as they left it. kamera sets
+    /// its format again on start.
+    ///
+    /// Only Linux, where the format is a setting of the device which outlives the process.
+    pub fn restore_format(mut self, restore: bool) -> Self {
+        self.restore_format = restore;
+        self
+    }
+
+    /// Which camera to open when there are several, default is
+    /// [`DefaultDevicePolicy::PreferBuiltin`].
+    pub fn default_device_policy(mut self, policy: DefaultDevicePolicy) -> Self {
+        self.device_policy = policy;
+        self
+    }
+
+    /// Retry opening and starting the device while another process streams from it, for
+    /// services which race for the camera at boot. Default is [`OpenPolicy::Immediate`].
+    ///
+    /// Only Linux, where a device can only stream to one process at a time.
+    pub fn open_policy(mut self, policy: OpenPolicy) -> Self {
+        self.open_policy = policy;
+        self
+    }
+
+    /// Capture with the `IMFCaptureEngine` (the default) or an `IMFSourceReader`, see
+    /// [`WinBackend`]. Only Windows.
+    pub fn win_backend(mut self, backend: WinBackend) -> Self {
+        self.win_backend = backend;
+        self
+    }
+
+    /// Capture with V4L2 (the default) or libcamera, see [`LinuxBackend`]. Only Linux.
+    pub fn linux_backend(mut self, backend: LinuxBackend) -> Self {
+        self.linux_backend = backend;
+        self
+    }
+
+    /// Decode compressed formats like MJPG or H264 with the decoders of `provider`, e.g.
+    /// hardware ones, before kamera's own. Add `H264` to [`CameraBuilder::pixel_formats`] to
+    /// negotiate it. A decoder sees one frame at a time, with more than one of the
+    /// [`CameraBuilder::pipeline_workers`] not always in capture order, which H264 needs.
+    ///
+    /// Only Linux, macOS and Windows decode in the OS.
+    pub fn decoder_provider(mut self, provider: impl DecoderProvider + 'static) -> Self {
+        self.decoder_provider = Some(SharedProvider(Arc::new(provider)));
+        self
+    }
+
+    /// Panics if there's no camera or it can't be opened, see [`CameraBuilder::try_build`].
+    pub fn build(self) -> Camera {
+        self.try_build().unwrap_or_else(|err| panic!("failed to open camera: {err}"))
+    }
+
+    pub fn try_build(self) -> Result<Camera, Error> {
+        Camera::from_builder(&self)
+    }
+}
@@ -0,0 +1,105 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::sync::lock;
+use crate::time::Instant;
+
+/// Intervals kept for [`Camera::cadence`](crate::Camera::cadence), a few seconds of frames.
+const WINDOW: usize = 240;
+
+/// Statistics of the intervals between the last frames, see
+/// [`Camera::cadence`](crate::Camera::cadence).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cadence {
+    /// Number of intervals the statistics are about.
+    pub intervals: usize,
+    pub mean: Duration,
+    /// 95th percentile, 5% of the intervals are longer.
+    pub p95: Duration,
+    pub max: Duration,
+    /// Frames which are estimated to be missing: an interval of about `n` times the median one
+    /// counts as `n - 1` dropped frames.
+    pub dropped: u64,
+}
+
+impl Cadence {
+    /// Frames per second from the mean interval.
+    pub fn fps(&self) -> Option<f64> {
+        (!self.mean.is_zero()).then(|| 1.0 / self.mean.as_secs_f64())
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct CadenceWindow {
+    state: Mutex<(Option<Instant>, VecDeque<Duration>)>,
+}
+
+impl CadenceWindow {
+    pub(crate) fn frame(&self, now: Instant) {
+        let mut state = lock(&self.state);
+        let (last, intervals) = &mut *state;
+        if let Some(last) = last.replace(now) {
+            if intervals.len() == WINDOW {
+                intervals.pop_front();
+            }
+            intervals.push_back(now.saturating_duration_since(last));
+        }
+    }
+
+    /// Forgets the frames so far, the interval across a pause says nothing about the stream.
+    pub(crate) fn reset(&self) {
+        *lock(&self.state) = Default::default();
+    }
+
+    pub(crate) fn snapshot(&self) -> Cadence {
+        let mut sorted: Vec<Duration> = lock(&self.state).1.iter().copied().collect();
+        if sorted.is_empty() {
+            return Cadence::default();
+        }
+        sorted.sort_unstable();
+        let n = sorted.len();
+        let median = sorted[n / 2];
+        let dropped = match median.is_zero() {
+            true => 0,
+            false => sorted
+                .iter()
+                .map(|i| (i.as_secs_f64() / median.as_secs_f64()).round() as u64)
+                .map(|frames| frames.saturating_sub(1))
+                .sum(),
+        };
+        Cadence {
+            intervals: n,
+            mean: sorted.iter().sum::<Duration>() / n as u32,
+            p95: sorted[(n * 95).div_ceil(100) - 1],
+            max: sorted[n - 1],
+            dropped,
+        }
+    }
+}
+
+#[test]
+fn cadence_statistics() {
+    let window = CadenceWindow::default();
+    assert_eq!(window.snapshot(), Cadence::default());
+    let ms = Duration::from_millis;
+    let start = Instant::now();
+    let mut t = ms(0);
+    // 40 frames at 10 ms with two missing after the 10th, and one late frame
+    for i in 0..40 {
+        t += match i {
+            10 => ms(30),
+            15 => ms(14),
+            _ => ms(10),
+        };
+        window.frame(start + t);
+    }
+    let cadence = window.snapshot();
+    assert_eq!((cadence.intervals, cadence.dropped), (39, 2));
+    assert_eq!((cadence.p95, cadence.max), (ms(14), ms(30)));
+    assert_eq!(cadence.mean, ms(10 * 37 + 30 + 14) / 39);
+    assert!(cadence.fps().is_some_and(|fps| (fps - 94.2).abs() < 0.1));
+    window.reset();
+    assert_eq!(window.snapshot().intervals, 0);
+}
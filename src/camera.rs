@@ -1,4 +1,4 @@
-#[cfg(target_os = "macos")]
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use super::mac_avf as backend;
 
 #[cfg(target_os = "windows")]
@@ -7,67 +7,1554 @@ use super::win_mf as backend;
 #[cfg(target_os = "linux")]
 use super::linux_v4l2 as backend;
 
+#[cfg(target_os = "android")]
+use super::android_ndk as backend;
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+use super::web_media as backend;
+
+use std::collections::BTreeMap;
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use crate::broadcast::Broadcast;
+use crate::cadence::CadenceWindow;
+use crate::config::RequestedConfig;
+use crate::event_bus::EventBus;
+use crate::fault::Fate;
+use crate::perf::Counters;
+use crate::power::{PowerEvent, PowerWatch};
+use crate::sync::lock;
+use crate::test_pattern::TestPattern;
+use crate::time::Instant;
+use crate::validation::FrameValidation;
+use crate::watchdog::{Liveness, Watchdog};
+use crate::{blit, convert};
+use crate::{
+    Backend, BackendCapabilities, Cadence, CameraBuilder, CameraProfile, CancelToken,
+    CaptureFormat, CaptureInfo, CaptureMetadata, ClockCalibration, ColorSpace, ConfigChanges,
+    ConfigMismatch, DepthFrame, DeviceCapabilities, DeviceDetails, Enhancement, EnumError, Error,
+    EventReceiver, FaceRect, FaultInjector, Filter, Fit, FourCC, FrameReceiver, FrameSource,
+    Latency, MetadataKind, OwnedFrame, PerfCounters, Photo, PtzAxis, PtzRange, Rect, SessionPreset,
+    ThreadPriority, VideoOrientation,
+};
+
 #[derive(Debug)]
 pub struct Camera {
-    inner: backend::Camera,
+    inner: Source,
+    counters: Arc<Counters>,
+    cadence: CadenceWindow,
+    validation: FrameValidation,
+    subscribers: Broadcast,
+    events: EventBus,
+    config: Mutex<RequestedConfig>,
+    state: Mutex<CameraState>,
+    /// Measured at the first frame and again by [`Camera::clock_calibration`].
+    clock: Mutex<Option<ClockCalibration>>,
+    /// `None` for a [`FrameSource`], which handles sleep on its own.
+    power: Option<PowerWatch>,
+    faults: Option<FaultInjector>,
+    liveness: Arc<Liveness>,
+    watchdog: Mutex<Option<Watchdog>>,
 }
 
+/// Frames are `Send` and `Sync`, so they can be handed to encoder or processing threads.
+/// On macOS and Windows a frame holds on to a capture buffer of the OS until it is dropped.
 #[derive(Debug)]
 pub struct Frame {
-    inner: backend::Frame,
+    inner: FrameInner,
+    converted: Converted,
+    faces: Vec<FaceRect>,
+    depth: Option<Arc<DepthFrame>>,
+    timestamp: Option<Duration>,
 }
 
 pub struct FrameData<'a> {
-    inner: backend::FrameData<'a>,
+    inner: FrameDataInner<'a>,
+    converted: &'a Converted,
+    size: (u32, u32),
+    fourcc: FourCC,
+}
+
+enum Source {
+    Native(backend::Camera),
+    /// With the receiver of its events and a sender for the events of the camera itself, e.g.
+    /// [`CameraEvent::Suspended`].
+    Custom(Box<dyn FrameSource>, Receiver<CameraEvent>, Sender<CameraEvent>),
+}
+
+#[derive(Debug)]
+enum FrameInner {
+    Native(backend::Frame),
+    /// Part of a native frame which shares its buffer, see [`Frame::crop`].
+    Cropped(backend::Frame, Rect),
+    Owned(OwnedFrame),
+}
+
+enum FrameDataInner<'a> {
+    Native(backend::FrameData<'a>),
+    Cropped(backend::FrameData<'a>, Rect),
+    Owned(&'a OwnedFrame),
+}
+
+/// One plane of a frame, e.g. the Y or the interleaved UV plane of NV12.
+///
+/// `width` and `height` are in samples of this plane, so chroma planes are often smaller.
+#[derive(Debug, Clone, Copy)]
+pub struct PlaneView<'a> {
+    pub data: &'a [u8],
+    pub stride: usize,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Conversions of a frame, done at most once per frame until [`Frame::clear_conversions`].
+struct Converted {
+    counters: Arc<Counters>,
+    bgra: OnceLock<Vec<u8>>,
+    rgb: OnceLock<Vec<u8>>,
+    gray: OnceLock<Vec<u8>>,
+    i420: OnceLock<Vec<u8>>,
+    nv12: OnceLock<Vec<u8>>,
+    histogram: OnceLock<[u32; 256]>,
 }
 
+/// OS handle which becomes readable (signaled on Windows) when a new frame is available.
+///
+/// This is a file descriptor on Linux, macOS and Android and can be registered with poll, epoll,
+/// kqueue or any event loop built on top of them. On Windows this is an event `HANDLE` which can
+/// be used with `WaitForMultipleObjects` and friends.
+#[cfg(unix)]
+pub type FrameReadyFd = std::os::fd::RawFd;
+
+#[cfg(windows)]
+pub type FrameReadyFd = std::os::windows::io::RawHandle;
+
+/// The browser has no such handle, [`Camera::frame_ready_fd`] is always `None` there.
+#[cfg(not(any(unix, windows)))]
+pub type FrameReadyFd = i32;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CameraDevice {
     pub id: String,
     pub name: String,
+    pub kind: DeviceKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DeviceKind {
+    /// A camera built into the computer or connected to it.
+    Physical,
+    /// A software camera like the OBS virtual camera or v4l2loopback.
+    Virtual,
+    /// A phone camera streamed over the network, like Continuity Camera on macOS.
+    Continuity,
+    Unknown,
+}
+
+/// AVFoundation device types, see [`Camera::device_list_of_types`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DeviceType {
+    /// The camera built into a Mac or its display.
+    BuiltInWideAngle,
+    /// USB and virtual cameras.
+    External,
+    /// An iPhone used as camera.
+    Continuity,
+    /// The desk view of an iPhone used as camera.
+    DeskView,
+}
+
+impl DeviceType {
+    pub const ALL: [Self; 4] =
+        [Self::BuiltInWideAngle, Self::External, Self::Continuity, Self::DeskView];
+}
+
+/// Set of [`DeviceKind`]s, combine them with `|`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DeviceKindMask(u8);
+
+impl DeviceKindMask {
+    pub const PHYSICAL: Self = Self(1 << 0);
+    pub const VIRTUAL: Self = Self(1 << 1);
+    pub const CONTINUITY: Self = Self(1 << 2);
+    pub const UNKNOWN: Self = Self(1 << 3);
+    pub const ALL: Self = Self(0b1111);
+
+    pub fn contains(self, kind: DeviceKind) -> bool {
+        self.0 & DeviceKindMask::from(kind).0 != 0
+    }
+}
+
+impl From<DeviceKind> for DeviceKindMask {
+    fn from(kind: DeviceKind) -> Self {
+        match kind {
+            DeviceKind::Physical => Self::PHYSICAL,
+            DeviceKind::Virtual => Self::VIRTUAL,
+            DeviceKind::Continuity => Self::CONTINUITY,
+            DeviceKind::Unknown => Self::UNKNOWN,
+        }
+    }
+}
+
+/// Whether a camera streams, see [`Camera::state`].
+///
+/// [`Camera::start`] moves to `Running` and [`Camera::stop`] to `Stopped` from any state. A
+/// running camera is `Suspended` while the system sleeps and `Running` again after wake, or
+/// `Stopped` if its stream couldn't be started again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CameraState {
+    #[default]
+    Stopped,
+    Running,
+    Suspended,
+}
+
+/// Asynchronous notifications from a running camera, see [`Camera::events`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CameraEvent {
+    /// The device was unplugged or otherwise stopped working.
+    DeviceLost,
+    /// The OS denied access to the device, e.g. because of privacy settings.
+    AccessDenied,
+    /// Another application is using the device and it can't be shared.
+    InUseByOtherApp,
+    /// The OS blocked the stream, e.g. because of privacy settings.
+    StreamBlocked,
+    StreamUnblocked,
+    /// The system goes to sleep, the stream was stopped. On Linux this arrives after wake,
+    /// right before [`CameraEvent::Resumed`].
+    Suspended,
+    /// The system woke up, a stream which ran before [`CameraEvent::Suspended`] was started
+    /// again.
+    Resumed,
+    /// The device delivers a pixel format which can't be converted to BGRA, e.g. MJPG on Linux
+    /// without the `mjpeg` feature.
+    /// `wait_for_frame` returns `None` for these frames.
+    UnsupportedFormat {
+        pixel_format: String,
+    },
+    /// Frame validation saw this many empty or black frames in a row and set the format of
+    /// the device again, see [`Camera::enable_frame_validation`].
+    StreamRenegotiated {
+        invalid_frames: u32,
+    },
+    /// A control changed outside of kamera, e.g. by another application or a button on the
+    /// camera, with its V4L2 control ID. Linux only.
+    ControlChanged {
+        id: u32,
+        value: i64,
+    },
+    /// The source of the device changed its resolution, e.g. the input of a HDMI capture
+    /// card, and the stream started again with it. Linux only.
+    ResolutionChanged {
+        width: u32,
+        height: u32,
+    },
+    /// The device has no more frames, e.g. a decoder at the end of its input. Linux only.
+    EndOfStream,
+    /// Any other capture error with the OS error code and message.
+    Error {
+        code: i32,
+        message: String,
+    },
+}
+
+/// The error with which an event ends [`Camera::run`].
+fn event_error(event: CameraEvent) -> Error {
+    match event {
+        CameraEvent::DeviceLost => Error::NoDevice,
+        CameraEvent::InUseByOtherApp => Error::InUseByOtherApp,
+        CameraEvent::AccessDenied => Error::Other("access to the camera was denied".into()),
+        CameraEvent::UnsupportedFormat { .. } => Error::Unsupported,
+        CameraEvent::Error { code, message } => Error::Other(format!("{message} ({code})")),
+        event => Error::Other(format!("{event:?}")),
+    }
+}
+
+impl std::ops::BitOr for DeviceKindMask {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::Not for DeviceKindMask {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self(!self.0 & Self::ALL.0)
+    }
 }
 
 impl Camera {
+    /// Panics if there's no camera or it can't be opened, see [`Camera::try_new_default_device`].
     pub fn new_default_device() -> Self {
-        Self { inner: backend::Camera::new_default_device() }
+        Self::builder().build()
+    }
+
+    pub fn try_new_default_device() -> Result<Self, Error> {
+        Self::builder().try_build()
+    }
+
+    pub fn builder() -> CameraBuilder {
+        CameraBuilder::default()
+    }
+
+    pub(crate) fn from_builder(builder: &CameraBuilder) -> Result<Self, Error> {
+        crate::clock::start();
+        let inner = Source::open(builder)?;
+        Ok(Self {
+            inner,
+            counters: Default::default(),
+            cadence: Default::default(),
+            validation: Default::default(),
+            subscribers: Default::default(),
+            events: Default::default(),
+            config: Default::default(),
+            state: Default::default(),
+            clock: Default::default(),
+            power: Some(PowerWatch::new()),
+            faults: None,
+            liveness: Default::default(),
+            watchdog: Default::default(),
+        })
+    }
+
+    /// Camera which gets its frames from `source` instead of a device.
+    pub fn from_source(source: impl FrameSource + 'static) -> Self {
+        Self::from_custom(Box::new(source))
+    }
+
+    /// Picks the backend at runtime, e.g. [`Backend::Test`] when no camera is available. Only
+    /// [`Backend::Native`] fails, like [`Camera::try_new_default_device`].
+    pub fn with_backend(backend: Backend) -> Result<Self, Error> {
+        match backend {
+            Backend::Native => Self::try_new_default_device(),
+            Backend::Test => Ok(Self::from_source(TestPattern::new(640, 480, 30.0))),
+            Backend::Custom(source) => Ok(Self::from_custom(source)),
+        }
+    }
+
+    fn from_custom(source: Box<dyn FrameSource>) -> Self {
+        Self {
+            inner: Source::custom(source),
+            counters: Default::default(),
+            cadence: Default::default(),
+            validation: Default::default(),
+            subscribers: Default::default(),
+            events: Default::default(),
+            config: Default::default(),
+            state: Default::default(),
+            clock: Default::default(),
+            power: None,
+            faults: None,
+            liveness: Default::default(),
+            watchdog: Default::default(),
+        }
     }
 
+    /// Camera which loops a YUV4MPEG2 video, see [`playback`](crate::playback).
+    #[cfg(feature = "playback")]
+    pub fn from_video_file(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        Ok(Self::from_source(crate::playback::VideoFile::open(path)?))
+    }
+
+    /// Camera which loops the images of a directory at `fps`, see
+    /// [`ImageSequence`](crate::playback::ImageSequence).
+    #[cfg(feature = "playback")]
+    pub fn from_image_sequence(
+        dir: impl AsRef<std::path::Path>,
+        fps: f64,
+    ) -> std::io::Result<Self> {
+        Ok(Self::from_source(crate::playback::ImageSequence::open(dir, fps)?))
+    }
+
+    /// Camera which receives an RTSP stream, see [`rtsp`](crate::rtsp).
+    #[cfg(feature = "rtsp")]
+    pub fn from_rtsp(url: &str) -> Result<Self, Error> {
+        Ok(Self::from_source(crate::rtsp::RtspCamera::open(url)?))
+    }
+
+    /// Camera which captures the main monitor, see [`screen`](crate::screen).
+    #[cfg(feature = "screen")]
+    pub fn from_screen() -> Result<Self, Error> {
+        Ok(Self::from_source(crate::screen::ScreenCamera::new()?))
+    }
+
+    /// Panics if the stream can't be started, see [`Camera::try_start`].
     pub fn start(&self) {
-        self.inner.start();
+        self.try_start().unwrap_or_else(|err| panic!("failed to start camera: {err}"))
+    }
+
+    pub fn try_start(&self) -> Result<(), Error> {
+        self.inner.start()?;
+        if let Some(faults) = &self.faults {
+            faults.start();
+        }
+        self.set_state(CameraState::Running);
+        Ok(())
+    }
+
+    /// Like [`Camera::start`], but reports [`Error::InUseByOtherApp`] instead of delivering no
+    /// frames when another application holds the device.
+    ///
+    /// macOS shares cameras between applications, there this only fails when the device is
+    /// marked as in use and doesn't start it then.
+    pub fn try_exclusive(&self) -> Result<(), Error> {
+        match &self.inner {
+            Source::Native(camera) => camera.try_exclusive()?,
+            Source::Custom(source, ..) => source.start(),
+        }
+        if let Some(faults) = &self.faults {
+            faults.start();
+        }
+        self.set_state(CameraState::Running);
+        Ok(())
     }
 
     pub fn stop(&self) {
         self.inner.stop();
+        self.set_state(CameraState::Stopped);
+        self.cadence.reset();
+    }
+
+    /// Stopping and starting again keeps the format, also when another application changed
+    /// the device in between.
+    pub fn state(&self) -> CameraState {
+        self.follow_power();
+        *lock(&self.state)
+    }
+
+    fn set_state(&self, state: CameraState) {
+        *lock(&self.state) = state;
+        self.liveness.set_running(state == CameraState::Running);
+        if let Some(power) = &self.power {
+            power.set_streaming(state == CameraState::Running);
+        }
+    }
+
+    /// Stops the stream before the system sleeps and starts it again after wake, many drivers
+    /// deliver no frames after wake otherwise. `true` if it started the stream again.
+    fn follow_power(&self) -> bool {
+        let Some(power) = &self.power else {
+            return false;
+        };
+        let mut restarted = false;
+        for change in power.changes() {
+            match change {
+                PowerEvent::Suspend => {
+                    if power.suspend() {
+                        self.inner.stop();
+                        *lock(&self.state) = CameraState::Suspended;
+                        self.liveness.set_running(false);
+                    }
+                    self.inner.send_event(CameraEvent::Suspended);
+                }
+                PowerEvent::Resume => {
+                    if power.resume() {
+                        let started = self.inner.start().is_ok();
+                        restarted |= started;
+                        self.set_state(if started {
+                            CameraState::Running
+                        } else {
+                            CameraState::Stopped
+                        });
+                    }
+                    self.inner.send_event(CameraEvent::Resumed);
+                }
+            }
+        }
+        restarted
     }
 
+    /// In the browser this doesn't block and is `None` until a frame arrived, the page delivers
+    /// frames only between calls into the application. On Linux it gives up with `None` after
+    /// a few seconds without frames, e.g. of a camera which stopped over system sleep.
     pub fn wait_for_frame(&self) -> Option<Frame> {
-        self.inner.wait_for_frame().map(|inner| Frame { inner })
+        self.frame_with(|camera| camera.wait_for_frame(), |source| source.wait_for_frame())
+    }
+
+    /// Returns immediately, `None` if no new frame is ready.
+    pub fn try_next_frame(&self) -> Option<Frame> {
+        self.frame_with(|camera| camera.try_next_frame(), |source| source.try_next_frame())
+    }
+
+    /// Waits for a frame like [`Camera::wait_for_frame`] but drops queued older frames and
+    /// returns only the most recent one, for render loops which care about latency.
+    pub fn latest_frame(&self) -> Option<Frame> {
+        self.frame_with(|camera| camera.latest_frame(), |source| source.latest_frame())
+    }
+
+    /// A receiver of every frame, for several consumers on their own threads. The camera can't
+    /// be shared between threads, so there's no delivery thread inside kamera: receivers only
+    /// get frames while the thread which owns the camera pumps them with [`Camera::broadcast`].
+    ///
+    /// ```no_run
+    /// let camera = kamera::Camera::new_default_device();
+    /// let frames = camera.subscribe();
+    /// std::thread::spawn(move || {
+    ///     while let Some(frame) = frames.recv() {
+    ///         println!("{:?}", frame.size_u32());
+    ///     }
+    /// });
+    /// camera.start();
+    /// camera.broadcast();
+    /// ```
+    pub fn subscribe(&self) -> FrameReceiver {
+        self.subscribers.subscribe()
+    }
+
+    /// Sends frames to the receivers of [`Camera::subscribe`] until all of them are dropped or
+    /// the camera delivers no more frames, e.g. after it was stopped. The receivers end then.
+    ///
+    /// Each receiver queues a few frames, which on macOS and Windows hold on to capture buffers
+    /// of the OS. A receiver which falls behind misses the oldest ones, also once its queue
+    /// reaches [`Camera::set_memory_limit`].
+    pub fn broadcast(&self) {
+        while self.subscribers.has_receivers() {
+            let Some(frame) = self.wait_for_frame() else { break };
+            self.subscribers.send(frame);
+        }
+        self.subscribers.close();
+    }
+
+    /// Starts the camera, calls `on_frame` with every frame until `cancel` is cancelled and
+    /// stops the camera again.
+    ///
+    /// A camera which delivers no frames for a few seconds is restarted, when that doesn't help
+    /// this fails with [`Error::Stalled`]. [`CameraEvent`]s like [`CameraEvent::DeviceLost`]
+    /// end it with the matching error and [`CameraEvent::EndOfStream`] with `Ok`, while the
+    /// stream is blocked it waits. The frames of a [`FrameSource`] are waited for on another
+    /// thread, which ends once [`FrameSource::wait_for_frame`] returns after the stop. A
+    /// source without frames stalls too.
+    ///
+    /// ```no_run
+    /// let camera = kamera::Camera::new_default_device();
+    /// let cancel = kamera::CancelToken::new();
+    /// let stop = cancel.clone();
+    /// std::thread::spawn(move || {
+    ///     std::thread::sleep(std::time::Duration::from_secs(10));
+    ///     stop.cancel();
+    /// });
+    /// camera.run(&cancel, |frame| println!("{:?}", frame.size_u32())).unwrap();
+    /// ```
+    pub fn run(&self, cancel: &CancelToken, mut on_frame: impl FnMut(Frame)) -> Result<(), Error> {
+        self.try_start()?;
+        let Source::Custom(source, ..) = &self.inner else {
+            let result = self.run_until(cancel, &mut on_frame, &|| self.try_next_frame());
+            self.stop();
+            return result;
+        };
+        // only `wait_for_frame` is sure to deliver, the receiver of its frames is polled
+        std::thread::scope(|scope| {
+            let (tx, rx) = std::sync::mpsc::sync_channel(1);
+            scope.spawn(move || while tx.send(source.wait_for_frame()).is_ok() {});
+            let next = || self.frame_with(|_| None, |_| rx.try_recv().ok().flatten());
+            let result = self.run_until(cancel, &mut on_frame, &next);
+            // fails the next send, the stop wakes up a waiting source
+            drop(rx);
+            self.stop();
+            result
+        })
+    }
+
+    fn run_until(
+        &self,
+        cancel: &CancelToken,
+        on_frame: &mut dyn FnMut(Frame),
+        next: &dyn Fn() -> Option<Frame>,
+    ) -> Result<(), Error> {
+        // the wait of cameras may block until the next frame forever, they are polled
+        const POLL_INTERVAL: Duration = Duration::from_millis(5);
+        const STALL_TIMEOUT: Duration = Duration::from_secs(3);
+        const MAX_RESTARTS: u32 = 2;
+
+        let (mut last_frame, mut restarts, mut blocked) = (Instant::now(), 0, false);
+        while !cancel.is_cancelled() {
+            for event in self.poll_events() {
+                match event {
+                    CameraEvent::StreamBlocked | CameraEvent::Suspended => blocked = true,
+                    CameraEvent::StreamUnblocked | CameraEvent::Resumed => {
+                        blocked = false;
+                        last_frame = Instant::now();
+                    }
+                    CameraEvent::StreamRenegotiated { .. }
+                    | CameraEvent::ControlChanged { .. }
+                    | CameraEvent::ResolutionChanged { .. } => {}
+                    CameraEvent::EndOfStream => return Ok(()),
+                    event => return Err(event_error(event)),
+                }
+            }
+            if let Some(frame) = next() {
+                on_frame(frame);
+                (last_frame, restarts) = (Instant::now(), 0);
+                continue;
+            }
+            if !blocked && last_frame.elapsed() > STALL_TIMEOUT {
+                if restarts == MAX_RESTARTS {
+                    return Err(Error::Stalled);
+                }
+                restarts += 1;
+                self.stop();
+                self.try_start()?;
+                last_frame = Instant::now();
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+        Ok(())
+    }
+
+    /// Takes the next frame through the faults of [`FaultInjector::wrap`].
+    fn frame_with(
+        &self,
+        native: impl Fn(&backend::Camera) -> Option<backend::Frame>,
+        custom: impl Fn(&dyn FrameSource) -> Option<OwnedFrame>,
+    ) -> Option<Frame> {
+        let frame = self.next_frame(native, custom);
+        // the events which arrived meanwhile, also after a `None` of a lost device
+        self.poll_events();
+        frame
+    }
+
+    fn next_frame(
+        &self,
+        native: impl Fn(&backend::Camera) -> Option<backend::Frame>,
+        custom: impl Fn(&dyn FrameSource) -> Option<OwnedFrame>,
+    ) -> Option<Frame> {
+        let frame = loop {
+            let Some(faults) = &self.faults else { break self.receive(&native, &custom)? };
+            if faults.disconnected() {
+                return None;
+            }
+            let frame = self.receive(&native, &custom)?;
+            match faults.next() {
+                Fate::Deliver { delay, noise } => {
+                    if let Some(delay) = delay {
+                        std::thread::sleep(delay);
+                    }
+                    break match noise {
+                        Some(noise) => frame.corrupted(noise),
+                        None => frame,
+                    };
+                }
+                Fate::Drop => {}
+                Fate::Disconnect => {
+                    match &self.inner {
+                        Source::Native(camera) => camera.send_event(CameraEvent::DeviceLost),
+                        Source::Custom(..) => faults.send_event(CameraEvent::DeviceLost),
+                    }
+                    return None;
+                }
+            }
+        };
+        self.counters.frame();
+        self.cadence.frame(Instant::now());
+        self.liveness.frame();
+        Some(frame)
+    }
+
+    fn receive(
+        &self,
+        native: impl Fn(&backend::Camera) -> Option<backend::Frame>,
+        custom: impl FnOnce(&dyn FrameSource) -> Option<OwnedFrame>,
+    ) -> Option<Frame> {
+        let (mut faces, mut depth, mut timestamp) = (Vec::new(), None, None);
+        let inner = match &self.inner {
+            Source::Native(camera) => {
+                self.follow_power();
+                let mut frame = native(camera);
+                // a wait which timed out over system sleep finds the resume only now
+                if frame.is_none() && self.follow_power() {
+                    frame = native(camera);
+                }
+                if let Some(invalid) = self.validation.count_invalid(camera.short_frames()) {
+                    camera.renegotiate(invalid);
+                }
+                let frame = frame?;
+                faces = camera.faces();
+                depth = camera.depth(&frame);
+                timestamp =
+                    frame.timestamp().and_then(|time| self.calibration()?.to_monotonic(time));
+                self.counters.conversion(frame.conversion_time());
+                let data = frame.data();
+                if let Some(invalid) =
+                    self.validation.check(data.data_u8(), frame.size_u32(), data.stride())
+                {
+                    camera.renegotiate(invalid);
+                }
+                FrameInner::Native(frame)
+            }
+            Source::Custom(source, ..) => {
+                self.follow_power();
+                FrameInner::Owned(custom(source.as_ref())?)
+            }
+        };
+        let converted = Converted::new(self.counters.clone());
+        Some(Frame { inner, converted, faces, depth, timestamp })
+    }
+
+    /// Watch for firmware quirks of some UVC cameras, which deliver empty or black frames after
+    /// resume until the format is set again. After a few of those in a row the format is set
+    /// again and [`CameraEvent::StreamRenegotiated`] is sent. Off by default, a covered lens
+    /// triggers it too. Not for a [`FrameSource`].
+    pub fn enable_frame_validation(&self, enabled: bool) {
+        self.validation.set_enabled(enabled);
+    }
+
+    /// Frame and conversion counts since the camera was created, cheap enough to keep enabled.
+    pub fn perf_counters(&self) -> PerfCounters {
+        self.counters.snapshot()
+    }
+
+    /// Intervals between the last few seconds of frames as the application receives them, to
+    /// tell USB bandwidth trouble or scheduling jitter from a steady stream. Starts over when
+    /// the camera is stopped.
+    pub fn cadence(&self) -> Cadence {
+        self.cadence.snapshot()
+    }
+
+    /// Whether the camera runs and the application received a frame in the last `within`, or
+    /// the camera started less than `within` ago. For health checks of kiosks and monitoring.
+    pub fn is_alive(&self, within: Duration) -> bool {
+        self.liveness.is_alive(within)
+    }
+
+    /// Calls `on_stall` from a thread of its own when the running camera delivered no frame to
+    /// the application for `window`, once until frames arrive again, e.g. to restart the
+    /// process or raise an alert. Replaces the previous watchdog, it ends with the camera.
+    ///
+    /// Frames count when the application takes them, an application which stops asking for
+    /// frames stalls too.
+    pub fn set_watchdog(&self, window: Duration, on_stall: impl FnMut() + Send + 'static) {
+        let watchdog = Watchdog::start(self.liveness.clone(), window, on_stall);
+        *lock(&self.watchdog) = Some(watchdog);
+    }
+
+    /// Removes the watchdog of [`Camera::set_watchdog`].
+    pub fn clear_watchdog(&self) {
+        lock(&self.watchdog).take();
+    }
+
+    /// Handle which is ready as long as [`Camera::wait_for_frame`] would return without blocking.
+    ///
+    /// The handle is owned by the camera, don't close it. It may change after [`Camera::set_device`].
+    /// `None` for a [`FrameSource`] which has no such handle and in the browser, poll
+    /// [`Camera::try_next_frame`] instead.
+    pub fn frame_ready_fd(&self) -> Option<FrameReadyFd> {
+        match &self.inner {
+            Source::Native(camera) => camera.frame_ready_fd(),
+            Source::Custom(source, ..) => source.frame_ready_fd(),
+        }
+    }
+
+    /// A receiver of errors and other events which would otherwise only show up as
+    /// `wait_for_frame` returning `None`, for several consumers on their own threads. It gets
+    /// the events which weren't handed out yet and all later ones, also of devices picked with
+    /// [`Camera::set_device`].
+    ///
+    /// Events are passed on while frames are taken and by [`Camera::poll_events`], a thread
+    /// which waits on a receiver relies on the thread which owns the camera for that.
+    ///
+    /// ```no_run
+    /// let camera = kamera::Camera::new_default_device();
+    /// let events = camera.events();
+    /// std::thread::spawn(move || loop {
+    ///     if let Some(event) = events.recv_timeout(std::time::Duration::from_secs(1)) {
+    ///         println!("{event:?}");
+    ///     }
+    /// });
+    /// camera.start();
+    /// while camera.wait_for_frame().is_some() {}
+    /// ```
+    pub fn events(&self) -> EventReceiver {
+        let receiver = self.events.subscribe();
+        self.poll_events();
+        receiver
+    }
+
+    /// Calls `on_event` with every event from now on, on the thread which passes events on, see
+    /// [`Camera::events`].
+    pub fn on_event(&self, on_event: impl FnMut(&CameraEvent) + Send + 'static) {
+        self.events.on_event(Box::new(on_event));
+    }
+
+    /// Passes the events which arrived since the last time on to the receivers of
+    /// [`Camera::events`] and the callbacks of [`Camera::on_event`] and returns them, without
+    /// waiting. For applications which don't take frames for a while, e.g. during a paused
+    /// preview.
+    pub fn poll_events(&self) -> Vec<CameraEvent> {
+        let receiver = match &self.inner {
+            Source::Native(camera) => camera.events(),
+            Source::Custom(_, events, _) => events,
+        };
+        let events: Vec<CameraEvent> = receiver.try_iter().collect();
+        for event in &events {
+            self.events.send(event);
+        }
+        events
+    }
+
+    /// Format the device delivers, before conversion to BGRA.
+    pub fn current_format(&self) -> Option<CaptureFormat> {
+        match &self.inner {
+            Source::Native(camera) => camera.current_format(),
+            Source::Custom(source, ..) => source.current_format(),
+        }
+    }
+
+    /// Measures the offset of the device clock to [`monotonic_now`](crate::monotonic_now) again,
+    /// which [`Frame::timestamp_monotonic`] uses from then on. The clocks of the OS barely
+    /// drift, a measurement every few minutes is plenty. `None` for a [`FrameSource`].
+    pub fn clock_calibration(&self) -> Option<ClockCalibration> {
+        let Source::Native(camera) = &self.inner else { return None };
+        let calibration = ClockCalibration::measure(|| camera.device_clock())?;
+        *lock(&self.clock) = Some(calibration);
+        Some(calibration)
+    }
+
+    fn calibration(&self) -> Option<ClockCalibration> {
+        let calibration = *lock(&self.clock);
+        calibration.or_else(|| self.clock_calibration())
+    }
+
+    /// Exposure, ISO and white balance the device currently uses, as far as it reports them.
+    pub fn capture_metadata(&self) -> CaptureMetadata {
+        match &self.inner {
+            Source::Native(camera) => camera.capture_metadata(),
+            Source::Custom(source, ..) => CaptureMetadata::new(source.device().name),
+        }
+    }
+
+    /// The next frame with its [`CaptureMetadata`], for still images with EXIF, see [`Photo`].
+    /// The exposure the OS attached to the frame replaces the one of the device.
+    pub fn take_photo(&self) -> Result<Photo, Error> {
+        let frame = self.wait_for_frame().ok_or(Error::Stalled)?;
+        let mut metadata = self.capture_metadata();
+        if let Some(info) = frame.capture_info() {
+            metadata.exposure_time = info.exposure_time.or(metadata.exposure_time);
+            metadata.iso = info.iso.map(|iso| iso.round() as u32).or(metadata.iso);
+            metadata.lens_aperture = info.lens_aperture.or(metadata.lens_aperture);
+        }
+        Ok(Photo { frame, metadata })
+    }
+
+    /// Whether [`Camera::set_enhancement`] can toggle `enhancement` on the current device.
+    pub fn supports_enhancement(&self, enhancement: Enhancement) -> bool {
+        match &self.inner {
+            Source::Native(camera) => camera.supports_enhancement(enhancement),
+            Source::Custom(..) => false,
+        }
+    }
+
+    /// `false` if the device doesn't support `enhancement` or refused the change.
+    pub fn set_enhancement(&self, enhancement: Enhancement, enabled: bool) -> bool {
+        match &self.inner {
+            Source::Native(camera) => camera.set_enhancement(enhancement, enabled),
+            Source::Custom(..) => false,
+        }
+    }
+
+    /// Rotates the frames, which otherwise arrive in the landscape orientation of the sensor.
+    /// Only AVFoundation rotates, on iOS pass the orientation of the interface. `false` on
+    /// other platforms and if the connection can't rotate, e.g. most cameras of a Mac.
+    pub fn set_video_orientation(&self, orientation: VideoOrientation) -> bool {
+        match &self.inner {
+            Source::Native(camera) => camera.set_video_orientation(orientation),
+            Source::Custom(..) => false,
+        }
+    }
+
+    /// The values the device accepts for `axis`, `None` if it can't move that way. Lets a UI
+    /// hide the controls a camera doesn't have.
+    pub fn ptz_range(&self, axis: PtzAxis) -> Option<PtzRange> {
+        match &self.inner {
+            Source::Native(camera) => camera.ptz_range(axis),
+            Source::Custom(..) => None,
+        }
+    }
+
+    /// The current position of `axis`.
+    pub fn ptz(&self, axis: PtzAxis) -> Option<f32> {
+        match &self.inner {
+            Source::Native(camera) => camera.ptz(axis),
+            Source::Custom(..) => None,
+        }
+    }
+
+    /// Moves `axis` to `value`, clamped to [`Camera::ptz_range`]. `false` if the device can't
+    /// move that way or refused.
+    pub fn set_ptz(&self, axis: PtzAxis, value: f32) -> bool {
+        match &self.inner {
+            Source::Native(camera) => match camera.ptz_range(axis) {
+                Some(range) => camera.set_ptz(axis, range.snap(value)),
+                None => false,
+            },
+            Source::Custom(..) => false,
+        }
+    }
+
+    pub fn zoom_range(&self) -> Option<PtzRange> {
+        self.ptz_range(PtzAxis::Zoom)
+    }
+
+    pub fn set_zoom(&self, zoom: f32) -> bool {
+        self.set_ptz(PtzAxis::Zoom, zoom)
+    }
+
+    /// Whether the device has a torch, e.g. an iPhone as Continuity Camera.
+    pub fn has_torch(&self) -> bool {
+        match &self.inner {
+            Source::Native(camera) => camera.has_torch(),
+            Source::Custom(..) => false,
+        }
+    }
+
+    /// Turns the torch on at full power or off, [`Error::Unsupported`] without a torch.
+    pub fn set_torch(&self, on: bool) -> Result<(), Error> {
+        self.set_torch_level(if on { 1.0 } else { 0.0 })
+    }
+
+    /// Sets the power of the torch from 0 for off to 1. Windows can only turn it on or off,
+    /// levels in between are [`Error::Unsupported`] there.
+    pub fn set_torch_level(&self, level: f32) -> Result<(), Error> {
+        match &self.inner {
+            Source::Native(camera) if camera.has_torch() => {
+                camera.set_torch_level(level.clamp(0.0, 1.0))
+            }
+            _ => Err(Error::Unsupported),
+        }
+    }
+
+    /// Switches to a device format of that size, `false` if the device doesn't offer it.
+    /// Frames of the old size may still arrive.
+    pub fn set_resolution(&self, width: u32, height: u32) -> bool {
+        let applied = match &self.inner {
+            Source::Native(camera) => camera.set_resolution(width, height),
+            Source::Custom(..) => false,
+        };
+        if applied {
+            let mut config = lock(&self.config);
+            (config.format, config.resolution) = (None, Some((width, height)));
+        }
+        applied
+    }
+
+    /// Switches to a session preset, which is simpler than picking a format, e.g.
+    /// [`SessionPreset::Hd1280x720`] for 720p at the frame rate the device prefers. `false` if
+    /// the device doesn't support it.
+    ///
+    /// Presets are part of AVFoundation, on the other platforms those with a
+    /// [`SessionPreset::size`] set that resolution and the others return `false`.
+    pub fn set_preset(&self, preset: SessionPreset) -> bool {
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        if let Source::Native(camera) = &self.inner {
+            let applied = camera.set_preset(preset);
+            if applied {
+                let mut config = lock(&self.config);
+                (config.format, config.resolution) = (None, None);
+            }
+            return applied;
+        }
+        preset.size().is_some_and(|(width, height)| self.set_resolution(width, height))
+    }
+
+    /// Formats of the current device, like [`describe_device`] for [`Camera::device`]. A
+    /// [`FrameSource`] only has its current format.
+    pub fn supported_formats(&self) -> Vec<CaptureFormat> {
+        match &self.inner {
+            Source::Native(_) => describe_device(&self.device()).formats,
+            Source::Custom(source, ..) => source.current_format().into_iter().collect(),
+        }
+    }
+
+    /// Switches to one of [`Camera::supported_formats`] at its highest frame rate, which
+    /// includes the high frame rate and binned low light formats of macOS devices. `false` if
+    /// the device doesn't offer it. Frames of the old format may still arrive.
+    pub fn set_format(&self, format: &CaptureFormat) -> bool {
+        let applied = match &self.inner {
+            Source::Native(camera) => camera.set_format(format),
+            Source::Custom(..) => false,
+        };
+        if applied {
+            let mut config = lock(&self.config);
+            (config.format, config.resolution) = (Some(format.clone()), None);
+        }
+        applied
+    }
+
+    /// Changes several settings at once, a running stream restarts once instead of for each
+    /// setting. Returns what the device refused, the other changes apply.
+    ///
+    /// macOS commits the changes as one session configuration, Linux sets them between one
+    /// stream off and on and Windows picks one media type for them.
+    ///
+    /// ```no_run
+    /// let camera = kamera::Camera::new_default_device();
+    /// camera.start();
+    /// camera.configure(|cfg| {
+    ///     cfg.resolution(1280, 720).fps(30.0).latency(kamera::Latency::Lowest);
+    /// });
+    /// ```
+    pub fn configure(&self, change: impl FnOnce(&mut ConfigChanges)) -> Vec<ConfigMismatch> {
+        let mut changes = ConfigChanges::default();
+        change(&mut changes);
+        let changes = changes.requested;
+        let mismatches = match &self.inner {
+            Source::Native(camera) => camera.configure(&changes),
+            Source::Custom(..) => return changes.set_size(|_| false),
+        };
+        lock(&self.config).merge(changes, &mismatches);
+        mismatches
+    }
+
+    /// Drops frames which arrive faster than `fps`, for devices which can't be set to a lower
+    /// frame rate. On Linux the dropped frames aren't converted. Zero removes the limit, not
+    /// for a [`FrameSource`].
+    pub fn set_max_fps(&self, fps: f32) {
+        if let Source::Native(camera) = &self.inner {
+            camera.set_max_fps(fps);
+            lock(&self.config).max_fps = Some(fps);
+        }
+    }
+
+    /// Tunes buffering for latency or for complete streams, a running stream may restart.
+    ///
+    /// [`Latency::Lowest`] uses two driver buffers and always returns the newest frame on
+    /// Linux, keeps a single frame and discards late ones on macOS, and turns on
+    /// `MF_LOW_LATENCY` and skips queued samples on Windows. Not for a [`FrameSource`].
+    pub fn set_latency_mode(&self, latency: Latency) {
+        if let Source::Native(camera) = &self.inner {
+            camera.set_latency_mode(latency);
+            lock(&self.config).latency = Some(latency);
+        }
+    }
+
+    /// Turns on detection of `kind` by the OS or the camera, which costs next to no CPU time.
+    /// Results show up in [`Frame::metadata`]. `false` if the device or platform can't detect
+    /// it, that is always on Linux and for a [`FrameSource`].
+    ///
+    /// On macOS faces are tracked by AVFoundation, on Windows the driver has to support face
+    /// detection.
+    pub fn enable_metadata(&self, kind: MetadataKind) -> bool {
+        match &self.inner {
+            Source::Native(camera) => camera.enable_metadata(kind),
+            Source::Custom(..) => false,
+        }
+    }
+
+    /// Turns on depth for cameras which measure it, see [`Frame::depth`]. `false` if the camera
+    /// can't, always on Windows and for a [`FrameSource`].
+    ///
+    /// On macOS and iOS this adds a depth output for TrueDepth and LiDAR cameras, depth then
+    /// arrives with the color frames. On Linux depth cameras like RealSense have a node of their
+    /// own with the `Z16 ` format, whose frames carry the depth and show it as gray.
+    pub fn enable_depth(&self) -> bool {
+        match &self.inner {
+            Source::Native(camera) => camera.enable_depth(),
+            Source::Custom(..) => false,
+        }
+    }
+
+    /// See [`FaultInjector::wrap`]. A [`FrameSource`] shares a new sender of events with the faults.
+    pub(crate) fn inject_faults(&mut self, mut faults: FaultInjector) {
+        if let Source::Custom(_, _, events) = &self.inner {
+            faults.connect_events(events.clone());
+        }
+        self.faults = Some(faults);
+    }
+
+    /// Sets the priority of the threads which deliver frames, so real-time applications can put
+    /// capture ahead of their UI and batch jobs behind it. Does nothing for a [`FrameSource`].
+    ///
+    /// On macOS and iOS this sets the QoS class of the dispatch queue of the frames, on Windows
+    /// the priority of the Media Foundation callback thread. On Linux it applies to the pipeline
+    /// threads, see [`CameraBuilder::pipeline_workers`], and without them to the thread which
+    /// reads the frames. Threads take the new priority with their next frame.
+    pub fn set_thread_priority(&self, priority: ThreadPriority) {
+        if let Source::Native(camera) = &self.inner {
+            camera.set_thread_priority(priority);
+        }
+    }
+
+    /// Limits the memory of the frames the camera queued for the application and the ones it
+    /// still holds. Frames over the limit are dropped as they arrive, so a stalled consumer
+    /// can't grow the queue without bound. The latest frame always fits while no other one is
+    /// held. No limit by default. For a [`FrameSource`] it only limits the queues of
+    /// [`Camera::broadcast`].
+    ///
+    /// macOS and iOS keep only the latest frame and aren't limited.
+    pub fn set_memory_limit(&self, bytes: usize) {
+        self.subscribers.set_memory_limit(bytes);
+        if let Source::Native(camera) = &self.inner {
+            camera.set_memory_limit(bytes);
+        }
+    }
+
+    /// Bytes of the queued and held frames which count against
+    /// [`Camera::set_memory_limit`].
+    pub fn memory_usage(&self) -> usize {
+        match &self.inner {
+            Source::Native(camera) => camera.memory_usage(),
+            Source::Custom(..) => 0,
+        }
+    }
+
+    /// The opened V4L2 device, to set controls kamera doesn't wrap. `None` for a
+    /// [`FrameSource`].
+    ///
+    /// Controls and queries only: starting, stopping or setting the format behind kamera's back
+    /// breaks the stream, and `set_*` methods block while the guard is held.
+    #[cfg(all(target_os = "linux", feature = "raw"))]
+    pub fn as_v4l2_device(&self) -> Option<std::sync::RwLockReadGuard<'_, v4l::Device>> {
+        match &self.inner {
+            Source::Native(camera) => Some(camera.raw_device()),
+            Source::Custom(..) => None,
+        }
+    }
+
+    /// The `AVCaptureSession`, for `msg_send!` calls kamera doesn't wrap. `None` for a
+    /// [`FrameSource`].
+    ///
+    /// Don't start or stop it, or add and remove inputs and outputs, kamera owns its lifecycle.
+    #[cfg(all(any(target_os = "macos", target_os = "ios"), feature = "raw"))]
+    pub fn as_avf_session(&self) -> Option<&objc2::runtime::AnyObject> {
+        match &self.inner {
+            Source::Native(camera) => Some(camera.raw_session()),
+            Source::Custom(..) => None,
+        }
+    }
+
+    /// The `AVCaptureDevice` of the session, e.g. for focus and exposure settings. Lock it with
+    /// `lockForConfiguration:` as usual, but leave `activeFormat` to kamera.
+    #[cfg(all(any(target_os = "macos", target_os = "ios"), feature = "raw"))]
+    pub fn as_avf_device(&self) -> Option<&objc2::runtime::AnyObject> {
+        match &self.inner {
+            Source::Native(camera) => Some(camera.raw_device()),
+            Source::Custom(..) => None,
+        }
+    }
+
+    /// The `IMFCaptureEngine`, e.g. for its `IMFCaptureSource`. `None` for a [`FrameSource`]
+    /// and with [`WinBackend::SourceReader`](crate::WinBackend::SourceReader).
+    ///
+    /// Don't start or stop the preview, or change the sink, kamera owns the engine's lifecycle.
+    #[cfg(all(target_os = "windows", feature = "raw"))]
+    pub fn as_mf_engine(
+        &self,
+    ) -> Option<&windows::Win32::Media::MediaFoundation::IMFCaptureEngine> {
+        match &self.inner {
+            Source::Native(camera) => camera.raw_engine(),
+            Source::Custom(..) => None,
+        }
+    }
+
+    /// The `IMFSourceReader` of [`WinBackend::SourceReader`](crate::WinBackend::SourceReader),
+    /// `None` otherwise.
+    ///
+    /// Don't read samples, flush or change the media type, kamera does.
+    #[cfg(all(target_os = "windows", feature = "raw"))]
+    pub fn as_mf_reader(&self) -> Option<&windows::Win32::Media::MediaFoundation::IMFSourceReader> {
+        match &self.inner {
+            Source::Native(camera) => camera.raw_reader(),
+            Source::Custom(..) => None,
+        }
+    }
+
+    /// The `IMFMediaSource` of the device, e.g. for `IAMCameraControl` and `IAMVideoProcAmp`
+    /// settings. Don't shut it down.
+    #[cfg(all(target_os = "windows", feature = "raw"))]
+    pub fn as_mf_source(&self) -> Option<&windows::Win32::Media::MediaFoundation::IMFMediaSource> {
+        match &self.inner {
+            Source::Native(camera) => Some(camera.raw_source()),
+            Source::Custom(..) => None,
+        }
     }
 
     pub fn device(&self) -> CameraDevice {
-        self.inner.device()
+        match &self.inner {
+            Source::Native(camera) => camera.device(),
+            Source::Custom(source, ..) => source.device(),
+        }
     }
 
+    /// A camera made [`from_source`](Camera::from_source) can't change its device.
     pub fn set_device(&mut self, device: &CameraDevice) -> bool {
-        self.inner.set_device(device)
+        self.try_set_device(device).is_ok()
+    }
+
+    /// Switches to `device` and starts it, the camera is stopped if that fails.
+    ///
+    /// The format, resolution and frame rate limit set before are set on the new device as far
+    /// as it offers them, what it doesn't is returned.
+    pub fn try_set_device(&mut self, device: &CameraDevice) -> Result<Vec<ConfigMismatch>, Error> {
+        let Source::Native(camera) = &mut self.inner else {
+            return Err(Error::Other("a frame source can't change its device".into()));
+        };
+        *lock(&self.clock) = None;
+        let result = camera.set_device(device);
+        // one of the devices only DirectShow lists, which keeps its format
+        #[cfg(all(target_os = "windows", feature = "dshow"))]
+        if let Err(Error::NoDevice) = result {
+            camera.stop();
+            let source = crate::win_dshow::DshowCamera::open(Some(device)).inspect_err(|_| {
+                self.set_state(CameraState::Stopped);
+            })?;
+            source.start();
+            self.inner = Source::custom(Box::new(source));
+            self.set_state(CameraState::Running);
+            return Ok(Vec::new());
+        }
+        if let Err(err) = result {
+            self.set_state(CameraState::Stopped);
+            return Err(err);
+        }
+        let config = lock(&self.config).clone();
+        let mut mismatches = Vec::new();
+        if let Some(requested) = config.format {
+            if !camera.set_format(&requested) {
+                let applied = camera
+                    .set_resolution(requested.width, requested.height)
+                    .then(|| camera.current_format())
+                    .flatten();
+                mismatches.push(ConfigMismatch::Format { requested, applied });
+            }
+        }
+        if let Some((width, height)) = config.resolution {
+            if !camera.set_resolution(width, height) {
+                mismatches.push(ConfigMismatch::Resolution { width, height });
+            }
+        }
+        if let Some(fps) = config.max_fps {
+            camera.set_max_fps(fps);
+        }
+        if let Some(latency) = config.latency {
+            camera.set_latency_mode(latency);
+        }
+        self.set_state(CameraState::Running);
+        Ok(mismatches)
+    }
+
+    /// The device, format, frame rate limit, latency mode and pan, tilt and zoom, to set them
+    /// again with [`Camera::apply_profile`].
+    pub fn current_profile(&self) -> CameraProfile {
+        let config = lock(&self.config).clone();
+        let device = match &self.inner {
+            Source::Native(_) => self.device().stable_id(),
+            Source::Custom(..) => String::new(),
+        };
+        let axes = [PtzAxis::Pan, PtzAxis::Tilt, PtzAxis::Zoom];
+        let controls = axes.into_iter().filter_map(|axis| Some((axis, self.ptz(axis)?))).collect();
+        CameraProfile {
+            device,
+            format: self.current_format(),
+            max_fps: config.max_fps,
+            latency: config.latency,
+            controls,
+        }
+    }
+
+    /// Sets up the camera like `profile`, first switching to its device if that is another
+    /// one, which starts the camera like [`Camera::try_set_device`]. Fails with
+    /// [`Error::NoDevice`] if the device isn't connected.
+    ///
+    /// A device which doesn't offer the format any more gets a format of the same size. What
+    /// the device refused is returned, the rest applies.
+    pub fn apply_profile(&mut self, profile: &CameraProfile) -> Result<Vec<ConfigMismatch>, Error> {
+        if matches!(self.inner, Source::Native(_))
+            && !profile.device.is_empty()
+            && profile.device != self.device().stable_id()
+        {
+            let device = Self::find_by_stable_id(&profile.device).ok_or(Error::NoDevice)?;
+            self.try_set_device(&device)?;
+        }
+        let mut mismatches = self.configure(|cfg| {
+            if let Some(format) = &profile.format {
+                cfg.format(format);
+            }
+            if let Some(fps) = profile.max_fps {
+                cfg.fps(fps);
+            }
+            if let Some(latency) = profile.latency {
+                cfg.latency(latency);
+            }
+        });
+        for mismatch in &mut mismatches {
+            if let ConfigMismatch::Format { requested, applied } = mismatch {
+                *applied = self
+                    .set_resolution(requested.width, requested.height)
+                    .then(|| self.current_format())
+                    .flatten();
+            }
+        }
+        for &(axis, value) in &profile.controls {
+            if !self.set_ptz(axis, value) {
+                mismatches.push(ConfigMismatch::Ptz { axis, value });
+            }
+        }
+        Ok(mismatches)
     }
 
     pub fn device_list() -> Vec<CameraDevice> {
         backend::Camera::device_list()
     }
+
+    /// Devices of the given types, e.g. only [`DeviceType::BuiltInWideAngle`] and
+    /// [`DeviceType::External`] to leave out iPhones. Only macOS, elsewhere all devices are listed.
+    pub fn device_list_of_types(types: &[DeviceType]) -> Vec<CameraDevice> {
+        backend::Camera::device_list_of_types(types)
+    }
+
+    pub fn find_by_stable_id(stable_id: &str) -> Option<CameraDevice> {
+        Self::device_list().into_iter().find(|device| device.stable_id() == stable_id)
+    }
+
+    /// Devices whose kind is in `mask`, e.g. `!DeviceKindMask::VIRTUAL` to hide virtual cameras.
+    pub fn device_list_filtered(mask: DeviceKindMask) -> Vec<CameraDevice> {
+        Self::device_list().into_iter().filter(|device| mask.contains(device.kind())).collect()
+    }
+}
+
+impl CameraDevice {
+    /// For [`FrameSource`]s, devices of the OS come from [`Camera::device_list`].
+    pub fn new(id: impl Into<String>, name: impl Into<String>, kind: DeviceKind) -> Self {
+        Self { id: id.into(), name: name.into(), kind }
+    }
+
+    pub fn kind(&self) -> DeviceKind {
+        self.kind
+    }
+
+    /// Identifies the device across reboots and replugging, unlike `id` on Linux which is the
+    /// `/dev/video*` path. Remember this one, see [`Camera::find_by_stable_id`].
+    ///
+    /// On Linux it is built from the USB vendor, product and serial number, identical cameras
+    /// without a serial number share it.
+    pub fn stable_id(&self) -> String {
+        backend::Camera::stable_id(self)
+    }
+
+    /// Equal for the devices of one physical camera, like the color and the infrared camera of
+    /// a Windows Hello webcam, see [`device_groups`]. The ID of the device if it is on its own.
+    ///
+    /// Linux groups the nodes of a USB device or of a MIPI camera, Windows the interfaces of a
+    /// USB device and iOS the cameras of a virtual device like the dual camera.
+    pub fn group_id(&self) -> String {
+        backend::Camera::group_id(self)
+    }
+
+    /// USB vendor and product, serial number and where the device is plugged in, as far as the
+    /// OS reports them. Default if the device is gone.
+    pub fn details(&self) -> DeviceDetails {
+        backend::Camera::device_details(self)
+    }
+
+    /// The format the OS gives the device when nothing else is asked for, e.g. to warn about a
+    /// camera which only delivers 640x480 while the user picks one. `None` if the device is gone.
+    ///
+    /// The first call opens and closes the device, later ones return that format without opening
+    /// it again.
+    /// On Linux this is the format the node has, with its current frame rate as both rates.
+    pub fn default_format(&self) -> Option<CaptureFormat> {
+        static FORMATS: Mutex<BTreeMap<(String, String), CaptureFormat>> =
+            Mutex::new(BTreeMap::new());
+        let key = (self.id.clone(), self.name.clone());
+        if let Some(format) = lock(&FORMATS).get(&key) {
+            return Some(format.clone());
+        }
+        let format = backend::Camera::default_format(self)?;
+        lock(&FORMATS).insert(key, format.clone());
+        Some(format)
+    }
+}
+
+/// Every device the OS knows, with the reason why it isn't in [`Camera::device_list`] for the
+/// ones which are not. macOS only reports devices which can be used.
+pub fn enumerate_with_errors() -> Vec<Result<CameraDevice, EnumError>> {
+    backend::Camera::enumerate_with_errors()
+}
+
+/// [`Camera::device_list`] grouped by [`CameraDevice::group_id`], in the order of the list. Pick
+/// the color camera of a physical camera from its group with [`describe_device`].
+pub fn device_groups() -> Vec<Vec<CameraDevice>> {
+    let mut groups: Vec<(String, Vec<CameraDevice>)> = vec![];
+    for device in Camera::device_list() {
+        let group_id = device.group_id();
+        match groups.iter_mut().find(|(id, _)| *id == group_id) {
+            Some((_, group)) => group.push(device),
+            None => groups.push((group_id, vec![device])),
+        }
+    }
+    groups.into_iter().map(|(_, group)| group).collect()
+}
+
+/// What the backend of the running OS can do, to check before relying on a feature instead of
+/// finding out from a failed call.
+pub fn backend_capabilities() -> BackendCapabilities {
+    backend::Camera::backend_capabilities()
+}
+
+/// Formats, resolutions and frame rates of `device`, without starting a capture session.
+///
+/// Empty if the device is gone.
+pub fn describe_device(device: &CameraDevice) -> DeviceCapabilities {
+    backend::Camera::describe_device(device)
 }
 
 impl Frame {
     pub fn data(&self) -> FrameData {
-        FrameData { inner: self.inner.data() }
+        let (inner, converted) = (self.inner.data(), &self.converted);
+        FrameData { inner, converted, size: self.size_u32(), fourcc: self.fourcc() }
     }
 
     pub fn size_u32(&self) -> (u32, u32) {
         self.inner.size_u32()
     }
+
+    pub fn color_space(&self) -> ColorSpace {
+        self.inner.color_space()
+    }
+
+    /// Pixel format of the buffer the OS delivered, before it was converted to BGRA on Linux.
+    /// Frames of a [`FrameSource`] are always BGRA.
+    pub fn fourcc(&self) -> FourCC {
+        match &self.inner {
+            FrameInner::Native(frame) | FrameInner::Cropped(frame, _) => frame.fourcc(),
+            FrameInner::Owned(_) => FourCC::BGRA,
+        }
+    }
+
+    /// Scales the frame into a `dst_width` x `dst_height` buffer of `0xAARRGGBB` pixels, the
+    /// format of softbuffer and minifb. Parts not covered by the frame turn black.
+    ///
+    /// Panics if `dst` is smaller than `dst_width * dst_height`.
+    pub fn copy_to_buffer(
+        &self,
+        dst: &mut [u32],
+        dst_width: u32,
+        dst_height: u32,
+        fit: Fit,
+        filter: Filter,
+    ) {
+        let data = self.data();
+        // a frame without a plane has no pixels and comes out black
+        let empty = PlaneView { data: &[], stride: 0, width: 0, height: 0 };
+        let PlaneView { data: src, stride, width, height } = data.plane(0).unwrap_or(empty);
+        blit::blit(src, (width, height), stride, dst, (dst_width, dst_height), fit, filter);
+    }
+
+    /// The `rect` part of the frame, clamped to the frame size, e.g. to hand a centered crop to
+    /// an encoder. On macOS and iOS the part shares the pixel buffer of this frame, elsewhere it
+    /// is copied.
+    pub fn crop(&self, rect: Rect) -> Frame {
+        let rect = rect.clamp_to(self.size_u32());
+        let shared = match &self.inner {
+            FrameInner::Native(frame) => frame.share().map(|frame| (frame, rect)),
+            FrameInner::Cropped(frame, outer) => {
+                let rect = Rect { x: outer.x + rect.x, y: outer.y + rect.y, ..rect };
+                frame.share().map(|frame| (frame, rect))
+            }
+            FrameInner::Owned(_) => None,
+        };
+        let inner = match shared {
+            Some((frame, rect)) => FrameInner::Cropped(frame, rect),
+            None => {
+                let data = self.data();
+                let (src, stride) = (data.data_u8(), data.inner.stride());
+                let row_len = rect.width as usize * 4;
+                let mut bgra = Vec::with_capacity(row_len * rect.height as usize);
+                for row in 0..rect.height as usize {
+                    bgra.extend_from_slice(&src[rect.offset(stride) + row * stride..][..row_len]);
+                }
+                let frame = OwnedFrame::new(bgra, rect.width, rect.height);
+                FrameInner::Owned(frame.with_color_space(self.color_space()))
+            }
+        };
+        let (w, h) = self.size_u32();
+        let faces = (self.faces.iter())
+            .map(|face| FaceRect {
+                x: (face.x * w as f32 - rect.x as f32) / rect.width as f32,
+                y: (face.y * h as f32 - rect.y as f32) / rect.height as f32,
+                width: face.width * w as f32 / rect.width as f32,
+                height: face.height * h as f32 / rect.height as f32,
+                ..*face
+            })
+            .filter(|f| f.x < 1.0 && f.y < 1.0 && f.x + f.width > 0.0 && f.y + f.height > 0.0)
+            .collect();
+        let converted = Converted::new(self.converted.counters.clone());
+        Frame { inner, converted, faces, depth: None, timestamp: self.timestamp }
+    }
+
+    /// A copy whose lower half is noise, see [`FaultInjector::corrupt_rate`].
+    fn corrupted(self, noise: u64) -> Frame {
+        let (w, h) = self.size_u32();
+        let mut bgra = self.data().data_bgra().to_vec();
+        crate::fault::fill_noise(&mut bgra[(h / 2) as usize * w as usize * 4..], noise);
+        let frame = OwnedFrame::new(bgra, w, h).with_color_space(self.color_space());
+        let converted = Converted::new(self.converted.counters.clone());
+        Frame { inner: FrameInner::Owned(frame), converted, ..self }
+    }
+
+    /// Frees the conversions [`FrameData`] cached, e.g. before keeping the frame around. They
+    /// run again when asked for.
+    pub fn clear_conversions(&mut self) {
+        self.converted = Converted::new(self.converted.counters.clone());
+    }
+
+    /// Copies the frame as BGRA into the shared memory segment `name` for another process,
+    /// see [`shm`](crate::shm).
+    pub fn export_shm(&self, name: &str) -> std::io::Result<crate::shm::ShmFrame> {
+        crate::shm::export(name, self.data().data_bgra(), self.size_u32())
+    }
+
+    /// The faces of the most recent detection when this frame was received, once
+    /// [`Camera::enable_metadata`] turned detection on. macOS detects faces on its own queue,
+    /// apart from the frames, so frames queued with
+    /// [`CameraBuilder::frame_queue_size`](crate::CameraBuilder::frame_queue_size) all get the
+    /// faces known when they are taken from the queue. A cropped frame has the faces which
+    /// overlap it, in its own coordinates.
+    pub fn metadata(&self) -> Vec<FaceRect> {
+        self.faces.clone()
+    }
+
+    /// The depth map which arrived with this frame, once [`Camera::enable_depth`] turned depth
+    /// on. Cropped frames have none.
+    pub fn depth(&self) -> Option<&DepthFrame> {
+        self.depth.as_deref()
+    }
+
+    /// The capture time on the clock of [`monotonic_now`](crate::monotonic_now), which all
+    /// cameras share, to match the frames of several cameras. From CMClock host time on macOS,
+    /// the QPC device timestamp on Windows and the `CLOCK_MONOTONIC` buffer time on Linux.
+    /// `None` for frames of a [`FrameSource`] and drivers without these times.
+    pub fn timestamp_monotonic(&self) -> Option<Duration> {
+        self.timestamp
+    }
+
+    /// The exposure the OS attached to this frame. Only AVFoundation attaches it, as EXIF of the
+    /// sample buffer, without a lens position. `None` on Linux and Windows and for frames of a
+    /// [`FrameSource`].
+    pub fn capture_info(&self) -> Option<CaptureInfo> {
+        match &self.inner {
+            #[cfg(any(target_os = "macos", target_os = "ios"))]
+            FrameInner::Native(frame) | FrameInner::Cropped(frame, _) => frame.capture_info(),
+            _ => None,
+        }
+    }
 }
 
 impl<'a> FrameData<'a> {
+    pub(crate) fn size_u32(&self) -> (u32, u32) {
+        self.size
+    }
+
     pub fn data_u8(&self) -> &[u8] {
         self.inner.data_u8()
     }
@@ -75,16 +1562,425 @@ impl<'a> FrameData<'a> {
     pub fn data_u32(&self) -> &[u32] {
         self.inner.data_u32()
     }
+
+    /// The samples of a grayscale, Bayer or depth format with more than 8 bits per sample, like
+    /// `Y10 `, `RG10` or `Z16 `, one per pixel without row padding and in the range of the format, e.g. up to
+    /// 1023 for 10 bits. Bayer samples aren't demosaiced.
+    ///
+    /// For `P010` of HDR capture cards the Y samples, then the interleaved UV samples of half
+    /// the width and height, all in the range 0..=1023. [`Frame::fourcc`] tells which format the
+    /// device delivered, ask for `P010` with [`CameraBuilder::pixel_formats`].
+    ///
+    /// `None` for other formats, for cropped frames and on macOS and Windows, whose backends
+    /// only deliver 8 bits.
+    pub fn data_u16(&self) -> Option<&[u16]> {
+        match &self.inner {
+            #[cfg(target_os = "linux")]
+            FrameDataInner::Native(data) => data.data_u16(),
+            _ => None,
+        }
+    }
+
+    /// Number of planes, 1 for packed formats like BGRA.
+    pub fn plane_count(&self) -> usize {
+        self.inner.plane_count()
+    }
+
+    pub fn plane(&self, index: usize) -> Option<PlaneView> {
+        self.inner.plane(index)
+    }
+
+    /// BGRA with 4 bytes per pixel and without padding at the end of rows.
+    ///
+    /// This is the native layout of all backends, the data is only copied if rows are padded.
+    pub fn data_bgra(&self) -> &[u8] {
+        let (w, h) = self.size;
+        let stride = self.inner.stride();
+        if stride == w as usize * 4 {
+            &self.inner.data_u8()[..stride * h as usize]
+        } else {
+            self.converted.get_or_init(&self.converted.bgra, || {
+                convert::bgra_packed(self.inner.data_u8(), w, h, stride)
+            })
+        }
+    }
+
+    /// RGB with 3 bytes per pixel and without row padding, converted once per frame.
+    pub fn data_rgb(&self) -> &[u8] {
+        let (w, h) = self.size;
+        let stride = self.inner.stride();
+        self.converted.get_or_init(&self.converted.rgb, || {
+            convert::bgra_to_rgb(self.inner.data_u8(), w, h, stride)
+        })
+    }
+
+    /// Number of pixels per BT.601 luma value, for exposure metering. Computed once per frame.
+    pub fn luma_histogram(&self) -> [u32; 256] {
+        let (w, h) = self.size;
+        *self.converted.histogram.get_or_init(|| {
+            convert::bgra_luma_histogram(self.inner.data_u8(), w, h, self.inner.stride())
+        })
+    }
+
+    /// Average luma from 0.0 for black to 1.0 for white, e.g. to notice a covered lens.
+    pub fn mean_brightness(&self) -> f32 {
+        let histogram = self.luma_histogram();
+        let pixels: u64 = histogram.iter().map(|&n| n as u64).sum();
+        let sum: u64 = histogram.iter().enumerate().map(|(luma, &n)| luma as u64 * n as u64).sum();
+        if pixels == 0 {
+            return 0.0;
+        }
+        sum as f32 / pixels as f32 / 255.0
+    }
+
+    /// I420 for encoders: the Y plane, then the U and V planes of half the width and height
+    /// rounded up, all without row padding. Copied from a frame the OS delivered as NV12 or a
+    /// YUV format Linux keeps, otherwise converted from BGRA as BT.601 limited range. Converted
+    /// once per frame.
+    pub fn data_i420(&self) -> &[u8] {
+        let (w, h) = self.size;
+        self.converted.get_or_init(&self.converted.i420, || match self.native_nv12() {
+            Some(nv12) => convert::nv12_to_i420(nv12, w, h),
+            None => convert::bgra_to_i420(self.inner.data_u8(), w, h, self.inner.stride()),
+        })
+    }
+
+    /// NV12 for encoders, like [`FrameData::data_i420`] with the U and V samples interleaved in
+    /// one plane.
+    pub fn data_nv12(&self) -> &[u8] {
+        let (w, h) = self.size;
+        self.converted.get_or_init(&self.converted.nv12, || {
+            self.native_nv12()
+                .unwrap_or_else(|| convert::i420_to_nv12(self.data_i420().to_vec(), w, h))
+        })
+    }
+
+    /// A copy of [`FrameData::data_i420`], for encoders which take ownership.
+    pub fn to_i420(&self) -> Vec<u8> {
+        self.data_i420().to_vec()
+    }
+
+    /// A copy of [`FrameData::data_nv12`].
+    pub fn to_nv12(&self) -> Vec<u8> {
+        self.data_nv12().to_vec()
+    }
+
+    /// I010 for AV1 and VP9 encoders, the planes of [`FrameData::data_i420`] with the 10 bit
+    /// samples of a `P010` frame. `None` for frames of other formats.
+    pub fn to_i010(&self) -> Option<Vec<u16>> {
+        let (w, h) = self.size;
+        let samples = self.data_u16()?;
+        let p010 = self.fourcc == FourCC::new(b"P010");
+        p010.then(|| convert::nv12_to_i420(samples.to_vec(), w, h))
+    }
+
+    /// The Y and UV planes without padding, if the frame has them in the sizes of NV12. On
+    /// Linux as kept while converting `YUYV`, `UYVY`, `NV12` or `P010`.
+    fn native_nv12(&self) -> Option<Vec<u8>> {
+        let (w, h) = self.size;
+        #[cfg(target_os = "linux")]
+        if let FrameDataInner::Native(data) = &self.inner {
+            if let (Some(luma), Some(chroma)) = (data.luma(), data.chroma()) {
+                return Some([luma, chroma].concat());
+            }
+        }
+        let (luma, chroma) = (self.plane(0)?, self.plane(1)?);
+        let chroma_size = (w.div_ceil(2), h.div_ceil(2));
+        if self.plane_count() != 2
+            || (luma.width, luma.height) != (w, h)
+            || (chroma.width, chroma.height) != chroma_size
+        {
+            return None;
+        }
+        let mut nv12 = Vec::with_capacity((w * h + 2 * chroma_size.0 * chroma_size.1) as usize);
+        for (plane, row_len) in [(luma, w as usize), (chroma, 2 * chroma_size.0 as usize)] {
+            for row in plane.data.chunks(plane.stride).take(plane.height as usize) {
+                nv12.extend_from_slice(&row[..row_len]);
+            }
+        }
+        Some(nv12)
+    }
+
+    /// The luma plane, the cheapest grayscale view of a frame for barcode and QR scanners or ML
+    /// models. YUV frames hand out their Y plane, without a copy for NV12 on macOS, on Linux as
+    /// kept while converting `YUYV`, `UYVY`, `NV12`, `P010` or grayscale. Other frames get
+    /// [`FrameData::data_gray`].
+    ///
+    /// The Y plane keeps the range of the camera, often 16 to 235.
+    pub fn luma(&self) -> PlaneView {
+        let (width, height) = self.size;
+        #[cfg(target_os = "linux")]
+        if let FrameDataInner::Native(data) = &self.inner {
+            if let Some(data) = data.luma() {
+                return PlaneView { data, stride: width as usize, width, height };
+            }
+        }
+        let y_plane = self.plane(0).filter(|y| (y.width, y.height) == (width, height));
+        match y_plane {
+            Some(y_plane) if self.plane_count() == 2 => y_plane,
+            _ => PlaneView { data: self.data_gray(), stride: width as usize, width, height },
+        }
+    }
+
+    /// Grayscale (BT.601 luma) with 1 byte per pixel and without row padding, converted once
+    /// per frame.
+    pub fn data_gray(&self) -> &[u8] {
+        let (w, h) = self.size;
+        let stride = self.inner.stride();
+        self.converted.get_or_init(&self.converted.gray, || {
+            convert::bgra_to_gray(self.inner.data_u8(), w, h, stride)
+        })
+    }
+}
+
+impl Source {
+    /// The native camera of the builder, or the source of a backend only some systems need.
+    fn open(builder: &CameraBuilder) -> Result<Self, Error> {
+        // sandboxes hide /dev/video*, unless the portal knows no camera and they don't
+        #[cfg(all(target_os = "linux", feature = "pipewire"))]
+        if crate::linux_pipewire::sandboxed() {
+            match crate::linux_pipewire::PipewireCamera::open() {
+                Ok(camera) => return Ok(Self::custom(Box::new(camera))),
+                Err(Error::NoDevice) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        #[cfg(target_os = "linux")]
+        if builder.linux_backend == crate::LinuxBackend::Libcamera {
+            return Self::libcamera(builder);
+        }
+        let native = backend::Camera::new_with(builder);
+        // cameras V4L2 can't drive on its own, when it finds none
+        #[cfg(all(target_os = "linux", feature = "libcamera"))]
+        if let Err(Error::NoDevice) = native {
+            return Self::libcamera(builder);
+        }
+        // cameras only DirectShow knows, when Media Foundation opens none
+        #[cfg(all(target_os = "windows", feature = "dshow"))]
+        if let Err(Error::NoDevice) = native {
+            return Ok(Self::custom(Box::new(crate::win_dshow::DshowCamera::open(None)?)));
+        }
+        Ok(Source::Native(native?))
+    }
+
+    #[cfg(all(target_os = "linux", feature = "libcamera"))]
+    fn libcamera(builder: &CameraBuilder) -> Result<Self, Error> {
+        Ok(Self::custom(Box::new(crate::linux_libcamera::LibCamera::open(builder)?)))
+    }
+
+    #[cfg(all(target_os = "linux", not(feature = "libcamera")))]
+    fn libcamera(_: &CameraBuilder) -> Result<Self, Error> {
+        Err(Error::Unsupported)
+    }
+
+    /// Connects `source` to the receiver of its events.
+    fn custom(source: Box<dyn FrameSource>) -> Self {
+        let (tx, rx) = std::sync::mpsc::channel();
+        source.connect_events(tx.clone());
+        Source::Custom(source, rx, tx)
+    }
+
+    fn start(&self) -> Result<(), Error> {
+        match self {
+            Source::Native(camera) => camera.start(),
+            Source::Custom(source, ..) => {
+                source.start();
+                Ok(())
+            }
+        }
+    }
+
+    fn stop(&self) {
+        match self {
+            Source::Native(camera) => camera.stop(),
+            Source::Custom(source, ..) => source.stop(),
+        }
+    }
+
+    fn send_event(&self, event: CameraEvent) {
+        match self {
+            Source::Native(camera) => camera.send_event(event),
+            Source::Custom(_, _, events) => {
+                let _ = events.send(event);
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Native(camera) => camera.fmt(f),
+            Source::Custom(source, ..) => write!(f, "FrameSource({:?})", source.device().name),
+        }
+    }
+}
+
+impl FrameInner {
+    fn data(&self) -> FrameDataInner {
+        match self {
+            FrameInner::Native(frame) => FrameDataInner::Native(frame.data()),
+            FrameInner::Cropped(frame, rect) => FrameDataInner::Cropped(frame.data(), *rect),
+            FrameInner::Owned(frame) => FrameDataInner::Owned(frame),
+        }
+    }
+
+    fn size_u32(&self) -> (u32, u32) {
+        match self {
+            FrameInner::Native(frame) => frame.size_u32(),
+            FrameInner::Cropped(_, rect) => (rect.width, rect.height),
+            FrameInner::Owned(frame) => frame.size_u32(),
+        }
+    }
+
+    fn color_space(&self) -> ColorSpace {
+        match self {
+            FrameInner::Native(frame) | FrameInner::Cropped(frame, _) => frame.color_space(),
+            FrameInner::Owned(frame) => frame.color_space(),
+        }
+    }
+}
+
+impl<'a> FrameDataInner<'a> {
+    fn data_u8(&self) -> &[u8] {
+        match self {
+            FrameDataInner::Native(data) => data.data_u8(),
+            FrameDataInner::Cropped(data, rect) => &data.data_u8()[rect.offset(data.stride())..],
+            FrameDataInner::Owned(frame) => frame.data(),
+        }
+    }
+
+    fn data_u32(&self) -> &[u32] {
+        match self {
+            FrameDataInner::Native(data) => data.data_u32(),
+            FrameDataInner::Cropped(data, rect) => {
+                &data.data_u32()[rect.offset(data.stride()) / 4..]
+            }
+            FrameDataInner::Owned(frame) => unsafe { frame.data().align_to().1 },
+        }
+    }
+
+    fn stride(&self) -> usize {
+        match self {
+            FrameDataInner::Native(data) | FrameDataInner::Cropped(data, _) => data.stride(),
+            FrameDataInner::Owned(frame) => frame.stride(),
+        }
+    }
+
+    fn plane_count(&self) -> usize {
+        match self {
+            FrameDataInner::Native(data) => data.plane_count(),
+            FrameDataInner::Cropped(..) | FrameDataInner::Owned(_) => 1,
+        }
+    }
+
+    fn plane(&self, index: usize) -> Option<PlaneView> {
+        match self {
+            FrameDataInner::Native(data) => data.plane(index),
+            FrameDataInner::Cropped(data, rect) => {
+                let (data, stride) = (self.data_u8(), data.stride());
+                let (width, height) = (rect.width, rect.height);
+                (index == 0).then_some(PlaneView { data, stride, width, height })
+            }
+            FrameDataInner::Owned(frame) => {
+                let (width, height) = frame.size_u32();
+                let (data, stride) = (frame.data(), frame.stride());
+                (index == 0).then_some(PlaneView { data, stride, width, height })
+            }
+        }
+    }
+}
+
+impl Converted {
+    fn new(counters: Arc<Counters>) -> Self {
+        Self {
+            counters,
+            bgra: OnceLock::new(),
+            rgb: OnceLock::new(),
+            gray: OnceLock::new(),
+            i420: OnceLock::new(),
+            nv12: OnceLock::new(),
+            histogram: OnceLock::new(),
+        }
+    }
+
+    fn get_or_init<'a>(
+        &self,
+        cell: &'a OnceLock<Vec<u8>>,
+        convert: impl FnOnce() -> Vec<u8>,
+    ) -> &'a [u8] {
+        cell.get_or_init(|| self.counters.time_conversion(convert))
+    }
+}
+
+impl std::fmt::Debug for Converted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Converted")
+            .field("bgra", &self.bgra.get().is_some())
+            .field("rgb", &self.rgb.get().is_some())
+            .field("gray", &self.gray.get().is_some())
+            .field("i420", &self.i420.get().is_some())
+            .field("nv12", &self.nv12.get().is_some())
+            .field("histogram", &self.histogram.get().is_some())
+            .finish()
+    }
 }
 
-pub(crate) trait InnerCamera: std::fmt::Debug {
+pub(crate) trait InnerCamera: std::fmt::Debug + Sized {
     type Frame;
 
-    fn new_default_device() -> Self;
-    fn start(&self);
+    fn new_with(builder: &CameraBuilder) -> Result<Self, Error>;
+    fn start(&self) -> Result<(), Error>;
+    fn try_exclusive(&self) -> Result<(), Error>;
     fn stop(&self);
     fn wait_for_frame(&self) -> Option<Self::Frame>;
+    fn try_next_frame(&self) -> Option<Self::Frame>;
+    fn latest_frame(&self) -> Option<Self::Frame>;
+    fn frame_ready_fd(&self) -> Option<FrameReadyFd>;
+    fn events(&self) -> &Receiver<CameraEvent>;
+    /// Sends an event of the facade, e.g. [`CameraEvent::Suspended`].
+    fn send_event(&self, event: CameraEvent);
+    fn current_format(&self) -> Option<CaptureFormat>;
+    /// Now on the clock of the frame timestamps.
+    fn device_clock(&self) -> Option<Duration>;
+    fn capture_metadata(&self) -> CaptureMetadata;
+    fn set_resolution(&self, width: u32, height: u32) -> bool;
+    fn set_format(&self, format: &CaptureFormat) -> bool;
+    fn renegotiate(&self, invalid_frames: u32);
+    /// The frames dropped before conversion since the last call for being empty or short.
+    fn short_frames(&self) -> u32;
+    fn set_max_fps(&self, fps: f32);
+    fn set_latency_mode(&self, latency: Latency);
+    /// Applies all of `changes` with at most one restart of the stream.
+    fn configure(&self, changes: &RequestedConfig) -> Vec<ConfigMismatch>;
+    fn enable_metadata(&self, kind: MetadataKind) -> bool;
+    /// The faces of the most recent frame.
+    fn faces(&self) -> Vec<FaceRect>;
+    fn enable_depth(&self) -> bool;
+    /// The depth map of `frame`, or the most recent one if it arrives on its own.
+    fn depth(&self, frame: &Self::Frame) -> Option<Arc<DepthFrame>>;
+    fn set_thread_priority(&self, priority: ThreadPriority);
+    fn set_memory_limit(&self, bytes: usize);
+    fn memory_usage(&self) -> usize;
+    fn supports_enhancement(&self, enhancement: Enhancement) -> bool;
+    fn set_enhancement(&self, enhancement: Enhancement, enabled: bool) -> bool;
+    fn set_video_orientation(&self, orientation: VideoOrientation) -> bool;
+    fn ptz_range(&self, axis: PtzAxis) -> Option<PtzRange>;
+    fn ptz(&self, axis: PtzAxis) -> Option<f32>;
+    /// `value` is within [`InnerCamera::ptz_range`].
+    fn set_ptz(&self, axis: PtzAxis, value: f32) -> bool;
+    fn has_torch(&self) -> bool;
+    /// `level` is between 0 for off and 1.
+    fn set_torch_level(&self, level: f32) -> Result<(), Error>;
     fn device(&self) -> CameraDevice;
-    fn set_device(&mut self, device: &CameraDevice) -> bool;
+    fn set_device(&mut self, device: &CameraDevice) -> Result<(), Error>;
     fn device_list() -> Vec<CameraDevice>;
+    fn device_list_of_types(types: &[DeviceType]) -> Vec<CameraDevice>;
+    fn enumerate_with_errors() -> Vec<Result<CameraDevice, EnumError>>;
+    fn stable_id(device: &CameraDevice) -> String;
+    fn group_id(device: &CameraDevice) -> String;
+    fn describe_device(device: &CameraDevice) -> DeviceCapabilities;
+    fn default_format(device: &CameraDevice) -> Option<CaptureFormat>;
+    fn backend_capabilities() -> BackendCapabilities;
+    fn device_details(device: &CameraDevice) -> DeviceDetails;
 }
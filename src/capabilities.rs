@@ -0,0 +1,94 @@
+/// What the capture API of the running OS offers, see
+/// [`backend_capabilities`](crate::backend_capabilities). On macOS and iOS the AVFoundation
+/// classes are looked up at runtime, older versions lack some of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BackendCapabilities {
+    /// Cameras can be listed and opened, without it [`Camera::try_new_default_device`] fails with
+    /// [`Error::Unsupported`] and the device lists are empty.
+    ///
+    /// [`Camera::try_new_default_device`]: crate::Camera::try_new_default_device
+    /// [`Error::Unsupported`]: crate::Error::Unsupported
+    pub capture: bool,
+    /// [`Camera::device_list_of_types`](crate::Camera::device_list_of_types) tells the types
+    /// apart instead of listing all devices.
+    pub device_types: bool,
+    /// [`Camera::enable_metadata`](crate::Camera::enable_metadata) can detect faces.
+    pub face_metadata: bool,
+    /// [`Camera::enable_depth`](crate::Camera::enable_depth) can deliver depth maps.
+    pub depth: bool,
+}
+
+/// Formats a device supports, see [`describe_device`](crate::describe_device).
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceCapabilities {
+    pub formats: Vec<CaptureFormat>,
+}
+
+/// One pixel format at one resolution and the frame rates the device offers for it.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CaptureFormat {
+    /// Four character code as reported by the OS, e.g. `YUYV`, `NV12` or `MJPG`.
+    pub pixel_format: String,
+    pub width: u32,
+    pub height: u32,
+    /// Frame rate range, both are 0 if the device doesn't report it.
+    pub min_fps: f64,
+    pub max_fps: f64,
+}
+
+impl DeviceCapabilities {
+    /// Largest resolution by pixel count.
+    pub fn max_resolution(&self) -> Option<(u32, u32)> {
+        self.resolutions().into_iter().max_by_key(|&(w, h)| w as u64 * h as u64)
+    }
+
+    /// Distinct resolutions over all pixel formats, sorted ascending.
+    pub fn resolutions(&self) -> Vec<(u32, u32)> {
+        let mut resolutions: Vec<_> = self.formats.iter().map(|f| (f.width, f.height)).collect();
+        resolutions.sort();
+        resolutions.dedup();
+        resolutions
+    }
+
+    /// Distinct pixel formats in the order the device reports them.
+    pub fn pixel_formats(&self) -> Vec<&str> {
+        let mut pixel_formats = Vec::<&str>::new();
+        for format in &self.formats {
+            if !pixel_formats.contains(&format.pixel_format.as_str()) {
+                pixel_formats.push(&format.pixel_format);
+            }
+        }
+        pixel_formats
+    }
+
+    pub fn max_fps(&self) -> Option<f64> {
+        self.formats.iter().map(|f| f.max_fps).reduce(f64::max)
+    }
+}
+
+#[test]
+fn device_capabilities_summary() {
+    let format = |pixel_format: &str, width, height, max_fps| CaptureFormat {
+        pixel_format: pixel_format.to_string(),
+        width,
+        height,
+        min_fps: 5.0,
+        max_fps,
+    };
+    let caps = DeviceCapabilities {
+        formats: vec![
+            format("YUYV", 1280, 720, 10.0),
+            format("YUYV", 640, 480, 30.0),
+            format("MJPG", 1920, 1080, 30.0),
+            format("MJPG", 1280, 720, 60.0),
+        ],
+    };
+    assert_eq!(caps.max_resolution(), Some((1920, 1080)));
+    assert_eq!(caps.resolutions(), vec![(640, 480), (1280, 720), (1920, 1080)]);
+    assert_eq!(caps.pixel_formats(), vec!["YUYV", "MJPG"]);
+    assert_eq!(caps.max_fps(), Some(60.0));
+    assert_eq!(DeviceCapabilities::default().max_resolution(), None);
+}
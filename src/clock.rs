@@ -0,0 +1,67 @@
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::time::Instant;
+
+static EPOCH: OnceLock<Instant> = OnceLock::new();
+
+/// The clock of [`Frame::timestamp_monotonic`](crate::Frame::timestamp_monotonic), the time
+/// since the first camera of the process was created. Frames of all cameras share it, so frames
+/// of a multi-camera rig can be matched by their timestamps.
+pub fn monotonic_now() -> Duration {
+    EPOCH.get_or_init(Instant::now).elapsed()
+}
+
+/// Starts the clock before the first frame arrives, earlier frames have no timestamp.
+pub(crate) fn start() {
+    EPOCH.get_or_init(Instant::now);
+}
+
+/// How the clock of a device relates to [`monotonic_now`], see
+/// [`Camera::clock_calibration`](crate::Camera::clock_calibration).
+///
+/// The device clock is CMClock host time on macOS, QPC on Windows and `CLOCK_MONOTONIC` on
+/// Linux.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockCalibration {
+    /// Device clock minus kamera's clock, in nanoseconds.
+    pub offset_nanos: i64,
+    /// The time between the readings of both clocks, the offset is exact up to half of it.
+    pub uncertainty: Duration,
+}
+
+impl ClockCalibration {
+    /// The closest of a few readings of both clocks, a thread which was preempted in between
+    /// gets a wider window.
+    pub(crate) fn measure(device_now: impl Fn() -> Option<Duration>) -> Option<Self> {
+        (0..5)
+            .map_while(|_| {
+                let before = monotonic_now();
+                let device = device_now()?;
+                let after = monotonic_now();
+                let middle = before + (after - before) / 2;
+                let offset_nanos = device.as_nanos() as i64 - middle.as_nanos() as i64;
+                Some(Self { offset_nanos, uncertainty: after - before })
+            })
+            .min_by_key(|calibration| calibration.uncertainty)
+    }
+
+    /// `time` of the device clock on kamera's clock, `None` before kamera's clock started.
+    pub fn to_monotonic(&self, time: Duration) -> Option<Duration> {
+        let nanos = time.as_nanos() as i64 - self.offset_nanos;
+        (nanos >= 0).then(|| Duration::from_nanos(nanos as u64))
+    }
+}
+
+#[test]
+fn clock_calibration() {
+    let start = monotonic_now();
+    let offset = Duration::from_secs(1000);
+    let calibration = ClockCalibration::measure(|| Some(monotonic_now() + offset)).unwrap();
+    let error = (calibration.offset_nanos - offset.as_nanos() as i64).unsigned_abs();
+    assert!(error <= calibration.uncertainty.as_nanos() as u64);
+    let time = calibration.to_monotonic(start + offset).unwrap();
+    assert!(time.abs_diff(start) <= calibration.uncertainty);
+    assert_eq!(calibration.to_monotonic(Duration::ZERO), None);
+    assert_eq!(ClockCalibration::measure(|| None), None);
+}
@@ -0,0 +1,54 @@
+/// Describes the colors of the camera's native stream.
+///
+/// Frame data is delivered as full range RGB, the YUV to RGB conversion uses `matrix` and
+/// `range`. Fields are `Unknown` when the platform doesn't report them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ColorSpace {
+    pub matrix: YuvMatrix,
+    pub range: ColorRange,
+    pub transfer: TransferFunction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum YuvMatrix {
+    #[default]
+    Unknown,
+    Bt601,
+    Bt709,
+    Bt2020,
+}
+
+/// Full range uses all values [0, 255], limited ("video") range uses Y [16, 235] and UV [16, 240].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorRange {
+    #[default]
+    Unknown,
+    Full,
+    Limited,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TransferFunction {
+    #[default]
+    Unknown,
+    Srgb,
+    Bt709,
+    Bt2020,
+}
+
+impl ColorSpace {
+    /// Fills unknown matrix and range with the common defaults for a YUV stream of this height:
+    /// BT.601 for SD and BT.709 for HD, both in limited range as UVC cameras deliver it.
+    pub fn or_defaults_for_height(self, height: u32) -> Self {
+        let matrix = match self.matrix {
+            YuvMatrix::Unknown if height >= 720 => YuvMatrix::Bt709,
+            YuvMatrix::Unknown => YuvMatrix::Bt601,
+            matrix => matrix,
+        };
+        let range = match self.range {
+            ColorRange::Unknown => ColorRange::Limited,
+            range => range,
+        };
+        Self { matrix, range, ..self }
+    }
+}
@@ -0,0 +1,150 @@
+//! Draw frames onto each other, e.g. the webcam as a picture-in-picture over a screen frame
+//! before handing it to an encoder.
+//!
+//! Sources are a [`FrameData`] or an [`OwnedFrame`] in BGRA, the destination is an
+//! [`OwnedFrame`]. Blending is integer math on bytes, a row at a time.
+//!
+//! ```no_run
+//! use kamera::compose::{self, Corner};
+//! use kamera::{Backend, Camera, Filter};
+//!
+//! let main = Camera::with_backend(Backend::Test).unwrap();
+//! let webcam = Camera::new_default_device();
+//! main.start();
+//! webcam.start();
+//! let (background, inset) = (main.wait_for_frame().unwrap(), webcam.wait_for_frame().unwrap());
+//! let mut canvas = compose::letterbox(&background.data(), 1920, 1080, Filter::Bilinear);
+//! compose::picture_in_picture(&mut canvas, &inset.data(), Corner::BottomRight, 0.25);
+//! ```
+
+use crate::blit::blit;
+use crate::{Filter, Fit, FrameData, OwnedFrame, PlaneView, Rect};
+
+/// Where [`picture_in_picture`] puts the inset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+impl<'a> From<&'a OwnedFrame> for PlaneView<'a> {
+    fn from(frame: &'a OwnedFrame) -> Self {
+        let (width, height) = frame.size_u32();
+        PlaneView { data: frame.data(), stride: frame.stride(), width, height }
+    }
+}
+
+/// The frame as packed BGRA, converted if the camera delivered another format.
+impl<'a, 'b: 'a> From<&'a FrameData<'b>> for PlaneView<'a> {
+    fn from(data: &'a FrameData<'b>) -> Self {
+        let (width, height) = data.size_u32();
+        PlaneView { data: data.data_bgra(), stride: width as usize * 4, width, height }
+    }
+}
+
+/// Scales `src` into `rect` of `dst` and blends it with `opacity` from 0.0, which leaves `dst`
+/// unchanged, to 1.0, which covers it. Parts of `rect` outside of `dst` are cut off.
+pub fn overlay<'a>(
+    dst: &mut OwnedFrame,
+    src: impl Into<PlaneView<'a>>,
+    rect: Rect,
+    opacity: f32,
+    filter: Filter,
+) {
+    let src = src.into();
+    let visible = rect.clamp_to(dst.size_u32());
+    if visible.width == 0 || visible.height == 0 {
+        return;
+    }
+    let mut scaled = vec![0; rect.width as usize * rect.height as usize];
+    let (src_size, dst_size) = ((src.width, src.height), (rect.width, rect.height));
+    blit(src.data, src_size, src.stride, &mut scaled, dst_size, Fit::Stretch, filter);
+
+    // 256 keeps the source as it is after the shift
+    let alpha = (opacity.clamp(0.0, 1.0) * 256.0).round() as u16;
+    let (stride, offset) = (dst.stride(), visible.offset(dst.stride()));
+    let row_len = visible.width as usize * 4;
+    let rows = scaled.chunks_exact(rect.width as usize).take(visible.height as usize);
+    for (y, src_row) in rows.enumerate() {
+        let dst_row = &mut dst.data_mut()[offset + y * stride..][..row_len];
+        for (dst_px, src_px) in dst_row.chunks_exact_mut(4).zip(src_row) {
+            for (d, s) in dst_px.iter_mut().zip(src_px.to_le_bytes()) {
+                *d = ((s as u16 * alpha + *d as u16 * (256 - alpha)) >> 8) as u8;
+            }
+        }
+    }
+}
+
+/// The rect of an inset which is `scale` times as wide as `dst_size`, keeps the aspect ratio of
+/// `src_size` and leaves a margin to the edges.
+pub fn inset_rect(dst_size: (u32, u32), src_size: (u32, u32), corner: Corner, scale: f32) -> Rect {
+    let (dst_w, dst_h) = dst_size;
+    let (src_w, src_h) = (src_size.0.max(1), src_size.1.max(1));
+    let margin = dst_w.min(dst_h) / 32;
+    let width = ((dst_w as f32 * scale.clamp(0.0, 1.0)) as u32).min(dst_w - 2 * margin);
+    let height = ((width as u64 * src_h as u64 / src_w as u64) as u32).min(dst_h - 2 * margin);
+    let (left, top) = (margin, margin);
+    let (right, bottom) = (dst_w - margin - width, dst_h - margin - height);
+    let (x, y) = match corner {
+        Corner::TopLeft => (left, top),
+        Corner::TopRight => (right, top),
+        Corner::BottomLeft => (left, bottom),
+        Corner::BottomRight => (right, bottom),
+    };
+    Rect::new(x, y, width, height)
+}
+
+/// Draws `src` opaque into a `corner` of `dst`, `scale` times as wide as `dst`.
+pub fn picture_in_picture<'a>(
+    dst: &mut OwnedFrame,
+    src: impl Into<PlaneView<'a>>,
+    corner: Corner,
+    scale: f32,
+) {
+    let src = src.into();
+    let rect = inset_rect(dst.size_u32(), (src.width, src.height), corner, scale);
+    overlay(dst, src, rect, 1.0, Filter::Bilinear);
+}
+
+/// Scales `src` to fit into a new `width` x `height` frame, with black bars where the aspect
+/// ratios differ.
+pub fn letterbox<'a>(
+    src: impl Into<PlaneView<'a>>,
+    width: u32,
+    height: u32,
+    filter: Filter,
+) -> OwnedFrame {
+    let src = src.into();
+    let mut pixels = vec![0; width as usize * height as usize];
+    let src_size = (src.width, src.height);
+    blit(src.data, src_size, src.stride, &mut pixels, (width, height), Fit::Contain, filter);
+    let bgra = pixels.into_iter().flat_map(u32::to_le_bytes).collect();
+    OwnedFrame::new(bgra, width, height)
+}
+
+#[test]
+fn compose_frames() {
+    let black = OwnedFrame::new([0, 0, 0, 255].repeat(16), 4, 4);
+    let white = OwnedFrame::new(vec![255; 4], 1, 1);
+    let pixel = |frame: &OwnedFrame, x: usize, y: usize| frame.data()[(y * 4 + x) * 4];
+
+    let mut dst = black.clone();
+    overlay(&mut dst, &white, Rect::new(1, 1, 2, 2), 1.0, Filter::Nearest);
+    assert_eq!([pixel(&dst, 0, 0), pixel(&dst, 1, 1), pixel(&dst, 2, 2)], [0, 255, 255]);
+
+    // half transparent and cut off at the edge
+    let mut dst = black.clone();
+    overlay(&mut dst, &white, Rect::new(3, 3, 2, 2), 0.5, Filter::Nearest);
+    assert_eq!([pixel(&dst, 2, 2), pixel(&dst, 3, 3)], [0, 127]);
+
+    let wide = OwnedFrame::new(vec![255; 4 * 2], 2, 1);
+    let boxed = letterbox(&wide, 4, 4, Filter::Nearest);
+    assert_eq!([pixel(&boxed, 0, 0), pixel(&boxed, 0, 1), pixel(&boxed, 3, 3)], [0, 255, 0]);
+
+    assert_eq!(inset_rect((640, 480), (4, 3), Corner::TopLeft, 0.25), Rect::new(15, 15, 160, 120));
+    let bottom_right = inset_rect((640, 480), (4, 3), Corner::BottomRight, 0.25);
+    assert_eq!(bottom_right, Rect::new(465, 345, 160, 120));
+}
@@ -0,0 +1,136 @@
+use crate::{CaptureFormat, Latency, PtzAxis};
+
+/// Configuration of the previous device which [`Camera::try_set_device`](crate::Camera::try_set_device)
+/// couldn't apply to the new one, or which the device refused in
+/// [`Camera::configure`](crate::Camera::configure).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ConfigMismatch {
+    /// The new device doesn't offer the format of [`Camera::set_format`](crate::Camera::set_format).
+    /// `applied` is the format of the same size it uses instead, `None` if it has no format of
+    /// that size and kept its default. `configure` keeps the previous format, with `None`.
+    Format { requested: CaptureFormat, applied: Option<CaptureFormat> },
+    /// The new device has no format of the size of
+    /// [`Camera::set_resolution`](crate::Camera::set_resolution) and kept its default.
+    Resolution { width: u32, height: u32 },
+    /// The device has no such axis or refused the value of
+    /// [`Camera::apply_profile`](crate::Camera::apply_profile).
+    Ptz { axis: PtzAxis, value: f32 },
+}
+
+/// What was set on a camera, to set it again after the device changed. The frame rate limit is
+/// applied by kamera and always carries over.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct RequestedConfig {
+    pub(crate) format: Option<CaptureFormat>,
+    pub(crate) resolution: Option<(u32, u32)>,
+    pub(crate) max_fps: Option<f32>,
+    pub(crate) latency: Option<Latency>,
+}
+
+/// Settings which [`Camera::configure`](crate::Camera::configure) changes together.
+///
+/// A later size replaces an earlier one, a resolution after a format picks the format of that
+/// size like [`Camera::set_resolution`](crate::Camera::set_resolution) does.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigChanges {
+    pub(crate) requested: RequestedConfig,
+}
+
+impl ConfigChanges {
+    /// See [`Camera::set_format`](crate::Camera::set_format).
+    pub fn format(&mut self, format: &CaptureFormat) -> &mut Self {
+        (self.requested.format, self.requested.resolution) = (Some(format.clone()), None);
+        self
+    }
+
+    /// See [`Camera::set_resolution`](crate::Camera::set_resolution).
+    pub fn resolution(&mut self, width: u32, height: u32) -> &mut Self {
+        self.requested.resolution = Some((width, height));
+        self
+    }
+
+    /// The frame rate limit of [`Camera::set_max_fps`](crate::Camera::set_max_fps).
+    pub fn fps(&mut self, fps: f32) -> &mut Self {
+        self.requested.max_fps = Some(fps);
+        self
+    }
+
+    /// See [`Camera::set_latency_mode`](crate::Camera::set_latency_mode).
+    pub fn latency(&mut self, latency: Latency) -> &mut Self {
+        self.requested.latency = Some(latency);
+        self
+    }
+}
+
+/// A size change of a [`RequestedConfig`] for the setters of a backend.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum SizeRequest<'a> {
+    Format(&'a CaptureFormat),
+    Resolution(u32, u32),
+}
+
+impl RequestedConfig {
+    /// Requests the format and then the resolution from `set`, what it refuses is returned.
+    pub(crate) fn set_size(&self, mut set: impl FnMut(SizeRequest) -> bool) -> Vec<ConfigMismatch> {
+        let mut mismatches = Vec::new();
+        if let Some(requested) = &self.format {
+            if !set(SizeRequest::Format(requested)) {
+                mismatches
+                    .push(ConfigMismatch::Format { requested: requested.clone(), applied: None });
+            }
+        }
+        if let Some((width, height)) = self.resolution {
+            if !set(SizeRequest::Resolution(width, height)) {
+                mismatches.push(ConfigMismatch::Resolution { width, height });
+            }
+        }
+        mismatches
+    }
+
+    /// Takes over what `changes` set and `mismatches` don't list.
+    pub(crate) fn merge(&mut self, changes: RequestedConfig, mismatches: &[ConfigMismatch]) {
+        let refused_format = mismatches.iter().any(|m| matches!(m, ConfigMismatch::Format { .. }));
+        let refused_size =
+            mismatches.iter().any(|m| matches!(m, ConfigMismatch::Resolution { .. }));
+        if let (Some(format), false) = (changes.format, refused_format) {
+            (self.format, self.resolution) = (Some(format), None);
+        }
+        if let (Some(resolution), false) = (changes.resolution, refused_size) {
+            (self.format, self.resolution) = (None, Some(resolution));
+        }
+        self.max_fps = changes.max_fps.or(self.max_fps);
+        self.latency = changes.latency.or(self.latency);
+    }
+}
+
+#[test]
+fn config_changes() {
+    let format = CaptureFormat {
+        pixel_format: "YUYV".into(),
+        width: 640,
+        height: 480,
+        min_fps: 5.0,
+        max_fps: 30.0,
+    };
+    let mut changes = ConfigChanges::default();
+    changes.resolution(1280, 720).format(&format).fps(15.0);
+    let mut requests = vec![];
+    let refused = changes.requested.set_size(|request| {
+        requests.push(format!("{request:?}"));
+        false
+    });
+    assert_eq!(requests.len(), 1);
+    assert_eq!(refused, vec![ConfigMismatch::Format { requested: format.clone(), applied: None }]);
+
+    let mut config = RequestedConfig { resolution: Some((320, 240)), ..Default::default() };
+    config.merge(changes.requested.clone(), &refused);
+    assert_eq!(
+        (config.format, config.resolution, config.max_fps),
+        (None, Some((320, 240)), Some(15.0))
+    );
+    let mut config = RequestedConfig::default();
+    changes.resolution(1280, 720);
+    config.merge(changes.requested, &[]);
+    assert_eq!((config.format, config.resolution), (None, Some((1280, 720))));
+}
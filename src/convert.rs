@@ -0,0 +1,641 @@
+//! Conversions from native camera pixel formats into BGRA, the layout all backends deliver.
+
+use crate::{ColorRange, ColorSpace, YuvMatrix};
+
+#[cfg(all(target_os = "linux", feature = "mjpeg"))]
+pub use crate::linux_v4l2::mjpeg::mjpeg_to_bgra;
+
+/// Fixed point YUV to RGB coefficients with 16 fractional bits.
+#[derive(Debug, Clone, Copy)]
+pub struct YuvToRgb {
+    y_offset: i32,
+    y: i32,
+    rv: i32,
+    gu: i32,
+    gv: i32,
+    bu: i32,
+}
+
+impl YuvToRgb {
+    pub fn new(color_space: ColorSpace, height: u32) -> Self {
+        let color_space = color_space.or_defaults_for_height(height);
+        let (kr, kb) = match color_space.matrix {
+            YuvMatrix::Bt709 => (0.2126, 0.0722),
+            YuvMatrix::Bt2020 => (0.2627, 0.0593),
+            YuvMatrix::Bt601 | YuvMatrix::Unknown => (0.299, 0.114),
+        };
+        let kg = 1.0 - kr - kb;
+        let (y_offset, y_scale, c_scale) = match color_space.range {
+            ColorRange::Limited | ColorRange::Unknown => (16, 255.0 / 219.0, 255.0 / 224.0),
+            ColorRange::Full => (0, 1.0, 1.0),
+        };
+        let fixed = |v: f64| (v * 65536.0).round() as i32;
+        Self {
+            y_offset,
+            y: fixed(y_scale),
+            rv: fixed(2.0 * (1.0 - kr) * c_scale),
+            gu: fixed(2.0 * (1.0 - kb) * kb / kg * c_scale),
+            gv: fixed(2.0 * (1.0 - kr) * kr / kg * c_scale),
+            bu: fixed(2.0 * (1.0 - kb) * c_scale),
+        }
+    }
+
+    #[inline]
+    pub fn bgra(&self, y: u8, u: u8, v: u8) -> [u8; 4] {
+        const HALF: i32 = 1 << 15;
+        let y = (y as i32 - self.y_offset) * self.y + HALF;
+        let u = u as i32 - 128;
+        let v = v as i32 - 128;
+        let r = (y + self.rv * v) >> 16;
+        let g = (y - self.gu * u - self.gv * v) >> 16;
+        let b = (y + self.bu * u) >> 16;
+        [b.clamp(0, 255) as u8, g.clamp(0, 255) as u8, r.clamp(0, 255) as u8, 255]
+    }
+}
+
+// The conversions to BGRA write into `bgra`, which is cleared first, so its allocation can be
+// reused from frame to frame. `stride` is the number of bytes per row of `buf`, drivers often
+// pad rows beyond the width.
+
+pub fn yuyv_to_bgra(
+    buf: &[u8],
+    w: u32,
+    h: u32,
+    stride: usize,
+    color_space: ColorSpace,
+    bgra: &mut Vec<u8>,
+) {
+    packed_422_to_bgra(buf, w, h, stride, color_space, [0, 1, 2, 3], bgra)
+}
+
+pub fn uyvy_to_bgra(
+    buf: &[u8],
+    w: u32,
+    h: u32,
+    stride: usize,
+    color_space: ColorSpace,
+    bgra: &mut Vec<u8>,
+) {
+    packed_422_to_bgra(buf, w, h, stride, color_space, [1, 0, 3, 2], bgra)
+}
+
+/// Two pixels in four bytes, `order` is the position of Y0, U, Y1 and V. With an odd width the
+/// last pixel has only its Y and shares the chroma of the pair before it.
+fn packed_422_to_bgra(
+    buf: &[u8],
+    w: u32,
+    h: u32,
+    stride: usize,
+    color_space: ColorSpace,
+    order: [usize; 4],
+    bgra: &mut Vec<u8>,
+) {
+    let yuv = YuvToRgb::new(color_space, h);
+    let [y0, u, y1, v] = order;
+    bgra.clear();
+    bgra.reserve(w as usize * h as usize * 4);
+    for row in buf.chunks(stride).take(h as usize) {
+        let row = &row[..w as usize * 2];
+        for px in row.chunks_exact(4) {
+            bgra.extend_from_slice(&yuv.bgra(px[y0], px[u], px[v]));
+            bgra.extend_from_slice(&yuv.bgra(px[y1], px[u], px[v]));
+        }
+        if w % 2 == 1 {
+            let last = row.len() - 2;
+            // a single pixel row has no pair, its missing sample is neutral
+            let pair = last.checked_sub(4).unwrap_or(last);
+            let chroma = |i| row.get(pair + i).copied().unwrap_or(128);
+            bgra.extend_from_slice(&yuv.bgra(row[last + y0], chroma(u), chroma(v)));
+        }
+    }
+}
+
+/// Full resolution Y plane followed by interleaved U and V at half resolution, both planes with
+/// rows of `stride` bytes.
+pub fn nv12_to_bgra(
+    buf: &[u8],
+    w: u32,
+    h: u32,
+    stride: usize,
+    color_space: ColorSpace,
+    bgra: &mut Vec<u8>,
+) {
+    let yuv = YuvToRgb::new(color_space, h);
+    let (w, h) = (w as usize, h as usize);
+    let (luma, chroma) = buf.split_at(stride * h);
+    bgra.clear();
+    bgra.reserve(w * h * 4);
+    for (row, luma) in luma.chunks(stride).enumerate() {
+        let chroma = &chroma[row / 2 * stride..];
+        for (col, &y) in luma[..w].iter().enumerate() {
+            let uv = &chroma[col / 2 * 2..];
+            bgra.extend_from_slice(&yuv.bgra(y, uv[0], uv[1]));
+        }
+    }
+}
+
+pub fn gray_to_bgra(buf: &[u8], w: u32, h: u32, stride: usize, bgra: &mut Vec<u8>) {
+    bgra.clear();
+    bgra.reserve(w as usize * h as usize * 4);
+    for row in buf.chunks(stride).take(h as usize) {
+        for &y in &row[..w as usize] {
+            bgra.extend_from_slice(&[y, y, y, 255]);
+        }
+    }
+}
+
+/// The color filter of a raw sensor, named after the top left 2x2 cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BayerPattern {
+    Rggb,
+    Bggr,
+    Grbg,
+    Gbrg,
+}
+
+impl BayerPattern {
+    /// Position of the red sample in the cell, blue is diagonal to it and green on the others.
+    fn red(self) -> (usize, usize) {
+        match self {
+            BayerPattern::Rggb => (0, 0),
+            BayerPattern::Bggr => (1, 1),
+            BayerPattern::Grbg => (1, 0),
+            BayerPattern::Gbrg => (0, 1),
+        }
+    }
+}
+
+/// Demosaics 8-bit Bayer samples by giving every pixel of a 2x2 cell the cell's red, blue and
+/// mean green. Half the color resolution of interpolating, but cheap and without artifacts at
+/// edges. An odd last row or column takes the color of the cell before it.
+pub fn bayer_to_bgra(
+    buf: &[u8],
+    w: u32,
+    h: u32,
+    stride: usize,
+    pattern: BayerPattern,
+    bgra: &mut Vec<u8>,
+) {
+    if w < 2 || h < 2 {
+        return gray_to_bgra(buf, w, h, stride, bgra);
+    }
+    let (w, h) = (w as usize, h as usize);
+    let (rx, ry) = pattern.red();
+    bgra.clear();
+    bgra.reserve(w * h * 4);
+    for y in 0..h {
+        let cell_y = (y & !1).min((h - 2) & !1);
+        let rows = [&buf[cell_y * stride..], &buf[(cell_y + 1) * stride..]];
+        for x in 0..w {
+            let cell_x = (x & !1).min((w - 2) & !1);
+            let at = |dx: usize, dy: usize| rows[dy][cell_x + dx];
+            let green = (at(1 - rx, ry) as u16 + at(rx, 1 - ry) as u16) / 2;
+            bgra.extend_from_slice(&[at(1 - rx, 1 - ry), green as u8, at(rx, ry), 255]);
+        }
+    }
+}
+
+/// Little endian 16-bit samples, as high bit depth formats like `Y10 ` store them, without the
+/// row padding.
+pub fn unpack_u16(buf: &[u8], w: u32, h: u32, stride: usize) -> Vec<u16> {
+    let mut samples = Vec::with_capacity(w as usize * h as usize);
+    for row in buf.chunks(stride).take(h as usize) {
+        let row = row[..w as usize * 2].chunks_exact(2);
+        samples.extend(row.map(|s| u16::from_le_bytes([s[0], s[1]])));
+    }
+    samples
+}
+
+/// The Y and interleaved UV samples of P010, which keeps 10 bits in the top of 16, without row
+/// padding and shifted down to 0..=1023.
+pub fn unpack_p010(buf: &[u8], w: u32, h: u32, stride: usize) -> Vec<u16> {
+    let chroma_width = w.div_ceil(2) * 2;
+    let (luma, chroma) = buf.split_at((stride * h as usize).min(buf.len()));
+    let mut samples = unpack_u16(luma, w, h, stride);
+    samples.extend(unpack_u16(chroma, chroma_width, h.div_ceil(2), stride));
+    samples.iter_mut().for_each(|s| *s >>= 6);
+    samples
+}
+
+/// Every `step`th byte of the rows from `offset` on without row padding, e.g. the Y plane of
+/// `YUYV` with a step of 2.
+pub fn luma_plane(
+    buf: &[u8],
+    w: u32,
+    h: u32,
+    stride: usize,
+    offset: usize,
+    step: usize,
+) -> Vec<u8> {
+    let mut luma = Vec::with_capacity(w as usize * h as usize);
+    for row in buf.chunks(stride).take(h as usize) {
+        luma.extend(row[offset..].iter().step_by(step).take(w as usize));
+    }
+    luma
+}
+
+/// The interleaved UV plane of NV12 from packed 4:2:2 with U at `offset` and V two bytes
+/// later, each sample the average of two rows. An odd last pixel repeats the pair before it.
+pub fn packed_422_chroma(buf: &[u8], w: u32, h: u32, stride: usize, offset: usize) -> Vec<u8> {
+    let (pairs, chroma_w) = (w as usize / 2, w.div_ceil(2) as usize);
+    let rows: Vec<&[u8]> = buf.chunks(stride).take(h as usize).collect();
+    let mut uv = Vec::with_capacity(chroma_w * 2 * rows.len().div_ceil(2));
+    for two_rows in rows.chunks(2) {
+        let (top, bottom) = (two_rows[0], two_rows[two_rows.len() - 1]);
+        for pair in (0..pairs).chain((chroma_w > pairs).then(|| pairs.saturating_sub(1))) {
+            for i in [pair * 4 + offset, pair * 4 + offset + 2] {
+                uv.push((top[i] as u16 + bottom[i] as u16).div_ceil(2) as u8);
+            }
+        }
+    }
+    uv
+}
+
+/// The top 8 of `bits` bits, saturating for samples which have more bits set than they should.
+pub fn narrow_u16(samples: &[u16], bits: u32) -> Vec<u8> {
+    let shift = bits.saturating_sub(8);
+    samples.iter().map(|&s| (s >> shift).min(255) as u8).collect()
+}
+
+pub fn rgb24_to_bgra(buf: &[u8], w: u32, h: u32, stride: usize, bgra: &mut Vec<u8>) {
+    bgra.clear();
+    bgra.reserve(w as usize * h as usize * 4);
+    for row in buf.chunks(stride).take(h as usize) {
+        for rgb in row[..w as usize * 3].chunks_exact(3) {
+            bgra.extend_from_slice(&[rgb[2], rgb[1], rgb[0], 255]);
+        }
+    }
+}
+
+/// Interleaves the rows of two fields stored one after the other, as with
+/// `V4L2_FIELD_SEQ_TB`, into a frame. `planes` are the heights of the planes in rows of
+/// `stride` bytes, each plane holds both fields.
+pub fn weave_fields(
+    buf: &[u8],
+    stride: usize,
+    planes: &[usize],
+    bottom_first: bool,
+    woven: &mut Vec<u8>,
+) {
+    woven.clear();
+    let mut plane_start = 0;
+    for &rows in planes {
+        let plane = &buf[plane_start..plane_start + rows * stride];
+        let first = if bottom_first { rows / 2 } else { rows.div_ceil(2) };
+        for row in 0..rows {
+            // top field rows are the even ones
+            let in_first = (row % 2 == 0) != bottom_first;
+            let field_row = if in_first { row / 2 } else { first + row / 2 };
+            woven.extend_from_slice(&plane[field_row * stride..][..stride]);
+        }
+        plane_start += rows * stride;
+    }
+}
+
+/// Removes the padding at the end of each row.
+pub fn bgra_packed(bgra: &[u8], w: u32, h: u32, stride: usize) -> Vec<u8> {
+    let row_len = w as usize * 4;
+    let mut packed = Vec::with_capacity(row_len * h as usize);
+    for row in bgra.chunks(stride).take(h as usize) {
+        packed.extend_from_slice(&row[..row_len]);
+    }
+    packed
+}
+
+pub fn bgra_to_rgb(bgra: &[u8], w: u32, h: u32, stride: usize) -> Vec<u8> {
+    let mut rgb = Vec::with_capacity(w as usize * h as usize * 3);
+    for row in bgra.chunks(stride).take(h as usize) {
+        for px in row[..w as usize * 4].chunks_exact(4) {
+            rgb.extend_from_slice(&[px[2], px[1], px[0]]);
+        }
+    }
+    rgb
+}
+
+/// BT.601 luma with weights scaled to 256.
+pub fn bgra_to_gray(bgra: &[u8], w: u32, h: u32, stride: usize) -> Vec<u8> {
+    let mut gray = Vec::with_capacity(w as usize * h as usize);
+    for row in bgra.chunks(stride).take(h as usize) {
+        for px in row[..w as usize * 4].chunks_exact(4) {
+            let luma = 29 * px[0] as u32 + 150 * px[1] as u32 + 77 * px[2] as u32;
+            gray.push(((luma + 128) >> 8) as u8);
+        }
+    }
+    gray
+}
+
+/// Number of pixels per BT.601 luma value, with the weights of `bgra_to_gray`.
+///
+/// Luma is computed a row at a time in a flat loop, the counts go to four histograms in turn so
+/// runs of equal values don't wait on each other's increments.
+pub fn bgra_luma_histogram(bgra: &[u8], w: u32, h: u32, stride: usize) -> [u32; 256] {
+    let mut partial = [[0u32; 256]; 4];
+    let mut luma = vec![0u8; w as usize];
+    for row in bgra.chunks(stride).take(h as usize) {
+        for (l, px) in luma.iter_mut().zip(row[..w as usize * 4].chunks_exact(4)) {
+            *l = ((29 * px[0] as u32 + 150 * px[1] as u32 + 77 * px[2] as u32 + 128) >> 8) as u8;
+        }
+        let mut quads = luma.chunks_exact(4);
+        for quad in &mut quads {
+            for (histogram, &l) in partial.iter_mut().zip(quad) {
+                histogram[l as usize] += 1;
+            }
+        }
+        for &l in quads.remainder() {
+            partial[0][l as usize] += 1;
+        }
+    }
+    std::array::from_fn(|i| partial.iter().map(|histogram| histogram[i]).sum())
+}
+
+/// Planar YUV with chroma planes subsampled by `1 << shift` horizontally and vertically,
+/// `(1, 1)` for 4:2:0 and `(0, 0)` for 4:4:4.
+#[cfg_attr(not(feature = "playback"), allow(unused))]
+pub fn planar_yuv_to_bgra(
+    [y, u, v]: [&[u8]; 3],
+    w: u32,
+    h: u32,
+    (shift_x, shift_y): (u32, u32),
+    color_space: ColorSpace,
+    bgra: &mut Vec<u8>,
+) {
+    let yuv = YuvToRgb::new(color_space, h);
+    let (w, h) = (w as usize, h as usize);
+    let chroma_w = w.div_ceil(1 << shift_x);
+    bgra.clear();
+    bgra.reserve(w * h * 4);
+    for row in 0..h {
+        let chroma_row = (row >> shift_y) * chroma_w;
+        for col in 0..w {
+            let c = chroma_row + (col >> shift_x);
+            bgra.extend_from_slice(&yuv.bgra(y[row * w + col], u[c], v[c]));
+        }
+    }
+}
+
+/// Planar BT.601 limited range Y, U and V planes, each `w * h` bytes, from packed BGRA.
+#[cfg_attr(not(feature = "record"), allow(unused))]
+pub fn bgra_to_yuv444(bgra: &[u8], w: u32, h: u32) -> [Vec<u8>; 3] {
+    let pixels = w as usize * h as usize;
+    let mut planes = [(); 3].map(|_| Vec::with_capacity(pixels));
+    for px in bgra[..pixels * 4].chunks_exact(4) {
+        let (b, g, r) = (px[0] as i32, px[1] as i32, px[2] as i32);
+        planes[0].push((((66 * r + 129 * g + 25 * b + 128) >> 8) + 16) as u8);
+        planes[1].push((((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128) as u8);
+        planes[2].push((((112 * r - 94 * g - 18 * b + 128) >> 8) + 128) as u8);
+    }
+    planes
+}
+
+/// BT.601 limited range I420 from BGRA, the Y plane followed by the U and V planes of half the
+/// width and height rounded up. A chroma sample is the average of its 2x2 pixels.
+pub fn bgra_to_i420(bgra: &[u8], w: u32, h: u32, stride: usize) -> Vec<u8> {
+    let (w, h) = (w as usize, h as usize);
+    let (chroma_w, chroma_h) = (w.div_ceil(2), h.div_ceil(2));
+    let mut i420 = vec![0; w * h + 2 * chroma_w * chroma_h];
+    let (y, chroma) = i420.split_at_mut(w * h);
+    let (u, v) = chroma.split_at_mut(chroma_w * chroma_h);
+    for (y_row, row) in y.chunks_exact_mut(w).zip(bgra.chunks(stride)) {
+        for (y, px) in y_row.iter_mut().zip(row[..w * 4].chunks_exact(4)) {
+            let (b, g, r) = (px[0] as i32, px[1] as i32, px[2] as i32);
+            *y = (((66 * r + 129 * g + 25 * b + 128) >> 8) + 16) as u8;
+        }
+    }
+    for chroma_row in 0..chroma_h {
+        for chroma_col in 0..chroma_w {
+            let (mut sum, mut n) = ([0; 3], 0);
+            for row in chroma_row * 2..(chroma_row * 2 + 2).min(h) {
+                for col in chroma_col * 2..(chroma_col * 2 + 2).min(w) {
+                    let px = &bgra[row * stride + col * 4..][..3];
+                    sum.iter_mut().zip(px).for_each(|(sum, &c)| *sum += c as i32);
+                    n += 1;
+                }
+            }
+            let [b, g, r] = sum.map(|sum| sum / n);
+            let i = chroma_row * chroma_w + chroma_col;
+            u[i] = (((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128) as u8;
+            v[i] = (((112 * r - 94 * g - 18 * b + 128) >> 8) + 128) as u8;
+        }
+    }
+    i420
+}
+
+/// Interleaves the U and V planes of I420 into the UV plane of NV12, in place.
+pub fn i420_to_nv12(mut yuv: Vec<u8>, w: u32, h: u32) -> Vec<u8> {
+    let luma = w as usize * h as usize;
+    let (u, v) = yuv[luma..].split_at(yuv[luma..].len() / 2);
+    let uv: Vec<u8> = u.iter().zip(v).flat_map(|(&u, &v)| [u, v]).collect();
+    yuv[luma..].copy_from_slice(&uv);
+    yuv
+}
+
+/// Splits the UV plane of NV12 into the U and V planes of I420, in place. Turns P010 samples
+/// into I010 as well.
+pub fn nv12_to_i420<T: Copy>(mut yuv: Vec<T>, w: u32, h: u32) -> Vec<T> {
+    let luma = w as usize * h as usize;
+    let uv = &yuv[luma..];
+    let planar: Vec<T> =
+        (uv.iter().step_by(2)).chain(uv.iter().skip(1).step_by(2)).copied().collect();
+    yuv[luma..].copy_from_slice(&planar);
+    yuv
+}
+
+#[test]
+fn yuv_to_rgb_limited_range_extremes() {
+    let yuv = YuvToRgb::new(
+        ColorSpace { matrix: YuvMatrix::Bt601, range: ColorRange::Limited, ..Default::default() },
+        480,
+    );
+    assert_eq!([0, 0, 0, 255], yuv.bgra(16, 128, 128));
+    assert_eq!([255, 255, 255, 255], yuv.bgra(235, 128, 128));
+}
+
+#[test]
+fn yuv_to_rgb_matrix_changes_colors() {
+    let full = |matrix| ColorSpace { matrix, range: ColorRange::Full, ..Default::default() };
+    let bt601 = YuvToRgb::new(full(YuvMatrix::Bt601), 480).bgra(81, 90, 240);
+    let bt709 = YuvToRgb::new(full(YuvMatrix::Bt709), 480).bgra(81, 90, 240);
+    assert_ne!(bt601, bt709);
+    // BT.601 full range encoding of pure red is roughly (76, 85, 255)
+    let red = YuvToRgb::new(full(YuvMatrix::Bt601), 480).bgra(76, 85, 255);
+    assert!(red[2] >= 253 && red[1] <= 2 && red[0] <= 2, "{red:?}");
+}
+
+#[test]
+fn yuyv_to_bgra_size() {
+    let buf = [16, 128, 235, 128, 16, 128, 235, 128];
+    let mut bgra = vec![1; 64];
+    yuyv_to_bgra(&buf, 2, 2, 4, ColorSpace::default(), &mut bgra);
+    assert_eq!(16, bgra.len());
+    assert_eq!([0, 0, 0, 255, 255, 255, 255, 255], bgra[0..8]);
+}
+
+#[test]
+fn packed_422_odd_width() {
+    let cs = ColorSpace::default();
+    // 3x1 pixels, the last one takes the chroma of the first two
+    let yuyv = [16, 100, 235, 150, 126, 100];
+    let uyvy = [100, 16, 150, 235, 100, 126];
+    let (mut bgra, mut other) = (Vec::new(), Vec::new());
+    yuyv_to_bgra(&yuyv, 3, 1, 6, cs, &mut bgra);
+    assert_eq!(bgra.len(), 3 * 4);
+    uyvy_to_bgra(&uyvy, 3, 1, 6, cs, &mut other);
+    assert_eq!(bgra, other);
+    let yuv = YuvToRgb::new(cs, 1);
+    assert_eq!(bgra[8..], yuv.bgra(126, 100, 150));
+}
+
+#[test]
+fn packed_and_planar_yuv_agree() {
+    let cs = ColorSpace::default();
+    // 2x2 pixels, top row black and white, bottom row gray, one chroma sample per 2x2 block
+    let yuyv = [16, 100, 235, 150, 126, 100, 126, 150];
+    let uyvy = [100, 16, 150, 235, 100, 126, 150, 126];
+    let nv12 = [16, 235, 126, 126, 100, 150];
+    let (mut bgra, mut other) = (Vec::new(), Vec::new());
+    yuyv_to_bgra(&yuyv, 2, 2, 4, cs, &mut bgra);
+    uyvy_to_bgra(&uyvy, 2, 2, 4, cs, &mut other);
+    assert_eq!(bgra, other);
+    nv12_to_bgra(&nv12, 2, 2, 2, cs, &mut other);
+    assert_eq!(bgra, other);
+    planar_yuv_to_bgra([&nv12[..4], &[100], &[150]], 2, 2, (1, 1), cs, &mut other);
+    assert_eq!(bgra, other);
+    gray_to_bgra(&[7, 8], 2, 1, 2, &mut other);
+    assert_eq!(other, [7, 7, 7, 255, 8, 8, 8, 255]);
+}
+
+#[test]
+fn luma_histogram_matches_gray() {
+    // 5x2 pixels, rows padded to 24 bytes, so the last pixel of a row is counted separately
+    let bgra = (0..48).map(|i| (i * 37 % 256) as u8).collect::<Vec<_>>();
+    let histogram = bgra_luma_histogram(&bgra, 5, 2, 24);
+    let mut expected = [0; 256];
+    for luma in bgra_to_gray(&bgra, 5, 2, 24) {
+        expected[luma as usize] += 1;
+    }
+    assert_eq!(histogram, expected);
+    assert_eq!(histogram.iter().sum::<u32>(), 10);
+}
+
+#[test]
+fn bgra_with_padding() {
+    // 2x2 pixels, rows padded to 12 bytes
+    let bgra = [
+        1, 2, 3, 255, 4, 5, 6, 255, 0, 0, 0, 0, //
+        7, 8, 9, 255, 255, 255, 255, 255, 0, 0, 0, 0,
+    ];
+    assert_eq!(bgra_packed(&bgra, 2, 2, 12), [&bgra[0..8], &bgra[12..20]].concat());
+    assert_eq!(bgra_to_rgb(&bgra, 2, 2, 12), [3, 2, 1, 6, 5, 4, 9, 8, 7, 255, 255, 255]);
+    assert_eq!(bgra_to_gray(&bgra, 2, 2, 12)[3], 255);
+    let mut converted = Vec::new();
+    rgb24_to_bgra(&[3, 2, 1], 1, 1, 3, &mut converted);
+    assert_eq!(converted, [1, 2, 3, 255]);
+}
+
+#[test]
+fn bgra_to_yuv444_round_trip() {
+    let [y, u, v] = bgra_to_yuv444(&[0, 0, 0, 255, 255, 255, 255, 255, 0, 0, 255, 255], 3, 1);
+    assert_eq!((y[0], u[0], v[0]), (16, 128, 128));
+    assert_eq!((y[1], u[1], v[1]), (235, 128, 128));
+    let yuv = YuvToRgb::new(
+        ColorSpace { matrix: YuvMatrix::Bt601, range: ColorRange::Limited, ..Default::default() },
+        480,
+    );
+    let red = yuv.bgra(y[2], u[2], v[2]);
+    assert!(red[2] >= 250 && red[1] <= 5 && red[0] <= 5, "{red:?}");
+}
+
+#[test]
+fn bgra_to_i420_subsamples() {
+    // 3x2, a white and a black column pair and a red column
+    let row = [255, 255, 255, 255, 0, 0, 0, 255, 0, 0, 255, 255, 0, 0];
+    let bgra = [row, row].concat();
+    let i420 = bgra_to_i420(&bgra, 3, 2, row.len());
+    assert_eq!(i420.len(), 6 + 2 * 2);
+    assert_eq!(&i420[..3], [235, 16, 82]);
+    // the average of white and black is gray, without color
+    assert_eq!((i420[6], i420[8]), (128, 128));
+    assert!(i420[9] > 200, "{i420:?}");
+    let nv12 = i420_to_nv12(i420.clone(), 3, 2);
+    assert_eq!(&nv12[6..], [i420[6], i420[8], i420[7], i420[9]]);
+    assert_eq!(nv12_to_i420(nv12, 3, 2), i420);
+}
+
+#[test]
+fn packed_422_chroma_round_trip() {
+    // 3x2 YUYV in rows padded to 8 bytes, the last pixel without a V of its own
+    let yuyv = [10, 100, 20, 200, 30, 110, 9, 9, 40, 102, 50, 202, 60, 112, 9, 9];
+    let mut nv12 = luma_plane(&yuyv, 3, 2, 8, 0, 2);
+    nv12.extend(packed_422_chroma(&yuyv, 3, 2, 8, 1));
+    assert_eq!(nv12, [10, 20, 30, 40, 50, 60, 101, 201, 101, 201]);
+    let i420 = nv12_to_i420(nv12.clone(), 3, 2);
+    assert_eq!(&i420[6..], [101, 101, 201, 201]);
+    assert_eq!(i420_to_nv12(i420, 3, 2), nv12);
+    // UYVY has the same samples one byte earlier
+    let uyvy: Vec<u8> = yuyv.chunks(2).flat_map(|px| [px[1], px[0]]).collect();
+    assert_eq!(packed_422_chroma(&uyvy, 3, 2, 8, 0), nv12[6..]);
+}
+
+#[test]
+fn padded_rows() {
+    let cs = ColorSpace::default();
+    // 2x2 pixels with rows padded to 6 bytes, the padding must not show up as pixels
+    let yuyv = [16, 100, 235, 150, 9, 9, 126, 100, 126, 150, 9, 9];
+    let nv12 = [16, 235, 9, 126, 126, 9, 100, 150, 9];
+    let (mut packed, mut padded) = (Vec::new(), Vec::new());
+    yuyv_to_bgra(&[16, 100, 235, 150, 126, 100, 126, 150], 2, 2, 4, cs, &mut packed);
+    yuyv_to_bgra(&yuyv, 2, 2, 6, cs, &mut padded);
+    assert_eq!(packed, padded);
+    nv12_to_bgra(&nv12, 2, 2, 3, cs, &mut padded);
+    assert_eq!(packed, padded);
+    gray_to_bgra(&[7, 8, 9, 10, 11, 9], 2, 2, 3, &mut padded);
+    assert_eq!(padded.chunks(4).map(|px| px[0]).collect::<Vec<_>>(), [7, 8, 10, 11]);
+    rgb24_to_bgra(&[3, 2, 1, 9, 6, 5, 4, 9], 1, 2, 4, &mut padded);
+    assert_eq!(padded, [1, 2, 3, 255, 4, 5, 6, 255]);
+}
+
+#[test]
+fn weave_sequential_fields() {
+    // rows 0 and 2 are the top field, 1 and 3 the bottom one, then a chroma plane of 2 rows
+    let top_first = [0, 2, 1, 3, 10, 11];
+    let mut woven = Vec::new();
+    weave_fields(&top_first, 1, &[4, 2], false, &mut woven);
+    assert_eq!(woven, [0, 1, 2, 3, 10, 11]);
+    let bottom_first = [1, 0, 2, 11, 10];
+    weave_fields(&bottom_first, 1, &[3, 2], true, &mut woven);
+    assert_eq!(woven, [0, 1, 2, 10, 11]);
+}
+
+#[test]
+fn raw_samples() {
+    // 10 bit, a row of 3 samples padded to 8 bytes, the last one saturates
+    let buf = [0xff, 0x03, 0x00, 0x02, 0xff, 0xff, 0, 0];
+    let samples = unpack_u16(&buf, 3, 1, 8);
+    assert_eq!(samples, [1023, 512, 0xffff]);
+    assert_eq!(narrow_u16(&samples, 10), [255, 128, 255]);
+    assert_eq!(narrow_u16(&[0x1234], 16), [0x12]);
+    // the Y samples of a 2x2 UYVY frame with 2 bytes of row padding
+    let uyvy = [128, 1, 128, 2, 0, 0, 128, 3, 128, 4, 0, 0];
+    assert_eq!(luma_plane(&uyvy, 2, 2, 6, 1, 2), [1, 2, 3, 4]);
+
+    // a 3x3 RGGB mosaic, the last row and column take the color of the first cell
+    let mosaic = [200, 60, 9, 40, 10, 9, 9, 9, 9];
+    let mut bgra = Vec::new();
+    bayer_to_bgra(&mosaic, 3, 3, 3, BayerPattern::Rggb, &mut bgra);
+    assert!(bgra.chunks(4).all(|px| px == [10, 50, 200, 255]));
+    let bggr = [10, 60, 40, 200];
+    bayer_to_bgra(&bggr, 2, 2, 2, BayerPattern::Bggr, &mut bgra);
+    assert_eq!(&bgra[..4], [10, 50, 200, 255]);
+}
+
+#[test]
+fn unpack_p010_planes() {
+    let sample = |s: u16| (s << 6).to_le_bytes();
+    // 3x2 with 2 bytes of row padding, the UV plane has 2 pairs for the odd width
+    let luma_row = [sample(64), sample(512), sample(940), [0xff, 0xff]].concat();
+    let chroma_row = [sample(512), sample(600), sample(100), sample(960)].concat();
+    let buf = [&luma_row[..], &luma_row, &chroma_row].concat();
+    let samples = unpack_p010(&buf, 3, 2, 8);
+    assert_eq!(samples, [64, 512, 940, 64, 512, 940, 512, 600, 100, 960]);
+    assert_eq!(nv12_to_i420(samples, 3, 2)[6..], [512, 100, 600, 960]);
+}
@@ -0,0 +1,117 @@
+use std::sync::{Arc, Mutex};
+
+use crate::sync::lock;
+use crate::{Error, FourCC};
+
+/// Decodes the buffers of a compressed format like MJPG or H264 into BGRA, created by a
+/// [`DecoderProvider`].
+pub trait Decoder: Send {
+    /// Decodes one buffer into `bgra`, which holds the frame as packed rows afterwards.
+    ///
+    /// `Ok(false)` while the decoder needs more buffers for a picture, e.g. an H264 stream
+    /// before its first key frame. An error drops the decoder, the following frames are
+    /// converted by kamera if it knows the format and dropped otherwise.
+    fn decode(&mut self, buf: &[u8], bgra: &mut Vec<u8>) -> Result<bool, Error>;
+}
+
+/// Plugs decoders into a camera, e.g. VideoToolbox, DXVA or VA-API ones which take the work
+/// off the CPU, see [`CameraBuilder::decoder_provider`](crate::CameraBuilder::decoder_provider).
+pub trait DecoderProvider: Send + Sync {
+    /// A decoder for buffers of `fourcc` and this size, `None` leaves them to kamera. Asked
+    /// again when the format changes.
+    fn decoder(&self, fourcc: FourCC, width: u32, height: u32) -> Option<Box<dyn Decoder>>;
+}
+
+/// A provider in a [`CameraBuilder`](crate::CameraBuilder), which is `Debug` and `Clone`.
+#[derive(Clone)]
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) struct SharedProvider(pub(crate) Arc<dyn DecoderProvider>);
+
+impl std::fmt::Debug for SharedProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("DecoderProvider")
+    }
+}
+
+type Current = Option<((FourCC, u32, u32), Option<Box<dyn Decoder>>)>;
+
+/// The decoder of a provider for the current format of a camera.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) struct Decoders {
+    provider: Option<SharedProvider>,
+    current: Mutex<Current>,
+}
+
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+impl Decoders {
+    pub(crate) fn new(provider: Option<SharedProvider>) -> Self {
+        Self { provider, current: Mutex::new(None) }
+    }
+
+    /// Decodes `buf` with the decoder for its format, `None` without one. Frames are decoded
+    /// one at a time, in the order of the calls.
+    pub(crate) fn decode(
+        &self,
+        fourcc: FourCC,
+        (width, height): (u32, u32),
+        buf: &[u8],
+        bgra: &mut Vec<u8>,
+    ) -> Option<bool> {
+        let provider = self.provider.as_ref()?;
+        let mut current = lock(&self.current);
+        let format = (fourcc, width, height);
+        if current.as_ref().map(|(f, _)| *f) != Some(format) {
+            *current = Some((format, provider.0.decoder(fourcc, width, height)));
+        }
+        let decoder = current.as_mut()?.1.as_mut()?;
+        match decoder.decode(buf, bgra) {
+            Ok(decoded) => Some(decoded),
+            Err(_) => {
+                *current = Some((format, None));
+                None
+            }
+        }
+    }
+}
+
+#[test]
+fn decoders_follow_format() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct Counting(AtomicUsize);
+    struct Fill(u8);
+
+    impl DecoderProvider for Counting {
+        fn decoder(&self, fourcc: FourCC, _: u32, height: u32) -> Option<Box<dyn Decoder>> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            (fourcc == FourCC::new(b"H264")).then(|| Box::new(Fill(height as u8)) as _)
+        }
+    }
+
+    impl Decoder for Fill {
+        fn decode(&mut self, buf: &[u8], bgra: &mut Vec<u8>) -> Result<bool, Error> {
+            match buf {
+                [] => Err(Error::Unsupported),
+                [0] => Ok(false),
+                _ => {
+                    *bgra = vec![self.0; 4];
+                    Ok(true)
+                }
+            }
+        }
+    }
+
+    let provider = Arc::new(Counting(AtomicUsize::new(0)));
+    let decoders = Decoders::new(Some(SharedProvider(provider.clone())));
+    let (h264, mut bgra) = (FourCC::new(b"H264"), vec![]);
+    assert_eq!(decoders.decode(h264, (1, 1), &[0], &mut bgra), Some(false));
+    assert_eq!(decoders.decode(h264, (1, 1), &[1], &mut bgra), Some(true));
+    assert_eq!(decoders.decode(h264, (1, 2), &[1], &mut bgra), Some(true));
+    assert_eq!((bgra, provider.0.load(Ordering::Relaxed)), (vec![2; 4], 2));
+    // a failed decoder and a format without one stay with kamera
+    assert_eq!(decoders.decode(h264, (1, 2), &[], &mut vec![]), None);
+    assert_eq!(decoders.decode(h264, (1, 2), &[1], &mut vec![]), None);
+    assert_eq!(decoders.decode(FourCC::new(b"YUYV"), (1, 2), &[1], &mut vec![]), None);
+    assert_eq!(provider.0.load(Ordering::Relaxed), 3);
+    assert_eq!(Decoders::new(None).decode(h264, (1, 1), &[1], &mut vec![]), None);
+}
@@ -0,0 +1,32 @@
+/// Distances of a depth camera for each pixel of its map, see
+/// [`Camera::enable_depth`](crate::Camera::enable_depth).
+///
+/// The map may be smaller than the color frame it belongs to, e.g. 640x480 depth for a 1920x1080
+/// frame on iPhones, and covers the same view.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DepthFrame {
+    pub width: u32,
+    pub height: u32,
+    /// Millimeters, row by row without padding. 0 where the camera measured nothing.
+    pub data: Vec<u16>,
+}
+
+impl DepthFrame {
+    /// Millimeters at `x`, `y` of the map, `None` outside of it or where nothing was measured.
+    pub fn at(&self, x: u32, y: u32) -> Option<u16> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let depth = self.data[y as usize * self.width as usize + x as usize];
+        (depth != 0).then_some(depth)
+    }
+}
+
+#[test]
+fn depth_at() {
+    let depth = DepthFrame { width: 2, height: 2, data: vec![0, 500, 1200, 65535] };
+    assert_eq!(depth.at(0, 0), None);
+    assert_eq!((depth.at(1, 0), depth.at(0, 1)), (Some(500), Some(1200)));
+    assert_eq!(depth.at(2, 0), None);
+    assert_eq!(depth.at(1, 2), None);
+}
@@ -0,0 +1,139 @@
+/// Hardware identity of a device, to bind configuration to one physical unit, see
+/// [`CameraDevice::details`](crate::CameraDevice::details). What the OS doesn't report is
+/// `None`, as for built-in cameras which aren't on USB.
+///
+/// Linux reads all of it from sysfs. macOS has no serial number and firmware version without
+/// IOKit, Windows only has what the symbolic link of the device contains.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceDetails {
+    pub vendor_id: Option<u16>,
+    pub product_id: Option<u16>,
+    /// Only for devices with a serial number in their USB descriptor, which many webcams lack.
+    pub serial: Option<String>,
+    /// Where the device is plugged in, stays the same for the same port: the sysfs name like
+    /// `3-2.1` on Linux and the hex location ID on macOS.
+    pub bus_location: Option<String>,
+    /// The device release number, e.g. `1.13`.
+    pub firmware: Option<String>,
+    pub manufacturer: Option<String>,
+    /// The product name, which may differ from [`CameraDevice::name`](crate::CameraDevice::name).
+    pub model: Option<String>,
+}
+
+/// `bcdDevice` as `major.minor`.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) fn bcd_version(bcd: &str) -> Option<String> {
+    let bcd = u16::from_str_radix(bcd.trim(), 16).ok()?;
+    Some(format!("{:x}.{:02x}", bcd >> 8, bcd & 0xff))
+}
+
+/// From a symbolic link like `\\?\usb#vid_046d&pid_085e&mi_00#7&1a2b3c4d&0&0000#{guid}`. The
+/// instance part is the serial number if the device has one, otherwise Windows generates one
+/// with `&` in it.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub(crate) fn from_symbolic_link(link: &str) -> DeviceDetails {
+    let link = link.to_lowercase();
+    let mut parts = link.trim_start_matches(r"\\?\").split('#');
+    let (bus, ids, instance) = (parts.next(), parts.next(), parts.next());
+    if bus != Some("usb") {
+        return DeviceDetails::default();
+    }
+    let id = |prefix: &str| {
+        let id = ids?.split('&').find_map(|part| part.strip_prefix(prefix))?;
+        u16::from_str_radix(id, 16).ok()
+    };
+    DeviceDetails {
+        vendor_id: id("vid_"),
+        product_id: id("pid_"),
+        serial: instance.filter(|i| !i.contains('&')).map(str::to_uppercase),
+        ..Default::default()
+    }
+}
+
+/// The symbolic link without the interface, `mi_00` and the last part of a generated instance,
+/// which the interfaces of a USB device share. Other links are their own group.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub(crate) fn group_from_symbolic_link(link: &str) -> String {
+    let lower = link.to_lowercase();
+    let mut parts = lower.trim_start_matches(r"\\?\").split('#');
+    let (Some("usb"), Some(ids), Some(instance)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return link.to_string();
+    };
+    let ids: Vec<_> = ids.split('&').filter(|part| !part.starts_with("mi_")).collect();
+    let instance = instance.rsplit_once('&').map_or(instance, |(parent, _)| parent);
+    format!("usb#{}#{instance}", ids.join("&"))
+}
+
+/// The symbolic link without its interface class, the same for the Media Foundation and the
+/// DirectShow interface of a device.
+#[cfg_attr(not(all(target_os = "windows", feature = "dshow")), allow(dead_code))]
+pub(crate) fn instance_from_symbolic_link(link: &str) -> String {
+    let lower = link.to_lowercase();
+    let trimmed = lower.trim_start_matches(r"\\?\");
+    match trimmed.rsplit_once("#{") {
+        Some((instance, _)) => instance.to_string(),
+        None => trimmed.to_string(),
+    }
+}
+
+/// From the model ID of AVFoundation like `UVC Camera VendorID_1133 ProductID_2093` and the
+/// unique ID of a USB camera, `0x` followed by the location ID, vendor and product in hex.
+#[cfg_attr(not(any(target_os = "macos", target_os = "ios")), allow(dead_code))]
+pub(crate) fn from_model_id(model_id: &str, unique_id: &str) -> DeviceDetails {
+    let id = |prefix: &str| {
+        let words = model_id.split_whitespace();
+        words.filter_map(|word| word.strip_prefix(prefix)).find_map(|id| id.parse().ok())
+    };
+    let (vendor_id, product_id) = (id("VendorID_"), id("ProductID_"));
+    let hex = unique_id
+        .strip_prefix("0x")
+        .filter(|hex| hex.len() == 16 && hex.chars().all(|c| c.is_ascii_hexdigit()));
+    let bus_location = match (hex, vendor_id, product_id) {
+        (Some(hex), Some(vendor), Some(product))
+            if hex[8..].eq_ignore_ascii_case(&format!("{vendor:04x}{product:04x}")) =>
+        {
+            Some(hex[..8].to_string())
+        }
+        _ => None,
+    };
+    DeviceDetails {
+        vendor_id,
+        product_id,
+        bus_location,
+        model: Some(model_id.to_string()),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn parse_device_details() {
+    assert_eq!(bcd_version("0113\n").as_deref(), Some("1.13"));
+
+    let link = r"\\?\usb#vid_046d&pid_085e&mi_00#7&1a2b3c4d&0&0000#{e5323777-f976-4f5b-9b55-b94699c46e44}\global";
+    let details = from_symbolic_link(link);
+    assert_eq!(
+        (details.vendor_id, details.product_id, details.serial),
+        (Some(0x046d), Some(0x085e), None)
+    );
+    let link = r"\\?\usb#vid_046d&pid_085e#a1b2c3#{e5323777-f976-4f5b-9b55-b94699c46e44}";
+    assert_eq!(from_symbolic_link(link).serial.as_deref(), Some("A1B2C3"));
+    assert_eq!(from_symbolic_link(r"\\?\swd#vcamdevapi#obs#{guid}"), DeviceDetails::default());
+    let ir =
+        r"\\?\usb#vid_046d&pid_085e&mi_02#7&1a2b3c4d&0&0002#{e5323777-f976-4f5b-9b55-b94699c46e44}";
+    let rgb = ir.replace("mi_02", "mi_00").replace("0&0002", "0&0000");
+    assert_eq!(group_from_symbolic_link(&rgb), "usb#vid_046d&pid_085e#7&1a2b3c4d&0");
+    assert_eq!(group_from_symbolic_link(ir), group_from_symbolic_link(&rgb));
+    assert_eq!(group_from_symbolic_link(link), "usb#vid_046d&pid_085e#a1b2c3");
+    let dshow = r"\\?\USB#VID_046D&PID_085E#A1B2C3#{65e8773d-8f56-11d0-a3b9-00a0c9223196}\global";
+    assert_eq!(instance_from_symbolic_link(dshow), "usb#vid_046d&pid_085e#a1b2c3");
+    assert_eq!(instance_from_symbolic_link(link), instance_from_symbolic_link(dshow));
+
+    let details = from_model_id("UVC Camera VendorID_1133 ProductID_2142", "0x14200000046d085e");
+    assert_eq!((details.vendor_id, details.product_id), (Some(1133), Some(2142)));
+    assert_eq!(details.bus_location.as_deref(), Some("14200000"));
+    let details = from_model_id("Mac15,3", "47B4B64B70674B9CAD2BAE273A71F4B5");
+    assert_eq!((details.vendor_id, details.bus_location), (None, None));
+    assert_eq!(details.model.as_deref(), Some("Mac15,3"));
+}
@@ -0,0 +1,97 @@
+use std::cmp::Reverse;
+
+use crate::{CameraDevice, DeviceKind};
+
+/// Which camera [`Camera::new_default_device`](crate::Camera::new_default_device) opens, see
+/// [`CameraBuilder::default_device_policy`](crate::CameraBuilder::default_device_policy).
+///
+/// Infrared and virtual cameras come last with every policy but [`Self::Manual`], among equal
+/// devices the first enumerated one wins.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum DefaultDevicePolicy {
+    /// The camera built into the laptop or display (the default).
+    #[default]
+    PreferBuiltin,
+    /// A USB or network camera plugged into the computer, e.g. a better webcam on a desk.
+    PreferExternal,
+    /// The first camera whose name contains this, ignoring case, otherwise like
+    /// [`Self::PreferBuiltin`].
+    ByNameSubstring(String),
+    /// Exactly this device, e.g. from [`Camera::find_by_stable_id`](crate::Camera::find_by_stable_id).
+    Manual(CameraDevice),
+}
+
+/// Where a camera sits as far as the backend can tell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Placement {
+    BuiltIn,
+    External,
+    Unknown,
+}
+
+/// The device `policy` prefers, `None` if there is none or the manual one is missing.
+pub(crate) fn pick(
+    devices: Vec<(CameraDevice, Placement)>,
+    policy: &DefaultDevicePolicy,
+) -> Option<CameraDevice> {
+    if let DefaultDevicePolicy::Manual(device) = policy {
+        return devices.into_iter().map(|(d, _)| d).find(|d| d.id == device.id);
+    }
+    let score = |(device, placement): &(CameraDevice, Placement)| {
+        let placement = match (placement, device.kind) {
+            (_, DeviceKind::Continuity) => Placement::External,
+            (Placement::Unknown, _) if looks_builtin(&device.name) => Placement::BuiltIn,
+            (placement, _) => *placement,
+        };
+        let mut score = match (policy, placement) {
+            (DefaultDevicePolicy::PreferExternal, Placement::External) => 2,
+            (DefaultDevicePolicy::PreferExternal, Placement::BuiltIn) => 0,
+            (_, Placement::BuiltIn) => 2,
+            (_, Placement::External) => 0,
+            (_, Placement::Unknown) => 1,
+        };
+        if let DefaultDevicePolicy::ByNameSubstring(part) = policy {
+            if device.name.to_lowercase().contains(&part.to_lowercase()) {
+                score += 8;
+            }
+        }
+        if device.kind == DeviceKind::Virtual || looks_infrared(&device.name) {
+            score -= 4;
+        }
+        score
+    };
+    let best = devices.iter().enumerate().max_by_key(|(i, d)| (score(d), Reverse(*i)))?.0;
+    devices.into_iter().nth(best).map(|(d, _)| d)
+}
+
+fn looks_builtin(name: &str) -> bool {
+    let name = name.to_lowercase();
+    ["integrated", "built-in", "facetime", "user facing", "front"].iter().any(|s| name.contains(s))
+}
+
+/// Windows Hello and similar face login cameras, which only deliver grayscale.
+fn looks_infrared(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name.contains("infrared") || name.split(|c: char| !c.is_alphanumeric()).any(|w| w == "ir")
+}
+
+#[test]
+fn pick_default_device() {
+    let device = |name: &str, kind| CameraDevice::new(name, name, kind);
+    let devices = vec![
+        (device("OBS Virtual Camera", DeviceKind::Virtual), Placement::Unknown),
+        (device("Integrated IR Camera", DeviceKind::Physical), Placement::BuiltIn),
+        (device("Logitech BRIO", DeviceKind::Physical), Placement::External),
+        (device("Integrated Camera", DeviceKind::Physical), Placement::Unknown),
+    ];
+    let picked = |policy| pick(devices.clone(), &policy).map(|d| d.name);
+
+    assert_eq!(picked(DefaultDevicePolicy::PreferBuiltin).unwrap(), "Integrated Camera");
+    assert_eq!(picked(DefaultDevicePolicy::PreferExternal).unwrap(), "Logitech BRIO");
+    let obs = DefaultDevicePolicy::ByNameSubstring("obs".into());
+    assert_eq!(picked(obs).unwrap(), "OBS Virtual Camera");
+    let manual = DefaultDevicePolicy::Manual(devices[1].0.clone());
+    assert_eq!(picked(manual).unwrap(), "Integrated IR Camera");
+    let missing = DefaultDevicePolicy::Manual(device("gone", DeviceKind::Physical));
+    assert_eq!(picked(missing), None);
+}
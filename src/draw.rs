@@ -0,0 +1,227 @@
+//! Debug overlays on frames, enabled with the `draw` feature: outlined and filled rectangles,
+//! lines and text in a built-in 5x7 pixel font, drawn into an [`OwnedFrame`] in place.
+//!
+//! Everything is clipped to the frame, so shapes may reach past its edges. There's no
+//! antialiasing and no blending, pixels take the color as is.
+//!
+//! ```no_run
+//! use kamera::draw::{self, Color};
+//! use kamera::{Camera, OwnedFrame, Rect};
+//!
+//! let camera = Camera::new_default_device();
+//! camera.start();
+//! let frame = camera.wait_for_frame().unwrap();
+//! let (w, h) = frame.size_u32();
+//! let mut canvas = OwnedFrame::new(frame.data().data_bgra().to_vec(), w, h);
+//! let face = Rect::new(120, 80, 200, 240);
+//! draw::rect(&mut canvas, face, Color::GREEN, 2);
+//! draw::text(&mut canvas, (120, 66), "face 0.97", Color::GREEN, 2);
+//! ```
+
+use crate::{OwnedFrame, Rect};
+
+/// An opaque color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+    pub const WHITE: Color = Color::rgb(255, 255, 255);
+    pub const RED: Color = Color::rgb(255, 0, 0);
+    pub const GREEN: Color = Color::rgb(0, 255, 0);
+    pub const BLUE: Color = Color::rgb(0, 0, 255);
+    pub const YELLOW: Color = Color::rgb(255, 255, 0);
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    fn bgra(self) -> [u8; 4] {
+        [self.b, self.g, self.r, 255]
+    }
+}
+
+/// The outline of `rect`, `thickness` pixels wide on the inside.
+pub fn rect(frame: &mut OwnedFrame, rect: Rect, color: Color, thickness: u32) {
+    let (x0, y0) = (rect.x as i64, rect.y as i64);
+    let (x1, y1) = (x0 + rect.width as i64, y0 + rect.height as i64);
+    let t = (thickness as i64).min(rect.width as i64 / 2 + 1).min(rect.height as i64 / 2 + 1);
+    fill(frame, (x0, y0), (x1, y0 + t), color);
+    fill(frame, (x0, y1 - t), (x1, y1), color);
+    fill(frame, (x0, y0 + t), (x0 + t, y1 - t), color);
+    fill(frame, (x1 - t, y0 + t), (x1, y1 - t), color);
+}
+
+pub fn fill_rect(frame: &mut OwnedFrame, rect: Rect, color: Color) {
+    let (x, y) = (rect.x as i64, rect.y as i64);
+    fill(frame, (x, y), (x + rect.width as i64, y + rect.height as i64), color);
+}
+
+/// A line between the centers of two pixels, with a square pen of `thickness` pixels.
+pub fn line(
+    frame: &mut OwnedFrame,
+    from: (i32, i32),
+    to: (i32, i32),
+    color: Color,
+    thickness: u32,
+) {
+    let t = thickness.max(1) as i64;
+    let (mut x, mut y) = (from.0 as i64, from.1 as i64);
+    let (x1, y1) = (to.0 as i64, to.1 as i64);
+    let (dx, dy) = ((x1 - x).abs(), -(y1 - y).abs());
+    let (sx, sy) = ((x1 - x).signum(), (y1 - y).signum());
+    let mut err = dx + dy;
+    loop {
+        fill(frame, (x - (t - 1) / 2, y - (t - 1) / 2), (x + t / 2 + 1, y + t / 2 + 1), color);
+        if (x, y) == (x1, y1) {
+            break;
+        }
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += sy;
+        }
+    }
+}
+
+/// Text with its top left corner at `at`, each pixel of the font becomes a square of `scale`
+/// pixels. A line is `8 * scale` pixels high, characters advance by `6 * scale` and `\n` starts
+/// a new line. Characters outside of printable ASCII show as `?`.
+pub fn text(frame: &mut OwnedFrame, at: (i32, i32), text: &str, color: Color, scale: u32) {
+    let scale = scale.max(1) as i64;
+    let (mut x, mut y) = (at.0 as i64, at.1 as i64);
+    for c in text.chars() {
+        if c == '\n' {
+            (x, y) = (at.0 as i64, y + 8 * scale);
+            continue;
+        }
+        for (column, bits) in glyph(c).iter().enumerate() {
+            for row in (0..7).filter(|row| bits & (1 << row) != 0) {
+                let (px, py) = (x + column as i64 * scale, y + row * scale);
+                fill(frame, (px, py), (px + scale, py + scale), color);
+            }
+        }
+        x += 6 * scale;
+    }
+}
+
+/// Fills the pixels from `(x0, y0)` up to but not including `(x1, y1)`, clipped to the frame.
+fn fill(frame: &mut OwnedFrame, (x0, y0): (i64, i64), (x1, y1): (i64, i64), color: Color) {
+    let (w, h) = frame.size_u32();
+    let (x0, x1) = (x0.clamp(0, w as i64) as usize, x1.clamp(0, w as i64) as usize);
+    let (y0, y1) = (y0.clamp(0, h as i64) as usize, y1.clamp(0, h as i64) as usize);
+    if x0 >= x1 {
+        return;
+    }
+    let (stride, bgra) = (frame.stride(), color.bgra());
+    let data = frame.data_mut();
+    for y in y0..y1 {
+        for px in data[y * stride + x0 * 4..y * stride + x1 * 4].chunks_exact_mut(4) {
+            px.copy_from_slice(&bgra);
+        }
+    }
+}
+
+/// Columns of the 5x7 font from left to right, the lowest bit is the top row.
+fn glyph(c: char) -> &'static [u8; 5] {
+    let index = match c {
+        ' '..='~' => c as usize - ' ' as usize,
+        _ => '?' as usize - ' ' as usize,
+    };
+    &FONT[index]
+}
+
+#[rustfmt::skip]
+const FONT: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], [0x00, 0x00, 0x5f, 0x00, 0x00], // space !
+    [0x00, 0x07, 0x00, 0x07, 0x00], [0x14, 0x7f, 0x14, 0x7f, 0x14], // " #
+    [0x24, 0x2a, 0x7f, 0x2a, 0x12], [0x23, 0x13, 0x08, 0x64, 0x62], // $ %
+    [0x36, 0x49, 0x55, 0x22, 0x50], [0x00, 0x05, 0x03, 0x00, 0x00], // & '
+    [0x00, 0x1c, 0x22, 0x41, 0x00], [0x00, 0x41, 0x22, 0x1c, 0x00], // ( )
+    [0x08, 0x2a, 0x1c, 0x2a, 0x08], [0x08, 0x08, 0x3e, 0x08, 0x08], // * +
+    [0x00, 0x50, 0x30, 0x00, 0x00], [0x08, 0x08, 0x08, 0x08, 0x08], // , -
+    [0x00, 0x60, 0x60, 0x00, 0x00], [0x20, 0x10, 0x08, 0x04, 0x02], // . /
+    [0x3e, 0x51, 0x49, 0x45, 0x3e], [0x00, 0x42, 0x7f, 0x40, 0x00], // 0 1
+    [0x42, 0x61, 0x51, 0x49, 0x46], [0x21, 0x41, 0x45, 0x4b, 0x31], // 2 3
+    [0x18, 0x14, 0x12, 0x7f, 0x10], [0x27, 0x45, 0x45, 0x45, 0x39], // 4 5
+    [0x3c, 0x4a, 0x49, 0x49, 0x30], [0x01, 0x71, 0x09, 0x05, 0x03], // 6 7
+    [0x36, 0x49, 0x49, 0x49, 0x36], [0x06, 0x49, 0x49, 0x29, 0x1e], // 8 9
+    [0x00, 0x36, 0x36, 0x00, 0x00], [0x00, 0x56, 0x36, 0x00, 0x00], // : ;
+    [0x08, 0x14, 0x22, 0x41, 0x00], [0x14, 0x14, 0x14, 0x14, 0x14], // < =
+    [0x00, 0x41, 0x22, 0x14, 0x08], [0x02, 0x01, 0x51, 0x09, 0x06], // > ?
+    [0x32, 0x49, 0x79, 0x41, 0x3e], [0x7e, 0x11, 0x11, 0x11, 0x7e], // @ A
+    [0x7f, 0x49, 0x49, 0x49, 0x36], [0x3e, 0x41, 0x41, 0x41, 0x22], // B C
+    [0x7f, 0x41, 0x41, 0x22, 0x1c], [0x7f, 0x49, 0x49, 0x49, 0x41], // D E
+    [0x7f, 0x09, 0x09, 0x09, 0x01], [0x3e, 0x41, 0x49, 0x49, 0x7a], // F G
+    [0x7f, 0x08, 0x08, 0x08, 0x7f], [0x00, 0x41, 0x7f, 0x41, 0x00], // H I
+    [0x20, 0x40, 0x41, 0x3f, 0x01], [0x7f, 0x08, 0x14, 0x22, 0x41], // J K
+    [0x7f, 0x40, 0x40, 0x40, 0x40], [0x7f, 0x02, 0x0c, 0x02, 0x7f], // L M
+    [0x7f, 0x04, 0x08, 0x10, 0x7f], [0x3e, 0x41, 0x41, 0x41, 0x3e], // N O
+    [0x7f, 0x09, 0x09, 0x09, 0x06], [0x3e, 0x41, 0x51, 0x21, 0x5e], // P Q
+    [0x7f, 0x09, 0x19, 0x29, 0x46], [0x46, 0x49, 0x49, 0x49, 0x31], // R S
+    [0x01, 0x01, 0x7f, 0x01, 0x01], [0x3f, 0x40, 0x40, 0x40, 0x3f], // T U
+    [0x1f, 0x20, 0x40, 0x20, 0x1f], [0x3f, 0x40, 0x38, 0x40, 0x3f], // V W
+    [0x63, 0x14, 0x08, 0x14, 0x63], [0x07, 0x08, 0x70, 0x08, 0x07], // X Y
+    [0x61, 0x51, 0x49, 0x45, 0x43], [0x00, 0x7f, 0x41, 0x41, 0x00], // Z [
+    [0x02, 0x04, 0x08, 0x10, 0x20], [0x00, 0x41, 0x41, 0x7f, 0x00], // \ ]
+    [0x04, 0x02, 0x01, 0x02, 0x04], [0x40, 0x40, 0x40, 0x40, 0x40], // ^ _
+    [0x00, 0x01, 0x02, 0x04, 0x00], [0x20, 0x54, 0x54, 0x54, 0x78], // ` a
+    [0x7f, 0x48, 0x44, 0x44, 0x38], [0x38, 0x44, 0x44, 0x44, 0x20], // b c
+    [0x38, 0x44, 0x44, 0x48, 0x7f], [0x38, 0x54, 0x54, 0x54, 0x18], // d e
+    [0x08, 0x7e, 0x09, 0x01, 0x02], [0x0c, 0x52, 0x52, 0x52, 0x3e], // f g
+    [0x7f, 0x08, 0x04, 0x04, 0x78], [0x00, 0x44, 0x7d, 0x40, 0x00], // h i
+    [0x20, 0x40, 0x44, 0x3d, 0x00], [0x7f, 0x10, 0x28, 0x44, 0x00], // j k
+    [0x00, 0x41, 0x7f, 0x40, 0x00], [0x7c, 0x04, 0x18, 0x04, 0x78], // l m
+    [0x7c, 0x08, 0x04, 0x04, 0x78], [0x38, 0x44, 0x44, 0x44, 0x38], // n o
+    [0x7c, 0x14, 0x14, 0x14, 0x08], [0x08, 0x14, 0x14, 0x18, 0x7c], // p q
+    [0x7c, 0x08, 0x04, 0x04, 0x08], [0x48, 0x54, 0x54, 0x54, 0x20], // r s
+    [0x04, 0x3f, 0x44, 0x40, 0x20], [0x3c, 0x40, 0x40, 0x20, 0x7c], // t u
+    [0x1c, 0x20, 0x40, 0x20, 0x1c], [0x3c, 0x40, 0x30, 0x40, 0x3c], // v w
+    [0x44, 0x28, 0x10, 0x28, 0x44], [0x0c, 0x50, 0x50, 0x50, 0x3c], // x y
+    [0x44, 0x64, 0x54, 0x4c, 0x44], [0x00, 0x08, 0x36, 0x41, 0x00], // z {
+    [0x00, 0x00, 0x7f, 0x00, 0x00], [0x00, 0x41, 0x36, 0x08, 0x00], // | }
+    [0x08, 0x04, 0x08, 0x10, 0x08],                                 // ~
+];
+
+#[test]
+fn draw_shapes() {
+    let mut frame = OwnedFrame::new(vec![0; 8 * 6 * 4], 8, 6);
+    let lit = |frame: &OwnedFrame| -> Vec<String> {
+        let rows = frame.data().chunks(8 * 4);
+        rows.map(|row| row.chunks(4).map(|px| if px[1] > 0 { '#' } else { '.' }).collect())
+            .collect()
+    };
+    rect(&mut frame, Rect::new(1, 1, 5, 4), Color::GREEN, 1);
+    line(&mut frame, (-2, 5), (20, 5), Color::GREEN, 1);
+    assert_eq!(
+        lit(&frame),
+        ["........", ".#####..", ".#...#..", ".#...#..", ".#####..", "########"]
+    );
+    assert_eq!(&frame.data()[(8 + 1) * 4..][..4], [0, 255, 0, 255]);
+
+    let mut frame = OwnedFrame::new(vec![0; 8 * 8 * 4], 8, 8);
+    text(&mut frame, (1, 0), "T", Color::WHITE, 1);
+    #[rustfmt::skip]
+    assert_eq!(lit(&frame), [
+        ".#####..",
+        "...#....",
+        "...#....",
+        "...#....",
+        "...#....",
+        "...#....",
+        "...#....",
+        "........",
+    ]);
+    fill_rect(&mut frame, Rect::new(6, 6, 10, 10), Color::RED);
+    assert_eq!(&frame.data()[(7 * 8 + 7) * 4..], [0, 0, 255, 255]);
+    line(&mut frame, (0, 0), (7, 7), Color::BLUE, 3);
+    assert_eq!(&frame.data()[(3 * 8 + 4) * 4..][..4], [255, 0, 0, 255]);
+}
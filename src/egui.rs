@@ -0,0 +1,128 @@
+//! Camera frames in egui, enabled with the `egui` feature.
+//!
+//! [`FrameTexture`] keeps a texture up to date with the frames it is given, converted to RGB
+//! and resized with them. [`CameraPreview`] builds on it with a device picker above the latest
+//! frame of a camera.
+//!
+//! ```no_run
+//! let mut camera = kamera::Camera::new_default_device();
+//! camera.start();
+//! let mut preview = kamera::egui::CameraPreview::default();
+//! # let ctx = egui::Context::default();
+//! # let _ = ctx.run(Default::default(), |ctx| {
+//! egui::CentralPanel::default().show(ctx, |ui| preview.ui(ui, &mut camera));
+//! # });
+//! ```
+
+use egui::{ColorImage, Context, TextureHandle, TextureOptions, Ui};
+
+use crate::{Camera, CameraDevice, Frame};
+
+/// A texture showing the last frame it was updated with.
+#[derive(Clone)]
+pub struct FrameTexture {
+    name: String,
+    options: TextureOptions,
+    texture: Option<TextureHandle>,
+}
+
+impl Default for FrameTexture {
+    fn default() -> Self {
+        Self::new("kamera")
+    }
+}
+
+impl FrameTexture {
+    /// `name` is for egui's debugging of textures.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into(), options: TextureOptions::LINEAR, texture: None }
+    }
+
+    /// Sampling of the texture, linear by default.
+    pub fn with_options(mut self, options: TextureOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Uploads `frame`, the texture takes its size.
+    pub fn update(&mut self, ctx: &Context, frame: &Frame) -> &TextureHandle {
+        let (w, h) = frame.size_u32();
+        let image = ColorImage::from_rgb([w as usize, h as usize], frame.data().data_rgb());
+        match &mut self.texture {
+            Some(texture) => {
+                texture.set(image, self.options);
+                texture
+            }
+            slot => slot.insert(ctx.load_texture(&self.name, image, self.options)),
+        }
+    }
+
+    /// `None` until the first update.
+    pub fn texture(&self) -> Option<&TextureHandle> {
+        self.texture.as_ref()
+    }
+}
+
+impl std::fmt::Debug for FrameTexture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let size = self.texture.as_ref().map(|texture| texture.size());
+        f.debug_struct("FrameTexture").field("name", &self.name).field("size", &size).finish()
+    }
+}
+
+/// A device picker above the latest frame of a camera, scaled to fit the space left.
+#[derive(Debug, Default)]
+pub struct CameraPreview {
+    texture: FrameTexture,
+    devices: Option<Vec<CameraDevice>>,
+}
+
+impl CameraPreview {
+    /// Shows the preview for the started `camera`. Picking a device switches the camera to it
+    /// with [`Camera::set_device`]. Asks for a repaint to keep up with the frames.
+    pub fn ui(&mut self, ui: &mut Ui, camera: &mut Camera) -> egui::Response {
+        let current = camera.device();
+        let mut picked = None;
+        let picker = egui::ComboBox::from_id_source("kamera device")
+            .selected_text(&current.name)
+            .show_ui(ui, |ui| {
+                let devices = self.devices.get_or_insert_with(Camera::device_list);
+                for device in devices.iter() {
+                    if ui.selectable_label(*device == current, &device.name).clicked() {
+                        picked = Some(device.clone());
+                    }
+                }
+            });
+        if picker.response.clicked() {
+            // plugged in or removed since it was last open
+            self.devices = None;
+        }
+        if let Some(device) = picked.filter(|device| *device != current) {
+            camera.set_device(&device);
+        }
+        // the frames which arrived since the last repaint, only the newest is shown
+        if let Some(frame) = std::iter::from_fn(|| camera.try_next_frame()).last() {
+            self.texture.update(ui.ctx(), &frame);
+        }
+        ui.ctx().request_repaint();
+        match self.texture.texture() {
+            Some(texture) => ui.add(egui::Image::new(texture).shrink_to_fit()),
+            None => ui.spinner(),
+        }
+    }
+}
+
+#[test]
+fn frame_texture_follows_size() {
+    let camera = Camera::with_backend(crate::Backend::Test).unwrap();
+    camera.start();
+    let frame = camera.wait_for_frame().unwrap();
+    let ctx = Context::default();
+    let mut texture = FrameTexture::default();
+    assert!(texture.texture().is_none());
+    let id = texture.update(&ctx, &frame).id();
+    assert_eq!(texture.texture().unwrap().size(), [640, 480]);
+    let crop = frame.crop(crate::Rect::new(0, 0, 320, 240));
+    assert_eq!(texture.update(&ctx, &crop).size(), [320, 240]);
+    assert_eq!(texture.texture().unwrap().id(), id);
+}
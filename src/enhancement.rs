@@ -0,0 +1,12 @@
+/// Image enhancements of the device, see [`Camera::set_enhancement`](crate::Camera::set_enhancement).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Enhancement {
+    /// Longer exposure in the dark at the cost of frame rate. On Linux the exposure may lower
+    /// the frame rate, on macOS it is only available on iOS devices like Continuity cameras and
+    /// on Windows it is the night scene mode of the driver.
+    LowLightBoost,
+    /// Video HDR on macOS and Windows, wide dynamic range on Linux.
+    Hdr,
+    /// Brightens a subject in front of a bright background, Linux and Windows.
+    BacklightCompensation,
+}
@@ -0,0 +1,119 @@
+use std::sync::Arc;
+
+/// Errors of camera operations.
+#[derive(Debug, Clone)]
+pub enum Error {
+    /// Another application is using the device, e.g. a video call.
+    InUseByOtherApp,
+    /// No camera is connected, or the requested one is gone.
+    NoDevice,
+    /// The device or the platform lacks the feature, e.g. a torch.
+    Unsupported,
+    /// The camera delivered no frames, also not after restarting it, see
+    /// [`Camera::run`](crate::Camera::run).
+    Stalled,
+    /// An error of the capture API of the OS, e.g. an `io::Error` on Linux, an `NSError` on
+    /// macOS or a `windows::core::Error`, which is also the [`source`](std::error::Error::source).
+    Os(Arc<dyn std::error::Error + Send + Sync>),
+    /// Any other error with a message.
+    Other(String),
+}
+
+impl Error {
+    pub(crate) fn os(err: impl std::error::Error + Send + Sync + 'static) -> Self {
+        Error::Os(Arc::new(err))
+    }
+}
+
+/// OS errors are equal by their message.
+impl PartialEq for Error {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Error::Os(a), Error::Os(b)) => a.to_string() == b.to_string(),
+            (Error::Other(a), Error::Other(b)) => a == b,
+            (a, b) => std::mem::discriminant(a) == std::mem::discriminant(b),
+        }
+    }
+}
+
+impl Eq for Error {}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::InUseByOtherApp => f.write_str("camera is in use by another application"),
+            Error::NoDevice => f.write_str("no camera found"),
+            Error::Unsupported => f.write_str("not supported by the camera"),
+            Error::Stalled => f.write_str("camera stopped delivering frames"),
+            Error::Os(err) => write!(f, "{err}"),
+            Error::Other(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Os(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// Why a device is missing from [`Camera::device_list`](crate::Camera::device_list), see
+/// [`enumerate_with_errors`](crate::enumerate_with_errors).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnumError {
+    /// The OS denied opening the device, on Linux e.g. without membership in the `video` group.
+    PermissionDenied { id: String },
+    /// Opening the device failed for another reason.
+    OpenFailed { id: String, message: String },
+    /// The device opened but can't capture video, e.g. the metadata node of a UVC camera.
+    Unsupported { id: String, reason: String },
+}
+
+impl EnumError {
+    /// Path or symbolic link of the device.
+    pub fn id(&self) -> &str {
+        match self {
+            EnumError::PermissionDenied { id }
+            | EnumError::OpenFailed { id, .. }
+            | EnumError::Unsupported { id, .. } => id,
+        }
+    }
+}
+
+impl std::fmt::Display for EnumError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EnumError::PermissionDenied { id } => write!(f, "{id}: permission denied"),
+            EnumError::OpenFailed { id, message } => write!(f, "{id}: {message}"),
+            EnumError::Unsupported { id, reason } => write!(f, "{id}: unsupported, {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for EnumError {}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        match err.kind() {
+            std::io::ErrorKind::ResourceBusy => Error::InUseByOtherApp,
+            std::io::ErrorKind::NotFound => Error::NoDevice,
+            _ => Error::os(err),
+        }
+    }
+}
+
+#[test]
+fn error_source() {
+    use std::error::Error as _;
+    let busy = Error::from(std::io::Error::from(std::io::ErrorKind::ResourceBusy));
+    assert_eq!(busy, Error::InUseByOtherApp);
+    let err = Error::from(std::io::Error::other("VIDIOC_STREAMON failed"));
+    assert_eq!(err.to_string(), "VIDIOC_STREAMON failed");
+    let source = err.source().unwrap().downcast_ref::<std::io::Error>().unwrap();
+    assert_eq!(source.kind(), std::io::ErrorKind::Other);
+    assert_eq!(err, Error::os(std::io::Error::other("VIDIOC_STREAMON failed")));
+    assert_ne!(err, Error::Other("VIDIOC_STREAMON failed".into()));
+}
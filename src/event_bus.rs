@@ -0,0 +1,110 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex, Weak};
+use std::time::Duration;
+
+use crate::sync::{lock, wait_timeout_while};
+use crate::CameraEvent;
+
+/// Events kept per receiver before the oldest one is dropped.
+const QUEUE_SIZE: usize = 64;
+
+type Callback = Box<dyn FnMut(&CameraEvent) + Send>;
+
+/// The receivers of [`Camera::events`](crate::Camera::events) and the callbacks of
+/// [`Camera::on_event`](crate::Camera::on_event).
+#[derive(Default)]
+pub(crate) struct EventBus {
+    receivers: Mutex<Vec<Weak<Queue>>>,
+    callbacks: Mutex<Vec<Callback>>,
+}
+
+#[derive(Debug, Default)]
+struct Queue {
+    events: Mutex<VecDeque<CameraEvent>>,
+    ready: Condvar,
+}
+
+/// One of several consumers of the events of a camera, see
+/// [`Camera::events`](crate::Camera::events).
+///
+/// Receivers are `Send` and can be moved to their own threads. Events reach them while the
+/// thread which owns the camera takes frames or calls
+/// [`Camera::poll_events`](crate::Camera::poll_events). A receiver which isn't read misses the
+/// oldest events.
+#[derive(Debug)]
+pub struct EventReceiver {
+    queue: Arc<Queue>,
+}
+
+impl std::fmt::Debug for EventBus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventBus")
+            .field("receivers", &lock(&self.receivers).len())
+            .field("callbacks", &lock(&self.callbacks).len())
+            .finish()
+    }
+}
+
+impl EventBus {
+    pub(crate) fn subscribe(&self) -> EventReceiver {
+        let queue = Arc::new(Queue::default());
+        lock(&self.receivers).push(Arc::downgrade(&queue));
+        EventReceiver { queue }
+    }
+
+    pub(crate) fn on_event(&self, callback: Callback) {
+        lock(&self.callbacks).push(callback);
+    }
+
+    /// Calls the callbacks outside of the lock, so they can add callbacks or panic without
+    /// breaking the bus.
+    pub(crate) fn send(&self, event: &CameraEvent) {
+        let mut callbacks = std::mem::take(&mut *lock(&self.callbacks));
+        for callback in &mut callbacks {
+            callback(event);
+        }
+        // the ones added meanwhile go last
+        let mut current = lock(&self.callbacks);
+        callbacks.append(&mut current);
+        *current = callbacks;
+        lock(&self.receivers).retain(|queue| {
+            let Some(queue) = queue.upgrade() else { return false };
+            let mut events = lock(&queue.events);
+            if events.len() == QUEUE_SIZE {
+                events.pop_front();
+            }
+            events.push_back(event.clone());
+            queue.ready.notify_all();
+            true
+        });
+    }
+}
+
+impl EventReceiver {
+    /// Returns immediately, `None` if no event is queued.
+    pub fn try_recv(&self) -> Option<CameraEvent> {
+        lock(&self.queue.events).pop_front()
+    }
+
+    /// Waits up to `timeout` for the next event.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<CameraEvent> {
+        let events = lock(&self.queue.events);
+        wait_timeout_while(&self.queue.ready, events, timeout, |e| e.is_empty()).pop_front()
+    }
+
+    /// The queued events, without waiting for more.
+    pub fn try_iter(&self) -> impl Iterator<Item = CameraEvent> + '_ {
+        std::iter::from_fn(|| self.try_recv())
+    }
+}
+
+#[test]
+fn panicking_callback() {
+    let bus = EventBus::default();
+    let receiver = bus.subscribe();
+    bus.on_event(Box::new(|_| panic!("callback of the application")));
+    let send = std::panic::AssertUnwindSafe(|| bus.send(&CameraEvent::DeviceLost));
+    assert!(std::panic::catch_unwind(send).is_err());
+    bus.send(&CameraEvent::DeviceLost);
+    assert_eq!(receiver.try_recv(), Some(CameraEvent::DeviceLost));
+}
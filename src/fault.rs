@@ -0,0 +1,193 @@
+use std::sync::mpsc::Sender;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::sync::lock;
+use crate::{Camera, CameraEvent};
+
+/// Simulated camera trouble, to test how an application copes with dropped frames, stalls,
+/// garbage and unplugged devices without provoking them.
+///
+/// The faults of a frame are drawn from the seed and the number of the frame, so the same seed
+/// gives the same faults for the same frames in every run.
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use kamera::{Backend, Camera, FaultInjector};
+///
+/// let camera = FaultInjector::new(7)
+///     .drop_rate(0.1)
+///     .latency_spikes(0.05, Duration::from_millis(200))
+///     .corrupt_rate(0.02)
+///     .disconnect_after(300)
+///     .wrap(Camera::with_backend(Backend::Test).unwrap());
+/// camera.start();
+/// while let Some(frame) = camera.wait_for_frame() {
+///     println!("{:?}", frame.size_u32());
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct FaultInjector {
+    seed: u64,
+    drop_rate: f32,
+    spike_rate: f32,
+    spike: Duration,
+    corrupt_rate: f32,
+    disconnect_after: Option<u64>,
+    state: Mutex<State>,
+    /// Events of a [`FrameSource`](crate::FrameSource), which has no sender of its own.
+    events: Option<Sender<CameraEvent>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Frames of the camera since it was wrapped, dropped ones included.
+    frame: u64,
+    /// Frames delivered since the last start.
+    delivered: u64,
+    disconnected: bool,
+}
+
+/// What happens to a frame of the camera.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Fate {
+    /// `noise` seeds the garbage which replaces the lower half of the frame.
+    Deliver {
+        delay: Option<Duration>,
+        noise: Option<u64>,
+    },
+    Drop,
+    /// The camera is lost with this frame.
+    Disconnect,
+}
+
+impl FaultInjector {
+    /// No faults until they are configured.
+    pub fn new(seed: u64) -> Self {
+        Self { seed, ..Default::default() }
+    }
+
+    /// Fraction of frames which never arrive, between 0 and 1.
+    pub fn drop_rate(mut self, rate: f32) -> Self {
+        self.drop_rate = rate;
+        self
+    }
+
+    /// Fraction of frames which arrive `delay` late, the frame methods block for that long.
+    pub fn latency_spikes(mut self, rate: f32, delay: Duration) -> Self {
+        (self.spike_rate, self.spike) = (rate, delay);
+        self
+    }
+
+    /// Fraction of frames whose lower half turns to noise, like a torn buffer. These frames
+    /// are copies.
+    pub fn corrupt_rate(mut self, rate: f32) -> Self {
+        self.corrupt_rate = rate;
+        self
+    }
+
+    /// The device is lost after `frames` frames since the camera was started:
+    /// [`CameraEvent::DeviceLost`] arrives and there are no frames until it is started again.
+    pub fn disconnect_after(mut self, frames: u64) -> Self {
+        self.disconnect_after = Some(frames);
+        self
+    }
+
+    /// `camera` with the faults, for native cameras as well as a
+    /// [`FrameSource`](crate::FrameSource).
+    pub fn wrap(self, mut camera: Camera) -> Camera {
+        camera.inject_faults(self);
+        camera
+    }
+
+    pub(crate) fn connect_events(&mut self, events: Sender<CameraEvent>) {
+        self.events = Some(events);
+    }
+
+    pub(crate) fn send_event(&self, event: CameraEvent) {
+        if let Some(events) = &self.events {
+            let _ = events.send(event);
+        }
+    }
+
+    /// Starting the camera plugs the device in again.
+    pub(crate) fn start(&self) {
+        let mut state = lock(&self.state);
+        (state.delivered, state.disconnected) = (0, false);
+    }
+
+    pub(crate) fn disconnected(&self) -> bool {
+        lock(&self.state).disconnected
+    }
+
+    /// The fate of the next frame the camera delivered.
+    pub(crate) fn next(&self) -> Fate {
+        let mut state = lock(&self.state);
+        if self.disconnect_after.is_some_and(|frames| state.delivered >= frames) {
+            state.disconnected = true;
+            return Fate::Disconnect;
+        }
+        let mut random = Random(self.seed.wrapping_add(state.frame.wrapping_mul(GOLDEN)));
+        state.frame += 1;
+        // all draws happen, so the rate of one fault doesn't change the others
+        let dropped = random.chance(self.drop_rate);
+        let delay = random.chance(self.spike_rate).then_some(self.spike);
+        let noise = random.chance(self.corrupt_rate).then_some(random.next());
+        if dropped {
+            return Fate::Drop;
+        }
+        state.delivered += 1;
+        Fate::Deliver { delay, noise }
+    }
+}
+
+const GOLDEN: u64 = 0x9e3779b97f4a7c15;
+
+/// splitmix64, the same numbers on every platform.
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(GOLDEN);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// `true` with probability `rate`.
+    fn chance(&mut self, rate: f32) -> bool {
+        ((self.next() >> 40) as f32 / (1 << 24) as f32) < rate
+    }
+}
+
+/// Opaque BGRA noise from `seed`.
+pub(crate) fn fill_noise(bgra: &mut [u8], seed: u64) {
+    let mut random = Random(seed);
+    for pixel in bgra.chunks_exact_mut(4) {
+        let [b, g, r, ..] = random.next().to_le_bytes();
+        pixel.copy_from_slice(&[b, g, r, 255]);
+    }
+}
+
+#[test]
+fn fault_injector_fates() {
+    let fates = |faults: &FaultInjector| (0..1000).map(|_| faults.next()).collect::<Vec<_>>();
+    let faults =
+        || FaultInjector::new(3).drop_rate(0.2).latency_spikes(0.1, Duration::from_millis(50));
+    let first = fates(&faults());
+    assert_eq!(first, fates(&faults()));
+    assert_ne!(first, fates(&FaultInjector::new(4).drop_rate(0.2)));
+    let dropped = first.iter().filter(|fate| **fate == Fate::Drop).count();
+    assert!((150..250).contains(&dropped), "{dropped} dropped");
+    assert!(first.iter().all(|fate| !matches!(fate, Fate::Deliver { noise: Some(_), .. })));
+
+    let faults = FaultInjector::new(0).corrupt_rate(1.0).disconnect_after(2);
+    assert!(matches!(faults.next(), Fate::Deliver { delay: None, noise: Some(_) }));
+    assert!(matches!(faults.next(), Fate::Deliver { .. }));
+    assert_eq!(faults.next(), Fate::Disconnect);
+    assert!(faults.disconnected());
+    faults.start();
+    assert!(!faults.disconnected());
+    assert!(matches!(faults.next(), Fate::Deliver { .. }));
+}
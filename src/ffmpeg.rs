@@ -0,0 +1,126 @@
+//! Conversions between frames and `ffmpeg_next::frame::Video`, enabled with the `ffmpeg` feature.
+//!
+//! A [`Frame`] is copied row by row into a BGRA video frame, an [`OwnedFrame`] is moved into one
+//! without copying. Video frames in BGRA are borrowed as a [`PlaneView`], other pixel formats
+//! like the YUV420P of most decoders are converted with swscale on the way to an [`OwnedFrame`].
+//!
+//! ```no_run
+//! use ffmpeg_next::frame::Video;
+//!
+//! let camera = kamera::Camera::new_default_device();
+//! camera.start();
+//! let frame = camera.wait_for_frame().unwrap();
+//! let video = Video::from(&frame);
+//! // hand `video` to a scaler and encoder
+//! let back = kamera::OwnedFrame::try_from(&video).unwrap();
+//! ```
+
+use std::ffi::c_void;
+
+use ffmpeg_next::ffi;
+use ffmpeg_next::format::Pixel;
+use ffmpeg_next::frame::Video;
+
+use crate::{Error, Frame, OwnedFrame, PlaneView};
+
+/// Copies the frame as BGRA, keeping the row padding of the video frame's own allocation.
+impl From<&Frame> for Video {
+    fn from(frame: &Frame) -> Self {
+        let (width, height) = frame.size_u32();
+        let data = frame.data();
+        let src = data.plane(0).expect("BGRA plane");
+        let mut video = Video::new(Pixel::BGRA, width, height);
+        let dst_stride = video.stride(0);
+        let row_len = width as usize * 4;
+        let dst = video.data_mut(0);
+        for y in 0..height as usize {
+            dst[y * dst_stride..][..row_len]
+                .copy_from_slice(&src.data[y * src.stride..][..row_len]);
+        }
+        video
+    }
+}
+
+/// Hands the pixels to FFmpeg without copying, they are freed with the video frame. Only a
+/// frame without padding after its last row is copied once.
+impl From<OwnedFrame> for Video {
+    fn from(frame: OwnedFrame) -> Self {
+        unsafe extern "C" fn free(opaque: *mut c_void, _data: *mut u8) {
+            drop(Box::from_raw(opaque as *mut OwnedFrame));
+        }
+
+        let ((width, height), stride) = (frame.size_u32(), frame.stride());
+        // FFmpeg expects the padding of the last row too
+        let frame = match stride * height as usize {
+            len if frame.data().len() < len => {
+                let mut data = frame.data().to_vec();
+                data.resize(len, 0);
+                OwnedFrame::with_stride(data, width, height, stride)
+            }
+            _ => frame,
+        };
+        let mut frame = Box::new(frame);
+        let (data, len) = (frame.data_mut().as_mut_ptr(), frame.data().len());
+        let mut video = Video::empty();
+        unsafe {
+            let opaque = Box::into_raw(frame) as *mut c_void;
+            let buf = ffi::av_buffer_create(data, len as _, Some(free), opaque, 0);
+            assert!(!buf.is_null(), "av_buffer_create failed");
+            let ptr = video.as_mut_ptr();
+            (*ptr).buf[0] = buf;
+            (*ptr).data[0] = data;
+            (*ptr).linesize[0] = stride as _;
+        }
+        video.set_format(Pixel::BGRA);
+        video.set_width(width);
+        video.set_height(height);
+        video
+    }
+}
+
+/// Borrows a BGRA video frame, `Err(Error::Unsupported)` for other pixel formats.
+impl<'a> TryFrom<&'a Video> for PlaneView<'a> {
+    type Error = Error;
+
+    fn try_from(video: &'a Video) -> Result<Self, Error> {
+        if video.format() != Pixel::BGRA {
+            return Err(Error::Unsupported);
+        }
+        let (data, stride) = (video.data(0), video.stride(0));
+        Ok(PlaneView { data, stride, width: video.width(), height: video.height() })
+    }
+}
+
+/// Copies a BGRA video frame, other pixel formats are converted first.
+impl TryFrom<&Video> for OwnedFrame {
+    type Error = Error;
+
+    fn try_from(video: &Video) -> Result<Self, Error> {
+        let (width, height) = (video.width(), video.height());
+        if video.format() == Pixel::BGRA {
+            let (data, stride) = (video.data(0).to_vec(), video.stride(0));
+            return Ok(OwnedFrame::with_stride(data, width, height, stride));
+        }
+        let mut converter =
+            ffmpeg_next::software::converter((width, height), video.format(), Pixel::BGRA)
+                .map_err(|_| Error::Unsupported)?;
+        let mut bgra = Video::empty();
+        converter.run(video, &mut bgra).map_err(Error::os)?;
+        OwnedFrame::try_from(&bgra)
+    }
+}
+
+#[test]
+fn video_roundtrip() {
+    let pixels: Vec<u8> = (0..4 * 3 * 2).collect();
+    let video = Video::from(OwnedFrame::new(pixels.clone(), 3, 2));
+    assert_eq!((video.format(), video.width(), video.height()), (Pixel::BGRA, 3, 2));
+    let view = PlaneView::try_from(&video).unwrap();
+    assert_eq!((view.stride, &view.data[4..8]), (12, &pixels[4..8]));
+    let back = OwnedFrame::try_from(&video).unwrap();
+    assert_eq!(back.data(), &pixels[..]);
+
+    let yuv = Video::new(Pixel::YUV420P, 4, 2);
+    assert!(PlaneView::try_from(&yuv).is_err());
+    assert_eq!(OwnedFrame::try_from(&yuv).unwrap().size_u32(), (4, 2));
+}
@@ -0,0 +1,152 @@
+//! Pixel formats under their names on each platform: V4L2 fourccs, CoreVideo pixel format types
+//! and Media Foundation subtype GUIDs, to hand formats between kamera and other platform code.
+//!
+//! ```
+//! use kamera::format::PixelFormat;
+//! use kamera::FourCC;
+//!
+//! let yuyv = PixelFormat::from_fourcc(FourCC::new(b"YUYV")).unwrap();
+//! assert_eq!(yuyv.core_video(), Some(u32::from_be_bytes(*b"yuvs")));
+//! assert_eq!(PixelFormat::from_mf_subtype(yuyv.mf_subtype().unwrap()), Some(yuyv));
+//! ```
+
+use crate::FourCC;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum PixelFormat {
+    /// Blue, green, red and alpha bytes.
+    Bgra,
+    /// Like `Bgra` with an unused fourth byte.
+    Bgrx,
+    /// Red, green and blue bytes.
+    Rgb24,
+    /// Blue, green and red bytes.
+    Bgr24,
+    /// Packed 4:2:2, Y U Y V.
+    Yuyv,
+    /// Packed 4:2:2, U Y V Y.
+    Uyvy,
+    /// A Y plane and an interleaved UV plane of half the width and height.
+    Nv12,
+    /// Like `Nv12` with V before U.
+    Nv21,
+    /// A Y plane, then U and V planes of half the width and height.
+    I420,
+    /// `Nv12` with 10 bits in the top of 16 bit samples.
+    P010,
+    /// 8 bit luma.
+    Gray8,
+    /// 16 bit luma.
+    Gray16,
+    Mjpeg,
+    H264,
+}
+
+struct Names {
+    format: PixelFormat,
+    v4l2: Option<&'static [u8; 4]>,
+    /// The first one is the preferred, CoreVideo has video and full range variants.
+    core_video: &'static [u32],
+    /// The first field of the subtype GUID, a FOURCC or a D3DFORMAT number.
+    mf: Option<u32>,
+}
+
+const fn cv(code: &[u8; 4]) -> u32 {
+    u32::from_be_bytes(*code)
+}
+
+const fn mf(code: &[u8; 4]) -> Option<u32> {
+    Some(u32::from_le_bytes(*code))
+}
+
+#[rustfmt::skip]
+const TABLE: &[Names] = &[
+    Names { format: PixelFormat::Bgra, v4l2: Some(b"AR24"), core_video: &[cv(b"BGRA")], mf: Some(21) },
+    Names { format: PixelFormat::Bgrx, v4l2: Some(b"XR24"), core_video: &[], mf: Some(22) },
+    Names { format: PixelFormat::Rgb24, v4l2: Some(b"RGB3"), core_video: &[24], mf: None },
+    Names { format: PixelFormat::Bgr24, v4l2: Some(b"BGR3"), core_video: &[cv(b"24BG")], mf: Some(20) },
+    Names { format: PixelFormat::Yuyv, v4l2: Some(b"YUYV"), core_video: &[cv(b"yuvs")], mf: mf(b"YUY2") },
+    Names { format: PixelFormat::Uyvy, v4l2: Some(b"UYVY"), core_video: &[cv(b"2vuy")], mf: mf(b"UYVY") },
+    Names { format: PixelFormat::Nv12, v4l2: Some(b"NV12"), core_video: &[cv(b"420v"), cv(b"420f")], mf: mf(b"NV12") },
+    Names { format: PixelFormat::Nv21, v4l2: Some(b"NV21"), core_video: &[], mf: mf(b"NV21") },
+    Names { format: PixelFormat::I420, v4l2: Some(b"YU12"), core_video: &[cv(b"y420"), cv(b"f420")], mf: mf(b"I420") },
+    Names { format: PixelFormat::I420, v4l2: None, core_video: &[], mf: mf(b"IYUV") },
+    Names { format: PixelFormat::P010, v4l2: Some(b"P010"), core_video: &[cv(b"x420"), cv(b"xf20")], mf: mf(b"P010") },
+    Names { format: PixelFormat::Gray8, v4l2: Some(b"GREY"), core_video: &[cv(b"L008")], mf: Some(50) },
+    Names { format: PixelFormat::Gray16, v4l2: Some(b"Y16 "), core_video: &[cv(b"L016")], mf: Some(81) },
+    Names { format: PixelFormat::Mjpeg, v4l2: Some(b"MJPG"), core_video: &[cv(b"jpeg")], mf: mf(b"MJPG") },
+    Names { format: PixelFormat::Mjpeg, v4l2: Some(b"JPEG"), core_video: &[], mf: None },
+    Names { format: PixelFormat::H264, v4l2: Some(b"H264"), core_video: &[cv(b"avc1")], mf: mf(b"H264") },
+];
+
+/// The fields after the first of every Media Foundation video subtype GUID.
+const MF_GUID_BASE: u128 = 0x0000_0010_8000_00aa_0038_9b71;
+
+impl PixelFormat {
+    fn names(self) -> &'static Names {
+        TABLE.iter().find(|names| names.format == self).unwrap()
+    }
+
+    /// From a V4L2 fourcc like `YUYV`, also the codes of DRM for the formats both share.
+    pub fn from_fourcc(fourcc: FourCC) -> Option<Self> {
+        TABLE.iter().find(|names| names.v4l2 == Some(&fourcc.0)).map(|names| names.format)
+    }
+
+    /// The V4L2 fourcc.
+    pub fn fourcc(self) -> Option<FourCC> {
+        self.names().v4l2.map(FourCC::new)
+    }
+
+    /// From a CoreVideo `OSType` like `kCVPixelFormatType_422YpCbCr8_yuvs`, or a CoreMedia codec
+    /// type.
+    pub fn from_core_video(code: u32) -> Option<Self> {
+        TABLE.iter().find(|names| names.core_video.contains(&code)).map(|names| names.format)
+    }
+
+    /// The CoreVideo pixel format type, the video range one of YUV formats.
+    pub fn core_video(self) -> Option<u32> {
+        self.names().core_video.first().copied()
+    }
+
+    /// From a Media Foundation subtype like `MFVideoFormat_YUY2`, as `GUID::to_u128`.
+    pub fn from_mf_subtype(guid: u128) -> Option<Self> {
+        if guid & (u128::MAX >> 32) != MF_GUID_BASE {
+            return None;
+        }
+        let code = (guid >> 96) as u32;
+        TABLE.iter().find(|names| names.mf == Some(code)).map(|names| names.format)
+    }
+
+    /// The Media Foundation subtype, for `GUID::from_u128`.
+    pub fn mf_subtype(self) -> Option<u128> {
+        self.names().mf.map(|code| (code as u128) << 96 | MF_GUID_BASE)
+    }
+}
+
+#[test]
+fn pixel_format_names() {
+    // MFVideoFormat_NV12 and MFVideoFormat_RGB32
+    assert_eq!(PixelFormat::Nv12.mf_subtype(), Some(0x3231564e_0000_0010_8000_00aa00389b71));
+    assert_eq!(
+        PixelFormat::from_mf_subtype(0x00000016_0000_0010_8000_00aa00389b71),
+        Some(PixelFormat::Bgrx)
+    );
+    assert_eq!(PixelFormat::from_mf_subtype(0x3231564e_0000_0000_0000_000000000000), None);
+    assert_eq!(PixelFormat::from_core_video(cv(b"420f")), Some(PixelFormat::Nv12));
+    assert_eq!(PixelFormat::from_fourcc(FourCC::new(b"JPEG")), Some(PixelFormat::Mjpeg));
+    assert_eq!(PixelFormat::I420.fourcc(), Some(FourCC::new(b"YU12")));
+    for names in TABLE {
+        let format = names.format;
+        if let Some(fourcc) = format.fourcc() {
+            assert_eq!(PixelFormat::from_fourcc(fourcc), Some(format));
+        }
+        if let Some(code) = format.core_video() {
+            assert_eq!(PixelFormat::from_core_video(code), Some(format));
+        }
+        if let Some(guid) = format.mf_subtype() {
+            assert_eq!(PixelFormat::from_mf_subtype(guid), Some(format));
+        }
+    }
+}
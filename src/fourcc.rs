@@ -0,0 +1,60 @@
+/// Four character code of a pixel format, e.g. `YUYV`, `NV12` or `BGRA`, see [`Frame::fourcc`].
+///
+/// The bytes are in reading order, whatever the byte order of the OS API which reported them.
+///
+/// [`Frame::fourcc`]: crate::Frame::fourcc
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FourCC(pub [u8; 4]);
+
+impl FourCC {
+    /// Blue, green, red and alpha bytes, the format of every frame after conversion.
+    pub const BGRA: Self = Self(*b"BGRA");
+
+    pub const fn new(code: &[u8; 4]) -> Self {
+        Self(*code)
+    }
+
+    /// From the little endian number of V4L2, DRM and Media Foundation subtypes.
+    pub const fn from_le(code: u32) -> Self {
+        Self(code.to_le_bytes())
+    }
+
+    /// From the big endian number of CoreVideo and CoreMedia.
+    pub const fn from_be(code: u32) -> Self {
+        Self(code.to_be_bytes())
+    }
+
+    /// The code as text, `"????"` if it isn't printable like some numeric CoreVideo and Direct3D
+    /// formats.
+    pub fn as_str(&self) -> &str {
+        match self.0.iter().all(|b| b.is_ascii_graphic() || *b == b' ') {
+            true => std::str::from_utf8(&self.0).unwrap_or("????"),
+            false => "????",
+        }
+    }
+}
+
+/// The text, or the big endian number in hex if it isn't printable.
+impl std::fmt::Display for FourCC {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.as_str() {
+            "????" => write!(f, "0x{:08X}", u32::from_be_bytes(self.0)),
+            code => f.write_str(code),
+        }
+    }
+}
+
+impl std::fmt::Debug for FourCC {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "FourCC({self})")
+    }
+}
+
+#[test]
+fn fourcc_text() {
+    assert_eq!(FourCC::from_le(u32::from_le_bytes(*b"YUYV")).as_str(), "YUYV");
+    assert_eq!(FourCC::from_be(0x34323076).as_str(), "420v");
+    assert_eq!(format!("{:?}", FourCC::new(b"2vuy")), "FourCC(2vuy)");
+    let numeric = FourCC::from_be(32);
+    assert_eq!((numeric.as_str(), numeric.to_string()), ("????", "0x00000020".into()));
+}
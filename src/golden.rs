@@ -0,0 +1,86 @@
+//! Golden frames for the conversions in [`crate::convert`]: raw buffers in camera pixel formats
+//! in `tests/golden` with the BGRA a floating point reference decodes from them, written by
+//! `tests/golden/generate.py`. They pin down channel order, matrix and range, so the converters
+//! can be refactored or vectorized without silently changing colors.
+//!
+//! Pixels may be off by one per channel, the converters use fixed point arithmetic. MJPEG
+//! is left to the JPEG decoders, the OS conversions on macOS and Windows are covered by `tests/camera.rs`.
+
+use crate::convert::*;
+use crate::{ColorRange, ColorSpace, YuvMatrix};
+
+const W: u32 = 16;
+const H: u32 = 8;
+
+fn fixture(file: &str) -> Vec<u8> {
+    let path = format!("{}/tests/golden/{file}", env!("CARGO_MANIFEST_DIR"));
+    std::fs::read(&path).unwrap_or_else(|err| panic!("{path}: {err}"))
+}
+
+fn color_space(matrix: YuvMatrix, range: ColorRange) -> ColorSpace {
+    ColorSpace { matrix, range, ..Default::default() }
+}
+
+fn assert_close(name: &str, bgra: &[u8], expected: &[u8], tolerance: u8) {
+    assert_eq!(bgra.len(), expected.len(), "{name}");
+    let pixels = bgra.chunks_exact(4).zip(expected.chunks_exact(4));
+    for (i, (px, expected)) in pixels.enumerate() {
+        let close = px.iter().zip(expected).all(|(a, b)| a.abs_diff(*b) <= tolerance);
+        let (x, y) = (i as u32 % W, i as u32 / W);
+        assert!(close, "{name} at {x},{y}: {px:?}, expected {expected:?}");
+    }
+}
+
+/// Converts `<name>.raw` and compares with `<name>.bgra`.
+fn assert_golden(name: &str, convert: impl Fn(&[u8], &mut Vec<u8>)) {
+    let mut bgra = vec![1; 3];
+    convert(&fixture(&format!("{name}.raw")), &mut bgra);
+    assert_close(name, &bgra, &fixture(&format!("{name}.bgra")), 1);
+}
+
+#[test]
+fn golden_packed_422() {
+    let cs = color_space(YuvMatrix::Bt601, ColorRange::Limited);
+    assert_golden("yuyv_bt601_limited", |raw, bgra| {
+        yuyv_to_bgra(raw, W, H, W as usize * 2, cs, bgra)
+    });
+    let cs = color_space(YuvMatrix::Bt709, ColorRange::Limited);
+    assert_golden("uyvy_bt709_limited", |raw, bgra| {
+        uyvy_to_bgra(raw, W, H, W as usize * 2, cs, bgra)
+    });
+}
+
+#[test]
+fn golden_nv12() {
+    let cs = color_space(YuvMatrix::Bt601, ColorRange::Full);
+    assert_golden("nv12_bt601_full", |raw, bgra| nv12_to_bgra(raw, W, H, W as usize, cs, bgra));
+    let cs = color_space(YuvMatrix::Bt709, ColorRange::Limited);
+    assert_golden("nv12_bt709_limited", |raw, bgra| nv12_to_bgra(raw, W, H, W as usize, cs, bgra));
+}
+
+#[test]
+fn golden_planar_420() {
+    let cs = color_space(YuvMatrix::Bt2020, ColorRange::Full);
+    assert_golden("i420_bt2020_full", |raw, bgra| {
+        let (y, chroma) = raw.split_at((W * H) as usize);
+        let (u, v) = chroma.split_at(chroma.len() / 2);
+        planar_yuv_to_bgra([y, u, v], W, H, (1, 1), cs, bgra)
+    });
+}
+
+#[test]
+fn golden_rgb_and_gray() {
+    assert_golden("rgb24", |raw, bgra| rgb24_to_bgra(raw, W, H, W as usize * 3, bgra));
+    assert_golden("gray", |raw, bgra| gray_to_bgra(raw, W, H, W as usize, bgra));
+}
+
+/// BGRA to YUV 4:4:4 as recorded and back as played, a few levels off after quantizing twice.
+#[test]
+fn golden_yuv444_round_trip() {
+    let expected = fixture("rgb24.bgra");
+    let planes = bgra_to_yuv444(&expected, W, H);
+    let mut bgra = Vec::new();
+    let cs = color_space(YuvMatrix::Bt601, ColorRange::Limited);
+    planar_yuv_to_bgra(planes.each_ref().map(|p| &p[..]), W, H, (0, 0), cs, &mut bgra);
+    assert_close("yuv444 round trip", &bgra, &expected, 3);
+}
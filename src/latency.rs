@@ -0,0 +1,48 @@
+/// How fresh frames are against how many of them arrive, see
+/// [`Camera::set_latency_mode`](crate::Camera::set_latency_mode).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Latency {
+    /// Always the newest frame and as few buffers as possible, frames which aren't picked up
+    /// in time are dropped. For video calls.
+    Lowest,
+    /// What the [`CameraBuilder`](crate::CameraBuilder) set up (the default).
+    #[default]
+    Balanced,
+    /// More buffers and no dropping of late frames, at the cost of latency. For recording.
+    Quality,
+}
+
+impl Latency {
+    /// Driver buffers on Linux, for `configured` ones of the builder.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    pub(crate) fn buffer_count(self, configured: u32) -> u32 {
+        match self {
+            Latency::Lowest => configured.min(2),
+            Latency::Balanced => configured,
+            Latency::Quality => configured.max(8),
+        }
+    }
+
+    /// Unread frames kept on macOS and whether late ones are discarded, for the `configured`
+    /// ones of the builder.
+    #[cfg_attr(not(any(target_os = "macos", target_os = "ios")), allow(dead_code))]
+    pub(crate) fn frame_queue(self, configured: (usize, bool)) -> (usize, bool) {
+        match self {
+            Latency::Lowest => (1, true),
+            Latency::Balanced => configured,
+            Latency::Quality => (configured.0.max(4), false),
+        }
+    }
+}
+
+#[test]
+fn latency_settings() {
+    assert_eq!(Latency::Lowest.buffer_count(4), 2);
+    assert_eq!(Latency::Lowest.buffer_count(1), 1);
+    assert_eq!(Latency::Balanced.buffer_count(4), 4);
+    assert_eq!(Latency::Quality.buffer_count(4), 8);
+    assert_eq!(Latency::Lowest.frame_queue((3, false)), (1, true));
+    assert_eq!(Latency::Balanced.frame_queue((3, false)), (3, false));
+    assert_eq!(Latency::Quality.frame_queue((1, true)), (4, false));
+}
@@ -1,11 +1,151 @@
+mod blit;
+mod broadcast;
+mod builder;
+mod cadence;
 mod camera;
+mod cancel;
+mod capabilities;
+mod clock;
+mod color;
+mod config;
+/// Public for the benchmarks, not part of the API.
+#[doc(hidden)]
+pub mod convert;
+mod decoder;
+mod depth;
+mod details;
+mod device_policy;
+mod enhancement;
+mod error;
+mod event_bus;
+mod fault;
+mod fourcc;
+#[cfg(test)]
+mod golden;
+mod latency;
+mod linux_backend;
+#[cfg_attr(any(target_os = "macos", target_os = "ios"), allow(dead_code))]
+mod memory;
+mod metadata;
+mod motion;
+mod open_policy;
+mod orientation;
+mod perf;
+mod photo;
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+mod pool;
+mod power;
+mod preset;
+mod priority;
+mod profile;
+mod ptz;
+mod rate_limit;
+mod rect;
+mod source;
+mod sync;
+mod test_pattern;
+mod time;
+mod validation;
+mod watchdog;
+mod win_backend;
+pub use blit::*;
+pub use broadcast::*;
+pub use builder::*;
+pub use cadence::*;
 pub use camera::*;
+pub use cancel::*;
+pub use capabilities::*;
+pub use clock::*;
+pub use color::*;
+pub use config::*;
+pub use decoder::*;
+pub use depth::*;
+pub use details::*;
+pub use device_policy::*;
+pub use enhancement::*;
+pub use error::*;
+pub use event_bus::*;
+pub use fault::*;
+pub use fourcc::*;
+pub use latency::*;
+pub use linux_backend::*;
+pub use metadata::*;
+pub use motion::*;
+pub use open_policy::*;
+pub use orientation::*;
+pub use perf::*;
+pub use photo::*;
+pub use preset::*;
+pub use priority::*;
+pub use profile::*;
+pub use ptz::*;
+pub use rect::*;
+pub use source::*;
+pub use win_backend::*;
 
-#[cfg(target_os = "macos")]
+#[cfg(feature = "gstreamer")]
+pub mod appsrc;
+#[cfg(feature = "bevy")]
+pub mod bevy;
+pub mod compose;
+#[cfg(feature = "draw")]
+pub mod draw;
+#[cfg(feature = "egui")]
+pub mod egui;
+#[cfg(feature = "ffmpeg")]
+pub mod ffmpeg;
+pub mod format;
+#[cfg(feature = "playback")]
+pub mod playback;
+#[cfg(feature = "record")]
+pub mod record;
+#[cfg(feature = "rtsp")]
+pub mod rtsp;
+pub mod scale;
+#[cfg(feature = "screen")]
+pub mod screen;
+pub mod shm;
+
+// the crates of the platform objects, see `Camera::as_v4l2_device` and the like
+#[cfg(all(any(target_os = "macos", target_os = "ios"), feature = "raw"))]
+pub use objc2;
+#[cfg(all(target_os = "linux", feature = "raw"))]
+pub use v4l;
+#[cfg(all(target_os = "windows", feature = "raw"))]
+pub use windows;
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub(crate) mod mac_avf;
 
-#[cfg(target_os = "windows")]
+// public only for its `raw` module
+#[cfg(all(target_os = "windows", feature = "raw"))]
+pub mod win_mf;
+#[cfg(all(target_os = "windows", not(feature = "raw")))]
 pub(crate) mod win_mf;
 
+#[cfg(all(target_os = "windows", feature = "dshow"))]
+pub(crate) mod win_dshow;
+
 #[cfg(target_os = "linux")]
 pub(crate) mod linux_v4l2;
+
+#[cfg(target_os = "android")]
+pub(crate) mod android_ndk;
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub(crate) mod web_media;
+
+#[cfg(all(target_os = "linux", feature = "libcamera"))]
+pub(crate) mod linux_libcamera;
+
+#[cfg(all(target_os = "linux", feature = "pipewire"))]
+pub(crate) mod linux_pipewire;
+
+#[cfg(all(target_os = "macos", feature = "screen"))]
+pub(crate) mod mac_screen;
+
+#[cfg(all(target_os = "windows", feature = "screen"))]
+pub(crate) mod win_screen;
+
+#[cfg(all(target_os = "linux", feature = "screen"))]
+pub(crate) mod linux_screen;
@@ -0,0 +1,13 @@
+/// How the Linux backend captures, see
+/// [`CameraBuilder::linux_backend`](crate::CameraBuilder::linux_backend).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LinuxBackend {
+    /// Plain V4L2 (the default). Falls back to libcamera with the `libcamera` feature when
+    /// there is no V4L2 camera.
+    #[default]
+    V4l2,
+    /// libcamera, for cameras behind an ISP like IPU6 whose V4L2 nodes deliver raw sensor
+    /// data. Needs the `libcamera` feature, [`Error::Unsupported`](crate::Error::Unsupported)
+    /// without it.
+    Libcamera,
+}
@@ -0,0 +1,226 @@
+//! Capture through libcamera, for cameras behind an ISP like IPU6 or Snapdragon which don't
+//! deliver usable frames via plain V4L2. Enabled with the `libcamera` feature.
+//!
+//! libcamera objects borrow their camera manager, so a capture thread owns all of them and
+//! sends converted frames back. The camera comes from the device policy of the builder,
+//! `device_list` and `set_device` still go through V4L2.
+
+use std::sync::mpsc::*;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use libcamera::{
+    camera::CameraConfigurationStatus,
+    camera_manager::CameraManager,
+    framebuffer_allocator::{FrameBuffer, FrameBufferAllocator},
+    framebuffer_map::MemoryMappedFrameBuffer,
+    geometry::Size,
+    pixel_format::PixelFormat,
+    properties,
+    request::{RequestStatus, ReuseFlag},
+    stream::StreamRole,
+};
+
+use crate::device_policy::{self, Placement};
+use crate::sync::lock;
+use crate::{
+    CameraBuilder, CameraDevice, CameraEvent, CaptureFormat, DeviceKind, Error, FrameSource,
+    OwnedFrame,
+};
+
+/// DRM fourcc of 32 bit BGRX in memory, what the facade hands out anyway.
+const XRGB8888: &[u8; 4] = b"XR24";
+
+#[derive(Debug)]
+enum Command {
+    Start,
+    Stop,
+    Events(Sender<CameraEvent>),
+}
+
+#[derive(Debug)]
+pub(crate) struct LibCamera {
+    device: CameraDevice,
+    format: CaptureFormat,
+    commands: Mutex<Sender<Command>>,
+    frames: Mutex<Receiver<OwnedFrame>>,
+}
+
+impl LibCamera {
+    /// Opens the camera the device policy of `builder` picks among the ones libcamera knows,
+    /// [`Error::Unsupported`] if it can't deliver BGRA.
+    pub(crate) fn open(builder: &CameraBuilder) -> Result<Self, Error> {
+        let (setup_tx, setup_rx) = channel();
+        let (command_tx, command_rx) = channel();
+        let (frame_tx, frame_rx) = sync_channel(1);
+        let builder = builder.clone();
+        std::thread::spawn(move || run(&builder, setup_tx, command_rx, frame_tx));
+        let setup = setup_rx.recv().map_err(|_| Error::Other("capture thread died".into()))?;
+        let (device, format) = setup?;
+        Ok(Self { device, format, commands: Mutex::new(command_tx), frames: Mutex::new(frame_rx) })
+    }
+}
+
+impl FrameSource for LibCamera {
+    fn start(&self) {
+        let _ = lock(&self.commands).send(Command::Start);
+    }
+
+    fn stop(&self) {
+        let _ = lock(&self.commands).send(Command::Stop);
+    }
+
+    fn wait_for_frame(&self) -> Option<OwnedFrame> {
+        lock(&self.frames).recv_timeout(Duration::from_secs(3)).ok()
+    }
+
+    fn try_next_frame(&self) -> Option<OwnedFrame> {
+        lock(&self.frames).try_recv().ok()
+    }
+
+    fn device(&self) -> CameraDevice {
+        self.device.clone()
+    }
+
+    fn current_format(&self) -> Option<CaptureFormat> {
+        Some(self.format.clone())
+    }
+
+    /// Gets [`CameraEvent::DeviceLost`] when the camera is unplugged while running.
+    fn connect_events(&self, events: Sender<CameraEvent>) {
+        let _ = lock(&self.commands).send(Command::Events(events));
+    }
+}
+
+type Setup = Result<(CameraDevice, CaptureFormat), Error>;
+
+fn run(
+    builder: &CameraBuilder,
+    setup: Sender<Setup>,
+    commands: Receiver<Command>,
+    frames: SyncSender<OwnedFrame>,
+) {
+    let fail = |err: Error| {
+        let _ = setup.send(Err(err));
+    };
+    let other = |message: &str| Error::Other(message.to_string());
+    let Ok(manager) = CameraManager::new() else { return fail(other("no camera manager")) };
+    let cameras = manager.cameras();
+    let cameras: Vec<_> = (0..cameras.len()).filter_map(|i| cameras.get(i)).collect();
+    let devices = cameras.iter().map(|camera| {
+        let name = camera.properties().get::<properties::Model>().map(|m| m.to_string());
+        let name = name.unwrap_or(camera.id().to_string());
+        (CameraDevice::new(camera.id(), name, DeviceKind::Physical), Placement::Unknown)
+    });
+    let Some(device) = device_policy::pick(devices.collect(), &builder.device_policy) else {
+        return fail(Error::NoDevice);
+    };
+    let camera = cameras.iter().find(|camera| camera.id() == device.id).unwrap();
+    let Ok(mut camera) = camera.acquire() else { return fail(Error::InUseByOtherApp) };
+
+    let Some(mut config) = camera.generate_configuration(&[StreamRole::ViewFinder]) else {
+        return fail(other("no viewfinder configuration"));
+    };
+    let xrgb = PixelFormat::new(u32::from_le_bytes(*XRGB8888), 0);
+    config.get_mut(0).unwrap().set_pixel_format(xrgb);
+    if matches!(config.validate(), CameraConfigurationStatus::Invalid)
+        || config.get(0).unwrap().get_pixel_format() != xrgb
+    {
+        return fail(Error::Unsupported);
+    }
+    if camera.configure(&mut config).is_err() {
+        return fail(other("configure failed"));
+    }
+    let stream_config = config.get(0).unwrap();
+    let Size { width, height } = stream_config.get_size();
+    let stride = stream_config.get_stride() as usize;
+    let Some(stream) = stream_config.stream() else { return fail(other("no stream")) };
+
+    let mut allocator = FrameBufferAllocator::new(&camera);
+    let Ok(buffers) = allocator.alloc(&stream) else {
+        return fail(other("buffer allocation failed"));
+    };
+    let requests: Vec<_> = buffers
+        .into_iter()
+        .take(builder.buffer_count.max(1) as usize)
+        .filter_map(|buffer| {
+            let buffer = MemoryMappedFrameBuffer::new(buffer).ok()?;
+            let mut request = camera.create_request(None)?;
+            request.add_buffer(&stream, buffer).ok()?;
+            Some(request)
+        })
+        .collect();
+    let (completed_tx, completed_rx) = channel();
+    camera.on_request_completed(move |request| {
+        let _ = completed_tx.send(request);
+    });
+
+    let format = CaptureFormat {
+        pixel_format: String::from_utf8_lossy(XRGB8888).into_owned(),
+        width,
+        height,
+        min_fps: 0.0,
+        max_fps: 0.0,
+    };
+    let _ = setup.send(Ok((device, format)));
+
+    let mut requests = Some(requests);
+    let mut events = None;
+    let mut running = false;
+    loop {
+        let command = if running {
+            match commands.try_recv() {
+                Ok(command) => Some(command),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => break,
+            }
+        } else {
+            let Ok(command) = commands.recv() else { break };
+            Some(command)
+        };
+        match command {
+            Some(Command::Start) if !running => {
+                running = camera.start(None).is_ok();
+                // requests come back through `completed_rx` and get queued again from there
+                for request in requests.take().into_iter().flatten() {
+                    let _ = camera.queue_request(request);
+                }
+            }
+            Some(Command::Stop) if running => {
+                // stopping cancels queued requests, which complete and get requeued on start
+                let _ = camera.stop();
+                running = false;
+            }
+            Some(Command::Events(sender)) => events = Some(sender),
+            _ => {}
+        }
+        if !running {
+            continue;
+        }
+        let mut request = match completed_rx.recv_timeout(Duration::from_millis(100)) {
+            Ok(request) => request,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        let buffer: &MemoryMappedFrameBuffer<FrameBuffer> = request.buffer(&stream).unwrap();
+        let data = buffer.data();
+        if let (RequestStatus::Complete, Some(data)) = (request.status(), data.first()) {
+            let mut bgra = data.to_vec();
+            // the X byte is undefined, frames are opaque
+            bgra.chunks_exact_mut(4).for_each(|px| px[3] = 255);
+            let _ = frames.try_send(OwnedFrame::with_stride(bgra, width, height, stride));
+        }
+        request.reuse(ReuseFlag::REUSE_BUFFERS);
+        // fails once the camera is unplugged, then no more requests complete
+        if camera.queue_request(request).is_err() {
+            if let Some(events) = &events {
+                let _ = events.send(CameraEvent::DeviceLost);
+            }
+            let _ = camera.stop();
+            running = false;
+        }
+    }
+    if running {
+        let _ = camera.stop();
+    }
+}
@@ -0,0 +1,374 @@
+//! Capture through the Camera portal of xdg-desktop-portal and PipeWire, for sandboxes like
+//! Flatpak and Snap which don't give access to `/dev/video*`. Enabled with the `pipewire`
+//! feature.
+//!
+//! Like with [`screen`](crate::screen) a capture thread owns the portal and the stream, which
+//! aren't `Send`, and sends converted frames back. Only the first camera of the portal is used.
+
+use std::cell::RefCell;
+use std::os::fd::OwnedFd;
+use std::rc::Rc;
+use std::sync::mpsc::*;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use ashpd::desktop::camera::Camera as CameraPortal;
+use pipewire as pw;
+use pollster::block_on;
+use pw::spa::param::format::{FormatProperties, MediaSubtype, MediaType};
+use pw::spa::param::video::{VideoFormat, VideoInfoRaw};
+use pw::spa::param::ParamType;
+use pw::spa::pod::{serialize::PodSerializer, Pod, Value};
+use pw::spa::utils::{Direction, Fraction, Rectangle, SpaTypes};
+use pw::stream::{Stream, StreamFlags, StreamState};
+use pw::types::ObjectType;
+
+use crate::convert::{nv12_to_bgra, yuyv_to_bgra};
+use crate::sync::lock;
+use crate::{
+    CameraDevice, CameraEvent, CaptureFormat, ColorSpace, DeviceKind, Error, FrameSource,
+    OwnedFrame,
+};
+
+/// Whether the process runs in a Flatpak or Snap sandbox, where cameras are only reachable
+/// through the portal.
+pub(crate) fn sandboxed() -> bool {
+    std::path::Path::new("/.flatpak-info").exists() || std::env::var_os("SNAP").is_some()
+}
+
+#[derive(Debug)]
+enum Command {
+    Start,
+    Stop,
+    Events(Sender<CameraEvent>),
+    Quit,
+}
+
+#[derive(Debug)]
+pub(crate) struct PipewireCamera {
+    device: CameraDevice,
+    format: Arc<Mutex<CaptureFormat>>,
+    commands: Mutex<pw::channel::Sender<Command>>,
+    frames: Mutex<Receiver<Option<OwnedFrame>>>,
+}
+
+impl PipewireCamera {
+    /// Asks the portal for camera access, which shows a dialog the first time. `NoDevice` if the
+    /// portal knows no camera, denying access fails with an `Os` error.
+    pub(crate) fn open() -> Result<Self, Error> {
+        let (setup_tx, setup_rx) = channel();
+        let (command_tx, command_rx) = pw::channel::channel();
+        let (frame_tx, frame_rx) = sync_channel(1);
+        std::thread::spawn(move || run(setup_tx, command_rx, frame_tx));
+        let (device, format) = setup_rx.recv().map_err(|_| thread_died())??;
+        Ok(Self { device, format, commands: Mutex::new(command_tx), frames: Mutex::new(frame_rx) })
+    }
+
+    fn send(&self, command: Command) {
+        let _ = lock(&self.commands).send(command);
+    }
+}
+
+impl Drop for PipewireCamera {
+    fn drop(&mut self) {
+        self.send(Command::Quit);
+    }
+}
+
+impl FrameSource for PipewireCamera {
+    fn start(&self) {
+        self.send(Command::Start);
+    }
+
+    /// Wakes up a waiting reader, which gets `None`.
+    fn stop(&self) {
+        self.send(Command::Stop);
+    }
+
+    fn wait_for_frame(&self) -> Option<OwnedFrame> {
+        lock(&self.frames).recv_timeout(Duration::from_secs(3)).ok().flatten()
+    }
+
+    fn try_next_frame(&self) -> Option<OwnedFrame> {
+        lock(&self.frames).try_recv().ok().flatten()
+    }
+
+    fn device(&self) -> CameraDevice {
+        self.device.clone()
+    }
+
+    fn current_format(&self) -> Option<CaptureFormat> {
+        Some(lock(&self.format).clone())
+    }
+
+    /// Gets [`CameraEvent::DeviceLost`] when the stream ends, e.g. when the camera is unplugged.
+    fn connect_events(&self, events: Sender<CameraEvent>) {
+        self.send(Command::Events(events));
+    }
+}
+
+type Setup = Result<(CameraDevice, Arc<Mutex<CaptureFormat>>), Error>;
+
+fn thread_died() -> Error {
+    Error::Other("camera capture thread died".into())
+}
+
+fn run(
+    setup_tx: Sender<Setup>,
+    commands: pw::channel::Receiver<Command>,
+    frame_tx: SyncSender<Option<OwnedFrame>>,
+) {
+    let fd = match block_on(open_remote()) {
+        Ok(Some(fd)) => fd,
+        Ok(None) => {
+            let _ = setup_tx.send(Err(Error::NoDevice));
+            return;
+        }
+        Err(err) => {
+            let _ = setup_tx.send(Err(Error::os(err)));
+            return;
+        }
+    };
+    if let Err(err) = stream(fd, &setup_tx, commands, frame_tx) {
+        // after a successful setup nobody listens anymore
+        let _ = setup_tx.send(Err(Error::os(err)));
+    }
+}
+
+/// The PipeWire remote which only shows the cameras, `None` without any.
+async fn open_remote() -> ashpd::Result<Option<OwnedFd>> {
+    let portal = CameraPortal::new().await?;
+    if !portal.is_present().await? {
+        return Ok(None);
+    }
+    portal.request_access().await?.response()?;
+    Ok(Some(portal.open_pipe_wire_remote().await?))
+}
+
+/// Node id and description of the first camera, after a roundtrip through the registry.
+fn first_camera(
+    main_loop: &pw::main_loop::MainLoop,
+    core: &pw::core::Core,
+) -> Result<Option<(u32, String)>, pw::Error> {
+    let cameras = Rc::new(RefCell::new(Vec::new()));
+    let registry = core.get_registry()?;
+    let found = cameras.clone();
+    let _registry = registry
+        .add_listener_local()
+        .global(move |global| {
+            let Some(props) = global.props.filter(|_| global.type_ == ObjectType::Node) else {
+                return;
+            };
+            if props.get(*pw::keys::MEDIA_CLASS) != Some("Video/Source") {
+                return;
+            }
+            let name = props.get(*pw::keys::NODE_DESCRIPTION).or(props.get(*pw::keys::NODE_NAME));
+            found.borrow_mut().push((global.id, name.unwrap_or("Camera").to_string()));
+        })
+        .register();
+    let pending = core.sync(0)?;
+    let done_loop = main_loop.clone();
+    let _core = core
+        .add_listener_local()
+        .done(move |id, seq| {
+            if id == pw::core::PW_ID_CORE && seq == pending {
+                done_loop.quit();
+            }
+        })
+        .register();
+    main_loop.run();
+    let first = cameras.borrow().first().cloned();
+    Ok(first)
+}
+
+/// Runs the PipeWire loop until [`Command::Quit`].
+fn stream(
+    fd: OwnedFd,
+    setup_tx: &Sender<Setup>,
+    commands: pw::channel::Receiver<Command>,
+    frame_tx: SyncSender<Option<OwnedFrame>>,
+) -> Result<(), pw::Error> {
+    pw::init();
+    let main_loop = pw::main_loop::MainLoop::new(None)?;
+    let context = pw::context::Context::new(&main_loop)?;
+    let core = context.connect_fd(fd, None)?;
+    let Some((node_id, name)) = first_camera(&main_loop, &core)? else {
+        let _ = setup_tx.send(Err(Error::NoDevice));
+        return Ok(());
+    };
+    let properties = pw::properties::properties! {
+        *pw::keys::MEDIA_TYPE => "Video",
+        *pw::keys::MEDIA_CATEGORY => "Capture",
+        *pw::keys::MEDIA_ROLE => "Camera",
+    };
+    let stream = Rc::new(Stream::new(&core, "kamera", properties)?);
+
+    let format = Arc::new(Mutex::new(CaptureFormat {
+        pixel_format: String::new(),
+        width: 0,
+        height: 0,
+        min_fps: 0.0,
+        max_fps: 0.0,
+    }));
+    let negotiated = format.clone();
+    let stop_tx = frame_tx.clone();
+    let events = Rc::new(RefCell::new(None::<Sender<CameraEvent>>));
+    let lost_events = events.clone();
+    let _listener = stream
+        .add_local_listener_with_user_data(VideoInfoRaw::new())
+        .state_changed(move |_, _, _, state| {
+            // the camera was unplugged or access through the portal was revoked
+            if let StreamState::Error(_) | StreamState::Unconnected = state {
+                if let Some(events) = &*lost_events.borrow() {
+                    let _ = events.send(CameraEvent::DeviceLost);
+                }
+            }
+        })
+        .param_changed(move |_, info, id, param| {
+            if let Some(param) = param.filter(|_| id == ParamType::Format.as_raw()) {
+                if info.parse(param).is_ok() {
+                    let Rectangle { width, height } = info.size();
+                    let Fraction { num, denom } = info.framerate();
+                    let fps = if denom == 0 { 0.0 } else { num as f64 / denom as f64 };
+                    *lock(&negotiated) = CaptureFormat {
+                        pixel_format: fourcc(info.format()).to_string(),
+                        width,
+                        height,
+                        min_fps: fps,
+                        max_fps: fps,
+                    };
+                }
+            }
+        })
+        .process(move |stream, info| {
+            if let Some(frame) = stream.dequeue_buffer().and_then(|mut buffer| {
+                let data = buffer.datas_mut().first_mut()?;
+                let (offset, len) = (data.chunk().offset() as usize, data.chunk().size() as usize);
+                let bytes = data.data()?.get(offset..offset + len)?;
+                let stride = data.chunk().stride().max(0) as usize;
+                let Rectangle { width, height } = info.size();
+                to_frame(bytes, info.format(), width, height, stride)
+            }) {
+                // drop frames nobody waits for, the next one is more recent anyway
+                let _ = frame_tx.try_send(Some(frame));
+            }
+        })
+        .register()?;
+
+    let params = format_params();
+    let mut params = [Pod::from_bytes(&params).unwrap()];
+    let flags = StreamFlags::AUTOCONNECT | StreamFlags::MAP_BUFFERS | StreamFlags::INACTIVE;
+    stream.connect(Direction::Input, Some(node_id), flags, &mut params)?;
+
+    let quit_loop = main_loop.clone();
+    let command_stream = stream.clone();
+    let _commands = commands.attach(main_loop.loop_(), move |command| match command {
+        Command::Start => drop(command_stream.set_active(true)),
+        Command::Stop => {
+            let _ = command_stream.set_active(false);
+            let _ = stop_tx.try_send(None);
+        }
+        Command::Events(sender) => *events.borrow_mut() = Some(sender),
+        Command::Quit => quit_loop.quit(),
+    });
+    let device = CameraDevice::new(format!("pipewire:{node_id}"), name, DeviceKind::Physical);
+    let _ = setup_tx.send(Ok((device, format)));
+    main_loop.run();
+    Ok(())
+}
+
+fn fourcc(format: VideoFormat) -> &'static str {
+    match format {
+        VideoFormat::YUY2 => "YUYV",
+        VideoFormat::NV12 => "NV12",
+        _ => "BGRA",
+    }
+}
+
+/// Converts to BGRA, `None` for short buffers. A `stride` of 0 means rows aren't padded.
+fn to_frame(
+    bytes: &[u8],
+    format: VideoFormat,
+    width: u32,
+    height: u32,
+    stride: usize,
+) -> Option<OwnedFrame> {
+    let (bytes_per_pixel, planes) = match format {
+        VideoFormat::YUY2 => (2, 2),
+        VideoFormat::NV12 => (1, 3),
+        _ => (4, 2),
+    };
+    let stride = if stride == 0 { width as usize * bytes_per_pixel } else { stride };
+    // NV12 has a Y row and half a UV row per row of pixels
+    if bytes.len() < stride * height as usize * planes / 2 {
+        return None;
+    }
+    let mut bgra = Vec::new();
+    match format {
+        VideoFormat::YUY2 => {
+            yuyv_to_bgra(bytes, width, height, stride, ColorSpace::default(), &mut bgra)
+        }
+        VideoFormat::NV12 => {
+            nv12_to_bgra(bytes, width, height, stride, ColorSpace::default(), &mut bgra)
+        }
+        VideoFormat::BGRx => {
+            bgra.extend_from_slice(bytes);
+            // the X byte is undefined, often 0, frames are opaque
+            bgra.chunks_exact_mut(4).for_each(|px| px[3] = 0xff);
+            return Some(OwnedFrame::with_stride(bgra, width, height, stride));
+        }
+        _ => return Some(OwnedFrame::with_stride(bytes.to_vec(), width, height, stride)),
+    }
+    Some(OwnedFrame::new(bgra, width, height))
+}
+
+/// The raw formats kamera converts, in the camera's sizes and rates. Most webcams also offer
+/// MJPG, which PipeWire passes through undecoded, so it's left out.
+fn format_params() -> Vec<u8> {
+    let object = pw::spa::pod::object!(
+        SpaTypes::ObjectParamFormat,
+        ParamType::EnumFormat,
+        pw::spa::pod::property!(FormatProperties::MediaType, Id, MediaType::Video),
+        pw::spa::pod::property!(FormatProperties::MediaSubtype, Id, MediaSubtype::Raw),
+        pw::spa::pod::property!(
+            FormatProperties::VideoFormat,
+            Choice,
+            Enum,
+            Id,
+            VideoFormat::YUY2,
+            VideoFormat::YUY2,
+            VideoFormat::NV12,
+            VideoFormat::BGRx,
+            VideoFormat::BGRA
+        ),
+        pw::spa::pod::property!(
+            FormatProperties::VideoSize,
+            Choice,
+            Range,
+            Rectangle,
+            Rectangle { width: 1280, height: 720 },
+            Rectangle { width: 1, height: 1 },
+            Rectangle { width: 8192, height: 8192 }
+        ),
+        pw::spa::pod::property!(
+            FormatProperties::VideoFramerate,
+            Choice,
+            Range,
+            Fraction,
+            Fraction { num: 30, denom: 1 },
+            Fraction { num: 0, denom: 1 },
+            Fraction { num: 1000, denom: 1 }
+        ),
+    );
+    let cursor = std::io::Cursor::new(Vec::new());
+    PodSerializer::serialize(cursor, &Value::Object(object)).unwrap().0.into_inner()
+}
+
+#[test]
+fn short_buffers_are_dropped() {
+    assert!(to_frame(&[0; 4 * 2 * 2 - 1], VideoFormat::BGRx, 2, 2, 0).is_none());
+    assert!(to_frame(&[0; 2 * 2 * 2], VideoFormat::YUY2, 2, 2, 0).is_some());
+    assert!(to_frame(&[0; 2 * 2 * 3 / 2 - 1], VideoFormat::NV12, 2, 2, 0).is_none());
+    let frame = to_frame(&[0; 2 * 2 * 3 / 2], VideoFormat::NV12, 2, 2, 0).unwrap();
+    assert_eq!(frame.size_u32(), (2, 2));
+}
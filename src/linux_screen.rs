@@ -0,0 +1,287 @@
+//! Screen capture through the ScreenCast portal of xdg-desktop-portal and PipeWire, which works
+//! on Wayland and X11. See [`screen`](crate::screen).
+//!
+//! PipeWire objects aren't `Send`, so like with libcamera a capture thread owns the portal
+//! session and the stream and sends converted frames back.
+
+use std::cell::RefCell;
+use std::os::fd::OwnedFd;
+use std::rc::Rc;
+use std::sync::mpsc::*;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use ashpd::desktop::screencast::{CursorMode, PersistMode, Screencast, SourceType};
+use ashpd::desktop::Session;
+use ashpd::WindowIdentifier;
+use pipewire as pw;
+use pollster::block_on;
+use pw::spa::param::format::{FormatProperties, MediaSubtype, MediaType};
+use pw::spa::param::video::{VideoFormat, VideoInfoRaw};
+use pw::spa::param::ParamType;
+use pw::spa::pod::{serialize::PodSerializer, Pod, Value};
+use pw::spa::utils::{Direction, Fraction, Rectangle, SpaTypes};
+use pw::stream::{Stream, StreamFlags, StreamState};
+
+use crate::sync::lock;
+use crate::{CameraDevice, CameraEvent, CaptureFormat, DeviceKind, Error, FrameSource, OwnedFrame};
+
+#[derive(Debug)]
+enum Command {
+    Start,
+    Stop,
+    Events(Sender<CameraEvent>),
+    Quit,
+}
+
+#[derive(Debug)]
+pub(crate) struct LinuxScreen {
+    node_id: u32,
+    size: Arc<Mutex<(u32, u32)>>,
+    commands: Mutex<pw::channel::Sender<Command>>,
+    frames: Mutex<Receiver<Option<OwnedFrame>>>,
+}
+
+impl LinuxScreen {
+    /// Asks the portal for a monitor, which usually shows a dialog to pick one. Cancelling it
+    /// fails with an `Os` error.
+    pub(crate) fn open() -> Result<Self, Error> {
+        let (setup_tx, setup_rx) = channel();
+        let (command_tx, command_rx) = pw::channel::channel();
+        let (frame_tx, frame_rx) = sync_channel(1);
+        std::thread::spawn(move || run(setup_tx, command_rx, frame_tx));
+        let (node_id, size) = setup_rx.recv().map_err(|_| thread_died())??;
+        Ok(Self { node_id, size, commands: Mutex::new(command_tx), frames: Mutex::new(frame_rx) })
+    }
+
+    fn send(&self, command: Command) {
+        let _ = lock(&self.commands).send(command);
+    }
+}
+
+impl Drop for LinuxScreen {
+    fn drop(&mut self) {
+        self.send(Command::Quit);
+    }
+}
+
+impl FrameSource for LinuxScreen {
+    fn start(&self) {
+        self.send(Command::Start);
+    }
+
+    /// Wakes up a waiting reader, which gets `None`.
+    fn stop(&self) {
+        self.send(Command::Stop);
+    }
+
+    /// An unchanged screen delivers no frames, this waits until it changes or capture stops.
+    fn wait_for_frame(&self) -> Option<OwnedFrame> {
+        let frames = lock(&self.frames);
+        loop {
+            match frames.recv_timeout(Duration::from_millis(100)) {
+                Ok(frame) => return frame,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => return None,
+            }
+        }
+    }
+
+    fn try_next_frame(&self) -> Option<OwnedFrame> {
+        lock(&self.frames).try_recv().ok().flatten()
+    }
+
+    fn device(&self) -> CameraDevice {
+        let id = format!("pipewire:{}", self.node_id);
+        CameraDevice::new(id, "Screen", DeviceKind::Virtual)
+    }
+
+    fn current_format(&self) -> Option<CaptureFormat> {
+        let (width, height) = *lock(&self.size);
+        let pixel_format = "BGRA".to_string();
+        Some(CaptureFormat { pixel_format, width, height, min_fps: 0.0, max_fps: 0.0 })
+    }
+
+    /// Gets [`CameraEvent::DeviceLost`] when the stream ends, e.g. when the user stops sharing.
+    fn connect_events(&self, events: Sender<CameraEvent>) {
+        self.send(Command::Events(events));
+    }
+}
+
+type Setup = Result<(u32, Arc<Mutex<(u32, u32)>>), Error>;
+
+fn thread_died() -> Error {
+    Error::Other("screen capture thread died".into())
+}
+
+fn run(
+    setup_tx: Sender<Setup>,
+    commands: pw::channel::Receiver<Command>,
+    frame_tx: SyncSender<Option<OwnedFrame>>,
+) {
+    let proxy = match block_on(Screencast::new()) {
+        Ok(proxy) => proxy,
+        Err(err) => {
+            let _ = setup_tx.send(Err(Error::os(err)));
+            return;
+        }
+    };
+    let (session, fd, node_id, size) = match block_on(open_session(&proxy)) {
+        Ok(opened) => opened,
+        Err(err) => {
+            let _ = setup_tx.send(Err(Error::os(err)));
+            return;
+        }
+    };
+    let size = Arc::new(Mutex::new(size));
+    if let Err(err) = stream(fd, node_id, size.clone(), &setup_tx, commands, frame_tx) {
+        // after a successful setup nobody listens anymore
+        let _ = setup_tx.send(Err(Error::os(err)));
+    }
+    let _ = block_on(session.close());
+}
+
+async fn open_session<'a>(
+    proxy: &Screencast<'a>,
+) -> ashpd::Result<(Session<'a>, OwnedFd, u32, (u32, u32))> {
+    let session = proxy.create_session().await?;
+    let monitor = SourceType::Monitor.into();
+    let persist = PersistMode::DoNot;
+    proxy
+        .select_sources(&session, CursorMode::Embedded, monitor, false, None, persist)
+        .await?
+        .response()?;
+    let streams = proxy.start(&session, &WindowIdentifier::default()).await?.response()?;
+    let stream = streams.streams().first().ok_or(ashpd::Error::NoResponse)?;
+    let (width, height) = stream.size().unwrap_or_default();
+    let node_id = stream.pipe_wire_node_id();
+    let fd = proxy.open_pipe_wire_remote(&session).await?;
+    Ok((session, fd, node_id, (width as u32, height as u32)))
+}
+
+/// Runs the PipeWire loop until [`Command::Quit`].
+fn stream(
+    fd: OwnedFd,
+    node_id: u32,
+    size: Arc<Mutex<(u32, u32)>>,
+    setup_tx: &Sender<Setup>,
+    commands: pw::channel::Receiver<Command>,
+    frame_tx: SyncSender<Option<OwnedFrame>>,
+) -> Result<(), pw::Error> {
+    pw::init();
+    let main_loop = pw::main_loop::MainLoop::new(None)?;
+    let context = pw::context::Context::new(&main_loop)?;
+    let core = context.connect_fd(fd, None)?;
+    let properties = pw::properties::properties! {
+        *pw::keys::MEDIA_TYPE => "Video",
+        *pw::keys::MEDIA_CATEGORY => "Capture",
+        *pw::keys::MEDIA_ROLE => "Screen",
+    };
+    let stream = Rc::new(Stream::new(&core, "kamera-screen", properties)?);
+
+    let format_size = size.clone();
+    let stop_tx = frame_tx.clone();
+    let events = Rc::new(RefCell::new(None::<Sender<CameraEvent>>));
+    let lost_events = events.clone();
+    let _listener = stream
+        .add_local_listener_with_user_data(VideoInfoRaw::new())
+        .state_changed(move |_, _, _, state| {
+            // the monitor was unplugged or the user stopped sharing it
+            if let StreamState::Error(_) | StreamState::Unconnected = state {
+                if let Some(events) = &*lost_events.borrow() {
+                    let _ = events.send(CameraEvent::DeviceLost);
+                }
+            }
+        })
+        .param_changed(move |_, format, id, param| {
+            if let Some(param) = param.filter(|_| id == ParamType::Format.as_raw()) {
+                if format.parse(param).is_ok() {
+                    let Rectangle { width, height } = format.size();
+                    *lock(&format_size) = (width, height);
+                }
+            }
+        })
+        .process(move |stream, format| {
+            if let Some(frame) = stream.dequeue_buffer().and_then(|mut buffer| {
+                let data = buffer.datas_mut().first_mut()?;
+                let (offset, len) = (data.chunk().offset() as usize, data.chunk().size() as usize);
+                let stride = data.chunk().stride().max(0) as usize;
+                let Rectangle { width, height } = format.size();
+                let stride = if stride == 0 { width as usize * 4 } else { stride };
+                // cursor only updates come with an empty chunk
+                let min_len = stride * (height as usize).saturating_sub(1) + width as usize * 4;
+                if len < min_len {
+                    return None;
+                }
+                let mut bytes = data.data()?.get(offset..offset + len)?.to_vec();
+                if format.format() == VideoFormat::BGRx {
+                    // the X byte is undefined, often 0, frames are opaque
+                    bytes.chunks_exact_mut(4).for_each(|px| px[3] = 0xff);
+                }
+                Some(OwnedFrame::with_stride(bytes, width, height, stride))
+            }) {
+                // drop frames nobody waits for, the next one is more recent anyway
+                let _ = frame_tx.try_send(Some(frame));
+            }
+        })
+        .register()?;
+
+    let params = format_params();
+    let mut params = [Pod::from_bytes(&params).unwrap()];
+    let flags = StreamFlags::AUTOCONNECT | StreamFlags::MAP_BUFFERS | StreamFlags::INACTIVE;
+    stream.connect(Direction::Input, Some(node_id), flags, &mut params)?;
+
+    let quit_loop = main_loop.clone();
+    let command_stream = stream.clone();
+    let _commands = commands.attach(main_loop.loop_(), move |command| match command {
+        Command::Start => drop(command_stream.set_active(true)),
+        Command::Stop => {
+            let _ = command_stream.set_active(false);
+            let _ = stop_tx.try_send(None);
+        }
+        Command::Events(sender) => *events.borrow_mut() = Some(sender),
+        Command::Quit => quit_loop.quit(),
+    });
+    let _ = setup_tx.send(Ok((node_id, size)));
+    main_loop.run();
+    Ok(())
+}
+
+/// BGRx or BGRA in any size and rate, what compositors usually offer.
+fn format_params() -> Vec<u8> {
+    let object = pw::spa::pod::object!(
+        SpaTypes::ObjectParamFormat,
+        ParamType::EnumFormat,
+        pw::spa::pod::property!(FormatProperties::MediaType, Id, MediaType::Video),
+        pw::spa::pod::property!(FormatProperties::MediaSubtype, Id, MediaSubtype::Raw),
+        pw::spa::pod::property!(
+            FormatProperties::VideoFormat,
+            Choice,
+            Enum,
+            Id,
+            VideoFormat::BGRx,
+            VideoFormat::BGRx,
+            VideoFormat::BGRA
+        ),
+        pw::spa::pod::property!(
+            FormatProperties::VideoSize,
+            Choice,
+            Range,
+            Rectangle,
+            Rectangle { width: 1920, height: 1080 },
+            Rectangle { width: 1, height: 1 },
+            Rectangle { width: 8192, height: 8192 }
+        ),
+        pw::spa::pod::property!(
+            FormatProperties::VideoFramerate,
+            Choice,
+            Range,
+            Fraction,
+            Fraction { num: 30, denom: 1 },
+            Fraction { num: 0, denom: 1 },
+            Fraction { num: 1000, denom: 1 }
+        ),
+    );
+    let cursor = std::io::Cursor::new(Vec::new());
+    PodSerializer::serialize(cursor, &Value::Object(object)).unwrap().0.into_inner()
+}
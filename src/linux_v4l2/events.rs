@@ -0,0 +1,149 @@
+//! V4L2 events of the device: controls which another application or a button changed, a new
+//! resolution of the source, e.g. a HDMI capture card, and the end of the stream.
+//!
+//! v4l doesn't wrap the event ioctls, the structs follow videodev2.h.
+
+use std::os::raw::{c_long, c_void};
+
+use v4l::control::Type;
+use v4l::v4l2;
+use v4l::Device;
+
+// from videodev2.h
+const EVENT_EOS: u32 = 2;
+const EVENT_CTRL: u32 = 3;
+const EVENT_SOURCE_CHANGE: u32 = 5;
+const EVENT_CTRL_CH_VALUE: u32 = 1;
+const EVENT_SRC_CH_RESOLUTION: u32 = 1;
+const CTRL_TYPE_INTEGER64: u32 = 5;
+
+const VIDIOC_DQEVENT: u64 = ioc(2, 89, std::mem::size_of::<Event>());
+const VIDIOC_SUBSCRIBE_EVENT: u64 = ioc(1, 90, std::mem::size_of::<Subscription>());
+
+/// `_IOR` or `_IOW` of ioctl.h for `dir` 2 or 1.
+const fn ioc(dir: u64, nr: u64, size: usize) -> u64 {
+    dir << 30 | (size as u64) << 16 | (b'V' as u64) << 8 | nr
+}
+
+/// `struct v4l2_event_subscription`
+#[repr(C)]
+#[derive(Default)]
+struct Subscription {
+    kind: u32,
+    id: u32,
+    flags: u32,
+    reserved: [u32; 5],
+}
+
+/// `struct v4l2_event`
+#[repr(C)]
+struct Event {
+    kind: u32,
+    payload: Payload,
+    pending: u32,
+    sequence: u32,
+    timestamp: [c_long; 2],
+    id: u32,
+    reserved: [u32; 8],
+}
+
+#[repr(C)]
+union Payload {
+    ctrl: ControlEvent,
+    /// `changes` of `struct v4l2_event_src_change`.
+    source_changes: u32,
+    data: [u8; 64],
+}
+
+/// `struct v4l2_event_ctrl`
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct ControlEvent {
+    changes: u32,
+    kind: u32,
+    value: ControlValue,
+    flags: u32,
+    minimum: i32,
+    maximum: i32,
+    step: i32,
+    default_value: i32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+union ControlValue {
+    value: i32,
+    value64: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum DeviceEvent {
+    ControlChanged { id: u32, value: i64 },
+    ResolutionChanged,
+    EndOfStream,
+}
+
+fn subscribe_one(device: &Device, kind: u32, id: u32) {
+    let mut subscription = Subscription { kind, id, ..Default::default() };
+    let request = VIDIOC_SUBSCRIBE_EVENT as _;
+    let arg = &mut subscription as *mut _ as *mut c_void;
+    // drivers without events refuse, they then never send any
+    let _ = unsafe { v4l2::ioctl(device.handle().fd(), request, arg) };
+}
+
+/// Subscribes to the changes of every control and to source changes and the end of stream.
+/// Changes kamera makes itself come from the same file handle and send no event.
+pub(super) fn subscribe(device: &Device) {
+    for control in device.query_controls().unwrap_or_default() {
+        if control.typ != Type::CtrlClass {
+            subscribe_one(device, EVENT_CTRL, control.id);
+        }
+    }
+    subscribe_one(device, EVENT_SOURCE_CHANGE, 0);
+    subscribe_one(device, EVENT_EOS, 0);
+}
+
+/// The events which arrived since the last call, without waiting for more.
+pub(super) fn dequeue(device: &Device) -> Vec<DeviceEvent> {
+    let mut events = Vec::new();
+    loop {
+        // fails with ENOENT once no event is pending, the device is opened non-blocking
+        let mut event: Event = unsafe { std::mem::zeroed() };
+        let arg = &mut event as *mut _ as *mut c_void;
+        if unsafe { v4l2::ioctl(device.handle().fd(), VIDIOC_DQEVENT as _, arg) }.is_err() {
+            return events;
+        }
+        let event = match event.kind {
+            EVENT_CTRL => {
+                let ctrl = unsafe { event.payload.ctrl };
+                if ctrl.changes & EVENT_CTRL_CH_VALUE == 0 {
+                    continue;
+                }
+                let value = match ctrl.kind {
+                    CTRL_TYPE_INTEGER64 => unsafe { ctrl.value.value64 },
+                    _ => unsafe { ctrl.value.value as i64 },
+                };
+                DeviceEvent::ControlChanged { id: event.id, value }
+            }
+            EVENT_SOURCE_CHANGE => {
+                if unsafe { event.payload.source_changes } & EVENT_SRC_CH_RESOLUTION == 0 {
+                    continue;
+                }
+                DeviceEvent::ResolutionChanged
+            }
+            EVENT_EOS => DeviceEvent::EndOfStream,
+            _ => continue,
+        };
+        events.push(event);
+    }
+}
+
+#[test]
+fn event_layout() {
+    // the sizes in the ioctl numbers of 64-bit Linux
+    if std::mem::size_of::<c_long>() == 8 {
+        assert_eq!(VIDIOC_DQEVENT, 0x80885659);
+    }
+    assert_eq!(VIDIOC_SUBSCRIBE_EVENT, 0x4020565a);
+    assert_eq!(std::mem::size_of::<ControlEvent>(), 40);
+}
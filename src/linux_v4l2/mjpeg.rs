@@ -0,0 +1,115 @@
+//! MJPEG decoding with the `mjpeg` feature. The `turbojpeg` and `zune-jpeg` features add faster
+//! decoders, which are tried first. A frame they reject, e.g. because the camera wrote a
+//! slightly broken stream, is decoded once more with the `image` crate.
+//!
+//! Decoding a 1080p frame takes several milliseconds, more than a frame lasts at 60 fps. Decode
+//! on [`CameraBuilder::pipeline_workers`](crate::CameraBuilder::pipeline_workers) to keep up.
+
+use std::cell::RefCell;
+
+use image::codecs::jpeg::JpegDecoder;
+use image::{ColorType, DynamicImage, ImageDecoder};
+
+use crate::convert::rgb24_to_bgra;
+
+/// Decodes a JPEG of `w` by `h` pixels into packed BGRA, `false` if it is broken or has another
+/// size.
+pub fn mjpeg_to_bgra(buf: &[u8], w: u32, h: u32, bgra: &mut Vec<u8>) -> bool {
+    #[cfg(feature = "turbojpeg")]
+    if turbojpeg_to_bgra(buf, w, h, bgra) {
+        return true;
+    }
+    #[cfg(feature = "zune-jpeg")]
+    if zune_to_bgra(buf, w, h, bgra) {
+        return true;
+    }
+    image_to_bgra(buf, w, h, bgra)
+}
+
+fn image_to_bgra(buf: &[u8], w: u32, h: u32, bgra: &mut Vec<u8>) -> bool {
+    thread_local! {
+        // the decoder's RGB output per pipeline worker, so only the frame pool allocates
+        static RGB: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+    }
+    let Ok(decoder) = JpegDecoder::new(std::io::Cursor::new(buf)) else { return false };
+    if decoder.dimensions() != (w, h) {
+        return false;
+    }
+    if decoder.color_type() != ColorType::Rgb8 {
+        // grayscale or CMYK, which cameras hardly send
+        let Ok(image) = DynamicImage::from_decoder(decoder) else { return false };
+        rgb24_to_bgra(image.to_rgb8().as_raw(), w, h, w as usize * 3, bgra);
+        return true;
+    }
+    RGB.with_borrow_mut(|rgb| {
+        rgb.resize(decoder.total_bytes() as usize, 0);
+        if decoder.read_image(rgb).is_err() {
+            return false;
+        }
+        rgb24_to_bgra(rgb, w, h, w as usize * 3, bgra);
+        true
+    })
+}
+
+#[cfg(feature = "zune-jpeg")]
+fn zune_to_bgra(buf: &[u8], w: u32, h: u32, bgra: &mut Vec<u8>) -> bool {
+    use zune_jpeg::zune_core::{colorspace::ColorSpace, options::DecoderOptions};
+
+    let options = DecoderOptions::default().jpeg_set_out_colorspace(ColorSpace::BGRA);
+    let mut decoder = zune_jpeg::JpegDecoder::new_with_options(buf, options);
+    if decoder.decode_headers().is_err() || decoder.dimensions() != Some((w as usize, h as usize)) {
+        return false;
+    }
+    bgra.clear();
+    bgra.resize(w as usize * h as usize * 4, 0);
+    decoder.decode_into(bgra).is_ok()
+}
+
+#[cfg(feature = "turbojpeg")]
+fn turbojpeg_to_bgra(buf: &[u8], w: u32, h: u32, bgra: &mut Vec<u8>) -> bool {
+    thread_local! {
+        // a decompressor per pipeline worker, creating one allocates
+        static DECOMPRESSOR: RefCell<Option<turbojpeg::Decompressor>> = const { RefCell::new(None) };
+    }
+    DECOMPRESSOR.with_borrow_mut(|decompressor| {
+        if decompressor.is_none() {
+            *decompressor = turbojpeg::Decompressor::new().ok();
+        }
+        let Some(decompressor) = decompressor else { return false };
+        match decompressor.read_header(buf) {
+            Ok(header) if (header.width, header.height) == (w as usize, h as usize) => {}
+            _ => return false,
+        }
+        bgra.clear();
+        bgra.resize(w as usize * h as usize * 4, 0);
+        let image = turbojpeg::Image {
+            pixels: &mut bgra[..],
+            width: w as usize,
+            pitch: w as usize * 4,
+            height: h as usize,
+            format: turbojpeg::PixelFormat::BGRA,
+        };
+        decompressor.decompress(buf, image).is_ok()
+    })
+}
+
+#[test]
+fn decode_mjpeg() {
+    use image::codecs::jpeg::JpegEncoder;
+
+    let (w, h) = (32, 16);
+    let rgb: Vec<u8> = [200, 100, 50].repeat(w as usize * h as usize);
+    let mut jpeg = vec![];
+    JpegEncoder::new_with_quality(&mut jpeg, 95)
+        .encode(&rgb, w, h, image::ExtendedColorType::Rgb8)
+        .unwrap();
+
+    let mut bgra = vec![];
+    assert!(mjpeg_to_bgra(&jpeg, w, h, &mut bgra));
+    assert_eq!(bgra.len(), w as usize * h as usize * 4);
+    for (pixel, expected) in bgra[..4].iter().zip([50, 100, 200, 255]) {
+        assert!(pixel.abs_diff(expected) <= 3, "{:?}", &bgra[..4]);
+    }
+    assert!(!mjpeg_to_bgra(&jpeg, w, h * 2, &mut bgra));
+    assert!(!mjpeg_to_bgra(&jpeg[..jpeg.len() / 2], w, h, &mut bgra));
+}
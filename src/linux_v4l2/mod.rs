@@ -1,41 +1,149 @@
-use ffimage::color::Bgra;
+mod events;
+#[cfg(feature = "mjpeg")]
+pub(crate) mod mjpeg;
+mod pipeline;
+use events::DeviceEvent;
+use pipeline::Pipeline;
 
 use v4l::context::Node;
 use v4l::io::traits::CaptureStream;
 
+use v4l::format::{Colorspace, FieldOrder, Quantization};
+use v4l::frameinterval::FrameIntervalEnum;
+use v4l::framesize::FrameSizeEnum;
+use v4l::video::capture::Parameters;
 use v4l::video::Capture;
 use v4l::*;
 
-use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    mpsc::{channel, Receiver, Sender},
+    Arc, Mutex, RwLock,
+};
+use std::time::{Duration, Instant};
 
-use std::sync::RwLock;
+use crate::config::{RequestedConfig, SizeRequest};
+use crate::convert::{
+    bayer_to_bgra, gray_to_bgra, luma_plane, narrow_u16, nv12_to_bgra, packed_422_chroma,
+    rgb24_to_bgra, unpack_p010, unpack_u16, uyvy_to_bgra, weave_fields, yuyv_to_bgra, BayerPattern,
+};
+use crate::decoder::Decoders;
+use crate::details::bcd_version;
+use crate::device_policy::{self, Placement};
+use crate::format::PixelFormat;
+use crate::memory::{MemoryBudget, Reservation};
+use crate::pool::FramePool;
+use crate::priority::PriorityRequest;
+use crate::rate_limit::FrameRateLimit;
+use crate::sync::{lock, read, write};
+use crate::validation::FrameValidation;
+use crate::{
+    BackendCapabilities, CameraBuilder, CameraDevice, CameraEvent, CaptureFormat, CaptureMetadata,
+    ColorRange, ColorSpace, ConfigMismatch, DepthFrame, DeviceCapabilities, DeviceDetails,
+    DeviceKind, DeviceType, Enhancement, EnumError, Error, FaceRect, FrameReadyFd, InnerCamera,
+    Latency, MetadataKind, PlaneView, PtzAxis, PtzRange, ThreadPriority, TransferFunction,
+    VideoOrientation, YuvMatrix,
+};
 
-use crate::{InnerCamera, CameraDevice};
+/// How long a blocking wait dequeues before it gives up with `None`, so a stream which stopped
+/// delivering, e.g. over system sleep, can be restarted. Longer than webcams take for their
+/// first frame or a frame lasts in the dark.
+pub(crate) const DEQUEUE_TIMEOUT: Duration = Duration::from_secs(5);
 
 pub struct Camera {
     device: RwLock<v4l::Device>,
     device_path: String,
     device_name: Option<String>,
     stream: RwLock<Option<v4l::io::mmap::Stream<'static>>>,
+    events: Receiver<CameraEvent>,
+    events_tx: Sender<CameraEvent>,
+    builder: CameraBuilder,
+    frame_pool: Arc<FramePool>,
+    decoders: Arc<Decoders>,
+    pipeline: RwLock<Option<Pipeline>>,
+    rate_limit: Arc<FrameRateLimit>,
+    priority: Arc<PriorityRequest>,
+    memory: Arc<MemoryBudget>,
+    /// Buffers dropped since [`InnerCamera::short_frames`] was last asked, see [`filled`].
+    short_frames: Arc<AtomicU32>,
+    latency: Mutex<Latency>,
+    /// The format of the device before it was opened, see [`CameraBuilder::restore_format`].
+    saved_format: Option<DeviceFormat>,
+    /// The format kamera set, kept while stopped and applied again on start in case another
+    /// application or the saved format replaced it.
+    own_format: Mutex<Option<DeviceFormat>>,
 }
 
-fn get_next_best_format(device: &Device) -> Format {
-    let _rgb = FourCC::new(b"RGB3");
-    let mut fmt = device.format().expect("device.format()");
-    let size = device
-        .enum_framesizes(fmt.fourcc)
-        .unwrap()
-        .into_iter()
-        .next()
-        .unwrap()
-        .size
-        .to_discrete()
-        .into_iter()
-        .last()
-        .unwrap();
-    fmt.width = size.width;
-    fmt.height = size.height;
-    fmt
+/// A format with its frame interval, which not every driver has.
+#[derive(Clone, Copy)]
+struct DeviceFormat {
+    format: Format,
+    params: Option<Parameters>,
+}
+
+impl DeviceFormat {
+    fn read(device: &Device) -> std::io::Result<Self> {
+        Ok(Self { format: device.format()?, params: device.params().ok() })
+    }
+
+    fn apply(&self, device: &Device) -> std::io::Result<()> {
+        if Self::read(device).is_ok_and(|current| current.same_as(self)) {
+            return Ok(());
+        }
+        device.set_format(&self.format)?;
+        if let Some(params) = &self.params {
+            let _ = device.set_params(params);
+        }
+        Ok(())
+    }
+
+    fn capture_format(&self) -> CaptureFormat {
+        let fps = self.params.map(|p| fps(&p.interval)).unwrap_or(0.0);
+        CaptureFormat {
+            pixel_format: self.format.fourcc.str().unwrap_or_default().to_string(),
+            width: self.format.width,
+            height: self.format.height,
+            min_fps: fps,
+            max_fps: fps,
+        }
+    }
+
+    fn same_as(&self, other: &Self) -> bool {
+        let interval = |params: Option<Parameters>| {
+            params.map(|p| (p.interval.numerator, p.interval.denominator))
+        };
+        let (a, b) = (&self.format, &other.format);
+        (a.fourcc, a.width, a.height) == (b.fourcc, b.width, b.height)
+            && interval(self.params) == interval(other.params)
+    }
+}
+
+/// The first of `preference` which the device offers at its largest size, or the current
+/// format if the device offers none of them.
+fn negotiate_format(device: &Device, preference: &[String]) -> std::io::Result<Format> {
+    let mut fmt = device.format()?;
+    let offered: Vec<FourCC> =
+        device.enum_formats().unwrap_or_default().into_iter().map(|d| d.fourcc).collect();
+    let fourcc = preference
+        .iter()
+        .filter_map(|p| <&[u8; 4]>::try_from(p.as_bytes()).ok())
+        .map(FourCC::new)
+        .find(|fourcc| offered.contains(fourcc))
+        .unwrap_or(fmt.fourcc);
+    let sizes = device.enum_framesizes(fourcc).unwrap_or_default().into_iter().map(|s| s.size);
+    let largest = sizes
+        .map(|size| match size {
+            FrameSizeEnum::Discrete(d) => (d.width, d.height),
+            FrameSizeEnum::Stepwise(s) => (s.max_width, s.max_height),
+        })
+        .max_by_key(|&(w, h)| w as u64 * h as u64);
+    fmt.fourcc = fourcc;
+    if let Some((width, height)) = largest {
+        fmt.width = width;
+        fmt.height = height;
+    }
+    Ok(fmt)
 }
 
 #[allow(unused)]
@@ -51,15 +159,98 @@ fn display_node(node: &Node) {
 #[allow(unused)]
 fn display_device_formats(device: &Device) {
     println!("Device formats:");
-    for fmt in device.enum_formats().unwrap() {
+    for fmt in device.enum_formats().unwrap_or_default() {
         println!("  {:?}", fmt);
 
-        for size in device.enum_framesizes(fmt.fourcc).unwrap() {
+        for size in device.enum_framesizes(fmt.fourcc).unwrap_or_default() {
             println!("  {:?}", size);
         }
     }
 }
 
+fn device_kind(device: &Device) -> DeviceKind {
+    match device.query_caps() {
+        Ok(caps) if caps.driver == "v4l2 loopback" => DeviceKind::Virtual,
+        Ok(caps) if ["usb-", "PCI:", "platform:"].iter().any(|bus| caps.bus.starts_with(bus)) => {
+            DeviceKind::Physical
+        }
+        _ => DeviceKind::Unknown,
+    }
+}
+
+/// V4L2 control ids from videodev2.h.
+fn enhancement_control(enhancement: Enhancement) -> u32 {
+    match enhancement {
+        // V4L2_CID_EXPOSURE_AUTO_PRIORITY, lets auto exposure lower the frame rate
+        Enhancement::LowLightBoost => 0x009a0903,
+        // V4L2_CID_WIDE_DYNAMIC_RANGE
+        Enhancement::Hdr => 0x009a0915,
+        // V4L2_CID_BACKLIGHT_COMPENSATION
+        Enhancement::BacklightCompensation => 0x0098091c,
+    }
+}
+
+/// V4L2_CID_FLASH_LED_MODE, a menu of none, flash and torch.
+const FLASH_LED_MODE: u32 = 0x009c0901;
+/// V4L2_CID_FLASH_TORCH_INTENSITY
+const FLASH_TORCH_INTENSITY: u32 = 0x009c0908;
+
+/// V4L2 control ids from videodev2.h and the factor from control units to [`PtzAxis`] units.
+fn ptz_control(axis: PtzAxis) -> (u32, f32) {
+    match axis {
+        // V4L2_CID_PAN_ABSOLUTE in arc seconds
+        PtzAxis::Pan => (0x009a0908, 1.0 / 3600.0),
+        // V4L2_CID_TILT_ABSOLUTE in arc seconds
+        PtzAxis::Tilt => (0x009a0909, 1.0 / 3600.0),
+        // V4L2_CID_ZOOM_ABSOLUTE
+        PtzAxis::Zoom => (0x009a090d, 1.0),
+    }
+}
+
+fn read_sysfs(path: &Path) -> Option<String> {
+    std::fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+/// The sysfs directory of the USB device behind a `/dev/video*` path, `None` for devices which
+/// are not on USB.
+fn usb_device(path: &str) -> Option<PathBuf> {
+    let sys = Path::new("/sys/class/video4linux").join(Path::new(path).file_name()?);
+    // device links to the USB interface, its parent is the USB device
+    let interface = std::fs::canonicalize(sys.join("device")).ok()?;
+    let usb = interface.parent()?;
+    usb.join("idVendor").exists().then(|| usb.to_path_buf())
+}
+
+/// `usb-VID:PID-SERIAL-indexN` from sysfs, `None` for devices which are not on USB.
+fn usb_id(path: &str) -> Option<String> {
+    let usb = usb_device(path)?;
+    let sys = Path::new("/sys/class/video4linux").join(Path::new(path).file_name()?);
+    let index = read_sysfs(&sys.join("index")).unwrap_or_default();
+    let vendor = read_sysfs(&usb.join("idVendor"))?;
+    let product = read_sysfs(&usb.join("idProduct"))?;
+    let serial = read_sysfs(&usb.join("serial")).unwrap_or_default();
+    Some(format!("usb-{vendor}:{product}-{serial}-index{index}"))
+}
+
+/// USB devices tell whether they are built in with `removable`, MIPI cameras of laptops sit on
+/// the platform bus.
+fn placement(path: &str) -> Placement {
+    let Some(name) = Path::new(path).file_name() else {
+        return Placement::Unknown;
+    };
+    let sys = Path::new("/sys/class/video4linux").join(name);
+    let Ok(interface) = std::fs::canonicalize(sys.join("device")) else {
+        return Placement::Unknown;
+    };
+    let removable = interface.parent().map(|usb| usb.join("removable"));
+    match removable.and_then(|path| std::fs::read_to_string(path).ok()).as_deref().map(str::trim) {
+        Some("fixed") => Placement::BuiltIn,
+        Some("removable") => Placement::External,
+        _ if interface.starts_with("/sys/devices/platform") => Placement::BuiltIn,
+        _ => Placement::Unknown,
+    }
+}
+
 fn enum_devices() -> Vec<Node> {
     v4l::context::enum_devices()
         .into_iter()
@@ -70,86 +261,886 @@ fn enum_devices() -> Vec<Node> {
 }
 
 impl Camera {
-    fn from_node(node: &v4l::context::Node) -> Self {
-        let device = v4l::Device::with_path(node.path()).unwrap();
-        device.set_format(&get_next_best_format(&device)).unwrap();
-        Self {
+    fn from_node(
+        node: &v4l::context::Node,
+        builder: &CameraBuilder,
+        frame_pool: Arc<FramePool>,
+    ) -> Result<Self, Error> {
+        let device = v4l::Device::with_path(node.path())?;
+        let saved_format = match builder.restore_format {
+            true => Some(DeviceFormat::read(&device)?),
+            false => None,
+        };
+        device.set_format(&negotiate_format(&device, &builder.pixel_formats)?)?;
+        events::subscribe(&device);
+        let (events_tx, events) = channel();
+        Ok(Self {
             device: RwLock::new(device),
             device_path: node.path().to_string_lossy().to_string(),
             device_name: node.name(),
             stream: RwLock::new(None),
+            events,
+            events_tx,
+            builder: builder.clone(),
+            frame_pool,
+            decoders: Arc::new(Decoders::new(builder.decoder_provider.clone())),
+            pipeline: RwLock::new(None),
+            rate_limit: Default::default(),
+            priority: Default::default(),
+            memory: Default::default(),
+            short_frames: Default::default(),
+            latency: Default::default(),
+            saved_format,
+            own_format: Mutex::new(None),
+        })
+    }
+
+    /// Remembers the format of kamera for the next start and gives the device back the format
+    /// it had before it was opened, unless it already has.
+    fn restore_format(&self, device: &Device) {
+        let mut own = lock(&self.own_format);
+        if own.is_none() {
+            *own = DeviceFormat::read(device).ok();
+            if let Some(saved) = &self.saved_format {
+                let _ = saved.apply(device);
+            }
+        }
+    }
+
+    /// Applies the format kamera negotiated again after [`Camera::restore_format`].
+    fn resume_format(&self, device: &Device) -> std::io::Result<()> {
+        match lock(&self.own_format).take() {
+            Some(own) => own.apply(device),
+            None => Ok(()),
+        }
+    }
+
+    fn stop_stream(&self) {
+        let _ = write(&self.stream).take();
+        let _ = write(&self.pipeline).take();
+    }
+
+    /// Without `block` only a buffer which is already filled is dequeued, with `skip_queued`
+    /// buffers are dequeued until the newest filled one.
+    fn next_frame(&self, block: bool, skip_queued: bool) -> Option<Frame> {
+        self.priority.follow();
+        self.handle_device_events();
+        if let Some(pipeline) = read(&self.pipeline).as_ref() {
+            return match (block, skip_queued) {
+                (false, _) => pipeline.try_next_frame(),
+                (true, false) => pipeline.wait_for_frame(),
+                (true, true) => pipeline.latest_frame(),
+            };
+        }
+        // POLLIN from poll.h
+        const POLLIN: i16 = 0x1;
+        let handle = read(&self.device).handle();
+        let ready = || handle.poll(POLLIN, 0).is_ok_and(|n| n > 0);
+        if !block && !ready() {
+            return None;
+        }
+        let format = read(&self.device).format().ok()?;
+        let mut stream = write(&self.stream);
+        // not started
+        let stream = stream.as_mut()?;
+        let mut next = stream.next();
+        let mut accepted = false;
+        while let Ok((buf, meta)) = next {
+            if skip_queued && ready() {
+                next = stream.next();
+                continue;
+            }
+            if filled(buf, meta, &format).is_none() {
+                // give the validation of the camera a chance to see a stream of these
+                let short = self.short_frames.fetch_add(1, Ordering::Relaxed) + 1;
+                if !block || short >= FrameValidation::LIMIT {
+                    return None;
+                }
+                next = stream.next();
+                continue;
+            }
+            accepted = self.rate_limit.accept(Instant::now());
+            if accepted || (!block && !ready()) {
+                break;
+            }
+            // dropped by the frame rate limit before converting it
+            next = stream.next();
+        }
+        let (buf, timestamp) = match next {
+            Ok((buf, meta)) if accepted => (filled(buf, meta, &format)?, buffer_timestamp(meta)),
+            Ok(_) => return None,
+            Err(err) => {
+                if err.kind() == std::io::ErrorKind::ResourceBusy {
+                    let _ = self.events_tx.send(CameraEvent::InUseByOtherApp);
+                }
+                return None;
+            }
+        };
+        // the application holds on to frames over the memory limit
+        let memory = self.memory.try_reserve(frame_bytes(&format))?;
+        let mut frame =
+            convert(buf, timestamp, &format, &self.frame_pool, &self.decoders, &self.events_tx)?;
+        frame.memory = Some(memory);
+        Some(frame)
+    }
+
+    /// Sends the events of the device, after the source changed its resolution the stream starts
+    /// again with buffers of the new size.
+    fn handle_device_events(&self) {
+        let device_events = events::dequeue(&read(&self.device));
+        for event in device_events {
+            let event = match event {
+                DeviceEvent::ControlChanged { id, value } => {
+                    CameraEvent::ControlChanged { id, value }
+                }
+                DeviceEvent::ResolutionChanged => {
+                    let reset = |device: &Device| device.set_format(&device.format()?);
+                    self.reconfigure(|device| reset(device).is_ok());
+                    let Some(format) = self.current_format() else { continue };
+                    CameraEvent::ResolutionChanged { width: format.width, height: format.height }
+                }
+                DeviceEvent::EndOfStream => CameraEvent::EndOfStream,
+            };
+            let _ = self.events_tx.send(event);
+        }
+    }
+
+    /// Stops the stream while `configure` changes the device, the buffers are sized for the old
+    /// format, and restarts it if it was running.
+    fn reconfigure(&self, configure: impl FnOnce(&Device) -> bool) -> bool {
+        let streaming = read(&self.stream).is_some() || read(&self.pipeline).is_some();
+        let stopped = lock(&self.own_format).is_some();
+        self.stop_stream();
+        let device = write(&self.device);
+        // the change applies to the format of kamera, not to the restored one
+        let applied = self.resume_format(&device).is_ok() && configure(&device);
+        if stopped {
+            self.restore_format(&device);
         }
+        drop(device);
+        if streaming {
+            // fails e.g. when another process took the device in the meantime
+            return self.start().is_ok() && applied;
+        }
+        applied
+    }
+
+    /// Whether the number of buffers changes with it.
+    fn replace_latency(&self, latency: Latency) -> bool {
+        let previous = std::mem::replace(&mut *lock(&self.latency), latency);
+        let configured = self.builder.buffer_count;
+        previous.buffer_count(configured) != latency.buffer_count(configured)
+    }
+
+    #[cfg(feature = "raw")]
+    pub(crate) fn raw_device(&self) -> std::sync::RwLockReadGuard<'_, Device> {
+        read(&self.device)
     }
 }
 
+/// Converts a raw buffer of `format` to a BGRA frame, with the decoder of a provider if there is
+/// one. Sends [`CameraEvent::UnsupportedFormat`] if there's no conversion for it.
+fn convert(
+    buf: &[u8],
+    timestamp: Option<Duration>,
+    format: &Format,
+    frame_pool: &Arc<FramePool>,
+    decoders: &Decoders,
+    events_tx: &Sender<CameraEvent>,
+) -> Option<Frame> {
+    let size = match format.field_order {
+        // every buffer holds a single field, which becomes a frame of half the height
+        FieldOrder::Alternate => (format.width, format.height / 2),
+        _ => (format.width, format.height),
+    };
+    let (w, h) = size;
+    let fourcc = &format.fourcc.repr;
+    let raw = raw_format(fourcc);
+    let pixel_format = PixelFormat::from_fourcc(crate::FourCC::new(fourcc));
+    let bytes_per_pixel = match pixel_format {
+        Some(PixelFormat::Rgb24) => 3,
+        Some(PixelFormat::Yuyv | PixelFormat::Uyvy | PixelFormat::P010) => 2,
+        _ if raw.is_some_and(|(_, bits)| bits > 8) => 2,
+        _ => 1,
+    };
+    // some drivers leave bytesperline at 0 for packed rows
+    let stride = match format.stride {
+        0 => w as usize * bytes_per_pixel,
+        stride => stride as usize,
+    };
+    let color_space = color_space_from_format(format);
+    let start = Instant::now();
+    let mut woven = Vec::new();
+    let buf = match format.field_order {
+        FieldOrder::SequentialTB | FieldOrder::SequentialBT => {
+            let bottom_first = matches!(format.field_order, FieldOrder::SequentialBT);
+            let planes = match pixel_format {
+                Some(PixelFormat::Nv12 | PixelFormat::P010) => {
+                    vec![h as usize, h.div_ceil(2) as usize]
+                }
+                _ => vec![h as usize],
+            };
+            weave_fields(buf, stride, &planes, bottom_first, &mut woven);
+            &woven
+        }
+        _ => buf,
+    };
+    let mut data = frame_pool.take();
+    let (mut samples, mut luma, mut chroma) = (Vec::new(), Vec::new(), Vec::new());
+    match (decoders.decode(crate::FourCC::new(fourcc), size, buf, &mut data), pixel_format) {
+        (Some(true), _) => {}
+        (Some(false), _) => {
+            frame_pool.put(data);
+            return None;
+        }
+        (None, Some(PixelFormat::Rgb24)) => rgb24_to_bgra(buf, w, h, stride, &mut data),
+        (None, Some(PixelFormat::Yuyv)) => {
+            luma = luma_plane(buf, w, h, stride, 0, 2);
+            chroma = packed_422_chroma(buf, w, h, stride, 1);
+            yuyv_to_bgra(buf, w, h, stride, color_space, &mut data);
+        }
+        (None, Some(PixelFormat::Uyvy)) => {
+            luma = luma_plane(buf, w, h, stride, 1, 2);
+            chroma = packed_422_chroma(buf, w, h, stride, 0);
+            uyvy_to_bgra(buf, w, h, stride, color_space, &mut data);
+        }
+        (None, Some(PixelFormat::Nv12)) => {
+            luma = luma_plane(buf, w, h, stride, 0, 1);
+            let uv = buf.get(stride * h as usize..).unwrap_or_default();
+            chroma = luma_plane(uv, w.div_ceil(2) * 2, h.div_ceil(2), stride, 0, 1);
+            nv12_to_bgra(buf, w, h, stride, color_space, &mut data);
+        }
+        (None, Some(PixelFormat::P010)) => {
+            // the 10 bit samples stay in `samples`, the frame gets the top 8 bits. 4:2:0 formats
+            // have even widths, so the UV rows are as long as the Y rows.
+            samples = unpack_p010(buf, w, h, stride);
+            let nv12 = narrow_u16(&samples, 10);
+            nv12_to_bgra(&nv12, w, h, w as usize, color_space, &mut data);
+            luma = nv12;
+            chroma = luma.split_off(w as usize * h as usize);
+        }
+        (None, Some(PixelFormat::Gray8)) => {
+            luma = luma_plane(buf, w, h, stride, 0, 1);
+            gray_to_bgra(buf, w, h, stride, &mut data);
+        }
+        #[cfg(feature = "mjpeg")]
+        (None, Some(PixelFormat::Mjpeg)) => {
+            if !mjpeg::mjpeg_to_bgra(buf, w, h, &mut data) {
+                // a broken frame, the next one is likely fine
+                frame_pool.put(data);
+                return None;
+            }
+        }
+        (None, _) => {
+            let Some((pattern, bits)) = raw else {
+                frame_pool.put(data);
+                let pixel_format = format.fourcc.str().unwrap_or_default().to_string();
+                let _ = events_tx.send(CameraEvent::UnsupportedFormat { pixel_format });
+                return None;
+            };
+            let narrowed;
+            let (buf, stride) = match bits {
+                8 => (buf, stride),
+                _ => {
+                    samples = unpack_u16(buf, w, h, stride);
+                    narrowed = narrow_u16(&samples, bits);
+                    (&narrowed[..], w as usize)
+                }
+            };
+            match pattern {
+                Some(pattern) => bayer_to_bgra(buf, w, h, stride, pattern, &mut data),
+                None => {
+                    luma = luma_plane(buf, w, h, stride, 0, 1);
+                    gray_to_bgra(buf, w, h, stride, &mut data);
+                }
+            }
+        }
+    }
+    let conversion_time = start.elapsed();
+    let frame_pool = frame_pool.clone();
+    let fourcc = crate::FourCC::new(fourcc);
+    Some(Frame {
+        data,
+        samples,
+        luma,
+        chroma,
+        size,
+        color_space,
+        fourcc,
+        conversion_time,
+        timestamp,
+        frame_pool,
+        memory: None,
+    })
+}
+
+/// The size of a BGRA frame of `format`, which the memory limit counts.
+fn frame_bytes(format: &Format) -> usize {
+    format.width as usize * format.height as usize * 4
+}
+
+/// The part of a dequeued buffer the driver filled, the mapping is as large as the biggest
+/// frame. `None` for an empty buffer, or one shorter than `sizeimage` of an uncompressed format,
+/// which some UVC firmware delivers after resume.
+pub(crate) fn filled<'a>(
+    buf: &'a [u8],
+    meta: &v4l::buffer::Metadata,
+    format: &Format,
+) -> Option<&'a [u8]> {
+    let buf = &buf[..buf.len().min(meta.bytesused as usize)];
+    let compressed = matches!(
+        PixelFormat::from_fourcc(crate::FourCC::new(&format.fourcc.repr)),
+        Some(PixelFormat::Mjpeg | PixelFormat::H264) | None
+    ) && raw_format(&format.fourcc.repr).is_none();
+    let short = buf.is_empty() || (!compressed && buf.len() < format.size as usize);
+    (!short).then_some(buf)
+}
+
+/// The time the driver captured the buffer, `None` unless it is on `CLOCK_MONOTONIC`. Drivers
+/// of memory-to-memory devices copy the time of the output buffer instead.
+pub(crate) fn buffer_timestamp(meta: &v4l::buffer::Metadata) -> Option<Duration> {
+    use v4l::buffer::Flags;
+    let monotonic = meta.flags & Flags::TIMESTAMP_MASK == Flags::TIMESTAMP_MONOTONIC;
+    let v4l::Timestamp { sec, usec } = meta.timestamp;
+    (monotonic && sec >= 0).then(|| Duration::new(sec as u64, usec as u32 * 1000))
+}
+
+/// `CLOCK_MONOTONIC`, the clock of V4L2 buffer timestamps. `Instant` reads it too but doesn't
+/// tell its value.
+fn monotonic_clock() -> Option<Duration> {
+    #[repr(C)]
+    struct Timespec {
+        tv_sec: i64,
+        tv_nsec: i64,
+    }
+    // from time.h
+    const CLOCK_MONOTONIC: i32 = 1;
+    extern "C" {
+        fn clock_gettime(clock: i32, time: *mut Timespec) -> i32;
+    }
+    let mut time = Timespec { tv_sec: 0, tv_nsec: 0 };
+    if unsafe { clock_gettime(CLOCK_MONOTONIC, &mut time) } != 0 {
+        return None;
+    }
+    Some(Duration::new(time.tv_sec as u64, time.tv_nsec as u32))
+}
+
+fn apply_resolution(device: &Device, width: u32, height: u32) -> bool {
+    device.format().is_ok_and(|mut format| {
+        (format.width, format.height) = (width, height);
+        device.set_format(&format).is_ok_and(|f| (f.width, f.height) == (width, height))
+    })
+}
+
+/// The frame rate is a request, drivers pick the closest interval they support.
+fn apply_format(device: &Device, format: &CaptureFormat) -> bool {
+    let Ok(fourcc) = <&[u8; 4]>::try_from(format.pixel_format.as_bytes()) else {
+        return false;
+    };
+    let size = (format.width, format.height);
+    let applied = device.format().is_ok_and(|mut current| {
+        current.fourcc = FourCC::new(fourcc);
+        (current.width, current.height) = size;
+        device
+            .set_format(&current)
+            .is_ok_and(|f| (f.fourcc.repr, (f.width, f.height)) == (*fourcc, size))
+    });
+    if applied && format.max_fps > 0.0 {
+        let params = v4l::video::capture::Parameters::with_fps(format.max_fps.round() as u32);
+        let _ = device.set_params(&params);
+    }
+    applied
+}
+
+/// The depth format of RealSense and other depth cameras, 16 bit millimeters.
+const DEPTH: &[u8; 4] = b"Z16 ";
+
+/// The Bayer pattern and bits per sample of the raw formats, without a pattern for grayscale.
+/// Samples of more than 8 bits take 16, the packed variants aren't supported.
+fn raw_format(fourcc: &[u8; 4]) -> Option<(Option<BayerPattern>, u32)> {
+    use BayerPattern::*;
+    let (pattern, bits) = match fourcc {
+        b"Y10 " => (None, 10),
+        b"Y12 " => (None, 12),
+        b"Y16 " => (None, 16),
+        // depth in millimeters, shown as gray up to about 8 m
+        DEPTH => (None, 13),
+        b"BA81" => (Some(Bggr), 8),
+        b"GBRG" => (Some(Gbrg), 8),
+        b"GRBG" => (Some(Grbg), 8),
+        b"RGGB" => (Some(Rggb), 8),
+        b"BG10" => (Some(Bggr), 10),
+        b"GB10" => (Some(Gbrg), 10),
+        b"BA10" => (Some(Grbg), 10),
+        b"RG10" => (Some(Rggb), 10),
+        b"BG12" => (Some(Bggr), 12),
+        b"GB12" => (Some(Gbrg), 12),
+        b"BA12" => (Some(Grbg), 12),
+        b"RG12" => (Some(Rggb), 12),
+        b"BYR2" => (Some(Bggr), 16),
+        b"GB16" => (Some(Gbrg), 16),
+        b"GR16" => (Some(Grbg), 16),
+        b"RG16" => (Some(Rggb), 16),
+        _ => return None,
+    };
+    Some((pattern, bits))
+}
+
 impl InnerCamera for Camera {
     type Frame = Frame;
 
-    fn new_default_device() -> Self {
-        let node = enum_devices().into_iter().next().unwrap();
-        Self::from_node(&node)
+    fn new_with(builder: &CameraBuilder) -> Result<Self, Error> {
+        let devices = Self::device_list().into_iter().map(|d| (d.clone(), placement(&d.id)));
+        let device = device_policy::pick(devices.collect(), &builder.device_policy)
+            .ok_or(Error::NoDevice)?;
+        let node = (enum_devices().into_iter())
+            .find(|node| node.path().to_string_lossy() == device.id)
+            .ok_or(Error::NoDevice)?;
+        let frame_pool = Arc::new(FramePool::new(builder.frame_pool_size));
+        // setting the format fails with EBUSY while another process streams
+        builder.open_policy.retry(|| Self::from_node(&node, builder, frame_pool.clone()))
     }
 
-    fn start(&self) {
-        if self.stream.read().unwrap().is_none() {
-            let device = self.device.write().unwrap();
-            let stream =
-                v4l::io::mmap::Stream::with_buffers(&device, v4l::buffer::Type::VideoCapture, 4)
-                    .expect("Failed to create buffer stream");
-            let _ = self.stream.write().unwrap().insert(stream);
+    fn start(&self) -> Result<(), Error> {
+        self.builder.open_policy.retry(|| self.try_exclusive())
+    }
+
+    fn try_exclusive(&self) -> Result<(), Error> {
+        if read(&self.stream).is_none() && read(&self.pipeline).is_none() {
+            let device = write(&self.device);
+            self.resume_format(&device)?;
+            // VIDIOC_REQBUFS fails with EBUSY while another process streams from the device
+            let buffer_type = v4l::buffer::Type::VideoCapture;
+            let buffer_count = lock(&self.latency).buffer_count(self.builder.buffer_count);
+            let mut stream =
+                v4l::io::mmap::Stream::with_buffers(&device, buffer_type, buffer_count)?;
+            stream.set_timeout(DEQUEUE_TIMEOUT);
+            if self.builder.pipeline_workers == 0 {
+                let _ = write(&self.stream).insert(stream);
+            } else {
+                let format = device.format()?;
+                let pipeline = Pipeline::start(
+                    stream,
+                    format,
+                    self.builder.pipeline_workers,
+                    self.frame_pool.clone(),
+                    self.decoders.clone(),
+                    self.rate_limit.clone(),
+                    self.priority.clone(),
+                    self.memory.clone(),
+                    self.short_frames.clone(),
+                    self.events_tx.clone(),
+                );
+                let _ = write(&self.pipeline).insert(pipeline);
+            }
         }
+        Ok(())
     }
 
     fn stop(&self) {
-        let _ = self.stream.write().unwrap().take();
+        self.stop_stream();
+        self.restore_format(&read(&self.device));
     }
 
     fn wait_for_frame(&self) -> Option<Frame> {
-        let format = self.device.read().unwrap().format().unwrap();
-        let size = (format.width, format.height);
-        if let Ok((buf, _meta)) = self.stream.write().unwrap().as_mut().unwrap().next() {
-            let data = match &format.fourcc.repr {
-                b"RGB3" => buf.to_vec(),
-                b"YUYV" => yuyv_to_rgb32(buf, size.0, size.1),
-                b"MJPG" => todo!("NJPG not implemented"),
-                _ => panic!("invalid buffer pixelformat"),
-            };
+        self.next_frame(true, *lock(&self.latency) == Latency::Lowest)
+    }
+
+    fn try_next_frame(&self) -> Option<Frame> {
+        self.next_frame(false, *lock(&self.latency) == Latency::Lowest)
+    }
+
+    fn latest_frame(&self) -> Option<Frame> {
+        self.next_frame(true, true)
+    }
+
+    fn frame_ready_fd(&self) -> Option<FrameReadyFd> {
+        // A V4L2 device polls readable once a filled buffer can be dequeued.
+        Some(read(&self.device).handle().fd())
+    }
+
+    fn events(&self) -> &Receiver<CameraEvent> {
+        &self.events
+    }
+
+    fn send_event(&self, event: CameraEvent) {
+        let _ = self.events_tx.send(event);
+    }
+
+    fn current_format(&self) -> Option<CaptureFormat> {
+        let device = read(&self.device);
+        let own = *lock(&self.own_format);
+        // while stopped the device may have its previous format back
+        match own {
+            Some(own) => Some(own.capture_format()),
+            None => DeviceFormat::read(&device).ok().map(|format| format.capture_format()),
+        }
+    }
 
-            Some(Frame { data, size })
-        } else {
-            None
+    fn set_resolution(&self, width: u32, height: u32) -> bool {
+        self.reconfigure(|device| apply_resolution(device, width, height))
+    }
+
+    fn set_format(&self, format: &CaptureFormat) -> bool {
+        <&[u8; 4]>::try_from(format.pixel_format.as_bytes()).is_ok()
+            && self.reconfigure(|device| apply_format(device, format))
+    }
+
+    fn renegotiate(&self, invalid_frames: u32) {
+        let Some(format) = self.current_format() else { return };
+        if self.set_resolution(format.width, format.height) {
+            let _ = self.events_tx.send(CameraEvent::StreamRenegotiated { invalid_frames });
+        }
+    }
+
+    fn short_frames(&self) -> u32 {
+        self.short_frames.swap(0, Ordering::Relaxed)
+    }
+
+    /// V4L2 drivers often refuse to change the frame interval while streaming, so frames are
+    /// dropped after dequeuing them.
+    fn set_max_fps(&self, fps: f32) {
+        self.rate_limit.set_max_fps(fps);
+    }
+
+    /// A running stream restarts if the number of buffers changes.
+    fn set_latency_mode(&self, latency: Latency) {
+        if self.replace_latency(latency) {
+            self.reconfigure(|_| true);
+        }
+    }
+
+    /// Sets the format and resolution between one stream off and on, which also takes up a
+    /// new number of buffers.
+    fn configure(&self, changes: &RequestedConfig) -> Vec<ConfigMismatch> {
+        if let Some(fps) = changes.max_fps {
+            self.rate_limit.set_max_fps(fps);
+        }
+        let buffers_changed = changes.latency.is_some_and(|latency| self.replace_latency(latency));
+        let resized = changes.format.is_some() || changes.resolution.is_some();
+        if !resized && !buffers_changed {
+            return Vec::new();
         }
+        let mut mismatches = Vec::new();
+        self.reconfigure(|device| {
+            mismatches = changes.set_size(|request| match request {
+                SizeRequest::Format(format) => apply_format(device, format),
+                SizeRequest::Resolution(width, height) => apply_resolution(device, width, height),
+            });
+            true
+        });
+        mismatches
+    }
+
+    /// V4L2 has no face detection.
+    fn enable_metadata(&self, _kind: MetadataKind) -> bool {
+        false
+    }
+
+    fn faces(&self) -> Vec<FaceRect> {
+        Vec::new()
+    }
+
+    /// Without pipeline workers the frames are dequeued on the thread of the application, which
+    /// then takes the priority.
+    fn set_thread_priority(&self, priority: ThreadPriority) {
+        self.priority.set(priority);
+    }
+
+    /// With a pipeline the capture thread drops buffers over the limit before converting them.
+    fn set_memory_limit(&self, bytes: usize) {
+        self.memory.set_limit(bytes);
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.memory.used()
+    }
+
+    /// Depth cameras have nodes of their own, the depth can't be added to a color node.
+    fn enable_depth(&self) -> bool {
+        self.current_format().is_some_and(|format| format.pixel_format.as_bytes() == DEPTH)
+    }
+
+    fn depth(&self, frame: &Frame) -> Option<Arc<DepthFrame>> {
+        if frame.fourcc != crate::FourCC::new(DEPTH) {
+            return None;
+        }
+        let (width, height) = frame.size;
+        Some(Arc::new(DepthFrame { width, height, data: frame.samples.clone() }))
+    }
+
+    /// V4L2 buffers carry their time on `CLOCK_MONOTONIC`.
+    fn device_clock(&self) -> Option<Duration> {
+        monotonic_clock()
+    }
+
+    fn capture_metadata(&self) -> CaptureMetadata {
+        let mut metadata = CaptureMetadata::new(self.device().name);
+        let device = read(&self.device);
+        let integer = |id| match device.control(id).map(|control| control.value) {
+            Ok(control::Value::Integer(value)) => u32::try_from(value).ok(),
+            _ => None,
+        };
+        // V4L2_CID_EXPOSURE_ABSOLUTE in units of 100 µs
+        metadata.exposure_time =
+            integer(0x009a0902).map(|value| Duration::from_micros(value as u64 * 100));
+        // V4L2_CID_ISO_SENSITIVITY is an integer menu, the control holds the item index
+        let iso_items = device.query_controls().ok().and_then(|controls| {
+            controls.into_iter().find(|c| c.id == 0x009a0917).and_then(|c| c.items)
+        });
+        metadata.iso = integer(0x009a0917).and_then(|index| {
+            iso_items?.into_iter().find_map(|(i, item)| match item {
+                control::MenuItem::Value(iso) if i == index => u32::try_from(iso).ok(),
+                _ => None,
+            })
+        });
+        // V4L2_CID_WHITE_BALANCE_TEMPERATURE
+        metadata.white_balance = integer(0x0098091a);
+        metadata
+    }
+
+    fn supports_enhancement(&self, enhancement: Enhancement) -> bool {
+        read(&self.device).control(enhancement_control(enhancement)).is_ok()
+    }
+
+    fn set_enhancement(&self, enhancement: Enhancement, enabled: bool) -> bool {
+        let device = read(&self.device);
+        let id = enhancement_control(enhancement);
+        let value = match device.control(id).map(|control| control.value) {
+            Ok(control::Value::Boolean(_)) => control::Value::Boolean(enabled),
+            // backlight compensation is a level, zero is off
+            Ok(control::Value::Integer(_)) => {
+                let controls = device.query_controls().unwrap_or_default();
+                let range = controls.iter().find(|c| c.id == id).map(|c| (c.minimum, c.maximum));
+                let (min, max) = range.unwrap_or((0, 1));
+                control::Value::Integer(if enabled { max } else { min })
+            }
+            _ => return false,
+        };
+        device.set_control(control::Control { id, value }).is_ok()
+    }
+
+    /// V4L2 has no rotation, only the flip controls of some sensors.
+    fn set_video_orientation(&self, _orientation: VideoOrientation) -> bool {
+        false
+    }
+
+    fn ptz_range(&self, axis: PtzAxis) -> Option<PtzRange> {
+        let (id, unit) = ptz_control(axis);
+        let controls = read(&self.device).query_controls().ok()?;
+        let control = controls.into_iter().find(|c| c.id == id)?;
+        let read_only = control::Flags::READ_ONLY | control::Flags::DISABLED;
+        if control.flags.intersects(read_only) {
+            return None;
+        }
+        Some(PtzRange {
+            min: control.minimum as f32 * unit,
+            max: control.maximum as f32 * unit,
+            step: control.step as f32 * unit,
+            default: control.default as f32 * unit,
+        })
+    }
+
+    fn ptz(&self, axis: PtzAxis) -> Option<f32> {
+        let (id, unit) = ptz_control(axis);
+        match read(&self.device).control(id).ok()?.value {
+            control::Value::Integer(value) => Some(value as f32 * unit),
+            _ => None,
+        }
+    }
+
+    fn set_ptz(&self, axis: PtzAxis, value: f32) -> bool {
+        let (id, unit) = ptz_control(axis);
+        let value = control::Value::Integer((value / unit).round() as i64);
+        read(&self.device).set_control(control::Control { id, value }).is_ok()
+    }
+
+    fn has_torch(&self) -> bool {
+        read(&self.device).control(FLASH_LED_MODE).is_ok()
+    }
+
+    /// Without an intensity control the torch is either off or on at its fixed power.
+    fn set_torch_level(&self, level: f32) -> Result<(), Error> {
+        // V4L2_FLASH_LED_MODE_NONE and V4L2_FLASH_LED_MODE_TORCH
+        let (none, torch) = (control::Value::Integer(0), control::Value::Integer(2));
+        let device = read(&self.device);
+        if level == 0.0 {
+            return Ok(device.set_control(control::Control { id: FLASH_LED_MODE, value: none })?);
+        }
+        let controls = device.query_controls()?;
+        match controls.iter().find(|c| c.id == FLASH_TORCH_INTENSITY) {
+            Some(c) => {
+                let range = (c.maximum - c.minimum) as f32;
+                let intensity = c.minimum + (level * range).round() as i64;
+                let value = control::Value::Integer(intensity);
+                device.set_control(control::Control { id: FLASH_TORCH_INTENSITY, value })?;
+            }
+            None if level < 1.0 => return Err(Error::Unsupported),
+            None => {}
+        }
+        Ok(device.set_control(control::Control { id: FLASH_LED_MODE, value: torch })?)
     }
 
     fn device(&self) -> CameraDevice {
-        CameraDevice { id: self.device_path.clone(), name: self.device_name.as_ref().unwrap_or(&self.device_path).clone() }
+        CameraDevice {
+            id: self.device_path.clone(),
+            name: self.device_name.as_ref().unwrap_or(&self.device_path).clone(),
+            kind: device_kind(&read(&self.device)),
+        }
     }
 
-    fn set_device(&mut self, device: &CameraDevice) -> bool {
+    fn set_device(&mut self, device: &CameraDevice) -> Result<(), Error> {
         if device.id == self.device_path {
-            return true;
+            return Ok(());
         }
         let find_device = enum_devices()
             .into_iter()
             .find(|d| d.path().to_string_lossy().to_string() == device.id);
-        if let Some(new_device) = find_device {
-            *self = Self::from_node(&new_device);
-            self.start();
-            return true;
-        }
+        let Some(new_device) = find_device else {
+            self.stop();
+            return Err(Error::NoDevice);
+        };
         self.stop();
-        return false;
+        let frame_pool = self.frame_pool.clone();
+        *self = Self::from_node(&new_device, &self.builder, frame_pool)?;
+        self.start()
     }
 
     fn device_list() -> Vec<CameraDevice> {
-        enum_devices()
+        Self::enumerate_with_errors().into_iter().filter_map(Result::ok).collect()
+    }
+
+    /// Device types only exist on macOS.
+    fn device_list_of_types(_types: &[DeviceType]) -> Vec<CameraDevice> {
+        Self::device_list()
+    }
+
+    fn enumerate_with_errors() -> Vec<Result<CameraDevice, EnumError>> {
+        v4l::context::enum_devices()
             .iter()
-            .map(|d| {
-                let path = d.path().to_string_lossy().to_string();
-                CameraDevice { id: path.clone(), name: d.name().unwrap_or(path) }
+            .map(|node| {
+                let id = node.path().to_string_lossy().to_string();
+                let device = match Device::with_path(node.path()) {
+                    Ok(device) => device,
+                    Err(err) if err.kind() == std::io::ErrorKind::PermissionDenied => {
+                        return Err(EnumError::PermissionDenied { id })
+                    }
+                    Err(err) => return Err(EnumError::OpenFailed { id, message: err.to_string() }),
+                };
+                if let Err(err) = device.format() {
+                    let reason = format!("no video capture format: {err}");
+                    return Err(EnumError::Unsupported { id, reason });
+                }
+                let kind = device_kind(&device);
+                Ok(CameraDevice { name: node.name().unwrap_or(id.clone()), id, kind })
             })
             .collect()
     }
+
+    fn stable_id(device: &CameraDevice) -> String {
+        usb_id(&device.id).unwrap_or_else(|| device.id.clone())
+    }
+
+    /// The sysfs path of the USB device, or of the device of the node for MIPI cameras, whose
+    /// nodes the media controller of that device links.
+    fn group_id(device: &CameraDevice) -> String {
+        let group = usb_device(&device.id).or_else(|| {
+            let sys = Path::new("/sys/class/video4linux").join(Path::new(&device.id).file_name()?);
+            std::fs::canonicalize(sys.join("device")).ok()
+        });
+        group.map_or_else(|| device.id.clone(), |path| path.to_string_lossy().to_string())
+    }
+
+    /// The attributes of the USB device in sysfs, which udev reports too.
+    fn device_details(device: &CameraDevice) -> DeviceDetails {
+        let Some(usb) = usb_device(&device.id) else {
+            return DeviceDetails::default();
+        };
+        let read = |name: &str| read_sysfs(&usb.join(name)).filter(|s| !s.is_empty());
+        let id = |name: &str| read(name).and_then(|id| u16::from_str_radix(&id, 16).ok());
+        DeviceDetails {
+            vendor_id: id("idVendor"),
+            product_id: id("idProduct"),
+            serial: read("serial"),
+            bus_location: usb.file_name().map(|name| name.to_string_lossy().to_string()),
+            firmware: read("bcdDevice").and_then(|bcd| bcd_version(&bcd)),
+            manufacturer: read("manufacturer"),
+            model: read("product"),
+        }
+    }
+
+    fn describe_device(device: &CameraDevice) -> DeviceCapabilities {
+        let Ok(device) = Device::with_path(&device.id) else {
+            return DeviceCapabilities::default();
+        };
+        let mut formats = vec![];
+        for description in device.enum_formats().unwrap_or_default() {
+            let fourcc = description.fourcc;
+            let pixel_format = fourcc.str().unwrap_or_default().to_string();
+            for framesize in device.enum_framesizes(fourcc).unwrap_or_default() {
+                let sizes = match framesize.size {
+                    FrameSizeEnum::Discrete(size) => vec![(size.width, size.height)],
+                    // Listing every step can mean thousands of sizes, the bounds are enough
+                    FrameSizeEnum::Stepwise(s) => {
+                        vec![(s.min_width, s.min_height), (s.max_width, s.max_height)]
+                    }
+                };
+                for (width, height) in sizes {
+                    let intervals = device.enum_frameintervals(fourcc, width, height);
+                    let (min_fps, max_fps) = fps_range(&intervals.unwrap_or_default());
+                    formats.push(CaptureFormat {
+                        pixel_format: pixel_format.clone(),
+                        width,
+                        height,
+                        min_fps,
+                        max_fps,
+                    });
+                }
+            }
+        }
+        DeviceCapabilities { formats }
+    }
+
+    /// The format the node has, which the last application left or the driver's default.
+    fn default_format(device: &CameraDevice) -> Option<CaptureFormat> {
+        let device = Device::with_path(&device.id).ok()?;
+        DeviceFormat::read(&device).ok().map(|format| format.capture_format())
+    }
+
+    /// Depth only comes from devices with a `Z16 ` format.
+    fn backend_capabilities() -> BackendCapabilities {
+        BackendCapabilities {
+            capture: true,
+            device_types: false,
+            face_metadata: false,
+            depth: true,
+        }
+    }
+}
+
+fn fps(interval: &v4l::Fraction) -> f64 {
+    interval.denominator as f64 / interval.numerator.max(1) as f64
+}
+
+fn fps_range(intervals: &[v4l::frameinterval::FrameInterval]) -> (f64, f64) {
+    let rates: Vec<f64> = intervals
+        .iter()
+        .flat_map(|interval| match &interval.interval {
+            FrameIntervalEnum::Discrete(f) => [fps(f), fps(f)],
+            FrameIntervalEnum::Stepwise(s) => [fps(&s.max), fps(&s.min)],
+        })
+        .collect();
+    let min = rates.iter().copied().reduce(f64::min).unwrap_or(0.0);
+    let max = rates.iter().copied().reduce(f64::max).unwrap_or(0.0);
+    (min, max)
+}
+
+/// Streaming stops and the capture thread of a pipeline ends before the device is closed, which
+/// unmaps the buffers first.
+impl Drop for Camera {
+    fn drop(&mut self) {
+        self.stop();
+    }
 }
 
 impl std::fmt::Debug for Camera {
@@ -160,54 +1151,159 @@ impl std::fmt::Debug for Camera {
 
 pub struct Frame {
     data: Vec<u8>,
+    /// The samples of formats with more than 8 bits, empty for the others.
+    samples: Vec<u16>,
+    /// The Y plane of YUV and grayscale formats without row padding, empty for the others.
+    luma: Vec<u8>,
+    /// The UV plane of NV12 without row padding, for 4:2:2 formats from every two rows, empty
+    /// for formats without chroma.
+    chroma: Vec<u8>,
     size: (u32, u32),
+    color_space: ColorSpace,
+    /// The format of the device buffer before conversion.
+    fourcc: crate::FourCC,
+    conversion_time: Duration,
+    /// Capture time on `CLOCK_MONOTONIC`.
+    timestamp: Option<Duration>,
+    frame_pool: Arc<FramePool>,
+    /// The part of [`Camera::set_memory_limit`](crate::Camera::set_memory_limit) of this frame.
+    memory: Option<Reservation>,
 }
 
 impl Frame {
     pub fn data(&self) -> FrameData {
-        FrameData { data: self.data.clone(), _phantom: PhantomData }
+        let (data, samples, luma, chroma) = (&self.data, &self.samples, &self.luma, &self.chroma);
+        let stride = self.size.0 as usize * 4;
+        FrameData { data, samples, luma, chroma, stride, size: self.size }
     }
 
     pub fn size_u32(&self) -> (u32, u32) {
         self.size
     }
+
+    pub fn conversion_time(&self) -> Duration {
+        self.conversion_time
+    }
+
+    pub fn timestamp(&self) -> Option<Duration> {
+        self.timestamp
+    }
+
+    pub fn color_space(&self) -> ColorSpace {
+        self.color_space
+    }
+
+    pub fn fourcc(&self) -> crate::FourCC {
+        self.fourcc
+    }
+
+    /// The converted buffer has a single owner, a crop copies.
+    pub fn share(&self) -> Option<Frame> {
+        None
+    }
+}
+
+impl Drop for Frame {
+    fn drop(&mut self) {
+        self.frame_pool.put(std::mem::take(&mut self.data));
+    }
 }
 
 impl std::fmt::Debug for Frame {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Frame").field("data", &self.data.len()).finish()
+        f.debug_struct("Frame")
+            .field("data", &self.data.len())
+            .field("fourcc", &self.fourcc)
+            .finish()
     }
 }
 
 #[derive(Debug)]
 pub struct FrameData<'a> {
-    data: Vec<u8>,
-    _phantom: PhantomData<&'a ()>,
+    data: &'a [u8],
+    samples: &'a [u16],
+    luma: &'a [u8],
+    chroma: &'a [u8],
+    stride: usize,
+    size: (u32, u32),
 }
 
 impl<'a> FrameData<'a> {
     pub fn data_u8(&self) -> &[u8] {
-        &self.data
+        self.data
     }
 
     pub fn data_u32(&self) -> &[u32] {
         unsafe { self.data.align_to().1 }
     }
-}
 
-fn yuyv_to_rgb32(buf: &[u8], w: u32, h: u32) -> Vec<u8> {
-    use ffimage::color::Rgb;
-    use ffimage::packed::{ImageBuffer, ImageView};
-    use ffimage::traits::Convert;
-    use ffimage_yuv::{yuv::Yuv, yuyv::Yuyv};
+    pub fn data_u16(&self) -> Option<&[u16]> {
+        (!self.samples.is_empty()).then_some(self.samples)
+    }
+
+    pub fn luma(&self) -> Option<&[u8]> {
+        (!self.luma.is_empty()).then_some(self.luma)
+    }
+
+    pub fn chroma(&self) -> Option<&[u8]> {
+        (!self.chroma.is_empty()).then_some(self.chroma)
+    }
+
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    pub fn plane_count(&self) -> usize {
+        1
+    }
 
-    let yuv422 = ImageView::<Yuyv<u8>>::from_buf(buf, w, h).unwrap();
-    let mut yuv444 = ImageBuffer::<Yuv<u8>>::new(w, h, 0u8);
-    let mut rgb = ImageBuffer::<Rgb<u8>>::new(w, h, 0u8);
-    let mut rgba = ImageBuffer::<Bgra<u8>>::new(w, h, 0u8);
-    yuv422.convert(&mut yuv444);
-    yuv444.convert(&mut rgb);
-    rgb.convert(&mut rgba);
+    pub fn plane(&self, index: usize) -> Option<PlaneView> {
+        let (width, height) = self.size;
+        (index == 0).then_some(PlaneView { data: self.data, stride: self.stride, width, height })
+    }
+}
+
+/// Maps the V4L2 colorspace to the Y'CbCr encoding, quantization and transfer function
+/// a driver implies with it, see the colorspaces chapter of the V4L2 documentation.
+///
+/// The encoding and a default quantization follow `V4L2_MAP_YCBCR_ENC_DEFAULT` and
+/// `V4L2_MAP_QUANTIZATION_DEFAULT`. Only without a colorspace the matrix and range stay
+/// unknown, for [`ColorSpace::or_defaults_for_height`] to guess from the height.
+fn color_space_from_format(format: &Format) -> ColorSpace {
+    let matrix = match format.colorspace {
+        Colorspace::Default => YuvMatrix::Unknown,
+        Colorspace::Rec709 | Colorspace::DCIP3 => YuvMatrix::Bt709,
+        Colorspace::Rec2020 => YuvMatrix::Bt2020,
+        // SMPTE 240M has no matrix of its own here, BT.709 is the closest
+        Colorspace::SMPTE240M => YuvMatrix::Bt709,
+        _ => YuvMatrix::Bt601,
+    };
+    let range = match (format.quantization, format.colorspace) {
+        (Quantization::FullRange, _) => ColorRange::Full,
+        (Quantization::LimitedRange, _) => ColorRange::Limited,
+        (Quantization::Default, Colorspace::Default) => ColorRange::Unknown,
+        (Quantization::Default, Colorspace::JPEG) => ColorRange::Full,
+        (Quantization::Default, _) => ColorRange::Limited,
+    };
+    let transfer = match format.colorspace {
+        Colorspace::SRGB | Colorspace::JPEG => TransferFunction::Srgb,
+        Colorspace::Rec709 | Colorspace::SMPTE170M | Colorspace::NTSC => TransferFunction::Bt709,
+        Colorspace::Rec2020 => TransferFunction::Bt2020,
+        _ => TransferFunction::Unknown,
+    };
+    ColorSpace { matrix, range, transfer }
+}
 
-    rgba.into_buf()
+#[test]
+fn jpeg_colorspace_is_full_range() {
+    let mut format = Format::new(640, 480, FourCC::new(b"YUYV"));
+    format.colorspace = Colorspace::JPEG;
+    format.quantization = Quantization::Default;
+    let color_space = color_space_from_format(&format);
+    assert_eq!(color_space.range, ColorRange::Full);
+    assert_eq!(color_space.matrix, YuvMatrix::Bt601);
+    format.colorspace = Colorspace::Rec709;
+    assert_eq!(color_space_from_format(&format).range, ColorRange::Limited);
+    format.colorspace = Colorspace::Default;
+    assert_eq!(color_space_from_format(&format).matrix, YuvMatrix::Unknown);
 }
@@ -0,0 +1,252 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicU32, Ordering},
+    mpsc::{channel, sync_channel, Receiver, Sender, SyncSender, TryRecvError},
+    Arc, Mutex,
+};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use v4l::io::traits::CaptureStream;
+use v4l::Format;
+
+use super::{buffer_timestamp, convert, filled, frame_bytes, Frame, DEQUEUE_TIMEOUT};
+use crate::decoder::Decoders;
+use crate::memory::{MemoryBudget, Reservation};
+use crate::pool::FramePool;
+use crate::priority::PriorityRequest;
+use crate::rate_limit::FrameRateLimit;
+use crate::sync::lock;
+use crate::validation::FrameValidation;
+use crate::CameraEvent;
+
+type Stream = v4l::io::mmap::Stream<'static>;
+
+/// A raw buffer with its capture time, where its converted frame goes and the memory of that
+/// frame.
+type Job = (Vec<u8>, Option<Duration>, SyncSender<Frame>, Reservation);
+
+/// Dequeues buffers on a capture thread and converts them on worker threads, so the conversion
+/// of one frame overlaps the capture of the next, see
+/// [`CameraBuilder::pipeline_workers`](crate::CameraBuilder::pipeline_workers).
+///
+/// Every buffer gets a one-shot channel for its frame, these go through a bounded queue in
+/// capture order, so frames come out in order however the workers finish. The capture thread
+/// drops buffers while the queue is full instead of waiting for the application.
+pub(crate) struct Pipeline {
+    /// One-shot channels of frames in capture order.
+    pending: Mutex<Receiver<Receiver<Frame>>>,
+    /// The channel [`Pipeline::try_next_frame`] is waiting on.
+    head: Mutex<Option<Receiver<Frame>>>,
+    /// Buffers the capture thread dropped for being empty or short, see [`filled`].
+    short_frames: Arc<AtomicU32>,
+    /// No buffer arrived for [`DEQUEUE_TIMEOUT`].
+    timed_out: Arc<AtomicBool>,
+    stop: Arc<AtomicBool>,
+    capture: Option<JoinHandle<()>>,
+}
+
+impl Pipeline {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn start(
+        stream: Stream,
+        format: Format,
+        workers: usize,
+        frame_pool: Arc<FramePool>,
+        decoders: Arc<Decoders>,
+        rate_limit: Arc<FrameRateLimit>,
+        priority: Arc<PriorityRequest>,
+        memory: Arc<MemoryBudget>,
+        short_frames: Arc<AtomicU32>,
+        events_tx: Sender<CameraEvent>,
+    ) -> Self {
+        let depth = workers + 1;
+        let (pending_tx, pending) = sync_channel(depth);
+        let (job_tx, job_rx) = channel::<Job>();
+        let job_rx = Arc::new(Mutex::new(job_rx));
+        let raw_pool = Arc::new(FramePool::new(depth));
+        for _ in 0..workers {
+            let job_rx = job_rx.clone();
+            let raw_pool = raw_pool.clone();
+            let frame_pool = frame_pool.clone();
+            let decoders = decoders.clone();
+            let events_tx = events_tx.clone();
+            let priority = priority.clone();
+            std::thread::spawn(move || loop {
+                // the capture thread hangs up on stop
+                let Ok((raw, timestamp, frame_tx, memory)) = lock(&job_rx).recv() else {
+                    break;
+                };
+                priority.follow();
+                let frame = convert(&raw, timestamp, &format, &frame_pool, &decoders, &events_tx);
+                if let Some(mut frame) = frame {
+                    frame.memory = Some(memory);
+                    let _ = frame_tx.send(frame);
+                }
+                raw_pool.put(raw);
+            });
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        let timed_out = Arc::new(AtomicBool::new(false));
+        let capture = {
+            let stop = stop.clone();
+            let jobs = Jobs {
+                pending_tx,
+                job_tx,
+                raw_pool,
+                memory,
+                format,
+                short_frames: short_frames.clone(),
+                timed_out: timed_out.clone(),
+            };
+            std::thread::spawn(move || capture(stream, stop, jobs, rate_limit, priority, events_tx))
+        };
+        Self {
+            pending: Mutex::new(pending),
+            head: Mutex::new(None),
+            short_frames,
+            timed_out,
+            stop,
+            capture: Some(capture),
+        }
+    }
+
+    /// `None` once the capture thread stopped, after a few short buffers so the camera can
+    /// validate the stream, or after [`DEQUEUE_TIMEOUT`] without buffers.
+    pub(crate) fn wait_for_frame(&self) -> Option<Frame> {
+        let head = lock(&self.head).take();
+        let mut head = head.or_else(|| lock(&self.pending).recv().ok());
+        loop {
+            // a frame the worker couldn't convert never arrives, neither does a short one
+            match head?.recv() {
+                Ok(frame) => return Some(frame),
+                Err(_) if self.short_frames.load(Ordering::Relaxed) >= FrameValidation::LIMIT => {
+                    return None;
+                }
+                Err(_) if self.timed_out.swap(false, Ordering::Relaxed) => return None,
+                Err(_) => head = lock(&self.pending).recv().ok(),
+            }
+        }
+    }
+
+    pub(crate) fn try_next_frame(&self) -> Option<Frame> {
+        let mut head = lock(&self.head);
+        loop {
+            let Some(frame_rx) = head.take().or_else(|| lock(&self.pending).try_recv().ok()) else {
+                return None;
+            };
+            match frame_rx.try_recv() {
+                Ok(frame) => return Some(frame),
+                Err(TryRecvError::Empty) => {
+                    *head = Some(frame_rx);
+                    return None;
+                }
+                Err(TryRecvError::Disconnected) => {}
+            }
+        }
+    }
+
+    /// Skips all frames but the most recently captured one.
+    pub(crate) fn latest_frame(&self) -> Option<Frame> {
+        {
+            let mut head = lock(&self.head);
+            let pending = lock(&self.pending);
+            while let Ok(frame_rx) = pending.try_recv() {
+                *head = Some(frame_rx);
+            }
+        }
+        self.wait_for_frame()
+    }
+}
+
+impl Drop for Pipeline {
+    /// Waits for the capture thread, which releases the buffers of the stream.
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(capture) = self.capture.take() {
+            let _ = capture.join();
+        }
+    }
+}
+
+/// Where the capture thread sends the buffers.
+struct Jobs {
+    pending_tx: SyncSender<Receiver<Frame>>,
+    job_tx: Sender<Job>,
+    raw_pool: Arc<FramePool>,
+    memory: Arc<MemoryBudget>,
+    format: Format,
+    short_frames: Arc<AtomicU32>,
+    timed_out: Arc<AtomicBool>,
+}
+
+impl Jobs {
+    /// Wakes a waiting application without a frame.
+    fn wake(&self) {
+        let _ = self.pending_tx.try_send(sync_channel(1).1);
+    }
+}
+
+fn capture(
+    mut stream: Stream,
+    stop: Arc<AtomicBool>,
+    jobs: Jobs,
+    rate_limit: Arc<FrameRateLimit>,
+    priority: Arc<PriorityRequest>,
+    events_tx: Sender<CameraEvent>,
+) {
+    // POLLIN from poll.h
+    const POLLIN: i16 = 0x1;
+    let handle = stream.handle();
+    // the stream starts with the first dequeue, polling before that never returns
+    let mut started = false;
+    let mut last_buffer = Instant::now();
+    while !stop.load(Ordering::Relaxed) {
+        priority.follow();
+        if last_buffer.elapsed() > DEQUEUE_TIMEOUT {
+            // the camera can restart the stream, e.g. one which stopped over system sleep
+            jobs.timed_out.store(true, Ordering::Relaxed);
+            jobs.wake();
+            last_buffer = Instant::now();
+        }
+        if started && !handle.poll(POLLIN, 100).is_ok_and(|n| n > 0) {
+            continue;
+        }
+        started = true;
+        let (buf, timestamp) = match stream.next() {
+            Ok((buf, meta)) => {
+                last_buffer = Instant::now();
+                match filled(buf, meta, &jobs.format) {
+                    Some(buf) => (buf, buffer_timestamp(meta)),
+                    None => {
+                        jobs.short_frames.fetch_add(1, Ordering::Relaxed);
+                        jobs.wake();
+                        continue;
+                    }
+                }
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::TimedOut => continue,
+            Err(err) => {
+                if err.kind() == std::io::ErrorKind::ResourceBusy {
+                    let _ = events_tx.send(CameraEvent::InUseByOtherApp);
+                }
+                break;
+            }
+        };
+        if !rate_limit.accept(Instant::now()) {
+            continue;
+        }
+        // the application holds on to frames over the memory limit, drop this buffer
+        let Some(memory) = jobs.memory.try_reserve(frame_bytes(&jobs.format)) else { continue };
+        let (frame_tx, frame_rx) = sync_channel(1);
+        if jobs.pending_tx.try_send(frame_rx).is_err() {
+            // the application is behind, drop this buffer
+            continue;
+        }
+        let mut raw = jobs.raw_pool.take();
+        raw.clear();
+        raw.extend_from_slice(buf);
+        if jobs.job_tx.send((raw, timestamp, frame_tx, memory)).is_err() {
+            break;
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use std::ptr::{null, null_mut};
+
+use objc2_foundation::*;
+use objc2::rc::Id;
+use objc2::runtime::{AnyClass, NSObject};
+use objc2::*;
+
+use super::{dispatch_queue_create, dispatch_release, DepthDelegate};
+
+extern_class!(
+    #[derive(PartialEq, Eq, Hash, Debug)]
+    pub struct AVCaptureDepthDataOutput;
+
+    unsafe impl ClassType for AVCaptureDepthDataOutput {
+        type Super = NSObject;
+        type Mutability = mutability::InteriorMutable;
+    }
+);
+
+unsafe impl NSObjectProtocol for AVCaptureDepthDataOutput {}
+
+impl AVCaptureDepthDataOutput {
+    /// `None` where AVFoundation has no depth output, before iOS 11 and on older macOS.
+    pub fn new() -> Option<Id<Self>> {
+        AnyClass::get("AVCaptureDepthDataOutput")?;
+        Some(unsafe { msg_send_id![Self::class(), new] })
+    }
+
+    /// Fills the holes of the depth map, e.g. at edges and on reflective surfaces.
+    pub fn set_filtering_enabled(&self, enabled: bool) {
+        unsafe { msg_send![self, setFilteringEnabled: enabled] }
+    }
+
+    /// Keep `delegate` alive until [`Self::remove_delegate`].
+    pub fn set_delegate(&self, delegate: &DepthDelegate) {
+        let name = std::ffi::CString::new("depth output").unwrap();
+        let queue = unsafe { dispatch_queue_create(name.as_ptr(), null()) };
+        let _: () = unsafe { msg_send!(self, setDelegate: delegate callbackQueue: queue) };
+        unsafe { dispatch_release(queue) };
+    }
+
+    pub fn remove_delegate(&self) {
+        let (delegate, queue) = (null::<NSObject>(), null_mut::<NSObject>());
+        let _: () = unsafe { msg_send!(self, setDelegate: delegate callbackQueue: queue) };
+    }
+}
+
+#[test]
+fn new() {
+    let output = AVCaptureDepthDataOutput::new();
+    println!("{output:?}");
+}
@@ -1,9 +1,19 @@
-use objc2_foundation::{NSArray, NSObjectProtocol, NSString};
+use objc2_foundation::{NSArray, NSError, NSObjectProtocol, NSProcessInfo, NSString};
 use objc2::rc::Id;
-use objc2::runtime::NSObject;
-use objc2::{extern_class, msg_send_id, mutability, ClassType};
+use objc2::runtime::{AnyClass, NSObject};
+use objc2::{extern_class, msg_send, msg_send_id, mutability, sel, ClassType};
 
-use super::AVCaptureDeviceFormat;
+use super::{AVCaptureDeviceFormat, CMTime};
+use crate::device_policy::Placement;
+use crate::{DeviceKind, DeviceType};
+
+/// Whether the classes of a capture session exist. A process which runs on an OS without them
+/// gets errors instead of panics from the class lookups.
+pub fn capture_available() -> bool {
+    ["AVCaptureDevice", "AVCaptureDeviceInput", "AVCaptureSession", "AVCaptureVideoDataOutput"]
+        .into_iter()
+        .all(|name| AnyClass::get(name).is_some())
+}
 
 extern_class! {
     #[derive(PartialEq, Eq, Hash, Debug)]
@@ -19,14 +29,73 @@ unsafe impl NSObjectProtocol for AVCaptureDevice {}
 
 #[allow(unused)]
 impl AVCaptureDevice {
-    pub fn default_video_device() -> Id<Self> {
+    /// `None` without a camera.
+    pub fn default_video_device() -> Option<Id<Self>> {
+        if !capture_available() {
+            return None;
+        }
         let video = Self::media_type_video();
         unsafe { msg_send_id![Self::class(), defaultDeviceWithMediaType: &*video] }
     }
 
     pub fn all_video_devices() -> Id<NSArray<AVCaptureDevice>> {
+        Self::video_devices_of_types(&DeviceType::ALL)
+    }
+
+    /// Uses AVCaptureDeviceDiscoverySession, which knows the newer device types. Before macOS
+    /// 10.15 there is none and all devices are listed.
+    pub fn video_devices_of_types(types: &[DeviceType]) -> Id<NSArray<AVCaptureDevice>> {
+        if !capture_available() {
+            return NSArray::new();
+        }
+        let video = Self::media_type_video();
+        let Some(class) = AnyClass::get("AVCaptureDeviceDiscoverySession") else {
+            return unsafe { msg_send_id!(Self::class(), devicesWithMediaType: &*video) };
+        };
+        let os_version = NSProcessInfo::processInfo().operatingSystemVersion().majorVersion;
+        let types = types.iter().filter_map(|t| device_type_name(*t, os_version));
+        Self::discover(class, types)
+    }
+
+    /// Devices made of several cameras, which only iOS has. Empty before iOS 13.
+    pub fn virtual_devices() -> Id<NSArray<AVCaptureDevice>> {
+        let Some(class) = AnyClass::get("AVCaptureDeviceDiscoverySession") else {
+            return NSArray::new();
+        };
+        let types = [
+            "AVCaptureDeviceTypeBuiltInDualCamera",
+            "AVCaptureDeviceTypeBuiltInDualWideCamera",
+            "AVCaptureDeviceTypeBuiltInTripleCamera",
+        ];
+        Self::discover(class, types.into_iter())
+    }
+
+    fn discover<'a>(
+        class: &AnyClass,
+        types: impl Iterator<Item = &'a str>,
+    ) -> Id<NSArray<AVCaptureDevice>> {
         let video = Self::media_type_video();
-        unsafe { msg_send_id!(Self::class(), devicesWithMediaType: &*video) }
+        let types = NSArray::from_vec(types.map(NSString::from_str).collect());
+        let position: isize = 0; // AVCaptureDevicePositionUnspecified
+        let session: Id<NSObject> = unsafe {
+            msg_send_id![
+                class,
+                discoverySessionWithDeviceTypes: &*types,
+                mediaType: &*video,
+                position: position
+            ]
+        };
+        unsafe { msg_send_id![&session, devices] }
+    }
+
+    /// The cameras of a virtual device, empty for others and before iOS 13.
+    pub fn constituent_devices(&self) -> Id<NSArray<AVCaptureDevice>> {
+        let responds: bool =
+            unsafe { msg_send![self, respondsToSelector: sel!(constituentDevices)] };
+        if !responds {
+            return NSArray::new();
+        }
+        unsafe { msg_send_id![self, constituentDevices] }
     }
 
     pub fn media_type_video() -> Id<NSString> {
@@ -37,18 +106,179 @@ impl AVCaptureDevice {
         unsafe { msg_send_id!(self, uniqueID) }
     }
 
+    pub fn model_id(&self) -> Id<NSString> {
+        unsafe { msg_send_id!(self, modelID) }
+    }
+
+    pub fn manufacturer(&self) -> Id<NSString> {
+        unsafe { msg_send_id!(self, manufacturer) }
+    }
+
     pub fn localized_name(&self) -> Id<NSString> {
         unsafe { msg_send_id!(self, localizedName) }
     }
 
+    /// `false` once the device was unplugged.
+    pub fn is_connected(&self) -> bool {
+        unsafe { msg_send![self, isConnected] }
+    }
+
     pub fn formats(&self) -> Id<NSArray<AVCaptureDeviceFormat>> {
         unsafe { msg_send_id![self, formats] }
     }
+
+    /// Only macOS, on iOS the session gets interrupted instead.
+    pub fn is_in_use_by_another_application(&self) -> bool {
+        let responds: bool =
+            unsafe { msg_send![self, respondsToSelector: sel!(isInUseByAnotherApplication)] };
+        responds && unsafe { msg_send![self, isInUseByAnotherApplication] }
+    }
+
+    pub fn active_format(&self) -> Id<AVCaptureDeviceFormat> {
+        unsafe { msg_send_id![self, activeFormat] }
+    }
+
+    pub fn set_active_format(&self, format: &AVCaptureDeviceFormat) {
+        unsafe { msg_send![self, setActiveFormat: format] }
+    }
+
+    /// Upper bound for the frame rate, the active format stays at 30 fps without it.
+    pub fn set_active_video_min_frame_duration(&self, duration: CMTime) {
+        unsafe { msg_send![self, setActiveVideoMinFrameDuration: duration] }
+    }
+
+    /// Needed before changing the active format, `false` if another app holds the lock.
+    pub fn lock_for_configuration(&self) -> bool {
+        let error: *mut *mut NSObject = std::ptr::null_mut();
+        unsafe { msg_send![self, lockForConfiguration: error] }
+    }
+
+    pub fn unlock_for_configuration(&self) {
+        unsafe { msg_send![self, unlockForConfiguration] }
+    }
+
+    /// Only on iOS devices, e.g. Continuity cameras.
+    pub fn is_low_light_boost_supported(&self) -> bool {
+        let responds: bool =
+            unsafe { msg_send![self, respondsToSelector: sel!(isLowLightBoostSupported)] };
+        responds && unsafe { msg_send![self, isLowLightBoostSupported] }
+    }
+
+    pub fn set_automatically_enables_low_light_boost(&self, enabled: bool) {
+        unsafe { msg_send![self, setAutomaticallyEnablesLowLightBoostWhenAvailable: enabled] }
+    }
+
+    /// Turns off automatic HDR, which otherwise overrides the setting.
+    pub fn set_video_hdr_enabled(&self, enabled: bool) {
+        unsafe {
+            let _: () = msg_send![self, setAutomaticallyAdjustsVideoHDREnabled: false];
+            msg_send![self, setVideoHDREnabled: enabled]
+        }
+    }
+
+    /// Only on iOS devices, e.g. Continuity cameras.
+    pub fn has_torch(&self) -> bool {
+        let responds: bool = unsafe { msg_send![self, respondsToSelector: sel!(hasTorch)] };
+        responds && unsafe { msg_send![self, hasTorch] }
+    }
+
+    /// Needs [`AVCaptureDevice::lock_for_configuration`]. `level` is in (0, 1].
+    pub fn set_torch_mode_on_with_level(&self, level: f32) -> Result<(), Id<NSError>> {
+        unsafe { msg_send![self, setTorchModeOnWithLevel: level, error: _] }
+    }
+
+    /// Needs [`AVCaptureDevice::lock_for_configuration`].
+    pub fn set_torch_mode_off(&self) {
+        // AVCaptureTorchModeOff
+        unsafe { msg_send![self, setTorchMode: 0isize] }
+    }
+
+    pub fn video_zoom_factor(&self) -> Option<f64> {
+        let responds: bool = unsafe { msg_send![self, respondsToSelector: sel!(videoZoomFactor)] };
+        responds.then(|| unsafe { msg_send![self, videoZoomFactor] })
+    }
+
+    /// Needs [`AVCaptureDevice::lock_for_configuration`].
+    pub fn set_video_zoom_factor(&self, factor: f64) {
+        unsafe { msg_send![self, setVideoZoomFactor: factor] }
+    }
+
+    /// Transport as FOURCC, e.g. 'bltn' for built-in, 'usb ' or 'virt' for virtual devices.
+    /// Only macOS.
+    pub fn transport_type(&self) -> Option<i32> {
+        let responds: bool = unsafe { msg_send![self, respondsToSelector: sel!(transportType)] };
+        responds.then(|| unsafe { msg_send![self, transportType] })
+    }
+
+    /// Current exposure duration, ISO and lens aperture, these are only available on iOS.
+    pub fn exposure(&self) -> Option<(CMTime, f32, f32)> {
+        let responds: bool = unsafe { msg_send![self, respondsToSelector: sel!(exposureDuration)] };
+        responds.then(|| unsafe {
+            (msg_send![self, exposureDuration], msg_send![self, ISO], msg_send![self, lensAperture])
+        })
+    }
+
+    /// Available since macOS 10.15.
+    pub fn device_type(&self) -> Option<Id<NSString>> {
+        let responds: bool = unsafe { msg_send![self, respondsToSelector: sel!(deviceType)] };
+        responds.then(|| unsafe { msg_send_id![self, deviceType] })
+    }
+
+    pub fn kind(&self) -> DeviceKind {
+        const VIRTUAL: i32 = i32::from_be_bytes(*b"virt");
+        let device_type = self.device_type().map(|t| t.to_string()).unwrap_or_default();
+        match (self.transport_type(), device_type.as_str()) {
+            (Some(VIRTUAL), _) => DeviceKind::Virtual,
+            (_, "AVCaptureDeviceTypeContinuityCamera" | "AVCaptureDeviceTypeDeskViewCamera") => {
+                DeviceKind::Continuity
+            }
+            (Some(0), _) => DeviceKind::Unknown,
+            _ => DeviceKind::Physical,
+        }
+    }
+
+    /// External devices are USB cameras and phones, before macOS 14 the type of USB cameras
+    /// was "external unknown".
+    pub fn placement(&self) -> Placement {
+        let device_type = self.device_type().map(|t| t.to_string()).unwrap_or_default();
+        match device_type.as_str() {
+            "AVCaptureDeviceTypeBuiltInWideAngleCamera" => Placement::BuiltIn,
+            "AVCaptureDeviceTypeExternal"
+            | "AVCaptureDeviceTypeExternalUnknown"
+            | "AVCaptureDeviceTypeContinuityCamera"
+            | "AVCaptureDeviceTypeDeskViewCamera" => Placement::External,
+            _ => Placement::Unknown,
+        }
+    }
+}
+
+/// Name of the AVCaptureDeviceType constant, `None` if this OS version doesn't know the type
+/// yet, discovery sessions raise an exception for those.
+#[cfg(target_os = "macos")]
+fn device_type_name(device_type: DeviceType, macos: isize) -> Option<&'static str> {
+    match device_type {
+        DeviceType::BuiltInWideAngle => Some("AVCaptureDeviceTypeBuiltInWideAngleCamera"),
+        DeviceType::External if macos >= 14 => Some("AVCaptureDeviceTypeExternal"),
+        DeviceType::External => Some("AVCaptureDeviceTypeExternalUnknown"),
+        DeviceType::Continuity if macos >= 14 => Some("AVCaptureDeviceTypeContinuityCamera"),
+        DeviceType::DeskView if macos >= 13 => Some("AVCaptureDeviceTypeDeskViewCamera"),
+        DeviceType::Continuity | DeviceType::DeskView => None,
+    }
+}
+
+/// iPads take external cameras since iPadOS 17, continuity cameras are a Mac feature.
+#[cfg(target_os = "ios")]
+fn device_type_name(device_type: DeviceType, ios: isize) -> Option<&'static str> {
+    match device_type {
+        DeviceType::BuiltInWideAngle => Some("AVCaptureDeviceTypeBuiltInWideAngleCamera"),
+        DeviceType::External if ios >= 17 => Some("AVCaptureDeviceTypeExternal"),
+        DeviceType::External | DeviceType::Continuity | DeviceType::DeskView => None,
+    }
 }
 
 #[test]
 fn default_video_device() {
-    let device = AVCaptureDevice::default_video_device();
+    let device = AVCaptureDevice::default_video_device().unwrap();
     println!("{device:#?}");
 }
 
@@ -75,6 +305,13 @@ fn localized_name() {
     }
 }
 
+#[test]
+fn kind() {
+    for device in AVCaptureDevice::all_video_devices().to_vec() {
+        println!("{} {:?} {:?}", device.localized_name(), device.device_type(), device.kind());
+    }
+}
+
 #[test]
 fn formats() {
     for device in AVCaptureDevice::all_video_devices().to_vec() {
@@ -1,6 +1,12 @@
-use objc2_foundation::NSObjectProtocol;
+use objc2_foundation::{NSArray, NSObjectProtocol};
+use objc2::rc::Id;
 use objc2::runtime::NSObject;
-use objc2::{extern_class, mutability, ClassType};
+use objc2::{extern_class, msg_send, msg_send_id, mutability, sel, ClassType};
+
+use super::{
+    fourcc_to_string, CMFormatDescriptionGetMediaSubType, CMFormatDescriptionRef, CMTime,
+    CMVideoFormatDescriptionGetDimensions,
+};
 
 extern_class!(
     #[derive(PartialEq, Eq, Hash, Debug)]
@@ -13,3 +19,79 @@ extern_class!(
 );
 
 unsafe impl NSObjectProtocol for AVCaptureDeviceFormat {}
+
+extern_class!(
+    #[derive(PartialEq, Eq, Hash, Debug)]
+    pub struct AVFrameRateRange;
+
+    unsafe impl ClassType for AVFrameRateRange {
+        type Super = NSObject;
+        type Mutability = mutability::InteriorMutable;
+    }
+);
+
+unsafe impl NSObjectProtocol for AVFrameRateRange {}
+
+impl AVCaptureDeviceFormat {
+    pub fn format_description(&self) -> CMFormatDescriptionRef {
+        unsafe { msg_send![self, formatDescription] }
+    }
+
+    pub fn pixel_format(&self) -> String {
+        fourcc_to_string(unsafe { CMFormatDescriptionGetMediaSubType(self.format_description()) })
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        let dims = unsafe { CMVideoFormatDescriptionGetDimensions(self.format_description()) };
+        (dims.width as _, dims.height as _)
+    }
+
+    pub fn is_video_hdr_supported(&self) -> bool {
+        let responds: bool =
+            unsafe { msg_send![self, respondsToSelector: sel!(isVideoHDRSupported)] };
+        responds && unsafe { msg_send![self, isVideoHDRSupported] }
+    }
+
+    /// 1 if the format can't zoom, only available on iOS and since macOS 14.
+    pub fn video_max_zoom_factor(&self) -> f64 {
+        let responds: bool =
+            unsafe { msg_send![self, respondsToSelector: sel!(videoMaxZoomFactor)] };
+        if responds {
+            unsafe { msg_send![self, videoMaxZoomFactor] }
+        } else {
+            1.0
+        }
+    }
+
+    pub fn video_supported_frame_rate_ranges(&self) -> Id<NSArray<AVFrameRateRange>> {
+        unsafe { msg_send_id![self, videoSupportedFrameRateRanges] }
+    }
+}
+
+impl AVFrameRateRange {
+    pub fn min_frame_rate(&self) -> f64 {
+        unsafe { msg_send![self, minFrameRate] }
+    }
+
+    pub fn max_frame_rate(&self) -> f64 {
+        unsafe { msg_send![self, maxFrameRate] }
+    }
+
+    /// Exact frame duration of the max frame rate, which `max_frame_rate` only approximates.
+    pub fn min_frame_duration(&self) -> CMTime {
+        unsafe { msg_send![self, minFrameDuration] }
+    }
+}
+
+#[test]
+fn format_frame_rate_ranges() {
+    for device in super::AVCaptureDevice::all_video_devices().to_vec() {
+        for format in device.formats().to_vec() {
+            let ranges = format.video_supported_frame_rate_ranges().to_vec();
+            let ranges: Vec<_> =
+                ranges.iter().map(|r| (r.min_frame_rate(), r.max_frame_rate())).collect();
+            println!("{} {:?} {ranges:?}", format.pixel_format(), format.dimensions());
+            assert!(!ranges.is_empty());
+        }
+    }
+}
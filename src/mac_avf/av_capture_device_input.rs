@@ -25,7 +25,7 @@ impl AVCaptureDeviceInput {
 
 #[test]
 fn from_device() {
-    let device = AVCaptureDevice::default_video_device();
+    let device = AVCaptureDevice::default_video_device().unwrap();
     let input = AVCaptureDeviceInput::from_device(&device);
     println!("{input:?}");
     assert!(input.is_ok());
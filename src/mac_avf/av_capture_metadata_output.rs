@@ -0,0 +1,68 @@
+use std::ptr::{null, null_mut};
+
+use objc2_foundation::*;
+use objc2::rc::Id;
+use objc2::runtime::NSObject;
+use objc2::*;
+
+use super::{dispatch_queue_create, dispatch_release, MetadataDelegate};
+
+extern_class!(
+    #[derive(PartialEq, Eq, Hash, Debug)]
+    pub struct AVCaptureMetadataOutput;
+
+    unsafe impl ClassType for AVCaptureMetadataOutput {
+        type Super = NSObject;
+        type Mutability = mutability::InteriorMutable;
+    }
+);
+
+unsafe impl NSObjectProtocol for AVCaptureMetadataOutput {}
+
+impl AVCaptureMetadataOutput {
+    /// `None` where AVFoundation has no metadata output, on macOS before 10.15.
+    pub fn new() -> Option<Id<Self>> {
+        runtime::AnyClass::get("AVCaptureMetadataOutput")?;
+        Some(unsafe { msg_send_id![Self::class(), new] })
+    }
+
+    /// Value of AVMetadataObjectTypeFace.
+    pub fn metadata_object_type_face() -> Id<NSString> {
+        NSString::from_str("face")
+    }
+
+    /// Detects faces, only once the output was added to a session with a camera input, before
+    /// that no type is available. `false` if the device can't.
+    pub fn detect_faces(&self) -> bool {
+        let face = Self::metadata_object_type_face();
+        let available: Id<NSArray<NSString>> =
+            unsafe { msg_send_id![self, availableMetadataObjectTypes] };
+        if !available.iter().any(|t| **t == *face) {
+            return false;
+        }
+        let types = NSArray::from_vec(vec![face]);
+        unsafe { msg_send![self, setMetadataObjectTypes: &*types] }
+        true
+    }
+
+    /// Keep `delegate` alive until [`Self::remove_metadata_objects_delegate`].
+    pub fn set_metadata_objects_delegate(&self, delegate: &MetadataDelegate) {
+        let name = std::ffi::CString::new("metadata output").unwrap();
+        let queue = unsafe { dispatch_queue_create(name.as_ptr(), null()) };
+        let _: () = unsafe { msg_send!(self, setMetadataObjectsDelegate: delegate queue: queue) };
+        unsafe { dispatch_release(queue) };
+    }
+
+    pub fn remove_metadata_objects_delegate(&self) {
+        let (delegate, queue) = (null::<NSObject>(), null_mut::<NSObject>());
+        let _: () = unsafe { msg_send!(self, setMetadataObjectsDelegate: delegate queue: queue) };
+    }
+}
+
+#[test]
+fn new() {
+    let output = AVCaptureMetadataOutput::new().unwrap();
+    println!("{output:?}");
+    // without a session there's nothing to detect
+    assert!(!output.detect_faces());
+}
@@ -1,9 +1,13 @@
-use objc2_foundation::NSObjectProtocol;
+use objc2_foundation::{NSObjectProtocol, NSString};
 use objc2::rc::Id;
 use objc2::runtime::NSObject;
-use objc2::{extern_class, msg_send, msg_send_id, mutability, ClassType};
+use objc2::{extern_class, msg_send, msg_send_id, mutability, sel, ClassType};
 
-use super::{AVCaptureDeviceInput, AVCaptureVideoDataOutput};
+use super::{
+    AVCaptureDepthDataOutput, AVCaptureDeviceInput, AVCaptureMetadataOutput,
+    AVCaptureVideoDataOutput,
+};
+use crate::SessionPreset;
 
 extern_class! {
     #[derive(PartialEq, Eq, Hash, Debug)]
@@ -27,6 +31,24 @@ impl AVCaptureSession {
         unsafe { msg_send!(self, beginConfiguration) }
     }
 
+    pub fn commit_configuration(&self) {
+        unsafe { msg_send!(self, commitConfiguration) }
+    }
+
+    /// `false` if the inputs and outputs of the session don't support the preset.
+    pub fn set_session_preset(&self, preset: SessionPreset) -> bool {
+        let preset = NSString::from_str(preset_name(preset));
+        let can_set: bool = unsafe { msg_send![self, canSetSessionPreset: &*preset] };
+        if can_set {
+            unsafe { msg_send![self, setSessionPreset: &*preset] }
+        }
+        can_set
+    }
+
+    pub fn session_preset(&self) -> Id<NSString> {
+        unsafe { msg_send_id![self, sessionPreset] }
+    }
+
     pub fn start_running(&self) {
         unsafe { msg_send!(self, startRunning) }
     }
@@ -43,9 +65,60 @@ impl AVCaptureSession {
         unsafe { msg_send!(self, addOutput: output) }
     }
 
+    pub fn add_metadata_output(&self, output: &AVCaptureMetadataOutput) -> bool {
+        let can_add: bool = unsafe { msg_send![self, canAddOutput: output] };
+        if can_add {
+            unsafe { msg_send![self, addOutput: output] }
+        }
+        can_add
+    }
+
+    pub fn remove_output(&self, output: &AVCaptureVideoDataOutput) {
+        unsafe { msg_send!(self, removeOutput: output) }
+    }
+
+    pub fn remove_metadata_output(&self, output: &AVCaptureMetadataOutput) {
+        unsafe { msg_send!(self, removeOutput: output) }
+    }
+
+    /// `false` for devices without depth.
+    pub fn add_depth_output(&self, output: &AVCaptureDepthDataOutput) -> bool {
+        let can_add: bool = unsafe { msg_send![self, canAddOutput: output] };
+        if can_add {
+            unsafe { msg_send![self, addOutput: output] }
+        }
+        can_add
+    }
+
+    pub fn remove_depth_output(&self, output: &AVCaptureDepthDataOutput) {
+        unsafe { msg_send!(self, removeOutput: output) }
+    }
+
     pub fn remove_input(&self, input: &AVCaptureDeviceInput) {
         unsafe { msg_send!(self, removeInput: input) }
     }
+
+    /// Only iOS, e.g. while the app is in the background or another app uses the camera.
+    pub fn is_interrupted(&self) -> bool {
+        let responds: bool = unsafe { msg_send![self, respondsToSelector: sel!(isInterrupted)] };
+        responds && unsafe { msg_send![self, isInterrupted] }
+    }
+}
+
+/// The values of the `AVCaptureSessionPreset` constants.
+fn preset_name(preset: SessionPreset) -> &'static str {
+    match preset {
+        SessionPreset::Low => "AVCaptureSessionPresetLow",
+        SessionPreset::Medium => "AVCaptureSessionPresetMedium",
+        SessionPreset::High => "AVCaptureSessionPresetHigh",
+        SessionPreset::Photo => "AVCaptureSessionPresetPhoto",
+        SessionPreset::Cif352x288 => "AVCaptureSessionPreset352x288",
+        SessionPreset::Vga640x480 => "AVCaptureSessionPreset640x480",
+        SessionPreset::Qhd960x540 => "AVCaptureSessionPreset960x540",
+        SessionPreset::Hd1280x720 => "AVCaptureSessionPreset1280x720",
+        SessionPreset::Hd1920x1080 => "AVCaptureSessionPreset1920x1080",
+        SessionPreset::Hd4K3840x2160 => "AVCaptureSessionPreset3840x2160",
+    }
 }
 
 #[test]
@@ -71,7 +144,7 @@ fn begin_configuration() {
 #[test]
 fn add_input() {
     use super::AVCaptureDevice;
-    let device = AVCaptureDevice::default_video_device();
+    let device = AVCaptureDevice::default_video_device().unwrap();
     let input = AVCaptureDeviceInput::from_device(&device).unwrap();
     AVCaptureSession::new().add_input(&input);
 }
@@ -81,3 +154,12 @@ fn add_output() {
     let output = AVCaptureVideoDataOutput::new();
     AVCaptureSession::new().add_output(&output);
 }
+
+#[test]
+fn session_preset() {
+    let session = AVCaptureSession::new();
+    assert_eq!(session.session_preset().to_string(), preset_name(SessionPreset::High));
+    if session.set_session_preset(SessionPreset::Vga640x480) {
+        assert_eq!(session.session_preset().to_string(), "AVCaptureSessionPreset640x480");
+    }
+}
@@ -1,12 +1,12 @@
 use std::ffi::*;
-use std::ptr::null;
+use std::ptr::{null, null_mut};
 
 use objc2_foundation::*;
 use objc2::rc::Id;
 use objc2::runtime::NSObject;
 use objc2::*;
 
-use super::SampleBufferDelegate;
+use super::{AVCaptureDevice, SampleBufferDelegate};
 
 extern_class!(
     #[derive(PartialEq, Eq, Hash, Debug)]
@@ -25,11 +25,42 @@ impl AVCaptureVideoDataOutput {
         unsafe { msg_send_id![Self::class(), new] }
     }
 
-    pub fn set_sample_buffer_delegate(&self, delegate: Id<SampleBufferDelegate>) {
+    /// Keep `delegate` alive until [`Self::remove_sample_buffer_delegate`].
+    pub fn set_sample_buffer_delegate(&self, delegate: &SampleBufferDelegate) {
         let name = std::ffi::CString::new("video input").unwrap();
         let queue = unsafe { dispatch_queue_create(name.as_ptr(), null()) };
-        let _: () = unsafe { msg_send!(self, setSampleBufferDelegate: &*delegate queue: queue) };
-        std::mem::forget(delegate);
+        let _: () = unsafe { msg_send!(self, setSampleBufferDelegate: delegate queue: queue) };
+        // the output retains the queue
+        unsafe { dispatch_release(queue) };
+    }
+
+    /// Runs the queue of the delegate on the global queue of the QoS class `qos`.
+    pub fn set_callback_qos(&self, qos: u32) {
+        let queue: DispatchQueueT = unsafe { msg_send![self, sampleBufferCallbackQueue] };
+        if queue.is_null() {
+            return;
+        }
+        unsafe { dispatch_set_target_queue(queue, dispatch_get_global_queue(qos as isize, 0)) };
+    }
+
+    /// Rotates the frames on the video connection of the output, `false` before the output
+    /// was added to a session with an input or if the connection can't rotate.
+    /// `orientation` is an `AVCaptureVideoOrientation`.
+    pub fn set_video_orientation(&self, orientation: isize) -> bool {
+        let video = AVCaptureDevice::media_type_video();
+        let connection: *mut NSObject =
+            unsafe { msg_send![self, connectionWithMediaType: &*video] };
+        let Some(connection) = (unsafe { connection.as_ref() }) else { return false };
+        let supported: bool = unsafe { msg_send![connection, isVideoOrientationSupported] };
+        if supported {
+            let _: () = unsafe { msg_send![connection, setVideoOrientation: orientation] };
+        }
+        supported
+    }
+
+    pub fn remove_sample_buffer_delegate(&self) {
+        let (delegate, queue) = (null::<NSObject>(), null_mut::<NSObject>());
+        let _: () = unsafe { msg_send!(self, setSampleBufferDelegate: delegate queue: queue) };
     }
 }
 
@@ -38,6 +69,12 @@ extern_methods! {
         #[method(setVideoSettings:)]
         pub fn set_video_settings(&self, settings: &NSDictionary<NSString, NSNumber>);
 
+        #[method(alwaysDiscardsLateVideoFrames)]
+        pub fn always_discards_late_video_frames(&self) -> bool;
+
+        #[method(setAlwaysDiscardsLateVideoFrames:)]
+        pub fn set_always_discards_late_video_frames(&self, discard: bool);
+
         // #[method(setSampleBufferDelegate:queue:)]
         // fn set_sample_buffer_delegate(&mut self, delegate: &NSObject, queue: DispatchQueueT);
     }
@@ -48,8 +85,9 @@ extern_methods! {
 #[link(name = "System", kind = "dylib")]
 extern "C" {
     pub fn dispatch_queue_create(name: *const c_char, attr: *const c_void) -> DispatchQueueT;
-    // TODO use it and don't leak queues
-    // pub fn dispatch_release(queue: DispatchQueueT);
+    pub fn dispatch_release(queue: DispatchQueueT);
+    fn dispatch_get_global_queue(identifier: isize, flags: usize) -> DispatchQueueT;
+    fn dispatch_set_target_queue(queue: DispatchQueueT, target: DispatchQueueT);
 }
 
 pub type DispatchQueueT = *mut NSObject;
@@ -59,3 +97,11 @@ fn new() {
     let output = AVCaptureVideoDataOutput::new();
     println!("{output:?}");
 }
+
+#[test]
+fn always_discards_late_video_frames() {
+    let output = AVCaptureVideoDataOutput::new();
+    assert!(output.always_discards_late_video_frames());
+    output.set_always_discards_late_video_frames(false);
+    assert!(!output.always_discards_late_video_frames());
+}
@@ -1,16 +1,45 @@
 use super::*;
+use crate::config::{RequestedConfig, SizeRequest};
+use crate::priority::qos_class;
+use crate::sync::lock;
+use crate::{details, device_policy};
+use crate::{
+    BackendCapabilities, CameraBuilder, CameraDevice, CameraEvent, CaptureFormat, CaptureMetadata,
+    ColorSpace, ConfigMismatch, DepthFrame, DeviceCapabilities, DeviceDetails, DeviceType,
+    Enhancement, EnumError, Error, FaceRect, FrameReadyFd, Latency, MetadataKind, PlaneView,
+    PtzAxis, PtzRange, SessionPreset, ThreadPriority, VideoOrientation,
+};
 use objc2::rc::Id;
-use std::sync::Arc;
-use crate::CameraDevice;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc::{channel, Receiver, Sender},
+    Arc, Mutex,
+};
+use std::time::Duration;
 
 #[derive(Debug)]
 pub struct Camera {
     device: Id<AVCaptureDevice>,
     input: Id<AVCaptureDeviceInput>,
-    #[allow(unused)]
     output: Id<AVCaptureVideoDataOutput>,
+    delegate: Id<SampleBufferDelegate>,
     session: Id<AVCaptureSession>,
     slot: Arc<Slot>,
+    events: Receiver<CameraEvent>,
+    events_tx: Sender<CameraEvent>,
+    interrupted: AtomicBool,
+    /// [`CameraEvent::DeviceLost`] was sent for the device.
+    lost: AtomicBool,
+    metadata_output: Mutex<Option<(Id<AVCaptureMetadataOutput>, Id<MetadataDelegate>)>>,
+    /// Written by the [`MetadataDelegate`].
+    faces: Arc<Mutex<Vec<FaceRect>>>,
+    depth_output: Mutex<Option<(Id<AVCaptureDepthDataOutput>, Id<DepthDelegate>)>>,
+    /// Written by the [`DepthDelegate`].
+    depth: Arc<Mutex<Option<Arc<DepthFrame>>>>,
+    /// Queue size and discarding of the builder, for [`Latency::Balanced`].
+    frame_queue: (usize, bool),
+    /// Applied again to the new connection of [`Camera::set_device`].
+    orientation: Mutex<Option<VideoOrientation>>,
 }
 
 #[derive(Debug)]
@@ -23,60 +52,524 @@ pub struct FrameData<'a> {
 }
 
 impl Camera {
-    pub fn new_default_device() -> Self {
-        let device = AVCaptureDevice::default_video_device();
-        let input = AVCaptureDeviceInput::from_device(&device).unwrap();
+    pub fn new_with(builder: &CameraBuilder) -> Result<Self, Error> {
+        if !capture_available() {
+            return Err(Error::Unsupported);
+        }
+        let devices = AVCaptureDevice::all_video_devices();
+        let candidates = devices.iter().map(|d| (camera_device(d), d.placement())).collect();
+        let picked =
+            device_policy::pick(candidates, &builder.device_policy).ok_or(Error::NoDevice)?;
+        let device = (devices.iter())
+            .find(|d| d.unique_id().to_string() == picked.id)
+            .ok_or(Error::NoDevice)?
+            .retain();
+        let input = AVCaptureDeviceInput::from_device(&device).map_err(Error::os)?;
         let output = AVCaptureVideoDataOutput::new();
         output.set_video_settings(&video_settings_from_pixel_format("ARGB"));
-        let delegate = SampleBufferDelegate::new();
+        output.set_always_discards_late_video_frames(builder.discard_late_frames);
+        let delegate = SampleBufferDelegate::new()?;
         let slot = delegate.slot();
+        slot.set_queue_size(builder.frame_queue_size);
         let session = AVCaptureSession::new();
-        output.set_sample_buffer_delegate(delegate);
+        output.set_sample_buffer_delegate(&delegate);
         session.add_input(&input);
         session.add_output(&output);
 
-        Camera { device, input, output, session, slot }
+        let (events_tx, events) = channel();
+        let (interrupted, lost) = (AtomicBool::new(false), AtomicBool::new(false));
+        Ok(Camera {
+            device,
+            input,
+            output,
+            delegate,
+            session,
+            slot,
+            events,
+            events_tx,
+            interrupted,
+            lost,
+            metadata_output: Default::default(),
+            faces: Default::default(),
+            depth_output: Default::default(),
+            depth: Default::default(),
+            frame_queue: (builder.frame_queue_size, builder.discard_late_frames),
+            orientation: Default::default(),
+        })
     }
 
-    pub fn start(&self) {
+    /// Errors of the running session arrive as [`CameraEvent`]s.
+    pub fn start(&self) -> Result<(), Error> {
         self.session.start_running();
+        Ok(())
+    }
+
+    pub fn try_exclusive(&self) -> Result<(), Error> {
+        if self.device.is_in_use_by_another_application() {
+            return Err(Error::InUseByOtherApp);
+        }
+        self.start()
     }
 
     pub fn stop(&self) {
         self.session.stop_running();
     }
 
+    /// `None` while iOS interrupts the session. An interruption which starts while waiting
+    /// blocks until the session resumes.
     pub fn wait_for_frame(&self) -> Option<Frame> {
+        if self.report_interruption() || self.report_disconnect() {
+            return None;
+        }
         self.slot.wait_for_sample().map(|sample| Frame { sample })
     }
 
+    pub fn try_next_frame(&self) -> Option<Frame> {
+        self.slot.try_sample().map(|sample| Frame { sample })
+    }
+
+    pub fn latest_frame(&self) -> Option<Frame> {
+        if self.report_interruption() || self.report_disconnect() {
+            return None;
+        }
+        self.slot.wait_for_latest_sample().map(|sample| Frame { sample })
+    }
+
+    pub fn frame_ready_fd(&self) -> Option<FrameReadyFd> {
+        Some(self.slot.frame_ready_fd())
+    }
+
+    pub fn events(&self) -> &Receiver<CameraEvent> {
+        &self.events
+    }
+
+    pub fn send_event(&self, event: CameraEvent) {
+        let _ = self.events_tx.send(event);
+    }
+
+    pub fn current_format(&self) -> Option<CaptureFormat> {
+        Some(capture_format(&self.device.active_format()))
+    }
+
+    /// Uses the device format of that size with the highest frame rate, AVFoundation then ignores
+    /// the session preset.
+    pub fn set_resolution(&self, width: u32, height: u32) -> bool {
+        let formats = self.device.formats();
+        let Some(format) = formats
+            .iter()
+            .filter(|f| f.dimensions() == (width, height))
+            .max_by(|a, b| capture_format(a).max_fps.total_cmp(&capture_format(b).max_fps))
+        else {
+            return false;
+        };
+        if !self.device.lock_for_configuration() {
+            return false;
+        }
+        self.device.set_active_format(format);
+        self.device.unlock_for_configuration();
+        true
+    }
+
+    /// AVFoundation picks the device format for the preset, until a format is set again.
+    pub fn set_preset(&self, preset: SessionPreset) -> bool {
+        self.session.begin_configuration();
+        let applied = self.session.set_session_preset(preset);
+        self.session.commit_configuration();
+        applied
+    }
+
+    /// Matches the device format by all fields, as [`Self::current_format`] reports them.
+    pub fn set_format(&self, format: &CaptureFormat) -> bool {
+        let formats = self.device.formats();
+        let Some(device_format) = formats.iter().find(|f| capture_format(f) == *format) else {
+            return false;
+        };
+        let ranges = device_format.video_supported_frame_rate_ranges();
+        let fastest =
+            ranges.iter().max_by(|a, b| a.max_frame_rate().total_cmp(&b.max_frame_rate()));
+        if !self.device.lock_for_configuration() {
+            return false;
+        }
+        self.device.set_active_format(device_format);
+        if let Some(range) = fastest {
+            self.device.set_active_video_min_frame_duration(range.min_frame_duration());
+        }
+        self.device.unlock_for_configuration();
+        true
+    }
+
+    pub fn renegotiate(&self, invalid_frames: u32) {
+        if !self.device.lock_for_configuration() {
+            return;
+        }
+        self.device.set_active_format(&self.device.active_format());
+        self.device.unlock_for_configuration();
+        let _ = self.events_tx.send(CameraEvent::StreamRenegotiated { invalid_frames });
+    }
+
+    /// Sample buffers always hold a whole image.
+    pub fn short_frames(&self) -> u32 {
+        0
+    }
+
+    pub fn set_max_fps(&self, fps: f32) {
+        self.slot.set_max_fps(fps);
+    }
+
+    /// Both apply to a running session.
+    pub fn set_latency_mode(&self, latency: Latency) {
+        let (queue_size, discard) = latency.frame_queue(self.frame_queue);
+        self.output.set_always_discards_late_video_frames(discard);
+        self.slot.set_queue_size(queue_size);
+    }
+
+    /// One session configuration, the running session takes all changes at its commit.
+    pub fn configure(&self, changes: &RequestedConfig) -> Vec<ConfigMismatch> {
+        self.session.begin_configuration();
+        let mismatches = changes.set_size(|request| match request {
+            SizeRequest::Format(format) => self.set_format(format),
+            SizeRequest::Resolution(width, height) => self.set_resolution(width, height),
+        });
+        if let Some(fps) = changes.max_fps {
+            self.set_max_fps(fps);
+        }
+        if let Some(latency) = changes.latency {
+            self.set_latency_mode(latency);
+        }
+        self.session.commit_configuration();
+        mismatches
+    }
+
+    pub fn enable_metadata(&self, kind: MetadataKind) -> bool {
+        let MetadataKind::Faces = kind;
+        let mut metadata_output = lock(&self.metadata_output);
+        if metadata_output.is_some() {
+            return true;
+        }
+        let Some(output) = AVCaptureMetadataOutput::new() else {
+            return false;
+        };
+        if !self.session.add_metadata_output(&output) {
+            return false;
+        }
+        if !output.detect_faces() {
+            self.session.remove_metadata_output(&output);
+            return false;
+        }
+        let delegate = MetadataDelegate::new(self.faces.clone());
+        output.set_metadata_objects_delegate(&delegate);
+        *metadata_output = Some((output, delegate));
+        true
+    }
+
+    pub fn faces(&self) -> Vec<FaceRect> {
+        lock(&self.faces).clone()
+    }
+
+    pub fn enable_depth(&self) -> bool {
+        let mut depth_output = lock(&self.depth_output);
+        if depth_output.is_some() {
+            return true;
+        }
+        let Some(output) = AVCaptureDepthDataOutput::new() else {
+            return false;
+        };
+        if !self.session.add_depth_output(&output) {
+            return false;
+        }
+        output.set_filtering_enabled(true);
+        let delegate = DepthDelegate::new(self.depth.clone());
+        output.set_delegate(&delegate);
+        *depth_output = Some((output, delegate));
+        true
+    }
+
+    /// Depth arrives on its own queue, a frame gets the most recent map.
+    pub fn depth(&self, _frame: &Frame) -> Option<Arc<DepthFrame>> {
+        lock(&self.depth).clone()
+    }
+
+    /// The frames arrive on a serial dispatch queue, which now runs with the QoS class of
+    /// `priority`.
+    pub fn set_thread_priority(&self, priority: ThreadPriority) {
+        self.output.set_callback_qos(qos_class(priority));
+    }
+
+    /// The delegate keeps only the most recent sample, there is no queue to bound.
+    pub fn set_memory_limit(&self, _bytes: usize) {}
+
+    pub fn memory_usage(&self) -> usize {
+        0
+    }
+
+    pub fn supports_enhancement(&self, enhancement: Enhancement) -> bool {
+        match enhancement {
+            Enhancement::LowLightBoost => self.device.is_low_light_boost_supported(),
+            Enhancement::Hdr => self.device.active_format().is_video_hdr_supported(),
+            Enhancement::BacklightCompensation => false,
+        }
+    }
+
+    pub fn set_enhancement(&self, enhancement: Enhancement, enabled: bool) -> bool {
+        if !self.supports_enhancement(enhancement) || !self.device.lock_for_configuration() {
+            return false;
+        }
+        match enhancement {
+            Enhancement::LowLightBoost => {
+                self.device.set_automatically_enables_low_light_boost(enabled)
+            }
+            Enhancement::Hdr => self.device.set_video_hdr_enabled(enabled),
+            Enhancement::BacklightCompensation => {}
+        }
+        self.device.unlock_for_configuration();
+        true
+    }
+
+    pub fn set_video_orientation(&self, orientation: VideoOrientation) -> bool {
+        // AVCaptureVideoOrientation
+        let value = match orientation {
+            VideoOrientation::Portrait => 1,
+            VideoOrientation::PortraitUpsideDown => 2,
+            VideoOrientation::LandscapeRight => 3,
+            VideoOrientation::LandscapeLeft => 4,
+        };
+        *lock(&self.orientation) = Some(orientation);
+        self.output.set_video_orientation(value)
+    }
+
+    /// AVFoundation has no pan and tilt, only zoom.
+    pub fn ptz_range(&self, axis: PtzAxis) -> Option<PtzRange> {
+        let max = self.device.active_format().video_max_zoom_factor() as f32;
+        match axis {
+            PtzAxis::Zoom if max > 1.0 && self.device.video_zoom_factor().is_some() => {
+                Some(PtzRange { min: 1.0, max, step: 0.0, default: 1.0 })
+            }
+            _ => None,
+        }
+    }
+
+    pub fn ptz(&self, axis: PtzAxis) -> Option<f32> {
+        match axis {
+            PtzAxis::Zoom => self.device.video_zoom_factor().map(|factor| factor as f32),
+            PtzAxis::Pan | PtzAxis::Tilt => None,
+        }
+    }
+
+    pub fn set_ptz(&self, axis: PtzAxis, value: f32) -> bool {
+        if axis != PtzAxis::Zoom || !self.device.lock_for_configuration() {
+            return false;
+        }
+        self.device.set_video_zoom_factor(value as f64);
+        self.device.unlock_for_configuration();
+        true
+    }
+
+    pub fn has_torch(&self) -> bool {
+        self.device.has_torch()
+    }
+
+    pub fn set_torch_level(&self, level: f32) -> Result<(), Error> {
+        if !self.device.lock_for_configuration() {
+            return Err(Error::Other("can't lock the device for configuration".into()));
+        }
+        let result = match level > 0.0 {
+            true => self.device.set_torch_mode_on_with_level(level).map_err(Error::os),
+            false => {
+                self.device.set_torch_mode_off();
+                Ok(())
+            }
+        };
+        self.device.unlock_for_configuration();
+        result
+    }
+
     pub fn device(&self) -> CameraDevice {
-        return CameraDevice { id: self.device.unique_id().to_string(), name: self.device.localized_name().to_string() }
+        return camera_device(&self.device);
+    }
+
+    /// The session runs on the host time clock.
+    pub fn device_clock(&self) -> Option<Duration> {
+        host_time()
+    }
+
+    /// Exposure duration, ISO and lens aperture of AVCaptureDevice are only available on iOS.
+    pub fn capture_metadata(&self) -> CaptureMetadata {
+        let mut metadata = CaptureMetadata::new(self.device.localized_name().to_string());
+        if let Some((duration, iso, aperture)) = self.device.exposure() {
+            metadata.exposure_time = duration.seconds().map(Duration::from_secs_f64);
+            metadata.iso = Some(iso.round() as u32);
+            metadata.lens_aperture = Some(aperture);
+        }
+        metadata
     }
 
-    pub fn set_device(&mut self, device: &CameraDevice) -> bool {
+    pub fn set_device(&mut self, device: &CameraDevice) -> Result<(), Error> {
         if device.id == self.device.unique_id().to_string() {
-            return true;
+            return Ok(());
         }
-        let find_device = AVCaptureDevice::all_video_devices()
+        let new_device = AVCaptureDevice::all_video_devices()
             .into_iter()
-            .find(|d| d.unique_id().to_string() == device.id);
-        if let Some(new_device) = find_device {
-            let new_input = AVCaptureDeviceInput::from_device(&new_device).unwrap();
-            self.session.remove_input(&self.input);
-            self.device = new_device.retain();
-            self.input = new_input;
-            self.session.add_input(&self.input);
-            return true;
+            .find(|d| d.unique_id().to_string() == device.id)
+            .ok_or(Error::NoDevice)?;
+        let new_input = AVCaptureDeviceInput::from_device(&new_device).map_err(Error::os)?;
+        self.session.remove_input(&self.input);
+        self.device = new_device.retain();
+        self.lost.store(false, Ordering::Relaxed);
+        self.input = new_input;
+        self.session.add_input(&self.input);
+        if let Some(orientation) = *lock(&self.orientation) {
+            self.set_video_orientation(orientation);
         }
-        return false;
+        Ok(())
     }
 
     pub fn device_list() -> Vec<CameraDevice> {
-        AVCaptureDevice::all_video_devices()
-            .iter()
-            .map(|device| CameraDevice { id: device.unique_id().to_string(), name: device.localized_name().to_string() })
-            .collect()
+        AVCaptureDevice::all_video_devices().iter().map(camera_device).collect()
+    }
+
+    pub fn device_list_of_types(types: &[DeviceType]) -> Vec<CameraDevice> {
+        AVCaptureDevice::video_devices_of_types(types).iter().map(camera_device).collect()
+    }
+
+    /// AVFoundation hides devices it can't use.
+    pub fn enumerate_with_errors() -> Vec<Result<CameraDevice, EnumError>> {
+        Self::device_list().into_iter().map(Ok).collect()
+    }
+
+    /// The unique ID of AVFoundation is already stable.
+    pub fn stable_id(device: &CameraDevice) -> String {
+        device.id.clone()
+    }
+
+    /// Cameras share the unique ID of the virtual device they are part of, like the dual
+    /// camera of an iPhone.
+    pub fn group_id(device: &CameraDevice) -> String {
+        let virtual_devices = AVCaptureDevice::virtual_devices();
+        let group = virtual_devices.iter().find(|group| {
+            group.constituent_devices().iter().any(|d| d.unique_id().to_string() == device.id)
+        });
+        group.map_or_else(|| device.id.clone(), |group| group.unique_id().to_string())
+    }
+
+    pub fn device_details(device: &CameraDevice) -> DeviceDetails {
+        let Some(device) = AVCaptureDevice::all_video_devices()
+            .to_vec()
+            .into_iter()
+            .find(|d| d.unique_id().to_string() == device.id)
+        else {
+            return DeviceDetails::default();
+        };
+        let (model_id, unique_id) = (device.model_id().to_string(), device.unique_id().to_string());
+        let manufacturer = Some(device.manufacturer().to_string()).filter(|m| !m.is_empty());
+        DeviceDetails { manufacturer, ..details::from_model_id(&model_id, &unique_id) }
+    }
+
+    pub fn describe_device(device: &CameraDevice) -> DeviceCapabilities {
+        let Some(device) = AVCaptureDevice::all_video_devices()
+            .to_vec()
+            .into_iter()
+            .find(|d| d.unique_id().to_string() == device.id)
+        else {
+            return DeviceCapabilities::default();
+        };
+        let formats = device.formats().iter().map(capture_format).collect();
+        DeviceCapabilities { formats }
+    }
+
+    /// The active format of a device outside of a session is its default.
+    pub fn default_format(device: &CameraDevice) -> Option<CaptureFormat> {
+        let device = AVCaptureDevice::all_video_devices()
+            .to_vec()
+            .into_iter()
+            .find(|d| d.unique_id().to_string() == device.id)?;
+        Some(capture_format(&device.active_format()))
+    }
+
+    pub fn backend_capabilities() -> BackendCapabilities {
+        let class = |name| objc2::runtime::AnyClass::get(name).is_some();
+        BackendCapabilities {
+            capture: capture_available(),
+            device_types: class("AVCaptureDeviceDiscoverySession"),
+            face_metadata: class("AVCaptureMetadataOutput"),
+            depth: class("AVCaptureDepthDataOutput"),
+        }
+    }
+}
+
+impl Camera {
+    /// iOS interrupts the session in the background or when another app takes the camera,
+    /// which otherwise only shows up as missing frames.
+    fn report_interruption(&self) -> bool {
+        let interrupted = self.session.is_interrupted();
+        if self.interrupted.swap(interrupted, Ordering::Relaxed) != interrupted {
+            let event =
+                if interrupted { CameraEvent::StreamBlocked } else { CameraEvent::StreamUnblocked };
+            let _ = self.events_tx.send(event);
+        }
+        interrupted
+    }
+
+    /// AVFoundation only stops delivering frames when the device is unplugged.
+    fn report_disconnect(&self) -> bool {
+        let lost = !self.device.is_connected();
+        if lost && !self.lost.swap(true, Ordering::Relaxed) {
+            let _ = self.events_tx.send(CameraEvent::DeviceLost);
+        }
+        lost
+    }
+
+    #[cfg(feature = "raw")]
+    pub(crate) fn raw_session(&self) -> &objc2::runtime::AnyObject {
+        &self.session
+    }
+
+    #[cfg(feature = "raw")]
+    pub(crate) fn raw_device(&self) -> &objc2::runtime::AnyObject {
+        &self.device
+    }
+}
+
+fn capture_format(format: &AVCaptureDeviceFormat) -> CaptureFormat {
+    let (width, height) = format.dimensions();
+    let ranges = format.video_supported_frame_rate_ranges();
+    let min_fps = ranges.iter().map(|r| r.min_frame_rate()).reduce(f64::min);
+    let max_fps = ranges.iter().map(|r| r.max_frame_rate()).reduce(f64::max);
+    CaptureFormat {
+        pixel_format: format.pixel_format(),
+        width,
+        height,
+        min_fps: min_fps.unwrap_or(0.0),
+        max_fps: max_fps.unwrap_or(0.0),
+    }
+}
+
+/// Stops the session and detaches the delegates before they are released, so no callback
+/// reaches a freed delegate. The pool frees what AVFoundation autoreleases on the way, a thread
+/// without a run loop would keep it until it ends.
+impl Drop for Camera {
+    fn drop(&mut self) {
+        objc2::rc::autoreleasepool(|_| {
+            self.session.stop_running();
+            self.output.remove_sample_buffer_delegate();
+            if let Some((output, _)) = lock(&self.metadata_output).as_ref() {
+                output.remove_metadata_objects_delegate();
+                self.session.remove_metadata_output(output);
+            }
+            if let Some((output, _)) = lock(&self.depth_output).as_ref() {
+                output.remove_delegate();
+                self.session.remove_depth_output(output);
+            }
+            self.session.remove_output(&self.output);
+            self.session.remove_input(&self.input);
+        });
+    }
+}
+
+fn camera_device(device: &AVCaptureDevice) -> CameraDevice {
+    CameraDevice {
+        id: device.unique_id().to_string(),
+        name: device.localized_name().to_string(),
+        kind: device.kind(),
     }
 }
 
@@ -89,6 +582,32 @@ impl Frame {
         let (w, h) = self.sample.size_usize();
         (w as _, h as _)
     }
+
+    /// The OS delivers BGRA already.
+    pub fn conversion_time(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    pub fn color_space(&self) -> ColorSpace {
+        self.sample.color_space()
+    }
+
+    pub fn fourcc(&self) -> crate::FourCC {
+        self.sample.fourcc()
+    }
+
+    pub fn capture_info(&self) -> Option<crate::CaptureInfo> {
+        self.sample.capture_info()
+    }
+
+    pub fn timestamp(&self) -> Option<Duration> {
+        self.sample.presentation_time()
+    }
+
+    /// Another reference to the same pixel buffer.
+    pub fn share(&self) -> Option<Frame> {
+        Some(Frame { sample: self.sample.clone() })
+    }
 }
 
 impl<'a> FrameData<'a> {
@@ -99,6 +618,18 @@ impl<'a> FrameData<'a> {
     pub fn data_u32(&self) -> &[u32] {
         self.pixels.u32
     }
+
+    pub fn stride(&self) -> usize {
+        self.pixels.stride
+    }
+
+    pub fn plane_count(&self) -> usize {
+        self.pixels.planes.len()
+    }
+
+    pub fn plane(&self, index: usize) -> Option<PlaneView> {
+        self.pixels.planes.get(index).copied()
+    }
 }
 
 #[cfg(test)]
@@ -106,18 +637,29 @@ const TEST_FRAMES: usize = 3;
 
 #[test]
 fn change_device() {
-    let mut camera = Camera::new_default_device();
-    camera.start();
+    let mut camera = Camera::new_with(&CameraBuilder::default()).unwrap();
+    camera.start().unwrap();
 
     std::iter::from_fn(|| camera.wait_for_frame())
         .map(|s| println!("{s:?}"))
         .take(TEST_FRAMES)
         .count();
 
-    camera.set_device(Camera::device_list().last().unwrap());
+    camera.set_device(Camera::device_list().last().unwrap()).unwrap();
 
     std::iter::from_fn(|| camera.wait_for_frame())
         .map(|s| println!("{s:?}"))
         .take(TEST_FRAMES)
         .count();
 }
+
+#[test]
+fn drop_releases_delegate() {
+    let camera = Camera::new_with(&CameraBuilder::default()).unwrap();
+    camera.start().unwrap();
+    assert!(camera.wait_for_frame().is_some());
+    // the delegate holds the other reference to the slot
+    let slot = Arc::downgrade(&camera.slot);
+    drop(camera);
+    assert!(slot.upgrade().is_none());
+}
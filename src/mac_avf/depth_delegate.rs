@@ -0,0 +1,88 @@
+use std::ffi::c_void;
+use std::sync::{Arc, Mutex};
+
+use objc2_foundation::NSObjectProtocol;
+use objc2::{
+    mutability::Mutable,
+    rc::Id,
+    runtime::NSObject,
+    *,
+};
+
+use super::{
+    CMTime, CVBufferRef, CVPixelBufferGetBaseAddress, CVPixelBufferGetBytesPerRow,
+    CVPixelBufferGetHeight, CVPixelBufferGetWidth, CVPixelBufferLockBaseAddress,
+    CVPixelBufferUnlockBaseAddress,
+};
+use crate::DepthFrame;
+
+pub struct DepthDelegateIvars {
+    depth: Box<Arc<Mutex<Option<Arc<DepthFrame>>>>>,
+}
+
+declare_class!(
+    #[derive(Debug)]
+    pub struct DepthDelegate;
+
+    unsafe impl ClassType for DepthDelegate {
+        type Super = NSObject;
+        type Mutability = Mutable;
+        const NAME: &'static str = "DepthDelegate";
+    }
+
+    impl DeclaredClass for DepthDelegate {
+        type Ivars = DepthDelegateIvars;
+    }
+
+    unsafe impl DepthDelegate {
+        #[method(depthDataOutput:didOutputDepthData:timestamp:connection:)]
+        unsafe fn on_output_depth_data(
+            &mut self,
+            _output: *const c_void,
+            depth_data: &NSObject,
+            _timestamp: CMTime,
+            _connection: *const c_void,
+        ) {
+            let depth = depth_frame(depth_data).map(Arc::new);
+            *self.ivars().depth.lock().unwrap() = depth;
+        }
+    }
+
+    unsafe impl NSObjectProtocol for DepthDelegate {}
+);
+
+impl DepthDelegate {
+    /// Writes the most recent depth map to `depth`.
+    pub fn new(depth: Arc<Mutex<Option<Arc<DepthFrame>>>>) -> Id<Self> {
+        let this = DepthDelegate::alloc();
+        let this = this.set_ivars(DepthDelegateIvars { depth: Box::new(depth) });
+        unsafe { msg_send_id![super(this), init] }
+    }
+}
+
+/// AVDepthData converted to meters as 32 bit floats, dual cameras deliver disparity otherwise.
+unsafe fn depth_frame(depth_data: &NSObject) -> Option<DepthFrame> {
+    // kCVPixelFormatType_DepthFloat32
+    let float_meters = u32::from_be_bytes(*b"fdep");
+    let depth: Option<Id<NSObject>> =
+        msg_send_id![depth_data, depthDataByConvertingToDepthDataType: float_meters];
+    let map: CVBufferRef = msg_send![&depth?, depthDataMap];
+    // kCVPixelBufferLock_ReadOnly
+    if CVPixelBufferLockBaseAddress(map, 1) != 0 {
+        return None;
+    }
+    let (width, height) = (CVPixelBufferGetWidth(map), CVPixelBufferGetHeight(map));
+    let stride = CVPixelBufferGetBytesPerRow(map);
+    let base = CVPixelBufferGetBaseAddress(map);
+    let mut data = Vec::with_capacity(width * height);
+    for y in 0..height {
+        let row = std::slice::from_raw_parts(base.add(y * stride), width * 4);
+        data.extend(row.chunks_exact(4).map(|meters| {
+            let millimeters = f32::from_ne_bytes(meters.try_into().unwrap()) * 1000.0;
+            // NaN where nothing was measured becomes 0
+            millimeters.round().clamp(0.0, u16::MAX as f32) as u16
+        }));
+    }
+    CVPixelBufferUnlockBaseAddress(map, 1);
+    Some(DepthFrame { width: width as u32, height: height as u32, data })
+}
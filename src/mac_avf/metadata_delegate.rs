@@ -0,0 +1,87 @@
+use std::ffi::c_void;
+use std::sync::{Arc, Mutex};
+
+use objc2_foundation::{CGRect, NSArray, NSObjectProtocol, NSString};
+use objc2::{
+    mutability::Mutable,
+    rc::Id,
+    runtime::NSObject,
+    *,
+};
+
+use super::AVCaptureMetadataOutput;
+use crate::FaceRect;
+
+pub struct MetadataDelegateIvars {
+    faces: Box<Arc<Mutex<Vec<FaceRect>>>>,
+}
+
+declare_class!(
+    #[derive(Debug)]
+    pub struct MetadataDelegate;
+
+    unsafe impl ClassType for MetadataDelegate {
+        type Super = NSObject;
+        type Mutability = Mutable;
+        const NAME: &'static str = "MetadataDelegate";
+    }
+
+    impl DeclaredClass for MetadataDelegate {
+        type Ivars = MetadataDelegateIvars;
+    }
+
+    unsafe impl MetadataDelegate {
+        /// Called with all faces in view whenever they change, with none when the last one left.
+        #[method(captureOutput:didOutputMetadataObjects:fromConnection:)]
+        unsafe fn on_output_metadata_objects(
+            &mut self,
+            _capture_output: *const c_void,
+            objects: &NSArray<NSObject>,
+            _connection: *const c_void,
+        ) {
+            let faces = objects.iter().filter_map(|object| face_rect(object)).collect();
+            *self.ivars().faces.lock().unwrap() = faces;
+        }
+    }
+
+    unsafe impl NSObjectProtocol for MetadataDelegate {}
+);
+
+impl MetadataDelegate {
+    /// Writes the faces of the most recent metadata to `faces`.
+    pub fn new(faces: Arc<Mutex<Vec<FaceRect>>>) -> Id<Self> {
+        let this = MetadataDelegate::alloc();
+        let this = this.set_ivars(MetadataDelegateIvars { faces: Box::new(faces) });
+        unsafe { msg_send_id![super(this), init] }
+    }
+}
+
+/// The bounds of an AVMetadataFaceObject are already normalized with the origin at the top left.
+unsafe fn face_rect(object: &NSObject) -> Option<FaceRect> {
+    let kind: Id<NSString> = msg_send_id![object, type];
+    if *kind != *AVCaptureMetadataOutput::metadata_object_type_face() {
+        return None;
+    }
+    let bounds: CGRect = msg_send![object, bounds];
+    let id: isize = msg_send![object, faceID];
+    Some(FaceRect {
+        x: bounds.origin.x as f32,
+        y: bounds.origin.y as f32,
+        width: bounds.size.width as f32,
+        height: bounds.size.height as f32,
+        id: Some(id as i64),
+    })
+}
+
+#[test]
+fn no_faces() {
+    let faces = Arc::new(Mutex::new(vec![FaceRect::default()]));
+    let delegate = MetadataDelegate::new(faces.clone());
+    let output: *const c_void = std::ptr::null();
+    let objects = NSArray::<NSObject>::new();
+    let connection: *const c_void = std::ptr::null();
+    let () = unsafe {
+        msg_send![&delegate, captureOutput: output didOutputMetadataObjects: &*objects fromConnection: connection]
+    };
+    assert!(faces.lock().unwrap().is_empty());
+}
@@ -1,9 +1,13 @@
+mod av_capture_depth_data_output;
 mod av_capture_device;
 mod av_capture_device_format;
 mod av_capture_device_input;
+mod av_capture_metadata_output;
 mod av_capture_session;
 mod av_capture_video_data_output;
 mod camera;
+mod depth_delegate;
+mod metadata_delegate;
 #[cfg(test)]
 mod reflect_class;
 mod sample_buffer;
@@ -14,12 +18,16 @@ mod video_output_settings;
 
 pub use objc2::*;
 
+pub use av_capture_depth_data_output::*;
 pub use av_capture_device::*;
 pub use av_capture_device_format::*;
 pub use av_capture_device_input::*;
+pub use av_capture_metadata_output::*;
 pub use av_capture_session::*;
 pub use av_capture_video_data_output::*;
 pub use camera::*;
+pub use depth_delegate::*;
+pub use metadata_delegate::*;
 pub use sample_buffer::*;
 pub use sample_buffer_delegate::*;
 pub use video_output_settings::*;
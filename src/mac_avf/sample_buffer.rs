@@ -1,12 +1,21 @@
 use std::ffi::c_void;
+use std::ptr::null_mut;
+use std::time::Duration;
 
 use objc2::{Encode, Encoding, RefEncode};
 
+use crate::{CaptureInfo, ColorSpace, FourCC, PlaneView, TransferFunction, YuvMatrix};
+
 pub struct SampleBuffer {
     inner: CMSampleBufferRef,
 }
 
 impl SampleBuffer {
+    pub fn fourcc(&self) -> FourCC {
+        let ibuf = unsafe { CMSampleBufferGetImageBuffer(self.inner) };
+        core_video_fourcc(unsafe { CVPixelBufferGetPixelFormatType(ibuf) })
+    }
+
     pub fn new(sample_buffer: CMSampleBufferRef) -> Self {
         Self { inner: unsafe { CFRetain(sample_buffer.cast()).cast_mut().cast() } }
     }
@@ -19,6 +28,100 @@ impl SampleBuffer {
     }
 }
 
+impl SampleBuffer {
+    /// The range is not attached to the converted buffer and stays unknown.
+    pub fn color_space(&self) -> ColorSpace {
+        let ibuf = unsafe { CMSampleBufferGetImageBuffer(self.inner) };
+        let matrix =
+            unsafe { CVBufferGetAttachment(ibuf, kCVImageBufferYCbCrMatrixKey, null_mut()) };
+        let transfer =
+            unsafe { CVBufferGetAttachment(ibuf, kCVImageBufferTransferFunctionKey, null_mut()) };
+        let is = |value: CFTypeRef, name: CFStringRef| {
+            !value.is_null() && unsafe { CFEqual(value, name) }
+        };
+
+        let matrix = unsafe {
+            if is(matrix, kCVImageBufferYCbCrMatrix_ITU_R_601_4) {
+                YuvMatrix::Bt601
+            } else if is(matrix, kCVImageBufferYCbCrMatrix_ITU_R_709_2) {
+                YuvMatrix::Bt709
+            } else if is(matrix, kCVImageBufferYCbCrMatrix_ITU_R_2020) {
+                YuvMatrix::Bt2020
+            } else {
+                YuvMatrix::Unknown
+            }
+        };
+        let transfer = unsafe {
+            if is(transfer, kCVImageBufferTransferFunction_sRGB) {
+                TransferFunction::Srgb
+            } else if is(transfer, kCVImageBufferTransferFunction_ITU_R_709_2) {
+                TransferFunction::Bt709
+            } else if is(transfer, kCVImageBufferTransferFunction_ITU_R_2020) {
+                TransferFunction::Bt2020
+            } else {
+                TransferFunction::Unknown
+            }
+        };
+        ColorSpace { matrix, transfer, ..Default::default() }
+    }
+}
+
+impl SampleBuffer {
+    /// The capture time on the host time clock, which the capture session uses.
+    pub fn presentation_time(&self) -> Option<Duration> {
+        unsafe { CMSampleBufferGetPresentationTimeStamp(self.inner) }.duration()
+    }
+
+    /// From the EXIF attachment, which AVFoundation adds to the samples of cameras with
+    /// exposure control.
+    pub fn capture_info(&self) -> Option<CaptureInfo> {
+        let exif = unsafe {
+            CMGetAttachment(self.inner.cast(), kCGImagePropertyExifDictionary, null_mut())
+        };
+        if exif.is_null() {
+            return None;
+        }
+        let number = |value: CFTypeRef| {
+            let mut number = 0f64;
+            // kCFNumberFloat64Type
+            let ok = !value.is_null() && unsafe { CFNumberGetValue(value, 6, &mut number) };
+            ok.then_some(number)
+        };
+        let get = |key: CFStringRef| number(unsafe { CFDictionaryGetValue(exif, key) });
+        // one rating per sensitivity, cameras have one
+        let ratings = unsafe { CFDictionaryGetValue(exif, kCGImagePropertyExifISOSpeedRatings) };
+        let iso = (!ratings.is_null() && unsafe { CFArrayGetCount(ratings) } > 0)
+            .then(|| number(unsafe { CFArrayGetValueAtIndex(ratings, 0) }))
+            .flatten();
+        let (exposure_time, aperture, focal_length) = unsafe {
+            (
+                get(kCGImagePropertyExifExposureTime),
+                get(kCGImagePropertyExifFNumber),
+                get(kCGImagePropertyExifFocalLength),
+            )
+        };
+        Some(CaptureInfo {
+            exposure_time: exposure_time
+                .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok()),
+            iso: iso.map(|iso| iso as f32),
+            lens_aperture: aperture.map(|f| f as f32),
+            focal_length: focal_length.map(|mm| mm as f32),
+        })
+    }
+}
+
+// CMSampleBuffer is a CoreFoundation object with thread safe reference counting.
+unsafe impl Send for SampleBuffer {}
+// The pixel buffer is only locked read-only, which CoreVideo allows from several threads.
+unsafe impl Sync for SampleBuffer {}
+
+/// Retains the sample buffer again, both share its pixels.
+impl Clone for SampleBuffer {
+    fn clone(&self) -> Self {
+        Self::new(self.inner)
+    }
+}
+
 impl Drop for SampleBuffer {
     fn drop(&mut self) {
         unsafe { CFRelease(self.inner.cast()) };
@@ -51,6 +154,10 @@ extern "C" {
     pub fn CMSampleBufferGetFormatDescription(sbuf: CMSampleBufferRef) -> CMFormatDescriptionRef;
     pub fn CMSampleBufferGetImageBuffer(sbuf: CMSampleBufferRef) -> CVImageBufferRef;
     pub fn CMFormatDescriptionGetMediaSubType(desc: CMFormatDescriptionRef) -> u32;
+    pub fn CMGetAttachment(target: CFTypeRef, key: CFStringRef, mode: *mut u32) -> CFTypeRef;
+    pub fn CMSampleBufferGetPresentationTimeStamp(sbuf: CMSampleBufferRef) -> CMTime;
+    pub fn CMClockGetHostTimeClock() -> CFTypeRef;
+    pub fn CMClockGetTime(clock: CFTypeRef) -> CMTime;
     pub fn CMVideoFormatDescriptionGetDimensions(desc: CMFormatDescriptionRef)
         -> CMVideoDimensions;
 }
@@ -59,6 +166,20 @@ extern "C" {
 extern "C" {
     pub fn CFRetain(cf: *const c_void) -> *const c_void;
     pub fn CFRelease(cf: *const c_void);
+    pub fn CFEqual(cf1: CFTypeRef, cf2: CFTypeRef) -> bool;
+    pub fn CFDictionaryGetValue(dict: CFTypeRef, key: CFTypeRef) -> CFTypeRef;
+    pub fn CFArrayGetCount(array: CFTypeRef) -> isize;
+    pub fn CFArrayGetValueAtIndex(array: CFTypeRef, index: isize) -> CFTypeRef;
+    pub fn CFNumberGetValue(number: CFTypeRef, kind: isize, value: *mut f64) -> bool;
+}
+
+#[link(name = "ImageIO", kind = "framework")]
+extern "C" {
+    pub static kCGImagePropertyExifDictionary: CFStringRef;
+    pub static kCGImagePropertyExifExposureTime: CFStringRef;
+    pub static kCGImagePropertyExifISOSpeedRatings: CFStringRef;
+    pub static kCGImagePropertyExifFNumber: CFStringRef;
+    pub static kCGImagePropertyExifFocalLength: CFStringRef;
 }
 
 #[link(name = "CoreVideo", kind = "framework")]
@@ -71,18 +192,39 @@ extern "C" {
     pub fn CVPixelBufferGetHeight(buf: CVBufferRef) -> usize;
     pub fn CVPixelBufferIsPlanar(buf: CVBufferRef) -> bool;
     pub fn CVPixelBufferGetPlaneCount(buf: CVBufferRef) -> usize;
+    pub fn CVPixelBufferGetWidthOfPlane(buf: CVBufferRef, index: usize) -> usize;
     pub fn CVPixelBufferGetHeightOfPlane(buf: CVBufferRef, index: usize) -> usize;
     pub fn CVPixelBufferGetBytesPerRowOfPlane(buf: CVBufferRef, index: usize) -> usize;
     pub fn CVPixelBufferGetDataSize(buf: CVBufferRef) -> usize;
     pub fn CVPixelBufferGetPixelFormatType(buf: CVBufferRef) -> u32;
     pub fn CVPixelBufferGetBaseAddressOfPlane(buf: CVBufferRef, index: usize) -> *const u8;
+    pub fn CVBufferGetAttachment(buf: CVBufferRef, key: CFStringRef, mode: *mut u32) -> CFTypeRef;
+
+    pub static kCVImageBufferYCbCrMatrixKey: CFStringRef;
+    pub static kCVImageBufferYCbCrMatrix_ITU_R_601_4: CFStringRef;
+    pub static kCVImageBufferYCbCrMatrix_ITU_R_709_2: CFStringRef;
+    pub static kCVImageBufferYCbCrMatrix_ITU_R_2020: CFStringRef;
+    pub static kCVImageBufferTransferFunctionKey: CFStringRef;
+    pub static kCVImageBufferTransferFunction_sRGB: CFStringRef;
+    pub static kCVImageBufferTransferFunction_ITU_R_709_2: CFStringRef;
+    pub static kCVImageBufferTransferFunction_ITU_R_2020: CFStringRef;
 }
 
+pub type CFTypeRef = *const c_void;
+pub type CFStringRef = *const c_void;
+
 #[repr(C)]
 pub struct CVBuffer {
     _priv: [u8; 0],
 }
 pub type CVBufferRef = *const CVBuffer;
+
+unsafe impl Encode for CVBuffer {
+    const ENCODING: Encoding = Encoding::Struct("__CVBuffer", &[]);
+}
+unsafe impl RefEncode for CVBuffer {
+    const ENCODING_REF: Encoding = Encoding::Pointer(&Self::ENCODING);
+}
 pub type CVImageBufferRef = CVBufferRef;
 
 #[repr(C)]
@@ -92,6 +234,42 @@ pub struct CMVideoDimensions {
     pub height: i32,
 }
 
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct CMTime {
+    pub value: i64,
+    pub timescale: i32,
+    pub flags: u32,
+    pub epoch: i64,
+}
+
+unsafe impl Encode for CMTime {
+    const ENCODING: Encoding =
+        Encoding::Struct("?", &[i64::ENCODING, i32::ENCODING, u32::ENCODING, i64::ENCODING]);
+}
+
+impl CMTime {
+    /// `None` unless the valid flag is set.
+    pub fn seconds(&self) -> Option<f64> {
+        (self.flags & 1 != 0 && self.timescale != 0)
+            .then(|| self.value as f64 / self.timescale as f64)
+    }
+
+    /// Like [`Self::seconds`] without the rounding of `f64`, `None` for negative times too.
+    pub fn duration(&self) -> Option<Duration> {
+        if self.flags & 1 == 0 || self.timescale <= 0 || self.value < 0 {
+            return None;
+        }
+        let nanos = self.value as u128 * 1_000_000_000 / self.timescale as u128;
+        Some(Duration::from_nanos(nanos as u64))
+    }
+}
+
+/// Now on the host time clock.
+pub fn host_time() -> Option<Duration> {
+    unsafe { CMClockGetTime(CMClockGetHostTimeClock()) }.duration()
+}
+
 #[repr(C)]
 pub struct CMSampleBuffer {
     _priv: [u8; 0],
@@ -111,6 +289,13 @@ pub struct CMFormatDescription {
 }
 pub type CMFormatDescriptionRef = *mut CMFormatDescription;
 
+unsafe impl Encode for CMFormatDescription {
+    const ENCODING: Encoding = Encoding::Struct("opaqueCMFormatDescription", &[]);
+}
+unsafe impl RefEncode for CMFormatDescription {
+    const ENCODING_REF: Encoding = Encoding::Pointer(&Self::ENCODING);
+}
+
 /// FOURCC is a little crazy. Look at some references to interpret this obfuscation.
 /// Look also into Chromium. There you can see that NV12 is a preferred format, 420v on Mac.
 ///
@@ -122,17 +307,15 @@ pub type CMFormatDescriptionRef = *mut CMFormatDescription;
 /// <https://softron.zendesk.com/hc/en-us/articles/207695697-List-of-FourCC-codes-for-video-codecs>
 /// <http://abcavi.kibi.ru/fourcc.php>
 pub fn fourcc_to_string(px_format_u32: u32) -> String {
-    let bytes = px_format_u32.to_ne_bytes();
+    core_video_fourcc(px_format_u32).to_string()
+}
 
-    if bytes[1..4] == [0, 0, 0] {
-        match bytes[0] {
-            32 => "ARGB",
-            24 => "RGB ",
-            _ => return format!("0x{px_format_u32:08X}"),
-        }
-        .into()
-    } else {
-        String::from_utf8_lossy(&bytes).to_string()
+/// The old packed RGB formats are plain numbers.
+pub fn core_video_fourcc(code: u32) -> FourCC {
+    match code {
+        32 => FourCC::new(b"ARGB"),
+        24 => FourCC::new(b"RGB "),
+        code => FourCC::from_be(code),
     }
 }
 
@@ -143,6 +326,8 @@ pub struct Pixels<'a> {
     pub u32: &'a [u32],
     pub width: usize,
     pub height: usize,
+    pub stride: usize,
+    pub planes: Vec<PlaneView<'a>>,
 }
 
 impl<'a> Pixels<'a> {
@@ -159,15 +344,24 @@ impl<'a> Pixels<'a> {
         let _fourcc = unsafe { CVPixelBufferGetPixelFormatType(ibuf) };
         let plane_address = unsafe { CVPixelBufferGetBaseAddressOfPlane(ibuf, 0) };
         let mut plane_sizes = 0;
+        let mut planes = vec![];
 
         // println!("pixels {:?}", (_address, stride, width, height, is_planar, plane_count, _data_size, fourcc_to_string(_fourcc)));
         if is_planar {
             for index in 0..plane_count {
-                let _plane_address = unsafe { CVPixelBufferGetBaseAddressOfPlane(ibuf, index) };
+                let plane_address = unsafe { CVPixelBufferGetBaseAddressOfPlane(ibuf, index) };
                 let plane_stride = unsafe { CVPixelBufferGetBytesPerRowOfPlane(ibuf, index) };
+                let plane_width = unsafe { CVPixelBufferGetWidthOfPlane(ibuf, index) };
                 let plane_height = unsafe { CVPixelBufferGetHeightOfPlane(ibuf, index) };
                 // println!("        {:?}", (plane_address, plane_stride, plane_height));
-                plane_sizes += plane_stride * plane_height;
+                let plane_len = plane_stride * plane_height;
+                plane_sizes += plane_len;
+                planes.push(PlaneView {
+                    data: unsafe { std::slice::from_raw_parts(plane_address, plane_len) },
+                    stride: plane_stride,
+                    width: plane_width as _,
+                    height: plane_height as _,
+                });
             }
         } else {
             plane_sizes += stride * height;
@@ -176,7 +370,10 @@ impl<'a> Pixels<'a> {
         let data = unsafe { std::slice::from_raw_parts(plane_address, plane_sizes) };
         let (a, u32, b) = unsafe { data.align_to() };
         debug_assert!(a.is_empty() && b.is_empty());
-        Self { ibuf, data, u32, width, height }
+        if !is_planar {
+            planes.push(PlaneView { data, stride, width: width as _, height: height as _ });
+        }
+        Self { ibuf, data, u32, width, height, stride, planes }
     }
 }
 
@@ -185,3 +382,11 @@ impl Drop for Pixels<'_> {
         assert_eq!(0, unsafe { CVPixelBufferUnlockBaseAddress(self.ibuf, 1) });
     }
 }
+
+#[test]
+fn fourcc_to_string_reads_big_endian_codes() {
+    // kCVPixelFormatType_420YpCbCr8BiPlanarVideoRange and kCVPixelFormatType_32BGRA
+    assert_eq!(fourcc_to_string(0x3432_3076), "420v");
+    assert_eq!(fourcc_to_string(0x4247_5241), "BGRA");
+    assert_eq!(fourcc_to_string(32), "ARGB");
+}
@@ -1,7 +1,10 @@
+use std::collections::VecDeque;
 use std::ffi::c_void;
-use std::ptr::null_mut;
-use std::sync::atomic::AtomicPtr;
+use std::io::{Read, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::net::UnixStream;
 use std::sync::{Arc, Condvar, Mutex};
+use std::time::Instant;
 
 use objc2_foundation::NSObjectProtocol;
 use objc2::{
@@ -11,13 +14,16 @@ use objc2::{
     *,
 };
 
-use super::{CMSampleBuffer, CMSampleBufferRef, SampleBuffer};
+use super::{CMSampleBufferRef, SampleBuffer};
+use crate::rate_limit::FrameRateLimit;
+use crate::sync::{lock, wait};
 
 pub struct SampleBufferIvars {
     slot: Box<Arc<Slot>>,
 }
 
 declare_class!(
+    #[derive(Debug)]
     pub struct SampleBufferDelegate;
 
     unsafe impl ClassType for SampleBufferDelegate {
@@ -57,13 +63,13 @@ declare_class!(
 );
 
 impl SampleBufferDelegate {
-    pub fn new() -> Id<Self> {
+    pub fn new() -> std::io::Result<Id<Self>> {
         let this = SampleBufferDelegate::alloc();
         let this = this.set_ivars(SampleBufferIvars {
-            slot: Box::new(Arc::new(Slot::new())),
+            slot: Box::new(Arc::new(Slot::new()?)),
         });
         let this = unsafe { msg_send_id![super(this), init] };
-        this
+        Ok(this)
     }
 
     pub fn slot(&self) -> Arc<Slot> {
@@ -71,75 +77,134 @@ impl SampleBufferDelegate {
     }
 
     fn set_slot(&mut self, sample: CMSampleBufferRef) {
-        let slot = &self.ivars().slot;
-        slot.set_sample(sample);
-        slot.notify_all();
+        self.ivars().slot.put_sample(sample);
     }
 }
 
 #[derive(Debug)]
 pub struct Slot {
-    sample: AtomicPtr<CMSampleBuffer>,
     state: Mutex<State>,
     condvar: Condvar,
+    /// Socket pair used as a pollable notification, readable as long as samples are queued.
+    ready_rx: UnixStream,
+    ready_tx: UnixStream,
+    rate_limit: FrameRateLimit,
 }
 
 impl Slot {
-    fn new() -> Self {
-        Self {
-            sample: AtomicPtr::new(null_mut()),
-            state: Mutex::new(State { frame_counter: 0 }),
+    pub(crate) fn new() -> std::io::Result<Self> {
+        let (ready_rx, ready_tx) = UnixStream::pair()?;
+        ready_rx.set_nonblocking(true)?;
+        ready_tx.set_nonblocking(true)?;
+        Ok(Self {
+            state: Mutex::new(State { frame_counter: 0, queue_size: 1, samples: VecDeque::new() }),
             condvar: Condvar::new(),
-        }
+            ready_rx,
+            ready_tx,
+            rate_limit: FrameRateLimit::default(),
+        })
     }
 
+    /// Waits until a sample arrives which was not returned before and returns the oldest one.
     pub fn wait_for_sample(&self) -> Option<SampleBuffer> {
-        let mut _guard = self.state.lock().unwrap();
-        _guard = self.condvar.wait(_guard).unwrap();
-        let ptr = self.sample.load(std::sync::atomic::Ordering::Relaxed);
-        if ptr.is_null() {
-            None
-        } else {
-            Some(SampleBuffer::new(ptr))
+        let mut state = lock(&self.state);
+        while state.samples.is_empty() {
+            state = wait(&self.condvar, state);
+        }
+        self.pop_sample(&mut state, false)
+    }
+
+    /// Oldest unread sample without waiting.
+    pub fn try_sample(&self) -> Option<SampleBuffer> {
+        let mut state = lock(&self.state);
+        self.pop_sample(&mut state, false)
+    }
+
+    /// Like `wait_for_sample` but returns the newest sample and drops the older ones.
+    pub fn wait_for_latest_sample(&self) -> Option<SampleBuffer> {
+        let mut state = lock(&self.state);
+        while state.samples.is_empty() {
+            state = wait(&self.condvar, state);
         }
+        self.pop_sample(&mut state, true)
     }
 
-    fn set_sample(&self, mut sample: CMSampleBufferRef) {
-        // TODO should instead use SampleBuffer directly, it already wraps Retain and Release
-        sample = if !sample.is_null() {
-            unsafe { super::CFRetain(sample.cast()).cast_mut().cast() }
-        } else {
-            sample
+    fn pop_sample(&self, state: &mut State, latest: bool) -> Option<SampleBuffer> {
+        let sample = match latest {
+            true => state.samples.drain(..).last().flatten(),
+            false => state.samples.pop_front().flatten(),
         };
-        let old_sample = self.sample.swap(sample, std::sync::atomic::Ordering::Relaxed);
-        if !old_sample.is_null() {
-            unsafe { super::CFRelease(old_sample.cast()) };
+        if state.samples.is_empty() {
+            self.drain_ready();
         }
+        sample
     }
 
-    fn notify_all(&self) {
-        self.condvar.notify_all();
+    pub fn frame_ready_fd(&self) -> std::os::fd::RawFd {
+        self.ready_rx.as_raw_fd()
+    }
+
+    /// Number of unread samples kept before the oldest is dropped. Every queued sample holds
+    /// a buffer of the capture pool, when the pool runs dry AVFoundation drops new frames.
+    pub fn set_queue_size(&self, queue_size: usize) {
+        let mut state = lock(&self.state);
+        state.queue_size = queue_size.max(1);
+        let excess = state.samples.len().saturating_sub(state.queue_size);
+        state.samples.drain(..excess);
+    }
+
+    /// Drops samples arriving faster than `fps` before they are queued.
+    pub fn set_max_fps(&self, fps: f32) {
+        self.rate_limit.set_max_fps(fps);
+    }
+
+    /// Queues `sample` and wakes up waiting readers, a null sample is read as `None`.
+    pub(crate) fn put_sample(&self, sample: CMSampleBufferRef) {
+        self.set_sample(sample);
+        self.notify_all();
     }
-}
 
-impl Drop for Slot {
-    fn drop(&mut self) {
-        let sample = self.sample.load(std::sync::atomic::Ordering::Relaxed);
-        if !sample.is_null() {
-            unsafe { super::CFRelease(sample.cast()) };
+    fn set_sample(&self, sample: CMSampleBufferRef) {
+        if !sample.is_null() && !self.rate_limit.accept(Instant::now()) {
+            return;
         }
+        let sample = (!sample.is_null()).then(|| SampleBuffer::new(sample));
+        let mut state = lock(&self.state);
+        if state.samples.len() == state.queue_size {
+            state.samples.pop_front();
+        }
+        state.samples.push_back(sample);
+        state.frame_counter += 1;
+        // A full socket buffer still means readable, so a failed write can be ignored.
+        let _ = (&self.ready_tx).write(&[1]);
+    }
+
+    fn drain_ready(&self) {
+        let mut buf = [0u8; 64];
+        while let Ok(n) = (&self.ready_rx).read(&mut buf) {
+            if n == 0 {
+                break;
+            }
+        }
+    }
+
+    fn notify_all(&self) {
+        self.condvar.notify_all();
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct State {
     pub frame_counter: usize,
+    pub queue_size: usize,
+    /// Unread samples, `None` for null sample buffers.
+    samples: VecDeque<Option<SampleBuffer>>,
 }
 
 #[test]
 fn msg_send_to_on_output_sample_buffer() {
-    use std::ptr::null;
-    let delegate = SampleBufferDelegate::new();
+    use std::ptr::{null, null_mut};
+    let delegate = SampleBufferDelegate::new().unwrap();
     let output: *const c_void = null();
     let buffer: CMSampleBufferRef = null_mut();
     let connection: *const c_void = null();
@@ -150,8 +215,8 @@ fn msg_send_to_on_output_sample_buffer() {
 
 #[test]
 fn msg_send_to_on_drop_sample_buffer() {
-    use std::ptr::null;
-    let delegate = SampleBufferDelegate::new();
+    use std::ptr::{null, null_mut};
+    let delegate = SampleBufferDelegate::new().unwrap();
     let output: *const c_void = null();
     let buffer: CMSampleBufferRef = null_mut();
     let connection: *const c_void = null();
@@ -162,6 +227,45 @@ fn msg_send_to_on_drop_sample_buffer() {
 
 #[test]
 fn slot() {
-    let delegate = SampleBufferDelegate::new();
+    let delegate = SampleBufferDelegate::new().unwrap();
     println!("slot {:?}", delegate.slot());
 }
+
+#[test]
+fn slot_does_not_block_for_unread_sample() {
+    use std::ptr::{null, null_mut};
+    let delegate = SampleBufferDelegate::new().unwrap();
+    let slot = delegate.slot();
+    let output: *const c_void = null();
+    let buffer: CMSampleBufferRef = null_mut();
+    let connection: *const c_void = null();
+    let () = unsafe {
+        msg_send![&delegate, captureOutput: output didOutputSampleBuffer: buffer fromConnection: connection]
+    };
+    let mut byte = [0u8; 1];
+    assert_eq!(1, (&slot.ready_rx).read(&mut byte).unwrap());
+    assert!(slot.wait_for_sample().is_none());
+}
+
+#[test]
+fn slot_queue_size() {
+    use std::ptr::null_mut;
+    let slot = Slot::new().unwrap();
+    slot.set_queue_size(2);
+    for _ in 0..3 {
+        slot.set_sample(null_mut());
+    }
+    assert_eq!(2, slot.state.lock().unwrap().samples.len());
+    assert!(slot.wait_for_sample().is_none());
+    assert!(slot.wait_for_sample().is_none());
+    assert!(slot.state.lock().unwrap().samples.is_empty());
+}
+
+#[test]
+fn dealloc_drops_slot() {
+    let delegate = SampleBufferDelegate::new().unwrap();
+    let slot = Arc::downgrade(&delegate.slot());
+    assert!(slot.upgrade().is_some());
+    drop(delegate);
+    assert!(slot.upgrade().is_none());
+}
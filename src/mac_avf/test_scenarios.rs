@@ -6,13 +6,13 @@ const TEST_FRAMES: usize = 3;
 
 #[test]
 fn running_capture_session() {
-    let device = AVCaptureDevice::default_video_device();
+    let device = AVCaptureDevice::default_video_device().unwrap();
     let input = AVCaptureDeviceInput::from_device(&device).unwrap();
     let output = AVCaptureVideoDataOutput::new();
-    let delegate = SampleBufferDelegate::new();
+    let delegate = SampleBufferDelegate::new().unwrap();
     let slot = delegate.slot();
     let session = AVCaptureSession::new();
-    output.set_sample_buffer_delegate(delegate);
+    output.set_sample_buffer_delegate(&delegate);
     session.add_input(&input);
     session.add_output(&output);
     session.start_running();
@@ -32,10 +32,10 @@ fn running_capture_session_for_all_cameras() {
         println!("{}", device.localized_name());
         let input = AVCaptureDeviceInput::from_device(&device).unwrap();
         let output = AVCaptureVideoDataOutput::new();
-        let delegate = SampleBufferDelegate::new();
+        let delegate = SampleBufferDelegate::new().unwrap();
         let slot = delegate.slot();
         let session = AVCaptureSession::new();
-        output.set_sample_buffer_delegate(delegate);
+        output.set_sample_buffer_delegate(&delegate);
         session.add_input(&input);
         session.add_output(&output);
         session.start_running();
@@ -57,10 +57,10 @@ fn running_capture_session_for_all_cameras_in_rgb32() {
         let input = AVCaptureDeviceInput::from_device(&device).unwrap();
         let output = AVCaptureVideoDataOutput::new();
         output.set_video_settings(&video_settings_rgb32());
-        let delegate = SampleBufferDelegate::new();
+        let delegate = SampleBufferDelegate::new().unwrap();
         let slot = delegate.slot();
         let session = AVCaptureSession::new();
-        output.set_sample_buffer_delegate(delegate);
+        output.set_sample_buffer_delegate(&delegate);
         session.add_input(&input);
         session.add_output(&output);
         session.start_running();
@@ -82,10 +82,10 @@ fn running_capture_session_for_all_cameras_in_rgb24() {
         let input = AVCaptureDeviceInput::from_device(&device).unwrap();
         let output = AVCaptureVideoDataOutput::new();
         output.set_video_settings(&video_settings_rgb24());
-        let delegate = SampleBufferDelegate::new();
+        let delegate = SampleBufferDelegate::new().unwrap();
         let slot = delegate.slot();
         let session = AVCaptureSession::new();
-        output.set_sample_buffer_delegate(delegate);
+        output.set_sample_buffer_delegate(&delegate);
         session.add_input(&input);
         session.add_output(&output);
         session.start_running();
@@ -107,10 +107,10 @@ fn running_capture_session_for_all_cameras_in_yuv2() {
         let input = AVCaptureDeviceInput::from_device(&device).unwrap();
         let output = AVCaptureVideoDataOutput::new();
         output.set_video_settings(&video_settings_from_pixel_format("yuv2"));
-        let delegate = SampleBufferDelegate::new();
+        let delegate = SampleBufferDelegate::new().unwrap();
         let slot = delegate.slot();
         let session = AVCaptureSession::new();
-        output.set_sample_buffer_delegate(delegate);
+        output.set_sample_buffer_delegate(&delegate);
         session.add_input(&input);
         session.add_output(&output);
         session.start_running();
@@ -129,9 +129,9 @@ fn running_capture_session_from_changing_cameras() {
     println!();
     let session = AVCaptureSession::new();
     let output = AVCaptureVideoDataOutput::new();
-    let delegate = SampleBufferDelegate::new();
+    let delegate = SampleBufferDelegate::new().unwrap();
     let slot = delegate.slot();
-    output.set_sample_buffer_delegate(delegate);
+    output.set_sample_buffer_delegate(&delegate);
     session.add_output(&output);
 
     let mut input: Option<Id<AVCaptureDeviceInput>> = None;
@@ -0,0 +1,274 @@
+//! Screen capture through ScreenCaptureKit, macOS 12.3 and newer. See [`screen`](crate::screen).
+//!
+//! Sample buffers arrive on a dispatch queue and go through the same [`Slot`] as camera samples.
+
+use std::ffi::{c_void, CString};
+use std::ptr::{null, null_mut};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex};
+
+use block2::{Block, RcBlock};
+use objc2::rc::Id;
+use objc2::runtime::NSObject;
+use objc2::{
+    declare_class, extern_class, msg_send, msg_send_id, mutability, ClassType, DeclaredClass,
+};
+use objc2_foundation::{NSArray, NSError, NSObjectProtocol};
+
+use crate::mac_avf::{
+    dispatch_queue_create, dispatch_release, CMSampleBufferGetImageBuffer, CMSampleBufferRef,
+    SampleBuffer, Slot,
+};
+use crate::sync::lock;
+use crate::{CameraDevice, CameraEvent, CaptureFormat, DeviceKind, Error, FrameSource, OwnedFrame};
+
+#[link(name = "ScreenCaptureKit", kind = "framework")]
+extern "C" {}
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGMainDisplayID() -> u32;
+    fn CGDisplayCopyDisplayMode(display: u32) -> *const c_void;
+    fn CGDisplayModeGetPixelWidth(mode: *const c_void) -> usize;
+    fn CGDisplayModeGetPixelHeight(mode: *const c_void) -> usize;
+    fn CGDisplayModeRelease(mode: *const c_void);
+}
+
+/// kCVPixelFormatType_32BGRA
+const BGRA: u32 = u32::from_be_bytes(*b"BGRA");
+/// SCStreamOutputTypeScreen
+const OUTPUT_TYPE_SCREEN: isize = 0;
+
+macro_rules! sc_class {
+    ($name:ident) => {
+        extern_class!(
+            #[derive(PartialEq, Eq, Hash, Debug)]
+            struct $name;
+
+            unsafe impl ClassType for $name {
+                type Super = NSObject;
+                type Mutability = mutability::InteriorMutable;
+            }
+        );
+
+        unsafe impl NSObjectProtocol for $name {}
+    };
+}
+
+sc_class!(SCShareableContent);
+sc_class!(SCDisplay);
+sc_class!(SCContentFilter);
+sc_class!(SCStreamConfiguration);
+sc_class!(SCStream);
+
+pub struct ScreenOutputIvars {
+    slot: Box<Arc<Slot>>,
+}
+
+declare_class!(
+    struct ScreenOutputDelegate;
+
+    unsafe impl ClassType for ScreenOutputDelegate {
+        type Super = NSObject;
+        type Mutability = mutability::Mutable;
+        const NAME: &'static str = "ScreenOutputDelegate";
+    }
+
+    impl DeclaredClass for ScreenOutputDelegate {
+        type Ivars = ScreenOutputIvars;
+    }
+
+    unsafe impl ScreenOutputDelegate {
+        #[method(stream:didOutputSampleBuffer:ofType:)]
+        unsafe fn on_output_sample_buffer(
+            &mut self,
+            _stream: *const c_void,
+            sample_buffer: CMSampleBufferRef,
+            output_type: isize,
+        ) {
+            // idle samples of an unchanged screen carry no image
+            if output_type == OUTPUT_TYPE_SCREEN
+                && !CMSampleBufferGetImageBuffer(sample_buffer).is_null()
+            {
+                self.ivars().slot.put_sample(sample_buffer);
+            }
+        }
+    }
+
+    unsafe impl NSObjectProtocol for ScreenOutputDelegate {}
+);
+
+impl ScreenOutputDelegate {
+    fn new(slot: Arc<Slot>) -> Id<Self> {
+        let this = Self::alloc().set_ivars(ScreenOutputIvars { slot: Box::new(slot) });
+        unsafe { msg_send_id![super(this), init] }
+    }
+}
+
+/// Moves results of completion handlers to the waiting thread.
+struct SendId<T>(T);
+
+// ScreenCaptureKit hands out immutable results which are safe to use from another thread.
+unsafe impl<T> Send for SendId<T> {}
+
+#[derive(Debug)]
+pub(crate) struct MacScreen {
+    stream: Id<SCStream>,
+    #[allow(unused)]
+    output: Id<ScreenOutputDelegate>,
+    slot: Arc<Slot>,
+    display_id: u32,
+    size: (u32, u32),
+    events: Arc<Mutex<Option<Sender<CameraEvent>>>>,
+}
+
+// SCStream is thread safe, the output only touches the slot.
+unsafe impl Send for MacScreen {}
+unsafe impl Sync for MacScreen {}
+
+impl MacScreen {
+    /// Captures the main display at its native resolution. The first call asks the user for the
+    /// screen recording permission, without it this fails.
+    pub(crate) fn open() -> Result<Self, Error> {
+        let display_id = unsafe { CGMainDisplayID() };
+        let displays = shareable_displays()?;
+        let is_main = |display: &&SCDisplay| {
+            let id: u32 = unsafe { msg_send![*display, displayID] };
+            id == display_id
+        };
+        let display =
+            (displays.iter().find(is_main)).or_else(|| displays.first()).ok_or(Error::NoDevice)?;
+        let size = display_pixel_size(display_id);
+
+        let windows = NSArray::<NSObject>::new();
+        let filter: Id<SCContentFilter> = unsafe {
+            msg_send_id![SCContentFilter::alloc(), initWithDisplay: display, excludingWindows: &*windows]
+        };
+        let config: Id<SCStreamConfiguration> =
+            unsafe { msg_send_id![SCStreamConfiguration::class(), new] };
+        unsafe {
+            let _: () = msg_send![&config, setWidth: size.0 as usize];
+            let _: () = msg_send![&config, setHeight: size.1 as usize];
+            let _: () = msg_send![&config, setPixelFormat: BGRA];
+            let _: () = msg_send![&config, setShowsCursor: true];
+        }
+        let stream: Id<SCStream> = unsafe {
+            msg_send_id![
+                SCStream::alloc(),
+                initWithFilter: &*filter,
+                configuration: &*config,
+                delegate: None::<&NSObject>
+            ]
+        };
+
+        let slot = Arc::new(Slot::new()?);
+        let output = ScreenOutputDelegate::new(slot.clone());
+        let name = CString::new("screen output").unwrap();
+        let queue = unsafe { dispatch_queue_create(name.as_ptr(), null()) };
+        let added: Result<(), Id<NSError>> = unsafe {
+            msg_send![
+                &stream,
+                addStreamOutput: &*output,
+                type: OUTPUT_TYPE_SCREEN,
+                sampleHandlerQueue: queue,
+                error: _
+            ]
+        };
+        // the stream retains the queue
+        unsafe { dispatch_release(queue) };
+        added.map_err(Error::os)?;
+        Ok(Self { stream, output, slot, display_id, size, events: Default::default() })
+    }
+}
+
+impl FrameSource for MacScreen {
+    /// A failed start, e.g. after the permission was revoked, ends the frames and sends
+    /// [`CameraEvent::DeviceLost`].
+    fn start(&self) {
+        let slot = self.slot.clone();
+        let events = self.events.clone();
+        let handler = RcBlock::new(move |error: *mut NSError| {
+            if !error.is_null() {
+                slot.put_sample(null_mut());
+                if let Some(events) = &*lock(&events) {
+                    let _ = events.send(CameraEvent::DeviceLost);
+                }
+            }
+        });
+        unsafe { msg_send![&self.stream, startCaptureWithCompletionHandler: &*handler] }
+    }
+
+    /// Wakes up a waiting reader, which gets `None`.
+    fn stop(&self) {
+        let handler = None::<&Block<dyn Fn(*mut NSError)>>;
+        unsafe { msg_send![&self.stream, stopCaptureWithCompletionHandler: handler] }
+        self.slot.put_sample(null_mut());
+    }
+
+    fn wait_for_frame(&self) -> Option<OwnedFrame> {
+        self.slot.wait_for_sample().map(|sample| owned_frame(&sample))
+    }
+
+    fn try_next_frame(&self) -> Option<OwnedFrame> {
+        self.slot.try_sample().map(|sample| owned_frame(&sample))
+    }
+
+    fn latest_frame(&self) -> Option<OwnedFrame> {
+        self.slot.wait_for_latest_sample().map(|sample| owned_frame(&sample))
+    }
+
+    fn device(&self) -> CameraDevice {
+        let id = format!("display:{}", self.display_id);
+        CameraDevice::new(id, "Main display", DeviceKind::Virtual)
+    }
+
+    fn current_format(&self) -> Option<CaptureFormat> {
+        let (width, height) = self.size;
+        let pixel_format = "BGRA".to_string();
+        Some(CaptureFormat { pixel_format, width, height, min_fps: 0.0, max_fps: 0.0 })
+    }
+
+    fn frame_ready_fd(&self) -> Option<crate::FrameReadyFd> {
+        Some(self.slot.frame_ready_fd())
+    }
+
+    fn connect_events(&self, events: Sender<CameraEvent>) {
+        *lock(&self.events) = Some(events);
+    }
+}
+
+fn owned_frame(sample: &SampleBuffer) -> OwnedFrame {
+    let pixels = sample.pixels();
+    let (width, height) = (pixels.width as u32, pixels.height as u32);
+    OwnedFrame::with_stride(pixels.data.to_vec(), width, height, pixels.stride)
+}
+
+/// Waits for the completion handler, which runs on another thread.
+fn shareable_displays() -> Result<Id<NSArray<SCDisplay>>, Error> {
+    let (tx, rx) = channel();
+    let handler = RcBlock::new(move |content: *mut SCShareableContent, error: *mut NSError| {
+        let result = match unsafe { Id::retain(content) } {
+            Some(content) => Ok(SendId(unsafe { msg_send_id![&content, displays] })),
+            None => Err(unsafe { Id::retain(error) }),
+        };
+        let _ = tx.send(result);
+    });
+    let class = SCShareableContent::class();
+    unsafe { msg_send![class, getShareableContentWithCompletionHandler: &*handler] }
+    match rx.recv() {
+        Ok(Ok(SendId(displays))) => Ok(displays),
+        Ok(Err(Some(error))) => Err(Error::os(error)),
+        Ok(Err(None)) | Err(_) => Err(Error::Unsupported),
+    }
+}
+
+/// The size in pixels, which is twice the size in points on Retina displays.
+fn display_pixel_size(display_id: u32) -> (u32, u32) {
+    unsafe {
+        let mode = CGDisplayCopyDisplayMode(display_id);
+        let size =
+            (CGDisplayModeGetPixelWidth(mode) as u32, CGDisplayModeGetPixelHeight(mode) as u32);
+        CGDisplayModeRelease(mode);
+        size
+    }
+}
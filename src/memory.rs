@@ -0,0 +1,68 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Bytes of the frames a camera queued for the application or handed to it, see
+/// [`Camera::set_memory_limit`](crate::Camera::set_memory_limit).
+#[derive(Debug)]
+pub(crate) struct MemoryBudget {
+    limit: AtomicUsize,
+    used: AtomicUsize,
+}
+
+impl Default for MemoryBudget {
+    fn default() -> Self {
+        Self { limit: usize::MAX.into(), used: 0.into() }
+    }
+}
+
+/// Bytes of one frame, given back to the budget when the frame is dropped.
+#[derive(Debug)]
+pub(crate) struct Reservation {
+    budget: Arc<MemoryBudget>,
+    bytes: usize,
+}
+
+impl MemoryBudget {
+    pub(crate) fn set_limit(&self, bytes: usize) {
+        self.limit.store(bytes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn used(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    /// `None` if `bytes` don't fit into the limit any more. A frame always fits while no other
+    /// one is held, so a limit below the size of a frame doesn't stop the stream.
+    pub(crate) fn try_reserve(self: &Arc<Self>, bytes: usize) -> Option<Reservation> {
+        let limit = self.limit.load(Ordering::Relaxed);
+        self.used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                let total = used.saturating_add(bytes);
+                (used == 0 || total <= limit).then_some(total)
+            })
+            .ok()?;
+        Some(Reservation { budget: self.clone(), bytes })
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        self.budget.used.fetch_sub(self.bytes, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn memory_budget() {
+    let budget = Arc::new(MemoryBudget::default());
+    budget.set_limit(100);
+    let first = budget.try_reserve(150).unwrap();
+    assert_eq!(budget.used(), 150);
+    assert!(budget.try_reserve(1).is_none());
+    drop(first);
+    let second = budget.try_reserve(60).unwrap();
+    assert!(budget.try_reserve(50).is_none());
+    let third = budget.try_reserve(40).unwrap();
+    assert_eq!(budget.used(), 100);
+    drop((second, third));
+    assert_eq!(budget.used(), 0);
+}
@@ -0,0 +1,69 @@
+use std::time::{Duration, SystemTime};
+
+/// Exposure settings of the device when
+/// [`Camera::capture_metadata`](crate::Camera::capture_metadata) was called, the kind of values
+/// EXIF stores with a photo.
+///
+/// Fields are `None` when the device or OS doesn't report them. macOS has no API for the
+/// exposure of external cameras, Linux drivers have no aperture in known units and only
+/// Linux reports ISO. [`Camera::take_photo`](crate::Camera::take_photo) attaches it to a frame
+/// and writes it as EXIF.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CaptureMetadata {
+    pub exposure_time: Option<Duration>,
+    pub iso: Option<u32>,
+    /// Color temperature in Kelvin.
+    pub white_balance: Option<u32>,
+    /// F-number, e.g. 2.8.
+    pub lens_aperture: Option<f32>,
+    pub device_name: String,
+    pub timestamp: SystemTime,
+}
+
+impl CaptureMetadata {
+    pub(crate) fn new(device_name: impl Into<String>) -> Self {
+        Self {
+            exposure_time: None,
+            iso: None,
+            white_balance: None,
+            lens_aperture: None,
+            device_name: device_name.into(),
+            timestamp: crate::time::system_time_now(),
+        }
+    }
+}
+
+/// Exposure of a single frame as the OS attached it, see
+/// [`Frame::capture_info`](crate::Frame::capture_info). Unlike [`CaptureMetadata`] it belongs to
+/// the frame, so frames of a burst can be matched by their exposure.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct CaptureInfo {
+    pub exposure_time: Option<Duration>,
+    pub iso: Option<f32>,
+    /// F-number, e.g. 2.8.
+    pub lens_aperture: Option<f32>,
+    /// Focal length of the lens in millimeters.
+    pub focal_length: Option<f32>,
+}
+
+/// Kinds of metadata the OS detects in frames, see
+/// [`Camera::enable_metadata`](crate::Camera::enable_metadata).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MetadataKind {
+    Faces,
+}
+
+/// A face the OS or the camera detected, see [`Frame::metadata`](crate::Frame::metadata).
+///
+/// The position and size are fractions of the frame width and height, with the origin at the
+/// top left, so they stay valid when the frame is scaled.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct FaceRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// The same face keeps its ID from frame to frame, if the OS tracks faces. Only macOS.
+    pub id: Option<i64>,
+}
@@ -0,0 +1,132 @@
+use crate::{Frame, Rect};
+
+/// Which parts of a frame changed since the previous one, a cheap motion hint for monitoring
+/// without handing every frame to an image processing crate.
+///
+/// Frames are compared in square tiles. A tile changed when the mean difference of its pixels,
+/// summed over blue, green and red, is above a threshold, which keeps sensor noise out.
+///
+/// ```no_run
+/// let camera = kamera::Camera::new_default_device();
+/// camera.start();
+/// let mut prev = camera.wait_for_frame().unwrap();
+/// while let Some(next) = camera.wait_for_frame() {
+///     let delta = kamera::FrameDelta::between(&prev, &next);
+///     if delta.changed_percent > 1.0 {
+///         println!("motion in {:?}", delta.bounds());
+///     }
+///     prev = next;
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrameDelta {
+    /// The changed tiles, row by row. Tiles at the right and bottom edge may be smaller.
+    pub tiles: Vec<Rect>,
+    /// Share of the frame area in changed tiles, from 0 to 100.
+    pub changed_percent: f32,
+}
+
+impl FrameDelta {
+    /// Tile size of [`FrameDelta::between`].
+    pub const TILE_SIZE: u32 = 32;
+    /// Threshold of [`FrameDelta::between`], about 8 per color channel.
+    pub const THRESHOLD: u32 = 24;
+
+    pub fn between(prev: &Frame, next: &Frame) -> Self {
+        Self::between_with(prev, next, Self::TILE_SIZE, Self::THRESHOLD)
+    }
+
+    /// Frames of different sizes differ everywhere.
+    pub fn between_with(prev: &Frame, next: &Frame, tile_size: u32, threshold: u32) -> Self {
+        let size = next.size_u32();
+        if prev.size_u32() != size {
+            let (width, height) = size;
+            return Self { tiles: vec![Rect::new(0, 0, width, height)], changed_percent: 100.0 };
+        }
+        let (prev, next) = (prev.data(), next.data());
+        changed_tiles(prev.data_bgra(), next.data_bgra(), size, tile_size.max(1), threshold)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    /// The smallest rectangle around all changed tiles.
+    pub fn bounds(&self) -> Option<Rect> {
+        let (first, rest) = self.tiles.split_first()?;
+        let (mut x0, mut y0) = (first.x, first.y);
+        let (mut x1, mut y1) = (first.x + first.width, first.y + first.height);
+        for tile in rest {
+            (x0, y0) = (x0.min(tile.x), y0.min(tile.y));
+            (x1, y1) = (x1.max(tile.x + tile.width), y1.max(tile.y + tile.height));
+        }
+        Some(Rect::new(x0, y0, x1 - x0, y1 - y0))
+    }
+}
+
+/// Compares packed BGRA a band of tile rows at a time. The differences of a row segment are
+/// summed in one flat loop, alpha is the same in both frames.
+fn changed_tiles(
+    prev: &[u8],
+    next: &[u8],
+    (w, h): (u32, u32),
+    tile: u32,
+    threshold: u32,
+) -> FrameDelta {
+    let stride = w as usize * 4;
+    let columns = w.div_ceil(tile) as usize;
+    let mut sums = vec![0u64; columns];
+    let mut delta = FrameDelta::default();
+    let mut changed_area = 0u64;
+    for band_y in (0..h).step_by(tile as usize) {
+        let band_h = tile.min(h - band_y);
+        sums.fill(0);
+        for y in band_y..band_y + band_h {
+            let row = y as usize * stride..(y as usize + 1) * stride;
+            let (a, b) = (&prev[row.clone()], &next[row]);
+            let segments = a.chunks(tile as usize * 4).zip(b.chunks(tile as usize * 4));
+            for (sum, (a, b)) in sums.iter_mut().zip(segments) {
+                *sum += a.iter().zip(b).map(|(a, b)| a.abs_diff(*b) as u32).sum::<u32>() as u64;
+            }
+        }
+        for (column, &sum) in sums.iter().enumerate() {
+            let x = column as u32 * tile;
+            let tile = Rect::new(x, band_y, tile.min(w - x), band_h);
+            let area = tile.width as u64 * tile.height as u64;
+            if sum > threshold as u64 * area {
+                changed_area += area;
+                delta.tiles.push(tile);
+            }
+        }
+    }
+    if w > 0 && h > 0 {
+        delta.changed_percent = (changed_area as f64 * 100.0 / (w as f64 * h as f64)) as f32;
+    }
+    delta
+}
+
+#[test]
+fn frame_delta() {
+    let (w, h) = (80, 40);
+    let prev = vec![100u8; w * h * 4];
+    let mut next = prev.clone();
+    // a bright square at 40..56, 8..16 and some noise everywhere
+    for (i, px) in next.chunks_exact_mut(4).enumerate() {
+        let (x, y) = (i % w, i / w);
+        if (40..56).contains(&x) && (8..16).contains(&y) {
+            px[..3].fill(200);
+        } else {
+            px[i % 3] += (i % 5) as u8;
+        }
+    }
+    let delta = changed_tiles(&prev, &next, (w as u32, h as u32), 32, 24);
+    assert_eq!(delta.tiles, vec![Rect::new(32, 0, 32, 32)]);
+    assert!((delta.changed_percent - 32.0).abs() < 0.01);
+    assert_eq!(delta.bounds(), Some(Rect::new(32, 0, 32, 32)));
+
+    let delta = changed_tiles(&prev, &next, (w as u32, h as u32), 16, 24);
+    assert_eq!(delta.tiles, vec![Rect::new(32, 0, 16, 16), Rect::new(48, 0, 16, 16)]);
+    assert_eq!(delta.bounds(), Some(Rect::new(32, 0, 32, 16)));
+    let unchanged = changed_tiles(&prev, &prev, (w as u32, h as u32), 32, 0);
+    assert_eq!(unchanged, FrameDelta::default());
+}
@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+use crate::time::Instant;
+use crate::Error;
+
+/// What to do when another process holds the device, see
+/// [`CameraBuilder::open_policy`](crate::CameraBuilder::open_policy).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OpenPolicy {
+    /// Fail right away with [`Error::InUseByOtherApp`] (the default).
+    #[default]
+    Immediate,
+    /// Try again up to `attempts` more times, waiting `initial_delay` at first and twice as long
+    /// after each attempt, up to two seconds.
+    RetryWithBackoff { attempts: u32, initial_delay: Duration },
+    /// Try again every 100 ms until the device is free or `timeout` passed.
+    WaitUntilFree { timeout: Duration },
+}
+
+const MAX_DELAY: Duration = Duration::from_secs(2);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+impl OpenPolicy {
+    /// Calls `open` again while it fails with [`Error::InUseByOtherApp`] and the policy allows
+    /// it, other errors return right away.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    pub(crate) fn retry<T>(&self, mut open: impl FnMut() -> Result<T, Error>) -> Result<T, Error> {
+        let start = Instant::now();
+        let mut attempt = 0;
+        loop {
+            let err = match open() {
+                Err(Error::InUseByOtherApp) => Error::InUseByOtherApp,
+                result => return result,
+            };
+            let delay = match *self {
+                OpenPolicy::Immediate => None,
+                OpenPolicy::RetryWithBackoff { attempts, initial_delay } => (attempt < attempts)
+                    .then(|| initial_delay.saturating_mul(1 << attempt.min(16)).min(MAX_DELAY)),
+                OpenPolicy::WaitUntilFree { timeout } => {
+                    let left = timeout.saturating_sub(start.elapsed());
+                    (!left.is_zero()).then(|| left.min(POLL_INTERVAL))
+                }
+            };
+            let Some(delay) = delay else { return Err(err) };
+            std::thread::sleep(delay);
+            attempt += 1;
+        }
+    }
+}
+
+#[test]
+fn retry_while_busy() {
+    let busy_for = |n: u32| {
+        let mut calls = 0;
+        move || {
+            calls += 1;
+            if calls <= n {
+                Err(Error::InUseByOtherApp)
+            } else {
+                Ok(calls)
+            }
+        }
+    };
+    let ms = Duration::from_millis;
+    assert_eq!(OpenPolicy::Immediate.retry(busy_for(1)), Err(Error::InUseByOtherApp));
+    let backoff = OpenPolicy::RetryWithBackoff { attempts: 3, initial_delay: ms(1) };
+    assert_eq!(backoff.retry(busy_for(3)), Ok(4));
+    assert_eq!(backoff.retry(busy_for(4)), Err(Error::InUseByOtherApp));
+    let wait = OpenPolicy::WaitUntilFree { timeout: ms(150) };
+    assert_eq!(wait.retry(busy_for(1)), Ok(2));
+    let start = Instant::now();
+    assert_eq!(wait.retry(busy_for(u32::MAX)), Err(Error::InUseByOtherApp));
+    assert!(start.elapsed() >= ms(150));
+    assert_eq!(backoff.retry(|| Err::<(), _>(Error::NoDevice)), Err(Error::NoDevice));
+}
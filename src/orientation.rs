@@ -0,0 +1,12 @@
+/// Which way up frames are delivered, see
+/// [`Camera::set_video_orientation`](crate::Camera::set_video_orientation).
+///
+/// The names are those of AVFoundation: the home button of an iPhone is at the bottom in
+/// portrait and on the right in landscape right.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VideoOrientation {
+    Portrait,
+    PortraitUpsideDown,
+    LandscapeRight,
+    LandscapeLeft,
+}
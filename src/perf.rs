@@ -0,0 +1,71 @@
+use std::sync::atomic::{AtomicU64, Ordering::Relaxed};
+use std::time::Duration;
+
+use crate::time::Instant;
+
+/// Snapshot of [`Camera::perf_counters`](crate::Camera::perf_counters).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PerfCounters {
+    /// Frames returned by `wait_for_frame`.
+    pub frames: u64,
+    /// Pixel format conversions done by kamera, while capturing and in
+    /// [`FrameData`](crate::FrameData).
+    pub conversions: u64,
+    /// Total time spent in these conversions.
+    pub conversion_time: Duration,
+}
+
+impl PerfCounters {
+    pub fn average_conversion_time(&self) -> Option<Duration> {
+        (self.conversions > 0).then(|| self.conversion_time / self.conversions as u32)
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    frames: AtomicU64,
+    conversions: AtomicU64,
+    conversion_nanos: AtomicU64,
+}
+
+impl Counters {
+    pub(crate) fn frame(&self) {
+        self.frames.fetch_add(1, Relaxed);
+    }
+
+    pub(crate) fn conversion(&self, time: Duration) {
+        if !time.is_zero() {
+            self.conversions.fetch_add(1, Relaxed);
+            self.conversion_nanos.fetch_add(time.as_nanos() as u64, Relaxed);
+        }
+    }
+
+    pub(crate) fn time_conversion<T>(&self, convert: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = convert();
+        self.conversion(start.elapsed());
+        result
+    }
+
+    pub(crate) fn snapshot(&self) -> PerfCounters {
+        PerfCounters {
+            frames: self.frames.load(Relaxed),
+            conversions: self.conversions.load(Relaxed),
+            conversion_time: Duration::from_nanos(self.conversion_nanos.load(Relaxed)),
+        }
+    }
+}
+
+#[test]
+fn counters() {
+    let counters = Counters::default();
+    assert_eq!(counters.snapshot().average_conversion_time(), None);
+    counters.frame();
+    counters.conversion(Duration::ZERO);
+    counters.conversion(Duration::from_millis(2));
+    counters.conversion(Duration::from_millis(4));
+    let snapshot = counters.snapshot();
+    assert_eq!((snapshot.frames, snapshot.conversions), (1, 2));
+    assert_eq!(snapshot.average_conversion_time(), Some(Duration::from_millis(3)));
+}
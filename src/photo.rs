@@ -0,0 +1,227 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{CaptureMetadata, Frame};
+
+/// A frame with the settings of the device it was taken with, see
+/// [`Camera::take_photo`](crate::Camera::take_photo).
+#[derive(Debug)]
+pub struct Photo {
+    pub frame: Frame,
+    pub metadata: CaptureMetadata,
+}
+
+impl Photo {
+    /// The metadata as the payload of a JPEG APP1 segment, starting with `Exif\0\0`, for
+    /// [`insert_exif`] or an encoder which takes EXIF.
+    ///
+    /// Holds the device name as camera model, the time in UTC, exposure time, f-number, ISO
+    /// and the frame size. EXIF has no tag for the color temperature of the white balance.
+    pub fn exif(&self) -> Vec<u8> {
+        exif(&self.metadata, self.frame.size_u32())
+    }
+
+    /// Encodes the frame as JPEG of `quality` from 1 to 100 with [`Photo::exif`], enabled with
+    /// the `photo` feature.
+    #[cfg(feature = "photo")]
+    pub fn to_jpeg(&self, quality: u8) -> Result<Vec<u8>, crate::Error> {
+        let (width, height) = self.frame.size_u32();
+        let mut jpeg = Vec::new();
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, quality)
+            .encode(self.frame.data().data_rgb(), width, height, image::ExtendedColorType::Rgb8)
+            .map_err(crate::Error::os)?;
+        insert_exif(&jpeg, &self.exif()).ok_or(crate::Error::Unsupported)
+    }
+}
+
+/// Adds `exif` of [`Photo::exif`] as APP1 segment to a JPEG file, after the JFIF segment if
+/// there is one. `None` if `jpeg` isn't JPEG or `exif` is too long for a segment.
+pub fn insert_exif(jpeg: &[u8], exif: &[u8]) -> Option<Vec<u8>> {
+    let segment_len = u16::try_from(exif.len() + 2).ok()?;
+    if !jpeg.starts_with(&[0xff, 0xd8]) {
+        return None;
+    }
+    let mut at = 2;
+    if jpeg.get(2..4) == Some(&[0xff, 0xe0]) {
+        let len = u16::from_be_bytes([*jpeg.get(4)?, *jpeg.get(5)?]) as usize;
+        at = (4 + len).min(jpeg.len());
+    }
+    let mut out = Vec::with_capacity(jpeg.len() + 4 + exif.len());
+    out.extend_from_slice(&jpeg[..at]);
+    out.extend_from_slice(&[0xff, 0xe1]);
+    out.extend_from_slice(&segment_len.to_be_bytes());
+    out.extend_from_slice(exif);
+    out.extend_from_slice(&jpeg[at..]);
+    Some(out)
+}
+
+enum Value {
+    Ascii(String),
+    Short(u16),
+    Long(u32),
+    Rational(u32, u32),
+    Undefined(&'static [u8]),
+}
+
+impl Value {
+    /// TIFF type, count and the bytes in big endian.
+    fn encode(&self) -> (u16, u32, Vec<u8>) {
+        match self {
+            Value::Ascii(text) => {
+                let mut bytes = text.replace('\0', " ").into_bytes();
+                bytes.push(0);
+                (2, bytes.len() as u32, bytes)
+            }
+            Value::Short(n) => (3, 1, n.to_be_bytes().to_vec()),
+            Value::Long(n) => (4, 1, n.to_be_bytes().to_vec()),
+            Value::Rational(n, d) => (5, 1, [n.to_be_bytes(), d.to_be_bytes()].concat()),
+            Value::Undefined(bytes) => (7, bytes.len() as u32, bytes.to_vec()),
+        }
+    }
+}
+
+const TAG_MODEL: u16 = 0x0110;
+const TAG_DATE_TIME: u16 = 0x0132;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_EXPOSURE_TIME: u16 = 0x829a;
+const TAG_F_NUMBER: u16 = 0x829d;
+const TAG_ISO: u16 = 0x8827;
+const TAG_EXIF_VERSION: u16 = 0x9000;
+const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+const TAG_OFFSET_TIME_ORIGINAL: u16 = 0x9011;
+const TAG_PIXEL_X: u16 = 0xa002;
+const TAG_PIXEL_Y: u16 = 0xa003;
+
+/// Big endian TIFF with IFD0 and the EXIF IFD after it.
+fn exif(metadata: &CaptureMetadata, (width, height): (u32, u32)) -> Vec<u8> {
+    let date = exif_date(metadata.timestamp);
+    let mut exif_ifd = Vec::new();
+    if let Some(time) = metadata.exposure_time {
+        exif_ifd.push((TAG_EXPOSURE_TIME, Value::Rational(time.as_micros() as u32, 1_000_000)));
+    }
+    if let Some(aperture) = metadata.lens_aperture {
+        exif_ifd.push((TAG_F_NUMBER, Value::Rational((aperture * 100.0).round() as u32, 100)));
+    }
+    if let Some(iso) = metadata.iso {
+        exif_ifd.push((TAG_ISO, Value::Short(iso.min(u16::MAX as u32) as u16)));
+    }
+    exif_ifd.push((TAG_EXIF_VERSION, Value::Undefined(b"0232")));
+    exif_ifd.push((TAG_DATE_TIME_ORIGINAL, Value::Ascii(date.clone())));
+    exif_ifd.push((TAG_OFFSET_TIME_ORIGINAL, Value::Ascii("+00:00".into())));
+    exif_ifd.push((TAG_PIXEL_X, Value::Long(width)));
+    exif_ifd.push((TAG_PIXEL_Y, Value::Long(height)));
+
+    let ifd0 = |exif_offset| {
+        let model = Value::Ascii(metadata.device_name.clone());
+        let entries = [
+            (TAG_MODEL, model),
+            (TAG_DATE_TIME, Value::Ascii(date.clone())),
+            (TAG_EXIF_IFD, exif_offset),
+        ];
+        ifd(8, &entries)
+    };
+    // the size of IFD0 doesn't depend on the offset it points to
+    let exif_offset = 8 + ifd0(Value::Long(0)).len() as u32;
+    let mut out = b"Exif\0\0MM\0\x2a".to_vec();
+    out.extend_from_slice(&8u32.to_be_bytes());
+    out.extend_from_slice(&ifd0(Value::Long(exif_offset)));
+    out.extend_from_slice(&ifd(exif_offset, &exif_ifd));
+    out
+}
+
+/// An IFD at `start` bytes into the TIFF data, followed by the values longer than 4 bytes.
+/// `entries` are sorted by tag.
+fn ifd(start: u32, entries: &[(u16, Value)]) -> Vec<u8> {
+    let data_start = start + 2 + 12 * entries.len() as u32 + 4;
+    let mut out = (entries.len() as u16).to_be_bytes().to_vec();
+    let mut data = Vec::new();
+    for (tag, value) in entries {
+        let (kind, count, mut bytes) = value.encode();
+        out.extend_from_slice(&tag.to_be_bytes());
+        out.extend_from_slice(&kind.to_be_bytes());
+        out.extend_from_slice(&count.to_be_bytes());
+        if bytes.len() <= 4 {
+            bytes.resize(4, 0);
+            out.extend_from_slice(&bytes);
+        } else {
+            out.extend_from_slice(&(data_start + data.len() as u32).to_be_bytes());
+            data.extend_from_slice(&bytes);
+            // values start on word boundaries
+            if data.len() % 2 == 1 {
+                data.push(0);
+            }
+        }
+    }
+    out.extend_from_slice(&0u32.to_be_bytes());
+    out.extend_from_slice(&data);
+    out
+}
+
+/// `YYYY:MM:DD HH:MM:SS` in UTC.
+fn exif_date(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).unwrap_or(Duration::ZERO).as_secs();
+    let (days, secs) = ((secs / 86400) as i64, secs % 86400);
+    // civil from days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    let (hour, minute, second) = (secs / 3600, secs / 60 % 60, secs % 60);
+    format!("{year:04}:{month:02}:{day:02} {hour:02}:{minute:02}:{second:02}")
+}
+
+#[test]
+fn exif_date_in_utc() {
+    assert_eq!(exif_date(UNIX_EPOCH), "1970:01:01 00:00:00");
+    let leap_day = UNIX_EPOCH + Duration::from_secs(951_782_400 + 3723);
+    assert_eq!(exif_date(leap_day), "2000:02:29 01:02:03");
+}
+
+#[test]
+fn exif_tiff_layout() {
+    let mut metadata = CaptureMetadata::new("Cam");
+    metadata.exposure_time = Some(Duration::from_millis(20));
+    metadata.iso = Some(400);
+    let exif = exif(&metadata, (640, 480));
+    let tiff = &exif[6..];
+    let u16_at = |at: usize| u16::from_be_bytes([tiff[at], tiff[at + 1]]);
+    let u32_at = |at: usize| u32::from_be_bytes(tiff[at..at + 4].try_into().unwrap());
+    assert_eq!(&tiff[..4], b"MM\0\x2a");
+    // IFD0: model inline, date and the EXIF IFD
+    assert_eq!(u16_at(8), 3);
+    assert_eq!((u16_at(10), u32_at(14)), (TAG_MODEL, 4));
+    assert_eq!(&tiff[18..22], b"Cam\0");
+    assert_eq!(u16_at(34), TAG_EXIF_IFD);
+    let exif_ifd = u32_at(42) as usize;
+    assert_eq!(u16_at(exif_ifd), 7);
+    let exposure = u32_at(exif_ifd + 2 + 8) as usize;
+    assert_eq!((u32_at(exposure), u32_at(exposure + 4)), (20_000, 1_000_000));
+    assert_eq!((u16_at(exif_ifd + 14), u16_at(exif_ifd + 22)), (TAG_ISO, 400));
+}
+
+#[test]
+fn insert_exif_after_jfif() {
+    let jpeg = [0xff, 0xd8, 0xff, 0xe0, 0, 4, 1, 2, 0xff, 0xd9];
+    let out = insert_exif(&jpeg, b"Exif\0\0").unwrap();
+    assert_eq!(out[..8], jpeg[..8]);
+    assert_eq!(out[8..18], [0xff, 0xe1, 0, 8, b'E', b'x', b'i', b'f', 0, 0]);
+    assert_eq!(out[18..], [0xff, 0xd9]);
+    assert!(insert_exif(b"\x89PNG", b"Exif\0\0").is_none());
+}
+
+#[cfg(feature = "photo")]
+#[test]
+fn test_pattern_photo_to_jpeg() {
+    let camera = crate::Camera::with_backend(crate::Backend::Test).unwrap();
+    camera.start();
+    let photo = camera.take_photo().unwrap();
+    let jpeg = photo.to_jpeg(80).unwrap();
+    assert_eq!(jpeg[..2], [0xff, 0xd8]);
+    assert!(jpeg.windows(6).any(|w| w == b"Exif\0\0"));
+    let decoded = image::load_from_memory(&jpeg).unwrap();
+    assert_eq!((decoded.width(), decoded.height()), photo.frame.size_u32());
+}
@@ -0,0 +1,332 @@
+//! Play a video file through the [`Camera`](crate::Camera) API, enabled with the `playback` feature.
+//!
+//! Reads uncompressed [YUV4MPEG2](https://wiki.multimedia.cx/index.php/YUV4MPEG2) files, like the ones
+//! written by the `record` feature or `ffmpeg -i clip.mp4 -pix_fmt yuv420p clip.y4m`, and loops them at
+//! the frame rate from the file header. Compressed video formats are not supported.
+//!
+//! [`ImageSequence`] loops the PNG, JPEG or BMP images of a directory instead, like the ones of
+//! `ffmpeg -i clip.mp4 frames/%04d.png`.
+//!
+//! ```no_run
+//! let camera = kamera::Camera::from_video_file("clip.y4m").unwrap();
+//! camera.start();
+//! let frame = camera.wait_for_frame().unwrap();
+//! ```
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use image::ImageFormat;
+
+use crate::convert::{gray_to_bgra, planar_yuv_to_bgra, rgb24_to_bgra};
+use crate::sync::lock;
+use crate::time::Instant;
+use crate::{
+    CameraDevice, CaptureFormat, ColorRange, ColorSpace, DeviceKind, FrameSource, OwnedFrame,
+    YuvMatrix,
+};
+
+/// [`FrameSource`] which loops a YUV4MPEG2 file.
+#[derive(Debug)]
+pub struct VideoFile {
+    name: String,
+    header: Header,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Header {
+    width: u32,
+    height: u32,
+    fps: f64,
+    /// Chroma subsampling shifts, `None` for monochrome.
+    chroma: Option<(u32, u32)>,
+    range: ColorRange,
+}
+
+#[derive(Debug)]
+struct State {
+    file: BufReader<File>,
+    data_start: u64,
+    next_frame: Option<Instant>,
+}
+
+impl VideoFile {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut file = BufReader::new(File::open(path)?);
+        let mut line = String::new();
+        file.read_line(&mut line)?;
+        let header = Header::parse(&line)?;
+        let data_start = file.stream_position()?;
+        let name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy().into_owned();
+        let state = Mutex::new(State { file, data_start, next_frame: None });
+        Ok(Self { name, header, state })
+    }
+
+    fn read_frame(&self, state: &mut State) -> io::Result<OwnedFrame> {
+        let Header { width, height, chroma, range, .. } = self.header;
+        let mut tag = Vec::new();
+        if state.file.read_until(b'\n', &mut tag)? == 0 {
+            state.file.seek(SeekFrom::Start(state.data_start))?;
+            state.file.read_until(b'\n', &mut tag)?;
+        }
+        if !tag.starts_with(b"FRAME") {
+            return Err(invalid_data("missing FRAME tag"));
+        }
+        let luma = width as usize * height as usize;
+        let chroma_len = chroma
+            .map_or(0, |(x, y)| width.div_ceil(1 << x) as usize * height.div_ceil(1 << y) as usize);
+        let mut buf = vec![0; luma + 2 * chroma_len];
+        state.file.read_exact(&mut buf)?;
+        let color_space = ColorSpace { matrix: YuvMatrix::Bt601, range, ..Default::default() };
+        let mut bgra = Vec::new();
+        match chroma {
+            Some(shift) => {
+                let (y, uv) = buf.split_at(luma);
+                let (u, v) = uv.split_at(chroma_len);
+                planar_yuv_to_bgra([y, u, v], width, height, shift, color_space, &mut bgra)
+            }
+            None => gray_to_bgra(&buf, width, height, width as usize, &mut bgra),
+        }
+        Ok(OwnedFrame::new(bgra, width, height).with_color_space(color_space))
+    }
+}
+
+impl FrameSource for VideoFile {
+    fn stop(&self) {
+        lock(&self.state).next_frame = None;
+    }
+
+    /// Sleeps until the frame is due, `None` if the file can't be read.
+    fn wait_for_frame(&self) -> Option<OwnedFrame> {
+        let mut state = lock(&self.state);
+        wait_until_due(&mut state.next_frame, self.header.fps);
+        self.read_frame(&mut state).ok()
+    }
+
+    fn try_next_frame(&self) -> Option<OwnedFrame> {
+        let due = lock(&self.state).next_frame;
+        if due.is_some_and(|due| due > Instant::now()) {
+            return None;
+        }
+        self.wait_for_frame()
+    }
+
+    fn device(&self) -> CameraDevice {
+        CameraDevice::new(&self.name, &self.name, DeviceKind::Virtual)
+    }
+
+    fn current_format(&self) -> Option<CaptureFormat> {
+        Some(CaptureFormat {
+            pixel_format: "Y4M".to_string(),
+            width: self.header.width,
+            height: self.header.height,
+            min_fps: self.header.fps,
+            max_fps: self.header.fps,
+        })
+    }
+}
+
+/// Sleeps until `next_frame` and schedules the one after it.
+fn wait_until_due(next_frame: &mut Option<Instant>, fps: f64) {
+    let now = Instant::now();
+    let due = next_frame.unwrap_or(now);
+    if due > now {
+        std::thread::sleep(due - now);
+    }
+    // fall behind instead of bursting after a slow consumer
+    *next_frame = Some(due.max(now) + Duration::from_secs_f64(1.0 / fps));
+}
+
+/// [`FrameSource`] which loops the images of a directory in the order of their file names.
+///
+/// Each image is decoded when it's due, images of another size than the first come out in
+/// their own size.
+#[derive(Debug)]
+pub struct ImageSequence {
+    name: String,
+    paths: Vec<PathBuf>,
+    fps: f64,
+    size: (u32, u32),
+    state: Mutex<SequenceState>,
+}
+
+#[derive(Debug)]
+struct SequenceState {
+    index: usize,
+    next_frame: Option<Instant>,
+}
+
+impl ImageSequence {
+    /// Reads the file names of `dir` and the size of the first image. Files of other types are
+    /// skipped, `InvalidData` if there are no images.
+    pub fn open(dir: impl AsRef<Path>, fps: f64) -> io::Result<Self> {
+        if !fps.is_finite() || fps <= 0.0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "bad frame rate"));
+        }
+        let dir = dir.as_ref();
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if ImageFormat::from_path(&path).is_ok_and(|format| format.reading_enabled()) {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        let first = paths.first().ok_or(invalid_data("no images in the directory"))?;
+        let size = image::image_dimensions(first).map_err(io::Error::other)?;
+        let name = dir.file_name().unwrap_or(dir.as_os_str()).to_string_lossy().into_owned();
+        let state = Mutex::new(SequenceState { index: 0, next_frame: None });
+        Ok(Self { name, paths, fps, size, state })
+    }
+
+    fn read_frame(&self, index: usize) -> io::Result<OwnedFrame> {
+        let rgb = image::open(&self.paths[index]).map_err(io::Error::other)?.into_rgb8();
+        let (width, height) = rgb.dimensions();
+        let mut bgra = Vec::new();
+        rgb24_to_bgra(rgb.as_raw(), width, height, width as usize * 3, &mut bgra);
+        Ok(OwnedFrame::new(bgra, width, height))
+    }
+}
+
+impl FrameSource for ImageSequence {
+    fn stop(&self) {
+        lock(&self.state).next_frame = None;
+    }
+
+    /// Sleeps until the frame is due, `None` if the image can't be decoded.
+    fn wait_for_frame(&self) -> Option<OwnedFrame> {
+        let mut state = lock(&self.state);
+        wait_until_due(&mut state.next_frame, self.fps);
+        let index = state.index;
+        state.index = (index + 1) % self.paths.len();
+        drop(state);
+        self.read_frame(index).ok()
+    }
+
+    fn try_next_frame(&self) -> Option<OwnedFrame> {
+        let due = lock(&self.state).next_frame;
+        if due.is_some_and(|due| due > Instant::now()) {
+            return None;
+        }
+        self.wait_for_frame()
+    }
+
+    fn device(&self) -> CameraDevice {
+        CameraDevice::new(&self.name, &self.name, DeviceKind::Virtual)
+    }
+
+    fn current_format(&self) -> Option<CaptureFormat> {
+        Some(CaptureFormat {
+            pixel_format: "IMAGES".to_string(),
+            width: self.size.0,
+            height: self.size.1,
+            min_fps: self.fps,
+            max_fps: self.fps,
+        })
+    }
+}
+
+impl Header {
+    fn parse(line: &str) -> io::Result<Self> {
+        let mut params = line.trim_end().split(' ');
+        if params.next() != Some("YUV4MPEG2") {
+            return Err(invalid_data("not a YUV4MPEG2 file"));
+        }
+        let mut header = Header {
+            width: 0,
+            height: 0,
+            fps: 25.0,
+            chroma: Some((1, 1)),
+            range: ColorRange::Limited,
+        };
+        for param in params.filter(|p| !p.is_empty()) {
+            let (key, value) = param.split_at(1);
+            match key {
+                "W" => header.width = value.parse().map_err(|_| invalid_data("bad width"))?,
+                "H" => header.height = value.parse().map_err(|_| invalid_data("bad height"))?,
+                "F" => {
+                    let (n, d) = value.split_once(':').ok_or(invalid_data("bad frame rate"))?;
+                    let (n, d) = (n.parse::<f64>(), d.parse::<f64>());
+                    match (n, d) {
+                        (Ok(n), Ok(d)) if n > 0.0 && d > 0.0 => header.fps = n / d,
+                        _ => return Err(invalid_data("bad frame rate")),
+                    }
+                }
+                "C" => {
+                    header.chroma = match value {
+                        "420" | "420jpeg" | "420mpeg2" | "420paldv" => Some((1, 1)),
+                        "422" => Some((1, 0)),
+                        "444" => Some((0, 0)),
+                        "mono" => None,
+                        _ => return Err(invalid_data("unsupported chroma format")),
+                    }
+                }
+                "X" if value == "COLORRANGE=FULL" => header.range = ColorRange::Full,
+                _ => {}
+            }
+        }
+        if header.width == 0 || header.height == 0 {
+            return Err(invalid_data("missing frame size"));
+        }
+        Ok(header)
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[test]
+fn parse_y4m_header() {
+    let header =
+        Header::parse("YUV4MPEG2 W640 H480 F30000:1001 Ip A1:1 C444 XYSCSS=444\n").unwrap();
+    assert_eq!((header.width, header.height, header.chroma), (640, 480, Some((0, 0))));
+    assert!((header.fps - 29.97).abs() < 0.01);
+    let header = Header::parse("YUV4MPEG2 W2 H2 F25:1 Cmono XCOLORRANGE=FULL").unwrap();
+    assert_eq!((header.chroma, header.range), (None, ColorRange::Full));
+    assert!(Header::parse("YUV4MPEG2 W2 F25:1").is_err());
+    assert!(Header::parse("RIFF").is_err());
+}
+
+#[test]
+fn play_y4m_loops() {
+    let path = std::env::temp_dir().join("kamera_play_y4m_loops.y4m");
+    let white = [235, 235, 235, 235, 128, 128];
+    let black = [16, 16, 16, 16, 128, 128];
+    let data =
+        [&b"YUV4MPEG2 W2 H2 F1000:1 C420jpeg\nFRAME\n"[..], &white, b"FRAME\n", &black].concat();
+    std::fs::write(&path, data).unwrap();
+
+    let video = VideoFile::open(&path).unwrap();
+    let pixel = |frame: OwnedFrame| frame.data()[..4].to_vec();
+    assert_eq!(pixel(video.wait_for_frame().unwrap()), [255, 255, 255, 255]);
+    assert_eq!(pixel(video.wait_for_frame().unwrap()), [0, 0, 0, 255]);
+    assert_eq!(pixel(video.wait_for_frame().unwrap()), [255, 255, 255, 255]);
+    assert_eq!(video.current_format().unwrap().width, 2);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn play_image_sequence_loops() {
+    let dir = std::env::temp_dir().join("kamera_play_image_sequence_loops");
+    std::fs::create_dir_all(&dir).unwrap();
+    // written out of order and with a file which isn't an image
+    for (name, value) in [("2.bmp", 0), ("1.bmp", 255)] {
+        image::RgbImage::from_pixel(16, 8, image::Rgb([value; 3])).save(dir.join(name)).unwrap();
+    }
+    std::fs::write(dir.join("notes.txt"), "not an image").unwrap();
+
+    let images = ImageSequence::open(&dir, 1000.0).unwrap();
+    assert_eq!(images.current_format().unwrap().width, 16);
+    let pixel = |frame: OwnedFrame| frame.data()[..4].to_vec();
+    assert_eq!(pixel(images.wait_for_frame().unwrap()), [255, 255, 255, 255]);
+    assert_eq!(pixel(images.wait_for_frame().unwrap()), [0, 0, 0, 255]);
+    assert_eq!(pixel(images.wait_for_frame().unwrap()), [255, 255, 255, 255]);
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(ImageSequence::open(&dir, 30.0).is_err());
+}
@@ -0,0 +1,45 @@
+use std::sync::Mutex;
+
+use crate::sync::lock;
+
+/// Recycles the buffers of converted frames, so a steady stream of frames of the same size
+/// doesn't allocate. A capacity of 0 keeps nothing.
+#[derive(Debug, Default)]
+pub(crate) struct FramePool {
+    buffers: Mutex<Vec<Vec<u8>>>,
+    capacity: usize,
+}
+
+impl FramePool {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self { buffers: Mutex::new(Vec::with_capacity(capacity)), capacity }
+    }
+
+    /// A returned buffer if there is one, otherwise a new empty one.
+    pub(crate) fn take(&self) -> Vec<u8> {
+        lock(&self.buffers).pop().unwrap_or_default()
+    }
+
+    /// Keeps `buffer` for the next frame unless the pool is full.
+    pub(crate) fn put(&self, buffer: Vec<u8>) {
+        let mut buffers = lock(&self.buffers);
+        if buffers.len() < self.capacity {
+            buffers.push(buffer);
+        }
+    }
+}
+
+#[test]
+fn frame_pool_reuses_buffers() {
+    let pool = FramePool::new(1);
+    let buffer = vec![0; 16];
+    let ptr = buffer.as_ptr();
+    pool.put(buffer);
+    pool.put(vec![0; 16]);
+    let reused = pool.take();
+    assert_eq!(reused.as_ptr(), ptr);
+    assert_eq!(pool.take().capacity(), 0);
+    let off = FramePool::new(0);
+    off.put(vec![0; 16]);
+    assert_eq!(off.take().capacity(), 0);
+}
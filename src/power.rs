@@ -0,0 +1,207 @@
+//! System sleep notifications, which stop the streams of native cameras before sleep and start
+//! them again after wake, see [`CameraEvent::Suspended`](crate::CameraEvent::Suspended).
+//!
+//! One watcher per process sends the notifications of the OS to every camera: IOKit system power
+//! notifications on macOS, a suspend/resume callback on Windows. Linux has no notification
+//! without D-Bus, the watcher compares the boot time clock, which counts sleep, with the
+//! monotonic one and reports both changes after wake.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Mutex, Once};
+
+use crate::sync::lock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PowerEvent {
+    Suspend,
+    Resume,
+}
+
+static SUBSCRIBERS: Mutex<Vec<Sender<PowerEvent>>> = Mutex::new(Vec::new());
+
+/// Sends `event` to the watches which are still alive.
+fn broadcast(event: PowerEvent) {
+    lock(&SUBSCRIBERS).retain(|subscriber| subscriber.send(event).is_ok());
+}
+
+/// The power state of one camera, whether its stream runs and has to start again after wake.
+#[derive(Debug)]
+pub(crate) struct PowerWatch {
+    events: Mutex<Receiver<PowerEvent>>,
+    streaming: AtomicBool,
+    restart: AtomicBool,
+}
+
+impl PowerWatch {
+    pub(crate) fn new() -> Self {
+        static WATCHER: Once = Once::new();
+        WATCHER.call_once(watch);
+        let (tx, events) = channel();
+        lock(&SUBSCRIBERS).push(tx);
+        Self { events: Mutex::new(events), streaming: false.into(), restart: false.into() }
+    }
+
+    pub(crate) fn set_streaming(&self, streaming: bool) {
+        self.streaming.store(streaming, Ordering::Relaxed);
+        if !streaming {
+            self.restart.store(false, Ordering::Relaxed);
+        }
+    }
+
+    /// The notifications since the last call.
+    pub(crate) fn changes(&self) -> Vec<PowerEvent> {
+        lock(&self.events).try_iter().collect()
+    }
+
+    /// Remembers whether the stream ran, it is stopped for the sleep.
+    pub(crate) fn suspend(&self) -> bool {
+        let streaming = self.streaming.swap(false, Ordering::Relaxed);
+        self.restart.fetch_or(streaming, Ordering::Relaxed);
+        streaming
+    }
+
+    /// Whether the stream ran before the sleep.
+    pub(crate) fn resume(&self) -> bool {
+        self.restart.swap(false, Ordering::Relaxed)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn watch() {
+    use std::time::{Duration, Instant};
+
+    const INTERVAL: Duration = Duration::from_secs(1);
+    // more than the scheduling delays of a busy system
+    const MIN_SLEEP: Duration = Duration::from_secs(2);
+
+    /// The boot time clock of /proc/uptime, which keeps counting during sleep unlike `Instant`.
+    fn uptime() -> Option<Duration> {
+        let uptime = std::fs::read_to_string("/proc/uptime").ok()?;
+        Duration::try_from_secs_f64(uptime.split_whitespace().next()?.parse().ok()?).ok()
+    }
+
+    let Some(mut boot) = uptime() else { return };
+    let mut monotonic = Instant::now();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(INTERVAL);
+        let Some(now) = uptime() else { return };
+        let slept = (now - boot).saturating_sub(monotonic.elapsed());
+        (boot, monotonic) = (now, Instant::now());
+        if slept > MIN_SLEEP {
+            broadcast(PowerEvent::Suspend);
+            broadcast(PowerEvent::Resume);
+        }
+    });
+}
+
+#[cfg(target_os = "windows")]
+fn watch() {
+    use std::ffi::c_void;
+
+    use windows::Win32::Foundation::HANDLE;
+    use windows::Win32::System::Power::{
+        PowerRegisterSuspendResumeNotification, DEVICE_NOTIFY_CALLBACK,
+        DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS,
+    };
+
+    // from WinUser.h
+    const PBT_APMSUSPEND: u32 = 0x4;
+    const PBT_APMRESUMEAUTOMATIC: u32 = 0x12;
+
+    unsafe extern "system" fn callback(_: *const c_void, kind: u32, _: *const c_void) -> u32 {
+        match kind {
+            PBT_APMSUSPEND => broadcast(PowerEvent::Suspend),
+            PBT_APMRESUMEAUTOMATIC => broadcast(PowerEvent::Resume),
+            _ => {}
+        }
+        0
+    }
+
+    // registered for the lifetime of the process
+    let params = Box::leak(Box::new(DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS {
+        Callback: Some(callback),
+        Context: std::ptr::null_mut(),
+    }));
+    let mut registration = std::ptr::null_mut();
+    unsafe {
+        PowerRegisterSuspendResumeNotification(
+            DEVICE_NOTIFY_CALLBACK.0,
+            HANDLE(params as *mut _ as isize),
+            &mut registration,
+        )
+    };
+}
+
+#[cfg(target_os = "macos")]
+fn watch() {
+    use std::ffi::{c_void, CString};
+    use std::ptr::{null, null_mut};
+
+    use crate::mac_avf::{dispatch_queue_create, DispatchQueueT};
+
+    // from IOKit/IOMessage.h
+    const CAN_SYSTEM_SLEEP: u32 = 0xe0000270;
+    const SYSTEM_WILL_SLEEP: u32 = 0xe0000280;
+    const SYSTEM_HAS_POWERED_ON: u32 = 0xe0000300;
+
+    #[link(name = "IOKit", kind = "framework")]
+    extern "C" {
+        fn IORegisterForSystemPower(
+            refcon: *mut c_void,
+            port: *mut *mut c_void,
+            callback: extern "C" fn(*mut c_void, u32, u32, *mut c_void),
+            notifier: *mut u32,
+        ) -> u32;
+        fn IONotificationPortSetDispatchQueue(port: *mut c_void, queue: DispatchQueueT);
+        fn IOAllowPowerChange(root_port: u32, notification: isize) -> i32;
+    }
+
+    static ROOT_PORT: std::sync::OnceLock<u32> = std::sync::OnceLock::new();
+
+    extern "C" fn callback(_: *mut c_void, _: u32, message: u32, argument: *mut c_void) {
+        let Some(&root_port) = ROOT_PORT.get() else { return };
+        match message {
+            CAN_SYSTEM_SLEEP => unsafe {
+                IOAllowPowerChange(root_port, argument as isize);
+            },
+            SYSTEM_WILL_SLEEP => {
+                broadcast(PowerEvent::Suspend);
+                // sleep waits up to 30 seconds for the answer
+                unsafe { IOAllowPowerChange(root_port, argument as isize) };
+            }
+            SYSTEM_HAS_POWERED_ON => broadcast(PowerEvent::Resume),
+            _ => {}
+        }
+    }
+
+    let (mut port, mut notifier) = (null_mut(), 0);
+    let root_port =
+        unsafe { IORegisterForSystemPower(null_mut(), &mut port, callback, &mut notifier) };
+    if root_port == 0 {
+        return;
+    }
+    let _ = ROOT_PORT.set(root_port);
+    let name = CString::new("kamera power").unwrap();
+    // kept for the lifetime of the process
+    let queue = unsafe { dispatch_queue_create(name.as_ptr(), null()) };
+    unsafe { IONotificationPortSetDispatchQueue(port, queue) };
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+fn watch() {}
+
+#[test]
+fn power_watch() {
+    let watch = PowerWatch::new();
+    watch.set_streaming(true);
+    broadcast(PowerEvent::Suspend);
+    assert_eq!(watch.changes(), vec![PowerEvent::Suspend]);
+    assert!(watch.suspend());
+    assert!(!watch.suspend());
+    broadcast(PowerEvent::Resume);
+    assert_eq!(watch.changes(), vec![PowerEvent::Resume]);
+    assert!(watch.resume());
+    assert!(!watch.resume());
+    assert!(watch.changes().is_empty());
+}
@@ -0,0 +1,34 @@
+/// Quality level or size of the capture session, see [`Camera::set_preset`](crate::Camera::set_preset).
+///
+/// The presets of `AVCaptureSession`, where AVFoundation picks a matching device format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SessionPreset {
+    Low,
+    Medium,
+    /// The highest quality for video, the default.
+    #[default]
+    High,
+    /// The full resolution of the sensor, often 4:3 and at a lower frame rate.
+    Photo,
+    Cif352x288,
+    Vga640x480,
+    Qhd960x540,
+    Hd1280x720,
+    Hd1920x1080,
+    Hd4K3840x2160,
+}
+
+impl SessionPreset {
+    /// The frame size of the fixed size presets.
+    pub fn size(self) -> Option<(u32, u32)> {
+        match self {
+            Self::Low | Self::Medium | Self::High | Self::Photo => None,
+            Self::Cif352x288 => Some((352, 288)),
+            Self::Vga640x480 => Some((640, 480)),
+            Self::Qhd960x540 => Some((960, 540)),
+            Self::Hd1280x720 => Some((1280, 720)),
+            Self::Hd1920x1080 => Some((1920, 1080)),
+            Self::Hd4K3840x2160 => Some((3840, 2160)),
+        }
+    }
+}
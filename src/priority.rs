@@ -0,0 +1,132 @@
+use std::cell::Cell;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Scheduling priority of the threads which deliver frames, see
+/// [`Camera::set_thread_priority`](crate::Camera::set_thread_priority).
+///
+/// | | macOS QoS class | Windows thread priority | Linux |
+/// |---|---|---|---|
+/// | `Background` | utility | lowest | nice 10 |
+/// | `Normal` | default | normal | nice 0 |
+/// | `High` | user initiated | highest | nice -10 |
+/// | `Realtime` | user interactive | time critical | `SCHED_FIFO` |
+///
+/// Linux needs `CAP_SYS_NICE` or an rtkit grant to raise the priority, without it the threads
+/// keep theirs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ThreadPriority {
+    Background,
+    #[default]
+    Normal,
+    High,
+    Realtime,
+}
+
+impl ThreadPriority {
+    const ALL: [Self; 4] = [Self::Background, Self::Normal, Self::High, Self::Realtime];
+}
+
+thread_local! {
+    static APPLIED: Cell<ThreadPriority> = const { Cell::new(ThreadPriority::Normal) };
+}
+
+/// The priority a camera wants for its delivery threads. Threads the OS owns can only be
+/// changed from inside, so each of them applies it when it delivers the next frame.
+#[derive(Debug)]
+pub(crate) struct PriorityRequest(AtomicU8);
+
+impl Default for PriorityRequest {
+    fn default() -> Self {
+        Self(AtomicU8::new(ThreadPriority::Normal as u8))
+    }
+}
+
+impl PriorityRequest {
+    pub(crate) fn set(&self, priority: ThreadPriority) {
+        self.0.store(priority as u8, Ordering::Relaxed);
+    }
+
+    pub(crate) fn get(&self) -> ThreadPriority {
+        ThreadPriority::ALL[self.0.load(Ordering::Relaxed) as usize]
+    }
+
+    /// Applies the requested priority to the calling thread, unless it already has it.
+    #[cfg_attr(any(target_os = "macos", target_os = "ios"), allow(dead_code))]
+    pub(crate) fn follow(&self) {
+        let priority = self.get();
+        if APPLIED.get() != priority {
+            APPLIED.set(priority);
+            apply(priority);
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn apply(priority: ThreadPriority) {
+    #[repr(C)]
+    struct SchedParam {
+        priority: i32,
+    }
+    // from sched.h and sys/resource.h
+    const SCHED_OTHER: i32 = 0;
+    const SCHED_FIFO: i32 = 1;
+    const PRIO_PROCESS: i32 = 0;
+    extern "C" {
+        fn sched_setscheduler(pid: i32, policy: i32, param: *const SchedParam) -> i32;
+        fn setpriority(which: i32, who: u32, prio: i32) -> i32;
+    }
+    // pid 0 is the calling thread, nice values are per thread on Linux
+    let (policy, param) = match priority {
+        ThreadPriority::Realtime => (SCHED_FIFO, SchedParam { priority: 10 }),
+        _ => (SCHED_OTHER, SchedParam { priority: 0 }),
+    };
+    let nice = match priority {
+        ThreadPriority::Background => 10,
+        ThreadPriority::Normal | ThreadPriority::Realtime => 0,
+        ThreadPriority::High => -10,
+    };
+    unsafe {
+        sched_setscheduler(0, policy, &param);
+        setpriority(PRIO_PROCESS, 0, nice);
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn apply(priority: ThreadPriority) {
+    use windows::Win32::System::Threading::{
+        GetCurrentThread, SetThreadPriority, THREAD_PRIORITY_HIGHEST, THREAD_PRIORITY_LOWEST,
+        THREAD_PRIORITY_NORMAL, THREAD_PRIORITY_TIME_CRITICAL,
+    };
+    let level = match priority {
+        ThreadPriority::Background => THREAD_PRIORITY_LOWEST,
+        ThreadPriority::Normal => THREAD_PRIORITY_NORMAL,
+        ThreadPriority::High => THREAD_PRIORITY_HIGHEST,
+        ThreadPriority::Realtime => THREAD_PRIORITY_TIME_CRITICAL,
+    };
+    unsafe { SetThreadPriority(GetCurrentThread(), level) };
+}
+
+/// macOS sets the QoS class of the dispatch queue instead.
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn apply(_priority: ThreadPriority) {}
+
+/// The `qos_class_t` of `priority`, from sys/qos.h.
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub(crate) fn qos_class(priority: ThreadPriority) -> u32 {
+    match priority {
+        ThreadPriority::Background => 0x11,
+        ThreadPriority::Normal => 0x15,
+        ThreadPriority::High => 0x19,
+        ThreadPriority::Realtime => 0x21,
+    }
+}
+
+#[test]
+fn priority_request() {
+    let request = PriorityRequest::default();
+    assert_eq!(request.get(), ThreadPriority::Normal);
+    for priority in ThreadPriority::ALL {
+        request.set(priority);
+        assert_eq!(request.get(), priority);
+    }
+}
@@ -0,0 +1,20 @@
+use crate::{CaptureFormat, Latency, PtzAxis};
+
+/// The setup of a camera, to restore the exact camera of a user in the next session, see
+/// [`Camera::current_profile`](crate::Camera::current_profile) and
+/// [`Camera::apply_profile`](crate::Camera::apply_profile). With the `serde` feature it can be
+/// saved with any serde format.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CameraProfile {
+    /// [`CameraDevice::stable_id`](crate::CameraDevice::stable_id) of the device, empty for a
+    /// [`FrameSource`](crate::FrameSource).
+    pub device: String,
+    /// Pixel format, resolution and frame rate range the device delivered.
+    pub format: Option<CaptureFormat>,
+    /// The frame rate limit of [`Camera::set_max_fps`](crate::Camera::set_max_fps).
+    pub max_fps: Option<f32>,
+    pub latency: Option<Latency>,
+    /// Pan, tilt and zoom of the axes the device has.
+    pub controls: Vec<(PtzAxis, f32)>,
+}
@@ -0,0 +1,46 @@
+/// Pan, tilt and zoom of conference cameras, see [`Camera::set_ptz`](crate::Camera::set_ptz).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PtzAxis {
+    /// Degrees, positive turns to the right. Linux and Windows.
+    Pan,
+    /// Degrees, positive turns up. Linux and Windows.
+    Tilt,
+    /// A magnification factor starting at 1 on macOS and iOS. Linux and Windows drivers use
+    /// their own units, larger values zoom in.
+    Zoom,
+}
+
+/// The values an axis accepts, see [`Camera::ptz_range`](crate::Camera::ptz_range).
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PtzRange {
+    pub min: f32,
+    pub max: f32,
+    /// Zero if the axis moves continuously.
+    pub step: f32,
+    pub default: f32,
+}
+
+impl PtzRange {
+    /// Clamps `value` into the range and rounds it to a step.
+    pub fn snap(&self, value: f32) -> f32 {
+        let value = value.clamp(self.min, self.max);
+        if self.step > 0.0 {
+            (self.min + ((value - self.min) / self.step).round() * self.step).min(self.max)
+        } else {
+            value
+        }
+    }
+}
+
+#[test]
+fn snap_to_step() {
+    let range = PtzRange { min: -10.0, max: 10.0, step: 4.0, default: 0.0 };
+    assert_eq!(range.snap(0.0), 2.0);
+    assert_eq!(range.snap(-0.5), -2.0);
+    assert_eq!(range.snap(100.0), 10.0);
+    assert_eq!(range.snap(-100.0), -10.0);
+    let continuous = PtzRange { step: 0.0, ..range };
+    assert_eq!(continuous.snap(0.3), 0.3);
+}
@@ -0,0 +1,66 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::sync::lock;
+use crate::time::Instant;
+
+/// Drops frames arriving faster than a maximum frame rate, see
+/// [`Camera::set_max_fps`](crate::Camera::set_max_fps).
+#[derive(Debug, Default)]
+pub(crate) struct FrameRateLimit {
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Zero without a limit.
+    interval: Duration,
+    /// When the next frame is due.
+    due: Option<Instant>,
+}
+
+impl FrameRateLimit {
+    /// Zero, a negative or a non finite frame rate removes the limit.
+    pub(crate) fn set_max_fps(&self, fps: f32) {
+        let interval = match fps > 0.0 && fps.is_finite() {
+            true => Duration::from_secs_f64(1.0 / fps as f64),
+            false => Duration::ZERO,
+        };
+        *lock(&self.state) = State { interval, due: None };
+    }
+
+    /// Whether to deliver a frame which arrived at `now`.
+    pub(crate) fn accept(&self, now: Instant) -> bool {
+        let mut state = lock(&self.state);
+        let interval = state.interval;
+        if interval.is_zero() {
+            return true;
+        }
+        // frames arrive with some jitter, one slightly early is still on schedule
+        let jitter = (interval / 4).min(Duration::from_millis(5));
+        if state.due.is_some_and(|due| now + jitter < due) {
+            return false;
+        }
+        // keep the schedule unless it fell behind, e.g. after the camera was stopped
+        state.due = match state.due {
+            Some(due) if now < due + interval => Some(due + interval),
+            _ => Some(now + interval),
+        };
+        true
+    }
+}
+
+#[test]
+fn frame_rate_limit_decimates() {
+    let limit = FrameRateLimit::default();
+    let start = Instant::now();
+    // 30 fps with up to 3 ms of jitter
+    let arrivals = (0..90).map(|i| start + Duration::from_micros(i * 33_333 + i % 3 * 1000));
+    assert_eq!(arrivals.clone().filter(|&t| limit.accept(t)).count(), 90);
+    limit.set_max_fps(15.0);
+    assert_eq!(arrivals.clone().filter(|&t| limit.accept(t)).count(), 45);
+    limit.set_max_fps(5.0);
+    assert_eq!(arrivals.clone().filter(|&t| limit.accept(t)).count(), 15);
+    limit.set_max_fps(0.0);
+    assert!(arrivals.clone().all(|t| limit.accept(t)));
+}
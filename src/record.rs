@@ -0,0 +1,208 @@
+//! Record frames to a video file, enabled with the `record` feature.
+//!
+//! Files ending in `.mp4` get H.264 from [openh264](https://github.com/cisco/openh264) in an MP4
+//! container, which plays everywhere. Other paths get uncompressed
+//! [YUV4MPEG2](https://wiki.multimedia.cx/index.php/YUV4MPEG2), which ffmpeg, mpv and VLC play
+//! directly, for when every pixel matters.
+//!
+//! ```no_run
+//! use kamera::record::{RecordSettings, VideoRecorder};
+//!
+//! let camera = kamera::Camera::new_default_device();
+//! camera.start();
+//! let mut recorder = VideoRecorder::new("clip.mp4", RecordSettings::default()).unwrap();
+//! for _ in 0..90 {
+//!     recorder.write_frame(&camera.wait_for_frame().unwrap()).unwrap();
+//! }
+//! recorder.finish().unwrap();
+//! ```
+
+mod mp4;
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use openh264::encoder::{Encoder, EncoderConfig};
+use openh264::formats::YUVBuffer;
+
+use crate::convert::{bgra_to_rgb, bgra_to_yuv444};
+use crate::Frame;
+use mp4::{nal_units, Mp4Writer};
+
+#[derive(Debug, Clone)]
+pub struct RecordSettings {
+    /// Playback frame rate written to the file, default is 30.
+    pub fps: u32,
+    /// Target bitrate of H.264 in bits per second, default is 4 Mbit/s.
+    pub bitrate: u32,
+}
+
+impl Default for RecordSettings {
+    fn default() -> Self {
+        Self { fps: 30, bitrate: 4_000_000 }
+    }
+}
+
+#[derive(Debug)]
+pub struct VideoRecorder {
+    output: Output,
+    settings: RecordSettings,
+    size: Option<(u32, u32)>,
+    frame_count: u64,
+}
+
+#[derive(Debug)]
+enum Output {
+    Y4m(BufWriter<File>),
+    /// The encoder is created with the first frame, which decides the size.
+    H264(Option<H264>, Option<BufWriter<File>>),
+}
+
+struct H264 {
+    encoder: Encoder,
+    mp4: Mp4Writer<BufWriter<File>>,
+}
+
+impl fmt::Debug for H264 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("H264").field("mp4", &self.mp4).finish_non_exhaustive()
+    }
+}
+
+impl VideoRecorder {
+    /// Records to MP4 if `path` ends in `.mp4`, otherwise to Y4M.
+    pub fn new(path: impl AsRef<Path>, settings: RecordSettings) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mp4 = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("mp4"));
+        let out = BufWriter::new(File::create(path)?);
+        let output = if mp4 { Output::H264(None, Some(out)) } else { Output::Y4m(out) };
+        Ok(Self { output, settings, size: None, frame_count: 0 })
+    }
+
+    /// The first frame decides the video size, frames of another size are rejected.
+    pub fn write_frame(&mut self, frame: &Frame) -> io::Result<()> {
+        let (w, h) = frame.size_u32();
+        self.write_bgra(frame.data().data_bgra(), w, h)
+    }
+
+    /// Like [`VideoRecorder::write_frame`] for packed BGRA pixels.
+    ///
+    /// H.264 needs an even width and height, MP4 drops the last column or row of odd sizes.
+    pub fn write_bgra(&mut self, bgra: &[u8], width: u32, height: u32) -> io::Result<()> {
+        if bgra.len() < width as usize * height as usize * 4 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "buffer smaller than frame"));
+        }
+        match self.size {
+            None => {
+                self.start(width, height)?;
+                self.size = Some((width, height));
+            }
+            Some(size) if size != (width, height) => {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "frame size changed"));
+            }
+            Some(_) => {}
+        }
+        match &mut self.output {
+            Output::Y4m(out) => {
+                out.write_all(b"FRAME\n")?;
+                for plane in bgra_to_yuv444(bgra, width, height) {
+                    out.write_all(&plane)?;
+                }
+            }
+            Output::H264(Some(h264), _) => {
+                let (w, h) = (width & !1, height & !1);
+                let rgb = bgra_to_rgb(bgra, w, h, width as usize * 4);
+                let yuv = YUVBuffer::with_rgb(w as usize, h as usize, &rgb);
+                let stream = h264.encoder.encode(&yuv).map_err(io::Error::other)?.to_vec();
+                let units = nal_units(&stream);
+                if units.is_empty() {
+                    // skipped by the rate control
+                    h264.mp4.extend_last(1);
+                } else {
+                    h264.mp4.write_sample(&units, 1)?;
+                }
+            }
+            Output::H264(None, _) => unreachable!("started with the first frame"),
+        }
+        self.frame_count += 1;
+        Ok(())
+    }
+
+    fn start(&mut self, width: u32, height: u32) -> io::Result<()> {
+        let RecordSettings { fps, bitrate } = self.settings;
+        match &mut self.output {
+            Output::Y4m(out) => {
+                writeln!(out, "YUV4MPEG2 W{width} H{height} F{fps}:1 Ip A1:1 C444")?;
+            }
+            Output::H264(h264, out) => {
+                let (w, h) = (width & !1, height & !1);
+                if w == 0 || h == 0 {
+                    return Err(io::Error::new(io::ErrorKind::InvalidInput, "frame too small"));
+                }
+                let config =
+                    EncoderConfig::new(w, h).set_bitrate_bps(bitrate).max_frame_rate(fps as f32);
+                let encoder = Encoder::with_config(config).map_err(io::Error::other)?;
+                let out = out.take().expect("only started once");
+                let mp4 = Mp4Writer::new(out, (w, h), fps)?;
+                *h264 = Some(H264 { encoder, mp4 });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Flushes the file, and for MP4 writes the index without which players can't open it.
+    /// Dropping the recorder flushes too but ignores errors and leaves MP4 files unplayable.
+    pub fn finish(self) -> io::Result<()> {
+        match self.output {
+            Output::Y4m(mut out) => out.flush(),
+            Output::H264(Some(h264), _) => h264.mp4.finish()?.flush(),
+            Output::H264(None, Some(mut out)) => out.flush(),
+            Output::H264(None, None) => Ok(()),
+        }
+    }
+}
+
+#[test]
+fn record_y4m() {
+    let path = std::env::temp_dir().join("kamera_record_y4m.y4m");
+    let mut recorder =
+        VideoRecorder::new(&path, RecordSettings { fps: 15, ..Default::default() }).unwrap();
+    recorder.write_bgra(&[0; 2 * 2 * 4], 2, 2).unwrap();
+    recorder.write_bgra(&[255; 2 * 2 * 4], 2, 2).unwrap();
+    assert!(recorder.write_bgra(&[0; 4 * 4], 4, 1).is_err());
+    assert_eq!(recorder.frame_count(), 2);
+    recorder.finish().unwrap();
+
+    let data = std::fs::read(&path).unwrap();
+    let header = b"YUV4MPEG2 W2 H2 F15:1 Ip A1:1 C444\n";
+    assert!(data.starts_with(header));
+    assert_eq!(data.len(), header.len() + 2 * (6 + 2 * 2 * 3));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn record_mp4() {
+    let path = std::env::temp_dir().join("kamera_record_mp4.mp4");
+    let mut recorder = VideoRecorder::new(&path, RecordSettings::default()).unwrap();
+    let gradient: Vec<u8> = (0..33 * 17 * 4).map(|i| i as u8).collect();
+    for _ in 0..3 {
+        recorder.write_bgra(&gradient, 33, 17).unwrap();
+    }
+    assert!(recorder.write_bgra(&gradient, 17, 33).is_err());
+    assert_eq!(recorder.frame_count(), 3);
+    recorder.finish().unwrap();
+
+    let data = std::fs::read(&path).unwrap();
+    assert_eq!(&data[4..8], b"ftyp");
+    assert!(data.windows(4).any(|w| w == b"moov"));
+    // the sample entry, after the brand in ftyp, has the size cropped to even
+    let avc1 = data.windows(4).rposition(|w| w == b"avc1").unwrap() + 4;
+    assert_eq!(data[avc1 + 24..avc1 + 28], [0, 32, 0, 16]);
+    std::fs::remove_file(path).unwrap();
+}
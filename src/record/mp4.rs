@@ -0,0 +1,356 @@
+//! A minimal MP4 writer for one H.264 track, enough for players and editors to open the files
+//! of [`VideoRecorder`](super::VideoRecorder).
+//!
+//! Samples go straight into the `mdat` box, the sample tables follow in the `moov` box at the
+//! end, when the sizes of all samples are known. One sample per chunk keeps the tables simple.
+
+use std::io::{self, Seek, SeekFrom, Write};
+
+/// Splits an Annex B stream, as H.264 encoders produce it, into NAL units without start codes.
+pub(crate) fn nal_units(stream: &[u8]) -> Vec<&[u8]> {
+    let mut starts = Vec::new();
+    let mut i = 0;
+    while i + 3 <= stream.len() {
+        if stream[i..i + 3] == [0, 0, 1] {
+            starts.push(i + 3);
+            i += 3;
+        } else {
+            i += 1;
+        }
+    }
+    let mut units = Vec::with_capacity(starts.len());
+    for (n, &start) in starts.iter().enumerate() {
+        let mut end = starts.get(n + 1).map_or(stream.len(), |next| next - 3);
+        // the zero of a four byte start code belongs to the next one
+        while end > start && stream[end - 1] == 0 && n + 1 < starts.len() {
+            end -= 1;
+        }
+        if end > start {
+            units.push(&stream[start..end]);
+        }
+    }
+    units
+}
+
+pub(crate) const NAL_IDR: u8 = 5;
+pub(crate) const NAL_SPS: u8 = 7;
+pub(crate) const NAL_PPS: u8 = 8;
+
+pub(crate) fn nal_type(unit: &[u8]) -> u8 {
+    unit.first().map_or(0, |header| header & 0x1f)
+}
+
+#[derive(Debug)]
+struct Sample {
+    offset: u64,
+    size: u32,
+    sync: bool,
+    duration: u32,
+}
+
+/// Writes an MP4 file with one H.264 video track of `timescale` ticks per second.
+#[derive(Debug)]
+pub(crate) struct Mp4Writer<W: Write + Seek> {
+    out: W,
+    size: (u32, u32),
+    timescale: u32,
+    sps: Vec<u8>,
+    pps: Vec<u8>,
+    mdat_start: u64,
+    position: u64,
+    samples: Vec<Sample>,
+}
+
+impl<W: Write + Seek> Mp4Writer<W> {
+    pub(crate) fn new(mut out: W, size: (u32, u32), timescale: u32) -> io::Result<Self> {
+        let mut ftyp = Vec::new();
+        ftyp.extend_from_slice(b"isom");
+        ftyp.extend_from_slice(&512u32.to_be_bytes());
+        for brand in [b"isom", b"iso2", b"avc1", b"mp41"] {
+            ftyp.extend_from_slice(brand);
+        }
+        let ftyp = boxed(b"ftyp", &ftyp);
+        out.write_all(&ftyp)?;
+        // a 64-bit size, filled in by `finish`
+        out.write_all(&1u32.to_be_bytes())?;
+        out.write_all(b"mdat")?;
+        out.write_all(&0u64.to_be_bytes())?;
+        let mdat_start = ftyp.len() as u64;
+        let position = mdat_start + 16;
+        let (sps, pps) = (Vec::new(), Vec::new());
+        Ok(Self { out, size, timescale, sps, pps, mdat_start, position, samples: Vec::new() })
+    }
+
+    /// Writes the NAL units of one frame, taking the first SPS and PPS for the track header.
+    /// `duration` is in ticks of the timescale.
+    pub(crate) fn write_sample(&mut self, units: &[&[u8]], duration: u32) -> io::Result<()> {
+        let mut sync = false;
+        let mut size = 0;
+        for unit in units {
+            match nal_type(unit) {
+                NAL_SPS if self.sps.is_empty() => self.sps = unit.to_vec(),
+                NAL_PPS if self.pps.is_empty() => self.pps = unit.to_vec(),
+                // parameter sets live in the sample description
+                NAL_SPS | NAL_PPS => {}
+                kind => {
+                    sync |= kind == NAL_IDR;
+                    self.out.write_all(&(unit.len() as u32).to_be_bytes())?;
+                    self.out.write_all(unit)?;
+                    size += 4 + unit.len() as u32;
+                }
+            }
+        }
+        if size > 0 {
+            self.samples.push(Sample { offset: self.position, size, sync, duration });
+            self.position += size as u64;
+        }
+        Ok(())
+    }
+
+    /// Lengthens the last sample, for a frame the encoder skipped.
+    pub(crate) fn extend_last(&mut self, duration: u32) {
+        if let Some(last) = self.samples.last_mut() {
+            last.duration += duration;
+        }
+    }
+
+    pub(crate) fn sample_count(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Completes the `mdat` box and appends the `moov` box.
+    pub(crate) fn finish(mut self) -> io::Result<W> {
+        let mdat_size = self.position - self.mdat_start;
+        self.out.seek(SeekFrom::Start(self.mdat_start + 8))?;
+        self.out.write_all(&mdat_size.to_be_bytes())?;
+        self.out.seek(SeekFrom::Start(self.position))?;
+        let moov = self.moov();
+        self.out.write_all(&moov)?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    fn moov(&self) -> Vec<u8> {
+        let duration: u64 = self.samples.iter().map(|s| s.duration as u64).sum();
+        let movie_duration = duration * 1000 / self.timescale.max(1) as u64;
+        let (width, height) = self.size;
+
+        let mut mvhd = full_box_header(0, 0);
+        mvhd.extend_from_slice(&[0; 8]); // creation and modification time
+        mvhd.extend_from_slice(&1000u32.to_be_bytes());
+        mvhd.extend_from_slice(&(movie_duration as u32).to_be_bytes());
+        mvhd.extend_from_slice(&0x0001_0000u32.to_be_bytes()); // rate 1.0
+        mvhd.extend_from_slice(&0x0100u16.to_be_bytes()); // volume 1.0
+        mvhd.extend_from_slice(&[0; 10]);
+        mvhd.extend_from_slice(&MATRIX);
+        mvhd.extend_from_slice(&[0; 24]);
+        mvhd.extend_from_slice(&2u32.to_be_bytes()); // next track id
+
+        let mut tkhd = full_box_header(0, 3); // enabled and in the movie
+        tkhd.extend_from_slice(&[0; 8]);
+        tkhd.extend_from_slice(&1u32.to_be_bytes()); // track id
+        tkhd.extend_from_slice(&[0; 4]);
+        tkhd.extend_from_slice(&(movie_duration as u32).to_be_bytes());
+        tkhd.extend_from_slice(&[0; 8]);
+        tkhd.extend_from_slice(&[0; 8]); // layer, alternate group, volume, reserved
+        tkhd.extend_from_slice(&MATRIX);
+        tkhd.extend_from_slice(&(width << 16).to_be_bytes());
+        tkhd.extend_from_slice(&(height << 16).to_be_bytes());
+
+        let mut mdhd = full_box_header(0, 0);
+        mdhd.extend_from_slice(&[0; 8]);
+        mdhd.extend_from_slice(&self.timescale.to_be_bytes());
+        mdhd.extend_from_slice(&(duration as u32).to_be_bytes());
+        mdhd.extend_from_slice(&0x55c4u16.to_be_bytes()); // "und"
+        mdhd.extend_from_slice(&[0; 2]);
+
+        let mut hdlr = full_box_header(0, 0);
+        hdlr.extend_from_slice(&[0; 4]);
+        hdlr.extend_from_slice(b"vide");
+        hdlr.extend_from_slice(&[0; 12]);
+        hdlr.extend_from_slice(b"VideoHandler\0");
+
+        let mut vmhd = full_box_header(0, 1);
+        vmhd.extend_from_slice(&[0; 8]);
+
+        let mut dref = full_box_header(0, 0);
+        dref.extend_from_slice(&1u32.to_be_bytes());
+        dref.extend_from_slice(&boxed(b"url ", &full_box_header(0, 1)));
+        let dinf = boxed(b"dinf", &boxed(b"dref", &dref));
+
+        let stbl = [
+            boxed(b"stsd", &self.stsd()),
+            boxed(b"stts", &self.stts()),
+            boxed(b"stss", &self.stss()),
+            boxed(b"stsc", &stsc()),
+            boxed(b"stsz", &self.stsz()),
+            boxed(b"co64", &self.co64()),
+        ]
+        .concat();
+        let minf = [boxed(b"vmhd", &vmhd), dinf, boxed(b"stbl", &stbl)].concat();
+        let mdia = [boxed(b"mdhd", &mdhd), boxed(b"hdlr", &hdlr), boxed(b"minf", &minf)].concat();
+        let trak = [boxed(b"tkhd", &tkhd), boxed(b"mdia", &mdia)].concat();
+        let moov = [boxed(b"mvhd", &mvhd), boxed(b"trak", &trak)].concat();
+        boxed(b"moov", &moov)
+    }
+
+    fn stsd(&self) -> Vec<u8> {
+        let (width, height) = self.size;
+        let mut avc1 = vec![0; 6];
+        avc1.extend_from_slice(&1u16.to_be_bytes()); // data reference index
+        avc1.extend_from_slice(&[0; 16]);
+        avc1.extend_from_slice(&(width as u16).to_be_bytes());
+        avc1.extend_from_slice(&(height as u16).to_be_bytes());
+        avc1.extend_from_slice(&0x0048_0000u32.to_be_bytes()); // 72 dpi
+        avc1.extend_from_slice(&0x0048_0000u32.to_be_bytes());
+        avc1.extend_from_slice(&[0; 4]);
+        avc1.extend_from_slice(&1u16.to_be_bytes()); // frames per sample
+        avc1.extend_from_slice(&[0; 32]); // compressor name
+        avc1.extend_from_slice(&0x0018u16.to_be_bytes()); // depth
+        avc1.extend_from_slice(&(-1i16).to_be_bytes());
+
+        let profile = |index: usize| self.sps.get(index).copied().unwrap_or(0);
+        let mut avcc = vec![1, profile(1), profile(2), profile(3), 0xff, 0xe1];
+        avcc.extend_from_slice(&(self.sps.len() as u16).to_be_bytes());
+        avcc.extend_from_slice(&self.sps);
+        avcc.push(1);
+        avcc.extend_from_slice(&(self.pps.len() as u16).to_be_bytes());
+        avcc.extend_from_slice(&self.pps);
+        avc1.extend_from_slice(&boxed(b"avcC", &avcc));
+
+        let mut stsd = full_box_header(0, 0);
+        stsd.extend_from_slice(&1u32.to_be_bytes());
+        stsd.extend_from_slice(&boxed(b"avc1", &avc1));
+        stsd
+    }
+
+    /// Runs of samples with the same duration.
+    fn stts(&self) -> Vec<u8> {
+        let mut runs: Vec<(u32, u32)> = Vec::new();
+        for sample in &self.samples {
+            match runs.last_mut() {
+                Some((count, duration)) if *duration == sample.duration => *count += 1,
+                _ => runs.push((1, sample.duration)),
+            }
+        }
+        let mut stts = full_box_header(0, 0);
+        stts.extend_from_slice(&(runs.len() as u32).to_be_bytes());
+        for (count, duration) in runs {
+            stts.extend_from_slice(&count.to_be_bytes());
+            stts.extend_from_slice(&duration.to_be_bytes());
+        }
+        stts
+    }
+
+    fn stss(&self) -> Vec<u8> {
+        let sync: Vec<u32> = (self.samples.iter().enumerate())
+            .filter(|(_, sample)| sample.sync)
+            .map(|(index, _)| index as u32 + 1)
+            .collect();
+        let mut stss = full_box_header(0, 0);
+        stss.extend_from_slice(&(sync.len() as u32).to_be_bytes());
+        for number in sync {
+            stss.extend_from_slice(&number.to_be_bytes());
+        }
+        stss
+    }
+
+    fn stsz(&self) -> Vec<u8> {
+        let mut stsz = full_box_header(0, 0);
+        stsz.extend_from_slice(&0u32.to_be_bytes()); // sizes differ
+        stsz.extend_from_slice(&(self.samples.len() as u32).to_be_bytes());
+        for sample in &self.samples {
+            stsz.extend_from_slice(&sample.size.to_be_bytes());
+        }
+        stsz
+    }
+
+    fn co64(&self) -> Vec<u8> {
+        let mut co64 = full_box_header(0, 0);
+        co64.extend_from_slice(&(self.samples.len() as u32).to_be_bytes());
+        for sample in &self.samples {
+            co64.extend_from_slice(&sample.offset.to_be_bytes());
+        }
+        co64
+    }
+}
+
+/// Every chunk holds one sample of the only sample description.
+fn stsc() -> Vec<u8> {
+    let mut stsc = full_box_header(0, 0);
+    for value in [1u32, 1, 1, 1] {
+        stsc.extend_from_slice(&value.to_be_bytes());
+    }
+    stsc
+}
+
+/// The identity transform in 16.16 and 2.30 fixed point.
+const MATRIX: [u8; 36] = {
+    let mut matrix = [0; 36];
+    matrix[1] = 1;
+    matrix[17] = 1;
+    matrix[32] = 0x40;
+    matrix
+};
+
+fn full_box_header(version: u8, flags: u32) -> Vec<u8> {
+    let mut header = flags.to_be_bytes();
+    header[0] = version;
+    header.to_vec()
+}
+
+fn boxed(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(8 + payload.len());
+    out.extend_from_slice(&(8 + payload.len() as u32).to_be_bytes());
+    out.extend_from_slice(kind);
+    out.extend_from_slice(payload);
+    out
+}
+
+#[test]
+fn split_annex_b() {
+    let stream = [0, 0, 0, 1, 0x67, 1, 2, 0, 0, 1, 0x68, 3, 0, 0, 0, 1, 0x65, 4, 0, 5];
+    let units = nal_units(&stream);
+    assert_eq!(units, [&[0x67, 1, 2][..], &[0x68, 3], &[0x65, 4, 0, 5]]);
+    assert_eq!(units.iter().map(|u| nal_type(u)).collect::<Vec<_>>(), [NAL_SPS, NAL_PPS, NAL_IDR]);
+    assert!(nal_units(&[1, 2, 3]).is_empty());
+}
+
+#[test]
+fn write_mp4() {
+    let sps = [0x67, 0x42, 0xc0, 0x1e, 9];
+    let (pps, idr, p) = ([0x68, 0xce], [0x65, 1, 2, 3], [0x41, 4]);
+    let mut writer = Mp4Writer::new(io::Cursor::new(Vec::new()), (64, 48), 30).unwrap();
+    writer.write_sample(&[&sps, &pps, &idr], 1).unwrap();
+    writer.write_sample(&[&p], 1).unwrap();
+    writer.extend_last(1);
+    writer.write_sample(&[], 1).unwrap();
+    assert_eq!(writer.sample_count(), 2);
+    let file = writer.finish().unwrap().into_inner();
+
+    // top level boxes
+    let mut boxes = Vec::new();
+    let mut at = 0;
+    while at < file.len() {
+        let mut size = u32::from_be_bytes(file[at..at + 4].try_into().unwrap()) as usize;
+        if size == 1 {
+            size = u64::from_be_bytes(file[at + 8..at + 16].try_into().unwrap()) as usize;
+        }
+        boxes.push((&file[at + 4..at + 8], size));
+        at += size;
+    }
+    assert_eq!(at, file.len());
+    let kinds: Vec<_> = boxes.iter().map(|(kind, _)| *kind).collect();
+    assert_eq!(kinds, [b"ftyp", b"mdat", b"moov"]);
+    // the two samples with length prefixes, without the parameter sets
+    assert_eq!(boxes[1].1, 16 + 4 + idr.len() + 4 + p.len());
+
+    let find = |kind: &[u8]| file.windows(4).position(|w| w == kind).unwrap() + 4;
+    let avcc = find(b"avcC");
+    assert_eq!(file[avcc..avcc + 4], [1, 0x42, 0xc0, 0x1e]);
+    // two runs: the long P frame after the IDR frame
+    let stts = find(b"stts");
+    assert_eq!(file[stts + 4..stts + 8], 2u32.to_be_bytes());
+    let stss = find(b"stss");
+    assert_eq!(file[stss + 4..stss + 12], [0, 0, 0, 1, 0, 0, 0, 1]);
+}
@@ -0,0 +1,37 @@
+/// Part of a frame in pixels, see [`Frame::crop`](crate::Frame::crop).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self { x, y, width, height }
+    }
+
+    /// The part inside a frame of `size`, an empty part is moved to the origin.
+    pub(crate) fn clamp_to(self, (w, h): (u32, u32)) -> Self {
+        let (x, y) = (self.x.min(w), self.y.min(h));
+        let (width, height) = (self.width.min(w - x), self.height.min(h - y));
+        if width == 0 || height == 0 {
+            return Self::default();
+        }
+        Self { x, y, width, height }
+    }
+
+    /// Byte offset of the top left pixel in BGRA rows of `stride` bytes.
+    pub(crate) fn offset(&self, stride: usize) -> usize {
+        self.y as usize * stride + self.x as usize * 4
+    }
+}
+
+#[test]
+fn rect_clamp_to() {
+    assert_eq!(Rect::new(2, 1, 4, 4).clamp_to((4, 3)), Rect::new(2, 1, 2, 2));
+    assert_eq!(Rect::new(1, 1, 2, 2).clamp_to((4, 3)), Rect::new(1, 1, 2, 2));
+    assert_eq!(Rect::new(5, 1, 2, 2).clamp_to((4, 3)), Rect::default());
+    assert_eq!(Rect::new(1, 1, 2, 2).offset(16), 20);
+}
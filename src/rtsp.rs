@@ -0,0 +1,233 @@
+//! Network cameras over RTSP through FFmpeg, enabled with the `rtsp` feature.
+//!
+//! FFmpeg receives the stream, decodes H.264, H.265 or MJPEG and swscale converts the frames to
+//! BGRA. A thread owns the connection and decoder and hands frames over like a camera driver,
+//! dropping them while the application is busy instead of falling behind the live stream.
+//!
+//! ```no_run
+//! let camera = kamera::Camera::from_rtsp("rtsp://192.168.1.20:554/stream1").unwrap();
+//! camera.start();
+//! let frame = camera.wait_for_frame().unwrap();
+//! ```
+
+use std::sync::mpsc::*;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use ffmpeg_next::format::{context::Input, Pixel};
+use ffmpeg_next::frame::Video;
+use ffmpeg_next::{codec, media, software, Dictionary, Packet};
+
+use crate::sync::lock;
+use crate::{CameraDevice, CameraEvent, CaptureFormat, DeviceKind, Error, FrameSource, OwnedFrame};
+
+#[derive(Debug)]
+enum Command {
+    Start,
+    Stop,
+    Events(Sender<CameraEvent>),
+    Quit,
+}
+
+/// [`FrameSource`] which decodes the video of an RTSP URL, see [`rtsp`](self).
+#[derive(Debug)]
+pub struct RtspCamera {
+    device: CameraDevice,
+    format: CaptureFormat,
+    commands: Mutex<Sender<Command>>,
+    frames: Mutex<Receiver<Option<OwnedFrame>>>,
+}
+
+impl RtspCamera {
+    /// Connects over TCP, which gets through NAT and loses no packets, and opens the decoder of
+    /// the first video stream. `NoDevice` if the server has no video.
+    pub fn open(url: &str) -> Result<Self, Error> {
+        let (setup_tx, setup_rx) = channel();
+        let (command_tx, command_rx) = channel();
+        let (frame_tx, frame_rx) = sync_channel(1);
+        let url = url.to_string();
+        std::thread::spawn(move || run(&url, setup_tx, command_rx, frame_tx));
+        let (device, format) = setup_rx.recv().map_err(|_| thread_died())??;
+        Ok(Self { device, format, commands: Mutex::new(command_tx), frames: Mutex::new(frame_rx) })
+    }
+
+    fn send(&self, command: Command) {
+        let _ = lock(&self.commands).send(command);
+    }
+}
+
+impl Drop for RtspCamera {
+    fn drop(&mut self) {
+        self.send(Command::Quit);
+    }
+}
+
+impl FrameSource for RtspCamera {
+    fn start(&self) {
+        self.send(Command::Start);
+    }
+
+    /// Pauses the stream on the server, a waiting reader gets `None`.
+    fn stop(&self) {
+        self.send(Command::Stop);
+    }
+
+    /// `None` after 5 seconds without a frame or once the connection is lost.
+    fn wait_for_frame(&self) -> Option<OwnedFrame> {
+        lock(&self.frames).recv_timeout(Duration::from_secs(5)).ok().flatten()
+    }
+
+    fn try_next_frame(&self) -> Option<OwnedFrame> {
+        lock(&self.frames).try_recv().ok().flatten()
+    }
+
+    fn device(&self) -> CameraDevice {
+        self.device.clone()
+    }
+
+    fn current_format(&self) -> Option<CaptureFormat> {
+        Some(self.format.clone())
+    }
+
+    /// Gets [`CameraEvent::DeviceLost`] when the connection fails.
+    fn connect_events(&self, events: Sender<CameraEvent>) {
+        self.send(Command::Events(events));
+    }
+}
+
+type Setup = Result<(CameraDevice, CaptureFormat), Error>;
+
+fn thread_died() -> Error {
+    Error::Other("RTSP thread died".into())
+}
+
+struct Stream {
+    input: Input,
+    index: usize,
+    decoder: codec::decoder::Video,
+}
+
+fn open(url: &str) -> Result<(Stream, CaptureFormat), Error> {
+    ffmpeg_next::init().map_err(Error::os)?;
+    let mut options = Dictionary::new();
+    options.set("rtsp_transport", "tcp");
+    // in microseconds, without it a dead camera blocks the read forever
+    options.set("timeout", "5000000");
+    let input = ffmpeg_next::format::input_with_dictionary(&url, options).map_err(Error::os)?;
+    let stream = input.streams().best(media::Type::Video).ok_or(Error::NoDevice)?;
+    let index = stream.index();
+    let rate = stream.avg_frame_rate();
+    let fps = if rate.denominator() > 0 { f64::from(rate) } else { 0.0 };
+    let context = codec::Context::from_parameters(stream.parameters()).map_err(Error::os)?;
+    let decoder = context.decoder().video().map_err(Error::os)?;
+    let format = CaptureFormat {
+        pixel_format: decoder.id().name().to_uppercase(),
+        width: decoder.width(),
+        height: decoder.height(),
+        min_fps: fps,
+        max_fps: fps,
+    };
+    Ok((Stream { input, index, decoder }, format))
+}
+
+/// Reads and decodes until [`Command::Quit`] or the connection fails, which is reported as
+/// [`CameraEvent::DeviceLost`].
+fn run(
+    url: &str,
+    setup_tx: Sender<Setup>,
+    commands: Receiver<Command>,
+    frame_tx: SyncSender<Option<OwnedFrame>>,
+) {
+    let (mut stream, format) = match open(url) {
+        Ok(opened) => opened,
+        Err(err) => {
+            let _ = setup_tx.send(Err(err));
+            return;
+        }
+    };
+    let url = without_credentials(url);
+    let device = CameraDevice::new(&url, &url, DeviceKind::Physical);
+    let _ = setup_tx.send(Ok((device, format)));
+
+    let mut converter: Option<(software::scaling::Context, (Pixel, u32, u32))> = None;
+    let mut events = None;
+    let mut playing = false;
+    loop {
+        let command = match playing {
+            true => match commands.try_recv() {
+                Err(TryRecvError::Empty) => None,
+                received => Some(received.unwrap_or(Command::Quit)),
+            },
+            false => Some(commands.recv().unwrap_or(Command::Quit)),
+        };
+        match command {
+            Some(Command::Start) if !playing => playing = stream.input.play().is_ok(),
+            Some(Command::Stop) => {
+                if playing {
+                    let _ = stream.input.pause();
+                    playing = false;
+                }
+                let _ = frame_tx.try_send(None);
+            }
+            Some(Command::Events(sender)) => events = Some(sender),
+            Some(Command::Quit) => return,
+            _ => {}
+        }
+        if !playing {
+            continue;
+        }
+
+        let mut packet = Packet::empty();
+        match packet.read(&mut stream.input) {
+            Ok(()) if packet.stream() == stream.index => {}
+            Ok(()) => continue,
+            Err(ffmpeg_next::Error::Other { errno: ffmpeg_next::error::EAGAIN }) => continue,
+            Err(_) => break,
+        }
+        if stream.decoder.send_packet(&packet).is_err() {
+            // a corrupt packet, the next key frame recovers
+            continue;
+        }
+        let mut decoded = Video::empty();
+        while stream.decoder.receive_frame(&mut decoded).is_ok() {
+            // the decoder may change size or format in the middle of a stream
+            let key = (decoded.format(), decoded.width(), decoded.height());
+            if !converter.as_ref().is_some_and(|(_, current)| *current == key) {
+                let (format, width, height) = key;
+                converter = software::converter((width, height), format, Pixel::BGRA)
+                    .ok()
+                    .map(|scaler| (scaler, key));
+            }
+            let Some((scaler, _)) = converter.as_mut() else { continue };
+            let mut bgra = Video::empty();
+            if scaler.run(&decoded, &mut bgra).is_err() {
+                continue;
+            }
+            if let Ok(frame) = OwnedFrame::try_from(&bgra) {
+                let _ = frame_tx.try_send(Some(frame));
+            }
+        }
+    }
+    // wakes up a waiting reader, later ones time out
+    let _ = frame_tx.try_send(None);
+    if let Some(events) = events {
+        let _ = events.send(CameraEvent::DeviceLost);
+    }
+}
+
+/// The URL without user and password, to show and log.
+fn without_credentials(url: &str) -> String {
+    let Some((scheme, rest)) = url.split_once("://") else { return url.to_string() };
+    let authority = &rest[..rest.find('/').unwrap_or(rest.len())];
+    match authority.rfind('@') {
+        Some(at) => format!("{scheme}://{}", &rest[at + 1..]),
+        None => url.to_string(),
+    }
+}
+
+#[test]
+fn url_without_credentials() {
+    let url = without_credentials("rtsp://admin:p@ss@10.0.0.5:554/h264");
+    assert_eq!(url, "rtsp://10.0.0.5:554/h264");
+    assert_eq!(without_credentials("rtsp://cam.local/a@b"), "rtsp://cam.local/a@b");
+}
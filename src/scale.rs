@@ -0,0 +1,287 @@
+//! Resizes BGRA frames, e.g. thumbnails or a smaller copy of each frame for a network stream.
+//!
+//! The filters run separately over rows and columns with 16 bit fixed point weights. The rows
+//! pass sums two source pixels per multiply-add instruction with SSE2 on x86_64 and NEON on
+//! aarch64, the columns pass is a flat loop over whole rows, `cargo bench --bench scale`
+//! measures both. When shrinking, [`Filter::Bilinear`] and [`Filter::Lanczos`] widen with the
+//! scale and average all source pixels, so fine detail doesn't alias.
+//!
+//! ```no_run
+//! use kamera::{scale, Camera, Filter};
+//!
+//! let camera = Camera::new_default_device();
+//! camera.start();
+//! let frame = camera.wait_for_frame().unwrap();
+//! let thumbnail = scale::thumbnail(&frame.data(), 160, 160, Filter::Lanczos);
+//! let half = scale::resize(&thumbnail, 80, 60, Filter::Bilinear);
+//! ```
+
+use crate::{Filter, OwnedFrame, PlaneView};
+
+/// Fractional bits of the weights. A weight stays below 2.0, so it fits into an `i16`.
+const SHIFT: u32 = 14;
+const ONE: i32 = 1 << SHIFT;
+
+/// The source pixels of one destination pixel, from `start` on.
+#[derive(Debug, PartialEq)]
+struct Taps {
+    start: usize,
+    weights: Vec<i16>,
+}
+
+fn kernel(filter: Filter, x: f32) -> f32 {
+    let sinc = |x: f32| match x {
+        0.0 => 1.0,
+        x => (std::f32::consts::PI * x).sin() / (std::f32::consts::PI * x),
+    };
+    match filter {
+        Filter::Nearest => 1.0,
+        Filter::Bilinear => (1.0 - x.abs()).max(0.0),
+        Filter::Lanczos => match x.abs() < 3.0 {
+            true => sinc(x) * sinc(x / 3.0),
+            false => 0.0,
+        },
+    }
+}
+
+fn radius(filter: Filter) -> f32 {
+    match filter {
+        Filter::Nearest => 0.5,
+        Filter::Bilinear => 1.0,
+        Filter::Lanczos => 3.0,
+    }
+}
+
+/// Taps for each of `dst_len` pixels which show `src_len` pixels.
+fn taps(src_len: u32, dst_len: u32, filter: Filter) -> Vec<Taps> {
+    let scale = src_len as f32 / dst_len as f32;
+    // shrinking stretches the kernel over all source pixels of a destination pixel
+    let stretch = scale.max(1.0);
+    let support = radius(filter) * stretch;
+    (0..dst_len)
+        .map(|dst| {
+            let center = (dst as f32 + 0.5) * scale;
+            if filter == Filter::Nearest {
+                let index = (center as usize).min(src_len as usize - 1);
+                return Taps { start: index, weights: vec![ONE as i16] };
+            }
+            let start = (center - support).floor().max(0.0) as usize;
+            let end = ((center + support).ceil() as usize).min(src_len as usize);
+            let weights: Vec<f32> =
+                (start..end).map(|i| kernel(filter, (i as f32 + 0.5 - center) / stretch)).collect();
+            let sum: f32 = weights.iter().sum();
+            let mut weights: Vec<i16> =
+                weights.iter().map(|w| (w / sum * ONE as f32).round() as i16).collect();
+            // the rounding error goes to the largest weight, so flat areas stay flat
+            let error = (ONE - weights.iter().map(|&w| w as i32).sum::<i32>()) as i16;
+            if let Some(largest) = weights.iter_mut().max() {
+                *largest += error;
+            }
+            Taps { start, weights }
+        })
+        .collect()
+}
+
+fn to_u8(acc: i32) -> u8 {
+    ((acc + (ONE >> 1)) >> SHIFT).clamp(0, 255) as u8
+}
+
+/// The weighted sums of each channel of the packed BGRA `pixels`, one pixel per weight.
+fn weigh(pixels: &[u8], weights: &[i16]) -> [i32; 4] {
+    let pixels = &pixels[..weights.len() * 4];
+    // SAFETY: SSE2 is part of x86_64 and NEON of aarch64, the loads stay within `pixels`
+    #[cfg(target_arch = "x86_64")]
+    let sums = unsafe { weigh_sse2(pixels, weights) };
+    #[cfg(target_arch = "aarch64")]
+    let sums = unsafe { weigh_neon(pixels, weights) };
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    let sums = weigh_scalar(pixels, weights);
+    sums
+}
+
+#[cfg(any(test, not(any(target_arch = "x86_64", target_arch = "aarch64"))))]
+fn weigh_scalar(pixels: &[u8], weights: &[i16]) -> [i32; 4] {
+    let mut acc = [0; 4];
+    for (px, &weight) in pixels.chunks_exact(4).zip(weights) {
+        for c in 0..4 {
+            acc[c] += px[c] as i32 * weight as i32;
+        }
+    }
+    acc
+}
+
+/// Pairs of pixels, their channels interleaved to `b0 b1 g0 g1 r0 r1 a0 a1` so `pmaddwd`
+/// multiplies and adds both at once.
+#[cfg(target_arch = "x86_64")]
+unsafe fn weigh_sse2(pixels: &[u8], weights: &[i16]) -> [i32; 4] {
+    use std::arch::x86_64::*;
+
+    let zero = _mm_setzero_si128();
+    let mut acc = zero;
+    let mut weigh_pair = |pair: __m128i, w0: i16, w1: i16| {
+        let wide = _mm_unpacklo_epi8(pair, zero);
+        let interleaved = _mm_unpacklo_epi16(wide, _mm_srli_si128::<8>(wide));
+        let weights = _mm_set1_epi32((w0 as u16 as i32) | ((w1 as i32) << 16));
+        acc = _mm_add_epi32(acc, _mm_madd_epi16(interleaved, weights));
+    };
+    let pairs = weights.chunks_exact(2);
+    let last = pairs.remainder().first();
+    for (pair, w) in pixels.chunks_exact(8).zip(pairs) {
+        weigh_pair(_mm_loadl_epi64(pair.as_ptr().cast()), w[0], w[1]);
+    }
+    if let Some(&w) = last {
+        let px = pixels[pixels.len() - 4..].try_into().unwrap();
+        weigh_pair(_mm_cvtsi32_si128(i32::from_ne_bytes(px)), w, 0);
+    }
+    let mut sums = [0; 4];
+    _mm_storeu_si128(sums.as_mut_ptr().cast(), acc);
+    sums
+}
+
+/// Pairs of pixels widened to 16 bits, each half multiplied by its weight and accumulated.
+#[cfg(target_arch = "aarch64")]
+unsafe fn weigh_neon(pixels: &[u8], weights: &[i16]) -> [i32; 4] {
+    use std::arch::aarch64::*;
+
+    let mut acc = vdupq_n_s32(0);
+    let mut weigh_pair = |pair: *const u8, w0: i16, w1: i16| {
+        let wide = vreinterpretq_s16_u16(vmovl_u8(vld1_u8(pair)));
+        acc = vmlal_n_s16(acc, vget_low_s16(wide), w0);
+        acc = vmlal_n_s16(acc, vget_high_s16(wide), w1);
+    };
+    let pairs = weights.chunks_exact(2);
+    let last = pairs.remainder().first();
+    for (pair, w) in pixels.chunks_exact(8).zip(pairs) {
+        weigh_pair(pair.as_ptr(), w[0], w[1]);
+    }
+    if let Some(&w) = last {
+        let mut pair = [0; 8];
+        pair[..4].copy_from_slice(&pixels[pixels.len() - 4..]);
+        weigh_pair(pair.as_ptr(), w, 0);
+    }
+    let mut sums = [0; 4];
+    vst1q_s32(sums.as_mut_ptr(), acc);
+    sums
+}
+
+/// Scales `src` to a new `width` x `height` frame, without keeping the aspect ratio.
+pub fn resize<'a>(
+    src: impl Into<PlaneView<'a>>,
+    width: u32,
+    height: u32,
+    filter: Filter,
+) -> OwnedFrame {
+    let src = src.into();
+    let bgra = resize_bgra(src.data, (src.width, src.height), src.stride, (width, height), filter);
+    OwnedFrame::new(bgra, width, height)
+}
+
+/// Scales `src` down to fit into `max_width` x `max_height` and keeps the aspect ratio. Smaller
+/// frames keep their size.
+pub fn thumbnail<'a>(
+    src: impl Into<PlaneView<'a>>,
+    max_width: u32,
+    max_height: u32,
+    filter: Filter,
+) -> OwnedFrame {
+    let src = src.into();
+    let (w, h) = (src.width as u64, src.height as u64);
+    let (mut width, mut height) = (w, h);
+    if w > max_width as u64 || h > max_height as u64 {
+        // the smaller of both scales, rounded down
+        if w * max_height as u64 > h * max_width as u64 {
+            (width, height) = (max_width as u64, (h * max_width as u64 / w).max(1));
+        } else {
+            (width, height) = ((w * max_height as u64 / h).max(1), max_height as u64);
+        }
+    }
+    resize(src, width as u32, height as u32, filter)
+}
+
+/// Packed BGRA of `dst_size` from rows of `stride` bytes.
+pub(crate) fn resize_bgra(
+    src: &[u8],
+    (w, h): (u32, u32),
+    stride: usize,
+    (dst_w, dst_h): (u32, u32),
+    filter: Filter,
+) -> Vec<u8> {
+    let dst_len = dst_w as usize * dst_h as usize * 4;
+    if w == 0 || h == 0 || dst_len == 0 {
+        return vec![0; dst_len];
+    }
+    // rows first, so the columns pass runs over fewer rows when shrinking
+    let columns = taps(w, dst_w, filter);
+    let row_len = dst_w as usize * 4;
+    let mut narrow = vec![0; row_len * h as usize];
+    for (src_row, dst_row) in src.chunks(stride).zip(narrow.chunks_exact_mut(row_len)) {
+        for (dst_px, taps) in dst_row.chunks_exact_mut(4).zip(&columns) {
+            let acc = weigh(&src_row[taps.start * 4..], &taps.weights);
+            for c in 0..4 {
+                dst_px[c] = to_u8(acc[c]);
+            }
+        }
+    }
+
+    let rows = taps(h, dst_h, filter);
+    let mut dst = vec![0; dst_len];
+    let mut acc = vec![0; row_len];
+    for (dst_row, taps) in dst.chunks_exact_mut(row_len).zip(&rows) {
+        acc.fill(0);
+        let src_rows = narrow[taps.start * row_len..].chunks_exact(row_len);
+        for (src_row, &weight) in src_rows.zip(&taps.weights) {
+            for (a, &s) in acc.iter_mut().zip(src_row) {
+                *a += s as i32 * weight as i32;
+            }
+        }
+        for (d, &a) in dst_row.iter_mut().zip(&acc) {
+            *d = to_u8(a);
+        }
+    }
+    dst
+}
+
+#[test]
+fn scale_taps() {
+    for filter in [Filter::Nearest, Filter::Bilinear, Filter::Lanczos] {
+        for (src, dst) in [(10, 3), (3, 10), (7, 7)] {
+            for taps in taps(src, dst, filter) {
+                let sum = taps.weights.iter().map(|&w| w as i32).sum::<i32>();
+                assert_eq!(sum, ONE, "{filter:?} {src} {dst}");
+                assert!(taps.start + taps.weights.len() <= src as usize);
+            }
+        }
+    }
+    // the same size is a copy
+    let same = taps(4, 4, Filter::Lanczos);
+    assert!(same.iter().enumerate().all(|(i, t)| t.weights[i - t.start] as i32 == ONE));
+}
+
+#[test]
+fn weigh_simd() {
+    let pixels: Vec<u8> = (0..7 * 4).map(|i| (i * 37 % 256) as u8).collect();
+    for len in 1..=7 {
+        let weights: Vec<i16> = (0..len).map(|i| [9000, -1500, 300, 16384][i % 4]).collect();
+        let pixels = &pixels[..len * 4];
+        assert_eq!(weigh(pixels, &weights), weigh_scalar(pixels, &weights), "{len}");
+    }
+}
+
+#[test]
+fn resize_frames() {
+    let (black, white) = ([0, 0, 0, 255], [255; 4]);
+    // a flat frame stays flat with every filter
+    let gray = OwnedFrame::new([128, 128, 128, 255].repeat(6 * 4), 6, 4);
+    for filter in [Filter::Nearest, Filter::Bilinear, Filter::Lanczos] {
+        let frame = resize(&gray, 4, 7, filter);
+        assert_eq!(frame.data(), [128, 128, 128, 255].repeat(4 * 7));
+    }
+    // a black and a white column average to gray when halved
+    let stripes = OwnedFrame::new([black, white].concat().repeat(2), 2, 2);
+    assert_eq!(resize(&stripes, 1, 1, Filter::Bilinear).data(), [128, 128, 128, 255]);
+    assert_eq!(resize(&stripes, 1, 1, Filter::Nearest).data(), white);
+
+    let wide = OwnedFrame::new(vec![0; 400 * 100 * 4], 400, 100);
+    assert_eq!(thumbnail(&wide, 160, 160, Filter::Lanczos).size_u32(), (160, 40));
+    assert_eq!(thumbnail(&wide, 800, 800, Filter::Lanczos).size_u32(), (400, 100));
+}
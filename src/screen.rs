@@ -0,0 +1,95 @@
+//! Capture the screen through the [`Camera`](crate::Camera) API, enabled with the `screen` feature.
+//!
+//! Uses ScreenCaptureKit on macOS 12.3 and newer, Windows.Graphics.Capture on Windows 10 1903 and
+//! newer and the ScreenCast portal with PipeWire on Linux. macOS asks for the screen recording
+//! permission and Linux shows a dialog to pick a monitor on the first [`ScreenCamera::new`].
+//! Frames are BGRA in the native resolution of the main monitor. An unchanged screen delivers no
+//! new frames, so [`Camera::wait_for_frame`](crate::Camera::wait_for_frame) waits until
+//! something moves.
+//!
+//! ```no_run
+//! let camera = kamera::Camera::from_screen().unwrap();
+//! camera.start();
+//! let frame = camera.wait_for_frame().unwrap();
+//! ```
+
+use std::sync::mpsc::Sender;
+
+use crate::{CameraDevice, CameraEvent, CaptureFormat, Error, FrameSource, OwnedFrame};
+
+#[cfg(target_os = "macos")]
+type Inner = crate::mac_screen::MacScreen;
+#[cfg(target_os = "windows")]
+type Inner = crate::win_screen::WinScreen;
+#[cfg(target_os = "linux")]
+type Inner = crate::linux_screen::LinuxScreen;
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+type Inner = std::convert::Infallible;
+
+/// [`FrameSource`] of the main monitor.
+#[derive(Debug)]
+pub struct ScreenCamera {
+    inner: Inner,
+}
+
+impl ScreenCamera {
+    /// `Err(Error::Unsupported)` on other platforms or older OS versions.
+    #[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+    pub fn new() -> Result<Self, Error> {
+        Ok(Self { inner: Inner::open()? })
+    }
+
+    /// `Err(Error::Unsupported)` on other platforms or older OS versions.
+    #[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+    pub fn new() -> Result<Self, Error> {
+        Err(Error::Unsupported)
+    }
+
+    #[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+    fn inner(&self) -> &dyn FrameSource {
+        &self.inner
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+    fn inner(&self) -> &dyn FrameSource {
+        match self.inner {}
+    }
+}
+
+impl FrameSource for ScreenCamera {
+    fn start(&self) {
+        self.inner().start()
+    }
+
+    fn stop(&self) {
+        self.inner().stop()
+    }
+
+    fn wait_for_frame(&self) -> Option<OwnedFrame> {
+        self.inner().wait_for_frame()
+    }
+
+    fn try_next_frame(&self) -> Option<OwnedFrame> {
+        self.inner().try_next_frame()
+    }
+
+    fn latest_frame(&self) -> Option<OwnedFrame> {
+        self.inner().latest_frame()
+    }
+
+    fn device(&self) -> CameraDevice {
+        self.inner().device()
+    }
+
+    fn current_format(&self) -> Option<CaptureFormat> {
+        self.inner().current_format()
+    }
+
+    fn frame_ready_fd(&self) -> Option<crate::FrameReadyFd> {
+        self.inner().frame_ready_fd()
+    }
+
+    fn connect_events(&self, events: Sender<CameraEvent>) {
+        self.inner().connect_events(events)
+    }
+}
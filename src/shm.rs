@@ -0,0 +1,431 @@
+//! Frames in shared memory, for pipelines of several processes, e.g. capture in one and
+//! inference in another. See [`Frame::export_shm`](crate::Frame::export_shm) and
+//! [`OwnedFrame::from_shm`](crate::OwnedFrame::from_shm).
+//!
+//! A segment holds a 32 byte header followed by `height` rows of `stride` bytes. The header
+//! fields are little endian, so other languages can read the segments too:
+//!
+//! | offset | bytes | field |
+//! |--------|-------|-------|
+//! | 0      | 4     | magic `KMRA` |
+//! | 4      | 4     | version, 1 |
+//! | 8      | 4     | width |
+//! | 12     | 4     | height |
+//! | 16     | 4     | stride |
+//! | 20     | 4     | pixel format, `BGRA` |
+//! | 24     | 8     | nanoseconds since the Unix epoch when the frame was exported |
+//!
+//! Names are plain words without slashes. Segments are POSIX shared memory on Linux, in
+//! `/dev/shm`, and macOS, which limits names to 30 bytes, and file mappings in the `Local\`
+//! namespace on Windows. A reader which opens a segment while it is being written sees a torn
+//! frame, use a name per frame or synchronize the processes otherwise. In the browser exporting
+//! and reading fail with `ErrorKind::Unsupported`.
+
+use std::io;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const MAGIC: &[u8; 4] = b"KMRA";
+const VERSION: u32 = 1;
+const HEADER_LEN: usize = 32;
+
+/// The header of a segment, see [`shm`](crate::shm).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShmHeader {
+    pub width: u32,
+    pub height: u32,
+    pub stride: u32,
+    pub pixel_format: [u8; 4],
+    pub timestamp: SystemTime,
+}
+
+impl ShmHeader {
+    fn to_bytes(self) -> [u8; HEADER_LEN] {
+        let nanos = self.timestamp.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+        let mut bytes = [0; HEADER_LEN];
+        bytes[0..4].copy_from_slice(MAGIC);
+        bytes[4..8].copy_from_slice(&VERSION.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.width.to_le_bytes());
+        bytes[12..16].copy_from_slice(&self.height.to_le_bytes());
+        bytes[16..20].copy_from_slice(&self.stride.to_le_bytes());
+        bytes[20..24].copy_from_slice(&self.pixel_format);
+        bytes[24..32].copy_from_slice(&(nanos as u64).to_le_bytes());
+        bytes
+    }
+
+    fn parse(bytes: &[u8]) -> io::Result<Self> {
+        let invalid = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+        let bytes: &[u8; HEADER_LEN] = bytes
+            .get(..HEADER_LEN)
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(|| invalid("segment shorter than the header"))?;
+        let u32_at = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        if &bytes[0..4] != MAGIC || u32_at(4) != VERSION {
+            return Err(invalid("not a kamera frame segment"));
+        }
+        let nanos = u64::from_le_bytes(bytes[24..32].try_into().unwrap());
+        Ok(Self {
+            width: u32_at(8),
+            height: u32_at(12),
+            stride: u32_at(16),
+            pixel_format: bytes[20..24].try_into().unwrap(),
+            timestamp: UNIX_EPOCH + Duration::from_nanos(nanos),
+        })
+    }
+
+    fn data_len(&self) -> usize {
+        self.stride as usize * self.height as usize
+    }
+}
+
+/// A frame exported by [`Frame::export_shm`](crate::Frame::export_shm). The segment name is
+/// removed when this is dropped, readers which opened it before keep their copy.
+#[derive(Debug)]
+pub struct ShmFrame {
+    name: String,
+    header: ShmHeader,
+    segment: Segment,
+}
+
+impl ShmFrame {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn header(&self) -> ShmHeader {
+        self.header
+    }
+}
+
+impl Drop for ShmFrame {
+    fn drop(&mut self) {
+        self.segment.unlink();
+    }
+}
+
+/// Writes BGRA rows without padding to the segment `name`, replacing an older one.
+pub(crate) fn export(name: &str, bgra: &[u8], (width, height): (u32, u32)) -> io::Result<ShmFrame> {
+    let header = ShmHeader {
+        width,
+        height,
+        stride: width * 4,
+        pixel_format: *b"BGRA",
+        timestamp: crate::time::system_time_now(),
+    };
+    let len = header.data_len();
+    let mut segment = Segment::create(name, HEADER_LEN + len)?;
+    let bytes = segment.bytes_mut();
+    bytes[..HEADER_LEN].copy_from_slice(&header.to_bytes());
+    bytes[HEADER_LEN..][..len].copy_from_slice(&bgra[..len]);
+    Ok(ShmFrame { name: name.to_string(), header, segment })
+}
+
+/// The header and the pixels of the segment `name`.
+pub(crate) fn read(name: &str) -> io::Result<(ShmHeader, Vec<u8>)> {
+    let segment = Segment::open(name)?;
+    let bytes = segment.bytes();
+    let header = ShmHeader::parse(bytes)?;
+    let data = bytes.get(HEADER_LEN..HEADER_LEN + header.data_len()).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "segment shorter than its frame")
+    })?;
+    if &header.pixel_format != b"BGRA" || (header.stride as usize) < header.width as usize * 4 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "unsupported frame layout"));
+    }
+    Ok((header, data.to_vec()))
+}
+
+fn check_name(name: &str) -> io::Result<()> {
+    if name.is_empty() || name.contains(['/', '\\', '\0']) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid segment name"));
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+use posix::Segment;
+#[cfg(not(any(unix, windows)))]
+use unsupported::Segment;
+#[cfg(windows)]
+use win32::Segment;
+
+#[cfg(unix)]
+mod posix {
+    use std::ffi::c_void;
+    use std::fs::File;
+    use std::io;
+    use std::os::fd::AsRawFd;
+
+    const PROT_READ: i32 = 1;
+    const PROT_WRITE: i32 = 2;
+    const MAP_SHARED: i32 = 1;
+
+    extern "C" {
+        fn mmap(
+            addr: *mut c_void,
+            len: usize,
+            prot: i32,
+            flags: i32,
+            fd: i32,
+            offset: i64,
+        ) -> *mut c_void;
+        fn munmap(addr: *mut c_void, len: usize) -> i32;
+    }
+
+    /// A mapped shared memory object.
+    #[derive(Debug)]
+    pub(crate) struct Segment {
+        name: String,
+        ptr: *mut u8,
+        len: usize,
+    }
+
+    // The mapping is owned by the segment like a Vec owns its buffer.
+    unsafe impl Send for Segment {}
+    unsafe impl Sync for Segment {}
+
+    impl Segment {
+        pub(crate) fn create(name: &str, len: usize) -> io::Result<Self> {
+            super::check_name(name)?;
+            let file = shm_open(name, true)?;
+            file.set_len(len as u64)?;
+            Self::map(name, &file, len, PROT_READ | PROT_WRITE)
+        }
+
+        pub(crate) fn open(name: &str) -> io::Result<Self> {
+            super::check_name(name)?;
+            let file = shm_open(name, false)?;
+            let len = file.metadata()?.len() as usize;
+            Self::map(name, &file, len, PROT_READ)
+        }
+
+        fn map(name: &str, file: &File, len: usize, prot: i32) -> io::Result<Self> {
+            // mmap refuses empty mappings
+            let map_len = len.max(1);
+            let ptr = unsafe {
+                mmap(std::ptr::null_mut(), map_len, prot, MAP_SHARED, file.as_raw_fd(), 0)
+            };
+            if ptr as isize == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Self { name: name.to_string(), ptr: ptr.cast(), len })
+        }
+
+        pub(crate) fn bytes(&self) -> &[u8] {
+            unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+        }
+
+        pub(crate) fn bytes_mut(&mut self) -> &mut [u8] {
+            unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
+        }
+
+        pub(crate) fn unlink(&self) {
+            shm_unlink(&self.name);
+        }
+    }
+
+    impl Drop for Segment {
+        fn drop(&mut self) {
+            unsafe { munmap(self.ptr.cast(), self.len.max(1)) };
+        }
+    }
+
+    /// glibc implements shm_open as a file in /dev/shm, this way it doesn't need librt.
+    ///
+    /// A created object is a new one, readers which still map an older one of the same name
+    /// keep it, that is also the only way on macOS which can't resize an object.
+    #[cfg(not(target_vendor = "apple"))]
+    fn shm_open(name: &str, create: bool) -> io::Result<File> {
+        use std::os::unix::fs::OpenOptionsExt;
+        if create {
+            shm_unlink(name);
+        }
+        let mut options = std::fs::OpenOptions::new();
+        options.read(true).write(create).create_new(create).mode(0o600);
+        options.open(format!("/dev/shm/{name}"))
+    }
+
+    #[cfg(not(target_vendor = "apple"))]
+    fn shm_unlink(name: &str) {
+        let _ = std::fs::remove_file(format!("/dev/shm/{name}"));
+    }
+
+    #[cfg(target_vendor = "apple")]
+    mod apple {
+        use std::ffi::c_char;
+
+        pub(super) const O_RDONLY: i32 = 0;
+        pub(super) const O_RDWR: i32 = 2;
+        pub(super) const O_CREAT: i32 = 0x200;
+        pub(super) const O_EXCL: i32 = 0x800;
+
+        extern "C" {
+            pub(super) fn shm_open(name: *const c_char, oflag: i32, ...) -> i32;
+            pub(super) fn shm_unlink(name: *const c_char) -> i32;
+        }
+    }
+
+    #[cfg(target_vendor = "apple")]
+    fn shm_open(name: &str, create: bool) -> io::Result<File> {
+        use std::os::fd::FromRawFd;
+        let path = std::ffi::CString::new(format!("/{name}"))?;
+        let fd = unsafe {
+            if create {
+                apple::shm_unlink(path.as_ptr());
+                let flags = apple::O_RDWR | apple::O_CREAT | apple::O_EXCL;
+                apple::shm_open(path.as_ptr(), flags, 0o600 as u32)
+            } else {
+                apple::shm_open(path.as_ptr(), apple::O_RDONLY)
+            }
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(unsafe { File::from_raw_fd(fd) })
+    }
+
+    #[cfg(target_vendor = "apple")]
+    fn shm_unlink(name: &str) {
+        if let Ok(path) = std::ffi::CString::new(format!("/{name}")) {
+            unsafe { apple::shm_unlink(path.as_ptr()) };
+        }
+    }
+}
+
+#[cfg(windows)]
+mod win32 {
+    use std::ffi::c_void;
+    use std::io;
+
+    use windows::core::HSTRING;
+    use windows::Win32::Foundation::{CloseHandle, HANDLE, INVALID_HANDLE_VALUE};
+    use windows::Win32::System::Memory::*;
+
+    /// A view of a named file mapping backed by the page file.
+    #[derive(Debug)]
+    pub(crate) struct Segment {
+        handle: HANDLE,
+        ptr: *mut u8,
+        len: usize,
+    }
+
+    // The view is owned by the segment like a Vec owns its buffer.
+    unsafe impl Send for Segment {}
+    unsafe impl Sync for Segment {}
+
+    fn mapping_name(name: &str) -> io::Result<HSTRING> {
+        super::check_name(name)?;
+        Ok(HSTRING::from(format!("Local\\{name}")))
+    }
+
+    impl Segment {
+        /// An existing mapping of that name is reused if it is large enough.
+        pub(crate) fn create(name: &str, len: usize) -> io::Result<Self> {
+            let name = mapping_name(name)?;
+            let (high, low) = ((len as u64 >> 32) as u32, len as u32);
+            let handle = unsafe {
+                CreateFileMappingW(INVALID_HANDLE_VALUE, None, PAGE_READWRITE, high, low, &name)?
+            };
+            Self::map(handle, FILE_MAP_ALL_ACCESS, len)
+        }
+
+        pub(crate) fn open(name: &str) -> io::Result<Self> {
+            let name = mapping_name(name)?;
+            let handle = unsafe { OpenFileMappingW(FILE_MAP_READ.0, false, &name)? };
+            Self::map(handle, FILE_MAP_READ, 0)
+        }
+
+        /// `len` zero maps the whole mapping, its size is then rounded up to pages.
+        fn map(handle: HANDLE, access: FILE_MAP, len: usize) -> io::Result<Self> {
+            let ptr = unsafe { MapViewOfFile(handle, access, 0, 0, len) };
+            if ptr.is_null() {
+                let err = io::Error::last_os_error();
+                unsafe { CloseHandle(handle) };
+                return Err(err);
+            }
+            let len = if len > 0 {
+                len
+            } else {
+                let mut info = MEMORY_BASIC_INFORMATION::default();
+                let size = std::mem::size_of_val(&info);
+                unsafe { VirtualQuery(Some(ptr as *const c_void), &mut info, size) };
+                info.RegionSize
+            };
+            Ok(Self { handle, ptr: ptr.cast(), len })
+        }
+
+        pub(crate) fn bytes(&self) -> &[u8] {
+            unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+        }
+
+        pub(crate) fn bytes_mut(&mut self) -> &mut [u8] {
+            unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
+        }
+
+        /// The mapping is gone once the last handle to it is closed.
+        pub(crate) fn unlink(&self) {}
+    }
+
+    impl Drop for Segment {
+        fn drop(&mut self) {
+            unsafe {
+                UnmapViewOfFile(self.ptr as *const c_void);
+                CloseHandle(self.handle);
+            }
+        }
+    }
+}
+
+/// The browser has no memory shared between processes.
+#[cfg(not(any(unix, windows)))]
+mod unsupported {
+    use std::io;
+
+    #[derive(Debug)]
+    pub(crate) struct Segment;
+
+    impl Segment {
+        pub(crate) fn create(_name: &str, _len: usize) -> io::Result<Self> {
+            Err(io::ErrorKind::Unsupported.into())
+        }
+
+        pub(crate) fn open(_name: &str) -> io::Result<Self> {
+            Err(io::ErrorKind::Unsupported.into())
+        }
+
+        pub(crate) fn bytes(&self) -> &[u8] {
+            &[]
+        }
+
+        pub(crate) fn bytes_mut(&mut self) -> &mut [u8] {
+            &mut []
+        }
+
+        pub(crate) fn unlink(&self) {}
+    }
+}
+
+#[test]
+fn export_and_read() {
+    let bgra: Vec<u8> = (0..2 * 3 * 4).collect();
+    let name = format!("kamera-test-{}", std::process::id());
+    let exported = export(&name, &bgra, (2, 3)).unwrap();
+    assert_eq!(exported.header().stride, 8);
+    let frame = crate::OwnedFrame::from_shm(&name).unwrap();
+    assert_eq!((frame.size_u32(), frame.data()), ((2, 3), &bgra[..]));
+    drop(exported);
+    assert!(crate::OwnedFrame::from_shm(&name).is_err());
+    assert!(export("a/b", &bgra, (2, 3)).is_err());
+}
+
+#[test]
+fn header_roundtrip() {
+    let header = ShmHeader {
+        width: 640,
+        height: 480,
+        stride: 2560,
+        pixel_format: *b"BGRA",
+        timestamp: UNIX_EPOCH + Duration::from_nanos(1_700_000_000_123_456_789),
+    };
+    assert_eq!(ShmHeader::parse(&header.to_bytes()).unwrap(), header);
+    assert!(ShmHeader::parse(&[0; HEADER_LEN]).is_err());
+    assert!(ShmHeader::parse(&header.to_bytes()[..16]).is_err());
+}
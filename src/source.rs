@@ -0,0 +1,145 @@
+use std::sync::mpsc::Sender;
+
+use crate::{CameraDevice, CameraEvent, CaptureFormat, ColorSpace, DeviceKind, FrameReadyFd};
+
+/// Custom source of frames behind the [`Camera`](crate::Camera) API, for network cameras,
+/// video files or generated test patterns. See [`Camera::from_source`](crate::Camera::from_source).
+///
+/// Only `wait_for_frame` is required. It should return soon after `stop`, which
+/// [`Camera::run`](crate::Camera::run) relies on to end.
+pub trait FrameSource: Send + Sync {
+    fn start(&self) {}
+
+    fn stop(&self) {}
+
+    /// Blocks until the next frame is available.
+    fn wait_for_frame(&self) -> Option<OwnedFrame>;
+
+    /// Returns immediately, the default never has a frame ready.
+    fn try_next_frame(&self) -> Option<OwnedFrame> {
+        None
+    }
+
+    /// Most recent frame, the default is the next one.
+    fn latest_frame(&self) -> Option<OwnedFrame> {
+        self.wait_for_frame()
+    }
+
+    fn device(&self) -> CameraDevice {
+        CameraDevice::new("custom", "Custom frame source", DeviceKind::Virtual)
+    }
+
+    fn current_format(&self) -> Option<CaptureFormat> {
+        None
+    }
+
+    /// See [`Camera::frame_ready_fd`](crate::Camera::frame_ready_fd).
+    fn frame_ready_fd(&self) -> Option<FrameReadyFd> {
+        None
+    }
+
+    /// Called by the [`Camera`](crate::Camera) with the sender of its events, e.g. to report
+    /// [`CameraEvent::DeviceLost`] when the stream ends. A later call replaces the sender.
+    fn connect_events(&self, _events: Sender<CameraEvent>) {}
+}
+
+/// Where a [`Camera`](crate::Camera) gets its frames from, see
+/// [`Camera::with_backend`](crate::Camera::with_backend).
+pub enum Backend {
+    /// The capture API of the OS with its default device.
+    Native,
+    /// Moving color bars at 640x480 and 30 fps, for tests and demos without a camera.
+    Test,
+    Custom(Box<dyn FrameSource>),
+}
+
+/// BGRA frame in memory, delivered by a [`FrameSource`].
+#[derive(Clone)]
+pub struct OwnedFrame {
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+    stride: usize,
+    color_space: ColorSpace,
+}
+
+impl OwnedFrame {
+    /// `bgra` holds `height` rows of `width` pixels without padding.
+    pub fn new(bgra: Vec<u8>, width: u32, height: u32) -> Self {
+        Self::with_stride(bgra, width, height, width as usize * 4)
+    }
+
+    /// Rows start every `stride` bytes. Panics if `bgra` is too short.
+    pub fn with_stride(bgra: Vec<u8>, width: u32, height: u32, stride: usize) -> Self {
+        let row_len = width as usize * 4;
+        assert!(stride >= row_len, "stride {stride} is shorter than a row");
+        let len = stride * (height as usize).saturating_sub(1) + row_len;
+        assert!(bgra.len() >= len, "{} bytes are too short for the frame", bgra.len());
+        Self { data: bgra, width, height, stride, color_space: ColorSpace::default() }
+    }
+
+    /// Reads a frame which another process exported with
+    /// [`Frame::export_shm`](crate::Frame::export_shm), see [`shm`](crate::shm).
+    pub fn from_shm(name: &str) -> std::io::Result<Self> {
+        let (header, data) = crate::shm::read(name)?;
+        Ok(Self::with_stride(data, header.width, header.height, header.stride as usize))
+    }
+
+    pub fn with_color_space(mut self, color_space: ColorSpace) -> Self {
+        self.color_space = color_space;
+        self
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
+    /// The pixels without a copy, to hand one frame to many threads or the clients of a
+    /// streaming server and slice it there. Rows keep their padding, see [`OwnedFrame::stride`].
+    #[cfg(feature = "bytes")]
+    pub fn into_bytes(self) -> bytes::Bytes {
+        self.data.into()
+    }
+
+    pub fn size_u32(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    pub fn color_space(&self) -> ColorSpace {
+        self.color_space
+    }
+}
+
+impl std::fmt::Debug for OwnedFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OwnedFrame")
+            .field("size", &(self.width, self.height))
+            .field("stride", &self.stride)
+            .finish()
+    }
+}
+
+#[test]
+fn owned_frame_checks_size() {
+    let frame = OwnedFrame::with_stride(vec![0; 12 + 8], 2, 2, 12);
+    assert_eq!((frame.size_u32(), frame.stride()), ((2, 2), 12));
+    assert!(std::panic::catch_unwind(|| OwnedFrame::new(vec![0; 15], 2, 2)).is_err());
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn owned_frame_into_bytes() {
+    let frame = OwnedFrame::new((0..16).collect(), 2, 2);
+    let pixels = frame.data().as_ptr();
+    let bytes = frame.into_bytes();
+    assert_eq!(bytes.as_ptr(), pixels);
+    assert_eq!(bytes.slice(8..12), [8, 9, 10, 11][..]);
+}
@@ -0,0 +1,96 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::sync::lock;
+use crate::time::Instant;
+use crate::{CameraDevice, CaptureFormat, DeviceKind, FrameSource, OwnedFrame};
+
+/// White, yellow, cyan, green, magenta, red, blue and black in BGRA.
+const BARS: [[u8; 4]; 8] = [
+    [255, 255, 255, 255],
+    [0, 255, 255, 255],
+    [255, 255, 0, 255],
+    [0, 255, 0, 255],
+    [255, 0, 255, 255],
+    [0, 0, 255, 255],
+    [255, 0, 0, 255],
+    [0, 0, 0, 255],
+];
+
+/// Color bars moving a few pixels per frame, see [`Backend::Test`](crate::Backend::Test).
+#[derive(Debug)]
+pub(crate) struct TestPattern {
+    width: u32,
+    height: u32,
+    fps: f64,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    frame: u64,
+    next_frame: Option<Instant>,
+}
+
+impl TestPattern {
+    pub(crate) fn new(width: u32, height: u32, fps: f64) -> Self {
+        Self { width, height, fps, state: Default::default() }
+    }
+
+    fn render(&self, frame: u64) -> OwnedFrame {
+        let (w, h) = (self.width as u64, self.height as usize);
+        let row: Vec<u8> =
+            (0..w).flat_map(|x| BARS[((x + frame * 4) * 8 / w % 8) as usize]).collect();
+        OwnedFrame::new(row.repeat(h), self.width, self.height)
+    }
+}
+
+impl FrameSource for TestPattern {
+    fn stop(&self) {
+        lock(&self.state).next_frame = None;
+    }
+
+    fn wait_for_frame(&self) -> Option<OwnedFrame> {
+        let mut state = lock(&self.state);
+        let now = Instant::now();
+        let due = state.next_frame.unwrap_or(now);
+        if due > now {
+            std::thread::sleep(due - now);
+        }
+        state.next_frame = Some(due.max(now) + Duration::from_secs_f64(1.0 / self.fps));
+        state.frame += 1;
+        Some(self.render(state.frame - 1))
+    }
+
+    fn try_next_frame(&self) -> Option<OwnedFrame> {
+        let due = lock(&self.state).next_frame;
+        if due.is_some_and(|due| due > Instant::now()) {
+            return None;
+        }
+        self.wait_for_frame()
+    }
+
+    fn device(&self) -> CameraDevice {
+        CameraDevice::new("test", "Test pattern", DeviceKind::Virtual)
+    }
+
+    fn current_format(&self) -> Option<CaptureFormat> {
+        Some(CaptureFormat {
+            pixel_format: "BGRA".to_string(),
+            width: self.width,
+            height: self.height,
+            min_fps: self.fps,
+            max_fps: self.fps,
+        })
+    }
+}
+
+#[test]
+fn test_pattern_moves() {
+    let pattern = TestPattern::new(16, 2, 1000.0);
+    let first = pattern.wait_for_frame().unwrap();
+    assert_eq!(&first.data()[..4], &BARS[0]);
+    assert_eq!(&first.data()[15 * 4..16 * 4], &BARS[7]);
+    let second = pattern.wait_for_frame().unwrap();
+    assert_eq!(&second.data()[..4], &BARS[2]);
+}
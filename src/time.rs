@@ -0,0 +1,20 @@
+//! Clocks which also work in the browser, where those of `std` panic.
+
+use std::time::SystemTime;
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub(crate) use std::time::Instant;
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub(crate) use web_time::Instant;
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub(crate) fn system_time_now() -> SystemTime {
+    SystemTime::now()
+}
+
+/// `Date.now` in the browser, as the `SystemTime` of the public fields.
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub(crate) fn system_time_now() -> SystemTime {
+    use web_time::web::SystemTimeExt;
+    web_time::SystemTime::now().to_std()
+}
@@ -0,0 +1,83 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+/// Counts consecutive empty or black frames, see
+/// [`Camera::enable_frame_validation`](crate::Camera::enable_frame_validation).
+#[derive(Debug, Default)]
+pub(crate) struct FrameValidation {
+    enabled: AtomicBool,
+    invalid: AtomicU32,
+}
+
+impl FrameValidation {
+    /// Invalid frames in a row before the stream is renegotiated.
+    pub(crate) const LIMIT: u32 = 5;
+
+    pub(crate) fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+        self.invalid.store(0, Ordering::Relaxed);
+    }
+
+    /// Number of invalid frames in a row once it reaches [`Self::LIMIT`], counting starts over
+    /// after that.
+    pub(crate) fn check(&self, bgra: &[u8], size: (u32, u32), stride: usize) -> Option<u32> {
+        if !self.enabled.load(Ordering::Relaxed) {
+            return None;
+        }
+        if !is_invalid(bgra, size, stride) {
+            self.invalid.store(0, Ordering::Relaxed);
+            return None;
+        }
+        self.count_invalid(1)
+    }
+
+    /// Counts `frames` the backend dropped before conversion, zero-byte or shorter than their
+    /// format, like [`Self::check`] counts empty or black ones.
+    pub(crate) fn count_invalid(&self, frames: u32) -> Option<u32> {
+        if frames == 0 || !self.enabled.load(Ordering::Relaxed) {
+            return None;
+        }
+        let invalid = self.invalid.fetch_add(frames, Ordering::Relaxed) + frames;
+        if invalid < Self::LIMIT {
+            return None;
+        }
+        self.invalid.store(0, Ordering::Relaxed);
+        Some(invalid)
+    }
+}
+
+/// Empty, or black in every sampled pixel. Sampling a grid of every 16th row and column is
+/// enough for frames that some UVC firmware delivers after resume, which are black throughout.
+fn is_invalid(bgra: &[u8], (w, h): (u32, u32), stride: usize) -> bool {
+    if w == 0 || h == 0 || bgra.len() < stride * (h as usize - 1) + w as usize * 4 {
+        return true;
+    }
+    let row_len = w as usize * 4;
+    bgra.chunks(stride)
+        .take(h as usize)
+        .step_by(16)
+        .flat_map(|row| row[..row_len].chunks_exact(4).step_by(16))
+        .all(|px| px[0] <= 2 && px[1] <= 2 && px[2] <= 2)
+}
+
+#[test]
+fn frame_validation_counts_black_frames() {
+    let validation = FrameValidation::default();
+    let black = [0, 0, 0, 255].repeat(32 * 32);
+    assert_eq!(validation.check(&black, (32, 32), 128), None);
+    validation.set_enabled(true);
+    for _ in 1..FrameValidation::LIMIT {
+        assert_eq!(validation.check(&black, (32, 32), 128), None);
+    }
+    assert_eq!(validation.check(&black, (32, 32), 128), Some(FrameValidation::LIMIT));
+
+    let mut image = black.clone();
+    image[16 * 4..17 * 4].copy_from_slice(&[0, 200, 0, 255]);
+    assert_eq!(validation.check(&black, (32, 32), 128), None);
+    assert_eq!(validation.check(&image, (32, 32), 128), None);
+    assert!(is_invalid(&[], (0, 0), 0));
+
+    assert_eq!(validation.count_invalid(FrameValidation::LIMIT - 1), None);
+    assert_eq!(validation.count_invalid(0), None);
+    assert_eq!(validation.check(&black, (32, 32), 128), Some(FrameValidation::LIMIT));
+    assert!(!is_invalid(&image, (32, 32), 128));
+}
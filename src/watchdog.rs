@@ -0,0 +1,116 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::sync::{lock, wait_timeout_while};
+use crate::time::Instant;
+
+/// When the application last received a frame, see [`Camera::is_alive`](crate::Camera::is_alive).
+#[derive(Debug)]
+pub(crate) struct Liveness {
+    running: AtomicBool,
+    /// Of the last frame, or of the start if there was none since.
+    last_frame: Mutex<Instant>,
+}
+
+impl Default for Liveness {
+    fn default() -> Self {
+        Self { running: false.into(), last_frame: Mutex::new(Instant::now()) }
+    }
+}
+
+impl Liveness {
+    pub(crate) fn set_running(&self, running: bool) {
+        if running && !self.running.load(Ordering::Relaxed) {
+            *lock(&self.last_frame) = Instant::now();
+        }
+        self.running.store(running, Ordering::Relaxed);
+    }
+
+    pub(crate) fn frame(&self) {
+        *lock(&self.last_frame) = Instant::now();
+    }
+
+    /// How long the running camera has gone without a frame, `None` while it doesn't run.
+    fn silence(&self) -> Option<Duration> {
+        let silence = lock(&self.last_frame).elapsed();
+        self.running.load(Ordering::Relaxed).then_some(silence)
+    }
+
+    pub(crate) fn is_alive(&self, within: Duration) -> bool {
+        self.silence().is_some_and(|silence| silence <= within)
+    }
+}
+
+/// A thread which calls back once per stall of the camera, see
+/// [`Camera::set_watchdog`](crate::Camera::set_watchdog).
+#[derive(Debug)]
+pub(crate) struct Watchdog {
+    stop: Arc<(Mutex<bool>, Condvar)>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Watchdog {
+    pub(crate) fn start(
+        liveness: Arc<Liveness>,
+        window: Duration,
+        mut on_stall: impl FnMut() + Send + 'static,
+    ) -> Self {
+        let stop = Arc::new((Mutex::new(false), Condvar::new()));
+        let check_interval = (window / 4).max(Duration::from_millis(10));
+        let thread = {
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                let mut stalled = false;
+                let (stopped, wake) = &*stop;
+                loop {
+                    let stopped =
+                        wait_timeout_while(wake, lock(stopped), check_interval, |stop| !*stop);
+                    if *stopped {
+                        break;
+                    }
+                    // once per stall, it ends with the next frame or a stop
+                    match liveness.silence().is_some_and(|silence| silence > window) {
+                        true if !stalled => {
+                            stalled = true;
+                            on_stall();
+                        }
+                        true => {}
+                        false => stalled = false,
+                    }
+                }
+            })
+        };
+        Self { stop, thread: Some(thread) }
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        let (stopped, wake) = &*self.stop;
+        *lock(stopped) = true;
+        wake.notify_one();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[test]
+fn watchdog_fires_once_per_stall() {
+    let liveness = Arc::new(Liveness::default());
+    assert!(!liveness.is_alive(Duration::from_secs(1)));
+    liveness.set_running(true);
+    assert!(liveness.is_alive(Duration::from_secs(1)));
+    let (tx, rx) = std::sync::mpsc::channel();
+    let watchdog =
+        Watchdog::start(liveness.clone(), Duration::from_millis(30), move || tx.send(()).unwrap());
+    assert!(rx.recv_timeout(Duration::from_secs(1)).is_ok());
+    assert!(!liveness.is_alive(Duration::from_millis(30)));
+    assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+    liveness.frame();
+    assert!(rx.recv_timeout(Duration::from_secs(1)).is_ok());
+    liveness.set_running(false);
+    drop(watchdog);
+}
@@ -0,0 +1,917 @@
+//! Cameras in the browser through `getUserMedia`. A hidden video element plays the stream, each
+//! new picture of it is copied out of a WebCodecs `VideoFrame` and converted to BGRA.
+//!
+//! The page only runs callbacks once its code returns to the event loop, so nothing here
+//! blocks: [`Camera::wait_for_frame`](crate::Camera::wait_for_frame) hands out a queued frame
+//! or `None` right away, call it from `requestAnimationFrame` or a timer instead of a loop. The
+//! JavaScript objects stay on the thread of the page in [`SESSIONS`], a [`Camera`] only shares
+//! the queue of converted frames with them.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use js_sys::{Array, Object, Promise, Reflect, Uint8Array};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{
+    DomException, HtmlVideoElement, MediaDeviceInfo, MediaDeviceKind, MediaDevices, MediaStream,
+    MediaStreamConstraints, MediaStreamTrack, MediaTrackConstraints, PlaneLayout, VideoFrame,
+    VideoMatrixCoefficients, VideoPixelFormat, VideoTransferCharacteristics,
+};
+
+use crate::config::{RequestedConfig, SizeRequest};
+use crate::device_policy::{self, Placement};
+use crate::rate_limit::FrameRateLimit;
+use crate::sync::lock;
+use crate::time::Instant;
+use crate::{
+    convert, BackendCapabilities, CameraBuilder, CameraDevice, CameraEvent, CaptureFormat,
+    CaptureMetadata, ColorRange, ColorSpace, ConfigMismatch, DepthFrame, DeviceCapabilities,
+    DeviceDetails, DeviceKind, DeviceType, Enhancement, EnumError, Error, FaceRect, FourCC,
+    FrameReadyFd, InnerCamera, Latency, MetadataKind, PlaneView, PtzAxis, PtzRange, ThreadPriority,
+    TransferFunction, VideoOrientation, YuvMatrix,
+};
+
+thread_local! {
+    /// The JavaScript objects of each open camera, which can't leave the thread of the page.
+    static SESSIONS: RefCell<HashMap<u32, Session>> = RefCell::new(HashMap::new());
+    /// The cameras of the last `enumerateDevices` with their group ids.
+    static DEVICES: RefCell<Vec<(CameraDevice, String)>> = const { RefCell::new(Vec::new()) };
+    static ON_DEVICES: Closure<dyn FnMut(JsValue)> = Closure::new(store_devices);
+}
+
+static NEXT_ID: AtomicU32 = AtomicU32::new(0);
+
+/// What the callbacks of the page hand over to the [`Camera`].
+#[derive(Debug)]
+struct Shared {
+    frames: Mutex<VecDeque<Frame>>,
+    /// Filled in once the stream runs, with the pixel format of the first frame.
+    format: Mutex<Option<CaptureFormat>>,
+    queue_size: usize,
+    memory_limit: AtomicUsize,
+    rate_limit: FrameRateLimit,
+    events_tx: Sender<CameraEvent>,
+}
+
+impl Shared {
+    /// Drops the oldest frames beyond the queue size or the memory limit.
+    fn push(&self, frame: Frame) {
+        let mut frames = lock(&self.frames);
+        frames.push_back(frame);
+        let limit = self.memory_limit.load(Ordering::Relaxed);
+        while frames.len() > self.queue_size
+            || frames.len() > 1 && frames.iter().map(|f| f.data.len()).sum::<usize>() > limit
+        {
+            frames.pop_front();
+        }
+    }
+}
+
+/// Closures the browser calls, they live as long as the session.
+struct Callbacks {
+    on_stream: Closure<dyn FnMut(JsValue)>,
+    on_refused: Closure<dyn FnMut(JsValue)>,
+    on_ended: Closure<dyn FnMut(JsValue)>,
+    on_animation_frame: Closure<dyn FnMut(JsValue)>,
+    on_copied: Closure<dyn FnMut(JsValue)>,
+    on_copy_failed: Closure<dyn FnMut(JsValue)>,
+}
+
+impl Callbacks {
+    fn new(id: u32) -> Self {
+        Callbacks {
+            on_stream: Closure::new(move |stream| opened(id, stream)),
+            on_refused: Closure::new(move |err| refused(id, err)),
+            on_ended: Closure::new(move |_| ended(id)),
+            on_animation_frame: Closure::new(move |time| animation_frame(id, time)),
+            on_copied: Closure::new(move |layouts| copied(id, layouts)),
+            on_copy_failed: Closure::new(move |_| copy_failed(id)),
+        }
+    }
+}
+
+struct Session {
+    video: HtmlVideoElement,
+    stream: Option<MediaStream>,
+    /// `getUserMedia` hasn't answered yet.
+    opening: bool,
+    animation_frame: Option<i32>,
+    /// The media time of the last copied picture, the video element shows each for several
+    /// animation frames.
+    last_time: f64,
+    /// The frame being copied, its size in bytes and the time of its animation frame. One at a
+    /// time into `buffer`, which is reused while it is large enough.
+    copying: Option<(VideoFrame, u32, f64)>,
+    buffer: Uint8Array,
+    unsupported_sent: bool,
+    /// `None` only while dropped.
+    callbacks: Option<Callbacks>,
+    shared: Arc<Shared>,
+}
+
+impl Session {
+    fn stop(&mut self) {
+        // an answer of `getUserMedia` after this stops its stream right away
+        self.opening = false;
+        if let Some(stream) = self.stream.take() {
+            stop_tracks(&stream);
+        }
+        self.video.set_src_object(None);
+        if let (Some(handle), Some(window)) = (self.animation_frame.take(), web_sys::window()) {
+            let _ = window.cancel_animation_frame(handle);
+        }
+    }
+
+    fn request_animation_frame(&mut self) {
+        let (Some(window), Some(callbacks)) = (web_sys::window(), &self.callbacks) else { return };
+        let callback = callbacks.on_animation_frame.as_ref().unchecked_ref();
+        self.animation_frame = window.request_animation_frame(callback).ok();
+    }
+}
+
+/// Promises which are still pending call their closures later, those are leaked instead of
+/// freed and find no session anymore.
+impl Drop for Session {
+    fn drop(&mut self) {
+        let pending = self.opening || self.copying.is_some();
+        self.stop();
+        if let Some((frame, ..)) = self.copying.take() {
+            frame.close();
+        }
+        if pending {
+            std::mem::forget(self.callbacks.take());
+        }
+    }
+}
+
+fn with_session<R>(id: u32, f: impl FnOnce(&mut Session) -> R) -> Option<R> {
+    SESSIONS.with(|sessions| sessions.borrow_mut().get_mut(&id).map(f))
+}
+
+fn media_devices() -> Option<MediaDevices> {
+    // missing outside of secure contexts, i.e. pages not served over HTTPS or from localhost
+    web_sys::window()?.navigator().media_devices().ok()
+}
+
+fn js_error(err: JsValue) -> Error {
+    match err.dyn_ref::<DomException>() {
+        Some(err) => Error::Other(format!("{}: {}", err.name(), err.message())),
+        None => Error::Other(format!("{err:?}")),
+    }
+}
+
+fn stop_tracks(stream: &MediaStream) {
+    for track in stream.get_tracks().iter() {
+        track.unchecked_into::<MediaStreamTrack>().stop();
+    }
+}
+
+/// Milliseconds of `performance.now`, the clock of the animation frames.
+fn performance_now() -> Option<Duration> {
+    let now = web_sys::window()?.performance()?.now();
+    Some(Duration::from_secs_f64(now / 1000.0))
+}
+
+fn opened(id: u32, stream: JsValue) {
+    let stream: MediaStream = stream.unchecked_into();
+    let kept = with_session(id, |session| {
+        if !session.opening {
+            return false;
+        }
+        session.opening = false;
+        session.video.set_src_object(Some(&stream));
+        // muted video may play without a user gesture
+        let _ = session.video.play();
+        if let Ok(track) = stream.get_video_tracks().get(0).dyn_into::<MediaStreamTrack>() {
+            if let Some(callbacks) = &session.callbacks {
+                track.set_onended(Some(callbacks.on_ended.as_ref().unchecked_ref()));
+            }
+            let settings = track.get_settings();
+            let fps = settings.get_frame_rate().unwrap_or_default();
+            *lock(&session.shared.format) = Some(CaptureFormat {
+                pixel_format: String::new(),
+                width: settings.get_width().unwrap_or_default() as u32,
+                height: settings.get_height().unwrap_or_default() as u32,
+                min_fps: fps,
+                max_fps: fps,
+            });
+        }
+        session.stream = Some(stream.clone());
+        session.request_animation_frame();
+        true
+    });
+    if kept != Some(true) {
+        stop_tracks(&stream);
+    }
+}
+
+fn refused(id: u32, err: JsValue) {
+    let Some(shared) = with_session(id, |session| {
+        session.opening = false;
+        session.shared.clone()
+    }) else {
+        return;
+    };
+    let event = match err.dyn_ref::<DomException>().map(|err| err.name()).as_deref() {
+        Some("NotAllowedError" | "SecurityError") => CameraEvent::AccessDenied,
+        Some("NotReadableError" | "AbortError") => CameraEvent::InUseByOtherApp,
+        _ => CameraEvent::DeviceLost,
+    };
+    let _ = shared.events_tx.send(event);
+}
+
+/// The camera was unplugged or the user revoked the permission.
+fn ended(id: u32) {
+    if let Some(shared) = with_session(id, |session| session.shared.clone()) {
+        let _ = shared.events_tx.send(CameraEvent::DeviceLost);
+    }
+}
+
+fn animation_frame(id: u32, time: JsValue) {
+    with_session(id, |session| {
+        session.animation_frame = None;
+        if session.stream.is_none() {
+            return;
+        }
+        session.request_animation_frame();
+        // the previous picture is still being copied, like a full queue of a driver
+        if session.copying.is_some() {
+            return;
+        }
+        let video = &session.video;
+        // HAVE_CURRENT_DATA
+        if video.ready_state() < 2 || video.current_time() == session.last_time {
+            return;
+        }
+        session.last_time = video.current_time();
+        if !session.shared.rate_limit.accept(Instant::now()) {
+            return;
+        }
+        let Ok(frame) = VideoFrame::new_with_html_video_element(video) else { return };
+        let Ok(len) = frame.allocation_size() else {
+            frame.close();
+            return;
+        };
+        if session.buffer.length() < len {
+            session.buffer = Uint8Array::new_with_length(len);
+        }
+        let Some(callbacks) = &session.callbacks else { return };
+        let copied: Promise = frame.copy_to_with_u8_array(&session.buffer).unchecked_into();
+        let _ = copied.then2(&callbacks.on_copied, &callbacks.on_copy_failed);
+        session.copying = Some((frame, len, time.as_f64().unwrap_or_default()));
+    });
+}
+
+fn copied(id: u32, layouts: JsValue) {
+    let converted = with_session(id, |session| {
+        let (video_frame, len, time) = session.copying.take()?;
+        let frame = session.stream.is_some().then(|| {
+            let planes: Vec<(usize, usize)> = (layouts.unchecked_ref::<Array>().iter())
+                .map(|layout| layout.unchecked_into::<PlaneLayout>())
+                .map(|layout| (layout.get_offset() as usize, layout.get_stride() as usize))
+                .collect();
+            let mut data = vec![0; len as usize];
+            session.buffer.subarray(0, len).copy_to(&mut data);
+            to_frame(&video_frame, &data, &planes, time)
+        });
+        video_frame.close();
+        let unsupported = match frame? {
+            Ok(frame) => {
+                session.shared.push(frame);
+                None
+            }
+            Err(pixel_format) if !session.unsupported_sent => {
+                session.unsupported_sent = true;
+                Some(pixel_format)
+            }
+            Err(_) => None,
+        };
+        Some((session.shared.clone(), unsupported))
+    });
+    if let Some(Some((shared, Some(pixel_format)))) = converted {
+        let _ = shared.events_tx.send(CameraEvent::UnsupportedFormat { pixel_format });
+    }
+}
+
+fn copy_failed(id: u32) {
+    with_session(id, |session| {
+        if let Some((frame, ..)) = session.copying.take() {
+            frame.close();
+        }
+    });
+}
+
+/// The BGRA frame of a copied `VideoFrame`, or the name of its pixel format if kamera can't
+/// convert it.
+fn to_frame(
+    video_frame: &VideoFrame,
+    data: &[u8],
+    planes: &[(usize, usize)],
+    time: f64,
+) -> Result<Frame, String> {
+    let start = Instant::now();
+    // `copyTo` without a rect copies the visible part
+    let size = match video_frame.visible_rect() {
+        Some(rect) => (rect.width() as u32, rect.height() as u32),
+        None => (video_frame.coded_width(), video_frame.coded_height()),
+    };
+    let color_space = color_space(video_frame);
+    let Some(format) = video_frame.format() else { return Err("unknown".into()) };
+    let bgra = to_bgra(format, data, planes, size, color_space);
+    let (Some(fourcc), Some(data)) = (fourcc(format), bgra) else {
+        return Err(format!("{format:?}"));
+    };
+    Ok(Frame {
+        data,
+        size,
+        color_space,
+        fourcc,
+        conversion_time: start.elapsed(),
+        timestamp: Duration::from_secs_f64(time / 1000.0),
+    })
+}
+
+/// The formats kamera converts, the WebCodecs names are four character codes too.
+fn fourcc(format: VideoPixelFormat) -> Option<FourCC> {
+    let code = match format {
+        VideoPixelFormat::I420 => b"I420",
+        VideoPixelFormat::I422 => b"I422",
+        VideoPixelFormat::I444 => b"I444",
+        VideoPixelFormat::Nv12 => b"NV12",
+        VideoPixelFormat::Rgba => b"RGBA",
+        VideoPixelFormat::Rgbx => b"RGBX",
+        VideoPixelFormat::Bgra => b"BGRA",
+        VideoPixelFormat::Bgrx => b"BGRX",
+        _ => return None,
+    };
+    Some(FourCC::new(code))
+}
+
+/// Converts the planes at `(offset, stride)` in `data` to BGRA, `None` if they aren't laid out
+/// like `copyTo` lays them out without options.
+fn to_bgra(
+    format: VideoPixelFormat,
+    data: &[u8],
+    planes: &[(usize, usize)],
+    (w, h): (u32, u32),
+    color_space: ColorSpace,
+) -> Option<Vec<u8>> {
+    let mut bgra = Vec::new();
+    let plane = |index: usize| data.get(planes.get(index)?.0..);
+    let (offset, stride) = *planes.first()?;
+    match format {
+        VideoPixelFormat::I420 | VideoPixelFormat::I422 | VideoPixelFormat::I444 => {
+            let shift = match format {
+                VideoPixelFormat::I420 => (1, 1),
+                VideoPixelFormat::I422 => (1, 0),
+                _ => (0, 0),
+            };
+            let chroma_w = (w as usize).div_ceil(1 << shift.0);
+            let strides: Vec<usize> = planes.iter().map(|(_, stride)| *stride).collect();
+            if strides != [w as usize, chroma_w, chroma_w] {
+                return None;
+            }
+            let [y, u, v] = [plane(0)?, plane(1)?, plane(2)?];
+            convert::planar_yuv_to_bgra([y, u, v], w, h, shift, color_space, &mut bgra);
+        }
+        VideoPixelFormat::Nv12 => {
+            // the chroma rows right after the luma rows
+            if planes.get(1) != Some(&(offset + stride * h as usize, stride)) {
+                return None;
+            }
+            convert::nv12_to_bgra(plane(0)?, w, h, stride, color_space, &mut bgra);
+        }
+        VideoPixelFormat::Rgba
+        | VideoPixelFormat::Rgbx
+        | VideoPixelFormat::Bgra
+        | VideoPixelFormat::Bgrx => {
+            let row_len = w as usize * 4;
+            for row in plane(0)?.chunks(stride).take(h as usize) {
+                bgra.extend_from_slice(row.get(..row_len)?);
+            }
+            if matches!(format, VideoPixelFormat::Rgba | VideoPixelFormat::Rgbx) {
+                bgra.chunks_exact_mut(4).for_each(|px| px.swap(0, 2));
+            }
+            // the fourth byte of the X formats is undefined
+            if matches!(format, VideoPixelFormat::Rgbx | VideoPixelFormat::Bgrx) {
+                bgra.chunks_exact_mut(4).for_each(|px| px[3] = 0xff);
+            }
+        }
+        _ => return None,
+    }
+    (bgra.len() == w as usize * h as usize * 4).then_some(bgra)
+}
+
+fn color_space(video_frame: &VideoFrame) -> ColorSpace {
+    let space = video_frame.color_space();
+    let matrix = match space.matrix() {
+        Some(VideoMatrixCoefficients::Bt709) => YuvMatrix::Bt709,
+        Some(VideoMatrixCoefficients::Bt470bg | VideoMatrixCoefficients::Smpte170m) => {
+            YuvMatrix::Bt601
+        }
+        Some(VideoMatrixCoefficients::Bt2020Ncl) => YuvMatrix::Bt2020,
+        _ => YuvMatrix::Unknown,
+    };
+    let range = match space.full_range() {
+        Some(true) => ColorRange::Full,
+        Some(false) => ColorRange::Limited,
+        None => ColorRange::Unknown,
+    };
+    let transfer = match space.transfer() {
+        Some(VideoTransferCharacteristics::Iec6196621) => TransferFunction::Srgb,
+        Some(VideoTransferCharacteristics::Bt709 | VideoTransferCharacteristics::Smpte170m) => {
+            TransferFunction::Bt709
+        }
+        _ => TransferFunction::Unknown,
+    };
+    ColorSpace { matrix, range, transfer }
+}
+
+/// Asks the browser for the devices again, [`Camera::device_list`] has them once it answers.
+fn refresh_devices() {
+    let Some(request) = media_devices().and_then(|devices| devices.enumerate_devices().ok()) else {
+        return;
+    };
+    ON_DEVICES.with(|on_devices| {
+        let _ = request.then(on_devices);
+    });
+}
+
+fn store_devices(infos: JsValue) {
+    let infos: Array = infos.unchecked_into();
+    let cameras = (infos.iter())
+        .map(|info| info.unchecked_into::<MediaDeviceInfo>())
+        .filter(|info| info.kind() == MediaDeviceKind::Videoinput);
+    let devices = cameras.enumerate().map(|(i, info)| {
+        // without the permission yet the labels are empty
+        let name = match info.label() {
+            label if label.is_empty() => format!("Camera {}", i + 1),
+            label => label,
+        };
+        (CameraDevice::new(info.device_id(), name, DeviceKind::Unknown), info.group_id())
+    });
+    DEVICES.with(|list| *list.borrow_mut() = devices.collect());
+}
+
+/// The camera the browser picks, while it doesn't list any before the first permission.
+fn default_device() -> CameraDevice {
+    CameraDevice::new("", "Camera", DeviceKind::Unknown)
+}
+
+/// `{ exact: value }` of a constraint, a plain value is only the ideal.
+fn exact(value: &JsValue) -> JsValue {
+    let constraint = Object::new();
+    let _ = Reflect::set(&constraint, &"exact".into(), value);
+    constraint.into()
+}
+
+pub struct Camera {
+    /// The key of the JavaScript objects in [`SESSIONS`].
+    id: u32,
+    device: CameraDevice,
+    shared: Arc<Shared>,
+    resolution: Mutex<Option<(u32, u32)>>,
+    latency: Mutex<Latency>,
+    events: Receiver<CameraEvent>,
+}
+
+impl Camera {
+    fn constraints(&self) -> MediaStreamConstraints {
+        let video = MediaTrackConstraints::new();
+        if !self.device.id.is_empty() {
+            video.set_device_id(&exact(&self.device.id.as_str().into()));
+        }
+        if let Some((width, height)) = *lock(&self.resolution) {
+            video.set_width(&width.into());
+            video.set_height(&height.into());
+        }
+        let constraints = MediaStreamConstraints::new();
+        constraints.set_video(&video);
+        constraints
+    }
+
+    /// Asks for the stream again with the current constraints, if it runs.
+    fn restart(&self) {
+        let running = with_session(self.id, |session| session.stream.is_some() || session.opening);
+        if running == Some(true) {
+            self.stop();
+            let _ = self.start();
+        }
+    }
+}
+
+impl InnerCamera for Camera {
+    type Frame = Frame;
+
+    fn new_with(builder: &CameraBuilder) -> Result<Self, Error> {
+        media_devices().ok_or(Error::Unsupported)?;
+        let device = match Self::device_list() {
+            devices if devices.is_empty() => default_device(),
+            devices => {
+                let devices = devices.into_iter().map(|d| (d, Placement::Unknown)).collect();
+                device_policy::pick(devices, &builder.device_policy).ok_or(Error::NoDevice)?
+            }
+        };
+        let document = web_sys::window().and_then(|window| window.document());
+        let video = document.ok_or(Error::Unsupported)?.create_element("video");
+        let video: HtmlVideoElement = video.map_err(js_error)?.unchecked_into();
+        video.set_muted(true);
+        // Safari on iOS plays video in full screen otherwise
+        let _ = video.set_attribute("playsinline", "");
+
+        let (events_tx, events) = channel();
+        let shared = Arc::new(Shared {
+            frames: Default::default(),
+            format: Default::default(),
+            queue_size: builder.frame_queue_size,
+            memory_limit: AtomicUsize::new(usize::MAX),
+            rate_limit: Default::default(),
+            events_tx,
+        });
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let session = Session {
+            video,
+            stream: None,
+            opening: false,
+            animation_frame: None,
+            last_time: -1.0,
+            copying: None,
+            buffer: Uint8Array::new_with_length(0),
+            unsupported_sent: false,
+            callbacks: Some(Callbacks::new(id)),
+            shared: shared.clone(),
+        };
+        SESSIONS.with(|sessions| sessions.borrow_mut().insert(id, session));
+        Ok(Camera {
+            id,
+            device,
+            shared,
+            resolution: Default::default(),
+            latency: Default::default(),
+            events,
+        })
+    }
+
+    /// Asks for the stream, which the browser may first ask the user to allow. A refusal
+    /// arrives later as [`CameraEvent::AccessDenied`].
+    fn start(&self) -> Result<(), Error> {
+        let media_devices = media_devices().ok_or(Error::Unsupported)?;
+        let constraints = self.constraints();
+        with_session(self.id, |session| {
+            if session.stream.is_some() || session.opening {
+                return Ok(());
+            }
+            let Some(callbacks) = &session.callbacks else { return Err(Error::NoDevice) };
+            let request = media_devices.get_user_media_with_constraints(&constraints);
+            let _ = request.map_err(js_error)?.then2(&callbacks.on_stream, &callbacks.on_refused);
+            session.opening = true;
+            Ok(())
+        })
+        .unwrap_or(Err(Error::NoDevice))
+    }
+
+    /// Browsers share a camera between pages.
+    fn try_exclusive(&self) -> Result<(), Error> {
+        self.start()
+    }
+
+    fn stop(&self) {
+        with_session(self.id, Session::stop);
+        lock(&self.shared.frames).clear();
+    }
+
+    /// Never blocks, the frames arrive in callbacks of the page, see [`web_media`](self).
+    fn wait_for_frame(&self) -> Option<Frame> {
+        match *lock(&self.latency) {
+            Latency::Lowest => self.latest_frame(),
+            _ => self.try_next_frame(),
+        }
+    }
+
+    fn try_next_frame(&self) -> Option<Frame> {
+        lock(&self.shared.frames).pop_front()
+    }
+
+    fn latest_frame(&self) -> Option<Frame> {
+        let mut frames = lock(&self.shared.frames);
+        let latest = frames.pop_back();
+        frames.clear();
+        latest
+    }
+
+    fn frame_ready_fd(&self) -> Option<FrameReadyFd> {
+        None
+    }
+
+    fn events(&self) -> &Receiver<CameraEvent> {
+        &self.events
+    }
+
+    fn send_event(&self, event: CameraEvent) {
+        let _ = self.shared.events_tx.send(event);
+    }
+
+    fn current_format(&self) -> Option<CaptureFormat> {
+        lock(&self.shared.format).clone()
+    }
+
+    /// The clock of the animation frames, which the frames are timestamped with.
+    fn device_clock(&self) -> Option<Duration> {
+        performance_now()
+    }
+
+    /// Browsers don't tell the exposure.
+    fn capture_metadata(&self) -> CaptureMetadata {
+        CaptureMetadata::new(self.device.name.clone())
+    }
+
+    /// Restarts the stream with the size as ideal, the browser picks the closest it has.
+    fn set_resolution(&self, width: u32, height: u32) -> bool {
+        *lock(&self.resolution) = Some((width, height));
+        self.restart();
+        true
+    }
+
+    /// The browser picks the pixel format, only the size is asked for.
+    fn set_format(&self, format: &CaptureFormat) -> bool {
+        self.set_resolution(format.width, format.height)
+    }
+
+    fn renegotiate(&self, invalid_frames: u32) {
+        self.restart();
+        let _ = self.shared.events_tx.send(CameraEvent::StreamRenegotiated { invalid_frames });
+    }
+
+    /// A `VideoFrame` is always a whole image.
+    fn short_frames(&self) -> u32 {
+        0
+    }
+
+    fn set_max_fps(&self, fps: f32) {
+        self.shared.rate_limit.set_max_fps(fps);
+    }
+
+    /// `Lowest` hands out the newest frame and drops the queued ones.
+    fn set_latency_mode(&self, latency: Latency) {
+        *lock(&self.latency) = latency;
+    }
+
+    /// The size is asked for with a single restart, the browser accepts any.
+    fn configure(&self, changes: &RequestedConfig) -> Vec<ConfigMismatch> {
+        if let Some(fps) = changes.max_fps {
+            self.set_max_fps(fps);
+        }
+        if let Some(latency) = changes.latency {
+            self.set_latency_mode(latency);
+        }
+        if changes.format.is_none() && changes.resolution.is_none() {
+            return Vec::new();
+        }
+        let mismatches = changes.set_size(|request| {
+            *lock(&self.resolution) = Some(match request {
+                SizeRequest::Format(format) => (format.width, format.height),
+                SizeRequest::Resolution(width, height) => (width, height),
+            });
+            true
+        });
+        self.restart();
+        mismatches
+    }
+
+    fn enable_metadata(&self, _kind: MetadataKind) -> bool {
+        false
+    }
+
+    fn faces(&self) -> Vec<FaceRect> {
+        Vec::new()
+    }
+
+    fn enable_depth(&self) -> bool {
+        false
+    }
+
+    fn depth(&self, _frame: &Frame) -> Option<Arc<DepthFrame>> {
+        None
+    }
+
+    /// The frames are converted on the thread of the page.
+    fn set_thread_priority(&self, _priority: ThreadPriority) {}
+
+    /// Queued frames over the limit are dropped, the newest one is always kept.
+    fn set_memory_limit(&self, bytes: usize) {
+        self.shared.memory_limit.store(bytes, Ordering::Relaxed);
+    }
+
+    fn memory_usage(&self) -> usize {
+        lock(&self.shared.frames).iter().map(|frame| frame.data.len()).sum()
+    }
+
+    fn supports_enhancement(&self, _enhancement: Enhancement) -> bool {
+        false
+    }
+
+    fn set_enhancement(&self, _enhancement: Enhancement, _enabled: bool) -> bool {
+        false
+    }
+
+    fn set_video_orientation(&self, _orientation: VideoOrientation) -> bool {
+        false
+    }
+
+    fn ptz_range(&self, _axis: PtzAxis) -> Option<PtzRange> {
+        None
+    }
+
+    fn ptz(&self, _axis: PtzAxis) -> Option<f32> {
+        None
+    }
+
+    fn set_ptz(&self, _axis: PtzAxis, _value: f32) -> bool {
+        false
+    }
+
+    fn has_torch(&self) -> bool {
+        false
+    }
+
+    fn set_torch_level(&self, _level: f32) -> Result<(), Error> {
+        Err(Error::Unsupported)
+    }
+
+    fn device(&self) -> CameraDevice {
+        self.device.clone()
+    }
+
+    fn set_device(&mut self, device: &CameraDevice) -> Result<(), Error> {
+        if device.id == self.device.id {
+            return Ok(());
+        }
+        self.stop();
+        if !Self::device_list().iter().any(|d| d.id == device.id) {
+            return Err(Error::NoDevice);
+        }
+        self.device = device.clone();
+        self.start()
+    }
+
+    /// The devices of the last answer of the browser, which also asks again. The first call
+    /// returns an empty list, the names are only known after the user allowed a camera.
+    fn device_list() -> Vec<CameraDevice> {
+        refresh_devices();
+        DEVICES.with(|list| list.borrow().iter().map(|(device, _)| device.clone()).collect())
+    }
+
+    /// Device types only exist on macOS.
+    fn device_list_of_types(_types: &[DeviceType]) -> Vec<CameraDevice> {
+        Self::device_list()
+    }
+
+    fn enumerate_with_errors() -> Vec<Result<CameraDevice, EnumError>> {
+        Self::device_list().into_iter().map(Ok).collect()
+    }
+
+    /// The device id of the browser stays the same for a site until its cookies are cleared.
+    fn stable_id(device: &CameraDevice) -> String {
+        device.id.clone()
+    }
+
+    fn group_id(device: &CameraDevice) -> String {
+        DEVICES.with(|list| {
+            let list = list.borrow();
+            let group = list.iter().find(|(d, _)| d.id == device.id).map(|(_, group)| group);
+            group.filter(|group| !group.is_empty()).unwrap_or(&device.id).clone()
+        })
+    }
+
+    /// Browsers only tell the capabilities of a running track.
+    fn describe_device(_device: &CameraDevice) -> DeviceCapabilities {
+        DeviceCapabilities::default()
+    }
+
+    fn default_format(_device: &CameraDevice) -> Option<CaptureFormat> {
+        None
+    }
+
+    fn backend_capabilities() -> BackendCapabilities {
+        BackendCapabilities {
+            capture: media_devices().is_some(),
+            device_types: false,
+            face_metadata: false,
+            depth: false,
+        }
+    }
+
+    fn device_details(_device: &CameraDevice) -> DeviceDetails {
+        DeviceDetails::default()
+    }
+}
+
+impl Drop for Camera {
+    fn drop(&mut self) {
+        SESSIONS.with(|sessions| sessions.borrow_mut().remove(&self.id));
+    }
+}
+
+impl std::fmt::Debug for Camera {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Camera").field("device", &self.device.name).finish()
+    }
+}
+
+pub struct Frame {
+    data: Vec<u8>,
+    size: (u32, u32),
+    color_space: ColorSpace,
+    /// The pixel format of the `VideoFrame` before conversion.
+    fourcc: FourCC,
+    conversion_time: Duration,
+    /// The time of the animation frame on `performance.now`.
+    timestamp: Duration,
+}
+
+impl Frame {
+    pub fn data(&self) -> FrameData {
+        FrameData { data: &self.data, stride: self.size.0 as usize * 4, size: self.size }
+    }
+
+    pub fn size_u32(&self) -> (u32, u32) {
+        self.size
+    }
+
+    pub fn conversion_time(&self) -> Duration {
+        self.conversion_time
+    }
+
+    pub fn timestamp(&self) -> Option<Duration> {
+        Some(self.timestamp)
+    }
+
+    pub fn color_space(&self) -> ColorSpace {
+        self.color_space
+    }
+
+    pub fn fourcc(&self) -> FourCC {
+        self.fourcc
+    }
+
+    /// The converted buffer has a single owner, a crop copies.
+    pub fn share(&self) -> Option<Frame> {
+        None
+    }
+}
+
+impl std::fmt::Debug for Frame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Frame")
+            .field("data", &self.data.len())
+            .field("fourcc", &self.fourcc)
+            .finish()
+    }
+}
+
+#[derive(Debug)]
+pub struct FrameData<'a> {
+    data: &'a [u8],
+    stride: usize,
+    size: (u32, u32),
+}
+
+impl<'a> FrameData<'a> {
+    pub fn data_u8(&self) -> &[u8] {
+        self.data
+    }
+
+    pub fn data_u32(&self) -> &[u32] {
+        unsafe { self.data.align_to().1 }
+    }
+
+    pub fn data_u16(&self) -> Option<&[u16]> {
+        None
+    }
+
+    pub fn luma(&self) -> Option<&[u8]> {
+        None
+    }
+
+    pub fn chroma(&self) -> Option<&[u8]> {
+        None
+    }
+
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    pub fn plane_count(&self) -> usize {
+        1
+    }
+
+    pub fn plane(&self, index: usize) -> Option<PlaneView> {
+        let (width, height) = self.size;
+        (index == 0).then_some(PlaneView { data: self.data, stride: self.stride, width, height })
+    }
+}
@@ -0,0 +1,12 @@
+/// How the Windows backend captures, see
+/// [`CameraBuilder::win_backend`](crate::CameraBuilder::win_backend).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WinBackend {
+    /// `IMFCaptureEngine` (the default), with face detection and torch control of the driver.
+    #[default]
+    CaptureEngine,
+    /// `IMFSourceReader` in async mode, which starts faster, delivers frames with less latency
+    /// and works where the capture engine doesn't, e.g. in some virtual machines. No face
+    /// metadata and no torch.
+    SourceReader,
+}
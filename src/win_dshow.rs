@@ -0,0 +1,362 @@
+//! Capture through DirectShow, for older and virtual cameras which only register a DirectShow
+//! filter and don't show up in Media Foundation. Enabled with the `dshow` feature.
+//!
+//! The filter graph runs the camera into a sample grabber, which converts to RGB32 and hands
+//! each buffer to a callback on the streaming thread of DirectShow. The COM objects aren't
+//! `Send`, so a capture thread owns them, like with libcamera.
+
+use std::ffi::c_void;
+use std::mem::ManuallyDrop;
+use std::sync::mpsc::*;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use windows::core::{implement, interface, IUnknown, IUnknown_Vtbl, Interface, Vtable, GUID};
+use windows::core::{HRESULT, PCWSTR};
+use windows::Win32::Foundation::{BOOL, E_FAIL, S_OK};
+use windows::Win32::Media::DirectShow::{
+    IBaseFilter, ICaptureGraphBuilder2, ICreateDevEnum, IGraphBuilder, IMediaControl, IMediaEvent,
+};
+use windows::Win32::Media::MediaFoundation::{
+    CLSID_CaptureGraphBuilder2, CLSID_FilterGraph, CLSID_SystemDeviceEnum,
+    CLSID_VideoInputDeviceCategory, FORMAT_VideoInfo, MEDIATYPE_Video, AM_MEDIA_TYPE,
+    MEDIASUBTYPE_RGB32, PIN_CATEGORY_CAPTURE, VIDEOINFOHEADER,
+};
+use windows::Win32::System::Com::StructuredStorage::IPropertyBag;
+use windows::Win32::System::Com::{
+    CoCreateInstance, CoTaskMemFree, IMoniker, CLSCTX_INPROC_SERVER, VARIANT, VT_BSTR,
+};
+
+use crate::details::instance_from_symbolic_link;
+use crate::sync::lock;
+use crate::win_mf::mf::co_initialize_multithreaded;
+use crate::{CameraDevice, CameraEvent, CaptureFormat, DeviceKind, Error, FrameSource, OwnedFrame};
+
+/// qedit.dll, which still ships with Windows but is gone from the SDK headers.
+const CLSID_SAMPLE_GRABBER: GUID = GUID::from_u128(0xc1f400a0_3f08_11d3_9f0b_006008039e37);
+const CLSID_NULL_RENDERER: GUID = GUID::from_u128(0xc1f400a4_3f08_11d3_9f0b_006008039e37);
+
+/// `ISampleGrabberCB::BufferCB` instead of `SampleCB`.
+const BUFFER_CALLBACK: i32 = 1;
+/// Graph event of a camera which was removed or came back.
+const EC_DEVICE_LOST: i32 = 0x1f;
+
+#[interface("6b652fff-11fe-4fce-92ad-0266b5d7c78f")]
+unsafe trait ISampleGrabber: IUnknown {
+    fn SetOneShot(&self, one_shot: BOOL) -> HRESULT;
+    fn SetMediaType(&self, media_type: *const AM_MEDIA_TYPE) -> HRESULT;
+    fn GetConnectedMediaType(&self, media_type: *mut AM_MEDIA_TYPE) -> HRESULT;
+    fn SetBufferSamples(&self, buffer: BOOL) -> HRESULT;
+    fn GetCurrentBuffer(&self, size: *mut i32, buffer: *mut i32) -> HRESULT;
+    fn GetCurrentSample(&self, sample: *mut *mut c_void) -> HRESULT;
+    fn SetCallback(&self, callback: *mut c_void, which: i32) -> HRESULT;
+}
+
+#[interface("0579154a-2b53-4994-b0d0-e773148eff85")]
+unsafe trait ISampleGrabberCB: IUnknown {
+    fn SampleCB(&self, time: f64, sample: *mut c_void) -> HRESULT;
+    fn BufferCB(&self, time: f64, buffer: *mut u8, len: i32) -> HRESULT;
+}
+
+#[derive(Debug)]
+enum Command {
+    Start,
+    Stop,
+    Events(Sender<CameraEvent>),
+}
+
+#[derive(Debug)]
+pub(crate) struct DshowCamera {
+    device: CameraDevice,
+    format: CaptureFormat,
+    commands: Mutex<Sender<Command>>,
+    frames: Mutex<Receiver<OwnedFrame>>,
+}
+
+impl DshowCamera {
+    /// Opens `device`, the first DirectShow camera if `None`.
+    pub(crate) fn open(device: Option<&CameraDevice>) -> Result<Self, Error> {
+        let id = device.map(|device| device.id.clone());
+        let (setup_tx, setup_rx) = channel();
+        let (command_tx, command_rx) = channel();
+        let (frame_tx, frame_rx) = sync_channel(1);
+        std::thread::spawn(move || run(id, setup_tx, command_rx, frame_tx));
+        let (device, format) = setup_rx.recv().map_err(|_| Error::NoDevice)??;
+        Ok(Self { device, format, commands: Mutex::new(command_tx), frames: Mutex::new(frame_rx) })
+    }
+}
+
+impl FrameSource for DshowCamera {
+    fn start(&self) {
+        let _ = lock(&self.commands).send(Command::Start);
+    }
+
+    fn stop(&self) {
+        let _ = lock(&self.commands).send(Command::Stop);
+    }
+
+    fn wait_for_frame(&self) -> Option<OwnedFrame> {
+        lock(&self.frames).recv_timeout(Duration::from_secs(3)).ok()
+    }
+
+    fn try_next_frame(&self) -> Option<OwnedFrame> {
+        lock(&self.frames).try_recv().ok()
+    }
+
+    fn device(&self) -> CameraDevice {
+        self.device.clone()
+    }
+
+    fn current_format(&self) -> Option<CaptureFormat> {
+        Some(self.format.clone())
+    }
+
+    /// Gets [`CameraEvent::DeviceLost`] when the camera is unplugged.
+    fn connect_events(&self, events: Sender<CameraEvent>) {
+        let _ = lock(&self.commands).send(Command::Events(events));
+    }
+}
+
+/// The video input devices DirectShow knows, including the ones Media Foundation lists too.
+pub(crate) fn device_list() -> Vec<CameraDevice> {
+    co_initialize_multithreaded();
+    monikers().iter().map(camera_device).collect()
+}
+
+/// The DirectShow devices which aren't in `listed`, by device path or for virtual cameras by
+/// name.
+pub(crate) fn devices_missing_from(listed: &[CameraDevice]) -> Vec<CameraDevice> {
+    let instances: Vec<_> = listed.iter().map(|d| instance_from_symbolic_link(&d.id)).collect();
+    let listed = |device: &CameraDevice| match device.id.strip_prefix("dshow:") {
+        Some(name) => listed.iter().any(|d| d.name == name),
+        None => instances.contains(&instance_from_symbolic_link(&device.id)),
+    };
+    device_list().into_iter().filter(|device| !listed(device)).collect()
+}
+
+fn monikers() -> Vec<IMoniker> {
+    unsafe {
+        let Ok(enumerator) = create::<ICreateDevEnum>(&CLSID_SystemDeviceEnum) else {
+            return Vec::new();
+        };
+        let mut monikers = None;
+        let category = &CLSID_VideoInputDeviceCategory;
+        // no enumerator without devices of the category
+        if enumerator.CreateClassEnumerator(category, &mut monikers, 0).is_err() {
+            return Vec::new();
+        }
+        let Some(monikers) = monikers else { return Vec::new() };
+        let mut list = Vec::new();
+        loop {
+            let mut next = [None];
+            if monikers.Next(&mut next, None) != S_OK {
+                return list;
+            }
+            list.extend(next[0].take());
+        }
+    }
+}
+
+/// The device path is the symbolic link of the device interface, virtual cameras have none and
+/// are identified by their name.
+fn camera_device(moniker: &IMoniker) -> CameraDevice {
+    let mut bag: Option<IPropertyBag> = None;
+    let bag_ptr = &mut bag as *mut _ as *mut *mut c_void;
+    let _ = unsafe { moniker.BindToStorage(None, None, &IPropertyBag::IID, bag_ptr) };
+    let property = |name| bag.as_ref().and_then(|bag| read_string(bag, name));
+    let name = property(windows::w!("FriendlyName")).unwrap_or_else(|| "NO NAME".into());
+    match property(windows::w!("DevicePath")) {
+        Some(path) => CameraDevice::new(path, name, DeviceKind::Physical),
+        None => CameraDevice::new(format!("dshow:{name}"), name, DeviceKind::Virtual),
+    }
+}
+
+fn read_string(bag: &IPropertyBag, name: PCWSTR) -> Option<String> {
+    let mut value = VARIANT::default();
+    unsafe {
+        bag.Read(name, &mut value, None).ok()?;
+        let value = &mut value.Anonymous.Anonymous;
+        if value.vt != VT_BSTR {
+            return None;
+        }
+        let string = ManuallyDrop::take(&mut value.Anonymous.bstrVal);
+        Some(string.to_string())
+    }
+}
+
+fn create<T: Interface>(clsid: &GUID) -> windows::core::Result<T> {
+    unsafe { CoCreateInstance::<Option<&IUnknown>, T>(clsid, None, CLSCTX_INPROC_SERVER) }
+}
+
+type Setup = Result<(CameraDevice, CaptureFormat), Error>;
+
+/// The filters of a running graph.
+struct Graph {
+    control: IMediaControl,
+    events: IMediaEvent,
+    grabber: ISampleGrabber,
+    _callback: ISampleGrabberCB,
+}
+
+impl Graph {
+    /// Takes the pending events of the graph, whether one of them says the camera was removed.
+    fn device_lost(&self) -> bool {
+        let mut lost = false;
+        let (mut code, mut param1, mut param2) = (0, 0, 0);
+        unsafe {
+            while self.events.GetEvent(&mut code, &mut param1, &mut param2, 0).is_ok() {
+                // the second parameter is 0 when the camera was removed, 1 when it's back
+                lost |= code == EC_DEVICE_LOST && param2 == 0;
+                let _ = self.events.FreeEventParams(code, param1, param2);
+            }
+        }
+        lost
+    }
+}
+
+fn run(
+    id: Option<String>,
+    setup: Sender<Setup>,
+    commands: Receiver<Command>,
+    frames: SyncSender<OwnedFrame>,
+) {
+    co_initialize_multithreaded();
+    let found = monikers().into_iter().find(|moniker| match &id {
+        Some(id) => camera_device(moniker).id == *id,
+        None => true,
+    });
+    let Some(moniker) = found else {
+        let _ = setup.send(Err(Error::NoDevice));
+        return;
+    };
+    let device = camera_device(&moniker);
+    let graph = match build_graph(&moniker, frames) {
+        Ok((graph, format)) => {
+            let _ = setup.send(Ok((device, format)));
+            graph
+        }
+        Err(err) => {
+            let _ = setup.send(Err(err.into()));
+            return;
+        }
+    };
+    let mut events = None;
+    // ends when the camera is dropped
+    loop {
+        match commands.recv_timeout(Duration::from_millis(100)) {
+            Ok(Command::Start) => drop(unsafe { graph.control.Run() }),
+            Ok(Command::Stop) => drop(unsafe { graph.control.Stop() }),
+            Ok(Command::Events(sender)) => events = Some(sender),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        if graph.device_lost() {
+            if let Some(events) = &events {
+                let _ = events.send(CameraEvent::DeviceLost);
+            }
+        }
+    }
+    unsafe {
+        let _ = graph.control.Stop();
+        let _ = graph.grabber.SetCallback(std::ptr::null_mut(), BUFFER_CALLBACK);
+    }
+}
+
+/// Camera, sample grabber and null renderer, the capture graph builder adds converters in
+/// between if the camera doesn't deliver RGB32.
+fn build_graph(
+    moniker: &IMoniker,
+    frames: SyncSender<OwnedFrame>,
+) -> windows::core::Result<(Graph, CaptureFormat)> {
+    unsafe {
+        let graph: IGraphBuilder = create(&CLSID_FilterGraph)?;
+        let builder: ICaptureGraphBuilder2 = create(&CLSID_CaptureGraphBuilder2)?;
+        builder.SetFiltergraph(&graph)?;
+
+        let mut source: Option<IBaseFilter> = None;
+        let source_ptr = &mut source as *mut _ as *mut *mut c_void;
+        moniker.BindToObject(None, None, &IBaseFilter::IID, source_ptr)?;
+        let source = source.ok_or_else(|| windows::core::Error::from(E_FAIL))?;
+        graph.AddFilter(&source, PCWSTR::null())?;
+
+        let grabber_filter: IBaseFilter = create(&CLSID_SAMPLE_GRABBER)?;
+        let grabber: ISampleGrabber = grabber_filter.cast()?;
+        let rgb32 = AM_MEDIA_TYPE {
+            majortype: MEDIATYPE_Video,
+            subtype: MEDIASUBTYPE_RGB32,
+            ..Default::default()
+        };
+        grabber.SetMediaType(&rgb32).ok()?;
+        graph.AddFilter(&grabber_filter, PCWSTR::null())?;
+        let renderer: IBaseFilter = create(&CLSID_NULL_RENDERER)?;
+        graph.AddFilter(&renderer, PCWSTR::null())?;
+        builder.RenderStream(
+            Some(&PIN_CATEGORY_CAPTURE),
+            &MEDIATYPE_Video,
+            <&IUnknown>::from(&source),
+            &grabber_filter,
+            &renderer,
+        )?;
+
+        let mut connected = AM_MEDIA_TYPE::default();
+        grabber.GetConnectedMediaType(&mut connected).ok()?;
+        let header = (connected.formattype == FORMAT_VideoInfo
+            && connected.cbFormat as usize >= std::mem::size_of::<VIDEOINFOHEADER>())
+        .then(|| *(connected.pbFormat as *const VIDEOINFOHEADER));
+        CoTaskMemFree(Some(connected.pbFormat as *const c_void));
+        let header = header.ok_or_else(|| windows::core::Error::from(E_FAIL))?;
+        let bitmap = header.bmiHeader;
+        let (width, height) = (bitmap.biWidth.unsigned_abs(), bitmap.biHeight.unsigned_abs());
+
+        let callback: ISampleGrabberCB =
+            GrabberCallback { frames, width, height, bottom_up: bitmap.biHeight > 0 }.into();
+        grabber.SetBufferSamples(false.into()).ok()?;
+        grabber.SetCallback(callback.as_raw(), BUFFER_CALLBACK).ok()?;
+
+        let fps = match header.AvgTimePerFrame {
+            0 => 0.0,
+            hundred_nanos => 10_000_000.0 / hundred_nanos as f64,
+        };
+        let format = CaptureFormat {
+            pixel_format: "RGB32".into(),
+            width,
+            height,
+            min_fps: fps,
+            max_fps: fps,
+        };
+        let graph =
+            Graph { control: graph.cast()?, events: graph.cast()?, grabber, _callback: callback };
+        Ok((graph, format))
+    }
+}
+
+#[implement(ISampleGrabberCB)]
+struct GrabberCallback {
+    frames: SyncSender<OwnedFrame>,
+    width: u32,
+    height: u32,
+    /// RGB DIBs with a positive height start with the bottom row.
+    bottom_up: bool,
+}
+
+impl ISampleGrabberCB_Impl for GrabberCallback {
+    unsafe fn SampleCB(&self, _time: f64, _sample: *mut c_void) -> HRESULT {
+        S_OK
+    }
+
+    /// Drops the frame while the previous one wasn't taken yet.
+    unsafe fn BufferCB(&self, _time: f64, buffer: *mut u8, len: i32) -> HRESULT {
+        let stride = self.width as usize * 4;
+        let size = stride * self.height as usize;
+        if buffer.is_null() || (len as usize) < size {
+            return S_OK;
+        }
+        let data = std::slice::from_raw_parts(buffer, size);
+        let bgra = match self.bottom_up {
+            true => data.chunks_exact(stride).rev().flatten().copied().collect(),
+            false => data.to_vec(),
+        };
+        let _ = self.frames.try_send(OwnedFrame::new(bgra, self.width, self.height));
+        S_OK
+    }
+}
@@ -1,134 +1,719 @@
+use super::attributes::mf_get_string;
+use super::media_type::MediaType;
 use super::mf::*;
-use crate::CameraDevice;
+use super::source_reader::SourceReader;
+use crate::config::{RequestedConfig, SizeRequest};
+use crate::memory::{MemoryBudget, Reservation};
+use crate::priority::PriorityRequest;
+use crate::rate_limit::FrameRateLimit;
+use crate::sync::lock;
+use crate::{details, device_policy};
+use crate::{
+    BackendCapabilities, CameraBuilder, CameraDevice, CameraEvent, CaptureFormat, CaptureMetadata,
+    ColorSpace, ConfigMismatch, DepthFrame, DeviceCapabilities, DeviceDetails, DeviceType,
+    Enhancement, EnumError, Error, FaceRect, FourCC, FrameReadyFd, Latency, MetadataKind,
+    PlaneView, PtzAxis, PtzRange, ThreadPriority, VideoOrientation, WinBackend,
+};
 
-use std::{sync::mpsc::*, time::Duration};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::*,
+        Arc, Mutex,
+    },
+    time::Duration,
+};
 
-use windows::Win32::Media::MediaFoundation::*;
+use windows::{
+    core::HRESULT,
+    Win32::{Foundation::E_ACCESSDENIED, Media::MediaFoundation::*},
+};
 
 #[allow(unused)]
 #[derive(Debug)]
 pub struct Camera {
-    engine: IMFCaptureEngine,
+    engine: Engine,
     device: Device,
-    event_rx: Receiver<CaptureEngineEvent>,
-    sample_rx: Receiver<Option<IMFSample>>,
-    event_cb: IMFCaptureEngineOnEventCallback,
-    sample_cb: IMFCaptureEngineOnSampleCallback,
+    /// Only the capture engine sends events.
+    event_rx: Receiver<(CaptureEngineEvent, HRESULT)>,
+    camera_event_tx: Sender<CameraEvent>,
+    camera_event_rx: Receiver<CameraEvent>,
+    sample_rx: Receiver<Option<QueuedSample>>,
+    frame_ready: Arc<FrameReadyEvent>,
+    rate_limit: Arc<FrameRateLimit>,
+    priority: Arc<PriorityRequest>,
+    memory: Arc<MemoryBudget>,
+    /// Of the most recent sample.
+    faces: Mutex<Vec<FaceRect>>,
+    /// Of the current media type, which frames would otherwise query one by one.
+    color_space: Mutex<ColorSpace>,
+    latency: Mutex<Latency>,
+    /// Another `StartPreview` while the preview runs sends no event to wait for.
+    previewing: AtomicBool,
+}
+
+/// The capture path, see [`WinBackend`].
+#[allow(unused)]
+#[derive(Debug)]
+enum Engine {
+    CaptureEngine {
+        engine: IMFCaptureEngine,
+        event_cb: IMFCaptureEngineOnEventCallback,
+        sample_cb: IMFCaptureEngineOnSampleCallback,
+    },
+    SourceReader(SourceReader),
 }
 
 #[derive(Debug)]
 pub struct Frame {
     buffer: LockedBuffer,
+    color_space: ColorSpace,
+    fourcc: FourCC,
+    /// Capture time on the QPC clock.
+    timestamp: Option<Duration>,
+    _memory: Reservation,
 }
 
 pub struct FrameData<'a> {
     data: &'a [u8],
+    stride: usize,
+    size: (u32, u32),
 }
 
 impl Camera {
-    pub fn new_default_device() -> Self {
+    pub fn new_with(builder: &CameraBuilder) -> Result<Self, Error> {
         co_initialize_multithreaded();
-        media_foundation_startup().expect("media_foundation_startup");
-
-        let engine = new_capture_engine().unwrap();
-        let (event_tx, event_rx) = channel::<CaptureEngineEvent>();
-        let (sample_tx, sample_rx) = channel::<Option<IMFSample>>();
-        let event_cb = CaptureEventCallback { event_tx }.into();
-        let sample_cb = CaptureSampleCallback { sample_tx }.into();
-
+        media_foundation_startup()?;
         let devices = Device::enum_devices();
-        let Some(device) = devices.first().cloned() else { todo!() };
-
-        init_capture_engine(&engine, Some(&device.source), &event_cb).unwrap();
+        let candidates = devices.iter().map(|d| (d.camera_device(), d.placement())).collect();
+        let picked =
+            device_policy::pick(candidates, &builder.device_policy).ok_or(Error::NoDevice)?;
+        let device = (devices.into_iter())
+            .find(|d| d.id().to_string_lossy() == picked.id)
+            .ok_or(Error::NoDevice)?;
+        let backend = builder.win_backend;
+        Self::from_device(
+            device,
+            backend,
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        )
+    }
 
-        let camera = Camera { engine, device, event_rx, sample_rx, event_cb, sample_cb };
-        camera.wait_for_event(CaptureEngineEvent::Initialized);
-        camera.prepare_source_sink();
-        camera
+    /// Waits for the preview to start, a stop right after `StartPreview` is lost otherwise.
+    pub fn start(&self) -> Result<(), Error> {
+        if self.previewing.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        let engine = match &self.engine {
+            Engine::CaptureEngine { engine, .. } => engine,
+            Engine::SourceReader(reader) => {
+                reader.start()?;
+                self.previewing.store(true, Ordering::Relaxed);
+                return Ok(());
+            }
+        };
+        self.drain_events();
+        unsafe { engine.StartPreview() }?;
+        if self.wait_for_event_timeout(CaptureEngineEvent::PreviewStarted) {
+            self.previewing.store(true, Ordering::Relaxed);
+        }
+        Ok(())
     }
 
-    pub fn start(&self) {
-        unsafe { self.engine.StartPreview().unwrap() }
+    /// The source reader opens the device with the first sample and reports a busy device as an
+    /// event, like a failure after the start.
+    pub fn try_exclusive(&self) -> Result<(), Error> {
+        let Some(engine) = self.capture_engine() else { return self.start() };
+        if self.previewing.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        self.drain_events();
+        unsafe { engine.StartPreview() }?;
+        loop {
+            match self.event_rx.recv_timeout(Duration::from_secs(3)) {
+                Ok((_, status)) if status.is_err() => return Err(hresult_error(status)),
+                Ok((CaptureEngineEvent::PreviewStarted, _)) => {
+                    self.previewing.store(true, Ordering::Relaxed);
+                    return Ok(());
+                }
+                Ok(_) => continue,
+                Err(_) => return Err(Error::Other("preview did not start".into())),
+            }
+        }
     }
 
+    /// Stopping a camera which isn't running does nothing. Waits for the preview to stop and
+    /// drops the samples which were still queued, the next start delivers only new ones.
     pub fn stop(&self) {
-        capture_engine_stop_preview(&self.engine).unwrap();
+        self.previewing.store(false, Ordering::Relaxed);
+        match &self.engine {
+            Engine::CaptureEngine { engine, .. } => {
+                if capture_engine_stop_preview(engine).is_ok() {
+                    self.wait_for_event_timeout(CaptureEngineEvent::PreviewStopped);
+                }
+            }
+            Engine::SourceReader(reader) => reader.stop(),
+        }
+        self.drain_samples();
     }
 
     pub fn wait_for_frame(&self) -> Option<Frame> {
-        self.sample_rx
-            // TODO sometimes running two engines on the same camera breaks frame delivery, so wait not too long
-            .recv_timeout(Duration::from_secs(3))
-            .ok()
-            .flatten()
-            .and_then(|sample| {
-                let Some(mt) = capture_engine_sink_get_media_type(&self.engine).ok() else {
-                    return None;
-                };
-                let width = mt.frame_width();
-                let height = mt.frame_height();
-                sample_to_locked_buffer(&sample, width, height).ok()
-            })
-            .map(|buffer: LockedBuffer| Frame { buffer })
+        if *lock(&self.latency) == Latency::Lowest {
+            return self.latest_frame();
+        }
+        // TODO sometimes running two engines on the same camera breaks frame delivery, so wait not too long
+        let sample = self.sample_rx.recv_timeout(Duration::from_secs(3)).ok()?;
+        self.frame_ready.consumed();
+        self.frame_from_sample(sample)
     }
 
-    pub fn device(&self) -> CameraDevice {
-        CameraDevice { id: self.device.id().to_string_lossy().to_string(), name: self.device.name() }
+    pub fn try_next_frame(&self) -> Option<Frame> {
+        let sample = self.sample_rx.try_recv().ok()?;
+        self.frame_ready.consumed();
+        self.frame_from_sample(sample)
     }
 
-    pub fn set_device(&mut self, device: &CameraDevice) -> bool {
-        if device.id == self.device.id().to_string_lossy().to_string() {
-            return true;
+    /// Like `wait_for_frame` but skips samples which queued up in the meantime.
+    pub fn latest_frame(&self) -> Option<Frame> {
+        let mut sample = self.sample_rx.recv_timeout(Duration::from_secs(3)).ok()?;
+        self.frame_ready.consumed();
+        while let Ok(newer) = self.sample_rx.try_recv() {
+            self.frame_ready.consumed();
+            sample = newer;
         }
-        let find_device = enum_device_sources()
+        self.frame_from_sample(sample)
+    }
+
+    pub fn frame_ready_fd(&self) -> Option<FrameReadyFd> {
+        Some(self.frame_ready.handle().0 as FrameReadyFd)
+    }
+
+    pub fn events(&self) -> &Receiver<CameraEvent> {
+        &self.camera_event_rx
+    }
+
+    pub fn send_event(&self, event: CameraEvent) {
+        let _ = self.camera_event_tx.send(event);
+    }
+
+    pub fn current_format(&self) -> Option<CaptureFormat> {
+        self.source_media_type().map(|mt| mt.capture_format())
+    }
+
+    /// Restarts a running preview with the device media type of that size and the highest
+    /// frame rate.
+    pub fn set_resolution(&self, width: u32, height: u32) -> bool {
+        let Some(media_type) = self
+            .device
+            .query_media_types_with_best_fps()
             .into_iter()
-            .map(Device::new)
-            .find(|d| d.id().to_string_lossy().to_string() == device.id);
-        if let Some(new_device) = find_device {
-            let engine = new_capture_engine().unwrap();
-            let (event_tx, event_rx) = channel::<CaptureEngineEvent>();
-            let (sample_tx, sample_rx) = channel::<Option<IMFSample>>();
-            let event_cb = CaptureEventCallback { event_tx }.into();
-            let sample_cb = CaptureSampleCallback { sample_tx }.into();
+            .find(|mt| mt.frame_size() == (width, height))
+        else {
+            return false;
+        };
+        self.set_media_type(&media_type)
+    }
 
-            init_capture_engine(&engine, Some(&new_device.source), &event_cb).unwrap();
+    pub fn set_format(&self, format: &CaptureFormat) -> bool {
+        let media_types = self.device.query_media_types();
+        let Some(media_type) = media_types.iter().find(|mt| mt.capture_format() == *format) else {
+            return false;
+        };
+        self.set_media_type(media_type)
+    }
 
-            *self = Camera { engine, device: new_device, event_rx, sample_rx, event_cb, sample_cb };
-            self.wait_for_event(CaptureEngineEvent::Initialized);
-            self.prepare_source_sink();
-            self.start(); // TODO watch out about playing state
-            return true;
+    pub fn renegotiate(&self, invalid_frames: u32) {
+        let Some(format) = self.current_format() else { return };
+        if self.set_resolution(format.width, format.height) {
+            let _ = self.camera_event_tx.send(CameraEvent::StreamRenegotiated { invalid_frames });
+        }
+    }
+
+    /// Media Foundation delivers samples with their own length, empty ones show up in
+    /// validation.
+    pub fn short_frames(&self) -> u32 {
+        0
+    }
+
+    pub fn set_max_fps(&self, fps: f32) {
+        self.rate_limit.set_max_fps(fps);
+    }
+
+    /// Sources which support `MF_LOW_LATENCY` pick it up when the preview starts.
+    pub fn set_latency_mode(&self, latency: Latency) {
+        *lock(&self.latency) = latency;
+        let _ = media_source_set_low_latency(&self.device.source, latency == Latency::Lowest);
+    }
+
+    /// Picks the media type first and restarts a running preview once with it, after the
+    /// latency for the sources which read it at the start.
+    pub fn configure(&self, changes: &RequestedConfig) -> Vec<ConfigMismatch> {
+        if let Some(fps) = changes.max_fps {
+            self.set_max_fps(fps);
+        }
+        if let Some(latency) = changes.latency {
+            self.set_latency_mode(latency);
+        }
+        let mut picked = None;
+        let mismatches = changes.set_size(|request| {
+            picked = match request {
+                SizeRequest::Format(format) => self
+                    .device
+                    .query_media_types()
+                    .into_iter()
+                    .find(|mt| mt.capture_format() == *format),
+                SizeRequest::Resolution(width, height) => self
+                    .device
+                    .query_media_types_with_best_fps()
+                    .into_iter()
+                    .find(|mt| mt.frame_size() == (width, height)),
+            };
+            picked.is_some()
+        });
+        match picked {
+            Some(media_type) if !self.set_media_type(&media_type) => changes.set_size(|_| false),
+            _ => mismatches,
         }
-        return false;
     }
 
+    /// Needs a driver with face detection, Windows itself doesn't detect faces.
+    pub fn enable_metadata(&self, kind: MetadataKind) -> bool {
+        let MetadataKind::Faces = kind;
+        self.capture_engine()
+            .is_some_and(|engine| capture_engine_enable_face_detection(engine).is_ok())
+    }
+
+    pub fn faces(&self) -> Vec<FaceRect> {
+        lock(&self.faces).clone()
+    }
+
+    /// Media Foundation has no depth streams of webcams.
+    pub fn enable_depth(&self) -> bool {
+        false
+    }
+
+    pub fn depth(&self, _frame: &Frame) -> Option<Arc<DepthFrame>> {
+        None
+    }
+
+    /// Media Foundation calls back on a thread of its own, which takes the priority with the next
+    /// sample.
+    pub fn set_thread_priority(&self, priority: ThreadPriority) {
+        self.priority.set(priority);
+    }
+
+    /// Samples over the limit are dropped in the sample callback.
+    pub fn set_memory_limit(&self, bytes: usize) {
+        self.memory.set_limit(bytes);
+    }
+
+    pub fn memory_usage(&self) -> usize {
+        self.memory.used()
+    }
+
+    /// Samples of the device carry their time on the QPC clock.
+    pub fn device_clock(&self) -> Option<Duration> {
+        qpc_time()
+    }
+
+    pub fn capture_metadata(&self) -> CaptureMetadata {
+        let mut metadata = CaptureMetadata::new(self.device.name());
+        metadata.exposure_time = self.device.exposure_time();
+        metadata.white_balance = self.device.white_balance();
+        metadata.lens_aperture = self.device.lens_aperture();
+        metadata
+    }
+
+    pub fn supports_enhancement(&self, enhancement: Enhancement) -> bool {
+        match enhancement {
+            Enhancement::BacklightCompensation => {
+                self.device.backlight_compensation_range().is_some()
+            }
+            // extended camera controls of the driver, only through the capture engine
+            Enhancement::Hdr | Enhancement::LowLightBoost => self
+                .capture_engine()
+                .is_some_and(|engine| capture_engine_has_enhancement(engine, enhancement)),
+        }
+    }
+
+    pub fn set_enhancement(&self, enhancement: Enhancement, enabled: bool) -> bool {
+        match enhancement {
+            Enhancement::BacklightCompensation => self.device.set_backlight_compensation(enabled),
+            Enhancement::Hdr | Enhancement::LowLightBoost => {
+                self.capture_engine().is_some_and(|engine| {
+                    capture_engine_set_enhancement(engine, enhancement, enabled).is_ok()
+                })
+            }
+        }
+    }
+
+    /// Media Foundation delivers frames the way the driver fills them.
+    pub fn set_video_orientation(&self, _orientation: VideoOrientation) -> bool {
+        false
+    }
+
+    pub fn ptz_range(&self, axis: PtzAxis) -> Option<PtzRange> {
+        self.device.ptz_range(axis)
+    }
+
+    pub fn ptz(&self, axis: PtzAxis) -> Option<f32> {
+        self.device.ptz(axis)
+    }
+
+    pub fn set_ptz(&self, axis: PtzAxis, value: f32) -> bool {
+        self.device.set_ptz(axis, value)
+    }
+
+    /// Needs the extended camera controls of the driver and the capture engine.
+    pub fn has_torch(&self) -> bool {
+        self.capture_engine().is_some_and(capture_engine_has_torch)
+    }
+
+    /// Only off and full power, the driver may support adjustable power but Media Foundation
+    /// has no simple way to set it.
+    pub fn set_torch_level(&self, level: f32) -> Result<(), Error> {
+        let Some(engine) = self.capture_engine() else { return Err(Error::Unsupported) };
+        if level != 0.0 && level != 1.0 {
+            return Err(Error::Unsupported);
+        }
+        Ok(capture_engine_set_torch(engine, level == 1.0)?)
+    }
+
+    pub fn device(&self) -> CameraDevice {
+        self.device.camera_device()
+    }
+
+    pub fn set_device(&mut self, device: &CameraDevice) -> Result<(), Error> {
+        if device.id == self.device.id().to_string_lossy().to_string() {
+            return Ok(());
+        }
+        let new_device = Device::enum_devices()
+            .into_iter()
+            .find(|d| d.id().to_string_lossy().to_string() == device.id)
+            .ok_or(Error::NoDevice)?;
+        let (rate_limit, priority) = (self.rate_limit.clone(), self.priority.clone());
+        let backend = match self.engine {
+            Engine::CaptureEngine { .. } => WinBackend::CaptureEngine,
+            Engine::SourceReader(_) => WinBackend::SourceReader,
+        };
+        *self = Self::from_device(new_device, backend, rate_limit, priority, self.memory.clone())?;
+        self.start() // TODO watch out about playing state
+    }
+
+    /// With the `dshow` feature followed by the cameras only DirectShow knows.
     pub fn device_list() -> Vec<CameraDevice> {
+        #[allow(unused_mut)]
+        let mut devices: Vec<_> =
+            Self::enumerate_with_errors().into_iter().filter_map(Result::ok).collect();
+        #[cfg(feature = "dshow")]
+        devices.extend(crate::win_dshow::devices_missing_from(&devices));
+        devices
+    }
+
+    /// Device types only exist on macOS.
+    pub fn device_list_of_types(_types: &[DeviceType]) -> Vec<CameraDevice> {
+        Self::device_list()
+    }
+
+    pub fn enumerate_with_errors() -> Vec<Result<CameraDevice, EnumError>> {
         enum_device_sources()
             .into_iter()
-            .map(Device::new)
-            .map(|d| CameraDevice { id: d.id().to_string_lossy().to_string(), name: d.name() })
+            .map(|activate| {
+                let symlink = &MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE_VIDCAP_SYMBOLIC_LINK;
+                let id = mf_get_string(&activate, symlink).unwrap_or_default();
+                let id = id.to_string_lossy().to_string();
+                match Device::try_new(activate) {
+                    Ok(device) => Ok(device.camera_device()),
+                    Err(err) if err.code() == E_ACCESSDENIED => {
+                        Err(EnumError::PermissionDenied { id })
+                    }
+                    Err(err) => {
+                        Err(EnumError::OpenFailed { id, message: err.message().to_string_lossy() })
+                    }
+                }
+            })
             .collect()
     }
+
+    /// The symbolic link already contains vendor, product and instance of the device.
+    pub fn stable_id(device: &CameraDevice) -> String {
+        device.id.clone()
+    }
+
+    pub fn group_id(device: &CameraDevice) -> String {
+        details::group_from_symbolic_link(&device.id)
+    }
+
+    /// From the symbolic link, the ID of the device.
+    pub fn device_details(device: &CameraDevice) -> DeviceDetails {
+        details::from_symbolic_link(&device.id)
+    }
+
+    pub fn describe_device(device: &CameraDevice) -> DeviceCapabilities {
+        let Some(device) = Device::enum_devices()
+            .into_iter()
+            .find(|d| d.id().to_string_lossy().to_string() == device.id)
+        else {
+            return DeviceCapabilities::default();
+        };
+        let formats = device.query_media_types().iter().map(|mt| mt.capture_format()).collect();
+        DeviceCapabilities { formats }
+    }
+
+    /// The current media type of the source, which the capture engine keeps unless kamera
+    /// sets another one.
+    pub fn default_format(device: &CameraDevice) -> Option<CaptureFormat> {
+        let device = Device::enum_devices()
+            .into_iter()
+            .find(|d| d.id().to_string_lossy().to_string() == device.id)?;
+        device.current_media_type().map(|mt| mt.capture_format())
+    }
+
+    /// Media Foundation is missing from the N editions of Windows without the Media Feature Pack.
+    pub fn backend_capabilities() -> BackendCapabilities {
+        let capture = media_foundation_startup().is_ok();
+        BackendCapabilities { capture, device_types: false, face_metadata: capture, depth: false }
+    }
 }
 
 impl Camera {
-    fn prepare_source_sink(&self) {
-        capture_engine_prepare_sample_callback(&self.engine, &self.sample_cb).unwrap();
+    fn from_device(
+        device: Device,
+        backend: WinBackend,
+        rate_limit: Arc<FrameRateLimit>,
+        priority: Arc<PriorityRequest>,
+        memory: Arc<MemoryBudget>,
+    ) -> Result<Self, Error> {
+        let (event_tx, event_rx) = channel::<(CaptureEngineEvent, HRESULT)>();
+        let (camera_event_tx, camera_event_rx) = channel::<CameraEvent>();
+        let (sample_tx, sample_rx) = channel::<Option<QueuedSample>>();
+        let frame_ready = Arc::new(FrameReadyEvent::new()?);
+        let samples = SampleQueue {
+            sample_tx,
+            frame_ready: frame_ready.clone(),
+            rate_limit: rate_limit.clone(),
+            priority: priority.clone(),
+            memory: memory.clone(),
+        };
+
+        let engine = match backend {
+            WinBackend::CaptureEngine => {
+                let engine = new_capture_engine()?;
+                let event_cb =
+                    CaptureEventCallback { event_tx, camera_event_tx: camera_event_tx.clone() }
+                        .into();
+                init_capture_engine(&engine, Some(&device.source), &event_cb)?;
+                let sample_cb = CaptureSampleCallback(samples).into();
+                Engine::CaptureEngine { engine, event_cb, sample_cb }
+            }
+            WinBackend::SourceReader => {
+                let reader = SourceReader::new(&device.source, samples, camera_event_tx.clone())?;
+                // the format the device has, in RGB32
+                if let Some(media_type) = device.current_media_type() {
+                    reader.set_media_type(&media_type)?;
+                }
+                Engine::SourceReader(reader)
+            }
+        };
+
+        let camera = Camera {
+            engine,
+            device,
+            event_rx,
+            camera_event_tx,
+            camera_event_rx,
+            sample_rx,
+            frame_ready,
+            rate_limit,
+            priority,
+            memory,
+            faces: Default::default(),
+            color_space: Default::default(),
+            latency: Default::default(),
+            previewing: false.into(),
+        };
+        if let Engine::CaptureEngine { engine, sample_cb, .. } = &camera.engine {
+            camera.wait_for_event(CaptureEngineEvent::Initialized);
+            capture_engine_prepare_sample_callback(engine, sample_cb)?;
+        }
+        camera.update_color_space();
+        Ok(camera)
+    }
+
+    fn frame_from_sample(&self, queued: Option<QueuedSample>) -> Option<Frame> {
+        queued
+            .and_then(|(sample, memory)| {
+                let mt = match &self.engine {
+                    Engine::CaptureEngine { engine, .. } => {
+                        capture_engine_sink_get_media_type(engine)
+                    }
+                    Engine::SourceReader(reader) => reader.output_media_type(),
+                };
+                let Some(mt) = mt.ok() else {
+                    return None;
+                };
+                // the size a source reader read the sample with, its output can change meanwhile
+                let size = unsafe { sample.GetUINT64(&MF_MT_FRAME_SIZE) };
+                let (width, height) = size.map_or_else(|_| mt.frame_size(), MediaType::unpack_u64);
+                *lock(&self.faces) = sample_faces(&sample);
+                let buffer = sample_to_locked_buffer(&sample, width, height).ok()?;
+                Some((buffer, mt.fourcc(), sample_device_time(&sample), memory))
+            })
+            .map(|(buffer, fourcc, timestamp, _memory): (LockedBuffer, FourCC, _, _)| {
+                let color_space = *lock(&self.color_space);
+                Frame { buffer, color_space, fourcc, timestamp, _memory }
+            })
+    }
+
+    /// Restarts a running preview with `media_type`.
+    fn set_media_type(&self, media_type: &MediaType) -> bool {
+        let (engine, sample_cb) = match &self.engine {
+            Engine::CaptureEngine { engine, sample_cb, .. } => (engine, sample_cb),
+            Engine::SourceReader(reader) => {
+                let set = reader.set_media_type(media_type).is_ok();
+                // samples of the old size are still queued
+                self.drain_samples();
+                self.update_color_space();
+                return set;
+            }
+        };
+        let running = unsafe { engine.StopPreview() }.is_ok();
+        self.previewing.store(false, Ordering::Relaxed);
+        if running {
+            self.wait_for_event_timeout(CaptureEngineEvent::PreviewStopped);
+        }
+        if capture_engine_set_media_type(engine, media_type, sample_cb).is_err() {
+            return false;
+        }
+        // samples of the old size are still queued
+        self.drain_samples();
+        self.update_color_space();
+        if running {
+            return self.start().is_ok();
+        }
+        true
+    }
+
+    fn drain_samples(&self) {
+        while self.sample_rx.try_recv().is_ok() {
+            self.frame_ready.consumed();
+        }
+    }
+
+    /// Drops the events of earlier starts and stops, which would end the next wait early.
+    fn drain_events(&self) {
+        while self.event_rx.try_recv().is_ok() {}
     }
 
     fn wait_for_event(&self, event: CaptureEngineEvent) {
-        self.event_rx.iter().find(|e| e == &event);
+        self.event_rx.iter().find(|(e, _)| e == &event);
+    }
+
+    /// Whether the event arrived, a failure ends the wait early.
+    fn wait_for_event_timeout(&self, event: CaptureEngineEvent) -> bool {
+        while let Ok((e, status)) = self.event_rx.recv_timeout(Duration::from_secs(3)) {
+            if status.is_err() {
+                return false;
+            }
+            if e == event {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn capture_engine(&self) -> Option<&IMFCaptureEngine> {
+        match &self.engine {
+            Engine::CaptureEngine { engine, .. } => Some(engine),
+            Engine::SourceReader(_) => None,
+        }
+    }
+
+    /// The native media type of the device, e.g. with the YUV matrix.
+    fn source_media_type(&self) -> Option<MediaType> {
+        match &self.engine {
+            Engine::CaptureEngine { engine, .. } => {
+                capture_engine_source_get_media_type(engine).ok()
+            }
+            Engine::SourceReader(_) => self.device.current_media_type(),
+        }
+    }
+
+    fn update_color_space(&self) {
+        let color_space = self.source_media_type().map(|mt| mt.color_space()).unwrap_or_default();
+        *lock(&self.color_space) = color_space;
+    }
+
+    #[cfg(feature = "raw")]
+    pub(crate) fn raw_engine(&self) -> Option<&IMFCaptureEngine> {
+        self.capture_engine()
+    }
+
+    #[cfg(feature = "raw")]
+    pub(crate) fn raw_reader(&self) -> Option<&IMFSourceReader> {
+        match &self.engine {
+            Engine::CaptureEngine { .. } => None,
+            Engine::SourceReader(reader) => Some(reader.raw_reader()),
+        }
+    }
+
+    #[cfg(feature = "raw")]
+    pub(crate) fn raw_source(&self) -> &IMFMediaSource {
+        &self.device.source
+    }
+}
+
+/// Stops the preview, lets go of the sample callback and shuts the media source down, otherwise
+/// the device stays busy until the process ends.
+impl Drop for Camera {
+    fn drop(&mut self) {
+        if let Some(engine) = self.capture_engine() {
+            if capture_engine_stop_preview(engine).is_ok() {
+                self.wait_for_event_timeout(CaptureEngineEvent::PreviewStopped);
+            }
+            let _ = capture_engine_remove_preview_streams(engine);
+        }
+        let _ = unsafe { self.device.source.Shutdown() };
     }
 }
 
 impl Frame {
     pub fn data(&self) -> FrameData {
-        FrameData { data: self.buffer.data() }
+        FrameData {
+            data: self.buffer.data(),
+            stride: self.buffer.stride,
+            size: (self.buffer.width, self.buffer.height),
+        }
     }
 
     pub fn size_u32(&self) -> (u32, u32) {
         (self.buffer.width, self.buffer.height)
     }
+
+    /// The OS delivers BGRA already.
+    pub fn conversion_time(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    pub fn timestamp(&self) -> Option<Duration> {
+        self.timestamp
+    }
+
+    pub fn color_space(&self) -> ColorSpace {
+        self.color_space
+    }
+
+    /// The RGB32 of the preview sink, whatever the device delivers.
+    pub fn fourcc(&self) -> FourCC {
+        self.fourcc
+    }
+
+    /// The buffer stays locked while the frame lives, a crop copies.
+    pub fn share(&self) -> Option<Frame> {
+        None
+    }
 }
 
 impl<'a> FrameData<'a> {
@@ -142,4 +727,17 @@ impl<'a> FrameData<'a> {
         debug_assert!(b.is_empty());
         data
     }
+
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    pub fn plane_count(&self) -> usize {
+        1
+    }
+
+    pub fn plane(&self, index: usize) -> Option<PlaneView> {
+        let (width, height) = self.size;
+        (index == 0).then_some(PlaneView { data: self.data, stride: self.stride, width, height })
+    }
 }
@@ -1,6 +1,7 @@
 use windows::Win32::Media::MediaFoundation::*;
 
 use super::VideoFormat;
+use crate::{CaptureFormat, ColorRange, ColorSpace, FourCC, TransferFunction, YuvMatrix};
 
 #[derive(Debug, Clone)]
 pub struct MediaType(pub IMFMediaType);
@@ -27,8 +28,47 @@ impl MediaType {
         unsafe { self.0.GetUINT64(&MF_MT_FRAME_RATE) }.map(MediaType::unpack_u64).unwrap_or((0, 1))
     }
 
-    pub fn set_rgb32(&mut self) {
-        unsafe { self.0.SetGUID(&MF_MT_SUBTYPE, &MFVideoFormat_RGB32) }.unwrap();
+    /// Copy with the RGB32 subtype, the device media type itself stays untouched.
+    pub fn to_rgb32(&self) -> windows::core::Result<MediaType> {
+        unsafe {
+            let copy = MFCreateMediaType()?;
+            self.0.CopyAllItems(<&IMFAttributes>::from(&copy))?;
+            copy.SetGUID(&MF_MT_SUBTYPE, &MFVideoFormat_RGB32)?;
+            Ok(MediaType(copy))
+        }
+    }
+
+    /// Frame rate range in fps, falls back to the nominal frame rate.
+    pub fn frame_rate_range(&self) -> (f64, f64) {
+        let get = |key| unsafe { self.0.GetUINT64(key) }.map(MediaType::unpack_u64);
+        let fps = |(n, d): (u32, u32)| n as f64 / d.max(1) as f64;
+        let nominal = fps(self.frame_rate());
+        let min = get(&MF_MT_FRAME_RATE_RANGE_MIN).map(fps).unwrap_or(nominal);
+        let max = get(&MF_MT_FRAME_RATE_RANGE_MAX).map(fps).unwrap_or(nominal);
+        (min, max)
+    }
+
+    pub fn subtype_name(&self) -> String {
+        let subtype = unsafe { self.0.GetGUID(&MF_MT_SUBTYPE) }.unwrap_or_default();
+        VideoFormat(subtype).to_string()
+    }
+
+    /// Video subtypes are a FOURCC in the first GUID field, except the RGB ones which carry a
+    /// D3DFORMAT number.
+    pub fn fourcc(&self) -> FourCC {
+        let subtype = unsafe { self.0.GetGUID(&MF_MT_SUBTYPE) }.unwrap_or_default();
+        match subtype.data1 {
+            // D3DFMT_A8R8G8B8 and D3DFMT_X8R8G8B8, bytes are B, G, R and alpha or unused
+            21 => FourCC::new(b"BGRA"),
+            22 => FourCC::new(b"BGRX"),
+            code => FourCC::from_le(code),
+        }
+    }
+
+    pub fn capture_format(&self) -> CaptureFormat {
+        let (width, height) = self.frame_size();
+        let (min_fps, max_fps) = self.frame_rate_range();
+        CaptureFormat { pixel_format: self.subtype_name(), width, height, min_fps, max_fps }
     }
 
     pub fn frame_rate_f32(&self) -> f32 {
@@ -36,7 +76,7 @@ impl MediaType {
         n as f32 / d as f32
     }
 
-    fn unpack_u64(v: u64) -> (u32, u32) {
+    pub(crate) fn unpack_u64(v: u64) -> (u32, u32) {
         ((v >> 32) as _, (v << 32 >> 32) as _)
     }
 
@@ -48,6 +88,29 @@ impl MediaType {
         self.frame_size().1
     }
 
+    #[allow(non_upper_case_globals)]
+    pub fn color_space(&self) -> ColorSpace {
+        let get = |key| unsafe { self.0.GetUINT32(key) }.map(|v| v as i32).unwrap_or(0);
+        let matrix = match MFVideoTransferMatrix(get(&MF_MT_YUV_MATRIX)) {
+            MFVideoTransferMatrix_BT601 => YuvMatrix::Bt601,
+            MFVideoTransferMatrix_BT709 => YuvMatrix::Bt709,
+            MFVideoTransferMatrix_BT2020_10 | MFVideoTransferMatrix_BT2020_12 => YuvMatrix::Bt2020,
+            _ => YuvMatrix::Unknown,
+        };
+        let range = match MFNominalRange(get(&MF_MT_VIDEO_NOMINAL_RANGE)) {
+            MFNominalRange_0_255 => ColorRange::Full,
+            MFNominalRange_16_235 => ColorRange::Limited,
+            _ => ColorRange::Unknown,
+        };
+        let transfer = match MFVideoTransferFunction(get(&MF_MT_TRANSFER_FUNCTION)) {
+            MFVideoTransFunc_sRGB => TransferFunction::Srgb,
+            MFVideoTransFunc_709 => TransferFunction::Bt709,
+            MFVideoTransFunc_2020 => TransferFunction::Bt2020,
+            _ => TransferFunction::Unknown,
+        };
+        ColorSpace { matrix, range, transfer }
+    }
+
     pub fn filter_resolutions_with_max_fps(media_types: &[MediaType]) -> Vec<MediaType> {
         let mut resolutions = std::collections::HashMap::<(u32, u32), MediaType>::new();
         for mt in media_types.iter() {
@@ -1,12 +1,49 @@
-use std::{ffi::OsString, mem::MaybeUninit, sync::mpsc::*};
+use std::{
+    ffi::OsString,
+    mem::MaybeUninit,
+    sync::{atomic::*, mpsc::*, Arc},
+    time::{Duration, Instant},
+};
 
 use windows::{
     core::*,
-    Win32::{Media::MediaFoundation::*, System::Com::*},
+    Win32::{
+        Foundation::{CloseHandle, E_ACCESSDENIED, E_UNEXPECTED, HANDLE},
+        Media::DirectShow::{
+            CameraControl_Exposure, CameraControl_Flags_Manual, CameraControl_Iris,
+            CameraControl_Pan, CameraControl_Tilt, CameraControl_Zoom, IAMCameraControl,
+            IAMVideoProcAmp, VideoProcAmp_BacklightCompensation, VideoProcAmp_Flags_Manual,
+            VideoProcAmp_WhiteBalance,
+        },
+        Media::KernelStreaming::{
+            KSCAMERA_EXTENDEDPROP_FACEDETECTION_PREVIEW, KSCAMERA_EXTENDEDPROP_SCENEMODE_AUTO,
+            KSCAMERA_EXTENDEDPROP_SCENEMODE_NIGHT, KSCAMERA_EXTENDEDPROP_VIDEOHDR_OFF,
+            KSCAMERA_EXTENDEDPROP_VIDEOHDR_ON, KSCAMERA_EXTENDEDPROP_VIDEOTORCH_OFF,
+            KSCAMERA_EXTENDEDPROP_VIDEOTORCH_ON, KSPROPERTY_CAMERACONTROL_EXTENDED_FACEDETECTION,
+            KSPROPERTY_CAMERACONTROL_EXTENDED_PROPERTY,
+            KSPROPERTY_CAMERACONTROL_EXTENDED_SCENEMODE,
+            KSPROPERTY_CAMERACONTROL_EXTENDED_TORCHMODE,
+            KSPROPERTY_CAMERACONTROL_EXTENDED_VIDEOHDR,
+        },
+        Media::MediaFoundation::*,
+        System::{
+            Com::*,
+            Performance::{QueryPerformanceCounter, QueryPerformanceFrequency},
+            Threading::*,
+        },
+    },
 };
 
 use super::attributes::{mf_create_attributes, mf_get_string};
 use super::media_type::MediaType;
+use crate::device_policy::Placement;
+use crate::memory::{MemoryBudget, Reservation};
+use crate::priority::PriorityRequest;
+use crate::rate_limit::FrameRateLimit;
+use crate::{
+    CameraDevice, CameraEvent, DeviceKind, Enhancement, Error as CameraError, FaceRect, PtzAxis,
+    PtzRange,
+};
 
 #[derive(Clone, Debug)]
 pub struct Device {
@@ -15,10 +52,10 @@ pub struct Device {
 }
 
 impl Device {
-    pub(crate) fn new(activate: IMFActivate) -> Self {
+    pub(crate) fn try_new(activate: IMFActivate) -> Result<Self> {
         co_initialize_multithreaded();
-        let source = unsafe { activate.ActivateObject().unwrap() };
-        Self { activate, source }
+        let source = unsafe { activate.ActivateObject()? };
+        Ok(Self { activate, source })
     }
 }
 
@@ -53,16 +90,152 @@ impl Device {
         mf_get_string(&self.activate, symlink).unwrap_or_else(|_| "NO ID".into())
     }
 
+    /// Software sources are virtual cameras, e.g. registered with MFCreateVirtualCamera.
+    pub fn kind(&self) -> DeviceKind {
+        let hw_source = &MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE_VIDCAP_HW_SOURCE;
+        match unsafe { self.activate.GetUINT32(hw_source) } {
+            Ok(0) => DeviceKind::Virtual,
+            Ok(_) => DeviceKind::Physical,
+            Err(_) => {
+                let id = self.id().to_string_lossy().to_lowercase();
+                if id.starts_with(r"\\?\usb#") || id.starts_with(r"\\?\pci#") {
+                    DeviceKind::Physical
+                } else if id.starts_with(r"\\?\swd#") {
+                    DeviceKind::Virtual
+                } else {
+                    DeviceKind::Unknown
+                }
+            }
+        }
+    }
+
+    /// Media Foundation doesn't tell where a USB camera is, but cameras on other buses are the
+    /// sensors of laptops and tablets.
+    pub fn placement(&self) -> Placement {
+        let id = self.id().to_string_lossy().to_lowercase();
+        match self.kind() {
+            DeviceKind::Physical if !id.starts_with(r"\\?\usb#") => Placement::BuiltIn,
+            _ => Placement::Unknown,
+        }
+    }
+
+    pub fn camera_device(&self) -> CameraDevice {
+        CameraDevice {
+            id: self.id().to_string_lossy().to_string(),
+            name: self.name(),
+            kind: self.kind(),
+        }
+    }
+
+    /// Minimum and maximum level, `None` if the device has no backlight compensation.
+    pub fn backlight_compensation_range(&self) -> Option<(i32, i32)> {
+        let proc_amp: IAMVideoProcAmp = self.source.cast().ok()?;
+        let (mut min, mut max, mut step, mut default, mut caps) = (0, 0, 0, 0, 0);
+        let property = VideoProcAmp_BacklightCompensation.0;
+        let result = unsafe {
+            proc_amp.GetRange(property, &mut min, &mut max, &mut step, &mut default, &mut caps)
+        };
+        result.ok()?;
+        Some((min, max))
+    }
+
+    pub fn set_backlight_compensation(&self, enabled: bool) -> bool {
+        let Some((min, max)) = self.backlight_compensation_range() else { return false };
+        let Ok(proc_amp) = self.source.cast::<IAMVideoProcAmp>() else { return false };
+        let level = if enabled { max } else { min };
+        let property = VideoProcAmp_BacklightCompensation.0;
+        unsafe { proc_amp.Set(property, level, VideoProcAmp_Flags_Manual.0) }.is_ok()
+    }
+
+    /// DirectShow reports exposure as log2 seconds, e.g. -5 for 1/32 s.
+    pub fn exposure_time(&self) -> Option<Duration> {
+        let value = self.camera_control(CameraControl_Exposure.0)?;
+        Some(Duration::from_secs_f64(2f64.powi(value)))
+    }
+
+    /// Iris in units of f-stop * 10.
+    pub fn lens_aperture(&self) -> Option<f32> {
+        self.camera_control(CameraControl_Iris.0).map(|value| value as f32 / 10.0)
+    }
+
+    /// Color temperature in Kelvin.
+    pub fn white_balance(&self) -> Option<u32> {
+        let proc_amp: IAMVideoProcAmp = self.source.cast().ok()?;
+        let (mut value, mut flags) = (0, 0);
+        let property = VideoProcAmp_WhiteBalance.0;
+        unsafe { proc_amp.Get(property, &mut value, &mut flags) }.ok()?;
+        u32::try_from(value).ok()
+    }
+
+    /// DirectShow reports pan and tilt in degrees and zoom in millimeters of focal length.
+    pub fn ptz_range(&self, axis: PtzAxis) -> Option<PtzRange> {
+        let camera_control: IAMCameraControl = self.source.cast().ok()?;
+        let (mut min, mut max, mut step, mut default, mut caps) = (0, 0, 0, 0, 0);
+        let property = ptz_property(axis);
+        let result = unsafe {
+            camera_control.GetRange(
+                property,
+                &mut min,
+                &mut max,
+                &mut step,
+                &mut default,
+                &mut caps,
+            )
+        };
+        result.ok()?;
+        // a range without any room to move is what some drivers report for missing controls
+        (min < max).then(|| PtzRange {
+            min: min as f32,
+            max: max as f32,
+            step: step as f32,
+            default: default as f32,
+        })
+    }
+
+    pub fn ptz(&self, axis: PtzAxis) -> Option<f32> {
+        self.camera_control(ptz_property(axis)).map(|value| value as f32)
+    }
+
+    pub fn set_ptz(&self, axis: PtzAxis, value: f32) -> bool {
+        let Ok(camera_control) = self.source.cast::<IAMCameraControl>() else { return false };
+        let (property, value) = (ptz_property(axis), value.round() as i32);
+        unsafe { camera_control.Set(property, value, CameraControl_Flags_Manual.0) }.is_ok()
+    }
+
+    fn camera_control(&self, property: i32) -> Option<i32> {
+        let camera_control: IAMCameraControl = self.source.cast().ok()?;
+        let (mut value, mut flags) = (0, 0);
+        unsafe { camera_control.Get(property, &mut value, &mut flags) }.ok()?;
+        Some(value)
+    }
+
     pub fn query_media_types(&self) -> Vec<MediaType> {
         query_media_types_from_media_source(&self.source)
     }
 
+    /// The first media type if the source has no current one yet.
+    pub fn current_media_type(&self) -> Option<MediaType> {
+        let handler = media_type_handler(&self.source)?;
+        unsafe { handler.GetCurrentMediaType().or_else(|_| handler.GetMediaTypeByIndex(0)) }
+            .ok()
+            .map(MediaType)
+    }
+
     pub fn query_media_types_with_best_fps(&self) -> Vec<MediaType> {
         MediaType::filter_resolutions_with_max_fps(&self.query_media_types())
     }
 
+    /// Skips devices which can't be activated.
     pub fn enum_devices() -> Vec<Device> {
-        enum_device_sources().into_iter().map(Device::new).collect()
+        enum_device_sources().into_iter().filter_map(|a| Device::try_new(a).ok()).collect()
+    }
+}
+
+fn ptz_property(axis: PtzAxis) -> i32 {
+    match axis {
+        PtzAxis::Pan => CameraControl_Pan.0,
+        PtzAxis::Tilt => CameraControl_Tilt.0,
+        PtzAxis::Zoom => CameraControl_Zoom.0,
     }
 }
 
@@ -74,8 +247,11 @@ pub(crate) fn enum_device_sources() -> Vec<IMFActivate> {
         let mut count: u32 = 0;
         let mut activates: MaybeUninit<*mut Option<IMFActivate>> = MaybeUninit::uninit();
         let attributes = mf_create_attributes();
-        attributes.SetGUID(source_type, vidcap_guid).unwrap();
-        MFEnumDeviceSources(&attributes, activates.as_mut_ptr(), &mut count).unwrap();
+        if attributes.SetGUID(source_type, vidcap_guid).is_err()
+            || MFEnumDeviceSources(&attributes, activates.as_mut_ptr(), &mut count).is_err()
+        {
+            return Vec::new();
+        }
 
         let activates = std::slice::from_raw_parts(activates.assume_init(), count as usize);
         let devices: Vec<_> = activates.iter().filter_map(|o| o.clone()).collect();
@@ -85,19 +261,23 @@ pub(crate) fn enum_device_sources() -> Vec<IMFActivate> {
     }
 }
 
-fn query_media_types_from_media_source(media_source: &IMFMediaSource) -> Vec<MediaType> {
+/// Of the first stream of `media_source`.
+fn media_type_handler(media_source: &IMFMediaSource) -> Option<IMFMediaTypeHandler> {
     unsafe {
-        let desc = media_source.CreatePresentationDescriptor().unwrap();
-
+        let desc = media_source.CreatePresentationDescriptor().ok()?;
         let mut selected = false.into();
         let mut descriptor = None;
-        desc.GetStreamDescriptorByIndex(0, &mut selected, &mut descriptor).unwrap();
-
-        let desc = descriptor.unwrap();
-        let mt_handler = desc.GetMediaTypeHandler().unwrap();
+        desc.GetStreamDescriptorByIndex(0, &mut selected, &mut descriptor).ok()?;
+        descriptor?.GetMediaTypeHandler().ok()
+    }
+}
 
-        let n = mt_handler.GetMediaTypeCount().unwrap();
-        (0..n).map(|index| MediaType(mt_handler.GetMediaTypeByIndex(index).unwrap())).collect()
+fn query_media_types_from_media_source(media_source: &IMFMediaSource) -> Vec<MediaType> {
+    let Some(mt_handler) = media_type_handler(media_source) else { return Vec::new() };
+    unsafe {
+        let n = mt_handler.GetMediaTypeCount().unwrap_or(0);
+        let media_types = (0..n).filter_map(|index| mt_handler.GetMediaTypeByIndex(index).ok());
+        media_types.map(MediaType).collect()
     }
 }
 
@@ -110,31 +290,20 @@ pub(crate) fn media_foundation_shutdown() -> Result<()> {
     unsafe { MFShutdown() }
 }
 
-// TODO use and fix it
-pub(crate) fn _capture_engine_change_media_type(
+/// Switches the device to `media_type` and replaces the preview stream with an RGB32 stream of
+/// the same size. The preview has to be stopped.
+pub(crate) fn capture_engine_set_media_type(
     engine: &IMFCaptureEngine,
     media_type: &MediaType,
+    sample_cb: &IMFCaptureEngineOnSampleCallback,
 ) -> Result<()> {
     unsafe {
-        let source = engine.GetSource()?;
-        let sink = engine.GetSink(MF_CAPTURE_ENGINE_SINK_TYPE_PREVIEW)?;
-        let sink: IMFCapturePreviewSink = sink.cast()?;
-        engine.StopPreview()?;
-
-        source.SetCurrentDeviceMediaType(0, &media_type.0)?;
+        engine.GetSource()?.SetCurrentDeviceMediaType(0, &media_type.0)?;
+        let sink: IMFCapturePreviewSink =
+            engine.GetSink(MF_CAPTURE_ENGINE_SINK_TYPE_PREVIEW)?.cast()?;
         sink.RemoveAllStreams()?;
-        let mut rgb_media_type = media_type.clone();
-        rgb_media_type.set_rgb32();
-        let stream_index = sink.AddStream(0, Some(&media_type.0), None)?;
-
-        // TODO maybe changing the sample callback is not necessary when the stream_index is the same?
-        let (sample_tx, _sample_rx) = channel();
-        let sample_cb = CaptureSampleCallback { sample_tx }.into();
-        sink.SetSampleCallback(stream_index, Some(&sample_cb))?;
-
-        engine.StartPreview()?;
     }
-    Ok(())
+    capture_engine_prepare_sample_callback(engine, sample_cb)
 }
 
 pub(crate) fn new_capture_engine() -> Result<IMFCaptureEngine> {
@@ -173,109 +342,343 @@ pub(crate) fn capture_engine_prepare_sample_callback(
     sample_cb: &IMFCaptureEngineOnSampleCallback,
 ) -> Result<()> {
     unsafe {
-        let source = capture_engine.GetSource().expect("GetSource");
-        let media_type = source.GetCurrentDeviceMediaType(0).expect("GetCurrentDeviceMediaType");
-        let sink = capture_engine.GetSink(MF_CAPTURE_ENGINE_SINK_TYPE_PREVIEW).expect("GetSink");
-        let preview_sink: IMFCapturePreviewSink = sink.cast().expect("CapturePreviewSink");
-        let mut rgb_media_type = MediaType(media_type);
-        rgb_media_type.set_rgb32();
-        let stream_index =
-            preview_sink.AddStream(0, Some(&rgb_media_type.0), None).expect("AddStream");
+        let source = capture_engine.GetSource()?;
+        let media_type = source.GetCurrentDeviceMediaType(0)?;
+        let sink = capture_engine.GetSink(MF_CAPTURE_ENGINE_SINK_TYPE_PREVIEW)?;
+        let preview_sink: IMFCapturePreviewSink = sink.cast()?;
+        let rgb_media_type = MediaType(media_type).to_rgb32()?;
+        let stream_index = preview_sink.AddStream(0, Some(&rgb_media_type.0), None)?;
         // let stream_index = preview_sink.AddStream(0, None, None).expect("AddStream");
 
-        preview_sink.SetSampleCallback(stream_index, Some(sample_cb)).expect("SetSampleCallback");
+        preview_sink.SetSampleCallback(stream_index, Some(sample_cb))
     }
-    Ok(())
 }
 
-pub fn capture_engine_sink_get_media_type(capture_engine: &IMFCaptureEngine) -> Result<MediaType> {
+pub(crate) fn capture_engine_sink_get_media_type(
+    capture_engine: &IMFCaptureEngine,
+) -> Result<MediaType> {
     Ok(MediaType(unsafe {
         capture_engine.GetSink(MF_CAPTURE_ENGINE_SINK_TYPE_PREVIEW)?.GetOutputMediaType(0)?
     }))
 }
 
+/// The source media type still describes the native format, e.g. the YUV matrix.
+pub(crate) fn capture_engine_source_get_media_type(
+    capture_engine: &IMFCaptureEngine,
+) -> Result<MediaType> {
+    Ok(MediaType(unsafe { capture_engine.GetSource()?.GetCurrentDeviceMediaType(0)? }))
+}
+
+pub(crate) fn media_source_set_low_latency(source: &IMFMediaSource, low: bool) -> Result<()> {
+    unsafe {
+        let attributes = source.cast::<IMFMediaSourceEx>()?.GetSourceAttributes()?;
+        attributes.SetUINT32(&MF_LOW_LATENCY, low as u32)
+    }
+}
+
 pub(crate) fn capture_engine_stop_preview(capture_engine: &IMFCaptureEngine) -> Result<()> {
     unsafe { capture_engine.StopPreview() }
 }
 
-pub fn sample_to_locked_buffer(
+/// Also releases the sample callback of the streams.
+pub(crate) fn capture_engine_remove_preview_streams(
+    capture_engine: &IMFCaptureEngine,
+) -> Result<()> {
+    unsafe {
+        let sink: IMFCapturePreviewSink =
+            capture_engine.GetSink(MF_CAPTURE_ENGINE_SINK_TYPE_PREVIEW)?.cast()?;
+        sink.RemoveAllStreams()
+    }
+}
+
+fn capture_engine_extended_control(
+    capture_engine: &IMFCaptureEngine,
+    property: KSPROPERTY_CAMERACONTROL_EXTENDED_PROPERTY,
+) -> Result<IMFExtendedCameraControl> {
+    unsafe {
+        let source = capture_engine.GetSource()?;
+        let controller: IMFExtendedCameraController =
+            source.GetService(&GUID::zeroed(), &IMFExtendedCameraController::IID)?.cast()?;
+        controller.GetExtendedCameraControl(MF_CAPTURE_ENGINE_MEDIASOURCE.0, property.0 as u32)
+    }
+}
+
+pub(crate) fn capture_engine_has_torch(capture_engine: &IMFCaptureEngine) -> bool {
+    let property = KSPROPERTY_CAMERACONTROL_EXTENDED_TORCHMODE;
+    capture_engine_extended_control(capture_engine, property).is_ok_and(
+        |control| unsafe { control.GetCapabilities() } & KSCAMERA_EXTENDEDPROP_VIDEOTORCH_ON != 0,
+    )
+}
+
+pub(crate) fn capture_engine_set_torch(capture_engine: &IMFCaptureEngine, on: bool) -> Result<()> {
+    let property = KSPROPERTY_CAMERACONTROL_EXTENDED_TORCHMODE;
+    let control = capture_engine_extended_control(capture_engine, property)?;
+    let flags =
+        if on { KSCAMERA_EXTENDEDPROP_VIDEOTORCH_ON } else { KSCAMERA_EXTENDEDPROP_VIDEOTORCH_OFF };
+    unsafe {
+        control.SetFlags(flags)?;
+        control.CommitSettings()
+    }
+}
+
+/// The extended property of `enhancement` with its flags for on and off. Drivers have no
+/// low light mode for video, the night scene mode comes closest.
+fn enhancement_property(
+    enhancement: Enhancement,
+) -> Option<(KSPROPERTY_CAMERACONTROL_EXTENDED_PROPERTY, u64, u64)> {
+    match enhancement {
+        Enhancement::Hdr => Some((
+            KSPROPERTY_CAMERACONTROL_EXTENDED_VIDEOHDR,
+            KSCAMERA_EXTENDEDPROP_VIDEOHDR_ON,
+            KSCAMERA_EXTENDEDPROP_VIDEOHDR_OFF,
+        )),
+        Enhancement::LowLightBoost => Some((
+            KSPROPERTY_CAMERACONTROL_EXTENDED_SCENEMODE,
+            KSCAMERA_EXTENDEDPROP_SCENEMODE_NIGHT,
+            KSCAMERA_EXTENDEDPROP_SCENEMODE_AUTO,
+        )),
+        Enhancement::BacklightCompensation => None,
+    }
+}
+
+pub(crate) fn capture_engine_has_enhancement(
+    capture_engine: &IMFCaptureEngine,
+    enhancement: Enhancement,
+) -> bool {
+    let Some((property, on, _)) = enhancement_property(enhancement) else { return false };
+    capture_engine_extended_control(capture_engine, property)
+        .is_ok_and(|control| unsafe { control.GetCapabilities() } & on != 0)
+}
+
+pub(crate) fn capture_engine_set_enhancement(
+    capture_engine: &IMFCaptureEngine,
+    enhancement: Enhancement,
+    enabled: bool,
+) -> Result<()> {
+    let Some((property, on, off)) = enhancement_property(enhancement) else {
+        return Err(MF_E_UNSUPPORTED_SERVICE.into());
+    };
+    let control = capture_engine_extended_control(capture_engine, property)?;
+    unsafe {
+        if control.GetCapabilities() & on == 0 {
+            return Err(MF_E_UNSUPPORTED_SERVICE.into());
+        }
+        control.SetFlags(if enabled { on } else { off })?;
+        control.CommitSettings()
+    }
+}
+
+/// Turns on face detection of the driver for the preview stream, an error if it can't.
+pub(crate) fn capture_engine_enable_face_detection(
+    capture_engine: &IMFCaptureEngine,
+) -> Result<()> {
+    let property = KSPROPERTY_CAMERACONTROL_EXTENDED_FACEDETECTION;
+    let control = capture_engine_extended_control(capture_engine, property)?;
+    unsafe {
+        if control.GetCapabilities() & KSCAMERA_EXTENDEDPROP_FACEDETECTION_PREVIEW == 0 {
+            return Err(MF_E_UNSUPPORTED_SERVICE.into());
+        }
+        control.SetFlags(KSCAMERA_EXTENDEDPROP_FACEDETECTION_PREVIEW)?;
+        control.CommitSettings()
+    }
+}
+
+/// The faces in the capture metadata of `sample`, empty without face detection.
+pub(crate) fn sample_faces(sample: &IMFSample) -> Vec<FaceRect> {
+    let blob = unsafe {
+        let Ok(metadata) = sample.GetUnknown::<IMFAttributes>(&MFSampleExtension_CaptureMetadata)
+        else {
+            return Vec::new();
+        };
+        let Ok(size) = metadata.GetBlobSize(&MF_CAPTURE_METADATA_FACEROIS) else {
+            return Vec::new();
+        };
+        let mut blob = vec![0; size as usize];
+        if metadata.GetBlob(&MF_CAPTURE_METADATA_FACEROIS, &mut blob, None).is_err() {
+            return Vec::new();
+        }
+        blob
+    };
+    parse_face_rois(&blob)
+}
+
+/// The capture time of `sample` on the QPC clock, in 100ns units as the driver attached it.
+pub(crate) fn sample_device_time(sample: &IMFSample) -> Option<Duration> {
+    let hundred_nanos = unsafe { sample.GetUINT64(&MFSampleExtension_DeviceTimestamp) }.ok()?;
+    Some(Duration::from_nanos(hundred_nanos * 100))
+}
+
+/// Now on the QPC clock.
+pub(crate) fn qpc_time() -> Option<Duration> {
+    let (mut counter, mut frequency) = (0, 0);
+    unsafe {
+        QueryPerformanceCounter(&mut counter).ok().ok()?;
+        QueryPerformanceFrequency(&mut frequency).ok().ok()?;
+    }
+    let nanos = counter as u128 * 1_000_000_000 / frequency.max(1) as u128;
+    Some(Duration::from_nanos(nanos as u64))
+}
+
+/// A FaceRectInfoBlobHeader of size and count followed by FaceRectInfo entries of a RECT in
+/// Q31 fixed point and a confidence level.
+pub(crate) fn parse_face_rois(blob: &[u8]) -> Vec<FaceRect> {
+    const HEADER: usize = 8;
+    const ENTRY: usize = 20;
+    let field = |offset: usize| {
+        let bytes = blob.get(offset..offset + 4).and_then(|b| b.try_into().ok());
+        bytes.map(i32::from_ne_bytes)
+    };
+    let q31 = |value: i32| (value as f64 / (1u64 << 31) as f64) as f32;
+    let Some(count) = field(4) else { return Vec::new() };
+    (0..count.max(0) as usize)
+        .map_while(|i| {
+            let entry = HEADER + i * ENTRY;
+            let (left, top) = (field(entry)?, field(entry + 4)?);
+            let (right, bottom) = (field(entry + 8)?, field(entry + 12)?);
+            Some(FaceRect {
+                x: q31(left),
+                y: q31(top),
+                width: q31(right) - q31(left),
+                height: q31(bottom) - q31(top),
+                id: None,
+            })
+        })
+        .collect()
+}
+
+/// Locks the only buffer of `sample` in place, which is what cameras deliver. Samples of several
+/// buffers, or buffers without 2D locking, are copied into a contiguous buffer first, with rows
+/// of `width` pixels.
+pub(crate) fn sample_to_locked_buffer(
     sample: &IMFSample,
     width: u32,
     height: u32,
 ) -> Result<LockedBuffer> {
+    let in_place = unsafe {
+        match sample.GetBufferCount()? {
+            1 => sample.GetBufferByIndex(0)?.cast::<IMF2DBuffer2>().ok(),
+            _ => None,
+        }
+    };
+    if let Some(buffer) = in_place {
+        if let Ok((scanline0, pitch, available)) = lock_2d(&buffer) {
+            // a negative pitch starts at the bottom row, so it doesn't fit and the copy is used
+            let stride = pitch.unsigned_abs() as usize;
+            let len = stride * height as usize;
+            // rows which don't fit the buffer, e.g. of a size from before a media type change
+            if stride >= width as usize * 4 && len <= available {
+                return Ok(LockedBuffer {
+                    buffer: Arc::new(BufferLock::TwoD(buffer)),
+                    width,
+                    height,
+                    scanline0,
+                    stride,
+                    len,
+                });
+            }
+            unsafe { buffer.Unlock2D()? };
+        }
+    }
+    let buffer = unsafe { sample.ConvertToContiguousBuffer()? };
+    let (scanline0, len) = lock_contiguous(&buffer)?;
+    let stride = width as usize * 4;
+    if len < stride * height as usize {
+        unsafe { buffer.Unlock()? };
+        return Err(E_UNEXPECTED.into());
+    }
+    Ok(LockedBuffer {
+        buffer: Arc::new(BufferLock::Contiguous(buffer)),
+        width,
+        height,
+        scanline0,
+        stride,
+        len: stride * height as usize,
+    })
+}
+
+/// The first row, the pitch and the bytes from the first row to the end of the buffer.
+fn lock_2d(buffer: &IMF2DBuffer2) -> Result<(*mut u8, i32, usize)> {
+    let mut scanline0 = std::ptr::null_mut();
+    let mut pitch = 0;
+    let mut buffer_start = std::ptr::null_mut();
+    let mut buffer_length = 0;
     unsafe {
-        let media_buffer = sample.ConvertToContiguousBuffer()?;
-        let mf2d_buffer: IMF2DBuffer2 = windows::core::Interface::cast(&media_buffer)?;
-
-        let mut scanline0 = std::ptr::null_mut();
-        let mut pitch = 0;
-        let mut buffer_start = std::ptr::null_mut();
-        let mut buffer_length: u32 = 0;
-        mf2d_buffer.Lock2DSize(
+        buffer.Lock2DSize(
             MF2DBuffer_LockFlags_Read,
             &mut scanline0,
             &mut pitch,
             &mut buffer_start,
             &mut buffer_length,
-        )?;
+        )?
+    };
+    let offset = (scanline0 as usize).saturating_sub(buffer_start as usize);
+    Ok((scanline0, pitch, (buffer_length as usize).saturating_sub(offset)))
+}
 
-        Ok(LockedBuffer {
-            buffer: mf2d_buffer,
-            width,
-            height,
-            scanline0,
-            // negative pitch means image is upside down. ignore for now to avoid crash.
-            len: pitch.abs() as usize * height as usize,
-        })
-    }
+fn lock_contiguous(buffer: &IMFMediaBuffer) -> Result<(*mut u8, usize)> {
+    let mut data = std::ptr::null_mut();
+    let mut len = 0;
+    unsafe { buffer.Lock(&mut data, None, Some(&mut len))? };
+    Ok((data, len as usize))
 }
 
-#[derive(Debug)]
+/// A sample's buffer, locked for reading until it and all its clones are dropped.
+#[derive(Debug, Clone)]
 pub struct LockedBuffer {
-    buffer: IMF2DBuffer2,
+    buffer: Arc<BufferLock>,
     pub(crate) width: u32,
     pub(crate) height: u32,
     scanline0: *mut u8,
+    pub(crate) stride: usize,
     len: usize,
 }
 
+#[derive(Debug)]
+enum BufferLock {
+    /// The sample's own buffer.
+    TwoD(IMF2DBuffer2),
+    /// A copy of the sample's buffers.
+    Contiguous(IMFMediaBuffer),
+}
+
 impl LockedBuffer {
-    pub(crate) fn data(&self) -> &[u8] {
+    /// The BGRA rows from the top one on, [`LockedBuffer::stride`] bytes apart.
+    pub fn data(&self) -> &[u8] {
         unsafe { std::slice::from_raw_parts(self.scanline0, self.len) }
     }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Bytes per row, the pitch of the 2D buffer, which drivers often pad beyond `width * 4`.
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Whether the buffer had to be copied, because the sample had several buffers or its
+    /// buffer can't be locked in 2D.
+    pub fn is_copy(&self) -> bool {
+        matches!(*self.buffer, BufferLock::Contiguous(_))
+    }
 }
 
-impl Drop for LockedBuffer {
-    fn drop(&mut self) {
-        unsafe { self.buffer.Unlock2D().expect("Unlock2D") };
-    }
-}
-
-impl Clone for LockedBuffer {
-    fn clone(&self) -> Self {
-        unsafe {
-            let mut scanline0 = std::ptr::null_mut();
-            let mut pitch = 0;
-            let mut buffer_start = std::ptr::null_mut();
-            let mut buffer_length = 0;
-            self.buffer
-                .Lock2DSize(
-                    MF2DBuffer_LockFlags_Read,
-                    &mut scanline0,
-                    &mut pitch,
-                    &mut buffer_start,
-                    &mut buffer_length,
-                )
-                .unwrap();
-        }
+// Media Foundation buffers are free threaded and the buffer is only locked for reading.
+unsafe impl Send for LockedBuffer {}
+unsafe impl Sync for LockedBuffer {}
 
-        Self {
-            buffer: self.buffer.clone(),
-            width: self.width,
-            height: self.height,
-            scanline0: self.scanline0,
-            len: self.len,
-        }
+/// Clones share the lock, the last one to go undoes it.
+impl Drop for BufferLock {
+    fn drop(&mut self) {
+        // a buffer which fails to unlock is released with its sample anyway
+        let _ = match self {
+            BufferLock::TwoD(buffer) => unsafe { buffer.Unlock2D() },
+            BufferLock::Contiguous(buffer) => unsafe { buffer.Unlock() },
+        };
     }
 }
 
@@ -296,17 +699,20 @@ fn capture_source_collect_available_device_media_types(
 impl IMFCaptureEngineOnEventCallback_Impl for CaptureEventCallback {
     fn OnEvent(&self, event: &Option<IMFMediaEvent>) -> windows::core::Result<()> {
         let Some(event) = event else { return Ok(()) };
-        let guid = unsafe { event.GetExtendedType().unwrap() };
-        let status = unsafe { event.GetStatus().unwrap() };
+        let guid = unsafe { event.GetExtendedType()? };
+        let status = unsafe { event.GetStatus()? };
         let engine_event = CaptureEngineEvent::from(&guid);
-        let time = std::time::UNIX_EPOCH.elapsed().unwrap().as_millis();
+        let time = std::time::UNIX_EPOCH.elapsed().unwrap_or_default().as_millis();
         println!(
             "Event {engine_event:?} {:x} {} {}",
             status.0,
             status.message().to_string_lossy(),
             time
         );
-        let _ = self.event_tx.send(engine_event);
+        if let Some(camera_event) = camera_event(&engine_event, status) {
+            let _ = self.camera_event_tx.send(camera_event);
+        }
+        let _ = self.event_tx.send((engine_event, status));
         Ok(())
     }
 }
@@ -320,11 +726,34 @@ impl IMFCaptureEngineOnSampleCallback_Impl for CaptureSampleCallback {
         //     let time = std::time::UNIX_EPOCH.elapsed().unwrap().as_millis();
         //     println!("Sample {len} {time_ms} {time}");
         // };
-        self.sample_tx.send(sample.clone()).unwrap();
+        self.0.push(sample);
         Ok(())
     }
 }
 
+impl SampleQueue {
+    /// Called on the thread of Media Foundation which delivers the sample.
+    pub(crate) fn push(&self, sample: &Option<IMFSample>) {
+        self.priority.follow();
+        if sample.is_some() && !self.rate_limit.accept(Instant::now()) {
+            return;
+        }
+        let queued = match sample {
+            Some(sample) => {
+                let len = unsafe { sample.GetTotalLength() }.unwrap_or(0) as usize;
+                // the application fell behind by more than the memory limit
+                let Some(reservation) = self.memory.try_reserve(len) else { return };
+                Some((sample.clone(), reservation))
+            }
+            None => None,
+        };
+        self.frame_ready.produced();
+        // fails after the camera is gone
+        let _ = self.sample_tx.send(queued);
+        self.frame_ready.signal();
+    }
+}
+
 #[derive(PartialEq, Eq, Debug)]
 enum StreamCategory {
     VideoPreview = 0,
@@ -397,14 +826,99 @@ impl From<&GUID> for CaptureEngineEvent {
     }
 }
 
+pub(crate) fn camera_event(event: &CaptureEngineEvent, status: HRESULT) -> Option<CameraEvent> {
+    match (event, status) {
+        (CaptureEngineEvent::CameraStreamBlocked, _) => Some(CameraEvent::StreamBlocked),
+        (CaptureEngineEvent::CameraStreamUnblocked, _) => Some(CameraEvent::StreamUnblocked),
+        (_, status) if status.is_ok() => None,
+        (_, MF_E_VIDEO_RECORDING_DEVICE_INVALIDATED) => Some(CameraEvent::DeviceLost),
+        (_, E_ACCESSDENIED) => Some(CameraEvent::AccessDenied),
+        (_, MF_E_VIDEO_RECORDING_DEVICE_PREEMPTED | MF_E_HW_MFT_FAILED_START_STREAMING) => {
+            Some(CameraEvent::InUseByOtherApp)
+        }
+        (_, status) => {
+            Some(CameraEvent::Error { code: status.0, message: status.message().to_string_lossy() })
+        }
+    }
+}
+
+pub(crate) fn hresult_error(status: HRESULT) -> CameraError {
+    windows::core::Error::from(status).into()
+}
+
+impl From<windows::core::Error> for CameraError {
+    fn from(err: windows::core::Error) -> Self {
+        match err.code() {
+            MF_E_VIDEO_RECORDING_DEVICE_PREEMPTED | MF_E_HW_MFT_FAILED_START_STREAMING => {
+                CameraError::InUseByOtherApp
+            }
+            _ => CameraError::os(err),
+        }
+    }
+}
+
 #[implement(IMFCaptureEngineOnEventCallback)]
 pub(crate) struct CaptureEventCallback {
-    pub event_tx: Sender<CaptureEngineEvent>,
+    pub event_tx: Sender<(CaptureEngineEvent, HRESULT)>,
+    pub camera_event_tx: Sender<CameraEvent>,
 }
 
 #[implement(IMFCaptureEngineOnSampleCallback)]
-pub(crate) struct CaptureSampleCallback {
-    pub sample_tx: Sender<Option<IMFSample>>,
+pub(crate) struct CaptureSampleCallback(pub SampleQueue);
+
+/// Where the callbacks of the capture engine and the source reader send their samples.
+pub(crate) struct SampleQueue {
+    pub sample_tx: Sender<Option<QueuedSample>>,
+    pub frame_ready: Arc<FrameReadyEvent>,
+    pub rate_limit: Arc<FrameRateLimit>,
+    pub priority: Arc<PriorityRequest>,
+    pub memory: Arc<MemoryBudget>,
+}
+
+/// A sample with its part of the memory limit, which the frame keeps until it is dropped.
+pub(crate) type QueuedSample = (IMFSample, Reservation);
+
+/// Manual reset event which stays signaled as long as samples are queued in the sample channel.
+#[derive(Debug)]
+pub(crate) struct FrameReadyEvent {
+    handle: HANDLE,
+    pending: AtomicUsize,
+}
+
+impl FrameReadyEvent {
+    pub(crate) fn new() -> Result<Self> {
+        let handle = unsafe { CreateEventW(None, true, false, PCWSTR::null())? };
+        Ok(Self { handle, pending: AtomicUsize::new(0) })
+    }
+
+    pub(crate) fn handle(&self) -> HANDLE {
+        self.handle
+    }
+
+    /// Call before sending a sample, so the consumer never sees a sample which is not counted.
+    fn produced(&self) {
+        self.pending.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn signal(&self) {
+        unsafe { SetEvent(self.handle) };
+    }
+
+    pub(crate) fn consumed(&self) {
+        if self.pending.fetch_sub(1, Ordering::SeqCst) == 1 {
+            unsafe { ResetEvent(self.handle) };
+            // a sample may have been produced between the decrement and the reset
+            if self.pending.load(Ordering::SeqCst) > 0 {
+                self.signal();
+            }
+        }
+    }
+}
+
+impl Drop for FrameReadyEvent {
+    fn drop(&mut self) {
+        unsafe { CloseHandle(self.handle) };
+    }
 }
 
 pub fn co_initialize_multithreaded() {
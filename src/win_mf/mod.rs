@@ -1,12 +1,14 @@
 mod attributes;
 mod camera;
 mod media_type;
-pub mod mf;
+pub(crate) mod mf;
+#[cfg(feature = "raw")]
+pub mod raw;
+mod source_reader;
 mod source_reader_flag;
 #[cfg(test)]
 mod tests;
 mod video_format;
 
-pub use camera::*;
-pub use media_type::*;
-pub use video_format::*;
+pub(crate) use camera::*;
+pub(crate) use video_format::*;
@@ -0,0 +1,102 @@
+//! The Media Foundation building blocks of the Windows backend, to capture in ways kamera
+//! doesn't without forking it. Enabled with the `raw` feature, see also
+//! [`Camera::as_mf_engine`](crate::Camera::as_mf_engine).
+//!
+//! kamera captures like this:
+//!
+//! 1. [`startup`] and [`video_capture_sources`], `ActivateObject` gives the `IMFMediaSource`
+//! 2. [`new_capture_engine`] and [`init_capture_engine`] with the source and an event callback,
+//!    then wait for `MF_CAPTURE_ENGINE_INITIALIZED`
+//! 3. [`add_rgb32_preview_stream`], the engine converts the device's format to RGB32 with the
+//!    video processor, and `StartPreview`
+//! 4. the sample callback receives an `IMFSample` on a Media Foundation thread per frame, which
+//!    kamera queues for [`Camera::wait_for_frame`](crate::Camera::wait_for_frame)
+//! 5. [`lock_sample`] maps it as BGRA in the size of [`preview_media_type`] while the frame
+//!    lives
+//!
+//! [`set_device_media_type`] switches the format of the device in between, with the preview
+//! stopped. The functions are thin wrappers, the COM objects stay usable with the `windows`
+//! crate as usual.
+
+use std::time::Duration;
+
+use windows::core::Result;
+use windows::Win32::Media::MediaFoundation::*;
+
+use super::media_type::MediaType;
+use super::mf;
+
+pub use super::mf::LockedBuffer;
+
+/// `MFStartup`, which counts its calls. kamera does this before it opens a camera.
+pub fn startup() -> Result<()> {
+    mf::media_foundation_startup()
+}
+
+/// Activation objects of the video capture devices, in the order of the OS.
+pub fn video_capture_sources() -> Vec<IMFActivate> {
+    mf::enum_device_sources()
+}
+
+pub fn new_capture_engine() -> Result<IMFCaptureEngine> {
+    mf::new_capture_engine()
+}
+
+/// Initializes `engine` for video only, `event_cb` gets the engine's events from then on.
+pub fn init_capture_engine(
+    engine: &IMFCaptureEngine,
+    source: &IMFMediaSource,
+    event_cb: &IMFCaptureEngineOnEventCallback,
+) -> Result<()> {
+    mf::init_capture_engine(engine, Some(source), event_cb)
+}
+
+/// Adds an RGB32 stream of the device's current size to the preview sink, whose samples go to
+/// `sample_cb`.
+pub fn add_rgb32_preview_stream(
+    engine: &IMFCaptureEngine,
+    sample_cb: &IMFCaptureEngineOnSampleCallback,
+) -> Result<()> {
+    mf::capture_engine_prepare_sample_callback(engine, sample_cb)
+}
+
+/// Sets `media_type`, one of the device's, and replaces the preview stream to match. The preview
+/// has to be stopped.
+pub fn set_device_media_type(
+    engine: &IMFCaptureEngine,
+    media_type: &IMFMediaType,
+    sample_cb: &IMFCaptureEngineOnSampleCallback,
+) -> Result<()> {
+    mf::capture_engine_set_media_type(engine, &MediaType(media_type.clone()), sample_cb)
+}
+
+/// The RGB32 media type of the preview samples.
+pub fn preview_media_type(engine: &IMFCaptureEngine) -> Result<IMFMediaType> {
+    Ok(mf::capture_engine_sink_get_media_type(engine)?.0)
+}
+
+/// The native media type of the device, which still has the YUV matrix and range.
+pub fn device_media_type(engine: &IMFCaptureEngine) -> Result<IMFMediaType> {
+    Ok(mf::capture_engine_source_get_media_type(engine)?.0)
+}
+
+/// RGB32 in the size and frame rate of `media_type`.
+pub fn to_rgb32(media_type: &IMFMediaType) -> Result<IMFMediaType> {
+    Ok(MediaType(media_type.clone()).to_rgb32()?.0)
+}
+
+/// `MF_LOW_LATENCY` on the source, which drivers may use to buffer fewer frames.
+pub fn set_low_latency(source: &IMFMediaSource, low: bool) -> Result<()> {
+    mf::media_source_set_low_latency(source, low)
+}
+
+/// Locks the buffer of a preview sample of `width` by `height` RGB32 pixels for reading, until
+/// the [`LockedBuffer`] is dropped.
+pub fn lock_sample(sample: &IMFSample, width: u32, height: u32) -> Result<LockedBuffer> {
+    mf::sample_to_locked_buffer(sample, width, height)
+}
+
+/// The capture time the driver attached to `sample`, on the QPC clock.
+pub fn sample_device_time(sample: &IMFSample) -> Option<Duration> {
+    mf::sample_device_time(sample)
+}
@@ -0,0 +1,203 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    mpsc::Sender,
+    Arc, Mutex,
+};
+
+use windows::{
+    core::{implement, IUnknown, Result, HRESULT},
+    Win32::{Foundation::E_FAIL, Media::MediaFoundation::*},
+};
+
+use super::attributes::mf_create_attributes;
+use super::media_type::MediaType;
+use super::mf::{camera_event, CaptureEngineEvent, SampleQueue};
+use crate::sync::lock;
+use crate::CameraEvent;
+
+const VIDEO_STREAM: u32 = MF_SOURCE_READER_FIRST_VIDEO_STREAM.0 as u32;
+
+/// Reads the video stream of a media source with an `IMFSourceReader` in async mode, see
+/// [`WinBackend::SourceReader`](crate::WinBackend::SourceReader).
+///
+/// Each sample the callback receives asks for the next one while the reader runs.
+#[derive(Debug)]
+pub(crate) struct SourceReader {
+    reader: IMFSourceReader,
+    state: Arc<ReaderState>,
+}
+
+struct ReaderState {
+    /// The callback's way to the reader, which holds on to the callback in turn. Taken when the
+    /// reader is dropped.
+    reader: Mutex<Option<IMFSourceReader>>,
+    running: AtomicBool,
+    /// `MF_MT_FRAME_SIZE` of the output, which each sample gets as its own. Samples of the old
+    /// size can still be queued after the reader changed its output.
+    frame_size: AtomicU64,
+    samples: SampleQueue,
+    camera_event_tx: Sender<CameraEvent>,
+}
+
+impl ReaderState {
+    fn update_frame_size(&self, reader: &IMFSourceReader) {
+        if let Ok(size) = unsafe {
+            reader.GetCurrentMediaType(VIDEO_STREAM).and_then(|mt| mt.GetUINT64(&MF_MT_FRAME_SIZE))
+        } {
+            self.frame_size.store(size, Ordering::SeqCst);
+        }
+    }
+}
+
+impl std::fmt::Debug for ReaderState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReaderState").field("running", &self.running).finish()
+    }
+}
+
+#[implement(IMFSourceReaderCallback)]
+struct ReadSampleCallback(Arc<ReaderState>);
+
+impl SourceReader {
+    /// Converts to RGB32 with the video processor of the reader, like the preview sink of the
+    /// capture engine.
+    pub(crate) fn new(
+        source: &IMFMediaSource,
+        samples: SampleQueue,
+        camera_event_tx: Sender<CameraEvent>,
+    ) -> Result<Self> {
+        let state = Arc::new(ReaderState {
+            reader: Mutex::new(None),
+            running: AtomicBool::new(false),
+            frame_size: AtomicU64::new(0),
+            samples,
+            camera_event_tx,
+        });
+        let callback: IMFSourceReaderCallback = ReadSampleCallback(state.clone()).into();
+        let reader = unsafe {
+            let attributes = mf_create_attributes();
+            attributes
+                .SetUnknown(&MF_SOURCE_READER_ASYNC_CALLBACK, <&IUnknown>::from(&callback))?;
+            attributes.SetUINT32(&MF_SOURCE_READER_ENABLE_VIDEO_PROCESSING, 1)?;
+            // the camera shuts the source down itself
+            attributes.SetUINT32(&MF_SOURCE_READER_DISCONNECT_MEDIASOURCE_ON_SHUTDOWN, 1)?;
+            MFCreateSourceReaderFromMediaSource(source, &attributes)?
+        };
+        state.update_frame_size(&reader);
+        *lock(&state.reader) = Some(reader.clone());
+        Ok(Self { reader, state })
+    }
+
+    /// Sets `media_type` on the device and RGB32 of its size as the output. Stops a running
+    /// reader and starts it again.
+    pub(crate) fn set_media_type(&self, media_type: &MediaType) -> Result<()> {
+        let running = self.is_running();
+        self.stop();
+        unsafe {
+            self.reader.SetCurrentMediaType(VIDEO_STREAM, None, &media_type.0)?;
+            self.reader.SetCurrentMediaType(VIDEO_STREAM, None, &media_type.to_rgb32()?.0)?;
+        }
+        self.state.update_frame_size(&self.reader);
+        if running {
+            self.start()?;
+        }
+        Ok(())
+    }
+
+    /// The RGB32 media type of the samples.
+    pub(crate) fn output_media_type(&self) -> Result<MediaType> {
+        Ok(MediaType(unsafe { self.reader.GetCurrentMediaType(VIDEO_STREAM) }?))
+    }
+
+    /// Requests the first sample, the callback requests the following ones.
+    pub(crate) fn start(&self) -> Result<()> {
+        if self.state.running.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+        let read = unsafe { self.reader.ReadSample(VIDEO_STREAM, 0, None, None, None, None) };
+        if read.is_err() {
+            self.state.running.store(false, Ordering::SeqCst);
+        }
+        read
+    }
+
+    /// Samples which are still pending arrive after the flush and are dropped.
+    pub(crate) fn stop(&self) {
+        if self.state.running.swap(false, Ordering::SeqCst) {
+            let _ = unsafe { self.reader.Flush(VIDEO_STREAM) };
+        }
+    }
+
+    pub(crate) fn is_running(&self) -> bool {
+        self.state.running.load(Ordering::SeqCst)
+    }
+
+    #[cfg(feature = "raw")]
+    pub(crate) fn raw_reader(&self) -> &IMFSourceReader {
+        &self.reader
+    }
+}
+
+impl Drop for SourceReader {
+    fn drop(&mut self) {
+        self.stop();
+        lock(&self.state.reader).take();
+    }
+}
+
+impl IMFSourceReaderCallback_Impl for ReadSampleCallback {
+    fn OnReadSample(
+        &self,
+        status: HRESULT,
+        _stream_index: u32,
+        stream_flags: u32,
+        _timestamp: i64,
+        sample: &Option<IMFSample>,
+    ) -> Result<()> {
+        let state = &self.0;
+        // the flags combine, e.g. an error with the end of the stream
+        let flagged = |flag: MF_SOURCE_READER_FLAG| stream_flags & flag.0 as u32 != 0;
+        let event = if status.is_err() {
+            camera_event(&CaptureEngineEvent::Error, status)
+        } else if flagged(MF_SOURCE_READERF_ERROR) {
+            // the reader takes no further calls after an error
+            camera_event(&CaptureEngineEvent::Error, E_FAIL)
+        } else if flagged(MF_SOURCE_READERF_ENDOFSTREAM) {
+            // a camera only ends its stream when it is unplugged
+            Some(CameraEvent::DeviceLost)
+        } else {
+            None
+        };
+        if let Some(event) = event {
+            state.running.store(false, Ordering::SeqCst);
+            let _ = state.camera_event_tx.send(event);
+            return Ok(());
+        }
+        if !state.running.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        if flagged(MF_SOURCE_READERF_CURRENTMEDIATYPECHANGED) {
+            if let Some(reader) = &*lock(&state.reader) {
+                state.update_frame_size(reader);
+            }
+        }
+        // stream ticks come without a sample, the others carry the size they were read with
+        if let Some(read) = sample {
+            let size = state.frame_size.load(Ordering::SeqCst);
+            let _ = unsafe { read.SetUINT64(&MF_MT_FRAME_SIZE, size) };
+            state.samples.push(sample);
+        }
+        if let Some(reader) = &*lock(&state.reader) {
+            unsafe { reader.ReadSample(VIDEO_STREAM, 0, None, None, None, None) }?;
+        }
+        Ok(())
+    }
+
+    fn OnFlush(&self, _stream_index: u32) -> Result<()> {
+        Ok(())
+    }
+
+    fn OnEvent(&self, _stream_index: u32, _event: &Option<IMFMediaEvent>) -> Result<()> {
+        Ok(())
+    }
+}
@@ -1,4 +1,10 @@
 use super::mf::*;
+use crate::CameraEvent;
+
+use windows::Win32::{
+    Foundation::{E_ACCESSDENIED, S_OK},
+    Media::MediaFoundation::*,
+};
 
 #[test]
 fn device_enum_devices() {
@@ -38,3 +44,36 @@ fn device_query_media_types_with_best_fps() {
     println!("{types:?}");
     assert!(!types.is_empty());
 }
+
+#[test]
+fn camera_event_from_engine_event() {
+    use CaptureEngineEvent::*;
+    assert_eq!(camera_event(&PreviewStarted, S_OK), None);
+    assert_eq!(camera_event(&Error, E_ACCESSDENIED), Some(CameraEvent::AccessDenied));
+    assert_eq!(
+        camera_event(&Error, MF_E_VIDEO_RECORDING_DEVICE_PREEMPTED),
+        Some(CameraEvent::InUseByOtherApp)
+    );
+    assert_eq!(
+        camera_event(&Error, MF_E_VIDEO_RECORDING_DEVICE_INVALIDATED),
+        Some(CameraEvent::DeviceLost)
+    );
+    assert_eq!(camera_event(&CameraStreamBlocked, S_OK), Some(CameraEvent::StreamBlocked));
+    assert!(matches!(camera_event(&Initialized, MF_E_SHUTDOWN), Some(CameraEvent::Error { .. })));
+}
+
+#[test]
+fn face_rois() {
+    let half = 1 << 30;
+    let quarter = 1 << 29;
+    let blob: Vec<u8> = [28, 1, quarter, quarter, half, i32::MAX, 80]
+        .iter()
+        .flat_map(|v: &i32| v.to_ne_bytes())
+        .collect();
+    let faces = parse_face_rois(&blob);
+    assert_eq!(faces.len(), 1);
+    assert_eq!((faces[0].x, faces[0].y, faces[0].width), (0.25, 0.25, 0.25));
+    assert!((faces[0].height - 0.75).abs() < 1e-6);
+    // a count larger than the blob stops at its end
+    assert_eq!(parse_face_rois(&blob[..16]), vec![]);
+}
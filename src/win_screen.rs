@@ -0,0 +1,240 @@
+//! Screen capture through Windows.Graphics.Capture, Windows 10 1903 and newer. See
+//! [`screen`](crate::screen).
+//!
+//! The frame pool signals new frames on a thread pool thread, the reader copies the texture of
+//! the frame through a staging texture into memory.
+
+use std::sync::mpsc::{sync_channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use windows::core::{factory, Interface, Result};
+use windows::Foundation::TypedEventHandler;
+use windows::Graphics::Capture::{
+    Direct3D11CaptureFramePool, GraphicsCaptureItem, GraphicsCaptureSession,
+};
+use windows::Graphics::DirectX::Direct3D11::IDirect3DDevice;
+use windows::Graphics::DirectX::DirectXPixelFormat;
+use windows::Graphics::SizeInt32;
+use windows::Win32::Foundation::{HINSTANCE, POINT};
+use windows::Win32::Graphics::Direct3D::D3D_DRIVER_TYPE_HARDWARE;
+use windows::Win32::Graphics::Direct3D11::*;
+use windows::Win32::Graphics::Dxgi::IDXGIDevice;
+use windows::Win32::Graphics::Gdi::{MonitorFromPoint, MONITOR_DEFAULTTOPRIMARY};
+use windows::Win32::System::WinRT::Direct3D11::{
+    CreateDirect3D11DeviceFromDXGIDevice, IDirect3DDxgiInterfaceAccess,
+};
+use windows::Win32::System::WinRT::Graphics::Capture::IGraphicsCaptureItemInterop;
+
+use crate::sync::lock;
+use crate::win_mf::mf::co_initialize_multithreaded;
+use crate::{CameraDevice, CameraEvent, CaptureFormat, DeviceKind, Error, FrameSource, OwnedFrame};
+
+/// Frames in the pool, one being read while the next is captured.
+const BUFFERS: i32 = 2;
+
+#[derive(Debug)]
+pub(crate) struct WinScreen {
+    device: ID3D11Device,
+    context: Mutex<ID3D11DeviceContext>,
+    item: GraphicsCaptureItem,
+    pool: Direct3D11CaptureFramePool,
+    /// The size the pool was created for.
+    size: Mutex<SizeInt32>,
+    session: Mutex<Option<GraphicsCaptureSession>>,
+    arrived: Mutex<Receiver<()>>,
+    staging: Mutex<Option<ID3D11Texture2D>>,
+    events: Arc<Mutex<Option<Sender<CameraEvent>>>>,
+}
+
+impl WinScreen {
+    /// Captures the primary monitor.
+    pub(crate) fn open() -> std::result::Result<Self, Error> {
+        co_initialize_multithreaded();
+        if !GraphicsCaptureSession::IsSupported()? {
+            return Err(Error::Unsupported);
+        }
+        let mut device = None;
+        let mut context = None;
+        unsafe {
+            D3D11CreateDevice(
+                None,
+                D3D_DRIVER_TYPE_HARDWARE,
+                HINSTANCE::default(),
+                D3D11_CREATE_DEVICE_BGRA_SUPPORT,
+                None,
+                D3D11_SDK_VERSION,
+                Some(&mut device),
+                None,
+                Some(&mut context),
+            )?
+        };
+        let (Some(device), Some(context)) = (device, context) else {
+            return Err(Error::Other("no Direct3D device".into()));
+        };
+
+        let monitor = unsafe { MonitorFromPoint(POINT { x: 0, y: 0 }, MONITOR_DEFAULTTOPRIMARY) };
+        let interop = factory::<GraphicsCaptureItem, IGraphicsCaptureItemInterop>()?;
+        let item: GraphicsCaptureItem = unsafe { interop.CreateForMonitor(monitor)? };
+        let size = item.Size()?;
+        let pool = Direct3D11CaptureFramePool::CreateFreeThreaded(
+            &direct3d_device(&device)?,
+            DirectXPixelFormat::B8G8R8A8UIntNormalized,
+            BUFFERS,
+            size,
+        )?;
+        let (arrived_tx, arrived) = sync_channel(1);
+        pool.FrameArrived(&TypedEventHandler::new(move |_, _| {
+            // a pending signal already covers this frame
+            let _ = arrived_tx.try_send(());
+            Ok(())
+        }))?;
+        let events: Arc<Mutex<Option<Sender<CameraEvent>>>> = Default::default();
+        let closed_events = events.clone();
+        // the monitor was disconnected
+        item.Closed(&TypedEventHandler::new(move |_, _| {
+            if let Some(events) = &*lock(&closed_events) {
+                let _ = events.send(CameraEvent::DeviceLost);
+            }
+            Ok(())
+        }))?;
+        Ok(Self {
+            device,
+            context: Mutex::new(context),
+            item,
+            pool,
+            size: Mutex::new(size),
+            session: Mutex::new(None),
+            arrived: Mutex::new(arrived),
+            staging: Mutex::new(None),
+            events,
+        })
+    }
+
+    /// `None` if no frame is ready.
+    fn next_frame(&self) -> Option<OwnedFrame> {
+        let frame = self.pool.TryGetNextFrame().ok()?;
+        let access: IDirect3DDxgiInterfaceAccess = frame.Surface().ok()?.cast().ok()?;
+        let texture: ID3D11Texture2D = unsafe { access.GetInterface() }.ok()?;
+        let owned = self.read_texture(&texture).ok();
+        let content_size = frame.ContentSize().ok();
+        let _ = frame.Close();
+        // a resized monitor keeps delivering frames of the old size until the pool is recreated
+        if let Some(content_size) = content_size {
+            let mut size = lock(&self.size);
+            if content_size != *size && self.recreate_pool(content_size).is_ok() {
+                *size = content_size;
+            }
+        }
+        owned
+    }
+
+    fn recreate_pool(&self, size: SizeInt32) -> Result<()> {
+        let device = direct3d_device(&self.device)?;
+        let format = DirectXPixelFormat::B8G8R8A8UIntNormalized;
+        self.pool.Recreate(&device, format, BUFFERS, size)
+    }
+
+    fn read_texture(&self, texture: &ID3D11Texture2D) -> Result<OwnedFrame> {
+        let mut desc = D3D11_TEXTURE2D_DESC::default();
+        unsafe { texture.GetDesc(&mut desc) };
+        let staging = self.staging_texture(desc)?;
+        let context = lock(&self.context);
+        unsafe {
+            context.CopyResource(&staging, texture);
+            let mapped = context.Map(&staging, 0, D3D11_MAP_READ, 0)?;
+            let stride = mapped.RowPitch as usize;
+            let len = stride * desc.Height as usize;
+            let bgra = std::slice::from_raw_parts(mapped.pData as *const u8, len).to_vec();
+            context.Unmap(&staging, 0);
+            Ok(OwnedFrame::with_stride(bgra, desc.Width, desc.Height, stride))
+        }
+    }
+
+    /// A texture the CPU can read, reused while the size stays the same.
+    fn staging_texture(&self, desc: D3D11_TEXTURE2D_DESC) -> Result<ID3D11Texture2D> {
+        let mut staging = lock(&self.staging);
+        if let Some(texture) = staging.as_ref() {
+            let mut current = D3D11_TEXTURE2D_DESC::default();
+            unsafe { texture.GetDesc(&mut current) };
+            if (current.Width, current.Height) == (desc.Width, desc.Height) {
+                return Ok(texture.clone());
+            }
+        }
+        let desc = D3D11_TEXTURE2D_DESC {
+            Usage: D3D11_USAGE_STAGING,
+            BindFlags: D3D11_BIND_FLAG(0),
+            CPUAccessFlags: D3D11_CPU_ACCESS_READ,
+            MiscFlags: D3D11_RESOURCE_MISC_FLAG(0),
+            ..desc
+        };
+        let texture = unsafe { self.device.CreateTexture2D(&desc, None)? };
+        *staging = Some(texture.clone());
+        Ok(texture)
+    }
+}
+
+impl FrameSource for WinScreen {
+    fn start(&self) {
+        let mut session = lock(&self.session);
+        if session.is_none() {
+            *session = self
+                .pool
+                .CreateCaptureSession(&self.item)
+                .and_then(|s| s.StartCapture().map(|_| s))
+                .ok();
+        }
+    }
+
+    fn stop(&self) {
+        if let Some(session) = lock(&self.session).take() {
+            let _ = session.Close();
+        }
+    }
+
+    /// An unchanged screen delivers no frames, this waits until it changes or capture stops.
+    fn wait_for_frame(&self) -> Option<OwnedFrame> {
+        loop {
+            if lock(&self.session).is_none() {
+                return None;
+            }
+            match lock(&self.arrived).recv_timeout(Duration::from_millis(100)) {
+                Ok(()) => {}
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => return None,
+            }
+            if let Some(frame) = self.next_frame() {
+                return Some(frame);
+            }
+        }
+    }
+
+    fn try_next_frame(&self) -> Option<OwnedFrame> {
+        self.next_frame()
+    }
+
+    fn device(&self) -> CameraDevice {
+        let name = self.item.DisplayName().map(|name| name.to_string());
+        CameraDevice::new("primary-monitor", name.unwrap_or_default(), DeviceKind::Virtual)
+    }
+
+    fn current_format(&self) -> Option<CaptureFormat> {
+        let size = *lock(&self.size);
+        Some(CaptureFormat {
+            pixel_format: "BGRA".to_string(),
+            width: size.Width as u32,
+            height: size.Height as u32,
+            min_fps: 0.0,
+            max_fps: 0.0,
+        })
+    }
+
+    fn connect_events(&self, events: Sender<CameraEvent>) {
+        *lock(&self.events) = Some(events);
+    }
+}
+
+fn direct3d_device(device: &ID3D11Device) -> Result<IDirect3DDevice> {
+    let dxgi_device: IDXGIDevice = device.cast()?;
+    unsafe { CreateDirect3D11DeviceFromDXGIDevice(&dxgi_device)? }.cast()
+}
@@ -1,4 +1,8 @@
-use kamera::Camera;
+use kamera::{
+    describe_device, Backend, Camera, CameraEvent, CameraState, CancelToken, ConfigMismatch,
+    DeviceKind, DeviceKindMask, Enhancement, Error, FaultInjector, FourCC, FrameDelta, FrameSource,
+    Latency, MetadataKind, OwnedFrame, PtzAxis, Rect, SessionPreset, ThreadPriority,
+};
 
 #[test]
 fn new_default_device() {
@@ -6,6 +10,15 @@ fn new_default_device() {
     println!("{:?}", camera);
 }
 
+#[test]
+fn try_new_default_device() {
+    let mut camera = Camera::try_new_default_device().unwrap();
+    camera.try_start().unwrap();
+    assert!(camera.wait_for_frame().is_some());
+    let gone = kamera::CameraDevice::new("gone", "Unplugged", DeviceKind::Physical);
+    assert!(camera.try_set_device(&gone).is_err());
+}
+
 #[test]
 fn start() {
     let camera = Camera::new_default_device();
@@ -19,6 +32,32 @@ fn start_stop() {
     camera.stop();
 }
 
+#[test]
+fn start_stop_cycles() {
+    let camera = Camera::new_default_device();
+    camera.start();
+    assert!(camera.wait_for_frame().is_some());
+    let format = camera.current_format();
+    for i in 0..100 {
+        camera.stop();
+        assert_eq!(camera.state(), CameraState::Stopped);
+        camera.start();
+        assert_eq!(camera.state(), CameraState::Running);
+        assert!(camera.wait_for_frame().is_some(), "cycle {i}");
+    }
+    assert_eq!(camera.current_format(), format);
+}
+
+#[test]
+fn state() {
+    let camera = Camera::with_backend(Backend::Test).unwrap();
+    assert_eq!(camera.state(), CameraState::Stopped);
+    camera.start();
+    assert_eq!(camera.state(), CameraState::Running);
+    camera.stop();
+    assert_eq!(camera.state(), CameraState::Stopped);
+}
+
 #[test]
 fn stop_without_start() {
     let camera = Camera::new_default_device();
@@ -74,6 +113,44 @@ fn frame_data() {
     assert_eq!(a, b);
 }
 
+#[test]
+fn frame_data_is_borrowed() {
+    let camera = Camera::new_default_device();
+    camera.start();
+    let frame = camera.wait_for_frame().unwrap();
+    assert_eq!(frame.data().data_u8().as_ptr(), frame.data().data_u8().as_ptr());
+}
+
+#[test]
+fn frame_data_formats() {
+    let camera = Camera::new_default_device();
+    camera.start();
+    let frame = camera.wait_for_frame().unwrap();
+    let (w, h) = frame.size_u32();
+    let pixels = (w * h) as usize;
+    let data = frame.data();
+    assert_eq!(data.data_bgra().len(), pixels * 4);
+    assert_eq!(data.data_rgb().len(), pixels * 3);
+    assert_eq!(data.data_gray().len(), pixels);
+    assert_eq!(data.data_rgb().as_ptr(), data.data_rgb().as_ptr());
+    if let Some(samples) = data.data_u16() {
+        assert_eq!(samples.len(), pixels);
+    }
+}
+
+#[test]
+fn frame_data_planes() {
+    let camera = Camera::new_default_device();
+    camera.start();
+    let frame = camera.wait_for_frame().unwrap();
+    let data = frame.data();
+    assert!(data.plane_count() >= 1);
+    let plane = data.plane(0).unwrap();
+    assert_eq!((plane.width, plane.height), frame.size_u32());
+    assert!(plane.data.len() >= plane.stride * (plane.height as usize - 1));
+    assert!(data.plane(data.plane_count()).is_none());
+}
+
 #[cfg(not(any(target_os = "linux", target_os = "windows")))]
 // linux_v4l2: ioctl VIDIOC_REQBUFS fails with Device Busy, Chromium also fails in this case, no alternative on this level
 // win_mf: fails to get frames because "The video recording device is preempted by another immersice application"
@@ -92,6 +169,73 @@ fn two_cameras_start_and_wait_for_frames() {
     println!("Camera 2 {:?}", camera2.wait_for_frame());
 }
 
+#[cfg(target_os = "linux")]
+#[test]
+fn open_policy_waits_for_busy_device() {
+    use kamera::OpenPolicy;
+    use std::time::Duration;
+
+    let camera1 = Camera::new_default_device();
+    camera1.start();
+    assert!(camera1.wait_for_frame().is_some());
+    let err = Camera::builder().try_build().unwrap_err();
+    assert_eq!(err, Error::InUseByOtherApp);
+
+    let policy = OpenPolicy::WaitUntilFree { timeout: Duration::from_secs(3) };
+    let release = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(300));
+        drop(camera1);
+    });
+    let camera2 = Camera::builder().open_policy(policy).try_build().unwrap();
+    release.join().unwrap();
+    camera2.start();
+    assert!(camera2.wait_for_frame().is_some());
+}
+
+#[cfg(target_os = "windows")]
+#[test]
+fn win_source_reader() {
+    use kamera::WinBackend;
+
+    let camera = Camera::builder().win_backend(WinBackend::SourceReader).build();
+    camera.start();
+    let frame = camera.wait_for_frame().unwrap();
+    let (w, h) = frame.size_u32();
+    assert_eq!(frame.data().data_bgra().len(), (w * h * 4) as usize);
+    camera.stop();
+    camera.start();
+    assert!(camera.wait_for_frame().is_some());
+    assert!(!camera.has_torch());
+}
+
+#[cfg(all(target_os = "windows", feature = "dshow"))]
+#[test]
+fn dshow_devices() {
+    // the DirectShow interface of a Media Foundation camera isn't listed again
+    let devices = Camera::device_list();
+    let mut ids: Vec<_> = devices.iter().map(|d| d.id.to_lowercase()).collect();
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), devices.len());
+    // DirectShow only devices come last, a camera can't switch away from them
+    let mut camera = Camera::new_default_device();
+    let last = devices.last().unwrap();
+    assert!(camera.set_device(last));
+    assert_eq!(camera.device().id, last.id);
+    assert!(camera.wait_for_frame().is_some());
+}
+
+#[cfg(all(target_os = "linux", feature = "raw"))]
+#[test]
+fn v4l2_device() {
+    let camera = Camera::new_default_device();
+    let caps = camera.as_v4l2_device().unwrap().query_caps().unwrap();
+    println!("{caps}");
+    camera.start();
+    assert!(camera.wait_for_frame().is_some());
+    assert!(Camera::with_backend(Backend::Test).unwrap().as_v4l2_device().is_none());
+}
+
 #[test]
 fn change_device() {
     let mut camera = Camera::new_default_device();
@@ -104,3 +248,710 @@ fn change_device() {
     assert!(camera.wait_for_frame().is_some());
     assert!(camera.wait_for_frame().is_some());
 }
+
+#[test]
+fn change_device_keeps_resolution() {
+    let mut camera = Camera::new_default_device();
+    let (w, h) = *describe_device(&camera.device()).resolutions().last().unwrap();
+    camera.start();
+    assert!(camera.set_resolution(w, h));
+    let mismatches = camera.try_set_device(Camera::device_list().last().unwrap()).unwrap();
+    if mismatches.is_empty() {
+        assert_eq!(camera.current_format().map(|f| (f.width, f.height)), Some((w, h)));
+    } else {
+        assert_eq!(mismatches, [ConfigMismatch::Resolution { width: w, height: h }]);
+    }
+}
+
+#[test]
+fn device_list_filtered() {
+    let devices = Camera::device_list();
+    println!("{devices:?}");
+    assert_eq!(devices, Camera::device_list_filtered(DeviceKindMask::ALL));
+    let physical = Camera::device_list_filtered(DeviceKindMask::PHYSICAL);
+    let others = Camera::device_list_filtered(!DeviceKindMask::PHYSICAL);
+    assert_eq!(devices.len(), physical.len() + others.len());
+}
+
+#[test]
+fn describe_devices() {
+    for device in Camera::device_list() {
+        let caps = describe_device(&device);
+        println!("{} {:?} {:?}", device.name, caps.pixel_formats(), caps.max_resolution());
+        assert!(!caps.formats.is_empty());
+    }
+}
+
+#[test]
+fn default_format() {
+    for device in Camera::device_list() {
+        let format = device.default_format().unwrap();
+        println!("{} {format:?}", device.name);
+        assert!(format.width > 0 && format.height > 0);
+        assert_eq!(device.default_format(), Some(format));
+    }
+}
+
+#[test]
+fn backend_capabilities() {
+    let capabilities = kamera::backend_capabilities();
+    println!("{capabilities:?}");
+    assert!(capabilities.capture);
+}
+
+#[test]
+fn current_format() {
+    let camera = Camera::new_default_device();
+    camera.start();
+    let frame = camera.wait_for_frame().unwrap();
+    let format = camera.current_format().unwrap();
+    println!("{format:?}");
+    assert_eq!((format.width, format.height), frame.size_u32());
+}
+
+#[test]
+fn try_exclusive() {
+    let camera = Camera::new_default_device();
+    camera.try_exclusive().unwrap();
+    assert!(camera.wait_for_frame().is_some());
+}
+
+#[test]
+fn perf_counters() {
+    let camera = Camera::new_default_device();
+    camera.start();
+    let frame = camera.wait_for_frame().unwrap();
+    frame.data().data_rgb();
+    assert!(camera.wait_for_frame().is_some());
+    let counters = camera.perf_counters();
+    println!("{counters:?}");
+    assert_eq!(counters.frames, 2);
+    assert!(counters.conversions >= 1);
+}
+
+struct TestPattern;
+
+impl FrameSource for TestPattern {
+    fn wait_for_frame(&self) -> Option<OwnedFrame> {
+        Some(OwnedFrame::new([0, 0, 255, 255].repeat(4 * 2), 4, 2))
+    }
+}
+
+#[test]
+fn custom_frame_source() {
+    let camera = Camera::from_source(TestPattern);
+    camera.start();
+    let frame = camera.wait_for_frame().unwrap();
+    assert_eq!(frame.size_u32(), (4, 2));
+    assert_eq!(&frame.data().data_rgb()[..3], &[255, 0, 0]);
+    assert_eq!(camera.perf_counters().frames, 1);
+    camera.stop();
+}
+
+#[test]
+fn capture_metadata() {
+    let camera = Camera::new_default_device();
+    let metadata = camera.capture_metadata();
+    println!("{metadata:?}");
+    assert_eq!(metadata.device_name, camera.device().name);
+}
+
+#[test]
+fn capture_info() {
+    let camera = Camera::new_default_device();
+    camera.start();
+    let frame = camera.wait_for_frame().unwrap();
+    let info = frame.capture_info();
+    println!("{info:?}");
+    if !cfg!(any(target_os = "macos", target_os = "ios")) {
+        assert_eq!(info, None);
+    }
+    assert_eq!(frame.crop(Rect::new(0, 0, 8, 8)).capture_info(), info);
+}
+
+#[test]
+fn test_backend() {
+    let camera = Camera::with_backend(Backend::Test).unwrap();
+    camera.start();
+    let frame = camera.wait_for_frame().unwrap();
+    assert_eq!((frame.size_u32(), frame.fourcc()), ((640, 480), FourCC::BGRA));
+    assert_eq!(camera.device().kind(), DeviceKind::Virtual);
+}
+
+#[test]
+fn yuv_export() {
+    let camera = Camera::with_backend(Backend::Test).unwrap();
+    camera.start();
+    let frame = camera.wait_for_frame().unwrap();
+    let i420 = frame.data().to_i420();
+    assert_eq!(i420.len(), 640 * 480 * 3 / 2);
+    let nv12 = frame.data().to_nv12();
+    assert_eq!(nv12[..640 * 480], i420[..640 * 480]);
+    assert_eq!((nv12[640 * 480], nv12[640 * 480 + 1]), (i420[640 * 480], i420[640 * 600]));
+    let crop = frame.crop(Rect::new(1, 1, 5, 3));
+    assert_eq!(crop.data().to_i420().len(), 5 * 3 + 2 * 3 * 2);
+    // 8 bit frames have no I010
+    assert_eq!(frame.data().to_i010(), None);
+}
+
+#[test]
+fn cached_conversions() {
+    let camera = Camera::with_backend(Backend::Test).unwrap();
+    camera.start();
+    let mut frame = camera.wait_for_frame().unwrap();
+    let (i420, histogram) = (frame.data().data_i420().as_ptr(), frame.data().luma_histogram());
+    assert_eq!(frame.data().data_i420().as_ptr(), i420);
+    assert_eq!(frame.data().to_i420(), frame.data().data_i420());
+    frame.clear_conversions();
+    assert_eq!(frame.data().luma_histogram(), histogram);
+    assert_eq!(frame.data().data_nv12().len(), frame.data().to_i420().len());
+}
+
+#[test]
+fn luma() {
+    let camera = Camera::with_backend(Backend::Test).unwrap();
+    camera.start();
+    let frame = camera.wait_for_frame().unwrap();
+    let data = frame.data();
+    let luma = data.luma();
+    assert_eq!((luma.width, luma.height), frame.size_u32());
+    assert_eq!(luma.data, data.data_gray());
+}
+
+#[test]
+fn cadence() {
+    let camera = Camera::with_backend(Backend::Test).unwrap();
+    camera.start();
+    for _ in 0..10 {
+        camera.wait_for_frame().unwrap();
+    }
+    let cadence = camera.cadence();
+    println!("{cadence:?}");
+    assert_eq!(cadence.intervals, 9);
+    assert!(cadence.fps().is_some_and(|fps| fps > 20.0 && fps < 40.0));
+    camera.stop();
+    assert_eq!(camera.cadence().intervals, 0);
+}
+
+#[test]
+fn frame_delta() {
+    let camera = Camera::with_backend(Backend::Test).unwrap();
+    camera.start();
+    let frame = camera.wait_for_frame().unwrap();
+    assert!(FrameDelta::between(&frame, &frame).is_empty());
+    let crop = frame.crop(Rect::new(0, 0, 64, 64));
+    let delta = FrameDelta::between(&frame, &crop);
+    assert_eq!((delta.bounds(), delta.changed_percent), (Some(Rect::new(0, 0, 64, 64)), 100.0));
+}
+
+#[test]
+fn enable_metadata() {
+    let camera = Camera::with_backend(Backend::Test).unwrap();
+    assert!(!camera.enable_metadata(MetadataKind::Faces));
+    camera.start();
+    assert!(camera.wait_for_frame().unwrap().metadata().is_empty());
+}
+
+#[test]
+fn ptz() {
+    let camera = Camera::new_default_device();
+    for axis in [PtzAxis::Pan, PtzAxis::Tilt, PtzAxis::Zoom] {
+        let Some(range) = camera.ptz_range(axis) else {
+            assert!(!camera.set_ptz(axis, 0.0));
+            continue;
+        };
+        println!("{axis:?} {range:?}");
+        assert!(camera.set_ptz(axis, range.max));
+        assert!(camera.set_ptz(axis, range.default));
+    }
+    assert_eq!(Camera::with_backend(Backend::Test).unwrap().zoom_range(), None);
+}
+
+#[test]
+fn torch() {
+    let camera = Camera::new_default_device();
+    if camera.has_torch() {
+        assert_eq!(camera.set_torch(true), Ok(()));
+        assert_eq!(camera.set_torch(false), Ok(()));
+    } else {
+        assert_eq!(camera.set_torch(true), Err(Error::Unsupported));
+    }
+    let test = Camera::with_backend(Backend::Test).unwrap();
+    assert_eq!(test.set_torch_level(0.5), Err(Error::Unsupported));
+}
+
+#[cfg(feature = "screen")]
+#[test]
+fn screen() {
+    let camera = Camera::from_screen().unwrap();
+    camera.start();
+    // the first frame comes without waiting for changes
+    let (w, h) = camera.wait_for_frame().unwrap().size_u32();
+    assert!(w > 0 && h > 0);
+    camera.stop();
+}
+
+#[test]
+fn device_details() {
+    for device in Camera::device_list() {
+        let details = device.details();
+        println!("{}: {details:?}", device.name);
+        assert_eq!(details.vendor_id.is_some(), details.product_id.is_some());
+    }
+}
+
+#[test]
+fn set_resolution() {
+    let camera = Camera::new_default_device();
+    let (w, h) = describe_device(&camera.device()).resolutions()[0];
+    camera.start();
+    assert!(camera.wait_for_frame().is_some());
+    assert!(camera.set_resolution(w, h));
+    for _ in 0..4 {
+        camera.wait_for_frame();
+    }
+    assert_eq!(camera.wait_for_frame().unwrap().size_u32(), (w, h));
+    assert!(!camera.set_resolution(1, 1));
+}
+
+#[test]
+fn set_preset() {
+    let camera = Camera::new_default_device();
+    camera.start();
+    assert!(camera.wait_for_frame().is_some());
+    if camera.set_preset(SessionPreset::Vga640x480) {
+        for _ in 0..4 {
+            camera.wait_for_frame();
+        }
+        assert_eq!(camera.wait_for_frame().unwrap().size_u32(), (640, 480));
+    }
+    assert!(!Camera::with_backend(Backend::Test).unwrap().set_preset(SessionPreset::High));
+}
+
+#[test]
+fn try_next_and_latest_frame() {
+    let camera = Camera::new_default_device();
+    camera.start();
+    assert!(camera.wait_for_frame().is_some());
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    assert!(camera.latest_frame().is_some());
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    assert!(camera.try_next_frame().is_some());
+}
+
+#[test]
+fn stable_id() {
+    for device in Camera::device_list() {
+        println!("{} {}", device.name, device.stable_id());
+        assert_eq!(Camera::find_by_stable_id(&device.stable_id()), Some(device));
+    }
+}
+
+#[test]
+fn depth() {
+    let camera = Camera::new_default_device();
+    let enabled = camera.enable_depth();
+    camera.start();
+    let frame = camera.wait_for_frame().unwrap();
+    let depth = frame.depth();
+    println!("depth {enabled} {:?}", depth.map(|depth| (depth.width, depth.height)));
+    if !enabled {
+        assert!(depth.is_none());
+    }
+    assert!(frame.crop(Rect::new(0, 0, 8, 8)).depth().is_none());
+}
+
+#[test]
+fn device_groups() {
+    let groups = kamera::device_groups();
+    println!("{groups:#?}");
+    for group in &groups {
+        assert!(group.iter().all(|device| device.group_id() == group[0].group_id()));
+    }
+    let grouped: Vec<_> = groups.into_iter().flatten().collect();
+    assert_eq!(grouped.len(), Camera::device_list().len());
+}
+
+#[test]
+fn enhancements() {
+    let camera = Camera::new_default_device();
+    camera.start();
+    let all = [Enhancement::LowLightBoost, Enhancement::Hdr, Enhancement::BacklightCompensation];
+    for enhancement in all {
+        let supported = camera.supports_enhancement(enhancement);
+        println!("{enhancement:?} {supported}");
+        if supported {
+            assert!(camera.set_enhancement(enhancement, true));
+            assert!(camera.set_enhancement(enhancement, false));
+        }
+    }
+    assert!(camera.wait_for_frame().is_some());
+}
+
+#[test]
+fn frame_is_send_and_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<kamera::Frame>();
+    let camera = Camera::builder().frame_pool_size(2).build();
+    camera.start();
+    let frame = camera.wait_for_frame().unwrap();
+    let (w, h) = frame.size_u32();
+    let len = std::thread::spawn(move || frame.data().data_rgb().len()).join().unwrap();
+    assert_eq!(len, (w * h * 3) as usize);
+    assert!(camera.wait_for_frame().is_some());
+}
+
+#[test]
+fn pipeline_workers() {
+    let camera = Camera::builder().pipeline_workers(2).build();
+    camera.start();
+    let (w, h) = camera.wait_for_frame().unwrap().size_u32();
+    for _ in 0..8 {
+        assert_eq!(camera.wait_for_frame().unwrap().size_u32(), (w, h));
+    }
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    assert!(camera.latest_frame().is_some());
+    camera.stop();
+}
+
+#[test]
+fn buffer_count() {
+    let camera = Camera::builder().buffer_count(2).build();
+    camera.start();
+    let (w, h) = camera.wait_for_frame().unwrap().size_u32();
+    let frame = camera.wait_for_frame().unwrap();
+    assert_eq!(frame.data().data_bgra().len(), (w * h * 4) as usize);
+}
+
+#[test]
+fn restore_format() {
+    let camera = Camera::new_default_device();
+    let format = camera.current_format().unwrap();
+    camera.start();
+    assert!(camera.wait_for_frame().is_some());
+    // the device has its previous format back, kamera sets its own again on start
+    camera.stop();
+    assert_eq!(camera.current_format().unwrap(), format);
+    camera.start();
+    assert_eq!(camera.wait_for_frame().unwrap().size_u32(), (format.width, format.height));
+}
+
+#[test]
+fn set_max_fps() {
+    let camera = Camera::new_default_device();
+    camera.set_max_fps(5.0);
+    camera.start();
+    camera.wait_for_frame();
+    let start = std::time::Instant::now();
+    let frames = std::iter::from_fn(|| camera.wait_for_frame()).take(5).count();
+    assert_eq!(frames, 5);
+    assert!(start.elapsed() >= std::time::Duration::from_millis(700), "{:?}", start.elapsed());
+}
+
+#[test]
+fn set_latency_mode() {
+    let camera = Camera::new_default_device();
+    camera.start();
+    assert!(camera.wait_for_frame().is_some());
+    for latency in [Latency::Lowest, Latency::Quality, Latency::Balanced] {
+        camera.set_latency_mode(latency);
+        assert!(camera.wait_for_frame().is_some(), "{latency:?}");
+    }
+}
+
+#[test]
+fn set_format() {
+    let camera = Camera::new_default_device();
+    let formats = camera.supported_formats();
+    let fastest = formats.iter().max_by(|a, b| a.max_fps.total_cmp(&b.max_fps)).unwrap();
+    println!("{fastest:?}");
+    camera.start();
+    assert!(camera.set_format(fastest));
+    for _ in 0..4 {
+        camera.wait_for_frame();
+    }
+    assert_eq!(camera.wait_for_frame().unwrap().size_u32(), (fastest.width, fastest.height));
+}
+
+#[test]
+fn timestamp_monotonic() {
+    let camera = Camera::new_default_device();
+    camera.start();
+    let first = camera.wait_for_frame().unwrap().timestamp_monotonic().unwrap();
+    let second = camera.wait_for_frame().unwrap().timestamp_monotonic().unwrap();
+    let now = kamera::monotonic_now();
+    println!("{first:?} {second:?} {now:?} {:?}", camera.clock_calibration());
+    assert!(first < second && second <= now);
+    assert!(now - second < std::time::Duration::from_secs(1));
+}
+
+#[test]
+fn configure() {
+    let camera = Camera::new_default_device();
+    let smallest = (camera.supported_formats().into_iter())
+        .min_by_key(|format| format.width * format.height)
+        .unwrap();
+    camera.start();
+    camera.wait_for_frame();
+    let mismatches = camera.configure(|config| {
+        config.resolution(smallest.width, smallest.height).fps(5.0).latency(Latency::Lowest);
+    });
+    assert!(mismatches.is_empty(), "{mismatches:?}");
+    let format = camera.current_format().unwrap();
+    assert_eq!((format.width, format.height), (smallest.width, smallest.height));
+    assert!(camera.wait_for_frame().is_some());
+}
+
+#[test]
+fn crop() {
+    let camera = Camera::with_backend(Backend::Test).unwrap();
+    camera.start();
+    let frame = camera.wait_for_frame().unwrap();
+    let crop = frame.crop(Rect::new(100, 50, 320, 240));
+    assert_eq!(crop.size_u32(), (320, 240));
+    let (data, cropped) = (frame.data(), crop.data());
+    let pixel = |bgra: &[u8], x: usize, y: usize, w: usize| bgra[(y * w + x) * 4..][..4].to_vec();
+    assert_eq!(pixel(cropped.data_bgra(), 10, 20, 320), pixel(data.data_bgra(), 110, 70, 640));
+    let inner = crop.crop(Rect::new(300, 200, 100, 100));
+    assert_eq!(inner.size_u32(), (20, 40));
+    assert_eq!(pixel(inner.data().data_bgra(), 0, 0, 20), pixel(data.data_bgra(), 400, 250, 640));
+}
+
+#[test]
+fn run_until_cancelled() {
+    let camera = Camera::with_backend(Backend::Test).unwrap();
+    let cancel = CancelToken::new();
+    let mut frames = 0;
+    let result = camera.run(&cancel, |_| {
+        frames += 1;
+        if frames == 3 {
+            cancel.cancel();
+        }
+    });
+    assert_eq!((result, frames), (Ok(()), 3));
+
+    let camera = Camera::new_default_device();
+    let cancel = CancelToken::new();
+    assert_eq!(camera.run(&cancel, |_| cancel.cancel()), Ok(()));
+}
+
+#[test]
+fn run_polls_sources() {
+    // a source which never has a frame ready doesn't block the cancel
+    let camera = Camera::from_source(FiniteSource(0.into()));
+    let cancel = CancelToken::new();
+    let stop = cancel.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        stop.cancel();
+    });
+    assert_eq!(camera.run(&cancel, |_| {}), Ok(()));
+}
+
+#[test]
+fn run_waits_for_sources() {
+    // only implements `wait_for_frame`
+    let camera = Camera::from_source(TestPattern);
+    let cancel = CancelToken::new();
+    let mut frames = 0;
+    let result = camera.run(&cancel, |frame| {
+        assert_eq!(frame.size_u32(), (4, 2));
+        frames += 1;
+        if frames == 3 {
+            cancel.cancel();
+        }
+    });
+    assert_eq!((result, frames), (Ok(()), 3));
+}
+
+#[test]
+fn subscribe() {
+    let camera = Camera::with_backend(Backend::Test).unwrap();
+    camera.start();
+    let consumers: Vec<_> = (0..2)
+        .map(|_| {
+            let frames = camera.subscribe();
+            std::thread::spawn(move || {
+                (0..3).map(|_| frames.recv().unwrap().size_u32()).collect::<Vec<_>>()
+            })
+        })
+        .collect();
+    // returns once both consumers are done and dropped their receivers
+    camera.broadcast();
+    for consumer in consumers {
+        assert_eq!(consumer.join().unwrap(), [(640, 480); 3]);
+    }
+}
+
+/// Delivers a number of frames, then none.
+struct FiniteSource(std::sync::Mutex<u32>);
+
+impl FrameSource for FiniteSource {
+    fn wait_for_frame(&self) -> Option<OwnedFrame> {
+        let mut remaining = self.0.lock().unwrap();
+        *remaining = remaining.checked_sub(1)?;
+        Some(OwnedFrame::new(vec![0; 4 * 2 * 2], 2, 2))
+    }
+}
+
+#[test]
+fn broadcast_ends() {
+    let camera = Camera::from_source(FiniteSource(2.into()));
+    let frames = camera.subscribe();
+    camera.start();
+    camera.broadcast();
+    assert!(frames.recv().is_some());
+    assert!(frames.latest().is_some());
+    assert!(frames.recv().is_none());
+}
+
+#[test]
+fn broadcast_memory_limit() {
+    let camera = Camera::from_source(FiniteSource(5.into()));
+    // room for two frames of 2x2 pixels
+    camera.set_memory_limit(2 * 4 * 2 * 2);
+    let frames = camera.subscribe();
+    camera.start();
+    camera.broadcast();
+    assert_eq!(std::iter::from_fn(|| frames.recv()).count(), 2);
+}
+
+#[test]
+fn fault_injector() {
+    let source = FiniteSource(100.into());
+    let faults = FaultInjector::new(1).drop_rate(0.5).corrupt_rate(1.0).disconnect_after(10);
+    let camera = faults.wrap(Camera::from_source(source));
+    let events = camera.events();
+    camera.start();
+    for _ in 0..2 {
+        for _ in 0..10 {
+            let frame = camera.wait_for_frame().unwrap();
+            let data = frame.data();
+            let bgra = data.data_bgra();
+            assert_eq!((&bgra[..8], bgra[11], bgra[15]), (&[0; 8][..], 255, 255));
+        }
+        assert!(camera.wait_for_frame().is_none());
+        assert_eq!(events.try_recv(), Some(CameraEvent::DeviceLost));
+        camera.start();
+    }
+    assert_eq!(camera.perf_counters().frames, 20);
+}
+
+/// Loses its device on the first read.
+struct UnpluggedSource(std::sync::Mutex<Option<std::sync::mpsc::Sender<CameraEvent>>>);
+
+impl FrameSource for UnpluggedSource {
+    fn wait_for_frame(&self) -> Option<OwnedFrame> {
+        let _ = self.0.lock().unwrap().as_ref()?.send(CameraEvent::DeviceLost);
+        None
+    }
+
+    fn connect_events(&self, events: std::sync::mpsc::Sender<CameraEvent>) {
+        *self.0.lock().unwrap() = Some(events);
+    }
+}
+
+#[test]
+fn frame_source_events() {
+    let camera = Camera::from_source(UnpluggedSource(Default::default()));
+    let events = camera.events();
+    camera.start();
+    assert!(camera.wait_for_frame().is_none());
+    assert_eq!(events.try_recv(), Some(CameraEvent::DeviceLost));
+}
+
+#[test]
+fn camera_profile() {
+    let mut camera = Camera::with_backend(Backend::Test).unwrap();
+    camera.start();
+    let mut profile = camera.current_profile();
+    assert_eq!(profile.device, "");
+    assert_eq!(profile.format.as_ref().map(|f| (f.width, f.height)), Some((640, 480)));
+    profile.format = None;
+    profile.controls = vec![(PtzAxis::Zoom, 2.0)];
+    let mismatches = camera.apply_profile(&profile).unwrap();
+    assert_eq!(mismatches, [ConfigMismatch::Ptz { axis: PtzAxis::Zoom, value: 2.0 }]);
+}
+
+#[test]
+fn event_bus() {
+    let faults = FaultInjector::new(1).disconnect_after(1);
+    let camera = faults.wrap(Camera::with_backend(Backend::Test).unwrap());
+    let (tx, rx) = std::sync::mpsc::channel();
+    camera.on_event(move |event| tx.send(event.clone()).unwrap());
+    let events = [camera.events(), camera.events()];
+    let moved = camera.events();
+    let waiting = std::thread::spawn(move || moved.recv_timeout(std::time::Duration::from_secs(5)));
+    camera.start();
+    assert!(camera.wait_for_frame().is_some());
+    assert!(camera.wait_for_frame().is_none());
+    assert_eq!(waiting.join().unwrap(), Some(CameraEvent::DeviceLost));
+    for events in &events {
+        assert_eq!(events.try_iter().collect::<Vec<_>>(), [CameraEvent::DeviceLost]);
+    }
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [CameraEvent::DeviceLost]);
+    assert!(camera.poll_events().is_empty());
+}
+
+/// Open file descriptors of the process, 0 where they can't be listed.
+fn open_fds() -> usize {
+    let dir = if cfg!(target_os = "linux") { "/proc/self/fd" } else { "/dev/fd" };
+    std::fs::read_dir(dir).map(|entries| entries.count()).unwrap_or(0)
+}
+
+#[test]
+fn drop_releases_device() {
+    // the first camera loads frameworks and opens handles which stay for the process
+    drop(Camera::new_default_device());
+    let fds = open_fds();
+    for _ in 0..5 {
+        let camera = Camera::new_default_device();
+        camera.start();
+        assert!(camera.wait_for_frame().is_some());
+        drop(camera);
+    }
+    assert_eq!(open_fds(), fds);
+}
+
+#[test]
+fn thread_priority() {
+    let camera = Camera::new_default_device();
+    camera.start();
+    for priority in [ThreadPriority::High, ThreadPriority::Background, ThreadPriority::Normal] {
+        camera.set_thread_priority(priority);
+        assert!(camera.wait_for_frame().is_some());
+        assert!(camera.wait_for_frame().is_some());
+    }
+}
+
+#[test]
+fn memory_limit() {
+    let camera = Camera::new_default_device();
+    camera.set_memory_limit(1);
+    camera.start();
+    let frame = camera.wait_for_frame().unwrap();
+    let held = camera.memory_usage();
+    // the only frame held always fits
+    assert!(cfg!(target_os = "macos") || held > 0);
+    drop(frame);
+    assert!(camera.wait_for_frame().is_some());
+    camera.set_memory_limit(usize::MAX);
+}
+
+#[test]
+fn watchdog() {
+    use std::time::Duration;
+
+    let camera = Camera::with_backend(Backend::Test).unwrap();
+    assert!(!camera.is_alive(Duration::from_secs(1)));
+    let (tx, rx) = std::sync::mpsc::channel();
+    camera.set_watchdog(Duration::from_millis(100), move || tx.send(()).unwrap());
+    camera.start();
+    assert!(camera.wait_for_frame().is_some());
+    assert!(camera.is_alive(Duration::from_secs(1)));
+    // nobody takes frames
+    assert!(rx.recv_timeout(Duration::from_secs(2)).is_ok());
+    assert!(!camera.is_alive(Duration::from_millis(100)));
+    camera.stop();
+    camera.clear_watchdog();
+}
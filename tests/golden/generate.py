@@ -0,0 +1,119 @@
+#!/usr/bin/env python3
+"""Writes the golden frames for src/golden.rs: `<name>.raw` in a camera pixel format and
+`<name>.bgra` with the colors a floating point reference decodes from it.
+
+The reference is written from the formulas of BT.601, BT.709 and BT.2020, independent of
+src/convert.rs, so the converters are checked against the standards, not against themselves.
+Run it from this directory, the output is deterministic.
+"""
+
+W, H = 16, 8
+
+KR_KB = {"bt601": (0.299, 0.114), "bt709": (0.2126, 0.0722), "bt2020": (0.2627, 0.0593)}
+
+
+def clamp(v):
+    return max(0, min(255, round(v)))
+
+
+def source():
+    """Color bars in the top half, a gray ramp below, so channel order and range both show."""
+    bars = [(255, 255, 255), (255, 255, 0), (0, 255, 255), (0, 255, 0),
+            (255, 0, 255), (255, 0, 0), (0, 0, 255), (0, 0, 0)]
+    rgb = []
+    for y in range(H):
+        for x in range(W):
+            if y < H // 2:
+                rgb.append(bars[x // 2])
+            else:
+                g = x * 17
+                rgb.append((g, (g + y * 40) % 256, 255 - g))
+    return rgb
+
+
+def encode(rgb, matrix, full):
+    kr, kb = KR_KB[matrix]
+    kg = 1 - kr - kb
+    out = []
+    for r, g, b in rgb:
+        y = kr * r + kg * g + kb * b
+        u = (b - y) / (2 * (1 - kb))
+        v = (r - y) / (2 * (1 - kr))
+        if full:
+            out.append((clamp(y), clamp(u + 128), clamp(v + 128)))
+        else:
+            out.append((clamp(16 + y * 219 / 255), clamp(128 + u * 224 / 255),
+                        clamp(128 + v * 224 / 255)))
+    return out
+
+
+def decode(y, u, v, matrix, full):
+    kr, kb = KR_KB[matrix]
+    kg = 1 - kr - kb
+    if full:
+        y, u, v = y, u - 128, v - 128
+    else:
+        y, u, v = (y - 16) * 255 / 219, (u - 128) * 255 / 224, (v - 128) * 255 / 224
+    r = y + 2 * (1 - kr) * v
+    b = y + 2 * (1 - kb) * u
+    g = (y - kr * r - kb * b) / kg
+    return bytes([clamp(b), clamp(g), clamp(r), 255])
+
+
+def subsample(yuv, sx, sy):
+    """Chroma averaged over blocks of `sx` by `sy` pixels, as planes of the block count."""
+    cw, ch = W // sx, H // sy
+    us, vs = [], []
+    for by in range(ch):
+        for bx in range(cw):
+            block = [yuv[(by * sy + dy) * W + bx * sx + dx] for dy in range(sy) for dx in range(sx)]
+            us.append(clamp(sum(p[1] for p in block) / len(block)))
+            vs.append(clamp(sum(p[2] for p in block) / len(block)))
+    return us, vs
+
+
+def yuv_case(name, layout, matrix, full):
+    yuv = encode(source(), matrix, full)
+    sx, sy = (2, 1) if layout in ("yuyv", "uyvy") else (2, 2)
+    us, vs = subsample(yuv, sx, sy)
+    ys = [p[0] for p in yuv]
+    cw = W // sx
+
+    def chroma(x, y):
+        i = (y // sy) * cw + x // sx
+        return us[i], vs[i]
+
+    if layout == "yuyv":
+        raw = bytes(b for i in range(0, W * H, 2)
+                    for b in (ys[i], chroma(i % W, i // W)[0], ys[i + 1], chroma(i % W, i // W)[1]))
+    elif layout == "uyvy":
+        raw = bytes(b for i in range(0, W * H, 2)
+                    for b in (chroma(i % W, i // W)[0], ys[i], chroma(i % W, i // W)[1], ys[i + 1]))
+    elif layout == "nv12":
+        raw = bytes(ys) + bytes(b for u, v in zip(us, vs) for b in (u, v))
+    elif layout == "i420":
+        raw = bytes(ys) + bytes(us) + bytes(vs)
+    bgra = b"".join(decode(ys[y * W + x], *chroma(x, y), matrix, full)
+                    for y in range(H) for x in range(W))
+    write(name, raw, bgra)
+
+
+def write(name, raw, bgra):
+    assert len(bgra) == W * H * 4
+    with open(f"{name}.raw", "wb") as f:
+        f.write(raw)
+    with open(f"{name}.bgra", "wb") as f:
+        f.write(bgra)
+
+
+yuv_case("yuyv_bt601_limited", "yuyv", "bt601", False)
+yuv_case("uyvy_bt709_limited", "uyvy", "bt709", False)
+yuv_case("nv12_bt601_full", "nv12", "bt601", True)
+yuv_case("nv12_bt709_limited", "nv12", "bt709", False)
+yuv_case("i420_bt2020_full", "i420", "bt2020", True)
+
+rgb = source()
+write("rgb24", bytes(c for px in rgb for c in px),
+      b"".join(bytes([b, g, r, 255]) for r, g, b in rgb))
+gray = [(r * 7 + g * 3) % 256 for r, g, _ in rgb]
+write("gray", bytes(gray), b"".join(bytes([y, y, y, 255]) for y in gray))